@@ -2,7 +2,9 @@
 
 use node_template_runtime::{self, opaque::Block, RuntimeApi};
 use sc_client_api::{BlockBackend, ExecutorProvider};
-use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
+use sc_consensus_aura::{
+	AuthoringKeyMetrics, ImportQueueParams, ShadowAuthoring, SlotProportion, StartAuraParams,
+};
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_finality_grandpa::SharedVoterState;
 use sc_keystore::LocalKeystore;
@@ -55,6 +57,7 @@ pub fn new_partial(
 				FullSelectChain,
 			>,
 			sc_finality_grandpa::LinkHalf<Block, FullClient, FullSelectChain>,
+			sc_consensus::EquivocationGuard,
 			Option<Telemetry>,
 		),
 	>,
@@ -75,12 +78,7 @@ pub fn new_partial(
 		})
 		.transpose()?;
 
-	let executor = NativeElseWasmExecutor::<ExecutorDispatch>::new(
-		config.wasm_method,
-		config.default_heap_pages,
-		config.max_runtime_instances,
-		config.runtime_cache_size,
-	);
+	let executor = sc_service::new_native_or_wasm_executor::<ExecutorDispatch>(config);
 
 	let (client, backend, keystore_container, task_manager) =
 		sc_service::new_full_parts::<Block, RuntimeApi, _>(
@@ -112,6 +110,13 @@ pub fn new_partial(
 		telemetry.as_ref().map(|x| x.handle()),
 	)?;
 
+	let equivocation_guard = sc_consensus::EquivocationGuard::new(
+		config.equivocation_circuit_breaker,
+		keystore_container.sync_keystore(),
+		config.prometheus_registry(),
+		telemetry.as_ref().map(|x| x.handle()),
+	)?;
+
 	let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
 
 	let import_queue =
@@ -136,6 +141,7 @@ pub fn new_partial(
 			),
 			registry: config.prometheus_registry(),
 			check_for_equivocation: Default::default(),
+			equivocation_guard: Some(equivocation_guard.clone()),
 			telemetry: telemetry.as_ref().map(|x| x.handle()),
 		})?;
 
@@ -147,7 +153,7 @@ pub fn new_partial(
 		keystore_container,
 		select_chain,
 		transaction_pool,
-		other: (grandpa_block_import, grandpa_link, telemetry),
+		other: (grandpa_block_import, grandpa_link, equivocation_guard, telemetry),
 	})
 }
 
@@ -168,7 +174,7 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		mut keystore_container,
 		select_chain,
 		transaction_pool,
-		other: (block_import, grandpa_link, mut telemetry),
+		other: (block_import, grandpa_link, equivocation_guard, mut telemetry),
 	} = new_partial(&config)?;
 
 	if let Some(url) = &config.keystore_remote {
@@ -221,9 +227,20 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 
 	let role = config.role.clone();
 	let force_authoring = config.force_authoring;
+	let shadow_authoring = config.shadow_authoring;
 	let backoff_authoring_blocks: Option<()> = None;
 	let name = config.network.node_name.clone();
 	let enable_grandpa = !config.disable_grandpa;
+	let grandpa_stall_threshold = config.grandpa_stall_threshold;
+	let grandpa_voting_policy = sc_finality_grandpa::SharedVotingPolicy::new(
+		config
+			.grandpa_voting_policy
+			.as_deref()
+			.map(sc_finality_grandpa::VotingPolicy::from_json_file)
+			.transpose()
+			.map_err(ServiceError::Other)?
+			.unwrap_or_default(),
+	);
 	let prometheus_registry = config.prometheus_registry().cloned();
 
 	let rpc_extensions_builder = {
@@ -291,6 +308,14 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 				justification_sync_link: network.clone(),
 				block_proposal_slot_portion: SlotProportion::new(2f32 / 3f32),
 				max_block_proposal_slot_portion: None,
+				shadow_authoring: shadow_authoring
+					.then(|| ShadowAuthoring::new(prometheus_registry.as_ref()))
+					.transpose()?,
+				equivocation_guard: Some(equivocation_guard.clone()),
+				authoring_metrics: prometheus_registry
+					.as_ref()
+					.map(AuthoringKeyMetrics::register)
+					.transpose()?,
 				telemetry: telemetry.as_ref().map(|x| x.handle()),
 			},
 		)?;
@@ -330,11 +355,16 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 			config: grandpa_config,
 			link: grandpa_link,
 			network,
-			voting_rule: sc_finality_grandpa::VotingRulesBuilder::default().build(),
+			voting_rule: sc_finality_grandpa::VotingRulesBuilder::default()
+				.add(grandpa_voting_policy)
+				.build(),
 			prometheus_registry,
 			shared_voter_state: SharedVoterState::empty(),
 			telemetry: telemetry.as_ref().map(|x| x.handle()),
 			permission_resolver,
+			equivocation_guard: Some(equivocation_guard),
+			finality_stall_threshold: grandpa_stall_threshold,
+			shared_stall_report: sc_finality_grandpa::SharedStallReport::empty(),
 		};
 
 		// the GRANDPA voter task is considered infallible, i.e.
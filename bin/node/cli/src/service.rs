@@ -25,7 +25,7 @@ use frame_system_rpc_runtime_api::AccountNonceApi;
 use futures::prelude::*;
 use kitchensink_runtime::RuntimeApi;
 use node_executor::ExecutorDispatch;
-use node_primitives::Block;
+use node_primitives::{Block, BlockNumber, Hash};
 use sc_client_api::{BlockBackend, ExecutorProvider};
 use sc_consensus_babe::{self, SlotProportion};
 use sc_executor::NativeElseWasmExecutor;
@@ -145,8 +145,13 @@ pub fn new_partial(
 				sc_consensus_babe::BabeBlockImport<Block, FullClient, FullGrandpaBlockImport>,
 				grandpa::LinkHalf<Block, FullClient, FullSelectChain>,
 				sc_consensus_babe::BabeLink<Block>,
+				sc_consensus::EquivocationGuard,
+			),
+			(
+				grandpa::SharedVoterState,
+				grandpa::SharedStallReport<BlockNumber>,
+				grandpa::SharedVotingPolicy<Hash, BlockNumber>,
 			),
-			grandpa::SharedVoterState,
 			Option<Telemetry>,
 		),
 	>,
@@ -163,12 +168,7 @@ pub fn new_partial(
 		})
 		.transpose()?;
 
-	let executor = NativeElseWasmExecutor::<ExecutorDispatch>::new(
-		config.wasm_method,
-		config.default_heap_pages,
-		config.max_runtime_instances,
-		config.runtime_cache_size,
-	);
+	let executor = sc_service::new_native_or_wasm_executor::<ExecutorDispatch>(config);
 
 	let (client, backend, keystore_container, task_manager) =
 		sc_service::new_full_parts::<Block, RuntimeApi, _>(
@@ -207,6 +207,13 @@ pub fn new_partial(
 		client.clone(),
 	)?;
 
+	let equivocation_guard = sc_consensus::EquivocationGuard::new(
+		config.equivocation_circuit_breaker,
+		keystore_container.sync_keystore(),
+		config.prometheus_registry(),
+		telemetry.as_ref().map(|x| x.handle()),
+	)?;
+
 	let slot_duration = babe_link.config().slot_duration();
 	let import_queue = sc_consensus_babe::import_queue(
 		babe_link.clone(),
@@ -231,18 +238,31 @@ pub fn new_partial(
 		&task_manager.spawn_essential_handle(),
 		config.prometheus_registry(),
 		sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone()),
+		Some(equivocation_guard.clone()),
 		telemetry.as_ref().map(|x| x.handle()),
 	)?;
 
-	let import_setup = (block_import, grandpa_link, babe_link);
+	let import_setup = (block_import, grandpa_link, babe_link, equivocation_guard);
 
 	let (rpc_extensions_builder, rpc_setup) = {
-		let (_, grandpa_link, babe_link) = &import_setup;
+		let (_, grandpa_link, babe_link, _) = &import_setup;
 
 		let justification_stream = grandpa_link.justification_stream();
 		let shared_authority_set = grandpa_link.shared_authority_set().clone();
 		let shared_voter_state = grandpa::SharedVoterState::empty();
 		let shared_voter_state2 = shared_voter_state.clone();
+		let shared_stall_report = grandpa::SharedStallReport::empty();
+		let shared_stall_report2 = shared_stall_report.clone();
+		let voting_policy = grandpa::SharedVotingPolicy::new(
+			config
+				.grandpa_voting_policy
+				.as_deref()
+				.map(grandpa::VotingPolicy::from_json_file)
+				.transpose()
+				.map_err(ServiceError::Other)?
+				.unwrap_or_default(),
+		);
+		let voting_policy2 = voting_policy.clone();
 
 		let finality_proof_provider = grandpa::FinalityProofProvider::new_for_service(
 			backend.clone(),
@@ -277,13 +297,15 @@ pub fn new_partial(
 					justification_stream: justification_stream.clone(),
 					subscription_executor,
 					finality_provider: finality_proof_provider.clone(),
+					shared_stall_report: shared_stall_report.clone(),
+					voting_policy: voting_policy.clone(),
 				},
 			};
 
 			node_rpc::create_full(deps, rpc_backend.clone()).map_err(Into::into)
 		};
 
-		(rpc_extensions_builder, shared_voter_state2)
+		(rpc_extensions_builder, (shared_voter_state2, shared_stall_report2, voting_policy2))
 	};
 
 	Ok(sc_service::PartialComponents {
@@ -341,7 +363,7 @@ pub fn new_full_base(
 		other: (rpc_builder, import_setup, rpc_setup, mut telemetry),
 	} = new_partial(&config)?;
 
-	let shared_voter_state = rpc_setup;
+	let (shared_voter_state, shared_stall_report, voting_policy) = rpc_setup;
 	let auth_disc_publish_non_global_ips = config.network.allow_non_globals_in_dht;
 	let grandpa_protocol_name = grandpa::protocol_standard_name(
 		&client.block_hash(0).ok().flatten().expect("Genesis block exists; qed"),
@@ -383,10 +405,12 @@ pub fn new_full_base(
 
 	let role = config.role.clone();
 	let force_authoring = config.force_authoring;
+	let shadow_authoring = config.shadow_authoring;
 	let backoff_authoring_blocks =
 		Some(sc_consensus_slots::BackoffAuthoringOnFinalizedHeadLagging::default());
 	let name = config.network.node_name.clone();
 	let enable_grandpa = !config.disable_grandpa;
+	let grandpa_stall_threshold = config.grandpa_stall_threshold;
 	let prometheus_registry = config.prometheus_registry().cloned();
 
 	let rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
@@ -415,7 +439,7 @@ pub fn new_full_base(
 		}
 	}
 
-	let (block_import, grandpa_link, babe_link) = import_setup;
+	let (block_import, grandpa_link, babe_link, equivocation_guard) = import_setup;
 
 	(with_startup_data)(&block_import, &babe_link);
 
@@ -473,6 +497,10 @@ pub fn new_full_base(
 			permission_resolver: permission_resolver.clone(),
 			block_proposal_slot_portion: SlotProportion::new(0.5),
 			max_block_proposal_slot_portion: None,
+			shadow_authoring: shadow_authoring
+				.then(|| sc_consensus_babe::ShadowAuthoring::new(prometheus_registry.as_ref()))
+				.transpose()?,
+			equivocation_guard: Some(equivocation_guard.clone()),
 			telemetry: telemetry.as_ref().map(|x| x.handle()),
 		};
 
@@ -544,10 +572,13 @@ pub fn new_full_base(
 			link: grandpa_link,
 			network: network.clone(),
 			telemetry: telemetry.as_ref().map(|x| x.handle()),
-			voting_rule: grandpa::VotingRulesBuilder::default().build(),
+			voting_rule: grandpa::VotingRulesBuilder::default().add(voting_policy).build(),
 			prometheus_registry,
 			shared_voter_state,
 			permission_resolver,
+			equivocation_guard: Some(equivocation_guard),
+			finality_stall_threshold: grandpa_stall_threshold,
+			shared_stall_report,
 		};
 
 		// the GRANDPA voter task is considered infallible, i.e.
@@ -39,7 +39,8 @@ use sc_client_api::AuxStore;
 use sc_consensus_babe::{BabeConfiguration, Epoch};
 use sc_consensus_epochs::SharedEpochChanges;
 use sc_finality_grandpa::{
-	FinalityProofProvider, GrandpaJustificationStream, SharedAuthoritySet, SharedVoterState,
+	FinalityProofProvider, GrandpaJustificationStream, SharedAuthoritySet, SharedStallReport,
+	SharedVoterState, SharedVotingPolicy,
 };
 use sc_rpc::SubscriptionTaskExecutor;
 pub use sc_rpc_api::DenyUnsafe;
@@ -73,6 +74,10 @@ pub struct GrandpaDeps<B> {
 	pub subscription_executor: SubscriptionTaskExecutor,
 	/// Finality proof provider.
 	pub finality_provider: Arc<FinalityProofProvider<B, Block>>,
+	/// Report of an ongoing finality stall.
+	pub shared_stall_report: SharedStallReport<BlockNumber>,
+	/// Policy restricting the votes of the GRANDPA voter.
+	pub voting_policy: SharedVotingPolicy<Hash, BlockNumber>,
 }
 
 /// Full client dependencies.
@@ -138,6 +143,8 @@ where
 		justification_stream,
 		subscription_executor,
 		finality_provider,
+		shared_stall_report,
+		voting_policy,
 	} = grandpa;
 
 	io.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
@@ -165,6 +172,9 @@ where
 			shared_voter_state,
 			justification_stream,
 			finality_provider,
+			shared_stall_report,
+			voting_policy,
+			deny_unsafe,
 		)
 		.into_rpc(),
 	)?;
@@ -0,0 +1,82 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reference remote signer, serving the keys of a local keystore to nodes.
+
+use clap::Parser;
+use sc_keystore::{
+	remote::{Endpoint, RemoteSigner, TlsConfig},
+	LocalKeystore,
+};
+use sp_core::crypto::SecretString;
+use std::{fs, path::PathBuf, sync::Arc};
+
+/// Serve the keys of a keystore to remote nodes.
+#[derive(Debug, Parser)]
+#[clap(name = "remote-signer", author, version)]
+struct Cli {
+	/// Path of the keystore holding the keys.
+	#[clap(long, value_name = "PATH", parse(from_os_str))]
+	keystore_path: PathBuf,
+
+	/// File that contains the password used by the keystore.
+	#[clap(long, value_name = "PATH", parse(from_os_str))]
+	password_filename: Option<PathBuf>,
+
+	/// Endpoint to listen on, either `tls://<host>:<port>` or `unix://<path>`.
+	#[clap(long, value_name = "URI")]
+	listen: String,
+
+	/// PEM file with the certificate authorities the node certificates are verified against.
+	#[clap(long, value_name = "PATH", parse(from_os_str), requires_all = &["cert", "key"])]
+	ca: Option<PathBuf>,
+
+	/// PEM file with the certificate of the signer.
+	#[clap(long, value_name = "PATH", parse(from_os_str), requires = "ca")]
+	cert: Option<PathBuf>,
+
+	/// PEM file with the private key of the signer certificate.
+	#[clap(long, value_name = "PATH", parse(from_os_str), requires = "ca")]
+	key: Option<PathBuf>,
+}
+
+fn main() -> Result<(), String> {
+	env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("keystore=info"))
+		.init();
+	let cli = Cli::parse();
+
+	let password = cli
+		.password_filename
+		.map(|file| fs::read_to_string(file).map(SecretString::new))
+		.transpose()
+		.map_err(|e| format!("Failed to read the password file: {}", e))?;
+	let keystore = LocalKeystore::open(cli.keystore_path, password)
+		.map_err(|e| format!("Failed to open the keystore: {}", e))?;
+
+	let endpoint = cli.listen.parse::<Endpoint>().map_err(|e| e.to_string())?;
+	let tls = match (cli.ca, cli.cert, cli.key) {
+		(Some(ca), Some(cert), Some(key)) => Some(TlsConfig { ca, cert, key }),
+		_ => None,
+	};
+
+	let signer = RemoteSigner::bind(&endpoint, tls.as_ref(), Arc::new(keystore))
+		.map_err(|e| format!("Failed to listen on {}: {}", cli.listen, e))?;
+	log::info!(target: "keystore", "Remote signer listening on {}", cli.listen);
+
+	signer.run().map_err(|e| e.to_string())
+}
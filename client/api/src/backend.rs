@@ -20,7 +20,7 @@
 
 use crate::{
 	blockchain::{well_known_cache_keys, Backend as BlockchainBackend},
-	UsageInfo,
+	StorageDiff, UsageInfo,
 };
 use parking_lot::RwLock;
 use sp_blockchain;
@@ -431,6 +431,35 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		child_info: &ChildInfo,
 		key: &StorageKey,
 	) -> sp_blockchain::Result<Option<Block::Hash>>;
+
+	/// Given two `BlockId`s and a key `prefix`, return up to `count` matching storage entries
+	/// that differ between the states of the blocks, ordered by key and starting after
+	/// `start_key`, along with the key to start the next page after. The entries of changed child
+	/// tries are included, `start_child_key` resumes within the child trie stored at `start_key`.
+	///
+	/// See [`storage_diff`](crate::storage_diff::storage_diff) for details.
+	fn storage_diff(
+		&self,
+		from: &BlockId<Block>,
+		to: &BlockId<Block>,
+		prefix: Option<&StorageKey>,
+		start_key: Option<&StorageKey>,
+		start_child_key: Option<&StorageKey>,
+		count: usize,
+	) -> sp_blockchain::Result<StorageDiff>;
+
+	/// Given two `BlockId`s, a child storage key and a key `prefix`, return up to `count`
+	/// matching child storage entries that differ between the states of the blocks, ordered by
+	/// key and starting after `start_key`, along with the key to start the next page after.
+	fn child_storage_diff(
+		&self,
+		from: &BlockId<Block>,
+		to: &BlockId<Block>,
+		child_info: &ChildInfo,
+		prefix: Option<&StorageKey>,
+		start_key: Option<&StorageKey>,
+		count: usize,
+	) -> sp_blockchain::Result<StorageDiff>;
 }
 
 /// Client backend.
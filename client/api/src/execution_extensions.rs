@@ -24,6 +24,7 @@
 
 use codec::Decode;
 use parking_lot::RwLock;
+use sc_executor::FuelLimitExt;
 use sc_transaction_pool_api::OffchainSubmitTransaction;
 use sp_core::{
 	offchain::{self, OffchainDbExt, OffchainWorkerExt, TransactionPoolExt},
@@ -63,6 +64,40 @@ impl Default for ExecutionStrategies {
 	}
 }
 
+/// Limits of the fuel that runtime calls may consume, by the kind of call.
+///
+/// The limits are only enforced if the executor compiles runtimes with fuel metering. `None`
+/// doesn't limit the calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FuelLimits {
+	/// Fuel limit of calls while importing, syncing and constructing blocks.
+	///
+	/// Blocks whose execution exceeds the limit can't be imported.
+	pub importing: Option<u64>,
+	/// Fuel limit of offchain worker calls.
+	pub offchain_worker: Option<u64>,
+	/// Fuel limit of calls made on behalf of RPC clients, and other offchain calls.
+	pub rpc: Option<u64>,
+}
+
+impl FuelLimits {
+	/// Returns `true` if any of the calls is limited.
+	pub fn is_enabled(&self) -> bool {
+		self.importing.is_some() || self.offchain_worker.is_some() || self.rpc.is_some()
+	}
+
+	/// Returns the fuel limit of calls in the given `context`.
+	pub fn for_context(&self, context: &ExecutionContext) -> Option<u64> {
+		match context {
+			ExecutionContext::Importing |
+			ExecutionContext::Syncing |
+			ExecutionContext::BlockConstruction => self.importing,
+			ExecutionContext::OffchainCall(Some(_)) => self.offchain_worker,
+			ExecutionContext::OffchainCall(None) => self.rpc,
+		}
+	}
+}
+
 /// Generate the starting set of ExternalitiesExtensions based upon the given capabilities
 pub trait ExtensionsFactory: Send + Sync {
 	/// Make `Extensions` for given `Capabilities`.
@@ -104,6 +139,7 @@ pub struct ExecutionExtensions<Block: traits::Block> {
 	// during initialization.
 	transaction_pool: RwLock<Option<Weak<dyn OffchainSubmitTransaction<Block>>>>,
 	extensions_factory: RwLock<Box<dyn ExtensionsFactory>>,
+	fuel_limits: FuelLimits,
 }
 
 impl<Block: traits::Block> Default for ExecutionExtensions<Block> {
@@ -114,6 +150,7 @@ impl<Block: traits::Block> Default for ExecutionExtensions<Block> {
 			offchain_db: None,
 			transaction_pool: RwLock::new(None),
 			extensions_factory: RwLock::new(Box::new(())),
+			fuel_limits: Default::default(),
 		}
 	}
 }
@@ -133,9 +170,21 @@ impl<Block: traits::Block> ExecutionExtensions<Block> {
 			offchain_db,
 			extensions_factory: RwLock::new(extensions_factory),
 			transaction_pool,
+			fuel_limits: Default::default(),
 		}
 	}
 
+	/// Limit the fuel runtime calls may consume, see [`FuelLimits`].
+	pub fn with_fuel_limits(mut self, fuel_limits: FuelLimits) -> Self {
+		self.fuel_limits = fuel_limits;
+		self
+	}
+
+	/// Get a reference to the fuel limits.
+	pub fn fuel_limits(&self) -> &FuelLimits {
+		&self.fuel_limits
+	}
+
 	/// Get a reference to the execution strategies.
 	pub fn strategies(&self) -> &ExecutionStrategies {
 		&self.strategies
@@ -187,6 +236,10 @@ impl<Block: traits::Block> ExecutionExtensions<Block> {
 			}
 		}
 
+		if let Some(limit) = self.fuel_limits.for_context(&context) {
+			extensions.register(FuelLimitExt(limit));
+		}
+
 		if let ExecutionContext::OffchainCall(Some(ext)) = context {
 			extensions.register(OffchainWorkerExt::new(offchain::LimitedExternalities::new(
 				capabilities,
@@ -197,6 +250,19 @@ impl<Block: traits::Block> ExecutionExtensions<Block> {
 		extensions
 	}
 
+	/// Produces the extensions for a call made directly on behalf of an RPC client, e.g.
+	/// `state_call`.
+	///
+	/// Unlike [`Self::extensions`] for offchain calls, no APIs are provided to the call, only its
+	/// fuel limit.
+	pub fn rpc_call_extensions(&self) -> Extensions {
+		let mut extensions = Extensions::new();
+		if let Some(limit) = self.fuel_limits.rpc {
+			extensions.register(FuelLimitExt(limit));
+		}
+		extensions
+	}
+
 	/// Create `ExecutionManager` and `Extensions` for given offchain call.
 	///
 	/// Based on the execution context and capabilities it produces
@@ -27,12 +27,14 @@ pub mod in_mem;
 pub mod leaves;
 pub mod notifications;
 pub mod proof_provider;
+pub mod storage_diff;
 
 pub use backend::*;
 pub use call_executor::*;
 pub use client::*;
 pub use notifications::*;
 pub use proof_provider::*;
+pub use storage_diff::{StorageDiff, StorageDiffEntry};
 pub use sp_blockchain as blockchain;
 pub use sp_blockchain::HeaderBackend;
 
@@ -0,0 +1,658 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Difference between the storage of two states.
+//!
+//! The tries of both states are descended in parallel, node by node. Subtries whose nodes have
+//! the same hash in both states are identical and are skipped without being read, so the cost of
+//! a diff depends on the size of the changes rather than on the size of the storage.
+
+use codec::Codec;
+use sp_core::{storage::well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, Hasher};
+use sp_state_machine::{
+	backend::AsTrieBackend, Backend as _, DefaultError, TrieBackend, TrieBackendStorage,
+};
+use sp_storage::{ChildInfo, StorageData, StorageKey};
+use sp_trie::{empty_trie_root, LayoutV1, NodeCodec};
+use std::{cmp::Ordering, rc::Rc};
+use trie_db::{
+	node::{Node, NodeHandle, Value},
+	NodeCodec as _,
+};
+
+/// Maximum number of trie nodes read by a single [`storage_diff`] call.
+///
+/// Large changes would otherwise keep a call busy for as long as it takes to walk them.
+pub const MAX_VISITED_NODES: usize = 16 * 1024;
+
+/// Storage entry that differs between two states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDiffEntry {
+	/// Key of the entry.
+	pub key: StorageKey,
+	/// Value in the old state, `None` if the entry was added.
+	pub old_value: Option<StorageData>,
+	/// Value in the new state, `None` if the entry was removed.
+	pub new_value: Option<StorageData>,
+	/// Child trie holding the entry, if it was found by descending into a changed child trie of
+	/// the top trie.
+	pub child_info: Option<ChildInfo>,
+}
+
+/// Page of the entries that differ between two states.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageDiff {
+	/// Entries that differ, in lexicographic order of their keys.
+	pub entries: Vec<StorageDiffEntry>,
+	/// Key to pass as `start_key` to get the next page, or `None` if there are no more entries.
+	pub next_key: Option<StorageKey>,
+	/// Key to pass as `start_child_key` to get the next page. Set if the page ended within the
+	/// child trie whose root is stored at `next_key`.
+	pub next_child_key: Option<StorageKey>,
+}
+
+/// Returns up to `count` entries under `prefix` that differ between the `old` and `new` states.
+///
+/// Entries are returned in lexicographic order of their keys, starting after `start_key` if it is
+/// given. At most [`MAX_VISITED_NODES`] trie nodes are read, so a page may hold fewer than `count`
+/// entries even though more follow: passing the returned `next_key` and `next_child_key` as
+/// `start_key` and `start_child_key` returns the next page.
+///
+/// When diffing the top tries, the changed child tries are diffed as well: the entries of a child
+/// trie come right before the change of its root in the top trie, and carry its [`ChildInfo`].
+/// `start_child_key` resumes the diff within the child trie whose root is stored at `start_key`.
+///
+/// If `child_info` is given, only the child tries are compared.
+pub fn storage_diff<H, State>(
+	old: &State,
+	new: &State,
+	child_info: Option<&ChildInfo>,
+	prefix: Option<&StorageKey>,
+	start_key: Option<&StorageKey>,
+	start_child_key: Option<&StorageKey>,
+	count: usize,
+) -> Result<StorageDiff, DefaultError>
+where
+	H: Hasher,
+	H::Out: Ord + Codec,
+	State: AsTrieBackend<H>,
+{
+	storage_diff_capped(
+		old,
+		new,
+		child_info,
+		prefix,
+		start_key,
+		start_child_key,
+		count,
+		MAX_VISITED_NODES,
+	)
+}
+
+fn storage_diff_capped<H, State>(
+	old: &State,
+	new: &State,
+	child_info: Option<&ChildInfo>,
+	prefix: Option<&StorageKey>,
+	start_key: Option<&StorageKey>,
+	start_child_key: Option<&StorageKey>,
+	count: usize,
+	max_visited: usize,
+) -> Result<StorageDiff, DefaultError>
+where
+	H: Hasher,
+	H::Out: Ord + Codec,
+	State: AsTrieBackend<H>,
+{
+	let mut walk = Walk {
+		old: old.as_trie_backend(),
+		new: new.as_trie_backend(),
+		count,
+		max_visited,
+		visited: 0,
+		entries: Vec::new(),
+		last: (start_key.cloned(), start_child_key.cloned()),
+		progressed: false,
+		interrupted: false,
+	};
+	let trie = Trie {
+		child_info,
+		parent_key: None,
+		prefix: prefix.map(|prefix| nibbles(&prefix.0)).unwrap_or_default(),
+		start: start_key.map(|start_key| nibbles(&start_key.0)),
+		start_child: start_key.and(start_child_key).map(|key| &key.0[..]),
+	};
+
+	let (old_root, new_root) = match child_info {
+		Some(child_info) => {
+			let root_key = child_info.prefixed_storage_key();
+			(
+				child_root::<H>(walk.old.storage(&root_key)?)?,
+				child_root::<H>(walk.new.storage(&root_key)?)?,
+			)
+		},
+		None => (root_subtrie::<H>(*walk.old.root()), root_subtrie::<H>(*walk.new.root())),
+	};
+	walk.walk(&trie, old_root, new_root, &mut Vec::new())?;
+
+	let (next_key, next_child_key) = if walk.interrupted { walk.last } else { (None, None) };
+	Ok(StorageDiff { entries: walk.entries, next_key, next_child_key })
+}
+
+/// Subtrie hanging at some position of a trie.
+///
+/// A node with a partial key spans several positions: `offset` is the number of nibbles of the
+/// partial key lying above the position.
+struct Subtrie<Hash> {
+	hash: Hash,
+	/// Encoded node, `None` until it is read from the database.
+	encoded: Option<Rc<[u8]>>,
+	offset: usize,
+}
+
+impl<Hash: PartialEq> PartialEq for Subtrie<Hash> {
+	fn eq(&self, other: &Self) -> bool {
+		self.hash == other.hash && self.offset == other.offset
+	}
+}
+
+/// Subtries hanging below a position of a trie, by nibble.
+type Children<Hash> = [Option<Subtrie<Hash>>; 16];
+
+/// Value stored at a position of a trie, as found in the node.
+#[derive(PartialEq)]
+enum ValueRef {
+	Inline(Vec<u8>),
+	Node(Vec<u8>),
+}
+
+/// Trie walked by [`Walk`].
+struct Trie<'a> {
+	/// Child trie, `None` for the top trie.
+	child_info: Option<&'a ChildInfo>,
+	/// Key of the root of the child trie in the top trie, if the child trie was found while
+	/// walking the top trie.
+	parent_key: Option<&'a [u8]>,
+	/// Nibbles of the prefix of the compared keys.
+	prefix: Vec<u8>,
+	/// Nibbles of the key after which keys are compared.
+	start: Option<Vec<u8>>,
+	/// Key after which the child trie stored at `start` is compared.
+	start_child: Option<&'a [u8]>,
+}
+
+/// Parallel walk of the tries of two states.
+struct Walk<'a, S: TrieBackendStorage<H>, H: Hasher> {
+	old: &'a TrieBackend<S, H>,
+	new: &'a TrieBackend<S, H>,
+	count: usize,
+	max_visited: usize,
+	/// Number of nodes read from the databases.
+	visited: usize,
+	entries: Vec<StorageDiffEntry>,
+	/// Position of the last compared entry: its key, and the key within its child trie if the
+	/// entry belongs to a child trie.
+	last: (Option<StorageKey>, Option<StorageKey>),
+	/// Whether an entry was compared. The limit of read nodes only applies once one was, so that
+	/// every call makes progress.
+	progressed: bool,
+	/// Whether the walk stopped before the end of the tries.
+	interrupted: bool,
+}
+
+impl<'a, S, H> Walk<'a, S, H>
+where
+	S: TrieBackendStorage<H>,
+	H: Hasher,
+	H::Out: Ord + Codec,
+{
+	/// Compares the subtries hanging at `path`.
+	fn walk(
+		&mut self,
+		trie: &Trie,
+		old: Option<Subtrie<H::Out>>,
+		new: Option<Subtrie<H::Out>>,
+		path: &mut Vec<u8>,
+	) -> Result<(), DefaultError> {
+		if self.interrupted || old == new || !may_hold_compared_keys(trie, path) {
+			return Ok(())
+		}
+		if self.entries.len() >= self.count || (self.progressed && self.visited >= self.max_visited)
+		{
+			self.interrupted = true;
+			return Ok(())
+		}
+
+		let (old_value, mut old_children) = self.expand(self.old, trie, old, path)?;
+		let (new_value, mut new_children) = self.expand(self.new, trie, new, path)?;
+		if old_value.is_some() || new_value.is_some() {
+			self.compare(trie, path, old_value, new_value)?;
+		}
+
+		for nibble in 0..16 {
+			path.push(nibble as u8);
+			self.walk(trie, old_children[nibble].take(), new_children[nibble].take(), path)?;
+			path.pop();
+		}
+		Ok(())
+	}
+
+	/// Returns the value and the children of a subtrie hanging at `path`.
+	fn expand(
+		&mut self,
+		backend: &TrieBackend<S, H>,
+		trie: &Trie,
+		subtrie: Option<Subtrie<H::Out>>,
+		path: &[u8],
+	) -> Result<(Option<ValueRef>, Children<H::Out>), DefaultError> {
+		let mut children: Children<H::Out> = Default::default();
+		let subtrie = match subtrie {
+			Some(subtrie) => subtrie,
+			None => return Ok((None, children)),
+		};
+		let encoded = match subtrie.encoded {
+			Some(encoded) => encoded,
+			None => self.read_node(backend, trie, &subtrie.hash, path)?,
+		};
+
+		let node = NodeCodec::<H>::decode(&encoded)
+			.map_err(|e| format!("Failed to decode trie node {:?}: {:?}", subtrie.hash, e))?;
+		let (partial, handles, value) = match node {
+			Node::Empty => return Ok((None, children)),
+			Node::Leaf(partial, value) => (Some(partial), None, Some(value)),
+			Node::Branch(handles, value) => (None, Some(handles), value),
+			Node::NibbledBranch(partial, handles, value) => (Some(partial), Some(handles), value),
+			Node::Extension(..) =>
+				return Err(format!("Unexpected extension node {:?}", subtrie.hash)),
+		};
+
+		// Within the partial key, the only child is the rest of the same node.
+		if let Some(partial) = partial.filter(|partial| subtrie.offset < partial.len()) {
+			children[partial.at(subtrie.offset) as usize] = Some(Subtrie {
+				hash: subtrie.hash,
+				encoded: Some(encoded.clone()),
+				offset: subtrie.offset + 1,
+			});
+			return Ok((None, children))
+		}
+
+		for (child, handle) in children.iter_mut().zip(handles.into_iter().flatten()) {
+			*child = match handle {
+				Some(NodeHandle::Hash(hash)) =>
+					Some(Subtrie { hash: decode_hash::<H>(hash)?, encoded: None, offset: 0 }),
+				Some(NodeHandle::Inline(data)) =>
+					Some(Subtrie { hash: H::hash(data), encoded: Some(data.into()), offset: 0 }),
+				None => None,
+			};
+		}
+		let value = value.map(|value| match value {
+			Value::Inline(value) => ValueRef::Inline(value.to_vec()),
+			Value::Node(hash) => ValueRef::Node(hash.to_vec()),
+		});
+		Ok((value, children))
+	}
+
+	/// Reads the node with the given hash, starting at `path`.
+	fn read_node(
+		&mut self,
+		backend: &TrieBackend<S, H>,
+		trie: &Trie,
+		hash: &H::Out,
+		path: &[u8],
+	) -> Result<Rc<[u8]>, DefaultError> {
+		let mut prefix = trie.child_info.map(|child_info| child_info.keyspace().to_vec());
+		let prefix = prefix.get_or_insert_with(Vec::new);
+		prefix.extend(pack(&path[..path.len() - path.len() % 2]));
+		let last_nibble = (path.len() % 2 == 1).then(|| path[path.len() - 1] << 4);
+
+		self.visited += 1;
+		backend
+			.backend_storage()
+			.get(hash, (prefix, last_nibble))?
+			.map(Into::into)
+			.ok_or_else(|| format!("Trie node {:?} is missing", hash))
+	}
+
+	/// Compares the entries stored at `path`.
+	fn compare(
+		&mut self,
+		trie: &Trie,
+		path: &[u8],
+		old: Option<ValueRef>,
+		new: Option<ValueRef>,
+	) -> Result<(), DefaultError> {
+		if !path.starts_with(&trie.prefix) {
+			return Ok(())
+		}
+		let resume_child = match trie.start.as_ref().map(|start| (*path).cmp(start)) {
+			Some(Ordering::Less) => return Ok(()),
+			Some(Ordering::Equal) => match trie.start_child {
+				Some(start_child) => Some(start_child),
+				None => return Ok(()),
+			},
+			_ => None,
+		};
+
+		let key = pack(path);
+		if old == new {
+			self.set_last(trie, &key);
+			return Ok(())
+		}
+
+		let (old, new) = match trie.child_info {
+			Some(child_info) => (
+				self.old.child_storage(child_info, &key)?,
+				self.new.child_storage(child_info, &key)?,
+			),
+			None => (self.old.storage(&key)?, self.new.storage(&key)?),
+		};
+		if old == new {
+			self.set_last(trie, &key);
+			return Ok(())
+		}
+
+		let storage_key = key.strip_prefix(DEFAULT_CHILD_STORAGE_KEY_PREFIX);
+		if let (None, Some(storage_key)) = (trie.child_info, storage_key) {
+			let child_info = ChildInfo::new_default(storage_key);
+			let child_trie = Trie {
+				child_info: Some(&child_info),
+				parent_key: Some(&key),
+				prefix: Vec::new(),
+				start: resume_child.map(nibbles),
+				start_child: None,
+			};
+			let (old_root, new_root) =
+				(child_root::<H>(old.clone())?, child_root::<H>(new.clone())?);
+			self.walk(&child_trie, old_root, new_root, &mut Vec::new())?;
+
+			// The change of the root follows the changes of the child trie.
+			if self.interrupted {
+				return Ok(())
+			}
+			if self.entries.len() >= self.count {
+				self.interrupted = true;
+				return Ok(())
+			}
+		} else if resume_child.is_some() {
+			return Ok(())
+		}
+
+		self.entries.push(StorageDiffEntry {
+			key: StorageKey(key.clone()),
+			old_value: old.map(StorageData),
+			new_value: new.map(StorageData),
+			child_info: trie.parent_key.and(trie.child_info).cloned(),
+		});
+		self.set_last(trie, &key);
+		Ok(())
+	}
+
+	fn set_last(&mut self, trie: &Trie, key: &[u8]) {
+		self.last = match trie.parent_key {
+			Some(parent_key) =>
+				(Some(StorageKey(parent_key.to_vec())), Some(StorageKey(key.to_vec()))),
+			None => (Some(StorageKey(key.to_vec())), None),
+		};
+		self.progressed = true;
+	}
+}
+
+/// Returns whether the subtrie hanging at `path` may hold keys that are compared.
+fn may_hold_compared_keys(trie: &Trie, path: &[u8]) -> bool {
+	let len = path.len().min(trie.prefix.len());
+	if path[..len] != trie.prefix[..len] {
+		return false
+	}
+
+	// Keys of the subtrie are below the start key only if the path itself is.
+	let first_difference =
+		trie.start.iter().flat_map(|start| path.iter().zip(start)).find(|(p, s)| p != s);
+	!matches!(first_difference, Some((p, s)) if p < s)
+}
+
+fn root_subtrie<H: Hasher>(root: H::Out) -> Option<Subtrie<H::Out>> {
+	let subtrie = Subtrie { hash: root, encoded: None, offset: 0 };
+	(root != empty_trie_root::<LayoutV1<H>>()).then_some(subtrie)
+}
+
+fn child_root<H: Hasher>(value: Option<Vec<u8>>) -> Result<Option<Subtrie<H::Out>>, DefaultError> {
+	match value {
+		Some(value) => Ok(root_subtrie::<H>(decode_hash::<H>(&value)?)),
+		None => Ok(None),
+	}
+}
+
+fn decode_hash<H: Hasher>(encoded: &[u8]) -> Result<H::Out, DefaultError> {
+	let mut hash = H::Out::default();
+	if encoded.len() != hash.as_ref().len() {
+		return Err(format!("Invalid trie node hash {:?}", encoded))
+	}
+	hash.as_mut().copy_from_slice(encoded);
+	Ok(hash)
+}
+
+fn nibbles(key: &[u8]) -> Vec<u8> {
+	key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+/// Packs nibbles into bytes, padding an odd nibble with zero.
+fn pack(nibbles: &[u8]) -> Vec<u8> {
+	nibbles
+		.chunks(2)
+		.map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or_default())
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::Blake2Hasher;
+	use sp_runtime::{StateVersion, Storage};
+	use sp_state_machine::InMemoryBackend;
+	use sp_storage::StorageChild;
+
+	type State = InMemoryBackend<Blake2Hasher>;
+
+	fn state(top: Vec<(&[u8], &[u8])>, child: Vec<(&[u8], &[u8])>) -> State {
+		let child_info = ChildInfo::new_default(b"child");
+		let mut storage = Storage {
+			top: top.into_iter().map(|(k, v)| (k.to_vec(), v.to_vec())).collect(),
+			children_default: Default::default(),
+		};
+		if !child.is_empty() {
+			storage.children_default.insert(
+				child_info.storage_key().to_vec(),
+				StorageChild {
+					data: child.into_iter().map(|(k, v)| (k.to_vec(), v.to_vec())).collect(),
+					child_info,
+				},
+			);
+		}
+		TrieBackend::from((storage, StateVersion::V1))
+	}
+
+	fn entry(key: &[u8], old: Option<&[u8]>, new: Option<&[u8]>) -> StorageDiffEntry {
+		StorageDiffEntry {
+			key: StorageKey(key.to_vec()),
+			old_value: old.map(|v| StorageData(v.to_vec())),
+			new_value: new.map(|v| StorageData(v.to_vec())),
+			child_info: None,
+		}
+	}
+
+	/// Values long enough to be stored in their own trie nodes.
+	fn large_values(keys: impl Iterator<Item = Vec<u8>>) -> Vec<(Vec<u8>, Vec<u8>)> {
+		keys.map(|key| (key.clone(), [key, vec![0; 40]].concat())).collect()
+	}
+
+	fn as_refs(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<(&[u8], &[u8])> {
+		entries.iter().map(|(k, v)| (&k[..], &v[..])).collect()
+	}
+
+	#[test]
+	fn diff_reports_added_removed_and_changed_entries() {
+		let old = state(vec![(b"a", b"1"), (b"b", b"2"), (b"c", b"3")], vec![]);
+		let new = state(vec![(b"b", b"2"), (b"c", b"4"), (b"d", b"5")], vec![]);
+
+		assert_eq!(
+			storage_diff(&old, &new, None, None, None, None, 10).unwrap().entries,
+			vec![
+				entry(b"a", Some(b"1"), None),
+				entry(b"c", Some(b"3"), Some(b"4")),
+				entry(b"d", None, Some(b"5")),
+			],
+		);
+		assert_eq!(
+			storage_diff(&old, &old, None, None, None, None, 10).unwrap(),
+			Default::default()
+		);
+	}
+
+	#[test]
+	fn diff_respects_prefix_and_paging() {
+		let old = state(vec![(b"p", b"0"), (b"pa", b"1"), (b"pb", b"2"), (b"q", b"3")], vec![]);
+		let new = state(vec![(b"pa", b"9"), (b"pb", b"8"), (b"pc", b"7"), (b"q", b"6")], vec![]);
+		let prefix = StorageKey(b"p".to_vec());
+
+		let first = storage_diff(&old, &new, None, Some(&prefix), None, None, 2).unwrap();
+		assert_eq!(
+			first.entries,
+			vec![entry(b"p", Some(b"0"), None), entry(b"pa", Some(b"1"), Some(b"9"))]
+		);
+		assert_eq!(first.next_key, Some(StorageKey(b"pa".to_vec())));
+
+		let second =
+			storage_diff(&old, &new, None, Some(&prefix), first.next_key.as_ref(), None, 2)
+				.unwrap();
+		assert_eq!(
+			second,
+			StorageDiff {
+				entries: vec![entry(b"pb", Some(b"2"), Some(b"8")), entry(b"pc", None, Some(b"7"))],
+				next_key: None,
+				next_child_key: None,
+			},
+		);
+
+		let third =
+			storage_diff(&old, &new, None, Some(&prefix), Some(&second.entries[1].key), None, 2)
+				.unwrap();
+		assert_eq!(third, StorageDiff::default());
+	}
+
+	#[test]
+	fn diff_descends_into_changed_child_tries() {
+		let child_info = ChildInfo::new_default(b"child");
+		let root_key = child_info.prefixed_storage_key().into_inner();
+		let old = state(vec![(b"a", b"1")], vec![(b"x", b"1"), (b"y", b"2")]);
+		let new = state(vec![(b"a", b"1")], vec![(b"y", b"3")]);
+		let child_entry = |key: &[u8], old: Option<&[u8]>, new: Option<&[u8]>| StorageDiffEntry {
+			child_info: Some(child_info.clone()),
+			..entry(key, old, new)
+		};
+
+		let top = storage_diff(&old, &new, None, None, None, None, 10).unwrap().entries;
+		assert_eq!(top.len(), 3);
+		assert_eq!(
+			top[..2],
+			[child_entry(b"x", Some(b"1"), None), child_entry(b"y", Some(b"2"), Some(b"3"))]
+		);
+		assert_eq!((&top[2].key.0, &top[2].child_info), (&root_key, &None));
+
+		// pages end and resume within the child trie.
+		let first = storage_diff(&old, &new, None, None, None, None, 1).unwrap();
+		assert_eq!(first.entries, top[..1]);
+		assert_eq!(first.next_key, Some(StorageKey(root_key.clone())));
+		assert_eq!(first.next_child_key, Some(StorageKey(b"x".to_vec())));
+		let second = storage_diff(
+			&old,
+			&new,
+			None,
+			None,
+			first.next_key.as_ref(),
+			first.next_child_key.as_ref(),
+			1,
+		)
+		.unwrap();
+		assert_eq!(second.entries, top[1..2]);
+		let third = storage_diff(
+			&old,
+			&new,
+			None,
+			None,
+			second.next_key.as_ref(),
+			second.next_child_key.as_ref(),
+			1,
+		)
+		.unwrap();
+		assert_eq!(
+			third,
+			StorageDiff { entries: top[2..].to_vec(), next_key: None, next_child_key: None }
+		);
+		let after_root_key =
+			storage_diff(&old, &new, None, None, Some(&StorageKey(root_key)), None, 1).unwrap();
+		assert_eq!(after_root_key, StorageDiff::default());
+
+		assert_eq!(
+			storage_diff(&old, &new, Some(&child_info), None, None, None, 10)
+				.unwrap()
+				.entries,
+			vec![entry(b"x", Some(b"1"), None), entry(b"y", Some(b"2"), Some(b"3"))],
+		);
+		assert_eq!(
+			storage_diff(&old, &old, Some(&child_info), None, None, None, 10).unwrap(),
+			StorageDiff::default()
+		);
+	}
+
+	#[test]
+	fn diff_skips_identical_subtries() {
+		let old = large_values((0..1000u32).map(|i| i.to_be_bytes().to_vec()));
+		let mut new = old.clone();
+		new[500].1 = vec![1; 40];
+		let (old, new) = (state(as_refs(&old), vec![]), state(as_refs(&new), vec![]));
+
+		// reading every node of a trie with 1000 entries would exceed the limit.
+		let diff = storage_diff_capped(&old, &new, None, None, None, None, 10, 20).unwrap();
+		assert_eq!(diff.entries.len(), 1);
+		assert_eq!(diff.entries[0].key.0, 500u32.to_be_bytes().to_vec());
+		assert_eq!(diff.next_key, None);
+	}
+
+	#[test]
+	fn diff_stops_after_max_visited_nodes() {
+		let old = large_values((b'a'..=b'z').map(|key| vec![key]));
+		let mut new = old.clone();
+		new[1].1 = vec![1; 40];
+		new[24].1 = vec![2; 40];
+		let (old, new) = (state(as_refs(&old), vec![]), state(as_refs(&new), vec![]));
+
+		// the roots and the nodes of "b" use up the limit before reaching "y".
+		let first = storage_diff_capped(&old, &new, None, None, None, None, 10, 4).unwrap();
+		assert_eq!(first.entries.len(), 1);
+		assert_eq!(first.entries[0].key.0, b"b".to_vec());
+		assert_eq!(first.next_key, Some(StorageKey(b"b".to_vec())));
+
+		// each call compares at least one entry, whatever the limit.
+		let second =
+			storage_diff_capped(&old, &new, None, None, first.next_key.as_ref(), None, 10, 1)
+				.unwrap();
+		assert_eq!(second.entries.len(), 1);
+		assert_eq!(second.entries[0].key.0, b"y".to_vec());
+		assert_eq!(second.next_key, None);
+	}
+}
@@ -86,6 +86,7 @@ pub fn beefy_peers_set_config(
 	let mut cfg = sc_network::config::NonDefaultSetConfig::new(protocol_name, 1024 * 1024);
 
 	cfg.allow_non_reserved(25, 25);
+	cfg.priority = sc_network::config::TrafficPriority::High;
 	cfg.add_fallback_names(beefy_protocol_name::LEGACY_NAMES.iter().map(|&n| n.into()).collect());
 	cfg
 }
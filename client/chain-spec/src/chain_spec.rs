@@ -194,6 +194,27 @@ struct ClientSpec<E> {
 	/// given block number until the `spec_version` on chain changes.
 	#[serde(default)]
 	code_substitutes: BTreeMap<String, Bytes>,
+	/// Finalized blocks trusted by the nodes of the chain.
+	///
+	/// Sync starts from the newest checkpoint, and blocks conflicting with any of them are
+	/// rejected.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	checkpoints: Vec<Checkpoint>,
+}
+
+/// A finalized block trusted by the chain specification.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct Checkpoint {
+	/// Number of the block.
+	pub number: u64,
+	/// Hash of the block.
+	pub hash: Bytes,
+	/// Id of the GRANDPA authority set finalizing the children of the block.
+	pub set_id: u64,
+	/// The authorities of the set `set_id` with their weight.
+	pub authorities: Vec<(Bytes, u64)>,
 }
 
 /// A type denoting empty extensions.
@@ -256,6 +277,16 @@ impl<G, E> ChainSpec<G, E> {
 		self.client_spec.boot_nodes.push(addr)
 	}
 
+	/// Finalized blocks trusted by the chain specification, if any.
+	pub fn checkpoints(&self) -> &[Checkpoint] {
+		&self.client_spec.checkpoints
+	}
+
+	/// Add a checkpoint to the list.
+	pub fn add_checkpoint(&mut self, checkpoint: Checkpoint) {
+		self.client_spec.checkpoints.push(checkpoint)
+	}
+
 	/// Returns a reference to the defined chain spec extensions.
 	pub fn extensions(&self) -> &E {
 		&self.client_spec.extensions
@@ -292,6 +323,7 @@ impl<G, E> ChainSpec<G, E> {
 			consensus_engine: (),
 			genesis: Default::default(),
 			code_substitutes: BTreeMap::new(),
+			checkpoints: Vec::new(),
 		};
 
 		ChainSpec { client_spec, genesis: GenesisSource::Factory(Arc::new(constructor)) }
@@ -416,6 +448,10 @@ where
 		ChainSpec::add_boot_node(self, addr)
 	}
 
+	fn checkpoints(&self) -> &[Checkpoint] {
+		ChainSpec::checkpoints(self)
+	}
+
 	fn extensions(&self) -> &dyn GetExtension {
 		ChainSpec::extensions(self) as &dyn GetExtension
 	}
@@ -513,6 +549,33 @@ mod tests {
 		assert_eq!(spec.extensions().my_property, "Test Extension");
 	}
 
+	#[test]
+	fn should_deserialize_chain_spec_with_checkpoints() {
+		let spec = TestSpec::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec_checkpoints.json").to_vec(),
+		))
+		.unwrap();
+
+		assert_eq!(
+			spec.checkpoints(),
+			&[Checkpoint {
+				number: 1024,
+				hash: Bytes(vec![0xab; 32]),
+				set_id: 3,
+				authorities: vec![(Bytes(vec![0x01; 32]), 1), (Bytes(vec![0x02; 32]), 1)],
+			}],
+		);
+
+		// checkpoints survive a round trip, and are omitted when there are none.
+		let json = spec.as_json(false).unwrap();
+		let decoded = TestSpec::from_json_bytes(json.into_bytes()).unwrap();
+		assert_eq!(decoded.checkpoints(), spec.checkpoints());
+
+		let spec = TestSpec::from_json_file(PathBuf::from("./res/chain_spec.json")).unwrap();
+		assert!(spec.checkpoints().is_empty());
+		assert!(!spec.as_json(false).unwrap().contains("checkpoints"));
+	}
+
 	#[test]
 	fn chain_spec_raw_output_should_be_deterministic() {
 		let mut spec = TestSpec2::from_json_bytes(Cow::Owned(
@@ -170,6 +170,21 @@
 //! /// there is no other way around it and only patch the problematic bug, the rest should be done
 //! /// with a on-chain runtime upgrade.
 //! "codeSubstitutes": [],
+//! // Optional list of finalized blocks trusted by the nodes of the chain.
+//! //
+//! // Each checkpoint gives the number and hash of a block, and the id and authorities of the
+//! // GRANDPA set finalizing its children. Sync starts from the newest checkpoint, with warp sync
+//! // or fast sync, and the blocks conflicting with any checkpoint are rejected.
+//! "checkpoints": [
+//!   {
+//!     "number": 1024,
+//!     "hash": "0xabababababababababababababababababababababababababababababababab",
+//!     "setId": 3,
+//!     "authorities": [
+//!       ["0x0101010101010101010101010101010101010101010101010101010101010101", 1]
+//!     ]
+//!   }
+//! ],
 //! ```
 //!
 //! The chain spec can be extended with other fields that are opaque to the default chain spec.
@@ -178,7 +193,7 @@
 mod chain_spec;
 mod extension;
 
-pub use chain_spec::{ChainSpec as GenericChainSpec, NoExtension};
+pub use chain_spec::{ChainSpec as GenericChainSpec, Checkpoint, NoExtension};
 pub use extension::{
 	get_extension, get_extension_mut, Extension, Fork, Forks, GetExtension, Group,
 };
@@ -245,6 +260,13 @@ pub trait ChainSpec: BuildStorage + Send + Sync {
 	fn extensions_mut(&mut self) -> &mut dyn GetExtension;
 	/// Add a bootnode to the list.
 	fn add_boot_node(&mut self, addr: MultiaddrWithPeerId);
+	/// Finalized blocks trusted by the chain specification, if any.
+	///
+	/// Sync starts from the newest checkpoint, and rejects the blocks conflicting with any of
+	/// them. None by default.
+	fn checkpoints(&self) -> &[Checkpoint] {
+		&[]
+	}
 	/// Return spec as JSON.
 	fn as_json(&self, raw: bool) -> Result<String, String>;
 	/// Return StorageBuilder for this spec.
@@ -0,0 +1,65 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `change-password` subcommand
+
+use super::key::{local_keystore_config, read_new_keystore_password};
+use crate::{Error, KeystoreParams, SharedParams, SubstrateCli};
+use clap::Parser;
+use sc_keystore::LocalKeystore;
+use std::path::PathBuf;
+
+/// The `change-password` command
+#[derive(Debug, Clone, Parser)]
+#[clap(
+	name = "change-password",
+	about = "Re-encrypt the keys of an encrypted keystore with a new password."
+)]
+pub struct ChangeKeystorePasswordCmd {
+	/// File that contains the new password.
+	///
+	/// If not given, you will be prompted for the new password.
+	#[clap(long, value_name = "PATH", parse(from_os_str))]
+	new_password_filename: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+impl ChangeKeystorePasswordCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let (path, password) =
+			local_keystore_config(cli, &self.shared_params, &self.keystore_params)?;
+		let keystore = LocalKeystore::open(path, password)?;
+		if !keystore.is_encrypted() {
+			return Err(Error::Input(
+				"Keystore is not encrypted, use `key encrypt-keystore` to encrypt it".into(),
+			))
+		}
+
+		let new_password = read_new_keystore_password(self.new_password_filename.as_ref())?;
+		keystore.change_password(new_password)?;
+
+		Ok(())
+	}
+}
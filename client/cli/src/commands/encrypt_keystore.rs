@@ -0,0 +1,122 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `encrypt-keystore` subcommand
+
+use super::key::{local_keystore_config, read_new_keystore_password};
+use crate::{Error, KeystoreParams, SharedParams, SubstrateCli};
+use clap::Parser;
+use sc_keystore::LocalKeystore;
+use std::path::PathBuf;
+
+/// The `encrypt-keystore` command
+#[derive(Debug, Clone, Parser)]
+#[clap(
+	name = "encrypt-keystore",
+	about = "Encrypt the keystore of a node, migrating its plaintext keys. The keystore password \
+		options are the password the plaintext keys were created with, if any."
+)]
+pub struct EncryptKeystoreCmd {
+	/// File that contains the password to encrypt the keystore with.
+	///
+	/// If not given, you will be prompted for the password.
+	#[clap(long, value_name = "PATH", parse(from_os_str))]
+	new_password_filename: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+impl EncryptKeystoreCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let (path, legacy_password) =
+			local_keystore_config(cli, &self.shared_params, &self.keystore_params)?;
+		let password = read_new_keystore_password(self.new_password_filename.as_ref())?;
+
+		let keystore = LocalKeystore::encrypt(&path, legacy_password, password)?;
+		println!(
+			"Encrypted keystore {} holds {} keys",
+			path.display(),
+			keystore.public_keys()?.len()
+		);
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{commands::test::Cli, ChangeKeystorePasswordCmd};
+	use sp_core::{crypto::KeyTypeId, sr25519::Pair, ByteArray, Pair as _};
+	use sp_keystore::SyncCryptoStore;
+	use std::{fs, str::FromStr};
+	use tempfile::TempDir;
+
+	#[test]
+	fn encrypt_keystore_and_change_password() {
+		let path = TempDir::new().unwrap();
+		let path_str = format!("{}", path.path().display());
+		let keystore_path = path.path().join("chains").join("test_id").join("keystore");
+		let key = (
+			Pair::from_string("//Alice", None).unwrap().public().to_raw_vec(),
+			KeyTypeId(*b"test"),
+		);
+
+		let keystore = LocalKeystore::open(&keystore_path, None).unwrap();
+		SyncCryptoStore::insert_unknown(&keystore, key.1, "//Alice", &key.0).unwrap();
+		drop(keystore);
+
+		let password_file = path.path().join("password");
+		fs::write(&password_file, "password").unwrap();
+		let new_password_file = path.path().join("new-password");
+		fs::write(&new_password_file, "new password").unwrap();
+
+		let encrypt = EncryptKeystoreCmd::parse_from(&[
+			"encrypt-keystore",
+			"-d",
+			&path_str,
+			"--new-password-filename",
+			password_file.to_str().unwrap(),
+		]);
+		assert!(encrypt.run(&Cli).is_ok());
+		assert!(LocalKeystore::open(&keystore_path, None).is_err());
+
+		let change_password = ChangeKeystorePasswordCmd::parse_from(&[
+			"change-password",
+			"-d",
+			&path_str,
+			"--password-filename",
+			password_file.to_str().unwrap(),
+			"--new-password-filename",
+			new_password_file.to_str().unwrap(),
+		]);
+		assert!(change_password.run(&Cli).is_ok());
+
+		let keystore =
+			LocalKeystore::open(&keystore_path, Some(FromStr::from_str("new password").unwrap()))
+				.unwrap();
+		assert!(keystore.is_encrypted());
+		assert!(keystore.has_keys(&[key]));
+	}
+}
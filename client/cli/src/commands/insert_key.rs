@@ -17,12 +17,12 @@
 
 //! Implementation of the `insert` subcommand
 
+use super::key::local_keystore_config;
 use crate::{
 	utils, with_crypto_scheme, CryptoScheme, Error, KeystoreParams, SharedParams, SubstrateCli,
 };
 use clap::Parser;
 use sc_keystore::LocalKeystore;
-use sc_service::config::{BasePath, KeystoreConfig};
 use sp_core::crypto::{KeyTypeId, SecretString};
 use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
 use std::sync::Arc;
@@ -58,22 +58,14 @@ impl InsertKeyCmd {
 	/// Run the command
 	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
 		let suri = utils::read_uri(self.suri.as_ref())?;
-		let base_path = self
-			.shared_params
-			.base_path()?
-			.unwrap_or_else(|| BasePath::from_project("", "", &C::executable_name()));
-		let chain_id = self.shared_params.chain_id(self.shared_params.is_dev());
-		let chain_spec = cli.load_spec(&chain_id)?;
-		let config_dir = base_path.config_dir(chain_spec.id());
-
-		let (keystore, public) = match self.keystore_params.keystore_config(&config_dir)? {
-			(_, KeystoreConfig::Path { path, password }) => {
-				let public = with_crypto_scheme!(self.scheme, to_vec(&suri, password.clone()))?;
-				let keystore: SyncCryptoStorePtr = Arc::new(LocalKeystore::open(path, password)?);
-				(keystore, public)
-			},
-			_ => unreachable!("keystore_config always returns path and password; qed"),
-		};
+		let (path, password) =
+			local_keystore_config(cli, &self.shared_params, &self.keystore_params)?;
+
+		let local = LocalKeystore::open(path, password.clone())?;
+		// The password of an encrypted keystore is not part of the key derivation.
+		let password = if local.is_encrypted() { None } else { password };
+		let public = with_crypto_scheme!(self.scheme, to_vec(&suri, password))?;
+		let keystore: SyncCryptoStorePtr = Arc::new(local);
 
 		let key_type =
 			KeyTypeId::try_from(self.key_type.as_str()).map_err(|_| Error::KeyTypeInvalid)?;
@@ -93,57 +85,10 @@ fn to_vec<P: sp_core::Pair>(uri: &str, pass: Option<SecretString>) -> Result<Vec
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use sc_service::{ChainSpec, ChainType, GenericChainSpec, NoExtension};
+	use crate::commands::test::Cli;
 	use sp_core::{sr25519::Pair, ByteArray, Pair as _};
 	use tempfile::TempDir;
 
-	struct Cli;
-
-	impl SubstrateCli for Cli {
-		fn impl_name() -> String {
-			"test".into()
-		}
-
-		fn impl_version() -> String {
-			"2.0".into()
-		}
-
-		fn description() -> String {
-			"test".into()
-		}
-
-		fn support_url() -> String {
-			"test.test".into()
-		}
-
-		fn copyright_start_year() -> i32 {
-			2021
-		}
-
-		fn author() -> String {
-			"test".into()
-		}
-
-		fn native_runtime_version(_: &Box<dyn ChainSpec>) -> &'static sp_version::RuntimeVersion {
-			unimplemented!("Not required in tests")
-		}
-
-		fn load_spec(&self, _: &str) -> std::result::Result<Box<dyn ChainSpec>, String> {
-			Ok(Box::new(GenericChainSpec::from_genesis(
-				"test",
-				"test_id",
-				ChainType::Development,
-				|| unimplemented!("Not required in tests"),
-				Vec::new(),
-				None,
-				None,
-				None,
-				None,
-				NoExtension::None,
-			)))
-		}
-	}
-
 	#[test]
 	fn insert_with_custom_base_path() {
 		let path = TempDir::new().unwrap();
@@ -18,10 +18,15 @@
 //! Key related CLI utilities
 
 use super::{
+	change_keystore_password::ChangeKeystorePasswordCmd, encrypt_keystore::EncryptKeystoreCmd,
 	generate::GenerateCmd, generate_node_key::GenerateNodeKeyCmd, insert_key::InsertKeyCmd,
-	inspect_key::InspectKeyCmd, inspect_node_key::InspectNodeKeyCmd,
+	inspect_key::InspectKeyCmd, inspect_node_key::InspectNodeKeyCmd, list_keys::ListKeysCmd,
+	remove_key::RemoveKeyCmd, rotate_session_keys::RotateSessionKeysCmd,
 };
-use crate::{Error, SubstrateCli};
+use crate::{Error, KeystoreParams, SharedParams, SubstrateCli};
+use sc_service::config::{BasePath, KeystoreConfig};
+use sp_core::crypto::SecretString;
+use std::{fs, path::PathBuf};
 
 /// Key utilities for the cli.
 #[derive(Debug, clap::Subcommand)]
@@ -41,6 +46,22 @@ pub enum KeySubcommand {
 
 	/// Insert a key to the keystore of a node.
 	Insert(InsertKeyCmd),
+
+	/// List the keys in the keystore of a node.
+	List(ListKeysCmd),
+
+	/// Remove a key from the keystore of a node.
+	Remove(RemoveKeyCmd),
+
+	/// Encrypt the keystore of a node, migrating its plaintext keys.
+	EncryptKeystore(EncryptKeystoreCmd),
+
+	/// Change the password of an encrypted keystore.
+	ChangePassword(ChangeKeystorePasswordCmd),
+
+	/// Rotate the session keys of a running node, importing them into its standby replicas
+	/// first.
+	RotateSessionKeys(RotateSessionKeysCmd),
 }
 
 impl KeySubcommand {
@@ -52,6 +73,51 @@ impl KeySubcommand {
 			KeySubcommand::Inspect(cmd) => cmd.run(),
 			KeySubcommand::Insert(cmd) => cmd.run(cli),
 			KeySubcommand::InspectNodeKey(cmd) => cmd.run(),
+			KeySubcommand::List(cmd) => cmd.run(cli),
+			KeySubcommand::Remove(cmd) => cmd.run(cli),
+			KeySubcommand::EncryptKeystore(cmd) => cmd.run(cli),
+			KeySubcommand::ChangePassword(cmd) => cmd.run(cli),
+			KeySubcommand::RotateSessionKeys(cmd) => cmd.run(),
 		}
 	}
 }
+
+/// Get the path and password of the local keystore the key subcommands operate on.
+pub(crate) fn local_keystore_config<C: SubstrateCli>(
+	cli: &C,
+	shared_params: &SharedParams,
+	keystore_params: &KeystoreParams,
+) -> Result<(PathBuf, Option<SecretString>), Error> {
+	let base_path = shared_params
+		.base_path()?
+		.unwrap_or_else(|| BasePath::from_project("", "", &C::executable_name()));
+	let chain_id = shared_params.chain_id(shared_params.is_dev());
+	let chain_spec = cli.load_spec(&chain_id)?;
+	let config_dir = base_path.config_dir(chain_spec.id());
+
+	match keystore_params.keystore_config(&config_dir)? {
+		(_, KeystoreConfig::Path { path, password }) => Ok((path, password)),
+		(_, KeystoreConfig::Remote(_)) => Err(Error::Input(
+			"Keys of a remote signer have to be managed on the signer host".into(),
+		)),
+		_ => unreachable!("keystore_config always returns path and password; qed"),
+	}
+}
+
+/// Read the new password of an encrypted keystore from `file`, or prompt for it.
+pub(crate) fn read_new_keystore_password(file: Option<&PathBuf>) -> Result<SecretString, Error> {
+	let password = match file {
+		Some(file) => fs::read_to_string(file)?,
+		None => {
+			let password = rpassword::prompt_password("New keystore password: ")?;
+			if password != rpassword::prompt_password("Repeat new keystore password: ")? {
+				return Err(Error::Input("Passwords do not match".into()))
+			}
+			password
+		},
+	};
+	if password.is_empty() {
+		return Err(Error::Input("Keystore password must not be empty".into()))
+	}
+	Ok(SecretString::new(password))
+}
@@ -0,0 +1,65 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `list` subcommand
+
+use super::key::local_keystore_config;
+use crate::{Error, KeystoreParams, SharedParams, SubstrateCli};
+use clap::Parser;
+use sc_keystore::LocalKeystore;
+use sp_core::crypto::KeyTypeId;
+
+/// The `list` command
+#[derive(Debug, Clone, Parser)]
+#[clap(name = "list", about = "List the keys in the keystore of a node.")]
+pub struct ListKeysCmd {
+	/// Only list keys of this key type, examples: "gran", or "imon"
+	#[clap(long)]
+	key_type: Option<String>,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+impl ListKeysCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let key_type = self
+			.key_type
+			.as_deref()
+			.map(KeyTypeId::try_from)
+			.transpose()
+			.map_err(|_| Error::KeyTypeInvalid)?;
+		let (path, password) =
+			local_keystore_config(cli, &self.shared_params, &self.keystore_params)?;
+		let keystore = LocalKeystore::open(path, password)?;
+
+		let mut keys = keystore.public_keys()?;
+		keys.retain(|(id, _)| key_type.map_or(true, |key_type| *id == key_type));
+		keys.sort();
+		for (id, public) in keys {
+			println!("{} 0x{}", String::from_utf8_lossy(&id.0), hex::encode(public));
+		}
+
+		Ok(())
+	}
+}
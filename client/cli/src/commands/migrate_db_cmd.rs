@@ -0,0 +1,92 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{arg_enums::Database, error, params::SharedParams, CliConfiguration};
+use clap::Parser;
+use sc_client_db::migrate_database;
+use sc_service::DatabaseSource;
+use sp_runtime::traits::Block as BlockT;
+
+/// The `db migrate` command used to convert the database to another backend.
+#[derive(Debug, Clone, Parser)]
+pub struct MigrateDbCmd {
+	/// Database backend to migrate to. ParityDb databases can only be migrated back to RocksDb
+	/// if they were created with a key index, as done since the introduction of this command.
+	#[clap(
+		long,
+		value_name = "DB",
+		ignore_case = true,
+		possible_values = Database::variants(),
+	)]
+	pub to: Database,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl MigrateDbCmd {
+	/// Run the migrate command
+	pub fn run<B: BlockT>(&self, database_config: DatabaseSource) -> error::Result<()> {
+		let (paritydb_path, rocksdb_path, cache_size) = match database_config {
+			DatabaseSource::Auto { paritydb_path, rocksdb_path, cache_size } =>
+				(paritydb_path, rocksdb_path, cache_size),
+			_ => return Err("Cannot migrate custom database implementation".into()),
+		};
+
+		let paritydb = DatabaseSource::ParityDb { path: paritydb_path };
+		let (source, target) = match self.to {
+			Database::ParityDb | Database::ParityDbDeprecated =>
+				(rocksdb_source(rocksdb_path, cache_size)?, paritydb),
+			#[cfg(feature = "rocksdb")]
+			Database::RocksDb => (paritydb, rocksdb_source(rocksdb_path, cache_size)?),
+			Database::Auto => return Err("Migration target must be a specific database".into()),
+		};
+
+		let summary = migrate_database::<B>(&source, &target)?;
+		let entries: u64 = summary.entries.iter().map(|(_, count)| count).sum();
+		println!(
+			"Migrated {} entries to {:?}. The previous database was moved to {:?}.",
+			entries,
+			target.path().expect("Database sources above are on disk; qed"),
+			summary.backup_path,
+		);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "rocksdb")]
+fn rocksdb_source(path: std::path::PathBuf, cache_size: usize) -> error::Result<DatabaseSource> {
+	Ok(DatabaseSource::RocksDb { path, cache_size })
+}
+
+#[cfg(not(feature = "rocksdb"))]
+fn rocksdb_source(_path: std::path::PathBuf, _cache_size: usize) -> error::Result<DatabaseSource> {
+	Err("RocksDb support is not enabled".into())
+}
+
+impl CliConfiguration for MigrateDbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database(&self) -> error::Result<Option<Database>> {
+		// Both database locations are needed to tell the source from the target.
+		Ok(Some(Database::Auto))
+	}
+}
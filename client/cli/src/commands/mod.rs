@@ -17,7 +17,10 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 mod build_spec_cmd;
 mod chain_info_cmd;
+mod change_keystore_password;
 mod check_block_cmd;
+mod db;
+mod encrypt_keystore;
 mod export_blocks_cmd;
 mod export_state_cmd;
 mod generate;
@@ -27,19 +30,28 @@ mod insert_key;
 mod inspect_key;
 mod inspect_node_key;
 mod key;
+mod list_keys;
+mod migrate_db_cmd;
 mod purge_chain_cmd;
+mod remove_key;
 mod revert_cmd;
+mod rotate_session_keys;
 mod run_cmd;
 mod sign;
+#[cfg(test)]
+mod test;
 pub mod utils;
 mod vanity;
 mod verify;
 
 pub use self::{
-	build_spec_cmd::BuildSpecCmd, chain_info_cmd::ChainInfoCmd, check_block_cmd::CheckBlockCmd,
-	export_blocks_cmd::ExportBlocksCmd, export_state_cmd::ExportStateCmd, generate::GenerateCmd,
-	generate_node_key::GenerateNodeKeyCmd, import_blocks_cmd::ImportBlocksCmd,
-	insert_key::InsertKeyCmd, inspect_key::InspectKeyCmd, inspect_node_key::InspectNodeKeyCmd,
-	key::KeySubcommand, purge_chain_cmd::PurgeChainCmd, revert_cmd::RevertCmd, run_cmd::RunCmd,
+	build_spec_cmd::BuildSpecCmd, chain_info_cmd::ChainInfoCmd,
+	change_keystore_password::ChangeKeystorePasswordCmd, check_block_cmd::CheckBlockCmd,
+	db::DbSubcommand, encrypt_keystore::EncryptKeystoreCmd, export_blocks_cmd::ExportBlocksCmd,
+	export_state_cmd::ExportStateCmd, generate::GenerateCmd, generate_node_key::GenerateNodeKeyCmd,
+	import_blocks_cmd::ImportBlocksCmd, insert_key::InsertKeyCmd, inspect_key::InspectKeyCmd,
+	inspect_node_key::InspectNodeKeyCmd, key::KeySubcommand, list_keys::ListKeysCmd,
+	migrate_db_cmd::MigrateDbCmd, purge_chain_cmd::PurgeChainCmd, remove_key::RemoveKeyCmd,
+	revert_cmd::RevertCmd, rotate_session_keys::RotateSessionKeysCmd, run_cmd::RunCmd,
 	sign::SignCmd, vanity::VanityCmd, verify::VerifyCmd,
 };
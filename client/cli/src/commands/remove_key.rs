@@ -0,0 +1,63 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `remove` subcommand
+
+use super::key::local_keystore_config;
+use crate::{utils, Error, KeystoreParams, SharedParams, SubstrateCli};
+use clap::Parser;
+use sc_keystore::LocalKeystore;
+use sp_core::crypto::KeyTypeId;
+
+/// The `remove` command
+#[derive(Debug, Clone, Parser)]
+#[clap(name = "remove", about = "Remove a key from the keystore of a node.")]
+pub struct RemoveKeyCmd {
+	/// Hex encoded public key of the key to remove.
+	#[clap(long)]
+	public: String,
+
+	/// Key type, examples: "gran", or "imon"
+	#[clap(long)]
+	key_type: String,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+impl RemoveKeyCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let key_type =
+			KeyTypeId::try_from(self.key_type.as_str()).map_err(|_| Error::KeyTypeInvalid)?;
+		let public = utils::decode_hex(&self.public)?;
+		let (path, password) =
+			local_keystore_config(cli, &self.shared_params, &self.keystore_params)?;
+		let keystore = LocalKeystore::open(path, password)?;
+
+		if !keystore.remove_key(key_type, &public)? {
+			return Err(Error::Input("Key not found in the keystore".into()))
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,50 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `rotate-session-keys` subcommand
+
+use crate::Error;
+use clap::Parser;
+
+/// The `rotate-session-keys` command
+#[derive(Debug, Clone, Parser)]
+#[clap(
+	name = "rotate-session-keys",
+	about = "Rotate the session keys of a node after importing them into its standby replicas."
+)]
+pub struct RotateSessionKeysCmd {
+	/// WebSocket RPC URL of the active node, e.g. "ws://127.0.0.1:9944".
+	///
+	/// The node has to expose unsafe RPC methods and be started with
+	/// `--key-rotation-password-filename`.
+	#[clap(long, value_name = "URL", default_value = "ws://127.0.0.1:9944")]
+	url: String,
+}
+
+impl RotateSessionKeysCmd {
+	/// Run the command
+	pub fn run(&self) -> Result<(), Error> {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+		let session_keys = runtime
+			.block_on(sc_rpc::author::rotate_keys_with_replicas(&self.url))
+			.map_err(|e| Error::Application(Box::new(e)))?;
+
+		println!("0x{}", hex::encode(session_keys.0));
+
+		Ok(())
+	}
+}
@@ -28,14 +28,16 @@ use crate::{
 use clap::Parser;
 use regex::Regex;
 use sc_service::{
-	config::{BasePath, PrometheusConfig, TransactionPoolOptions},
+	config::{BasePath, KeyRotationConfig, PrometheusConfig, TransactionPoolOptions},
 	ChainSpec, Role,
 };
 use sc_telemetry::TelemetryEndpoints;
-use sp_authority_permission::{
-	AlwaysPermissionGrantedFactory, PermissionResolverFactory,
+use sp_authority_permission::{AlwaysPermissionGrantedFactory, PermissionResolverFactory};
+use sp_core::crypto::SecretString;
+use std::{
+	net::{IpAddr, Ipv4Addr, SocketAddr},
+	path::PathBuf,
 };
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 /// The `run` command used to run a node.
 #[derive(Debug, Clone, Parser)]
@@ -53,6 +55,20 @@ pub struct RunCmd {
 	#[clap(long)]
 	pub no_grandpa: bool,
 
+	/// Restrict the GRANDPA votes of this node with the policy in the given JSON file.
+	///
+	/// On nodes exposing the unsafe `grandpa_setVotingPolicy` RPC, the policy can be replaced at
+	/// runtime.
+	#[clap(long, value_name = "PATH", parse(from_os_str))]
+	pub grandpa_voting_policy: Option<PathBuf>,
+
+	/// Report the state of the GRANDPA voter once the finalized block lags the best block by
+	/// more than this many blocks.
+	///
+	/// The report is logged periodically and returned by the `grandpa_stallReport` RPC.
+	#[clap(long, value_name = "BLOCKS")]
+	pub grandpa_stall_threshold: Option<u32>,
+
 	/// Listen to all RPC interfaces.
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC
@@ -242,10 +258,43 @@ pub struct RunCmd {
 	#[clap(long)]
 	pub force_authoring: bool,
 
+	/// Build, but neither seal nor announce, blocks in slots this node has no permission to
+	/// author in.
+	///
+	/// Keeps a standby authority ready to take over block production.
+	#[clap(long)]
+	pub shadow_authoring: bool,
+
+	/// Stop authoring and voting with a key once this node sees an equivocation committed with
+	/// it, until the node is restarted.
+	///
+	/// Such equivocations mean that another node signs with the same key. They are always
+	/// reported in the logs, telemetry and metrics.
+	#[clap(long)]
+	pub equivocation_circuit_breaker: bool,
+
 	#[allow(missing_docs)]
 	#[clap(flatten)]
 	pub keystore_params: KeystoreParams,
 
+	/// WebSocket RPC URL of a standby replica that has to import new session keys before
+	/// `author_rotateKeysWithReplicas` switches this node to them.
+	///
+	/// This flag can be passed multiple times. The replicas need to be started with the same
+	/// `--key-rotation-password-filename`.
+	#[clap(
+		long = "key-rotation-replica",
+		value_name = "URL",
+		requires = "key-rotation-password-filename"
+	)]
+	pub key_rotation_replicas: Vec<String>,
+
+	/// File that contains the password used to seal session keys sent to standby replicas.
+	///
+	/// Enables `author_rotateKeysWithReplicas` and `author_importKeys`.
+	#[clap(long, value_name = "PATH", parse(from_os_str))]
+	pub key_rotation_password_filename: Option<PathBuf>,
+
 	/// The size of the instances cache for each runtime.
 	///
 	/// The default value is 8 and the values higher than 256 are ignored.
@@ -372,6 +421,14 @@ impl CliConfiguration for RunCmd {
 		Ok(self.shared_params.dev || self.force_authoring)
 	}
 
+	fn shadow_authoring(&self) -> Result<bool> {
+		Ok(self.shadow_authoring)
+	}
+
+	fn equivocation_circuit_breaker(&self) -> Result<bool> {
+		Ok(self.equivocation_circuit_breaker)
+	}
+
 	fn prometheus_config(
 		&self,
 		default_listen_port: u16,
@@ -397,6 +454,14 @@ impl CliConfiguration for RunCmd {
 		Ok(self.no_grandpa)
 	}
 
+	fn grandpa_voting_policy(&self) -> Result<Option<PathBuf>> {
+		Ok(self.grandpa_voting_policy.clone())
+	}
+
+	fn grandpa_stall_threshold(&self) -> Result<Option<u32>> {
+		Ok(self.grandpa_stall_threshold)
+	}
+
 	fn rpc_ws_max_connections(&self) -> Result<Option<usize>> {
 		Ok(self.ws_max_connections)
 	}
@@ -472,8 +537,29 @@ impl CliConfiguration for RunCmd {
 		Ok(self.ws_max_out_buffer_capacity)
 	}
 
-	fn transaction_pool(&self, is_dev: bool) -> Result<TransactionPoolOptions> {
-		Ok(self.pool_config.transaction_pool(is_dev))
+	fn key_rotation(&self) -> Result<Option<KeyRotationConfig>> {
+		let file = match self.key_rotation_password_filename {
+			Some(ref file) => file,
+			None => return Ok(None),
+		};
+		let password = std::fs::read_to_string(file)?;
+		let password = password.trim_end_matches(&['\r', '\n'][..]);
+		if password.is_empty() {
+			return Err(Error::Input("Key rotation password must not be empty".into()))
+		}
+
+		Ok(Some(KeyRotationConfig {
+			replicas: self.key_rotation_replicas.clone(),
+			password: SecretString::new(password.to_string()),
+		}))
+	}
+
+	fn transaction_pool(
+		&self,
+		is_dev: bool,
+		config_dir: &PathBuf,
+	) -> Result<TransactionPoolOptions> {
+		Ok(self.pool_config.transaction_pool(is_dev, config_dir))
 	}
 
 	fn max_runtime_instances(&self) -> Result<Option<usize>> {
@@ -0,0 +1,70 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test utils
+
+use crate::SubstrateCli;
+use sc_service::{ChainSpec, ChainType, GenericChainSpec, NoExtension};
+
+/// A [`SubstrateCli`] whose chain spec has the id `test_id`, so that the keystore of a base path
+/// is at `chains/test_id/keystore`.
+pub struct Cli;
+
+impl SubstrateCli for Cli {
+	fn impl_name() -> String {
+		"test".into()
+	}
+
+	fn impl_version() -> String {
+		"2.0".into()
+	}
+
+	fn description() -> String {
+		"test".into()
+	}
+
+	fn support_url() -> String {
+		"test.test".into()
+	}
+
+	fn copyright_start_year() -> i32 {
+		2021
+	}
+
+	fn author() -> String {
+		"test".into()
+	}
+
+	fn native_runtime_version(_: &Box<dyn ChainSpec>) -> &'static sp_version::RuntimeVersion {
+		unimplemented!("Not required in tests")
+	}
+
+	fn load_spec(&self, _: &str) -> std::result::Result<Box<dyn ChainSpec>, String> {
+		Ok(Box::new(GenericChainSpec::from_genesis(
+			"test",
+			"test_id",
+			ChainType::Development,
+			|| unimplemented!("Not required in tests"),
+			Vec::new(),
+			None,
+			None,
+			None,
+			None,
+			NoExtension::None,
+		)))
+	}
+}
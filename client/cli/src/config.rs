@@ -24,12 +24,12 @@ use crate::{
 };
 use log::warn;
 use names::{Generator, Name};
-use sc_client_api::execution_extensions::ExecutionStrategies;
+use sc_client_api::execution_extensions::{ExecutionStrategies, FuelLimits};
 use sc_service::{
 	config::{
-		BasePath, Configuration, DatabaseSource, KeystoreConfig, NetworkConfiguration,
-		NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
-		TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
+		BasePath, Configuration, DatabaseSource, KeyRotationConfig, KeystoreConfig,
+		NetworkConfiguration, NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode,
+		Role, RpcMethods, TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
 	},
 	BlocksPruning, ChainSpec, TracingReceiver,
 };
@@ -146,7 +146,11 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 	/// Get the transaction pool options
 	///
 	/// By default this is `TransactionPoolOptions::default()`.
-	fn transaction_pool(&self, _is_dev: bool) -> Result<TransactionPoolOptions> {
+	fn transaction_pool(
+		&self,
+		_is_dev: bool,
+		_config_dir: &PathBuf,
+	) -> Result<TransactionPoolOptions> {
 		Ok(Default::default())
 	}
 
@@ -303,6 +307,14 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			.unwrap_or_default())
 	}
 
+	/// Get the fuel limits of runtime calls.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise the calls
+	/// aren't limited.
+	fn fuel_limits(&self) -> Result<FuelLimits> {
+		Ok(self.import_params().map(|x| x.fuel_limits()).unwrap_or_default())
+	}
+
 	/// Get the RPC HTTP address (`None` if disabled).
 	///
 	/// By default this is `None`.
@@ -371,6 +383,13 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(None)
 	}
 
+	/// Get the configuration of session key rotation through standby replicas.
+	///
+	/// By default this is `None`.
+	fn key_rotation(&self) -> Result<Option<KeyRotationConfig>> {
+		Ok(None)
+	}
+
 	/// Get the prometheus configuration (`None` if disabled)
 	///
 	/// By default this is `None`.
@@ -415,6 +434,20 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns `Ok(true)` if blocks should be built in slots without authoring permission
+	///
+	/// By default this is `false`.
+	fn shadow_authoring(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
+	/// Returns `Ok(true)` if the node should stop signing with keys that equivocated
+	///
+	/// By default this is `false`.
+	fn equivocation_circuit_breaker(&self) -> Result<bool> {
+		Ok(Default::default())
+	}
+
 	/// Returns `Ok(true)` if grandpa should be disabled
 	///
 	/// By default this is `false`.
@@ -422,6 +455,20 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the path of the policy restricting GRANDPA votes
+	///
+	/// By default this is `None`.
+	fn grandpa_voting_policy(&self) -> Result<Option<PathBuf>> {
+		Ok(Default::default())
+	}
+
+	/// Get the finality lag, in blocks, from which GRANDPA reports a finality stall
+	///
+	/// By default this is `None`.
+	fn grandpa_stall_threshold(&self) -> Result<Option<u32>> {
+		Ok(Default::default())
+	}
+
 	/// Get the development key seed from the current object
 	///
 	/// By default this is `None`.
@@ -519,7 +566,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			impl_name: C::impl_name(),
 			impl_version: C::impl_version(),
 			tokio_handle,
-			transaction_pool: self.transaction_pool(is_dev)?,
+			transaction_pool: self.transaction_pool(is_dev, &config_dir)?,
 			network: self.network_config(
 				&chain_spec,
 				is_dev,
@@ -532,6 +579,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			)?,
 			keystore_remote,
 			keystore,
+			key_rotation: self.key_rotation()?,
 			database: self.database_config(&config_dir, database_cache_size, database)?,
 			trie_cache_maximum_size: self.trie_cache_maximum_size()?,
 			state_pruning: self.state_pruning()?,
@@ -539,6 +587,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			wasm_method: self.wasm_method()?,
 			wasm_runtime_overrides: self.wasm_runtime_overrides(),
 			execution_strategies: self.execution_strategies(is_dev, is_validator)?,
+			fuel_limits: self.fuel_limits()?,
 			rpc_http: self.rpc_http(DCV::rpc_http_listen_port())?,
 			rpc_ws: self.rpc_ws(DCV::rpc_ws_listen_port())?,
 			rpc_ipc: self.rpc_ipc()?,
@@ -557,7 +606,11 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			default_heap_pages: self.default_heap_pages()?,
 			offchain_worker: self.offchain_worker(&role)?,
 			force_authoring: self.force_authoring()?,
+			shadow_authoring: self.shadow_authoring()?,
+			equivocation_circuit_breaker: self.equivocation_circuit_breaker()?,
 			disable_grandpa: self.disable_grandpa()?,
+			grandpa_voting_policy: self.grandpa_voting_policy()?,
+			grandpa_stall_threshold: self.grandpa_stall_threshold()?,
 			dev_key_seed: self.dev_key_seed(is_dev)?,
 			tracing_targets: self.tracing_targets()?,
 			tracing_receiver: self.tracing_receiver()?,
@@ -27,7 +27,7 @@ use crate::{
 	params::{DatabaseParams, PruningParams},
 };
 use clap::Args;
-use sc_client_api::execution_extensions::ExecutionStrategies;
+use sc_client_api::execution_extensions::{ExecutionStrategies, FuelLimits};
 use std::path::PathBuf;
 
 /// Parameters for block import.
@@ -94,6 +94,10 @@ pub struct ImportParams {
 	#[clap(flatten)]
 	pub execution_strategies: ExecutionStrategiesParams,
 
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub fuel_limits: FuelLimitsParams,
+
 	/// Specify the state cache size.
 	///
 	/// Providing `0` will disable the cache.
@@ -161,6 +165,16 @@ impl ImportParams {
 			other: exec_all_or(exec.execution_other, DEFAULT_EXECUTION_OTHER),
 		}
 	}
+
+	/// Get the fuel limits of runtime calls for the parameters
+	pub fn fuel_limits(&self) -> FuelLimits {
+		let limits = &self.fuel_limits;
+		FuelLimits {
+			importing: limits.import_fuel_limit,
+			offchain_worker: limits.offchain_fuel_limit,
+			rpc: limits.rpc_fuel_limit,
+		}
+	}
 }
 
 /// Execution strategies parameters.
@@ -205,3 +219,27 @@ pub struct ExecutionStrategiesParams {
 	)]
 	pub execution: Option<ExecutionStrategy>,
 }
+
+/// Fuel limits parameters.
+///
+/// Fuel roughly corresponds to the number of WebAssembly instructions executed by a call. Setting
+/// any of the limits compiles runtimes with fuel metering, which slows down all calls, and is
+/// only supported by the compiled WASM execution method, without the legacy instance reuse
+/// instantiation strategy. Calls executed natively aren't limited.
+#[derive(Debug, Clone, Args)]
+pub struct FuelLimitsParams {
+	/// The maximum fuel a call into the runtime may consume while importing, syncing or
+	/// constructing blocks.
+	///
+	/// Blocks whose execution exceeds the limit can't be imported.
+	#[clap(long, value_name = "FUEL")]
+	pub import_fuel_limit: Option<u64>,
+
+	/// The maximum fuel a call into the runtime may consume while using an off-chain worker.
+	#[clap(long, value_name = "FUEL")]
+	pub offchain_fuel_limit: Option<u64>,
+
+	/// The maximum fuel a call into the runtime made on behalf of an RPC client may consume.
+	#[clap(long, value_name = "FUEL")]
+	pub rpc_fuel_limit: Option<u64>,
+}
@@ -18,7 +18,9 @@
 
 use crate::{error, error::Result};
 use clap::Args;
-use sc_service::config::KeystoreConfig;
+use sc_service::config::{
+	KeystoreConfig, RemoteKeystoreConfig, RemoteKeystoreEndpoint, RemoteKeystoreTlsConfig,
+};
 use sp_core::crypto::SecretString;
 use std::{
 	fs,
@@ -39,12 +41,41 @@ pub struct KeystoreParams {
 	#[clap(long, value_name = "PATH", parse(from_os_str))]
 	pub keystore_path: Option<PathBuf>,
 
+	/// Keep the keys on a remote signer instead of the local keystore.
+	///
+	/// Either `tls://<host>:<port>`, which requires the `--keystore-signer-ca`,
+	/// `--keystore-signer-cert` and `--keystore-signer-key` options, or `unix://<path>`.
+	#[clap(
+		long,
+		value_name = "URI",
+		conflicts_with_all = &["keystore-path", "password-interactive", "password", "password-filename"]
+	)]
+	pub keystore_signer: Option<String>,
+
+	/// PEM file with the certificate authorities the certificate of the remote signer is
+	/// verified against.
+	#[clap(
+		long,
+		value_name = "PATH",
+		parse(from_os_str),
+		requires_all = &["keystore-signer", "keystore-signer-cert", "keystore-signer-key"]
+	)]
+	pub keystore_signer_ca: Option<PathBuf>,
+
+	/// PEM file with the client certificate presented to the remote signer.
+	#[clap(long, value_name = "PATH", parse(from_os_str), requires = "keystore-signer-ca")]
+	pub keystore_signer_cert: Option<PathBuf>,
+
+	/// PEM file with the private key of the client certificate presented to the remote signer.
+	#[clap(long, value_name = "PATH", parse(from_os_str), requires = "keystore-signer-ca")]
+	pub keystore_signer_key: Option<PathBuf>,
+
 	/// Use interactive shell for entering the password used by the keystore.
 	#[clap(long, conflicts_with_all = &["password", "password-filename"])]
 	pub password_interactive: bool,
 
 	/// Password used by the keystore. This allows appending an extra user-defined secret to the
-	/// seed.
+	/// seed, or decrypts the keys of an encrypted keystore.
 	#[clap(
 		long,
 		parse(try_from_str = secret_string_from_str),
@@ -72,6 +103,26 @@ impl KeystoreParams {
 	///
 	/// Returns a vector of remote-urls and the local Keystore configuration
 	pub fn keystore_config(&self, config_dir: &Path) -> Result<(Option<String>, KeystoreConfig)> {
+		if let Some(ref signer) = self.keystore_signer {
+			let endpoint = signer
+				.parse::<RemoteKeystoreEndpoint>()
+				.map_err(|e| error::Error::Input(e.to_string()))?;
+			let tls = match (
+				&self.keystore_signer_ca,
+				&self.keystore_signer_cert,
+				&self.keystore_signer_key,
+			) {
+				(Some(ca), Some(cert), Some(key)) => Some(RemoteKeystoreTlsConfig {
+					ca: ca.clone(),
+					cert: cert.clone(),
+					key: key.clone(),
+				}),
+				_ => None,
+			};
+			let config = RemoteKeystoreConfig { endpoint, tls };
+			return Ok((self.keystore_uri.clone(), KeystoreConfig::Remote(config)))
+		}
+
 		let password = if self.password_interactive {
 			Some(SecretString::new(input_keystore_password()?))
 		} else if let Some(ref file) = self.password_filename {
@@ -20,7 +20,8 @@ use crate::{arg_enums::SyncMode, params::node_key_params::NodeKeyParams};
 use clap::Args;
 use sc_network::{
 	config::{
-		NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig, TransportConfig,
+		BandwidthConfig, NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode,
+		ProtocolBandwidth, SetConfig, TransportConfig,
 	},
 	multiaddr::Protocol,
 };
@@ -28,7 +29,7 @@ use sc_service::{
 	config::{Multiaddr, MultiaddrWithPeerId},
 	ChainSpec, ChainType,
 };
-use std::{borrow::Cow, path::PathBuf};
+use std::{borrow::Cow, num::NonZeroU64, path::PathBuf};
 
 /// Parameters used to create the network configuration.
 #[derive(Debug, Clone, Args)]
@@ -100,6 +101,40 @@ pub struct NetworkParams {
 	#[clap(long)]
 	pub no_mdns: bool,
 
+	/// Disable the address book.
+	///
+	/// By default, the addresses of the nodes we successfully connected to are saved in the
+	/// network configuration directory and used to find these nodes again after a restart.
+	/// This disables it.
+	#[clap(long)]
+	pub no_address_book: bool,
+
+	/// Maximum bandwidth received over all the networking protocols, in KiB/s.
+	///
+	/// Traffic over the limit is delayed. A share of the limit is reserved to consensus
+	/// protocols, such as block announces and GRANDPA, so that they are never starved by
+	/// transactions gossip.
+	#[clap(long, value_name = "KIB_PER_SEC")]
+	pub max_inbound_bandwidth: Option<NonZeroU64>,
+
+	/// Maximum bandwidth sent over all the networking protocols, in KiB/s.
+	///
+	/// Traffic over the limit is delayed. A share of the limit is reserved to consensus
+	/// protocols, such as block announces and GRANDPA, so that they are never starved by
+	/// transactions gossip.
+	#[clap(long, value_name = "KIB_PER_SEC")]
+	pub max_outbound_bandwidth: Option<NonZeroU64>,
+
+	/// Limit the bandwidth of a single networking protocol, in KiB/s in each direction.
+	///
+	/// Format is `<PROTOCOL_NAME>=<KIB_PER_SEC>`. Can be passed multiple times.
+	#[clap(
+		long,
+		value_name = "PROTOCOL=KIB_PER_SEC",
+		parse(try_from_str = parse_protocol_bandwidth)
+	)]
+	pub protocol_bandwidth: Vec<(String, NonZeroU64)>,
+
 	/// Maximum number of peers from which to ask for the same blocks in parallel.
 	///
 	/// This allows downloading announced blocks from multiple peers. Decrease to save
@@ -130,6 +165,13 @@ pub struct NetworkParams {
 	#[clap(long)]
 	pub ipfs_server: bool,
 
+	/// Enable the QUIC transport.
+	///
+	/// QUIC addresses, such as `/ip4/0.0.0.0/udp/30333/quic`, can then be passed to
+	/// `--listen-addr`, `--public-addr` and `--bootnodes`.
+	#[clap(long)]
+	pub enable_quic: bool,
+
 	/// Blockchain syncing mode.
 	///
 	/// - `full`: Download and validate full blockchain history.
@@ -239,11 +281,43 @@ impl NetworkParams {
 			kademlia_disjoint_query_paths: self.kademlia_disjoint_query_paths,
 			yamux_window_size: None,
 			ipfs_server: self.ipfs_server,
+			enable_quic: self.enable_quic,
 			sync_mode: self.sync.into(),
+			address_book: if self.no_address_book { None } else { Some(Default::default()) },
+			bandwidth: self.bandwidth_config(),
+		}
+	}
+
+	/// Builds the bandwidth limits from the cli parameters.
+	fn bandwidth_config(&self) -> BandwidthConfig {
+		let kib =
+			|limit: NonZeroU64| limit.saturating_mul(NonZeroU64::new(1024).expect("1024 > 0"));
+
+		BandwidthConfig {
+			max_inbound: self.max_inbound_bandwidth.map(kib),
+			max_outbound: self.max_outbound_bandwidth.map(kib),
+			protocols: self
+				.protocol_bandwidth
+				.iter()
+				.map(|(protocol, limit)| {
+					let limit = Some(kib(*limit));
+					let limits = ProtocolBandwidth { max_inbound: limit, max_outbound: limit };
+					(protocol.clone().into(), limits)
+				})
+				.collect(),
+			..Default::default()
 		}
 	}
 }
 
+fn parse_protocol_bandwidth(s: &str) -> Result<(String, NonZeroU64), String> {
+	let (protocol, limit) = s
+		.rsplit_once('=')
+		.ok_or_else(|| format!("expected `<PROTOCOL_NAME>=<KIB_PER_SEC>`, got `{}`", s))?;
+	let limit = limit.parse().map_err(|e| format!("invalid bandwidth `{}`: {}", limit, e))?;
+	Ok((protocol.to_string(), limit))
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -294,4 +368,27 @@ mod tests {
 
 		assert_eq!(SyncMode::Warp, params.network_params.sync);
 	}
+
+	#[test]
+	fn bandwidth_limits_are_converted_to_bytes() {
+		let params = Cli::try_parse_from([
+			"",
+			"--max-outbound-bandwidth",
+			"100",
+			"--protocol-bandwidth",
+			"/foo/transactions/1=10",
+		])
+		.expect("Parses network params");
+
+		let config = params.network_params.bandwidth_config();
+		assert_eq!(config.max_inbound, None);
+		assert_eq!(config.max_outbound, NonZeroU64::new(100 * 1024));
+		let (name, protocol) = config.protocols.iter().next().expect("One protocol limit");
+		assert_eq!(&**name, "/foo/transactions/1");
+		assert_eq!(protocol.max_inbound, NonZeroU64::new(10 * 1024));
+		assert_eq!(protocol.max_outbound, NonZeroU64::new(10 * 1024));
+
+		assert!(Cli::try_parse_from(["", "--protocol-bandwidth", "/foo/1"]).is_err());
+		assert!(Cli::try_parse_from(["", "--protocol-bandwidth", "/foo/1=0"]).is_err());
+	}
 }
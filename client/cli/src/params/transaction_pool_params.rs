@@ -17,7 +17,11 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use clap::Args;
-use sc_service::config::TransactionPoolOptions;
+use sc_service::config::{PoolJournalOptions, PoolLimit, PoolSenderLimit, TransactionPoolOptions};
+use std::path::Path;
+
+/// Path of the transaction pool journal, relative to the chain config directory.
+const DEFAULT_JOURNAL_PATH: &str = "txpool/journal";
 
 /// Parameters used to create the pool configuration.
 #[derive(Debug, Clone, Args)]
@@ -33,11 +37,45 @@ pub struct TransactionPoolParams {
 	/// How long a transaction is banned for, if it is considered invalid. Defaults to 1800s.
 	#[clap(long, value_name = "SECONDS")]
 	pub tx_ban_seconds: Option<u64>,
+
+	/// Maximum number of transactions of a single sender in the transaction pool.
+	///
+	/// Transactions submitted locally are not subject to this limit.
+	#[clap(long, value_name = "COUNT")]
+	pub pool_sender_limit: Option<usize>,
+
+	/// Maximum number of kilobytes of all transactions of a single sender stored in the pool.
+	#[clap(long, value_name = "COUNT", requires = "pool-sender-limit")]
+	pub pool_sender_kbytes: Option<usize>,
+
+	/// Number of leading bytes of a transaction's first `provides` tag that identify its sender.
+	///
+	/// For FRAME based runtimes this is the encoded length of the `AccountId`.
+	#[clap(long, value_name = "BYTES", default_value = "32")]
+	pub pool_sender_tag_prefix: usize,
+
+	/// Number of transactions in the pool reserved for locally submitted transactions.
+	#[clap(long, value_name = "COUNT", default_value = "0")]
+	pub pool_reserved_limit: usize,
+
+	/// Number of kilobytes in the pool reserved for locally submitted transactions.
+	#[clap(long, value_name = "COUNT", default_value = "0")]
+	pub pool_reserved_kbytes: usize,
+
+	/// Persist locally submitted transactions across restarts.
+	///
+	/// Journaled transactions are validated again against the best block after a restart.
+	#[clap(long)]
+	pub pool_journal: bool,
+
+	/// Persist transactions received from the network across restarts as well.
+	#[clap(long, requires = "pool-journal")]
+	pub pool_journal_all: bool,
 }
 
 impl TransactionPoolParams {
 	/// Fill the given `PoolConfiguration` by looking at the cli parameters.
-	pub fn transaction_pool(&self, is_dev: bool) -> TransactionPoolOptions {
+	pub fn transaction_pool(&self, is_dev: bool, config_dir: &Path) -> TransactionPoolOptions {
 		let mut opts = TransactionPoolOptions::default();
 
 		// ready queue
@@ -49,6 +87,25 @@ impl TransactionPoolParams {
 		opts.future.count = self.pool_limit / factor;
 		opts.future.total_bytes = self.pool_kbytes * 1024 / factor;
 
+		opts.sender = self.pool_sender_limit.map(|count| PoolSenderLimit {
+			tag_prefix_len: self.pool_sender_tag_prefix,
+			limit: PoolLimit {
+				count,
+				total_bytes: self
+					.pool_sender_kbytes
+					.map_or(opts.ready.total_bytes, |kbytes| kbytes * 1024),
+			},
+		});
+		opts.reserved = PoolLimit {
+			count: self.pool_reserved_limit,
+			total_bytes: self.pool_reserved_kbytes * 1024,
+		};
+
+		opts.journal = self.pool_journal.then(|| PoolJournalOptions {
+			path: config_dir.join(DEFAULT_JOURNAL_PATH),
+			include_external: self.pool_journal_all,
+		});
+
 		opts.ban_time = if let Some(ban_seconds) = self.tx_ban_seconds {
 			std::time::Duration::from_secs(ban_seconds)
 		} else if is_dev {
@@ -26,8 +26,11 @@ use sc_client_api::{backend::AuxStore, BlockOf, UsageProvider};
 use sc_consensus::{
 	block_import::{BlockImport, BlockImportParams, ForkChoiceStrategy},
 	import_queue::{BasicQueue, BoxJustificationImport, DefaultImportQueue, Verifier},
+	EquivocationGuard,
+};
+use sc_consensus_slots::{
+	check_equivocation, report_self_equivocation, CheckedHeader, InherentDataProviderExt,
 };
-use sc_consensus_slots::{check_equivocation, CheckedHeader, InherentDataProviderExt};
 use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_DEBUG, CONSENSUS_TRACE};
 use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_block_builder::BlockBuilder as BlockBuilderApi;
@@ -62,6 +65,7 @@ fn check_header<C, B: BlockT, P: Pair>(
 	hash: B::Hash,
 	authorities: &[AuthorityId<P>],
 	check_for_equivocation: CheckForEquivocation,
+	equivocation_guard: Option<&EquivocationGuard>,
 ) -> Result<CheckedHeader<B::Header, (Slot, DigestItem)>, Error<B>>
 where
 	P::Signature: Codec,
@@ -98,6 +102,14 @@ where
 						equivocation_proof.first_header.hash(),
 						equivocation_proof.second_header.hash(),
 					);
+					if let Some(guard) = equivocation_guard {
+						report_self_equivocation(
+							guard,
+							"aura",
+							sp_application_crypto::key_types::AURA,
+							&equivocation_proof,
+						);
+					}
 				}
 			}
 
@@ -115,6 +127,7 @@ pub struct AuraVerifier<C, P, CAW, CIDP> {
 	create_inherent_data_providers: CIDP,
 	can_author_with: CAW,
 	check_for_equivocation: CheckForEquivocation,
+	equivocation_guard: Option<EquivocationGuard>,
 	telemetry: Option<TelemetryHandle>,
 }
 
@@ -124,6 +137,7 @@ impl<C, P, CAW, CIDP> AuraVerifier<C, P, CAW, CIDP> {
 		create_inherent_data_providers: CIDP,
 		can_author_with: CAW,
 		check_for_equivocation: CheckForEquivocation,
+		equivocation_guard: Option<EquivocationGuard>,
 		telemetry: Option<TelemetryHandle>,
 	) -> Self {
 		Self {
@@ -131,6 +145,7 @@ impl<C, P, CAW, CIDP> AuraVerifier<C, P, CAW, CIDP> {
 			create_inherent_data_providers,
 			can_author_with,
 			check_for_equivocation,
+			equivocation_guard,
 			telemetry,
 			phantom: PhantomData,
 		}
@@ -229,6 +244,7 @@ where
 			hash,
 			&authorities[..],
 			self.check_for_equivocation,
+			self.equivocation_guard.as_ref(),
 		)
 		.map_err(|e| e.to_string())?;
 		match checked_header {
@@ -355,6 +371,8 @@ pub struct ImportQueueParams<'a, Block, I, C, S, CAW, CIDP> {
 	pub can_author_with: CAW,
 	/// Should we check for equivocation?
 	pub check_for_equivocation: CheckForEquivocation,
+	/// Alerts when headers signed with our keys equivocate.
+	pub equivocation_guard: Option<EquivocationGuard>,
 	/// Telemetry instance used to report telemetry metrics.
 	pub telemetry: Option<TelemetryHandle>,
 }
@@ -370,6 +388,7 @@ pub fn import_queue<P, Block, I, C, S, CAW, CIDP>(
 		registry,
 		can_author_with,
 		check_for_equivocation,
+		equivocation_guard,
 		telemetry,
 	}: ImportQueueParams<Block, I, C, S, CAW, CIDP>,
 ) -> Result<DefaultImportQueue<Block, C>, sp_consensus::Error>
@@ -401,6 +420,7 @@ where
 		create_inherent_data_providers,
 		can_author_with,
 		check_for_equivocation,
+		equivocation_guard,
 		telemetry,
 	});
 
@@ -417,6 +437,8 @@ pub struct BuildVerifierParams<C, CIDP, CAW> {
 	pub can_author_with: CAW,
 	/// Should we check for equivocation?
 	pub check_for_equivocation: CheckForEquivocation,
+	/// Alerts when headers signed with our keys equivocate.
+	pub equivocation_guard: Option<EquivocationGuard>,
 	/// Telemetry instance used to report telemetry metrics.
 	pub telemetry: Option<TelemetryHandle>,
 }
@@ -428,6 +450,7 @@ pub fn build_verifier<P, C, CIDP, CAW>(
 		create_inherent_data_providers,
 		can_author_with,
 		check_for_equivocation,
+		equivocation_guard,
 		telemetry,
 	}: BuildVerifierParams<C, CIDP, CAW>,
 ) -> AuraVerifier<C, P, CAW, CIDP> {
@@ -436,6 +459,7 @@ pub fn build_verifier<P, C, CIDP, CAW>(
 		create_inherent_data_providers,
 		can_author_with,
 		check_for_equivocation,
+		equivocation_guard,
 		telemetry,
 	)
 }
@@ -38,10 +38,12 @@ use log::{debug, trace};
 use codec::{Codec, Decode, Encode};
 
 use sc_client_api::{backend::AuxStore, BlockOf, UsageProvider};
-use sc_consensus::{BlockImport, BlockImportParams, ForkChoiceStrategy, StateAction};
+use sc_consensus::{
+	BlockImport, BlockImportParams, EquivocationGuard, ForkChoiceStrategy, StateAction,
+};
 use sc_consensus_slots::{
-	BackoffAuthoringBlocksStrategy, InherentDataProviderExt, SimpleSlotWorkerToSlotWorker,
-	SlotInfo, StorageChanges,
+	check_authored_equivocation, BackoffAuthoringBlocksStrategy, InherentDataProviderExt,
+	SimpleSlotWorkerToSlotWorker, SlotInfo, StorageChanges,
 };
 use sc_telemetry::TelemetryHandle;
 use sp_api::ProvideRuntimeApi;
@@ -61,12 +63,14 @@ use sp_runtime::{
 };
 
 mod import_queue;
+mod metrics;
 
 pub use import_queue::{
 	build_verifier, import_queue, AuraVerifier, BuildVerifierParams, CheckForEquivocation,
 	ImportQueueParams,
 };
-pub use sc_consensus_slots::SlotProportion;
+pub use metrics::AuthoringKeyMetrics;
+pub use sc_consensus_slots::{ShadowAuthoring, SlotProportion};
 use sp_authority_permission::PermissionResolver;
 pub use sp_consensus::SyncOracle;
 pub use sp_consensus_aura::{
@@ -145,6 +149,15 @@ pub struct StartAuraParams<C, SC, I, PF, SO, L, CIDP, BS, CAW> {
 	/// The maximum proportion of the slot dedicated to proposing with any lenience factor applied
 	/// due to no blocks being produced.
 	pub max_block_proposal_slot_portion: Option<SlotProportion>,
+	/// Build, but neither seal nor import, blocks in slots we have no permission to author in.
+	///
+	/// See [`ShadowAuthoring`].
+	pub shadow_authoring: Option<ShadowAuthoring>,
+	/// Alerts when blocks authored with our keys equivocate and optionally stops signing with
+	/// them.
+	pub equivocation_guard: Option<EquivocationGuard>,
+	/// Metrics of the slots claimed and blocks authored with each of our keys.
+	pub authoring_metrics: Option<AuthoringKeyMetrics>,
 	/// Telemetry instance used to report telemetry metrics.
 	pub telemetry: Option<TelemetryHandle>,
 }
@@ -167,6 +180,9 @@ pub fn start_aura<P, B, C, SC, I, PF, SO, L, CIDP, BS, CAW, Error>(
 		permission_resolver,
 		block_proposal_slot_portion,
 		max_block_proposal_slot_portion,
+		shadow_authoring,
+		equivocation_guard,
+		authoring_metrics,
 		telemetry,
 	}: StartAuraParams<C, SC, I, PF, SO, L, CIDP, BS, CAW>,
 ) -> Result<impl Future<Output = ()>, sp_consensus::Error>
@@ -201,6 +217,10 @@ where
 		telemetry,
 		block_proposal_slot_portion,
 		max_block_proposal_slot_portion,
+		shadow_authoring,
+		equivocation_guard,
+		permission_resolver,
+		authoring_metrics,
 	});
 
 	Ok(sc_consensus_slots::start_slot_worker(
@@ -210,7 +230,6 @@ where
 		sync_oracle,
 		create_inherent_data_providers,
 		can_author_with,
-		permission_resolver,
 	))
 }
 
@@ -241,6 +260,17 @@ pub struct BuildAuraWorkerParams<C, I, PF, SO, L, BS> {
 	/// The maximum proportion of the slot dedicated to proposing with any lenience factor applied
 	/// due to no blocks being produced.
 	pub max_block_proposal_slot_portion: Option<SlotProportion>,
+	/// Build, but neither seal nor import, blocks in slots we have no permission to author in.
+	///
+	/// See [`ShadowAuthoring`].
+	pub shadow_authoring: Option<ShadowAuthoring>,
+	/// Alerts when blocks authored with our keys equivocate and optionally stops signing with
+	/// them.
+	pub equivocation_guard: Option<EquivocationGuard>,
+	/// Decides whether we may author with each of our keys, in the slots the key is claimed in.
+	pub permission_resolver: Arc<dyn PermissionResolver>,
+	/// Metrics of the slots claimed and blocks authored with each of our keys.
+	pub authoring_metrics: Option<AuthoringKeyMetrics>,
 	/// Telemetry instance used to report telemetry metrics.
 	pub telemetry: Option<TelemetryHandle>,
 }
@@ -261,6 +291,10 @@ pub fn build_aura_worker<P, B, C, PF, I, SO, L, BS, Error>(
 		max_block_proposal_slot_portion,
 		telemetry,
 		force_authoring,
+		shadow_authoring,
+		equivocation_guard,
+		permission_resolver,
+		authoring_metrics,
 	}: BuildAuraWorkerParams<C, I, PF, SO, L, BS>,
 ) -> impl sc_consensus_slots::SimpleSlotWorker<
 	B,
@@ -298,6 +332,10 @@ where
 		telemetry,
 		block_proposal_slot_portion,
 		max_block_proposal_slot_portion,
+		shadow_authoring,
+		equivocation_guard,
+		permission_resolver,
+		authoring_metrics,
 		_key_type: PhantomData::<P>,
 	}
 }
@@ -313,6 +351,10 @@ struct AuraWorker<C, E, I, P, SO, L, BS> {
 	backoff_authoring_blocks: Option<BS>,
 	block_proposal_slot_portion: SlotProportion,
 	max_block_proposal_slot_portion: Option<SlotProportion>,
+	shadow_authoring: Option<ShadowAuthoring>,
+	equivocation_guard: Option<EquivocationGuard>,
+	permission_resolver: Arc<dyn PermissionResolver>,
+	authoring_metrics: Option<AuthoringKeyMetrics>,
 	telemetry: Option<TelemetryHandle>,
 	_key_type: PhantomData<P>,
 }
@@ -322,7 +364,7 @@ impl<B, C, E, I, P, Error, SO, L, BS> sc_consensus_slots::SimpleSlotWorker<B>
 	for AuraWorker<C, E, I, P, SO, L, BS>
 where
 	B: BlockT,
-	C: ProvideRuntimeApi<B> + BlockOf + HeaderBackend<B> + Sync,
+	C: ProvideRuntimeApi<B> + BlockOf + AuxStore + HeaderBackend<B> + Sync,
 	C::Api: AuraApi<B, AuthorityId<P>>,
 	E: Environment<B, Error = Error> + Send + Sync,
 	E::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
@@ -372,10 +414,20 @@ where
 	) -> Option<Self::Claim> {
 		let expected_author = slot_author::<P>(slot, epoch_data);
 		expected_author.and_then(|p| {
+			if self
+				.equivocation_guard
+				.as_ref()
+				.map_or(false, |guard| guard.is_tripped(&p.encode()))
+			{
+				debug!(target: "aura", "Not claiming slot {} with key {:?} that equivocated", slot, p);
+				return None
+			}
+
 			if SyncCryptoStore::has_keys(
 				&*self.keystore,
 				&[(p.to_raw_vec(), sp_application_crypto::key_types::AURA)],
 			) {
+				debug!(target: "aura", "Claimed slot {} with key {:?}", slot, p);
 				Some(p.clone())
 			} else {
 				None
@@ -383,6 +435,24 @@ where
 		})
 	}
 
+	async fn is_claim_permitted(&self, slot: Slot, claim: &Self::Claim) -> bool {
+		let key = claim.to_raw_vec();
+		let permitted = self.permission_resolver.resolve_slot_for_key(slot, &key).await;
+		// Claims are only counted once the permission is known, so that slots handed to shadow
+		// authoring aren't reported as claimed.
+		if let Some(metrics) = &self.authoring_metrics {
+			if permitted {
+				metrics.report_claimed(&key);
+			} else {
+				metrics.report_denied(&key);
+			}
+		}
+		if !permitted {
+			debug!(target: "aura", "No permission to author slot {} with key {:?}", slot, claim);
+		}
+		permitted
+	}
+
 	fn pre_digest_data(&self, slot: Slot, _claim: &Self::Claim) -> Vec<sp_runtime::DigestItem> {
 		vec![<DigestItem as CompatibleDigestItem<P::Signature>>::aura_pre_digest(slot)]
 	}
@@ -399,6 +469,20 @@ where
 		sc_consensus::BlockImportParams<B, <Self::BlockImport as BlockImport<B>>::Transaction>,
 		sp_consensus::Error,
 	> {
+		if let Some(guard) = &self.equivocation_guard {
+			let slot = find_pre_digest::<B, P::Signature>(&header)
+				.map_err(|e| sp_consensus::Error::ClientImport(e.to_string()))?;
+			check_authored_equivocation(
+				guard,
+				"aura",
+				sp_application_crypto::key_types::AURA,
+				&*self.client,
+				slot,
+				&header,
+				&public,
+			)?;
+		}
+
 		// sign the pre-sealed hash of the block and then
 		// add it to a digest item.
 		let public_type_pair = public.to_public_crypto_pair();
@@ -416,6 +500,9 @@ where
 				"Could not find key in keystore.".into(),
 			)
 		})?;
+		if let Some(metrics) = &self.authoring_metrics {
+			metrics.report_authored(&public);
+		}
 		let signature = signature
 			.clone()
 			.try_into()
@@ -438,6 +525,10 @@ where
 		self.force_authoring
 	}
 
+	fn shadow_authoring(&self) -> Option<ShadowAuthoring> {
+		self.shadow_authoring.clone()
+	}
+
 	fn should_backoff(&self, slot: Slot, chain_head: &B::Header) -> bool {
 		if let Some(ref strategy) = self.backoff_authoring_blocks {
 			if let Ok(chain_head_slot) = find_pre_digest::<B, P::Signature>(chain_head) {
@@ -573,7 +664,7 @@ mod tests {
 	use sc_keystore::LocalKeystore;
 	use sc_network_test::{Block as TestBlock, *};
 	use sp_application_crypto::key_types::AURA;
-	use sp_authority_permission::AlwaysPermissionGranted;
+	use sp_authority_permission::{AlwaysPermissionGranted, NeverPermissionGranted};
 	use sp_consensus::{
 		AlwaysCanAuthor, DisableProofRecording, NoNetwork as DummyOracle, Proposal,
 	};
@@ -679,6 +770,7 @@ mod tests {
 				AlwaysCanAuthor,
 				CheckForEquivocation::Yes,
 				None,
+				None,
 			)
 		}
 
@@ -770,6 +862,9 @@ mod tests {
 					permission_resolver: Arc::new(AlwaysPermissionGranted {}),
 					block_proposal_slot_portion: SlotProportion::new(0.5),
 					max_block_proposal_slot_portion: None,
+					shadow_authoring: None,
+					equivocation_guard: None,
+					authoring_metrics: None,
 					telemetry: None,
 				})
 				.expect("Starts aura"),
@@ -836,6 +931,10 @@ mod tests {
 			_key_type: PhantomData::<AuthorityPair>,
 			block_proposal_slot_portion: SlotProportion::new(0.5),
 			max_block_proposal_slot_portion: None,
+			shadow_authoring: None,
+			equivocation_guard: None,
+			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			authoring_metrics: None,
 		};
 
 		let head = Header::new(
@@ -855,6 +954,107 @@ mod tests {
 		assert!(executor::block_on(worker.claim_slot(&head, 7.into(), &authorities)).is_some());
 	}
 
+	/// Grants permission to author with a single key.
+	struct SingleKeyPermission(Vec<u8>);
+
+	#[async_trait::async_trait]
+	impl PermissionResolver for SingleKeyPermission {
+		async fn resolve_slot(&self, _: Slot) -> bool {
+			true
+		}
+
+		async fn resolve_round(&self, _: u64) -> bool {
+			true
+		}
+
+		async fn resolve_session(&self, _: u32) -> bool {
+			true
+		}
+
+		async fn resolve_slot_for_key(&self, _: Slot, key: &[u8]) -> bool {
+			key == &self.0[..]
+		}
+	}
+
+	#[test]
+	fn claims_slots_of_every_local_key_with_their_own_permission() {
+		let net = AuraTestNet::new(4);
+
+		let authorities: Vec<AuthorityId<AuthorityPair>> = vec![
+			Keyring::Alice.public().into(),
+			Keyring::Bob.public().into(),
+			Keyring::Charlie.public().into(),
+		];
+
+		let keystore_path = tempfile::tempdir().expect("Creates keystore path");
+		let keystore = LocalKeystore::open(keystore_path.path(), None).expect("Creates keystore.");
+		for key in [Keyring::Alice, Keyring::Bob] {
+			SyncCryptoStore::sr25519_generate_new(
+				&keystore,
+				AuthorityPair::ID,
+				Some(&key.to_seed()),
+			)
+			.expect("Key should be created");
+		}
+
+		let net = Arc::new(Mutex::new(net));
+
+		let mut net = net.lock();
+		let peer = net.peer(3);
+		let client = peer.client().as_client();
+		let environ = DummyFactory(client.clone());
+		let registry = prometheus_endpoint::Registry::new();
+
+		let worker = AuraWorker {
+			client: client.clone(),
+			block_import: client,
+			env: environ,
+			keystore: keystore.into(),
+			sync_oracle: DummyOracle,
+			justification_sync_link: (),
+			force_authoring: false,
+			backoff_authoring_blocks: Option::<()>::None,
+			telemetry: None,
+			_key_type: PhantomData::<AuthorityPair>,
+			block_proposal_slot_portion: SlotProportion::new(0.5),
+			max_block_proposal_slot_portion: None,
+			shadow_authoring: None,
+			equivocation_guard: None,
+			permission_resolver: Arc::new(SingleKeyPermission(Keyring::Bob.public().to_raw_vec())),
+			authoring_metrics: Some(AuthoringKeyMetrics::register(&registry).unwrap()),
+		};
+
+		let head = Header::new(
+			1,
+			H256::from_low_u64_be(0),
+			H256::from_low_u64_be(0),
+			Default::default(),
+			Default::default(),
+		);
+		let claim =
+			|slot: u64| executor::block_on(worker.claim_slot(&head, slot.into(), &authorities));
+
+		let alice = claim(0).expect("Alice's slot is claimed");
+		let bob = claim(1).expect("Bob's slot is claimed");
+		assert!(claim(2).is_none());
+		assert_eq!(alice, Keyring::Alice.public().into());
+		assert_eq!(bob, Keyring::Bob.public().into());
+
+		assert!(!executor::block_on(worker.is_claim_permitted(0.into(), &alice)));
+		assert!(executor::block_on(worker.is_claim_permitted(1.into(), &bob)));
+
+		let labeled_series = |name: &str| {
+			let family = registry
+				.gather()
+				.into_iter()
+				.find(|family| family.get_name() == name)
+				.expect("Metric is registered");
+			family.get_metric().len()
+		};
+		assert_eq!(labeled_series("substrate_aura_slots_claimed_total"), 1);
+		assert_eq!(labeled_series("substrate_aura_slots_denied_total"), 1);
+	}
+
 	#[test]
 	fn on_slot_returns_correct_block() {
 		let net = AuraTestNet::new(4);
@@ -888,6 +1088,10 @@ mod tests {
 			_key_type: PhantomData::<AuthorityPair>,
 			block_proposal_slot_portion: SlotProportion::new(0.5),
 			max_block_proposal_slot_portion: None,
+			shadow_authoring: None,
+			equivocation_guard: None,
+			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			authoring_metrics: None,
 		};
 
 		let head = client.header(&BlockId::Number(0)).unwrap().unwrap();
@@ -906,4 +1110,68 @@ mod tests {
 		// The returned block should be imported and we should be able to get its header by now.
 		assert!(client.header(&BlockId::Hash(res.block.hash())).unwrap().is_some());
 	}
+
+	#[test]
+	fn on_slot_without_permission_builds_shadow_block() {
+		let net = AuraTestNet::new(4);
+
+		let keystore_path = tempfile::tempdir().expect("Creates keystore path");
+		let keystore = LocalKeystore::open(keystore_path.path(), None).expect("Creates keystore.");
+		SyncCryptoStore::sr25519_generate_new(
+			&keystore,
+			AuthorityPair::ID,
+			Some(&Keyring::Alice.to_seed()),
+		)
+		.expect("Key should be created");
+
+		let net = Arc::new(Mutex::new(net));
+
+		let mut net = net.lock();
+		let peer = net.peer(3);
+		let client = peer.client().as_client();
+		let environ = DummyFactory(client.clone());
+		let registry = prometheus_endpoint::Registry::new();
+
+		let mut worker = AuraWorker {
+			client: client.clone(),
+			block_import: client.clone(),
+			env: environ,
+			keystore: keystore.into(),
+			sync_oracle: DummyOracle,
+			justification_sync_link: (),
+			force_authoring: false,
+			backoff_authoring_blocks: Option::<()>::None,
+			telemetry: None,
+			_key_type: PhantomData::<AuthorityPair>,
+			block_proposal_slot_portion: SlotProportion::new(0.5),
+			max_block_proposal_slot_portion: None,
+			shadow_authoring: Some(ShadowAuthoring::new(Some(&registry)).unwrap()),
+			equivocation_guard: None,
+			permission_resolver: Arc::new(NeverPermissionGranted {}),
+			authoring_metrics: None,
+		};
+
+		let head = client.header(&BlockId::Number(0)).unwrap().unwrap();
+
+		let res = executor::block_on(worker.on_slot(SlotInfo {
+			slot: 0.into(),
+			timestamp: 0.into(),
+			ends_at: Instant::now() + Duration::from_secs(100),
+			inherent_data: InherentData::new(),
+			duration: Duration::from_millis(1000),
+			chain_head: head,
+			block_size_limit: None,
+		}));
+
+		// The block was built, but not imported.
+		assert!(res.is_none());
+		assert_eq!(client.info().best_number, 0);
+		let proposals = registry
+			.gather()
+			.into_iter()
+			.find(|family| family.get_name() == "substrate_shadow_authoring_proposals_total")
+			.expect("Shadow block is reported");
+		assert_eq!(proposals.get_metric()[0].get_label()[0].get_value(), "in_time");
+		assert_eq!(proposals.get_metric()[0].get_counter().get_value(), 1.0);
+	}
 }
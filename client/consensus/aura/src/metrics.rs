@@ -0,0 +1,87 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per authority key metrics of the Aura worker.
+//!
+//! A node may author for several Aura authority keys of its keystore, so slot claims, permission
+//! denials and authored blocks are counted per key.
+
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use sp_core::hexdisplay::HexDisplay;
+
+/// Authoring metrics labeled by the hex encoded raw public key they relate to.
+#[derive(Clone)]
+pub struct AuthoringKeyMetrics {
+	slots_claimed: CounterVec<U64>,
+	slots_denied: CounterVec<U64>,
+	blocks_authored: CounterVec<U64>,
+}
+
+impl AuthoringKeyMetrics {
+	/// Register the metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			slots_claimed: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_aura_slots_claimed_total",
+						"Slots claimed with authoring permission per authority key",
+					),
+					&["key"],
+				)?,
+				registry,
+			)?,
+			slots_denied: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_aura_slots_denied_total",
+						"Claimed slots the permission resolver denied per authority key",
+					),
+					&["key"],
+				)?,
+				registry,
+			)?,
+			blocks_authored: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_aura_blocks_authored_total",
+						"Blocks sealed per authority key",
+					),
+					&["key"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	pub(crate) fn report_claimed(&self, key: &[u8]) {
+		self.slots_claimed.with_label_values(&[&label(key)]).inc();
+	}
+
+	pub(crate) fn report_denied(&self, key: &[u8]) {
+		self.slots_denied.with_label_values(&[&label(key)]).inc();
+	}
+
+	pub(crate) fn report_authored(&self, key: &[u8]) {
+		self.blocks_authored.with_label_values(&[&label(key)]).inc();
+	}
+}
+
+fn label(key: &[u8]) -> String {
+	format!("0x{}", HexDisplay::from(&key))
+}
@@ -39,7 +39,11 @@ use sp_consensus_babe::{
 use sp_core::crypto::ByteArray;
 use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
 use sp_runtime::traits::{Block as BlockT, Header as _};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::HashMap,
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
 
 /// Provides rpc methods for interacting with Babe.
 #[rpc(client, server)]
@@ -48,6 +52,12 @@ pub trait BabeApi {
 	/// with the keys in the keystore.
 	#[method(name = "babe_epochAuthorship")]
 	async fn epoch_authorship(&self) -> RpcResult<HashMap<AuthorityId, EpochAuthorship>>;
+
+	/// Returns the primary and secondary slots that can be claimed in the current and the next
+	/// epoch with the keys in the keystore, along with the longest upcoming stretch of slots
+	/// without any such claim.
+	#[method(name = "babe_authorshipForecast")]
+	async fn authorship_forecast(&self) -> RpcResult<AuthorshipForecast>;
 }
 
 /// Provides RPC methods for interacting with Babe.
@@ -108,47 +118,118 @@ where
 			&self.select_chain,
 		)
 		.await?;
-		let (epoch_start, epoch_end) = (epoch.start_slot(), epoch.end_slot());
 		let mut claims: HashMap<AuthorityId, EpochAuthorship> = HashMap::new();
 
-		let keys = {
-			epoch
-				.authorities
-				.iter()
-				.enumerate()
-				.filter_map(|(i, a)| {
-					if SyncCryptoStore::has_keys(
-						&*self.keystore,
-						&[(a.0.to_raw_vec(), AuthorityId::ID)],
-					) {
-						Some((a.0.clone(), i))
-					} else {
-						None
-					}
-				})
-				.collect::<Vec<_>>()
-		};
-
-		for slot in *epoch_start..*epoch_end {
-			if let Some((claim, key)) =
-				authorship::claim_slot_using_keys(slot.into(), &epoch, &self.keystore, &keys)
-			{
-				match claim {
-					PreDigest::Primary { .. } => {
-						claims.entry(key).or_default().primary.push(slot);
-					},
-					PreDigest::SecondaryPlain { .. } => {
-						claims.entry(key).or_default().secondary.push(slot);
-					},
-					PreDigest::SecondaryVRF { .. } => {
-						claims.entry(key).or_default().secondary_vrf.push(slot.into());
-					},
-				};
-			}
+		for (slot, claim, key) in slot_claims(&epoch, &self.keystore) {
+			match claim {
+				PreDigest::Primary { .. } => {
+					claims.entry(key).or_default().primary.push(slot);
+				},
+				PreDigest::SecondaryPlain { .. } => {
+					claims.entry(key).or_default().secondary.push(slot);
+				},
+				PreDigest::SecondaryVRF { .. } => {
+					claims.entry(key).or_default().secondary_vrf.push(slot.into());
+				},
+			};
 		}
 
 		Ok(claims)
 	}
+
+	async fn authorship_forecast(&self) -> RpcResult<AuthorshipForecast> {
+		self.deny_unsafe.check_if_safe()?;
+		let header = self.select_chain.best_chain().map_err(Error::Consensus).await?;
+		let epoch_start = self
+			.client
+			.runtime_api()
+			.current_epoch_start(&BlockId::Hash(header.hash()))
+			.map_err(|err| Error::StringError(format!("{:?}", err)))?;
+
+		let current_epoch = epoch_data(
+			&self.shared_epoch_changes,
+			&self.client,
+			&self.babe_config,
+			*epoch_start,
+			&self.select_chain,
+		)
+		.await?;
+		// the next epoch is only known once it has been announced in the current epoch.
+		let next_epoch = epoch_data(
+			&self.shared_epoch_changes,
+			&self.client,
+			&self.babe_config,
+			*current_epoch.end_slot(),
+			&self.select_chain,
+		)
+		.await
+		.ok()
+		.filter(|epoch| epoch.epoch_index == current_epoch.epoch_index + 1);
+
+		let slot_duration = self.babe_config.slot_duration;
+		let current_epoch = EpochForecast::new(&current_epoch, &self.keystore, slot_duration);
+		let next_epoch =
+			next_epoch.map(|epoch| EpochForecast::new(&epoch, &self.keystore, slot_duration));
+
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_err(|err| Error::StringError(err.to_string()))?;
+		let current_slot = now.as_millis() as u64 / slot_duration;
+		let forecast_end = next_epoch.as_ref().unwrap_or(&current_epoch).end_slot;
+		let upcoming_claims = current_epoch
+			.claims
+			.iter()
+			.chain(next_epoch.iter().flat_map(|epoch| epoch.claims.iter()))
+			.map(|claim| claim.slot);
+		let longest_gap = longest_gap(current_slot, forecast_end, upcoming_claims)
+			.map(|(start_slot, end_slot)| SlotGap::new(start_slot, end_slot, slot_duration));
+
+		Ok(AuthorshipForecast { current_epoch, next_epoch, longest_gap })
+	}
+}
+
+/// Returns the slots of `epoch` that can be claimed with the keys in the keystore, along with the
+/// claim and the claiming key.
+fn slot_claims(epoch: &Epoch, keystore: &SyncCryptoStorePtr) -> Vec<(u64, PreDigest, AuthorityId)> {
+	let keys = epoch
+		.authorities
+		.iter()
+		.enumerate()
+		.filter_map(|(i, a)| {
+			if SyncCryptoStore::has_keys(&**keystore, &[(a.0.to_raw_vec(), AuthorityId::ID)]) {
+				Some((a.0.clone(), i))
+			} else {
+				None
+			}
+		})
+		.collect::<Vec<_>>();
+
+	(*epoch.start_slot()..*epoch.end_slot())
+		.filter_map(|slot| {
+			authorship::claim_slot_using_keys(slot.into(), epoch, keystore, &keys)
+				.map(|(claim, key)| (slot, claim, key))
+		})
+		.collect()
+}
+
+/// Returns the longest stretch of slots in `[from, to)` without any of the (sorted) `claims`, as
+/// a range of slots.
+fn longest_gap(from: u64, to: u64, claims: impl IntoIterator<Item = u64>) -> Option<(u64, u64)> {
+	let mut longest: Option<(u64, u64)> = None;
+	let mut gap_start = from;
+
+	for slot in claims.into_iter().chain(std::iter::once(to)) {
+		if slot < gap_start {
+			continue
+		}
+		let slot = slot.min(to);
+		if slot > gap_start && longest.map_or(true, |(start, end)| slot - gap_start > end - start) {
+			longest = Some((gap_start, slot));
+		}
+		gap_start = slot + 1;
+	}
+
+	longest
 }
 
 /// Holds information about the `slot`'s that can be claimed by a given key.
@@ -162,6 +243,110 @@ pub struct EpochAuthorship {
 	secondary_vrf: Vec<u64>,
 }
 
+/// Holds the slots that can be claimed with the keys in the keystore in the current and the next
+/// epoch.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorshipForecast {
+	/// The slots that can be claimed in the current epoch.
+	current_epoch: EpochForecast,
+	/// The slots that can be claimed in the next epoch, if it has been announced already.
+	next_epoch: Option<EpochForecast>,
+	/// The longest stretch of upcoming slots, until the end of the last known epoch, that can't
+	/// be claimed with the keys in the keystore.
+	longest_gap: Option<SlotGap>,
+}
+
+/// Holds the slots of an epoch that can be claimed with the keys in the keystore.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochForecast {
+	/// The epoch index.
+	epoch_index: u64,
+	/// The first slot of the epoch.
+	start_slot: u64,
+	/// The first slot after the epoch.
+	end_slot: u64,
+	/// The slots that can be claimed, in ascending order.
+	claims: Vec<SlotClaim>,
+}
+
+impl EpochForecast {
+	fn new(epoch: &Epoch, keystore: &SyncCryptoStorePtr, slot_duration: u64) -> Self {
+		let claims = slot_claims(epoch, keystore)
+			.into_iter()
+			.map(|(slot, claim, authority)| SlotClaim {
+				slot,
+				timestamp: slot * slot_duration,
+				kind: match claim {
+					PreDigest::Primary { .. } => ClaimKind::Primary,
+					PreDigest::SecondaryPlain { .. } => ClaimKind::Secondary,
+					PreDigest::SecondaryVRF { .. } => ClaimKind::SecondaryVrf,
+				},
+				authority,
+			})
+			.collect();
+
+		EpochForecast {
+			epoch_index: epoch.epoch_index,
+			start_slot: *epoch.start_slot(),
+			end_slot: *epoch.end_slot(),
+			claims,
+		}
+	}
+}
+
+/// A slot that can be claimed with a key in the keystore.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotClaim {
+	/// The slot number.
+	slot: u64,
+	/// The start of the slot, in milliseconds since the UNIX epoch.
+	timestamp: u64,
+	/// The kind of claim.
+	kind: ClaimKind,
+	/// The key the slot is claimed with.
+	authority: AuthorityId,
+}
+
+/// The kind of a slot claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClaimKind {
+	/// A primary slot claim.
+	Primary,
+	/// A secondary slot claim, without VRF output.
+	Secondary,
+	/// A secondary slot claim, with VRF output.
+	SecondaryVrf,
+}
+
+/// A stretch of slots that can't be claimed with the keys in the keystore.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotGap {
+	/// The first slot of the gap.
+	start_slot: u64,
+	/// The first slot after the gap.
+	end_slot: u64,
+	/// The start of the gap, in milliseconds since the UNIX epoch.
+	start_timestamp: u64,
+	/// The end of the gap, in milliseconds since the UNIX epoch.
+	end_timestamp: u64,
+}
+
+impl SlotGap {
+	fn new(start_slot: u64, end_slot: u64, slot_duration: u64) -> Self {
+		SlotGap {
+			start_slot,
+			end_slot,
+			start_timestamp: start_slot * slot_duration,
+			end_timestamp: end_slot * slot_duration,
+		}
+	}
+}
+
 /// Errors encountered by the RPC
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -213,6 +398,7 @@ where
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use jsonrpsee::types::EmptyParams;
 	use sc_keystore::LocalKeystore;
 	use sp_application_crypto::AppPair;
 	use sp_core::crypto::key_types::BABE;
@@ -267,6 +453,35 @@ mod tests {
 		assert_eq!(&response.result, expected);
 	}
 
+	#[tokio::test]
+	async fn authorship_forecast_works() {
+		let babe_rpc = test_babe_rpc_module(DenyUnsafe::No);
+		let api = babe_rpc.into_rpc();
+
+		let forecast: serde_json::Value =
+			api.call("babe_authorshipForecast", EmptyParams::new()).await.unwrap();
+		let claims = forecast["currentEpoch"]["claims"].as_array().unwrap();
+		let slots: Vec<_> = claims.iter().map(|claim| claim["slot"].as_u64().unwrap()).collect();
+		let kinds: Vec<_> = claims.iter().map(|claim| claim["kind"].as_str().unwrap()).collect();
+
+		assert_eq!(slots, vec![0, 1, 2, 4]);
+		assert_eq!(kinds, vec!["primary", "secondary", "secondary", "secondary"]);
+		// the next epoch hasn't been announced at genesis.
+		assert!(forecast["nextEpoch"].is_null());
+		// the genesis epoch is long over.
+		assert!(forecast["longestGap"].is_null());
+	}
+
+	#[test]
+	fn longest_gap_works() {
+		assert_eq!(longest_gap(10, 20, vec![]), Some((10, 20)));
+		assert_eq!(longest_gap(10, 20, vec![12, 17]), Some((13, 17)));
+		assert_eq!(longest_gap(10, 20, vec![5, 14, 19]), Some((10, 14)));
+		assert_eq!(longest_gap(10, 20, vec![10, 15]), Some((11, 15)));
+		assert_eq!(longest_gap(10, 12, vec![10, 11]), None);
+		assert_eq!(longest_gap(20, 20, vec![]), None);
+	}
+
 	#[tokio::test]
 	async fn epoch_authorship_is_unsafe() {
 		let babe_rpc = test_babe_rpc_module(DenyUnsafe::Yes);
@@ -99,13 +99,15 @@ use sc_consensus::{
 		StateAction,
 	},
 	import_queue::{BasicQueue, BoxJustificationImport, DefaultImportQueue, Verifier},
+	EquivocationGuard,
 };
 use sc_consensus_epochs::{
 	descendent_query, Epoch as EpochT, EpochChangesFor, SharedEpochChanges, ViableEpochDescriptor,
 };
 use sc_consensus_slots::{
-	check_equivocation, BackoffAuthoringBlocksStrategy, CheckedHeader, InherentDataProviderExt,
-	SlotInfo, StorageChanges,
+	check_authored_equivocation, check_equivocation, report_self_equivocation,
+	BackoffAuthoringBlocksStrategy, CheckedHeader, InherentDataProviderExt, SlotInfo,
+	StorageChanges,
 };
 use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_DEBUG, CONSENSUS_TRACE};
 use sp_api::{ApiExt, ProvideRuntimeApi};
@@ -129,7 +131,7 @@ use sp_runtime::{
 	DigestItem,
 };
 
-pub use sc_consensus_slots::SlotProportion;
+pub use sc_consensus_slots::{ShadowAuthoring, SlotProportion};
 pub use sp_consensus::SyncOracle;
 pub use sp_consensus_babe::{
 	digests::{
@@ -424,6 +426,15 @@ pub struct BabeParams<B: BlockT, C, SC, E, I, SO, L, CIDP, BS, CAW> {
 	/// due to no blocks being produced.
 	pub max_block_proposal_slot_portion: Option<SlotProportion>,
 
+	/// Build, but neither seal nor import, blocks in slots we have no permission to author in.
+	///
+	/// See [`ShadowAuthoring`].
+	pub shadow_authoring: Option<ShadowAuthoring>,
+
+	/// Alerts when blocks authored with our keys equivocate and optionally stops signing with
+	/// them.
+	pub equivocation_guard: Option<EquivocationGuard>,
+
 	/// Handle use to report telemetries.
 	pub telemetry: Option<TelemetryHandle>,
 }
@@ -446,6 +457,8 @@ pub fn start_babe<B, C, SC, E, I, SO, CIDP, BS, CAW, L, Error>(
 		permission_resolver,
 		block_proposal_slot_portion,
 		max_block_proposal_slot_portion,
+		shadow_authoring,
+		equivocation_guard,
 		telemetry,
 	}: BabeParams<B, C, SC, E, I, SO, L, CIDP, BS, CAW>,
 ) -> Result<BabeWorker<B>, sp_consensus::Error>
@@ -457,6 +470,7 @@ where
 		+ PreCommitActions<B>
 		+ HeaderBackend<B>
 		+ HeaderMetadata<B, Error = ClientError>
+		+ AuxStore
 		+ Send
 		+ Sync
 		+ 'static,
@@ -494,6 +508,9 @@ where
 		config: babe_link.config.clone(),
 		block_proposal_slot_portion,
 		max_block_proposal_slot_portion,
+		shadow_authoring,
+		equivocation_guard,
+		permission_resolver,
 		telemetry,
 	};
 
@@ -506,7 +523,6 @@ where
 		sync_oracle,
 		create_inherent_data_providers,
 		can_author_with,
-		permission_resolver,
 	);
 
 	let (worker_tx, worker_rx) = channel(HANDLE_BUFFER_SIZE);
@@ -725,6 +741,9 @@ struct BabeSlotWorker<B: BlockT, C, E, I, SO, L, BS> {
 	config: BabeConfiguration,
 	block_proposal_slot_portion: SlotProportion,
 	max_block_proposal_slot_portion: Option<SlotProportion>,
+	shadow_authoring: Option<ShadowAuthoring>,
+	equivocation_guard: Option<EquivocationGuard>,
+	permission_resolver: Arc<dyn PermissionResolver>,
 	telemetry: Option<TelemetryHandle>,
 }
 
@@ -733,7 +752,7 @@ impl<B, C, E, I, Error, SO, L, BS> sc_consensus_slots::SimpleSlotWorker<B>
 	for BabeSlotWorker<B, C, E, I, SO, L, BS>
 where
 	B: BlockT,
-	C: ProvideRuntimeApi<B> + HeaderBackend<B> + HeaderMetadata<B, Error = ClientError>,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + HeaderMetadata<B, Error = ClientError> + AuxStore,
 	C::Api: BabeApi<B>,
 	E: Environment<B, Error = Error> + Sync,
 	E::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
@@ -800,6 +819,13 @@ where
 			&self.keystore,
 		);
 
+		if let (Some((_, public)), Some(guard)) = (&s, &self.equivocation_guard) {
+			if guard.is_tripped(&public.encode()) {
+				debug!(target: "babe", "Not claiming slot {} with key {:?} that equivocated", slot, public);
+				return None
+			}
+		}
+
 		if s.is_some() {
 			debug!(target: "babe", "Claimed slot {}", slot);
 		}
@@ -807,6 +833,15 @@ where
 		s
 	}
 
+	async fn is_claim_permitted(&self, slot: Slot, claim: &Self::Claim) -> bool {
+		let permitted =
+			self.permission_resolver.resolve_slot_for_key(slot, claim.1.as_slice()).await;
+		if !permitted {
+			debug!(target: "babe", "No permission to author slot {} with key {:?}", slot, claim.1);
+		}
+		permitted
+	}
+
 	fn notify_slot(
 		&self,
 		_parent_header: &B::Header,
@@ -836,12 +871,25 @@ where
 		header_hash: &B::Hash,
 		body: Vec<B::Extrinsic>,
 		storage_changes: StorageChanges<<Self::BlockImport as BlockImport<B>>::Transaction, B>,
-		(_, public): Self::Claim,
+		(pre_digest, public): Self::Claim,
 		epoch_descriptor: Self::EpochData,
 	) -> Result<
 		sc_consensus::BlockImportParams<B, <Self::BlockImport as BlockImport<B>>::Transaction>,
 		sp_consensus::Error,
 	> {
+		if let Some(guard) = &self.equivocation_guard {
+			let slot = pre_digest.slot();
+			check_authored_equivocation(
+				guard,
+				"babe",
+				AuthorityId::ID,
+				&*self.client,
+				slot,
+				&header,
+				&public,
+			)?;
+		}
+
 		// sign the pre-sealed hash of the block and then
 		// add it to a digest item.
 		let public_type_pair = public.clone().into();
@@ -882,6 +930,10 @@ where
 		self.force_authoring
 	}
 
+	fn shadow_authoring(&self) -> Option<ShadowAuthoring> {
+		self.shadow_authoring.clone()
+	}
+
 	fn should_backoff(&self, slot: Slot, chain_head: &B::Header) -> bool {
 		if let Some(ref strategy) = self.backoff_authoring_blocks {
 			if let Ok(chain_head_slot) =
@@ -1022,6 +1074,7 @@ pub struct BabeVerifier<Block: BlockT, Client, SelectChain, CAW, CIDP> {
 	config: BabeConfiguration,
 	epoch_changes: SharedEpochChanges<Block, Epoch>,
 	can_author_with: CAW,
+	equivocation_guard: Option<EquivocationGuard>,
 	telemetry: Option<TelemetryHandle>,
 }
 
@@ -1101,6 +1154,13 @@ where
 			equivocation_proof.second_header.hash(),
 		);
 
+		if let Some(guard) = &self.equivocation_guard {
+			if report_self_equivocation(guard, "babe", AuthorityId::ID, &equivocation_proof) {
+				info!(target: "babe", "Refraining from sending equivocation report for our own equivocation.");
+				return Ok(())
+			}
+		}
+
 		// get the best block on which we will build and send the equivocation report.
 		let best_id = self
 			.select_chain
@@ -1779,6 +1839,9 @@ where
 ///
 /// The block import object provided must be the `BabeBlockImport` or a wrapper
 /// of it, otherwise crucial import logic will be omitted.
+///
+/// If an `equivocation_guard` is given, equivocations of keys the node authored blocks with are
+/// reported to it instead of the runtime.
 pub fn import_queue<Block: BlockT, Client, SelectChain, Inner, CAW, CIDP>(
 	babe_link: BabeLink<Block>,
 	block_import: Inner,
@@ -1789,6 +1852,7 @@ pub fn import_queue<Block: BlockT, Client, SelectChain, Inner, CAW, CIDP>(
 	spawner: &impl sp_core::traits::SpawnEssentialNamed,
 	registry: Option<&Registry>,
 	can_author_with: CAW,
+	equivocation_guard: Option<EquivocationGuard>,
 	telemetry: Option<TelemetryHandle>,
 ) -> ClientResult<DefaultImportQueue<Block, Client>>
 where
@@ -1818,6 +1882,7 @@ where
 		config: babe_link.config,
 		epoch_changes: babe_link.epoch_changes,
 		can_author_with,
+		equivocation_guard,
 		telemetry,
 		client,
 	};
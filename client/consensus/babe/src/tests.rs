@@ -334,6 +334,7 @@ impl TestNetFactory for BabeTestNet {
 				config: data.link.config.clone(),
 				epoch_changes: data.link.epoch_changes.clone(),
 				can_author_with: AlwaysCanAuthor,
+				equivocation_guard: None,
 				telemetry: None,
 			},
 			mutator: MUTATOR.with(|m| m.borrow().clone()),
@@ -453,6 +454,8 @@ fn run_one_test(mutator: impl Fn(&mut TestHeader, Stage) + Send + Sync + 'static
 				justification_sync_link: (),
 				block_proposal_slot_portion: SlotProportion::new(0.5),
 				max_block_proposal_slot_portion: None,
+				shadow_authoring: None,
+				equivocation_guard: None,
 				telemetry: None,
 			})
 			.expect("Starts babe"),
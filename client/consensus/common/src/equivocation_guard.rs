@@ -0,0 +1,176 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Detection of equivocations committed with the keys of the local node.
+//!
+//! Equivocations by other authorities are reported to the runtime by the consensus engines. An
+//! equivocation committed with a local key however means that another node, e.g. a misconfigured
+//! replica, is signing with the same key, and the operator must be alerted before the key gets
+//! slashed. The [`EquivocationGuard`] is shared by the consensus engines of a node: they check
+//! with it whether an offender is a key of the node's keystore, report the equivocations of local
+//! keys to it, and check with it whether they can still sign with a key.
+
+use std::{collections::HashSet, sync::Arc};
+
+use log::error;
+use parking_lot::Mutex;
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_WARN};
+use sp_core::{crypto::KeyTypeId, hexdisplay::HexDisplay};
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+
+#[derive(Clone)]
+struct Metrics {
+	self_equivocations: CounterVec<U64>,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			self_equivocations: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_self_equivocations_total",
+						"Equivocations committed with a key of this node",
+					),
+					&["engine"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+struct Inner {
+	circuit_breaker: bool,
+	keystore: SyncCryptoStorePtr,
+	tripped_keys: Mutex<HashSet<Vec<u8>>>,
+	metrics: Option<Metrics>,
+	telemetry: Option<TelemetryHandle>,
+}
+
+/// Raises an alert when a key of this node equivocates and optionally stops signing with it.
+///
+/// Keys are identified by their raw public key, which is also their SCALE encoding.
+#[derive(Clone)]
+pub struct EquivocationGuard {
+	inner: Arc<Inner>,
+}
+
+impl EquivocationGuard {
+	/// Create a new `EquivocationGuard`.
+	///
+	/// Keys are considered local if they are in `keystore`. If `circuit_breaker` is set, keys
+	/// stop signing once they equivocated, until the node is restarted.
+	pub fn new(
+		circuit_breaker: bool,
+		keystore: SyncCryptoStorePtr,
+		registry: Option<&Registry>,
+		telemetry: Option<TelemetryHandle>,
+	) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			inner: Arc::new(Inner {
+				circuit_breaker,
+				keystore,
+				tripped_keys: Default::default(),
+				metrics: registry.map(Metrics::register).transpose()?,
+				telemetry,
+			}),
+		})
+	}
+
+	/// Returns whether `key` of type `key_type` is in the keystore of this node.
+	pub fn is_local_key(&self, key_type: KeyTypeId, key: &[u8]) -> bool {
+		SyncCryptoStore::has_keys(&*self.inner.keystore, &[(key.to_vec(), key_type)])
+	}
+
+	/// Returns whether the circuit breaker stopped signing with `key`.
+	pub fn is_tripped(&self, key: &[u8]) -> bool {
+		self.inner.tripped_keys.lock().contains(key)
+	}
+
+	/// Report an equivocation committed with the local `key` in `engine`, described by
+	/// `evidence`.
+	pub fn report(&self, engine: &'static str, key: &[u8], evidence: &str) {
+		let key_hex = HexDisplay::from(&key).to_string();
+		error!(
+			target: engine,
+			"🚨 Local key 0x{} equivocated: {}. Another node is signing with the same key!",
+			key_hex,
+			evidence,
+		);
+		telemetry!(
+			self.inner.telemetry;
+			CONSENSUS_WARN;
+			"consensus.self_equivocation";
+			"engine" => engine,
+			"key" => &key_hex,
+			"evidence" => evidence,
+		);
+		if let Some(metrics) = &self.inner.metrics {
+			metrics.self_equivocations.with_label_values(&[engine]).inc();
+		}
+
+		if self.inner.circuit_breaker && self.inner.tripped_keys.lock().insert(key.to_vec()) {
+			error!(target: engine, "🚨 Stopped signing with key 0x{} until restart.", key_hex);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::crypto::key_types::AURA;
+	use sp_keystore::testing::KeyStore;
+
+	#[test]
+	fn local_keys_are_looked_up_in_the_keystore() {
+		let keystore = Arc::new(KeyStore::new());
+		let alice = SyncCryptoStore::sr25519_generate_new(&*keystore, AURA, None).unwrap();
+		let guard = EquivocationGuard::new(false, keystore, None, None).unwrap();
+
+		assert!(guard.is_local_key(AURA, alice.as_ref()));
+		assert!(!guard.is_local_key(AURA, &[1; 32]));
+		assert!(!guard.is_local_key(KeyTypeId(*b"test"), alice.as_ref()));
+	}
+
+	#[test]
+	fn circuit_breaker_trips_on_report() {
+		let registry = Registry::new();
+		let guard =
+			EquivocationGuard::new(true, Arc::new(KeyStore::new()), Some(&registry), None).unwrap();
+
+		assert!(!guard.is_tripped(b"alice"));
+
+		guard.report("test", b"alice", "two blocks");
+		assert!(guard.is_tripped(b"alice"));
+		assert!(!guard.is_tripped(b"bob"));
+
+		let reported = registry.gather();
+		assert_eq!(reported[0].get_name(), "substrate_self_equivocations_total");
+		assert_eq!(reported[0].get_metric()[0].get_counter().get_value(), 1.0);
+	}
+
+	#[test]
+	fn keys_are_not_tripped_without_circuit_breaker() {
+		let guard = EquivocationGuard::new(false, Arc::new(KeyStore::new()), None, None).unwrap();
+
+		guard.report("test", b"alice", "two blocks");
+		assert!(!guard.is_tripped(b"alice"));
+	}
+}
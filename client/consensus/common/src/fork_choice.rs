@@ -0,0 +1,488 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Configurable fork choice.
+//!
+//! A [`ForkChoiceRule`] decides which of two chains is preferred. The same rule is applied at
+//! import time by [`ForkChoiceBlockImport`], which sets [`ForkChoiceStrategy::Custom`] on every
+//! imported block, and by [`ForkChoiceSelectChain`], which picks the best leaf for
+//! [`SelectChain::best_chain`] and [`SelectChain::finality_target`]. Permissioned deployments can
+//! implement rules such as preferring the chains authored by the current leader, or, with
+//! [`CheckpointRule`], the chains containing given checkpoints.
+
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+
+use sc_client_api::backend;
+use sp_blockchain::{lowest_common_ancestor, Backend as _, HeaderBackend, HeaderMetadata};
+use sp_consensus::{CacheKeyId, Error as ConsensusError, SelectChain};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, NumberFor},
+};
+
+use crate::{BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult};
+
+/// A rule deciding which of two chains is preferred.
+pub trait ForkChoiceRule<Block: BlockT>: Send + Sync {
+	/// Returns whether the chain ending at `candidate` is preferred over the chain ending at
+	/// `best`.
+	fn is_preferred(
+		&self,
+		candidate: &Block::Header,
+		best: &Block::Header,
+	) -> sp_blockchain::Result<bool>;
+}
+
+/// Prefers the chain with the highest number of blocks, keeping the current best chain on ties.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LongestChainRule;
+
+impl<Block: BlockT> ForkChoiceRule<Block> for LongestChainRule {
+	fn is_preferred(
+		&self,
+		candidate: &Block::Header,
+		best: &Block::Header,
+	) -> sp_blockchain::Result<bool> {
+		Ok(candidate.number() > best.number())
+	}
+}
+
+/// Prefers the chains that contain all the given checkpoints, then the longest chain.
+///
+/// A chain that doesn't reach the number of a checkpoint yet is considered to contain it.
+pub struct CheckpointRule<Block: BlockT, C> {
+	client: Arc<C>,
+	checkpoints: Vec<(NumberFor<Block>, Block::Hash)>,
+}
+
+impl<Block: BlockT, C> CheckpointRule<Block, C>
+where
+	C: HeaderMetadata<Block, Error = sp_blockchain::Error>,
+{
+	/// Create a new `CheckpointRule` preferring the chains containing the blocks `checkpoints`,
+	/// given as number and hash.
+	pub fn new(client: Arc<C>, checkpoints: Vec<(NumberFor<Block>, Block::Hash)>) -> Self {
+		CheckpointRule { client, checkpoints }
+	}
+
+	fn contains_checkpoints(&self, header: &Block::Header) -> sp_blockchain::Result<bool> {
+		for (number, hash) in &self.checkpoints {
+			if !self.contains_checkpoint(header, *number, *hash)? {
+				return Ok(false)
+			}
+		}
+		Ok(true)
+	}
+
+	fn contains_checkpoint(
+		&self,
+		header: &Block::Header,
+		number: NumberFor<Block>,
+		hash: Block::Hash,
+	) -> sp_blockchain::Result<bool> {
+		if number > *header.number() {
+			return Ok(true)
+		}
+		if number == *header.number() {
+			return Ok(header.hash() == hash)
+		}
+
+		// A checkpoint that isn't imported yet can't be part of an imported chain.
+		match self.client.header_metadata(hash) {
+			Ok(_) => {},
+			Err(sp_blockchain::Error::UnknownBlock(_)) => return Ok(false),
+			Err(e) => return Err(e),
+		}
+
+		// `header` may be the one being imported, so start from its parent.
+		let ancestor = lowest_common_ancestor(&*self.client, *header.parent_hash(), hash)?;
+		Ok(ancestor.hash == hash)
+	}
+}
+
+impl<Block: BlockT, C> ForkChoiceRule<Block> for CheckpointRule<Block, C>
+where
+	C: HeaderMetadata<Block, Error = sp_blockchain::Error> + Send + Sync,
+{
+	fn is_preferred(
+		&self,
+		candidate: &Block::Header,
+		best: &Block::Header,
+	) -> sp_blockchain::Result<bool> {
+		match (self.contains_checkpoints(candidate)?, self.contains_checkpoints(best)?) {
+			(true, false) => Ok(true),
+			(false, true) => Ok(false),
+			_ => ForkChoiceRule::<Block>::is_preferred(&LongestChainRule, candidate, best),
+		}
+	}
+}
+
+/// Returns the hash of the ancestor of `header`, or `header` itself, at `number`, or `None` if
+/// `number` is above `header`.
+///
+/// This walks the parent links, so it should only be used for ancestors close to `header`.
+fn ancestor_at<Block, C>(
+	client: &C,
+	header: &Block::Header,
+	number: NumberFor<Block>,
+) -> sp_blockchain::Result<Option<Block::Hash>>
+where
+	Block: BlockT,
+	C: HeaderMetadata<Block, Error = sp_blockchain::Error>,
+{
+	if number > *header.number() {
+		return Ok(None)
+	}
+	if number == *header.number() {
+		return Ok(Some(header.hash()))
+	}
+
+	let mut current = client.header_metadata(*header.parent_hash())?;
+	while current.number > number {
+		current = client.header_metadata(current.parent)?;
+	}
+	Ok(Some(current.hash))
+}
+
+/// Returns the header among `headers` that is preferred by `rule`.
+fn select_preferred<Block, R>(
+	rule: &R,
+	headers: impl IntoIterator<Item = Block::Header>,
+) -> sp_blockchain::Result<Option<Block::Header>>
+where
+	Block: BlockT,
+	R: ForkChoiceRule<Block> + ?Sized,
+{
+	let mut best: Option<Block::Header> = None;
+	for header in headers {
+		best = match best {
+			Some(best) if !rule.is_preferred(&header, &best)? => Some(best),
+			_ => Some(header),
+		};
+	}
+	Ok(best)
+}
+
+/// A block import that sets the fork choice of every imported block according to a
+/// [`ForkChoiceRule`], overriding the one set by the consensus engine.
+pub struct ForkChoiceBlockImport<Block: BlockT, I, C, R: ?Sized> {
+	inner: I,
+	client: Arc<C>,
+	rule: Arc<R>,
+	_phantom: PhantomData<Block>,
+}
+
+impl<Block: BlockT, I: Clone, C, R: ?Sized> Clone for ForkChoiceBlockImport<Block, I, C, R> {
+	fn clone(&self) -> Self {
+		ForkChoiceBlockImport {
+			inner: self.inner.clone(),
+			client: self.client.clone(),
+			rule: self.rule.clone(),
+			_phantom: PhantomData,
+		}
+	}
+}
+
+impl<Block: BlockT, I, C, R: ?Sized> ForkChoiceBlockImport<Block, I, C, R> {
+	/// Create a new `ForkChoiceBlockImport` wrapping `inner`.
+	pub fn new(inner: I, client: Arc<C>, rule: Arc<R>) -> Self {
+		ForkChoiceBlockImport { inner, client, rule, _phantom: PhantomData }
+	}
+}
+
+#[async_trait::async_trait]
+impl<Block, I, C, R> BlockImport<Block> for ForkChoiceBlockImport<Block, I, C, R>
+where
+	Block: BlockT,
+	I: BlockImport<Block, Error = ConsensusError> + Send,
+	C: HeaderBackend<Block> + Send + Sync,
+	R: ForkChoiceRule<Block> + ?Sized,
+{
+	type Error = ConsensusError;
+	type Transaction = I::Transaction;
+
+	async fn check_block(
+		&mut self,
+		block: BlockCheckParams<Block>,
+	) -> Result<ImportResult, Self::Error> {
+		self.inner.check_block(block).await
+	}
+
+	async fn import_block(
+		&mut self,
+		mut block: BlockImportParams<Block, Self::Transaction>,
+		cache: HashMap<CacheKeyId, Vec<u8>>,
+	) -> Result<ImportResult, Self::Error> {
+		let best_hash = self.client.info().best_hash;
+		let best = self
+			.client
+			.header(BlockId::Hash(best_hash))
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.ok_or_else(|| {
+				ConsensusError::ChainLookup(format!("Missing header of best block {}", best_hash))
+			})?;
+
+		let is_preferred = self
+			.rule
+			.is_preferred(&block.post_header(), &best)
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?;
+		block.fork_choice = Some(ForkChoiceStrategy::Custom(is_preferred));
+
+		self.inner.import_block(block, cache).await
+	}
+}
+
+/// A [`SelectChain`] picking the leaf preferred by a [`ForkChoiceRule`].
+pub struct ForkChoiceSelectChain<B, Block, R: ?Sized> {
+	backend: Arc<B>,
+	rule: Arc<R>,
+	_phantom: PhantomData<Block>,
+}
+
+impl<B, Block, R: ?Sized> Clone for ForkChoiceSelectChain<B, Block, R> {
+	fn clone(&self) -> Self {
+		ForkChoiceSelectChain {
+			backend: self.backend.clone(),
+			rule: self.rule.clone(),
+			_phantom: PhantomData,
+		}
+	}
+}
+
+impl<B, Block, R> ForkChoiceSelectChain<B, Block, R>
+where
+	B: backend::Backend<Block>,
+	Block: BlockT,
+	R: ForkChoiceRule<Block> + ?Sized,
+{
+	/// Create a new `ForkChoiceSelectChain` for the chain stored in `backend`.
+	pub fn new(backend: Arc<B>, rule: Arc<R>) -> Self {
+		ForkChoiceSelectChain { backend, rule, _phantom: PhantomData }
+	}
+
+	fn leaf_headers(&self) -> sp_blockchain::Result<Vec<Block::Header>> {
+		let blockchain = self.backend.blockchain();
+		blockchain
+			.leaves()?
+			.into_iter()
+			.map(|hash| blockchain.expect_header(BlockId::Hash(hash)))
+			.collect()
+	}
+
+	fn best_block_header(&self) -> sp_blockchain::Result<Block::Header> {
+		let _import_lock = self.backend.get_import_lock().read();
+		match select_preferred::<Block, _>(&*self.rule, self.leaf_headers()?)? {
+			Some(best) => Ok(best),
+			None => {
+				let blockchain = self.backend.blockchain();
+				blockchain.expect_header(BlockId::Hash(blockchain.info().best_hash))
+			},
+		}
+	}
+
+	fn preferred_finality_target(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> sp_blockchain::Result<Block::Hash> {
+		let _import_lock = self.backend.get_import_lock().read();
+		let blockchain = self.backend.blockchain();
+		let target_number = blockchain.expect_block_number_from_id(&BlockId::Hash(target_hash))?;
+
+		let mut descendants = Vec::new();
+		for leaf in self.leaf_headers()? {
+			if *leaf.number() >= target_number &&
+				lowest_common_ancestor(blockchain, leaf.hash(), target_hash)?.hash == target_hash
+			{
+				descendants.push(leaf);
+			}
+		}
+
+		let best = match select_preferred::<Block, _>(&*self.rule, descendants)? {
+			Some(best) => best,
+			None => return Ok(target_hash),
+		};
+
+		match maybe_max_number {
+			Some(max_number) if max_number < *best.number() => {
+				let max_number = max_number.max(target_number);
+				// The preferred leaf is usually the best block, whose ancestors are indexed by
+				// number.
+				if blockchain.hash(*best.number())? == Some(best.hash()) {
+					if let Some(hash) = blockchain.hash(max_number)? {
+						return Ok(hash)
+					}
+				}
+				Ok(ancestor_at::<Block, _>(blockchain, &best, max_number)?.unwrap_or(target_hash))
+			},
+			_ => Ok(best.hash()),
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl<B, Block, R> SelectChain<Block> for ForkChoiceSelectChain<B, Block, R>
+where
+	B: backend::Backend<Block>,
+	Block: BlockT,
+	R: ForkChoiceRule<Block> + ?Sized,
+{
+	async fn leaves(&self) -> Result<Vec<Block::Hash>, ConsensusError> {
+		self.backend
+			.blockchain()
+			.leaves()
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))
+	}
+
+	async fn best_chain(&self) -> Result<Block::Header, ConsensusError> {
+		self.best_block_header().map_err(|e| ConsensusError::ChainLookup(e.to_string()))
+	}
+
+	async fn finality_target(
+		&self,
+		target_hash: Block::Hash,
+		maybe_max_number: Option<NumberFor<Block>>,
+	) -> Result<Block::Hash, ConsensusError> {
+		self.preferred_finality_target(target_hash, maybe_max_number)
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parking_lot::Mutex;
+	use sp_blockchain::CachedHeaderMetadata;
+	use sp_test_primitives::{Block, Header};
+
+	/// A block tree keyed by hash.
+	#[derive(Default)]
+	struct Tree(Mutex<HashMap<<Block as BlockT>::Hash, Header>>);
+
+	impl Tree {
+		/// Adds `len` blocks on top of `parent`, the `fork` byte distinguishing sibling forks.
+		fn extend(&self, parent: &Header, len: u64, fork: u8) -> Vec<Header> {
+			let mut parent = parent.clone();
+			(0..len)
+				.map(|_| {
+					let mut header = Header::new(
+						parent.number + 1,
+						Default::default(),
+						Default::default(),
+						parent.hash(),
+						Default::default(),
+					);
+					header.extrinsics_root = [fork; 32].into();
+					self.0.lock().insert(header.hash(), header.clone());
+					parent = header.clone();
+					header
+				})
+				.collect()
+		}
+	}
+
+	impl HeaderMetadata<Block> for Tree {
+		type Error = sp_blockchain::Error;
+
+		fn header_metadata(
+			&self,
+			hash: <Block as BlockT>::Hash,
+		) -> sp_blockchain::Result<CachedHeaderMetadata<Block>> {
+			self.0
+				.lock()
+				.get(&hash)
+				.map(CachedHeaderMetadata::from)
+				.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("{:?}", hash)))
+		}
+
+		fn insert_header_metadata(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: CachedHeaderMetadata<Block>,
+		) {
+		}
+
+		fn remove_header_metadata(&self, _: <Block as BlockT>::Hash) {}
+	}
+
+	fn genesis(tree: &Tree) -> Header {
+		let genesis = Header::new(
+			0,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		);
+		tree.0.lock().insert(genesis.hash(), genesis.clone());
+		genesis
+	}
+
+	#[test]
+	fn longest_chain_rule_keeps_best_on_ties() {
+		let tree = Tree::default();
+		let genesis = genesis(&tree);
+		let a = tree.extend(&genesis, 3, 1);
+		let b = tree.extend(&genesis, 3, 2);
+
+		let rule = LongestChainRule;
+		assert!(ForkChoiceRule::<Block>::is_preferred(&rule, &a[2], &b[1]).unwrap());
+		assert!(!ForkChoiceRule::<Block>::is_preferred(&rule, &a[2], &b[2]).unwrap());
+		assert_eq!(
+			select_preferred::<Block, _>(&rule, vec![b[1].clone(), a[2].clone(), b[2].clone()])
+				.unwrap(),
+			Some(a[2].clone())
+		);
+	}
+
+	#[test]
+	fn checkpoint_rule_prefers_chains_with_checkpoints() {
+		let tree = Arc::new(Tree::default());
+		let genesis = genesis(&tree);
+		let a = tree.extend(&genesis, 5, 1);
+		let b = tree.extend(&a[1], 2, 2);
+
+		let checkpoint = b[0].clone();
+		let rule = CheckpointRule::<Block, _>::new(
+			tree.clone(),
+			vec![(checkpoint.number, checkpoint.hash())],
+		);
+
+		// the shorter fork contains the checkpoint.
+		assert!(rule.is_preferred(&b[1], &a[4]).unwrap());
+		assert!(!rule.is_preferred(&a[4], &b[1]).unwrap());
+		// chains below the checkpoint are considered to contain it, so the longest one wins.
+		assert!(rule.is_preferred(&a[1], &a[0]).unwrap());
+		assert!(rule.is_preferred(&b[1], &a[1]).unwrap());
+		assert_eq!(
+			select_preferred::<Block, _>(&rule, vec![a[4].clone(), b[1].clone()]).unwrap(),
+			Some(b[1].clone())
+		);
+	}
+
+	#[test]
+	fn ancestor_at_works() {
+		let tree = Tree::default();
+		let genesis = genesis(&tree);
+		let a = tree.extend(&genesis, 4, 1);
+
+		assert_eq!(ancestor_at::<Block, _>(&tree, &a[3], 5).unwrap(), None);
+		assert_eq!(ancestor_at::<Block, _>(&tree, &a[3], 4).unwrap(), Some(a[3].hash()));
+		assert_eq!(ancestor_at::<Block, _>(&tree, &a[3], 2).unwrap(), Some(a[1].hash()));
+		assert_eq!(ancestor_at::<Block, _>(&tree, &a[3], 0).unwrap(), Some(genesis.hash()));
+	}
+}
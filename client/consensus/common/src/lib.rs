@@ -19,6 +19,8 @@
 //! Collection of common consensus specific implementations
 
 pub mod block_import;
+pub mod equivocation_guard;
+pub mod fork_choice;
 pub mod import_queue;
 pub mod metrics;
 
@@ -27,6 +29,10 @@ pub use block_import::{
 	ImportedAux, ImportedState, JustificationImport, JustificationSyncLink, StateAction,
 	StorageChanges,
 };
+pub use equivocation_guard::EquivocationGuard;
+pub use fork_choice::{
+	CheckpointRule, ForkChoiceBlockImport, ForkChoiceRule, ForkChoiceSelectChain, LongestChainRule,
+};
 pub use import_queue::{
 	import_single_block, BasicQueue, BlockImportError, BlockImportStatus, BoxBlockImport,
 	BoxJustificationImport, DefaultImportQueue, ImportQueue, IncomingBlock, Link, Verifier,
@@ -0,0 +1,243 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Hash based PoW algorithms, and a CPU miner for them.
+//!
+//! The seal of a [`HashPow`] block is a nonce, such that the hash of the pre-hash, the pre-runtime
+//! digest and the nonce, read as a big endian number, times the difficulty doesn't overflow.
+
+use codec::{Decode, Encode};
+use log::*;
+use sp_consensus_pow::Seal;
+use sp_core::{
+	hashing::{blake2_256, sha2_256},
+	U256,
+};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::{
+	marker::PhantomData,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread,
+	time::Duration,
+};
+
+use crate::{DifficultyAdjustment, Error, MiningHandle, PowAlgorithm};
+
+/// Number of nonces a mining thread tries before looking for a new build.
+const NONCES_PER_ROUND: u64 = 10_000;
+
+/// How long a mining thread waits when there is nothing to mine.
+const IDLE_DURATION: Duration = Duration::from_millis(100);
+
+/// Hash function of the [`HashPow`] seals.
+pub trait PowHasher: Clone + Send + Sync + 'static {
+	/// Hash `data`.
+	fn hash(&self, data: &[u8]) -> [u8; 32];
+}
+
+/// The BLAKE2b-256 hash function.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake2;
+
+impl PowHasher for Blake2 {
+	fn hash(&self, data: &[u8]) -> [u8; 32] {
+		blake2_256(data)
+	}
+}
+
+/// The SHA-256 hash function, applied twice.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256d;
+
+impl PowHasher for Sha256d {
+	fn hash(&self, data: &[u8]) -> [u8; 32] {
+		sha2_256(&sha2_256(data))
+	}
+}
+
+/// The work of the seal `nonce`, to compare with the difficulty with [`meets_difficulty`].
+pub fn seal_work<H: PowHasher>(
+	hasher: &H,
+	pre_hash: &[u8],
+	pre_runtime: Option<&[u8]>,
+	nonce: u64,
+) -> U256 {
+	U256::from_big_endian(&hasher.hash(&(pre_hash, pre_runtime, nonce).encode()))
+}
+
+/// Whether `work` meets the `difficulty`.
+pub fn meets_difficulty(work: U256, difficulty: U256) -> bool {
+	!work.overflowing_mul(difficulty).1
+}
+
+/// A [`PowAlgorithm`] sealing blocks with a nonce, hashed by `H`, meeting the difficulty given by
+/// `D`.
+pub struct HashPow<B, H, D> {
+	hasher: H,
+	difficulty: D,
+	_marker: PhantomData<B>,
+}
+
+impl<B, H, D> HashPow<B, H, D> {
+	/// Create a new instance.
+	pub fn new(hasher: H, difficulty: D) -> Self {
+		Self { hasher, difficulty, _marker: PhantomData }
+	}
+}
+
+impl<B, H: Clone, D: Clone> Clone for HashPow<B, H, D> {
+	fn clone(&self) -> Self {
+		Self::new(self.hasher.clone(), self.difficulty.clone())
+	}
+}
+
+impl<B, H, D> PowAlgorithm<B> for HashPow<B, H, D>
+where
+	B: BlockT,
+	H: PowHasher,
+	D: DifficultyAdjustment<B>,
+{
+	type Difficulty = U256;
+
+	fn difficulty(&self, parent: B::Hash) -> Result<U256, Error<B>> {
+		self.difficulty.next_difficulty(parent)
+	}
+
+	fn timestamp(&self, parent: B::Hash) -> Result<Option<u64>, Error<B>> {
+		self.difficulty.timestamp(parent)
+	}
+
+	fn verify(
+		&self,
+		_parent: &BlockId<B>,
+		pre_hash: &B::Hash,
+		pre_digest: Option<&[u8]>,
+		seal: &Seal,
+		difficulty: U256,
+	) -> Result<bool, Error<B>> {
+		let nonce = match u64::decode(&mut &seal[..]) {
+			Ok(nonce) => nonce,
+			Err(_) => return Ok(false),
+		};
+
+		let work = seal_work(&self.hasher, pre_hash.as_ref(), pre_digest, nonce);
+		Ok(meets_difficulty(work, difficulty))
+	}
+}
+
+/// A multi-threaded CPU miner of [`HashPow`] seals, mining the builds of a [`MiningHandle`].
+///
+/// The mining threads are stopped when the miner is dropped.
+pub struct CpuMiner {
+	stop: Arc<AtomicBool>,
+	threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl CpuMiner {
+	/// Start mining with `threads` threads, each trying its own nonces, and submit the seals found
+	/// to `handle`.
+	pub fn start<B, H, D, C, L, Proof>(
+		handle: MiningHandle<B, HashPow<B, H, D>, C, L, Proof>,
+		hasher: H,
+		threads: usize,
+	) -> std::io::Result<Self>
+	where
+		B: BlockT,
+		H: PowHasher,
+		D: DifficultyAdjustment<B>,
+		C: sp_api::ProvideRuntimeApi<B>,
+		L: sc_consensus::JustificationSyncLink<B>,
+		sp_api::TransactionFor<C, B>: Send + 'static,
+		MiningHandle<B, HashPow<B, H, D>, C, L, Proof>: Send + 'static,
+	{
+		let stop = Arc::new(AtomicBool::new(false));
+		let step = threads.max(1) as u64;
+		let threads = (0..step)
+			.map(|index| {
+				let handle = handle.clone();
+				let hasher = hasher.clone();
+				let stop = stop.clone();
+				thread::Builder::new()
+					.name(format!("pow-miner-{}", index))
+					.spawn(move || mine(handle, hasher, stop, index, step))
+			})
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self { stop, threads })
+	}
+}
+
+impl Drop for CpuMiner {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		for thread in self.threads.drain(..) {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Mining loop of a thread, trying the nonces `first_nonce + k * step` of each build.
+fn mine<B, H, D, C, L, Proof>(
+	handle: MiningHandle<B, HashPow<B, H, D>, C, L, Proof>,
+	hasher: H,
+	stop: Arc<AtomicBool>,
+	first_nonce: u64,
+	step: u64,
+) where
+	B: BlockT,
+	H: PowHasher,
+	D: DifficultyAdjustment<B>,
+	C: sp_api::ProvideRuntimeApi<B>,
+	L: sc_consensus::JustificationSyncLink<B>,
+	sp_api::TransactionFor<C, B>: Send + 'static,
+{
+	let mut last_version = None;
+	let mut nonce = first_nonce;
+	while !stop.load(Ordering::Relaxed) {
+		let version = handle.version();
+		let metadata = match handle.metadata() {
+			Some(metadata) => metadata,
+			None => {
+				thread::sleep(IDLE_DURATION);
+				continue
+			},
+		};
+		if last_version != Some(version) {
+			last_version = Some(version);
+			nonce = first_nonce;
+		}
+
+		for _ in 0..NONCES_PER_ROUND {
+			let work = seal_work(
+				&hasher,
+				metadata.pre_hash.as_ref(),
+				metadata.pre_runtime.as_deref(),
+				nonce,
+			);
+			if meets_difficulty(work, metadata.difficulty) {
+				debug!(target: "pow", "Found seal {} on top of {}", nonce, metadata.best_hash);
+				futures::executor::block_on(handle.submit(nonce.encode()));
+				break
+			}
+			nonce = nonce.wrapping_add(step);
+		}
+	}
+}
@@ -0,0 +1,167 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Difficulty adjustment of the PoW algorithms.
+
+use codec::Decode;
+use parking_lot::Mutex;
+use sc_client_api::backend::AuxStore;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_consensus_pow::{
+	lwma::{self, LwmaParams},
+	LwmaApi, TimestampApi,
+};
+use sp_core::U256;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, Zero},
+};
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::{aux_key, Error, PowAux};
+
+/// Difficulty of the blocks built on top of a given parent.
+pub trait DifficultyAdjustment<B: BlockT> {
+	/// Get the difficulty of the children of `parent`.
+	fn next_difficulty(&self, parent: B::Hash) -> Result<U256, Error<B>>;
+
+	/// Get the timestamp of `parent`, see [`PowAlgorithm::timestamp`].
+	///
+	/// [`PowAlgorithm::timestamp`]: crate::PowAlgorithm::timestamp
+	fn timestamp(&self, _parent: B::Hash) -> Result<Option<u64>, Error<B>> {
+		Ok(None)
+	}
+}
+
+/// A fixed difficulty, e.g. for development chains.
+impl<B: BlockT> DifficultyAdjustment<B> for U256 {
+	fn next_difficulty(&self, _parent: B::Hash) -> Result<U256, Error<B>> {
+		Ok(*self)
+	}
+}
+
+/// Number of parents whose children difficulty is cached.
+const CACHE_SIZE: usize = 16;
+
+/// LWMA difficulty adjustment of the chains implementing [`LwmaApi`] and [`TimestampApi`].
+///
+/// The timestamp of each block is recorded in the [`PowAux`] of its children at their import, so
+/// the history of the past blocks is read from the auxiliary storage and the runtime is only
+/// called at the parent: the state of the older blocks may be pruned, whatever the `window`. The
+/// [`PowAux`] of the last `window` blocks must however be available, which requires importing
+/// them through the [`PowBlockImport`] with the state of their parent. Syncing strategies skipping
+/// either, such as warp sync, are not supported and fail with [`Error::UnknownDifficulty`] or
+/// [`Error::UnknownTimestamp`].
+///
+/// [`PowBlockImport`]: crate::PowBlockImport
+pub struct LwmaDifficulty<B: BlockT, C> {
+	client: Arc<C>,
+	cache: Arc<Mutex<VecDeque<(B::Hash, U256)>>>,
+}
+
+impl<B: BlockT, C> Clone for LwmaDifficulty<B, C> {
+	fn clone(&self) -> Self {
+		Self { client: self.client.clone(), cache: self.cache.clone() }
+	}
+}
+
+impl<B: BlockT, C> LwmaDifficulty<B, C>
+where
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + AuxStore,
+	C::Api: LwmaApi<B> + TimestampApi<B, u64>,
+{
+	/// Create a new instance computing the difficulty from the chain of `client`.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, cache: Arc::new(Mutex::new(VecDeque::with_capacity(CACHE_SIZE))) }
+	}
+
+	/// The timestamps and difficulties of the last blocks up to `parent`, oldest first.
+	///
+	/// The genesis block has no timestamp, so it is never part of the history. Fails if the
+	/// [`PowAux`] of one of the blocks, or the timestamp it records, is missing.
+	fn history(&self, parent: B::Hash, params: &LwmaParams) -> Result<Vec<(u64, U256)>, Error<B>> {
+		let mut history = Vec::with_capacity(params.window as usize + 1);
+		let mut hash = parent;
+		let mut timestamp = None;
+		while history.len() <= params.window as usize {
+			let header = self.client.expect_header(BlockId::Hash(hash)).map_err(Error::Client)?;
+			if header.number().is_zero() {
+				break
+			}
+
+			let timestamp_of_block = match timestamp {
+				Some(timestamp) => timestamp,
+				None if hash == parent => self.runtime_timestamp(parent)?,
+				None => return Err(Error::UnknownTimestamp(hash)),
+			};
+			let aux = match self.client.get_aux(&aux_key(&hash)).map_err(Error::Client)? {
+				Some(bytes) => PowAux::<U256>::decode(&mut &bytes[..]).map_err(Error::Codec)?,
+				None => return Err(Error::UnknownDifficulty(hash)),
+			};
+			history.push((timestamp_of_block, aux.difficulty));
+			timestamp = aux.parent_timestamp;
+			hash = *header.parent_hash();
+		}
+
+		history.reverse();
+		Ok(history)
+	}
+
+	fn runtime_timestamp(&self, hash: B::Hash) -> Result<u64, Error<B>> {
+		self.client
+			.runtime_api()
+			.timestamp(&BlockId::Hash(hash))
+			.map_err(|e| Error::Client(e.into()))
+	}
+}
+
+impl<B: BlockT, C> DifficultyAdjustment<B> for LwmaDifficulty<B, C>
+where
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + AuxStore,
+	C::Api: LwmaApi<B> + TimestampApi<B, u64>,
+{
+	fn next_difficulty(&self, parent: B::Hash) -> Result<U256, Error<B>> {
+		if let Some((_, difficulty)) = self.cache.lock().iter().find(|(hash, _)| *hash == parent) {
+			return Ok(*difficulty)
+		}
+
+		let params = self
+			.client
+			.runtime_api()
+			.lwma_params(&BlockId::Hash(parent))
+			.map_err(|e| Error::Client(e.into()))?;
+		let difficulty = lwma::next_difficulty(&params, &self.history(parent, &params)?);
+
+		let mut cache = self.cache.lock();
+		if cache.len() == CACHE_SIZE {
+			cache.pop_front();
+		}
+		cache.push_back((parent, difficulty));
+		Ok(difficulty)
+	}
+
+	fn timestamp(&self, parent: B::Hash) -> Result<Option<u64>, Error<B>> {
+		let header = self.client.expect_header(BlockId::Hash(parent)).map_err(Error::Client)?;
+		if header.number().is_zero() {
+			return Ok(None)
+		}
+
+		self.runtime_timestamp(parent).map(Some)
+	}
+}
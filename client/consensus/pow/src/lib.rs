@@ -31,7 +31,14 @@
 //! mining on a standalone thread. Finally, when a seal is found, call
 //! [`MiningHandle::submit`] to build the block.
 //!
-//! The auxiliary storage for PoW engine only stores the total difficulty.
+//! For chains sealing blocks with a hash meeting the difficulty, [`HashPow`] implements
+//! [`PowAlgorithm`] with either [`Blake2`] or [`Sha256d`], and [`CpuMiner`] does the mining on as
+//! many threads as wanted. The difficulty of [`HashPow`] is given by a [`DifficultyAdjustment`],
+//! such as a fixed difficulty or [`LwmaDifficulty`], which follows the hash rate with the LWMA
+//! algorithm parametrized by the runtime.
+//!
+//! The auxiliary storage for PoW engine only stores the difficulty, the total difficulty and, if
+//! the algorithm needs it, the timestamp of the parent of each block.
 //! For other storage requirements for particular PoW algorithm (such as
 //! the actual difficulty for each particular blocks), you can take a client
 //! reference in your [`PowAlgorithm`] implementation, and use a separate prefix
@@ -39,9 +46,15 @@
 //! as the storage, but it is not recommended as it won't work well with light
 //! clients.
 
+mod cpu;
+mod difficulty;
 mod worker;
 
-pub use crate::worker::{MiningBuild, MiningHandle, MiningMetadata};
+pub use crate::{
+	cpu::{meets_difficulty, seal_work, Blake2, CpuMiner, HashPow, PowHasher, Sha256d},
+	difficulty::{DifficultyAdjustment, LwmaDifficulty},
+	worker::{MiningBuild, MiningHandle, MiningMetadata},
+};
 
 use crate::worker::UntilImportedOrTimeout;
 use codec::{Decode, Encode};
@@ -107,6 +120,10 @@ pub enum Error<B: BlockT> {
 	CheckInherentsUnknownError(sp_inherents::InherentIdentifier),
 	#[error("Multiple pre-runtime digests")]
 	MultiplePreRuntimeDigests,
+	#[error("Difficulty of block {0:?} is unknown")]
+	UnknownDifficulty(B::Hash),
+	#[error("Timestamp of block {0:?} is unknown")]
+	UnknownTimestamp(B::Hash),
 	#[error(transparent)]
 	Client(sp_blockchain::Error),
 	#[error(transparent)]
@@ -150,12 +167,29 @@ pub struct PowIntermediate<Difficulty> {
 pub static INTERMEDIATE_KEY: &[u8] = b"pow1";
 
 /// Auxiliary storage data for PoW.
-#[derive(Encode, Decode, Clone, Debug, Default)]
+#[derive(Encode, Clone, Debug, Default)]
 pub struct PowAux<Difficulty> {
 	/// Difficulty of the current block.
 	pub difficulty: Difficulty,
 	/// Total difficulty up to current block.
 	pub total_difficulty: Difficulty,
+	/// Timestamp of the parent block, if the algorithm records it, see
+	/// [`PowAlgorithm::timestamp`].
+	pub parent_timestamp: Option<u64>,
+}
+
+impl<Difficulty: Decode> Decode for PowAux<Difficulty> {
+	fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let difficulty = Difficulty::decode(input)?;
+		let total_difficulty = Difficulty::decode(input)?;
+		// Entries written before the parent timestamp was recorded end here.
+		let parent_timestamp = match input.remaining_len()? {
+			Some(0) => None,
+			_ => Decode::decode(input)?,
+		};
+
+		Ok(Self { difficulty, total_difficulty, parent_timestamp })
+	}
 }
 
 impl<Difficulty> PowAux<Difficulty>
@@ -193,6 +227,12 @@ pub trait PowAlgorithm<B: BlockT> {
 	) -> Result<Option<bool>, Error<B>> {
 		Ok(None)
 	}
+	/// Get the timestamp of `parent`, recorded in the [`PowAux`] of its children at their import.
+	///
+	/// None means that the algorithm doesn't need the timestamps of the past blocks.
+	fn timestamp(&self, _parent: B::Hash) -> Result<Option<u64>, Error<B>> {
+		Ok(None)
+	}
 	/// Break a fork choice tie.
 	///
 	/// By default this chooses the earliest block seen. Using uniform tie
@@ -397,6 +437,7 @@ where
 
 		aux.difficulty = difficulty;
 		aux.total_difficulty.increment(difficulty);
+		aux.parent_timestamp = self.algorithm.timestamp(parent_hash)?;
 
 		let key = aux_key(&block.post_hash());
 		block.auxiliary.push((key, Some(aux.encode())));
@@ -713,3 +754,6 @@ fn fetch_seal<B: BlockT>(digest: Option<&DigestItem>, hash: B::Hash) -> Result<V
 		_ => Err(Error::<B>::HeaderUnsealed(hash)),
 	}
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,386 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! PoW testsuite
+
+use super::*;
+use futures::{executor, future};
+use sc_block_builder::BlockBuilderProvider;
+use sc_consensus::{BoxJustificationImport, LongestChain};
+use sc_network_test::{Block as TestBlock, *};
+use sp_api::ApiRef;
+use sp_consensus::{
+	AlwaysCanAuthor, BlockOrigin, DisableProofRecording, NoNetwork as DummyOracle, Proposal,
+};
+use sp_consensus_pow::{
+	lwma::{self, LwmaParams},
+	LwmaApi, TimestampApi,
+};
+use sp_core::U256;
+use sp_inherents::InherentData;
+use sp_runtime::traits::NumberFor;
+use std::task::Poll;
+use substrate_test_runtime_client::ClientBlockImportExt;
+
+type Error = sp_blockchain::Error;
+
+type TestAlgorithm = HashPow<TestBlock, Sha256d, U256>;
+
+type TestBlockImport = PowBlockImport<
+	TestBlock,
+	Arc<PeersFullClient>,
+	PeersFullClient,
+	LongestChain<substrate_test_runtime_client::Backend, TestBlock>,
+	TestAlgorithm,
+	AlwaysCanAuthor,
+	Box<dyn CreateInherentDataProviders<TestBlock, (), InherentDataProviders = ()>>,
+>;
+
+type PowPeer = Peer<(), TestBlockImport>;
+
+/// Low enough for a seal to be found within a few thousand hashes.
+const DIFFICULTY: u64 = 1000;
+
+fn algorithm() -> TestAlgorithm {
+	HashPow::new(Sha256d, U256::from(DIFFICULTY))
+}
+
+fn block_import(client: &PeersClient) -> TestBlockImport {
+	PowBlockImport::new(
+		client.as_client(),
+		client.as_client(),
+		algorithm(),
+		0,
+		LongestChain::new(client.as_backend()),
+		Box::new(|_, _| async { Ok(()) }),
+		AlwaysCanAuthor,
+	)
+}
+
+struct DummyFactory(Arc<PeersFullClient>);
+struct DummyProposer(<TestBlock as BlockT>::Hash, Arc<PeersFullClient>);
+
+impl Environment<TestBlock> for DummyFactory {
+	type Proposer = DummyProposer;
+	type CreateProposer = future::Ready<Result<DummyProposer, Error>>;
+	type Error = Error;
+
+	fn init(&mut self, parent_header: &<TestBlock as BlockT>::Header) -> Self::CreateProposer {
+		future::ready(Ok(DummyProposer(parent_header.hash(), self.0.clone())))
+	}
+}
+
+impl Proposer<TestBlock> for DummyProposer {
+	type Error = Error;
+	type Transaction =
+		sc_client_api::TransactionFor<substrate_test_runtime_client::Backend, TestBlock>;
+	type Proposal = future::Ready<Result<Proposal<TestBlock, Self::Transaction, ()>, Error>>;
+	type ProofRecording = DisableProofRecording;
+	type Proof = ();
+
+	fn propose(
+		self,
+		_: InherentData,
+		digests: Digest,
+		_: Duration,
+		_: Option<usize>,
+	) -> Self::Proposal {
+		let r = self
+			.1
+			.new_block_at(&BlockId::Hash(self.0), digests, false)
+			.unwrap()
+			.build()
+			.map_err(|e| e.into());
+
+		future::ready(r.map(|b| Proposal {
+			block: b.block,
+			proof: (),
+			storage_changes: b.storage_changes,
+		}))
+	}
+}
+
+#[derive(Default)]
+struct PowTestNet {
+	peers: Vec<PowPeer>,
+}
+
+impl TestNetFactory for PowTestNet {
+	type Verifier = PowVerifier<TestBlock, TestAlgorithm>;
+	type PeerData = ();
+	type BlockImport = TestBlockImport;
+
+	fn make_verifier(&self, _client: PeersClient, _peer_data: &()) -> Self::Verifier {
+		PowVerifier::new(algorithm())
+	}
+
+	fn make_block_import(
+		&self,
+		client: PeersClient,
+	) -> (BlockImportAdapter<Self::BlockImport>, Option<BoxJustificationImport<TestBlock>>, ()) {
+		(BlockImportAdapter::new(block_import(&client)), None, ())
+	}
+
+	fn peer(&mut self, i: usize) -> &mut PowPeer {
+		&mut self.peers[i]
+	}
+
+	fn peers(&self) -> &Vec<PowPeer> {
+		&self.peers
+	}
+
+	fn mut_peers<F: FnOnce(&mut Vec<PowPeer>)>(&mut self, closure: F) {
+		closure(&mut self.peers);
+	}
+}
+
+#[test]
+fn hash_pow_verifies_seals() {
+	let algorithm = algorithm();
+	let pre_hash = <TestBlock as BlockT>::Hash::repeat_byte(1);
+	let at = BlockId::Number(0);
+	let difficulty = U256::from(DIFFICULTY);
+
+	let nonce = (0u64..)
+		.find(|nonce| {
+			meets_difficulty(seal_work(&Sha256d, pre_hash.as_ref(), None, *nonce), difficulty)
+		})
+		.unwrap();
+	assert!(algorithm.verify(&at, &pre_hash, None, &nonce.encode(), difficulty).unwrap());
+
+	// the seal is bound to the pre-hash, the pre-runtime digest and the difficulty.
+	let other_hash = <TestBlock as BlockT>::Hash::repeat_byte(2);
+	let seal_work_of = |pre_hash: &<TestBlock as BlockT>::Hash, pre_runtime: Option<&[u8]>| {
+		seal_work(&Sha256d, pre_hash.as_ref(), pre_runtime, nonce)
+	};
+	assert_ne!(seal_work_of(&pre_hash, None), seal_work_of(&other_hash, None));
+	assert_ne!(seal_work_of(&pre_hash, None), seal_work_of(&pre_hash, Some(&b"graffiti"[..])));
+	assert!(!algorithm.verify(&at, &pre_hash, None, &nonce.encode(), U256::MAX).unwrap());
+
+	// an undecodable seal is invalid.
+	assert!(!algorithm.verify(&at, &pre_hash, None, &vec![1, 2], difficulty).unwrap());
+}
+
+#[test]
+fn mined_blocks_are_imported_by_peers() {
+	sp_tracing::try_init_simple();
+	let mut net = PowTestNet::new(2);
+
+	let peer = net.peer(0);
+	let client = peer.client().as_client();
+	let select_chain = peer.select_chain().expect("full client has a select chain");
+	let (handle, mining_worker) = start_mining_worker(
+		Box::new(block_import(peer.client())),
+		client.clone(),
+		select_chain,
+		algorithm(),
+		DummyFactory(client),
+		DummyOracle,
+		(),
+		Some(b"graffiti".to_vec()),
+		|_, _| async { Ok(()) },
+		Duration::from_millis(100),
+		Duration::from_secs(1),
+		AlwaysCanAuthor,
+	);
+	let _miner = CpuMiner::start(handle, Sha256d, 2).expect("Starts the mining threads");
+
+	executor::block_on(future::select(
+		Box::pin(mining_worker),
+		future::poll_fn(move |cx| {
+			net.poll(cx);
+			if net.peer(1).client().info().best_number < 3 {
+				return Poll::Pending
+			}
+
+			let best_hash = net.peer(1).client().info().best_hash;
+			let client = net.peer(1).client().as_client();
+			let aux = PowAux::<U256>::read::<_, TestBlock>(&*client, &best_hash)
+				.expect("imported blocks have a PoW aux");
+			assert_eq!(aux.difficulty, U256::from(DIFFICULTY));
+			assert!(aux.total_difficulty >= U256::from(3 * DIFFICULTY));
+			Poll::Ready(())
+		}),
+	));
+}
+
+const LWMA_PARAMS: LwmaParams = LwmaParams {
+	window: 3,
+	target_block_time: 1000,
+	initial_difficulty: U256([50, 0, 0, 0]),
+	min_difficulty: U256([1, 0, 0, 0]),
+};
+
+/// A client whose runtime adjusts the difficulty with [`LWMA_PARAMS`] and whose blocks have the
+/// given timestamps.
+struct LwmaClient {
+	client: Arc<TestClient>,
+	timestamps: HashMap<<TestBlock as BlockT>::Hash, u64>,
+}
+
+struct LwmaRuntimeApi {
+	timestamps: HashMap<<TestBlock as BlockT>::Hash, u64>,
+}
+
+impl ProvideRuntimeApi<TestBlock> for LwmaClient {
+	type Api = LwmaRuntimeApi;
+
+	fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+		LwmaRuntimeApi { timestamps: self.timestamps.clone() }.into()
+	}
+}
+
+sp_api::mock_impl_runtime_apis! {
+	impl LwmaApi<TestBlock> for LwmaRuntimeApi {
+		fn lwma_params() -> LwmaParams {
+			LWMA_PARAMS
+		}
+	}
+
+	impl TimestampApi<TestBlock, u64> for LwmaRuntimeApi {
+		#[advanced]
+		fn timestamp(&self, at: &BlockId<TestBlock>) -> Result<u64, sp_api::ApiError> {
+			match at {
+				BlockId::Hash(hash) => Ok(self.timestamps[hash]),
+				BlockId::Number(_) => unimplemented!(),
+			}
+		}
+	}
+}
+
+impl HeaderBackend<TestBlock> for LwmaClient {
+	fn header(
+		&self,
+		id: BlockId<TestBlock>,
+	) -> sp_blockchain::Result<Option<<TestBlock as BlockT>::Header>> {
+		self.client.header(id)
+	}
+
+	fn info(&self) -> sp_blockchain::Info<TestBlock> {
+		self.client.info()
+	}
+
+	fn status(&self, id: BlockId<TestBlock>) -> sp_blockchain::Result<sp_blockchain::BlockStatus> {
+		self.client.status(id)
+	}
+
+	fn number(
+		&self,
+		hash: <TestBlock as BlockT>::Hash,
+	) -> sp_blockchain::Result<Option<NumberFor<TestBlock>>> {
+		self.client.number(hash)
+	}
+
+	fn hash(
+		&self,
+		number: NumberFor<TestBlock>,
+	) -> sp_blockchain::Result<Option<<TestBlock as BlockT>::Hash>> {
+		self.client.hash(number)
+	}
+}
+
+impl AuxStore for LwmaClient {
+	fn insert_aux<
+		'a,
+		'b: 'a,
+		'c: 'a,
+		I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+		D: IntoIterator<Item = &'a &'b [u8]>,
+	>(
+		&self,
+		insert: I,
+		delete: D,
+	) -> sp_blockchain::Result<()> {
+		self.client.insert_aux(insert, delete)
+	}
+
+	fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		self.client.get_aux(key)
+	}
+}
+
+#[test]
+fn lwma_difficulty_is_computed_from_the_chain() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.info().genesis_hash;
+
+	// blocks 1 to 3 are found on target, block 4 half a block time after block 3.
+	let mut timestamps = HashMap::new();
+	let mut hashes = Vec::new();
+	let mut parent_timestamp = None;
+	for (number, timestamp) in [(1u64, 1000), (2, 2000), (3, 3000), (4, 3500), (5, 4500)] {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		let hash = block.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		timestamps.insert(hash, timestamp);
+		hashes.push(hash);
+
+		// block 5 is imported without its difficulty
+		if number < 5 {
+			let aux = PowAux {
+				difficulty: U256::from(100),
+				total_difficulty: U256::from(100 * number),
+				parent_timestamp,
+			};
+			client.insert_aux(&[(&aux_key(&hash)[..], &aux.encode()[..])], &[]).unwrap();
+		}
+		parent_timestamp = Some(timestamp);
+	}
+
+	// the runtime is only called at the parents, the state of the older blocks may be pruned.
+	timestamps.retain(|hash, _| *hash == hashes[3] || *hash == hashes[4]);
+	let lwma = LwmaDifficulty::new(Arc::new(LwmaClient { client, timestamps }));
+
+	// the genesis block has no timestamp, so its children have the initial difficulty.
+	assert_eq!(lwma.next_difficulty(genesis_hash).unwrap(), LWMA_PARAMS.initial_difficulty);
+
+	// the last `window` solve times are averaged, block 4 was found quickly.
+	let expected = lwma::next_difficulty(
+		&LWMA_PARAMS,
+		&[(1000, 100.into()), (2000, 100.into()), (3000, 100.into()), (3500, 100.into())],
+	);
+	assert!(expected > U256::from(100));
+	assert_eq!(lwma.next_difficulty(hashes[3]).unwrap(), expected);
+	// the difficulty of the children of block 4 is cached.
+	assert_eq!(lwma.next_difficulty(hashes[3]).unwrap(), expected);
+
+	// the timestamp of the parent is recorded in the aux of its children.
+	assert_eq!(DifficultyAdjustment::<TestBlock>::timestamp(&lwma, genesis_hash).unwrap(), None);
+	assert_eq!(DifficultyAdjustment::<TestBlock>::timestamp(&lwma, hashes[3]).unwrap(), Some(3500));
+
+	// the difficulty can't be computed without the difficulty of the parent.
+	assert!(matches!(
+		lwma.next_difficulty(hashes[4]),
+		Err(crate::Error::UnknownDifficulty(hash)) if hash == hashes[4]
+	));
+}
+
+#[test]
+fn pow_aux_without_parent_timestamp_is_decoded() {
+	let legacy = (U256::from(100), U256::from(300)).encode();
+	let aux = PowAux::<U256>::decode(&mut &legacy[..]).unwrap();
+	assert_eq!(aux.difficulty, U256::from(100));
+	assert_eq!(aux.total_difficulty, U256::from(300));
+	assert_eq!(aux.parent_timestamp, None);
+
+	let aux = PowAux { parent_timestamp: Some(1000), ..aux };
+	assert_eq!(
+		PowAux::<U256>::decode(&mut &aux.encode()[..]).unwrap().parent_timestamp,
+		Some(1000)
+	);
+}
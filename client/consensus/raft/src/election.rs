@@ -0,0 +1,440 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The Raft leader election.
+//!
+//! Each authority is a follower, a candidate or the leader of the current term. A follower that
+//! doesn't hear from a leader within its election timeout starts a new term as candidate and asks
+//! the other authorities for their vote. An authority votes for at most one candidate per term,
+//! and only for candidates that acknowledged blocks at least as recent as the ones it
+//! acknowledged itself, so that a new leader always builds on the blocks a majority acknowledged.
+//! The candidate receiving the votes of a majority becomes leader and sends heartbeats.
+//!
+//! The election is a pure state machine: the caller feeds it the messages of the other
+//! authorities and the passing of time, and sends the messages it returns to all authorities.
+
+use std::{
+	collections::BTreeSet,
+	time::{Duration, Instant},
+};
+
+use codec::{Decode, Encode};
+use rand::Rng;
+use sp_consensus_raft::{majority, AuthorityIndex, Term};
+
+/// The timeouts of the leader election.
+#[derive(Debug, Clone, Copy)]
+pub struct ElectionTimeouts {
+	/// The minimum time without hearing from a leader after which a new election is started.
+	pub election_timeout_min: Duration,
+	/// The maximum time without hearing from a leader after which a new election is started.
+	///
+	/// The actual timeout is picked at random between the minimum and the maximum to avoid
+	/// candidates splitting the votes.
+	pub election_timeout_max: Duration,
+	/// How often the leader sends heartbeats. Must be well below the minimum election timeout.
+	pub heartbeat_interval: Duration,
+}
+
+impl Default for ElectionTimeouts {
+	fn default() -> Self {
+		ElectionTimeouts {
+			election_timeout_min: Duration::from_millis(1500),
+			election_timeout_max: Duration::from_millis(3000),
+			heartbeat_interval: Duration::from_millis(500),
+		}
+	}
+}
+
+/// A message of the leader election.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum ElectionMessage<N> {
+	/// A candidate asks for votes.
+	RequestVote {
+		/// The term the candidate started.
+		term: Term,
+		/// The term and number of the latest block the candidate acknowledged.
+		last_ack: (Term, N),
+	},
+	/// A vote for a candidate.
+	Vote {
+		/// The term of the vote.
+		term: Term,
+		/// The candidate voted for.
+		candidate: AuthorityIndex,
+	},
+	/// The leader of the term is alive.
+	Heartbeat {
+		/// The term of the leader.
+		term: Term,
+	},
+}
+
+impl<N> ElectionMessage<N> {
+	/// The term of the message.
+	pub fn term(&self) -> Term {
+		match self {
+			ElectionMessage::RequestVote { term, .. } |
+			ElectionMessage::Vote { term, .. } |
+			ElectionMessage::Heartbeat { term } => *term,
+		}
+	}
+}
+
+/// The role of the local node in the current term.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Role {
+	/// Following the leader of the term.
+	Follower {
+		/// The leader of the term, if known.
+		leader: Option<AuthorityIndex>,
+	},
+	/// Asking for votes.
+	Candidate {
+		/// The authorities that voted for us, including ourselves.
+		votes: BTreeSet<AuthorityIndex>,
+	},
+	/// Leading the term.
+	Leader,
+}
+
+/// The state of the leader election of the local node.
+pub struct Election<N> {
+	local: Option<AuthorityIndex>,
+	n_authorities: usize,
+	timeouts: ElectionTimeouts,
+	term: Term,
+	voted_for: Option<AuthorityIndex>,
+	role: Role,
+	last_ack: (Term, N),
+	/// When the election timeout expires, or when the next heartbeat is due when leading.
+	deadline: Instant,
+}
+
+impl<N: Ord + Copy> Election<N> {
+	/// Create a new election among `n_authorities` authorities, `local` being our index if we
+	/// are one of them, starting as a follower in `term` in which we voted for `voted_for`.
+	///
+	/// `last_ack` is the term and number of the latest block we acknowledged.
+	pub fn new(
+		local: Option<AuthorityIndex>,
+		n_authorities: usize,
+		term: Term,
+		voted_for: Option<AuthorityIndex>,
+		last_ack: (Term, N),
+		timeouts: ElectionTimeouts,
+		now: Instant,
+	) -> Self {
+		let mut election = Election {
+			local,
+			n_authorities,
+			timeouts,
+			term,
+			voted_for,
+			role: Role::Follower { leader: None },
+			last_ack,
+			deadline: now,
+		};
+		election.deadline = now + election.election_timeout();
+		election
+	}
+
+	/// The current term.
+	pub fn term(&self) -> Term {
+		self.term
+	}
+
+	/// The candidate we voted for in the current term.
+	pub fn voted_for(&self) -> Option<AuthorityIndex> {
+		self.voted_for
+	}
+
+	/// The term and number of the latest block we acknowledged.
+	pub fn last_ack(&self) -> (Term, N) {
+		self.last_ack
+	}
+
+	/// Our role in the current term.
+	pub fn role(&self) -> &Role {
+		&self.role
+	}
+
+	/// The leader of the current term, if known.
+	pub fn leader(&self) -> Option<AuthorityIndex> {
+		match self.role {
+			Role::Leader => self.local,
+			Role::Follower { leader } => leader,
+			Role::Candidate { .. } => None,
+		}
+	}
+
+	/// Whether we lead the current term.
+	pub fn is_leader(&self) -> bool {
+		self.role == Role::Leader
+	}
+
+	/// Note that we acknowledged the block `number` authored during `term`.
+	pub fn note_ack(&mut self, term: Term, number: N) {
+		self.last_ack = self.last_ack.max((term, number));
+	}
+
+	/// Advance the election to `now`, starting a new election if the election timeout expired,
+	/// and returns the messages to send.
+	pub fn tick(&mut self, now: Instant) -> Vec<ElectionMessage<N>> {
+		if now < self.deadline {
+			return Vec::new()
+		}
+
+		match (&self.role, self.local) {
+			(Role::Leader, _) => {
+				self.deadline = now + self.timeouts.heartbeat_interval;
+				vec![ElectionMessage::Heartbeat { term: self.term }]
+			},
+			(_, Some(local)) => self.start_election(local, now),
+			(_, None) => {
+				self.deadline = now + self.election_timeout();
+				Vec::new()
+			},
+		}
+	}
+
+	/// Handle a message of the authority `from`, and returns the messages to send.
+	pub fn on_message(
+		&mut self,
+		from: AuthorityIndex,
+		message: ElectionMessage<N>,
+		now: Instant,
+	) -> Vec<ElectionMessage<N>> {
+		if from as usize >= self.n_authorities || Some(from) == self.local {
+			return Vec::new()
+		}
+
+		if message.term() > self.term {
+			self.term = message.term();
+			self.voted_for = None;
+			self.role = Role::Follower { leader: None };
+			self.deadline = now + self.election_timeout();
+		} else if message.term() < self.term {
+			return Vec::new()
+		}
+
+		match message {
+			ElectionMessage::RequestVote { term, last_ack } => {
+				let can_vote = self.local.is_some() &&
+					matches!(self.role, Role::Follower { .. }) &&
+					self.voted_for.map_or(true, |candidate| candidate == from) &&
+					last_ack >= self.last_ack;
+				if !can_vote {
+					return Vec::new()
+				}
+
+				self.voted_for = Some(from);
+				self.deadline = now + self.election_timeout();
+				vec![ElectionMessage::Vote { term, candidate: from }]
+			},
+			ElectionMessage::Vote { candidate, .. } => {
+				if Some(candidate) != self.local {
+					return Vec::new()
+				}
+				match &mut self.role {
+					Role::Candidate { votes } => {
+						votes.insert(from);
+						if votes.len() >= majority(self.n_authorities) {
+							return self.become_leader(now)
+						}
+						Vec::new()
+					},
+					_ => Vec::new(),
+				}
+			},
+			ElectionMessage::Heartbeat { .. } => {
+				if self.role != Role::Leader {
+					self.role = Role::Follower { leader: Some(from) };
+					self.deadline = now + self.election_timeout();
+				}
+				Vec::new()
+			},
+		}
+	}
+
+	fn start_election(&mut self, local: AuthorityIndex, now: Instant) -> Vec<ElectionMessage<N>> {
+		self.term += 1;
+		self.voted_for = Some(local);
+		self.role = Role::Candidate { votes: BTreeSet::from([local]) };
+		self.deadline = now + self.election_timeout();
+
+		if majority(self.n_authorities) <= 1 {
+			return self.become_leader(now)
+		}
+		vec![ElectionMessage::RequestVote { term: self.term, last_ack: self.last_ack }]
+	}
+
+	fn become_leader(&mut self, now: Instant) -> Vec<ElectionMessage<N>> {
+		self.role = Role::Leader;
+		self.deadline = now + self.timeouts.heartbeat_interval;
+		vec![ElectionMessage::Heartbeat { term: self.term }]
+	}
+
+	fn election_timeout(&self) -> Duration {
+		let ElectionTimeouts { election_timeout_min: min, election_timeout_max: max, .. } =
+			self.timeouts;
+		if max > min {
+			rand::thread_rng().gen_range(min..=max)
+		} else {
+			min
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TIMEOUTS: ElectionTimeouts = ElectionTimeouts {
+		election_timeout_min: Duration::from_millis(100),
+		election_timeout_max: Duration::from_millis(100),
+		heartbeat_interval: Duration::from_millis(20),
+	};
+
+	fn elections(n: u32, now: Instant) -> Vec<Election<u64>> {
+		(0..n).map(|i| Election::new(Some(i), n as usize, 0, None, (0, 0), TIMEOUTS, now)).collect()
+	}
+
+	/// Delivers the messages of `from` to all the other elections, and their replies back, until
+	/// no message is left.
+	fn deliver(
+		elections: &mut [Election<u64>],
+		from: AuthorityIndex,
+		messages: Vec<ElectionMessage<u64>>,
+		now: Instant,
+	) {
+		let mut queue: Vec<_> = messages.into_iter().map(|m| (from, m)).collect();
+		while let Some((from, message)) = queue.pop() {
+			for (to, election) in elections.iter_mut().enumerate() {
+				for reply in election.on_message(from, message.clone(), now) {
+					queue.push((to as AuthorityIndex, reply));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn first_candidate_becomes_leader() {
+		let start = Instant::now();
+		let mut elections = elections(3, start);
+
+		assert!(elections[0].tick(start).is_empty());
+
+		let now = start + TIMEOUTS.election_timeout_min;
+		let messages = elections[0].tick(now);
+		assert_eq!(messages, vec![ElectionMessage::RequestVote { term: 1, last_ack: (0, 0) }]);
+
+		deliver(&mut elections, 0, messages, now);
+
+		assert!(elections[0].is_leader());
+		for election in &elections {
+			assert_eq!(election.term(), 1);
+			assert_eq!(election.leader(), Some(0));
+		}
+	}
+
+	#[test]
+	fn single_authority_leads_alone() {
+		let now = Instant::now();
+		let mut election = Election::<u64>::new(Some(0), 1, 4, None, (0, 0), TIMEOUTS, now);
+
+		let messages = election.tick(now + TIMEOUTS.election_timeout_min);
+		assert_eq!(messages, vec![ElectionMessage::Heartbeat { term: 5 }]);
+		assert!(election.is_leader());
+	}
+
+	#[test]
+	fn votes_once_per_term() {
+		let now = Instant::now();
+		let mut election = Election::<u64>::new(Some(0), 3, 0, None, (0, 0), TIMEOUTS, now);
+
+		let request = ElectionMessage::RequestVote { term: 1, last_ack: (0, 0) };
+		assert_eq!(
+			election.on_message(1, request.clone(), now),
+			vec![ElectionMessage::Vote { term: 1, candidate: 1 }],
+		);
+		assert!(election.on_message(2, request.clone(), now).is_empty());
+		// the vote may be sent again to the same candidate.
+		assert_eq!(
+			election.on_message(1, request, now),
+			vec![ElectionMessage::Vote { term: 1, candidate: 1 }],
+		);
+	}
+
+	#[test]
+	fn does_not_vote_for_outdated_candidate() {
+		let now = Instant::now();
+		let mut election = Election::<u64>::new(Some(0), 3, 2, None, (2, 10), TIMEOUTS, now);
+
+		let request = |last_ack| ElectionMessage::RequestVote { term: 3, last_ack };
+		assert!(election.on_message(1, request((2, 9)), now).is_empty());
+		assert!(election.on_message(1, request((1, 20)), now).is_empty());
+		assert_eq!(
+			election.on_message(2, request((3, 1)), now),
+			vec![ElectionMessage::Vote { term: 3, candidate: 2 }],
+		);
+	}
+
+	#[test]
+	fn leader_steps_down_on_higher_term() {
+		let start = Instant::now();
+		let mut elections = elections(3, start);
+
+		let now = start + TIMEOUTS.election_timeout_min;
+		let messages = elections[0].tick(now);
+		deliver(&mut elections, 0, messages, now);
+		assert!(elections[0].is_leader());
+
+		// the leader is partitioned away and the others elect a new one.
+		let now = now + TIMEOUTS.election_timeout_min;
+		let messages = elections[1].tick(now);
+		assert_eq!(messages, vec![ElectionMessage::RequestVote { term: 2, last_ack: (0, 0) }]);
+		let reply = elections[2].on_message(1, messages[0].clone(), now);
+		assert_eq!(elections[1].on_message(2, reply[0].clone(), now).len(), 1);
+		assert!(elections[1].is_leader());
+
+		// the old leader hears from the new one and follows it.
+		assert!(elections[0].on_message(1, ElectionMessage::Heartbeat { term: 2 }, now).is_empty());
+		assert_eq!(elections[0].role(), &Role::Follower { leader: Some(1) });
+
+		// messages of the old term are ignored.
+		assert!(elections[2].on_message(0, ElectionMessage::Heartbeat { term: 1 }, now).is_empty());
+		assert_eq!(elections[2].leader(), None);
+	}
+
+	#[test]
+	fn ignores_unknown_authorities() {
+		let now = Instant::now();
+		let mut election = Election::<u64>::new(None, 3, 0, None, (0, 0), TIMEOUTS, now);
+
+		assert!(election.on_message(3, ElectionMessage::Heartbeat { term: 1 }, now).is_empty());
+		assert_eq!(election.term(), 0);
+
+		// observers follow the leader but never vote nor become candidate.
+		assert!(election.on_message(2, ElectionMessage::Heartbeat { term: 1 }, now).is_empty());
+		assert_eq!(election.leader(), Some(2));
+		let request = ElectionMessage::RequestVote { term: 2, last_ack: (1, 0) };
+		assert!(election.on_message(1, request, now).is_empty());
+		assert!(election.tick(now + TIMEOUTS.election_timeout_min).is_empty());
+	}
+}
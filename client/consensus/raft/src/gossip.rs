@@ -0,0 +1,179 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Gossip of the Raft messages between the authorities.
+//!
+//! Messages are sent directly to the connected peers and aren't propagated further, so the
+//! authorities are expected to be connected to each other, e.g. as reserved peers.
+
+use codec::{Decode, Encode};
+use log::trace;
+use sc_network::PeerId;
+use sc_network_gossip::{MessageIntent, ValidationResult, Validator, ValidatorContext};
+use sp_consensus_raft::{
+	ack_payload, AuthorityId, AuthorityIndex, AuthoritySignature, RaftJustification, Term,
+	RAFT_ENGINE_ID,
+};
+use sp_runtime::{
+	traits::{Block as BlockT, Hash, Header as HeaderT, NumberFor},
+	RuntimeAppPublic,
+};
+
+use crate::election::ElectionMessage;
+
+/// The topic of all the Raft messages.
+pub(crate) fn topic<B: BlockT>() -> B::Hash {
+	<<B::Header as HeaderT>::Hashing as Hash>::hash(b"raft")
+}
+
+/// A message of the Raft protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub(crate) enum Message<H, N> {
+	/// A message of the leader election.
+	Election(ElectionMessage<N>),
+	/// An acknowledgement of a block authored by the leader of `term`.
+	Ack { term: Term, block_hash: H, block_number: N },
+	/// A block acknowledged by a majority of the authorities.
+	Commit(RaftJustification<H, N>),
+}
+
+impl<H: Encode, N: Encode> Message<H, N> {
+	/// The payload signed by the sender of the message.
+	///
+	/// The signature of an acknowledgement is part of the justification of the block.
+	fn signing_payload(&self, sequence: u64) -> Vec<u8> {
+		match self {
+			Message::Ack { term, block_hash, block_number } =>
+				ack_payload(*term, block_hash, block_number),
+			message => (RAFT_ENGINE_ID, sequence, message).encode(),
+		}
+	}
+}
+
+/// A message signed by the authority sending it.
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct SignedMessage<H, N> {
+	/// The message.
+	pub message: Message<H, N>,
+	/// The index of the sender in the authority set.
+	pub authority_index: AuthorityIndex,
+	/// Distinguishes messages that are otherwise equal, e.g. the heartbeats of a term, which the
+	/// gossip would drop as already known.
+	pub sequence: u64,
+	/// The signature of the sender.
+	pub signature: AuthoritySignature,
+}
+
+impl<H: Encode, N: Encode> SignedMessage<H, N> {
+	/// Sign `message` as the authority `authority_index` with `sign`.
+	pub fn sign(
+		message: Message<H, N>,
+		authority_index: AuthorityIndex,
+		sequence: u64,
+		sign: impl FnOnce(&[u8]) -> Option<AuthoritySignature>,
+	) -> Option<Self> {
+		let signature = sign(&message.signing_payload(sequence))?;
+		Some(SignedMessage { message, authority_index, sequence, signature })
+	}
+
+	/// Checks the signature of the message against the authority set `authorities`.
+	pub fn verify(&self, authorities: &[AuthorityId]) -> bool {
+		authorities.get(self.authority_index as usize).map_or(false, |authority| {
+			authority.verify(&self.message.signing_payload(self.sequence), &self.signature)
+		})
+	}
+}
+
+/// Gossip validator of the Raft messages.
+///
+/// Messages are only checked to be well-formed here, their signature is checked by the worker
+/// that knows the current authority set.
+pub(crate) struct GossipValidator<B: BlockT> {
+	topic: B::Hash,
+}
+
+impl<B: BlockT> GossipValidator<B> {
+	pub fn new() -> Self {
+		GossipValidator { topic: topic::<B>() }
+	}
+}
+
+impl<B: BlockT> Validator<B> for GossipValidator<B> {
+	fn validate(
+		&self,
+		_context: &mut dyn ValidatorContext<B>,
+		sender: &PeerId,
+		mut data: &[u8],
+	) -> ValidationResult<B::Hash> {
+		match SignedMessage::<B::Hash, NumberFor<B>>::decode(&mut data) {
+			Ok(_) => ValidationResult::ProcessAndDiscard(self.topic),
+			Err(e) => {
+				trace!(target: "raft", "Discarding undecodable message from {}: {}", sender, e);
+				ValidationResult::Discard
+			},
+		}
+	}
+
+	fn message_expired<'a>(&'a self) -> Box<dyn FnMut(B::Hash, &[u8]) -> bool + 'a> {
+		// our own messages are sent to the connected peers once, and not kept around.
+		Box::new(|_topic, _data| true)
+	}
+
+	fn message_allowed<'a>(
+		&'a self,
+	) -> Box<dyn FnMut(&PeerId, MessageIntent, &B::Hash, &[u8]) -> bool + 'a> {
+		Box::new(|_who, intent, _topic, _data| !matches!(intent, MessageIntent::PeriodicRebroadcast))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_application_crypto::Pair;
+	use sp_consensus_raft::AuthorityPair;
+	use sp_core::H256;
+
+	#[test]
+	fn signed_messages_are_verified() {
+		let pairs: Vec<_> = (0..2u8).map(|i| AuthorityPair::from_seed(&[i; 32])).collect();
+		let authorities: Vec<_> = pairs.iter().map(|p| p.public()).collect();
+
+		let heartbeat = Message::<H256, u64>::Election(ElectionMessage::Heartbeat { term: 3 });
+		let signed =
+			SignedMessage::sign(heartbeat.clone(), 1, 7, |payload| Some(pairs[1].sign(payload)))
+				.unwrap();
+		assert!(signed.verify(&authorities));
+
+		// a replayed signature doesn't cover another sequence, nor another sender.
+		assert!(!SignedMessage { sequence: 8, ..signed.clone() }.verify(&authorities));
+		assert!(!SignedMessage { authority_index: 0, ..signed.clone() }.verify(&authorities));
+		assert!(!SignedMessage { authority_index: 2, ..signed }.verify(&authorities));
+
+		// acknowledgements are signed with the payload of the justifications.
+		let ack = Message::Ack { term: 3, block_hash: H256::repeat_byte(1), block_number: 5u64 };
+		let signed = SignedMessage::sign(ack, 0, 8, |payload| Some(pairs[0].sign(payload))).unwrap();
+		let justification = RaftJustification {
+			term: 3,
+			block_hash: H256::repeat_byte(1),
+			block_number: 5u64,
+			acks: vec![(0, signed.signature.clone())],
+		};
+		assert!(signed.verify(&authorities));
+		assert!(justification.verify(&authorities[..1]).is_ok());
+	}
+}
@@ -0,0 +1,456 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Module implementing the logic for verifying and importing Raft blocks.
+
+use crate::{authorities, find_pre_digest, raft_err, Error};
+use codec::{Decode, Encode};
+use log::{debug, trace, warn};
+use prometheus_endpoint::Registry;
+use sc_client_api::{Backend, Finalizer};
+use sc_consensus::{
+	block_import::{BlockImport, BlockImportParams, ForkChoiceStrategy, JustificationImport},
+	import_queue::{BasicQueue, BoxJustificationImport, DefaultImportQueue, Verifier},
+};
+use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_TRACE};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_block_builder::BlockBuilder as BlockBuilderApi;
+use sp_blockchain::{
+	well_known_cache_keys::{self, Id as CacheKeyId},
+	HeaderBackend,
+};
+use sp_consensus::Error as ConsensusError;
+use sp_consensus_raft::{
+	digests::CompatibleDigestItem, AuthorityId, AuthorityPair, ConsensusLog, PreDigest, RaftApi,
+	RaftJustification, RAFT_ENGINE_ID,
+};
+use sp_core::{crypto::Pair, ExecutionContext};
+use sp_inherents::{CreateInherentDataProviders, InherentDataProvider as _};
+use sp_runtime::{
+	generic::{BlockId, OpaqueDigestItemId},
+	traits::{Block as BlockT, Header, NumberFor},
+	DigestItem, Justification,
+};
+use std::{marker::PhantomData, sync::Arc};
+
+/// Check a header has been signed by the authority claimed in its pre-digest, in a term not
+/// lower than the one of its parent. If it's successful, returns the pre-header, the pre-digest
+/// and the digest item containing the seal.
+fn check_header<B: BlockT>(
+	mut header: B::Header,
+	hash: B::Hash,
+	parent_header: &B::Header,
+	authorities: &[AuthorityId],
+) -> Result<(B::Header, PreDigest, DigestItem), Error<B>> {
+	let seal = header.digest_mut().pop().ok_or(Error::HeaderUnsealed(hash))?;
+
+	let sig = seal.as_raft_seal().ok_or_else(|| raft_err(Error::HeaderBadSeal(hash)))?;
+
+	let pre_digest = find_pre_digest::<B>(&header)?;
+	let parent_term = find_pre_digest::<B>(parent_header)?.term;
+	if pre_digest.term < parent_term {
+		return Err(raft_err(Error::TermDecreased(hash, pre_digest.term, parent_term)))
+	}
+
+	let author = authorities
+		.get(pre_digest.authority_index as usize)
+		.ok_or(Error::AuthorityNotFound(pre_digest.authority_index))?;
+
+	if AuthorityPair::verify(&sig, header.hash().as_ref(), author) {
+		Ok((header, pre_digest, seal))
+	} else {
+		Err(Error::BadSignature(hash))
+	}
+}
+
+/// Decodes a Raft justification of the block `hash` and checks it against `authorities`.
+fn check_justification<B: BlockT>(
+	hash: B::Hash,
+	number: NumberFor<B>,
+	encoded: &[u8],
+	authorities: &[AuthorityId],
+) -> Result<RaftJustification<B::Hash, NumberFor<B>>, String> {
+	let justification = RaftJustification::<B::Hash, NumberFor<B>>::decode(&mut &*encoded)
+		.map_err(|e| format!("Could not decode justification of {:?}: {}", hash, e))?;
+
+	if justification.block_hash != hash || justification.block_number != number {
+		return Err(format!(
+			"Justification of #{} ({:?}) given for #{} ({:?})",
+			justification.block_number, justification.block_hash, number, hash,
+		))
+	}
+
+	justification
+		.verify(authorities)
+		.map_err(|e| format!("Invalid justification of {:?}: {:?}", hash, e))?;
+
+	Ok(justification)
+}
+
+/// A verifier for Raft blocks.
+///
+/// Blocks carrying a valid Raft justification are imported as finalized.
+pub struct RaftVerifier<C, CIDP> {
+	client: Arc<C>,
+	create_inherent_data_providers: CIDP,
+	telemetry: Option<TelemetryHandle>,
+}
+
+impl<C, CIDP> RaftVerifier<C, CIDP> {
+	/// Create a new `RaftVerifier`.
+	pub fn new(
+		client: Arc<C>,
+		create_inherent_data_providers: CIDP,
+		telemetry: Option<TelemetryHandle>,
+	) -> Self {
+		RaftVerifier { client, create_inherent_data_providers, telemetry }
+	}
+}
+
+impl<C, CIDP> RaftVerifier<C, CIDP>
+where
+	CIDP: Send,
+{
+	async fn check_inherents<B: BlockT>(
+		&self,
+		block: B,
+		block_id: BlockId<B>,
+		inherent_data: sp_inherents::InherentData,
+		create_inherent_data_providers: CIDP::InherentDataProviders,
+		execution_context: ExecutionContext,
+	) -> Result<(), Error<B>>
+	where
+		C: ProvideRuntimeApi<B>,
+		C::Api: BlockBuilderApi<B>,
+		CIDP: CreateInherentDataProviders<B, ()>,
+	{
+		let inherent_res = self
+			.client
+			.runtime_api()
+			.check_inherents_with_context(&block_id, execution_context, block, inherent_data)
+			.map_err(|e| Error::Client(e.into()))?;
+
+		if !inherent_res.ok() {
+			for (i, e) in inherent_res.into_errors() {
+				match create_inherent_data_providers.try_handle_error(&i, &e).await {
+					Some(res) => res.map_err(Error::Inherent)?,
+					None => return Err(Error::UnknownInherentError(i)),
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[async_trait::async_trait]
+impl<B: BlockT, C, CIDP> Verifier<B> for RaftVerifier<C, CIDP>
+where
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + Send + Sync,
+	C::Api: BlockBuilderApi<B> + RaftApi<B, AuthorityId> + ApiExt<B>,
+	CIDP: CreateInherentDataProviders<B, ()> + Send + Sync,
+	CIDP::InherentDataProviders: Send + Sync,
+{
+	async fn verify(
+		&mut self,
+		mut block: BlockImportParams<B, ()>,
+	) -> Result<(BlockImportParams<B, ()>, Option<Vec<(CacheKeyId, Vec<u8>)>>), String> {
+		let hash = block.header.hash();
+		let number = *block.header.number();
+		let parent_hash = *block.header.parent_hash();
+		let authorities = authorities(self.client.as_ref(), &BlockId::Hash(parent_hash))
+			.map_err(|e| format!("Could not fetch authorities at {:?}: {}", parent_hash, e))?;
+		let parent_header = self
+			.client
+			.header(BlockId::Hash(parent_hash))
+			.map_err(|e| format!("Could not fetch parent header of {:?}: {}", hash, e))?
+			.ok_or_else(|| format!("Parent header of {:?} not found", hash))?;
+
+		let (pre_header, pre_digest, seal) =
+			check_header::<B>(block.header, hash, &parent_header, &authorities)
+				.map_err(|e| e.to_string())?;
+
+		if let Some(inner_body) = block.body.take() {
+			let new_block = B::new(pre_header.clone(), inner_body);
+
+			let create_inherent_data_providers = self
+				.create_inherent_data_providers
+				.create_inherent_data_providers(parent_hash, ())
+				.await
+				.map_err(|e| Error::<B>::Client(sp_blockchain::Error::Application(e)))?;
+			let inherent_data = create_inherent_data_providers
+				.create_inherent_data()
+				.map_err(Error::<B>::Inherent)?;
+
+			self.check_inherents(
+				new_block.clone(),
+				BlockId::Hash(parent_hash),
+				inherent_data,
+				create_inherent_data_providers,
+				block.origin.into(),
+			)
+			.await
+			.map_err(|e| e.to_string())?;
+
+			let (_, inner_body) = new_block.deconstruct();
+			block.body = Some(inner_body);
+		}
+
+		trace!(target: "raft", "Checked {:?}; importing.", pre_header);
+		telemetry!(
+			self.telemetry;
+			CONSENSUS_TRACE;
+			"raft.checked_and_importing";
+			"pre_header" => ?pre_header,
+			"term" => pre_digest.term,
+		);
+
+		// A block carrying its justification is final.
+		if let Some(encoded) = block.justifications.as_ref().and_then(|j| j.get(RAFT_ENGINE_ID)) {
+			match check_justification::<B>(hash, number, encoded, &authorities) {
+				Ok(_) => block.finalized = true,
+				Err(e) => warn!(target: "raft", "Importing {:?} without finalizing it: {}", hash, e),
+			}
+		}
+
+		// Look for an authorities-change log.
+		let maybe_keys = pre_header
+			.digest()
+			.logs()
+			.iter()
+			.filter_map(|l| {
+				l.try_to::<ConsensusLog<AuthorityId>>(OpaqueDigestItemId::Consensus(
+					&RAFT_ENGINE_ID,
+				))
+			})
+			.find_map(|l| match l {
+				ConsensusLog::AuthoritiesChange(a) =>
+					Some(vec![(well_known_cache_keys::AUTHORITIES, a.encode())]),
+			});
+
+		block.header = pre_header;
+		block.post_digests.push(seal);
+		block.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+		block.post_hash = Some(hash);
+
+		Ok((block, maybe_keys))
+	}
+}
+
+/// Finalizes the blocks whose Raft justification is received from the network.
+pub struct RaftJustificationImport<C, BE> {
+	client: Arc<C>,
+	_phantom: PhantomData<BE>,
+}
+
+impl<C, BE> RaftJustificationImport<C, BE> {
+	/// Create a new `RaftJustificationImport`.
+	pub fn new(client: Arc<C>) -> Self {
+		RaftJustificationImport { client, _phantom: PhantomData }
+	}
+}
+
+impl<C, BE> Clone for RaftJustificationImport<C, BE> {
+	fn clone(&self) -> Self {
+		RaftJustificationImport { client: self.client.clone(), _phantom: PhantomData }
+	}
+}
+
+#[async_trait::async_trait]
+impl<B, C, BE> JustificationImport<B> for RaftJustificationImport<C, BE>
+where
+	B: BlockT,
+	BE: Backend<B>,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + Finalizer<B, BE> + Send + Sync,
+	C::Api: RaftApi<B, AuthorityId>,
+{
+	type Error = ConsensusError;
+
+	async fn on_start(&mut self) -> Vec<(B::Hash, NumberFor<B>)> {
+		Vec::new()
+	}
+
+	async fn import_justification(
+		&mut self,
+		hash: B::Hash,
+		number: NumberFor<B>,
+		justification: Justification,
+	) -> Result<(), Self::Error> {
+		if justification.0 != RAFT_ENGINE_ID {
+			return Err(ConsensusError::ClientImport(format!(
+				"Expected Raft justification, got {}",
+				String::from_utf8_lossy(&justification.0),
+			)))
+		}
+
+		let header = self
+			.client
+			.header(BlockId::Hash(hash))
+			.map_err(|e| ConsensusError::ClientImport(e.to_string()))?
+			.ok_or_else(|| ConsensusError::ClientImport(format!("Unknown block {:?}", hash)))?;
+		let authorities = authorities(self.client.as_ref(), &BlockId::Hash(*header.parent_hash()))?;
+
+		check_justification::<B>(hash, number, &justification.1, &authorities)
+			.map_err(ConsensusError::ClientImport)?;
+
+		debug!(target: "raft", "Finalizing #{} ({:?}) with imported justification", number, hash);
+		self.client
+			.finalize_block(BlockId::Hash(hash), Some(justification), true)
+			.map_err(|e| ConsensusError::ClientImport(e.to_string()))
+	}
+}
+
+/// Parameters of [`import_queue`].
+pub struct ImportQueueParams<'a, Block, I, C, S, CIDP> {
+	/// The block import to use.
+	pub block_import: I,
+	/// The justification import, e.g. a [`RaftJustificationImport`].
+	pub justification_import: Option<BoxJustificationImport<Block>>,
+	/// The client to interact with the chain.
+	pub client: Arc<C>,
+	/// Something that can create the inherent data providers.
+	pub create_inherent_data_providers: CIDP,
+	/// The spawner to spawn background tasks.
+	pub spawner: &'a S,
+	/// The prometheus registry.
+	pub registry: Option<&'a Registry>,
+	/// Telemetry instance used to report telemetry metrics.
+	pub telemetry: Option<TelemetryHandle>,
+}
+
+/// Start an import queue for the Raft consensus algorithm.
+pub fn import_queue<Block, I, C, S, CIDP>(
+	ImportQueueParams {
+		block_import,
+		justification_import,
+		client,
+		create_inherent_data_providers,
+		spawner,
+		registry,
+		telemetry,
+	}: ImportQueueParams<Block, I, C, S, CIDP>,
+) -> Result<DefaultImportQueue<Block, C>, ConsensusError>
+where
+	Block: BlockT,
+	C::Api: BlockBuilderApi<Block> + RaftApi<Block, AuthorityId> + ApiExt<Block>,
+	C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync,
+	I: BlockImport<Block, Error = ConsensusError, Transaction = sp_api::TransactionFor<C, Block>>
+		+ Send
+		+ Sync
+		+ 'static,
+	S: sp_core::traits::SpawnEssentialNamed,
+	CIDP: CreateInherentDataProviders<Block, ()> + Sync + Send + 'static,
+	CIDP::InherentDataProviders: Send + Sync,
+{
+	let verifier = RaftVerifier::new(client, create_inherent_data_providers, telemetry);
+
+	Ok(BasicQueue::new(verifier, Box::new(block_import), justification_import, spawner, registry))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_consensus_raft::ack_payload;
+	use sp_core::H256;
+	use sp_runtime::traits::Header as _;
+	use substrate_test_runtime_client::runtime::{Block, Header};
+
+	fn pairs(n: u8) -> Vec<AuthorityPair> {
+		(0..n).map(|i| AuthorityPair::from_seed(&[i; 32])).collect()
+	}
+
+	fn header(number: u64, parent_hash: H256, pre_digest: PreDigest) -> Header {
+		let mut header = Header::new(
+			number,
+			Default::default(),
+			Default::default(),
+			parent_hash,
+			Default::default(),
+		);
+		header.digest_mut().push(DigestItem::raft_pre_digest(pre_digest));
+		header
+	}
+
+	fn seal(mut header: Header, pair: &AuthorityPair) -> Header {
+		let signature = pair.sign(header.hash().as_ref());
+		header.digest_mut().push(DigestItem::raft_seal(signature));
+		header
+	}
+
+	#[test]
+	fn check_header_works() {
+		let pairs = pairs(3);
+		let authorities: Vec<_> = pairs.iter().map(|p| p.public()).collect();
+		let parent = header(1, Default::default(), PreDigest { term: 2, authority_index: 0 });
+
+		let pre_digest = PreDigest { term: 3, authority_index: 1 };
+		let sealed = seal(header(2, parent.hash(), pre_digest), &pairs[1]);
+		let hash = sealed.hash();
+		let (pre_header, checked, _) =
+			check_header::<Block>(sealed.clone(), hash, &parent, &authorities).unwrap();
+		assert_eq!(checked, pre_digest);
+		assert_eq!(pre_header, header(2, parent.hash(), pre_digest));
+
+		// sealed by another authority than the one of the pre-digest.
+		let sealed = seal(header(2, parent.hash(), pre_digest), &pairs[2]);
+		assert!(matches!(
+			check_header::<Block>(sealed.clone(), sealed.hash(), &parent, &authorities),
+			Err(Error::BadSignature(_)),
+		));
+
+		// authored in an older term than the parent.
+		let pre_digest = PreDigest { term: 1, authority_index: 1 };
+		let sealed = seal(header(2, parent.hash(), pre_digest), &pairs[1]);
+		assert!(matches!(
+			check_header::<Block>(sealed.clone(), sealed.hash(), &parent, &authorities),
+			Err(Error::TermDecreased(_, 1, 2)),
+		));
+
+		// by an unknown authority.
+		let pre_digest = PreDigest { term: 3, authority_index: 3 };
+		let sealed = seal(header(2, parent.hash(), pre_digest), &pairs[1]);
+		assert!(matches!(
+			check_header::<Block>(sealed.clone(), sealed.hash(), &parent, &authorities),
+			Err(Error::AuthorityNotFound(3)),
+		));
+	}
+
+	#[test]
+	fn check_justification_works() {
+		let pairs = pairs(3);
+		let authorities: Vec<_> = pairs.iter().map(|p| p.public()).collect();
+		let hash = H256::repeat_byte(1);
+		let payload = ack_payload(2, &hash, &5u64);
+
+		let justification = RaftJustification {
+			term: 2,
+			block_hash: hash,
+			block_number: 5u64,
+			acks: vec![(0, pairs[0].sign(&payload)), (2, pairs[2].sign(&payload))],
+		};
+		let encoded = justification.encode();
+
+		assert_eq!(
+			check_justification::<Block>(hash, 5, &encoded, &authorities),
+			Ok(justification),
+		);
+		assert!(check_justification::<Block>(hash, 6, &encoded, &authorities).is_err());
+		assert!(check_justification::<Block>(H256::zero(), 5, &encoded, &authorities).is_err());
+		assert!(check_justification::<Block>(hash, 5, &encoded[1..], &authorities).is_err());
+	}
+}
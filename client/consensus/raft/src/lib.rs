@@ -0,0 +1,286 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Raft consensus in substrate.
+//!
+//! Raft is a crash fault tolerant consensus for permissioned chains, where the authorities are
+//! trusted to follow the protocol but may crash or be partitioned away.
+//!
+//! The authorities elect a leader for a term using the Raft leader election (see [`election`]).
+//! Only the leader of the current term authors blocks, every `block_duration`, on top of the
+//! latest block it acknowledged. The authorities acknowledge the blocks authored by the leader of
+//! their current term, and a block acknowledged by a majority of the authorities is final: the
+//! leader finalizes it with the acknowledgements as [`RaftJustification`], and sends the
+//! justification to the other nodes. Since a candidate is only elected by authorities that didn't
+//! acknowledge more recent blocks than it did, the leader of a later term always builds on the
+//! finalized blocks.
+//!
+//! The authorities of a block are the ones returned by the [`RaftApi`] at its parent, and are
+//! managed in the runtime by `pallet-raft`. The Raft messages are gossiped on a dedicated
+//! notifications protocol, see [`raft_peers_set_config`].
+#![forbid(missing_docs, unsafe_code)]
+
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use codec::Codec;
+use futures::prelude::*;
+use log::{debug, trace};
+use prometheus_endpoint::Registry;
+use sc_client_api::{backend::AuxStore, Backend, BlockchainEvents, Finalizer};
+use sc_consensus::BlockImport;
+use sc_network::ProtocolName;
+use sc_network_gossip::{GossipEngine, Network as GossipNetwork};
+use sp_api::ProvideRuntimeApi;
+use sp_application_crypto::AppKey;
+use sp_blockchain::{HeaderBackend, HeaderMetadata};
+use sp_consensus::{Environment, Error as ConsensusError, Proposer, SyncOracle};
+use sp_core::crypto::Public;
+use sp_inherents::CreateInherentDataProviders;
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header, Zero},
+};
+
+pub mod election;
+mod gossip;
+mod import_queue;
+mod worker;
+
+pub use election::ElectionTimeouts;
+pub use import_queue::{import_queue, ImportQueueParams, RaftJustificationImport, RaftVerifier};
+pub use raft_protocol_name::standard_name as protocol_standard_name;
+pub use sp_consensus_raft::{
+	digests::CompatibleDigestItem, AuthorityId, AuthorityIndex, AuthorityPair, AuthoritySignature,
+	ConsensusLog, PreDigest, RaftApi, RaftJustification, Term, RAFT_ENGINE_ID,
+};
+
+pub(crate) mod raft_protocol_name {
+	use sc_chain_spec::ChainSpec;
+	use sc_network::ProtocolName;
+
+	const NAME: &str = "/raft/1";
+
+	/// Name of the notifications protocol used by Raft.
+	///
+	/// Must be registered towards the networking in order for Raft to properly function.
+	pub fn standard_name<Hash: AsRef<[u8]>>(
+		genesis_hash: &Hash,
+		chain_spec: &Box<dyn ChainSpec>,
+	) -> ProtocolName {
+		let chain_prefix = match chain_spec.fork_id() {
+			Some(fork_id) => format!("/{}/{}", hex::encode(genesis_hash), fork_id),
+			None => format!("/{}", hex::encode(genesis_hash)),
+		};
+		format!("{}{}", chain_prefix, NAME).into()
+	}
+}
+
+/// Returns the configuration value to put in
+/// [`sc_network::config::NetworkConfiguration::extra_sets`].
+/// For standard protocol name see [`raft_protocol_name::standard_name`].
+pub fn raft_peers_set_config(
+	protocol_name: ProtocolName,
+) -> sc_network::config::NonDefaultSetConfig {
+	let mut cfg = sc_network::config::NonDefaultSetConfig::new(protocol_name, 1024 * 1024);
+
+	cfg.allow_non_reserved(25, 25);
+	cfg.priority = sc_network::config::TrafficPriority::High;
+	cfg
+}
+
+/// Raft Errors
+#[derive(Debug, thiserror::Error)]
+pub enum Error<B: BlockT> {
+	/// Multiple Raft pre-runtime headers
+	#[error("Multiple Raft pre-runtime headers")]
+	MultipleHeaders,
+	/// No Raft pre-runtime digest found
+	#[error("No Raft pre-runtime digest found")]
+	NoDigestFound,
+	/// Header is unsealed
+	#[error("Header {0:?} is unsealed")]
+	HeaderUnsealed(B::Hash),
+	/// Header has a bad seal
+	#[error("Header {0:?} has a bad seal")]
+	HeaderBadSeal(B::Hash),
+	/// The author isn't one of the authorities
+	#[error("Authority {0} not found")]
+	AuthorityNotFound(AuthorityIndex),
+	/// Bad signature
+	#[error("Bad signature on {0:?}")]
+	BadSignature(B::Hash),
+	/// The term of a block is lower than the one of its parent
+	#[error("Block {0:?} authored in term {1} after its parent of term {2}")]
+	TermDecreased(B::Hash, Term, Term),
+	/// Client Error
+	#[error(transparent)]
+	Client(sp_blockchain::Error),
+	/// Unknown inherent error for identifier
+	#[error("Unknown inherent error for identifier: {}", String::from_utf8_lossy(.0))]
+	UnknownInherentError(sp_inherents::InherentIdentifier),
+	/// Inherents Error
+	#[error("Inherent error: {0}")]
+	Inherent(sp_inherents::Error),
+}
+
+impl<B: BlockT> From<Error<B>> for String {
+	fn from(error: Error<B>) -> String {
+		error.to_string()
+	}
+}
+
+fn raft_err<B: BlockT>(error: Error<B>) -> Error<B> {
+	debug!(target: "raft", "{}", error);
+	error
+}
+
+/// Get the pre-digest from the header
+pub fn find_pre_digest<B: BlockT>(header: &B::Header) -> Result<PreDigest, Error<B>> {
+	if header.number().is_zero() {
+		return Ok(PreDigest { term: 0, authority_index: 0 })
+	}
+
+	let mut pre_digest: Option<PreDigest> = None;
+	for log in header.digest().logs() {
+		trace!(target: "raft", "Checking log {:?}", log);
+		match (log.as_raft_pre_digest(), pre_digest.is_some()) {
+			(Some(_), true) => return Err(raft_err(Error::MultipleHeaders)),
+			(None, _) => trace!(target: "raft", "Ignoring digest not meant for us"),
+			(s, false) => pre_digest = s,
+		}
+	}
+	pre_digest.ok_or_else(|| raft_err(Error::NoDigestFound))
+}
+
+fn authorities<A, B, C>(client: &C, at: &BlockId<B>) -> Result<Vec<A>, ConsensusError>
+where
+	A: Codec + Debug,
+	B: BlockT,
+	C: ProvideRuntimeApi<B>,
+	C::Api: RaftApi<B, A>,
+{
+	client
+		.runtime_api()
+		.authorities(at)
+		.ok()
+		.ok_or(sp_consensus::Error::InvalidAuthoritiesSet)
+}
+
+/// Sign `payload` with the key of `public` in the keystore.
+fn sign(
+	keystore: &SyncCryptoStorePtr,
+	public: &AuthorityId,
+	payload: &[u8],
+) -> Option<AuthoritySignature> {
+	SyncCryptoStore::sign_with(
+		&**keystore,
+		<AuthorityId as AppKey>::ID,
+		&public.to_public_crypto_pair(),
+		payload,
+	)
+	.map_err(|e| debug!(target: "raft", "Failed to sign with {:?}: {}", public, e))
+	.ok()
+	.flatten()
+	.and_then(|signature| signature.try_into().ok())
+}
+
+/// Parameters of [`start_raft`].
+pub struct StartRaftParams<C, I, PF, N, CIDP> {
+	/// The client to interact with the chain.
+	pub client: Arc<C>,
+	/// A block importer, used to import the blocks we author.
+	pub block_import: I,
+	/// The proposer factory to build proposer instances.
+	pub proposer_factory: PF,
+	/// The network, used to gossip the Raft messages and to know whether we are syncing.
+	pub network: N,
+	/// Chain specific Raft protocol name. See [`protocol_standard_name`].
+	pub protocol_name: ProtocolName,
+	/// Something that can create the inherent data providers.
+	pub create_inherent_data_providers: CIDP,
+	/// The keystore that manages the keys of the node.
+	pub keystore: SyncCryptoStorePtr,
+	/// How often the leader authors a block.
+	pub block_duration: Duration,
+	/// The timeouts of the leader election.
+	pub election_timeouts: ElectionTimeouts,
+	/// The prometheus registry.
+	pub prometheus_registry: Option<Registry>,
+}
+
+/// Start the Raft worker. The returned future should be run in a futures executor.
+///
+/// Nodes without a key of the authority set follow the election and finalize the blocks the
+/// leader commits, but never vote nor author blocks.
+pub fn start_raft<B, BE, C, I, PF, N, CIDP, Error>(
+	StartRaftParams {
+		client,
+		block_import,
+		proposer_factory,
+		network,
+		protocol_name,
+		create_inherent_data_providers,
+		keystore,
+		block_duration,
+		election_timeouts,
+		prometheus_registry,
+	}: StartRaftParams<C, I, PF, N, CIDP>,
+) -> Result<impl Future<Output = ()>, ConsensusError>
+where
+	B: BlockT,
+	BE: Backend<B>,
+	C: ProvideRuntimeApi<B>
+		+ HeaderBackend<B>
+		+ HeaderMetadata<B, Error = sp_blockchain::Error>
+		+ BlockchainEvents<B>
+		+ Finalizer<B, BE>
+		+ AuxStore
+		+ Send
+		+ Sync
+		+ 'static,
+	C::Api: RaftApi<B, AuthorityId>,
+	I: BlockImport<B, Transaction = sp_api::TransactionFor<C, B>> + Send + Sync + 'static,
+	PF: Environment<B, Error = Error> + Send + Sync + 'static,
+	PF::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
+	N: GossipNetwork<B> + SyncOracle + Clone + Send + Sync + 'static,
+	CIDP: CreateInherentDataProviders<B, ()> + Send + 'static,
+	CIDP::InherentDataProviders: Send,
+	Error: std::error::Error + Send + From<ConsensusError> + 'static,
+{
+	let gossip_engine = GossipEngine::new(
+		network.clone(),
+		protocol_name,
+		Arc::new(gossip::GossipValidator::new()),
+		prometheus_registry.as_ref(),
+	);
+
+	let worker = worker::RaftWorker::new(
+		client,
+		block_import,
+		proposer_factory,
+		network,
+		gossip_engine,
+		create_inherent_data_providers,
+		keystore,
+		block_duration,
+		election_timeouts,
+	)?;
+
+	Ok(worker.run())
+}
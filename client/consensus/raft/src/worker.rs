@@ -0,0 +1,1020 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The Raft worker, driving the election, the acknowledgement of the blocks and, when leading,
+//! the authoring and finalization of the blocks.
+//!
+//! The authority set used by the worker is the one of the children of the latest block we
+//! acknowledged, i.e. the authorities returned by the runtime at the parent of the blocks we
+//! author and acknowledge next, as checked by the import queue. The acknowledgements of a block
+//! are checked against the authorities of its parent.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	marker::PhantomData,
+	sync::Arc,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use codec::{Decode, Encode};
+use futures::{
+	future::{BoxFuture, Fuse, FusedFuture},
+	prelude::*,
+};
+use futures_timer::Delay;
+use log::{debug, error, info, trace, warn};
+use sc_client_api::{backend::AuxStore, Backend, BlockchainEvents, Finalizer};
+use sc_consensus::{BlockImport, BlockImportParams, ForkChoiceStrategy, StateAction};
+use sc_network_gossip::{GossipEngine, TopicNotification};
+use sp_api::ProvideRuntimeApi;
+use sp_application_crypto::AppKey;
+use sp_blockchain::{HeaderBackend, HeaderMetadata};
+use sp_consensus::{
+	BlockOrigin, Environment, Error as ConsensusError, Proposal, Proposer, SyncOracle,
+};
+use sp_consensus_raft::{
+	digests::CompatibleDigestItem, majority, AuthorityId, AuthorityIndex, AuthoritySignature,
+	PreDigest, RaftApi, RaftJustification, Term, RAFT_ENGINE_ID,
+};
+use sp_core::crypto::ByteArray;
+use sp_inherents::{CreateInherentDataProviders, InherentDataProvider};
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+use sp_runtime::{
+	generic::{BlockId, Digest},
+	traits::{Block as BlockT, Header as HeaderT, NumberFor},
+	DigestItem,
+};
+
+use crate::{
+	authorities,
+	election::{Election, ElectionTimeouts},
+	find_pre_digest,
+	gossip::{topic, Message, SignedMessage},
+	sign,
+};
+
+/// The aux storage key of the persisted election state.
+const RAFT_ELECTION_STATE_KEY: &[u8] = b"raft_election_state";
+
+/// How often the election and the block authoring are advanced.
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A block proposed by the leader.
+type ProposalFor<B, C, PF> = Proposal<
+	B,
+	sp_api::TransactionFor<C, B>,
+	<<PF as Environment<B>>::Proposer as Proposer<B>>::Proof,
+>;
+
+/// The proposal of a block being authored, with its pre-digest.
+type PendingProposal<B, C, PF> =
+	BoxFuture<'static, Result<(PreDigest, ProposalFor<B, C, PF>), ConsensusError>>;
+
+/// The election state that must survive restarts, so that we never vote twice in a term nor
+/// forget the blocks we acknowledged.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct PersistedState<H, N> {
+	term: Term,
+	voted_for: Option<AuthorityIndex>,
+	last_ack: (Term, N, H),
+}
+
+/// The acknowledgements of a block authored during the current term, collected by the leader.
+struct Acks<N> {
+	number: N,
+	signatures: BTreeMap<AuthorityIndex, AuthoritySignature>,
+}
+
+pub(crate) struct RaftWorker<B: BlockT, BE, C, I, PF, N, CIDP> {
+	client: Arc<C>,
+	block_import: I,
+	proposer_factory: PF,
+	sync_oracle: N,
+	gossip_engine: GossipEngine<B>,
+	create_inherent_data_providers: Arc<CIDP>,
+	keystore: SyncCryptoStorePtr,
+	block_duration: Duration,
+	election_timeouts: ElectionTimeouts,
+	/// The authorities of the children of the latest block we acknowledged.
+	authorities: Vec<AuthorityId>,
+	local: Option<(AuthorityIndex, AuthorityId)>,
+	election: Election<NumberFor<B>>,
+	/// The hash of the latest block we acknowledged, see [`Election::last_ack`].
+	last_ack_hash: B::Hash,
+	persisted: PersistedState<B::Hash, NumberFor<B>>,
+	/// The term of the acknowledgements in `acks`.
+	acks_term: Term,
+	acks: HashMap<B::Hash, Acks<NumberFor<B>>>,
+	/// The sequence number of our next message.
+	sequence: u64,
+	/// When to author the next block when leading.
+	next_block: Instant,
+	_phantom: PhantomData<BE>,
+}
+
+impl<B, BE, C, I, PF, N, CIDP, Error> RaftWorker<B, BE, C, I, PF, N, CIDP>
+where
+	B: BlockT,
+	BE: Backend<B>,
+	C: ProvideRuntimeApi<B>
+		+ HeaderBackend<B>
+		+ HeaderMetadata<B, Error = sp_blockchain::Error>
+		+ BlockchainEvents<B>
+		+ Finalizer<B, BE>
+		+ AuxStore
+		+ Send
+		+ Sync
+		+ 'static,
+	C::Api: RaftApi<B, AuthorityId>,
+	I: BlockImport<B, Transaction = sp_api::TransactionFor<C, B>> + Send + Sync + 'static,
+	PF: Environment<B, Error = Error> + Send + Sync + 'static,
+	PF::Proposer: Proposer<B, Error = Error, Transaction = sp_api::TransactionFor<C, B>>,
+	N: SyncOracle + Send + Sync + 'static,
+	CIDP: CreateInherentDataProviders<B, ()> + Send + 'static,
+	CIDP::InherentDataProviders: Send,
+	Error: std::error::Error + Send + From<ConsensusError> + 'static,
+{
+	pub(crate) fn new(
+		client: Arc<C>,
+		block_import: I,
+		proposer_factory: PF,
+		sync_oracle: N,
+		gossip_engine: GossipEngine<B>,
+		create_inherent_data_providers: CIDP,
+		keystore: SyncCryptoStorePtr,
+		block_duration: Duration,
+		election_timeouts: ElectionTimeouts,
+	) -> Result<Self, ConsensusError> {
+		let info = client.info();
+		let finalized_header = client
+			.header(BlockId::Hash(info.finalized_hash))
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.ok_or_else(|| {
+				ConsensusError::ChainLookup(format!(
+					"Finalized block {:?} not found",
+					info.finalized_hash
+				))
+			})?;
+		let finalized_term = find_pre_digest::<B>(&finalized_header)
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.term;
+
+		let mut persisted = load_state::<B, _>(client.as_ref())
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.unwrap_or(PersistedState {
+				term: finalized_term,
+				voted_for: None,
+				last_ack: (finalized_term, info.finalized_number, info.finalized_hash),
+			});
+		if (finalized_term, info.finalized_number) > (persisted.last_ack.0, persisted.last_ack.1) {
+			persisted.last_ack = (finalized_term, info.finalized_number, info.finalized_hash);
+		}
+		persisted.term = persisted.term.max(persisted.last_ack.0);
+
+		let authorities = authorities(client.as_ref(), &BlockId::Hash(persisted.last_ack.2))?;
+		let local = local_authority(&keystore, &authorities);
+		let (last_ack_term, last_ack_number, last_ack_hash) = persisted.last_ack;
+		let now = Instant::now();
+		let election = Election::new(
+			local.as_ref().map(|(index, _)| *index),
+			authorities.len(),
+			persisted.term,
+			persisted.voted_for,
+			(last_ack_term, last_ack_number),
+			election_timeouts,
+			now,
+		);
+
+		info!(
+			target: "raft",
+			"Starting Raft in term {} with {} authorities, local authority: {:?}",
+			persisted.term,
+			authorities.len(),
+			local.as_ref().map(|(index, _)| index),
+		);
+
+		Ok(RaftWorker {
+			client,
+			block_import,
+			proposer_factory,
+			sync_oracle,
+			gossip_engine,
+			create_inherent_data_providers: Arc::new(create_inherent_data_providers),
+			keystore,
+			block_duration,
+			election_timeouts,
+			authorities,
+			local,
+			election,
+			last_ack_hash,
+			persisted,
+			acks_term: 0,
+			acks: HashMap::new(),
+			// start after the sequence numbers used before a restart, which the peers may still
+			// know as duplicates.
+			sequence: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|d| d.as_millis() as u64)
+				.unwrap_or_default(),
+			next_block: now,
+			_phantom: PhantomData,
+		})
+	}
+
+	/// Run the worker until the gossip engine or the client notification streams terminate.
+	pub(crate) async fn run(mut self) {
+		let mut messages = Box::pin(
+			self.gossip_engine
+				.messages_for(topic::<B>())
+				.filter_map(|TopicNotification { message, .. }| async move {
+					SignedMessage::<B::Hash, NumberFor<B>>::decode(&mut &message[..]).ok()
+				})
+				.fuse(),
+		);
+		let mut import_notifications = self.client.import_notification_stream().fuse();
+		let mut finality_notifications = self.client.finality_notification_stream().fuse();
+		let mut tick = Delay::new(TICK_INTERVAL).fuse();
+		// the block being proposed when leading, out of the loop so that we keep sending
+		// heartbeats and handling the messages meanwhile.
+		let mut proposal: Fuse<PendingProposal<B, C, PF>> = Fuse::terminated();
+
+		loop {
+			let mut gossip_engine = &mut self.gossip_engine;
+			futures::select! {
+				message = messages.next() => {
+					if let Some(message) = message {
+						self.on_message(message);
+					} else {
+						return;
+					}
+				},
+				notification = import_notifications.next() => {
+					if let Some(notification) = notification {
+						self.on_block_imported(&notification.header);
+					} else {
+						return;
+					}
+				},
+				notification = finality_notifications.next() => {
+					if let Some(notification) = notification {
+						self.on_block_finalized(&notification.header);
+					} else {
+						return;
+					}
+				},
+				_ = tick => {
+					tick = Delay::new(TICK_INTERVAL).fuse();
+					if self.on_tick(proposal.is_terminated()) {
+						match self.propose() {
+							Ok(pending) => proposal = pending.fuse(),
+							Err(e) => warn!(target: "raft", "Failed to author a block: {}", e),
+						}
+					}
+				},
+				result = proposal => {
+					let imported = match result {
+						Ok((pre_digest, block)) => self.import_proposal(pre_digest, block).await,
+						Err(e) => Err(e),
+					};
+					if let Err(e) = imported {
+						warn!(target: "raft", "Failed to author a block: {}", e);
+					}
+				},
+				_ = gossip_engine => {
+					error!(target: "raft", "Gossip engine has terminated.");
+					return;
+				},
+			}
+
+			if self.election.term() > self.acks_term {
+				self.acks_term = self.election.term();
+				self.acks.clear();
+			}
+			self.persist_state();
+		}
+	}
+
+	fn on_message(&mut self, signed: SignedMessage<B::Hash, NumberFor<B>>) {
+		// acknowledgements are signed by the authorities of the block, the other messages by the
+		// current authorities.
+		let block_authorities = match &signed.message {
+			Message::Ack { block_hash, .. } => match self.block_authorities(*block_hash) {
+				Ok(authorities) => Some(authorities),
+				Err(e) => {
+					debug!(target: "raft", "Discarding acknowledgement of {:?}: {}", block_hash, e);
+					return
+				},
+			},
+			_ => None,
+		};
+		let authorities = block_authorities.as_deref().unwrap_or(&self.authorities);
+		if !signed.verify(authorities) {
+			debug!(target: "raft", "Discarding message with a bad signature: {:?}", signed);
+			return
+		}
+		let n_authorities = authorities.len();
+		let from = signed.authority_index;
+		trace!(target: "raft", "Received {:?} from authority {}", signed.message, from);
+
+		match signed.message {
+			Message::Election(message) => {
+				let term = self.election.term();
+				for message in self.election.on_message(from, message, Instant::now()) {
+					self.gossip(Message::Election(message));
+				}
+				if self.election.term() > term {
+					debug!(target: "raft", "Moved to term {}", self.election.term());
+				}
+			},
+			Message::Ack { term, block_hash, block_number } =>
+				self.on_ack(from, n_authorities, term, block_hash, block_number, signed.signature),
+			Message::Commit(justification) => self.on_commit(justification),
+		}
+	}
+
+	/// Collect the acknowledgement of `from`, and finalize the block once acknowledged by a
+	/// majority of its `n_authorities` authorities.
+	fn on_ack(
+		&mut self,
+		from: AuthorityIndex,
+		n_authorities: usize,
+		term: Term,
+		block_hash: B::Hash,
+		block_number: NumberFor<B>,
+		signature: AuthoritySignature,
+	) {
+		if !self.election.is_leader() ||
+			term != self.election.term() ||
+			block_number <= self.client.info().finalized_number
+		{
+			return
+		}
+
+		let acks = self
+			.acks
+			.entry(block_hash)
+			.or_insert_with(|| Acks { number: block_number, signatures: BTreeMap::new() });
+		acks.signatures.insert(from, signature);
+		if acks.signatures.len() < majority(n_authorities) {
+			return
+		}
+
+		let justification = RaftJustification {
+			term,
+			block_hash,
+			block_number: acks.number,
+			acks: acks.signatures.iter().map(|(i, s)| (*i, s.clone())).collect(),
+		};
+		debug!(
+			target: "raft",
+			"Block #{} ({:?}) acknowledged by {} authorities, finalizing",
+			block_number,
+			block_hash,
+			justification.acks.len(),
+		);
+
+		if let Err(e) = self.client.finalize_block(
+			BlockId::Hash(block_hash),
+			Some((RAFT_ENGINE_ID, justification.encode())),
+			true,
+		) {
+			warn!(target: "raft", "Failed to finalize block {:?}: {}", block_hash, e);
+			return
+		}
+		self.gossip(Message::Commit(justification));
+	}
+
+	/// Finalize a block committed by the leader.
+	fn on_commit(&mut self, justification: RaftJustification<B::Hash, NumberFor<B>>) {
+		if justification.block_number <= self.client.info().finalized_number {
+			return
+		}
+
+		let header = match self.client.header(BlockId::Hash(justification.block_hash)) {
+			Ok(Some(header)) => header,
+			// we don't know the block yet, the justification of a later block will finalize it.
+			_ => return,
+		};
+		let verified = authorities(self.client.as_ref(), &BlockId::Hash(*header.parent_hash()))
+			.map_err(|e| format!("{}", e))
+			.and_then(|authorities| {
+				justification.verify(&authorities).map_err(|e| format!("{:?}", e))
+			});
+		if let Err(e) = verified {
+			debug!(target: "raft", "Discarding invalid commit of {:?}: {}", header.hash(), e);
+			return
+		}
+
+		if let Err(e) = self.client.finalize_block(
+			BlockId::Hash(justification.block_hash),
+			Some((RAFT_ENGINE_ID, justification.encode())),
+			true,
+		) {
+			warn!(target: "raft", "Failed to finalize block {:?}: {}", justification.block_hash, e);
+		}
+	}
+
+	/// Acknowledge the blocks authored by the leader of the current term.
+	fn on_block_imported(&mut self, header: &B::Header) {
+		if self.local.is_none() ||
+			self.sync_oracle.is_major_syncing() ||
+			*header.number() <= self.client.info().finalized_number
+		{
+			return
+		}
+
+		let pre_digest = match find_pre_digest::<B>(header) {
+			Ok(pre_digest) => pre_digest,
+			Err(_) => return,
+		};
+		let authored_by_leader = self
+			.election
+			.leader()
+			.map_or(true, |leader| leader == pre_digest.authority_index);
+		if pre_digest.term != self.election.term() || !authored_by_leader {
+			return
+		}
+
+		// the block is acknowledged by the authorities of its parent, which are ours unless the
+		// parent isn't the latest block we acknowledged.
+		let (hash, number) = (header.hash(), *header.number());
+		let block_authorities =
+			match authorities(self.client.as_ref(), &BlockId::Hash(*header.parent_hash())) {
+				Ok(authorities) => authorities,
+				Err(e) => {
+					warn!(target: "raft", "Failed to fetch the authorities of {:?}: {}", hash, e);
+					return
+				},
+			};
+		let signer = match local_authority(&self.keystore, &block_authorities) {
+			Some(signer) => signer,
+			None => return,
+		};
+
+		let is_leader = self.election.is_leader();
+		self.note_ack(pre_digest.term, number, hash);
+
+		let ack = Message::Ack { term: pre_digest.term, block_hash: hash, block_number: number };
+		if is_leader {
+			if !self.persist_state() {
+				return
+			}
+			if let Some(signed) = self.sign_message(ack, &signer) {
+				let n_authorities = block_authorities.len();
+				self.on_ack(
+					signer.0,
+					n_authorities,
+					pre_digest.term,
+					hash,
+					number,
+					signed.signature,
+				);
+			}
+		} else {
+			self.gossip_as(ack, &signer);
+		}
+	}
+
+	/// Note the blocks finalized with the justification of another leader, or by sync.
+	fn on_block_finalized(&mut self, header: &B::Header) {
+		let (hash, number) = (header.hash(), *header.number());
+		if let Ok(pre_digest) = find_pre_digest::<B>(header) {
+			self.note_ack(pre_digest.term, number, hash);
+		}
+		self.acks.retain(|_, acks| acks.number > number);
+	}
+
+	/// Advance the election, and returns whether a block should be authored now. `can_propose`
+	/// is false while the previous block is still being proposed.
+	fn on_tick(&mut self, can_propose: bool) -> bool {
+		let now = Instant::now();
+		let was_leader = self.election.is_leader();
+		for message in self.election.tick(now) {
+			self.gossip(Message::Election(message));
+		}
+
+		if !self.election.is_leader() {
+			return false
+		}
+		if !was_leader {
+			info!(target: "raft", "Elected leader of term {}", self.election.term());
+			self.next_block = now;
+		}
+		if !can_propose || now < self.next_block || self.sync_oracle.is_major_syncing() {
+			return false
+		}
+
+		self.next_block = now + self.block_duration;
+		true
+	}
+
+	/// Start proposing a block on top of the latest block we acknowledged, or of the latest
+	/// finalized block if it isn't one of its descendants.
+	fn propose(&mut self) -> Result<PendingProposal<B, C, PF>, ConsensusError> {
+		let (index, _) = self.local.clone().ok_or(ConsensusError::InvalidAuthoritiesSet)?;
+		let finalized_hash = self.client.info().finalized_hash;
+		let parent_hash = match sp_blockchain::lowest_common_ancestor(
+			self.client.as_ref(),
+			self.last_ack_hash,
+			finalized_hash,
+		) {
+			Ok(ancestor) if ancestor.hash == finalized_hash => self.last_ack_hash,
+			_ => finalized_hash,
+		};
+		let parent_header = self
+			.client
+			.header(BlockId::Hash(parent_hash))
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.ok_or_else(|| {
+				ConsensusError::ChainLookup(format!("Unknown block {:?}", parent_hash))
+			})?;
+
+		let pre_digest = PreDigest { term: self.election.term(), authority_index: index };
+		let create_inherent_data_providers = self.create_inherent_data_providers.clone();
+		let proposer = self.proposer_factory.init(&parent_header);
+		let max_duration = self.block_duration.mul_f32(0.98);
+
+		Ok(async move {
+			let inherent_data = create_inherent_data_providers
+				.create_inherent_data_providers(parent_hash, ())
+				.await
+				.map_err(|e| ConsensusError::ClientImport(e.to_string()))?
+				.create_inherent_data()
+				.map_err(|e| ConsensusError::ClientImport(e.to_string()))?;
+
+			let proposal = proposer
+				.await
+				.map_err(|e| ConsensusError::ClientImport(e.to_string()))?
+				.propose(
+					inherent_data,
+					Digest { logs: vec![DigestItem::raft_pre_digest(pre_digest)] },
+					max_duration,
+					None,
+				)
+				.await
+				.map_err(|e| ConsensusError::ClientImport(e.to_string()))?;
+			Ok((pre_digest, proposal))
+		}
+		.boxed())
+	}
+
+	/// Seal and import a block we proposed, unless we stopped leading meanwhile.
+	async fn import_proposal(
+		&mut self,
+		pre_digest: PreDigest,
+		proposal: ProposalFor<B, C, PF>,
+	) -> Result<(), ConsensusError> {
+		let public = match &self.local {
+			Some((index, public))
+				if *index == pre_digest.authority_index &&
+					self.election.is_leader() &&
+					self.election.term() == pre_digest.term =>
+				public.clone(),
+			_ => {
+				debug!(
+					target: "raft",
+					"Discarding the block proposed in term {}, not leading anymore",
+					pre_digest.term,
+				);
+				return Ok(())
+			},
+		};
+
+		let (header, body) = proposal.block.deconstruct();
+		let header_hash = header.hash();
+		let parent_hash = *header.parent_hash();
+		let signature = sign(&self.keystore, &public, header_hash.as_ref()).ok_or_else(|| {
+			ConsensusError::CannotSign(
+				public.to_raw_vec(),
+				"Could not find key in keystore.".into(),
+			)
+		})?;
+
+		let mut import_block = BlockImportParams::new(BlockOrigin::Own, header);
+		import_block.post_digests.push(DigestItem::raft_seal(signature));
+		import_block.body = Some(body);
+		import_block.state_action = StateAction::ApplyChanges(
+			sc_consensus::StorageChanges::Changes(proposal.storage_changes),
+		);
+		import_block.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+
+		info!(
+			target: "raft",
+			"Authored block #{} ({:?}) in term {} on top of {:?}",
+			import_block.header.number(),
+			header_hash,
+			pre_digest.term,
+			parent_hash,
+		);
+		self.block_import
+			.import_block(import_block, Default::default())
+			.await
+			.map(|_| ())
+			.map_err(|e| ConsensusError::ClientImport(e.to_string()))
+	}
+
+	/// Note that we acknowledged a block, and follow the authority set of its children.
+	fn note_ack(&mut self, term: Term, number: NumberFor<B>, hash: B::Hash) {
+		if (term, number) <= self.election.last_ack() {
+			return
+		}
+		self.election.note_ack(term, number);
+		self.last_ack_hash = hash;
+
+		let authorities = match authorities(self.client.as_ref(), &BlockId::Hash(hash)) {
+			Ok(authorities) => authorities,
+			Err(e) => {
+				warn!(target: "raft", "Failed to fetch the authorities at {:?}: {}", hash, e);
+				return
+			},
+		};
+		if authorities == self.authorities {
+			return
+		}
+
+		self.local = local_authority(&self.keystore, &authorities);
+		info!(
+			target: "raft",
+			"Authority set changed at #{} to {} authorities, local authority: {:?}",
+			number,
+			authorities.len(),
+			self.local.as_ref().map(|(index, _)| index),
+		);
+		self.election = Election::new(
+			self.local.as_ref().map(|(index, _)| *index),
+			authorities.len(),
+			self.election.term(),
+			self.election.voted_for(),
+			self.election.last_ack(),
+			self.election_timeouts,
+			Instant::now(),
+		);
+		self.authorities = authorities;
+	}
+
+	/// The authorities of the block `hash`, i.e. the ones at its parent.
+	fn block_authorities(&self, hash: B::Hash) -> Result<Vec<AuthorityId>, ConsensusError> {
+		let header = self
+			.client
+			.header(BlockId::Hash(hash))
+			.map_err(|e| ConsensusError::ChainLookup(e.to_string()))?
+			.ok_or_else(|| ConsensusError::ChainLookup(format!("Unknown block {:?}", hash)))?;
+		authorities(self.client.as_ref(), &BlockId::Hash(*header.parent_hash()))
+	}
+
+	fn sign_message(
+		&mut self,
+		message: Message<B::Hash, NumberFor<B>>,
+		(index, public): &(AuthorityIndex, AuthorityId),
+	) -> Option<SignedMessage<B::Hash, NumberFor<B>>> {
+		self.sequence += 1;
+		SignedMessage::sign(message, *index, self.sequence, |payload| {
+			sign(&self.keystore, public, payload)
+		})
+	}
+
+	/// Sign and gossip `message`, if we are one of the authorities.
+	fn gossip(&mut self, message: Message<B::Hash, NumberFor<B>>) {
+		if let Some(local) = self.local.clone() {
+			self.gossip_as(message, &local);
+		}
+	}
+
+	/// Sign `message` as `signer` and gossip it, once the election state is persisted: our
+	/// votes and acknowledgements must not be forgotten by a crash after being sent.
+	fn gossip_as(
+		&mut self,
+		message: Message<B::Hash, NumberFor<B>>,
+		signer: &(AuthorityIndex, AuthorityId),
+	) {
+		if !self.persist_state() {
+			return
+		}
+		if let Some(signed) = self.sign_message(message, signer) {
+			trace!(target: "raft", "Sending {:?}", signed.message);
+			self.gossip_engine.gossip_message(topic::<B>(), signed.encode(), false);
+		}
+	}
+
+	/// Persist the election state if it changed, and returns whether it is persisted.
+	fn persist_state(&mut self) -> bool {
+		let (last_ack_term, last_ack_number) = self.election.last_ack();
+		let state = PersistedState {
+			term: self.election.term(),
+			voted_for: self.election.voted_for(),
+			last_ack: (last_ack_term, last_ack_number, self.last_ack_hash),
+		};
+		if state == self.persisted {
+			return true
+		}
+
+		if let Err(e) = self
+			.client
+			.insert_aux(&[(RAFT_ELECTION_STATE_KEY, state.encode().as_slice())], &[])
+		{
+			error!(target: "raft", "Failed to persist the election state: {}", e);
+			return false
+		}
+		self.persisted = state;
+		true
+	}
+}
+
+/// The index and key of the authority in `authorities` that we have a key for.
+fn local_authority(
+	keystore: &SyncCryptoStorePtr,
+	authorities: &[AuthorityId],
+) -> Option<(AuthorityIndex, AuthorityId)> {
+	authorities.iter().enumerate().find_map(|(index, authority)| {
+		SyncCryptoStore::has_keys(&**keystore, &[(authority.to_raw_vec(), AuthorityId::ID)])
+			.then(|| (index as AuthorityIndex, authority.clone()))
+	})
+}
+
+fn load_state<B: BlockT, A: AuxStore>(
+	backend: &A,
+) -> sp_blockchain::Result<Option<PersistedState<B::Hash, NumberFor<B>>>> {
+	match backend.get_aux(RAFT_ELECTION_STATE_KEY)? {
+		None => Ok(None),
+		Some(state) => PersistedState::decode(&mut &state[..]).map(Some).map_err(|e| {
+			sp_blockchain::Error::Backend(format!("Raft DB is corrupted. Decode error: {}", e))
+		}),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{start_raft, RaftJustificationImport, RaftVerifier, StartRaftParams};
+	use futures::{executor, future::AbortHandle};
+	use parking_lot::Mutex;
+	use sc_block_builder::BlockBuilderProvider;
+	use sc_client_api::BlockBackend;
+	use sc_consensus::BoxJustificationImport;
+	use sc_keystore::LocalKeystore;
+	use sc_network_test::{Block as TestBlock, *};
+	use sp_application_crypto::key_types::RAFT;
+	use sp_consensus::DisableProofRecording;
+	use sp_inherents::InherentData;
+	use sp_keyring::Ed25519Keyring;
+	use std::task::Poll;
+	use substrate_test_runtime_client::runtime::{system::raft_authorities_storage, Header, H256};
+
+	type Error = sp_blockchain::Error;
+
+	const PROTOCOL_NAME: &str = "/raft/1";
+	const AUTHORITIES: [Ed25519Keyring; 3] =
+		[Ed25519Keyring::Alice, Ed25519Keyring::Bob, Ed25519Keyring::Charlie];
+
+	fn authorities() -> Vec<AuthorityId> {
+		AUTHORITIES.iter().map(|key| key.public().into()).collect()
+	}
+
+	struct DummyFactory(Arc<PeersFullClient>);
+	struct DummyProposer(H256, Arc<PeersFullClient>);
+
+	impl Environment<TestBlock> for DummyFactory {
+		type Proposer = DummyProposer;
+		type CreateProposer = future::Ready<Result<DummyProposer, Error>>;
+		type Error = Error;
+
+		fn init(&mut self, parent_header: &<TestBlock as BlockT>::Header) -> Self::CreateProposer {
+			future::ready(Ok(DummyProposer(parent_header.hash(), self.0.clone())))
+		}
+	}
+
+	impl Proposer<TestBlock> for DummyProposer {
+		type Error = Error;
+		type Transaction =
+			sc_client_api::TransactionFor<substrate_test_runtime_client::Backend, TestBlock>;
+		type Proposal = future::Ready<Result<Proposal<TestBlock, Self::Transaction, ()>, Error>>;
+		type ProofRecording = DisableProofRecording;
+		type Proof = ();
+
+		fn propose(
+			self,
+			_: InherentData,
+			digests: Digest,
+			_: Duration,
+			_: Option<usize>,
+		) -> Self::Proposal {
+			let r = self
+				.1
+				.new_block_at(&BlockId::Hash(self.0), digests, false)
+				.and_then(|builder| builder.build());
+
+			future::ready(r.map(|b| Proposal {
+				block: b.block,
+				proof: (),
+				storage_changes: b.storage_changes,
+			}))
+		}
+	}
+
+	type RaftTestVerifier = RaftVerifier<
+		PeersFullClient,
+		Box<dyn CreateInherentDataProviders<TestBlock, (), InherentDataProviders = ()>>,
+	>;
+	type RaftPeer = Peer<(), PeersClient>;
+
+	#[derive(Default)]
+	struct RaftTestNet {
+		peers: Vec<RaftPeer>,
+	}
+
+	impl TestNetFactory for RaftTestNet {
+		type Verifier = RaftTestVerifier;
+		type PeerData = ();
+		type BlockImport = PeersClient;
+
+		fn add_full_peer(&mut self) {
+			let (key, value) = raft_authorities_storage(&authorities());
+			let mut extra_storage = sp_core::storage::Storage::default();
+			extra_storage.top.insert(key, value);
+
+			self.add_full_peer_with_config(FullPeerConfig {
+				notifications_protocols: vec![PROTOCOL_NAME.into()],
+				is_authority: true,
+				extra_storage: Some(extra_storage),
+				..Default::default()
+			})
+		}
+
+		fn make_verifier(&self, client: PeersClient, _: &()) -> Self::Verifier {
+			RaftVerifier::new(client.as_client(), Box::new(|_, _| async { Ok(()) }), None)
+		}
+
+		fn make_block_import(
+			&self,
+			client: PeersClient,
+		) -> (BlockImportAdapter<Self::BlockImport>, Option<BoxJustificationImport<TestBlock>>, ())
+		{
+			let justification_import = RaftJustificationImport::<
+				_,
+				substrate_test_runtime_client::Backend,
+			>::new(client.as_client());
+			(client.as_block_import(), Some(Box::new(justification_import)), ())
+		}
+
+		fn peer(&mut self, i: usize) -> &mut RaftPeer {
+			&mut self.peers[i]
+		}
+
+		fn peers(&self) -> &Vec<RaftPeer> {
+			&self.peers
+		}
+
+		fn mut_peers<F: FnOnce(&mut Vec<RaftPeer>)>(&mut self, closure: F) {
+			closure(&mut self.peers);
+		}
+	}
+
+	/// Start a Raft worker on each peer, returning the workers and the handles to stop them.
+	fn start_workers(
+		net: &Arc<Mutex<RaftTestNet>>,
+	) -> (Vec<impl Future<Output = ()>>, Vec<AbortHandle>) {
+		let mut net = net.lock();
+		AUTHORITIES
+			.iter()
+			.enumerate()
+			.map(|(peer_id, key)| {
+				let peer = net.peer(peer_id);
+				let client = peer.client().as_client();
+				let keystore: SyncCryptoStorePtr = Arc::new(LocalKeystore::in_memory());
+				SyncCryptoStore::ed25519_generate_new(&*keystore, RAFT, Some(&key.to_seed()))
+					.expect("Creates authority key");
+
+				let worker =
+					start_raft::<_, substrate_test_runtime_client::Backend, _, _, _, _, _, _>(
+						StartRaftParams {
+							client: client.clone(),
+							block_import: client.clone(),
+							proposer_factory: DummyFactory(client),
+							network: peer.network_service().clone(),
+							protocol_name: PROTOCOL_NAME.into(),
+							create_inherent_data_providers: |_, _| async { Ok(()) },
+							keystore,
+							block_duration: Duration::from_millis(200),
+							election_timeouts: ElectionTimeouts {
+								election_timeout_min: Duration::from_millis(500),
+								election_timeout_max: Duration::from_millis(1000),
+								heartbeat_interval: Duration::from_millis(100),
+							},
+							prometheus_registry: None,
+						},
+					)
+					.expect("Starts Raft");
+				let (worker, handle) = future::abortable(worker);
+				(worker.map(|_| ()), handle)
+			})
+			.unzip()
+	}
+
+	/// Wait until the latest block finalized by `client` satisfies `done`, and returns it.
+	async fn finalized_block(client: &PeersFullClient, done: impl Fn(&Header) -> bool) -> Header {
+		let mut notifications = client.finality_notification_stream();
+		loop {
+			let finalized_hash = client.info().finalized_hash;
+			let header = client.header(BlockId::Hash(finalized_hash)).unwrap().unwrap();
+			if done(&header) {
+				return header
+			}
+			notifications.next().await;
+		}
+	}
+
+	/// Checks that `header` was finalized with a valid Raft justification, and returns its term
+	/// and author.
+	fn check_finalized_with_justification(client: &PeersFullClient, header: &Header) -> PreDigest {
+		let hash = header.hash();
+		let encoded = client
+			.justifications(&BlockId::Hash(hash))
+			.unwrap()
+			.and_then(|justifications| justifications.into_justification(RAFT_ENGINE_ID))
+			.expect("Finalized with a Raft justification");
+		let justification = RaftJustification::<H256, u64>::decode(&mut &encoded[..]).unwrap();
+
+		assert_eq!((justification.block_hash, justification.block_number), (hash, header.number));
+		assert_eq!(justification.verify(&authorities()), Ok(()));
+		let pre_digest = find_pre_digest::<TestBlock>(header).unwrap();
+		assert_eq!(justification.term, pre_digest.term);
+		pre_digest
+	}
+
+	#[test]
+	fn authors_finalizes_and_reelects_leader() {
+		sp_tracing::try_init_simple();
+		let net = Arc::new(Mutex::new(RaftTestNet::new(AUTHORITIES.len())));
+		let clients: Vec<_> = (0..AUTHORITIES.len())
+			.map(|i| net.lock().peer(i).client().as_client())
+			.collect();
+		let (workers, handles) = start_workers(&net);
+
+		let scenario = async {
+			// the leader of the first term authors blocks, finalized on every node.
+			let finalized = future::join_all(
+				clients
+					.iter()
+					.map(|client| finalized_block(client, |header| header.number >= 3)),
+			)
+			.await;
+			let leader_digest = clients
+				.iter()
+				.zip(&finalized)
+				.map(|(client, header)| check_finalized_with_justification(client, header))
+				.max_by_key(|pre_digest| pre_digest.term)
+				.unwrap();
+			let leader = leader_digest.authority_index as usize;
+
+			let (client, best_finalized) =
+				clients.iter().zip(&finalized).max_by_key(|(_, header)| header.number).unwrap();
+			let finalized_chain: Vec<_> = (1..=best_finalized.number)
+				.map(|number| (number, client.hash(number).unwrap().unwrap()))
+				.collect();
+
+			// once the leader stops, the others elect a new leader that authors and finalizes
+			// further blocks on top of the finalized ones.
+			handles[leader].abort();
+			let followers: Vec<_> = (0..clients.len()).filter(|i| *i != leader).collect();
+			let finalized = future::join_all(followers.iter().map(|i| {
+				finalized_block(&clients[*i], |header| {
+					find_pre_digest::<TestBlock>(header).unwrap().term > leader_digest.term
+				})
+			}))
+			.await;
+
+			for (i, header) in followers.iter().zip(&finalized) {
+				let client = &clients[*i];
+				let pre_digest = check_finalized_with_justification(client, header);
+				assert_ne!(pre_digest.authority_index as usize, leader);
+				assert!(header.number > best_finalized.number);
+				for (number, hash) in &finalized_chain {
+					assert_eq!(client.hash(*number).unwrap(), Some(*hash));
+				}
+			}
+		};
+
+		executor::block_on(future::select(
+			future::poll_fn(move |cx| {
+				net.lock().poll(cx);
+				Poll::<()>::Pending
+			}),
+			future::select(future::join_all(workers), scenario.boxed_local()),
+		));
+	}
+}
@@ -26,28 +26,36 @@
 #![warn(missing_docs)]
 
 mod aux_schema;
+mod shadow;
 mod slots;
 
 pub use aux_schema::{check_equivocation, MAX_SLOT_CAPACITY, PRUNING_BOUND};
+pub use shadow::{ShadowAuthoring, ShadowProposalOutcome};
 pub use slots::SlotInfo;
 use slots::Slots;
 
+use codec::Encode;
 use futures::{future::Either, Future, TryFutureExt};
 use futures_timer::Delay;
 use log::{debug, info, warn};
-use sc_consensus::{BlockImport, JustificationSyncLink};
+use sc_client_api::backend::AuxStore;
+use sc_consensus::{BlockImport, EquivocationGuard, JustificationSyncLink};
 use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_DEBUG, CONSENSUS_INFO, CONSENSUS_WARN};
 use sp_arithmetic::traits::BaseArithmetic;
-use sp_authority_permission::PermissionResolver;
 use sp_consensus::{CanAuthorWith, Proposal, Proposer, SelectChain, SyncOracle};
-use sp_consensus_slots::{Slot, SlotDuration};
+use sp_consensus_slots::{EquivocationProof, Slot, SlotDuration};
+use sp_core::crypto::KeyTypeId;
 use sp_inherents::CreateInherentDataProviders;
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, HashFor, Header as HeaderT},
 };
 use sp_timestamp::Timestamp;
-use std::{fmt::Debug, ops::Deref, sync::Arc, time::Duration};
+use std::{
+	fmt::Debug,
+	ops::Deref,
+	time::{Duration, Instant},
+};
 
 /// The changes that need to applied to the storage to create the state for a block.
 ///
@@ -135,6 +143,17 @@ pub trait SimpleSlotWorker<B: BlockT> {
 		epoch_data: &Self::EpochData,
 	) -> Option<Self::Claim>;
 
+	/// Returns whether the claim of the given slot may be used to author a block, e.g. depending
+	/// on the [`PermissionResolver`](sp_authority_permission::PermissionResolver) decision for
+	/// the claimed key.
+	///
+	/// This is the only permission check of a slot, made once the slot is claimed: slots whose
+	/// claim isn't permitted are passed to [`Self::on_shadow_slot`]. By default every claim is
+	/// permitted.
+	async fn is_claim_permitted(&self, _slot: Slot, _claim: &Self::Claim) -> bool {
+		true
+	}
+
 	/// Notifies the given slot. Similar to `claim_slot`, but will be called no matter whether we
 	/// need to author blocks or not.
 	fn notify_slot(&self, _header: &B::Header, _slot: Slot, _epoch_data: &Self::EpochData) {}
@@ -169,6 +188,13 @@ pub trait SimpleSlotWorker<B: BlockT> {
 		false
 	}
 
+	/// Returns the [`ShadowAuthoring`] handle if shadow authoring is enabled.
+	///
+	/// By default shadow authoring is disabled.
+	fn shadow_authoring(&self) -> Option<ShadowAuthoring> {
+		None
+	}
+
 	/// Returns a handle to a `SyncOracle`.
 	fn sync_oracle(&mut self) -> &mut Self::SyncOracle;
 
@@ -316,6 +342,12 @@ pub trait SimpleSlotWorker<B: BlockT> {
 
 		let claim = self.claim_slot(&slot_info.chain_head, slot, &epoch_data).await?;
 
+		if !self.is_claim_permitted(slot, &claim).await {
+			debug!(target: logging_target, "Skipping proposal slot due to lack of permission.");
+			self.on_shadow_slot(slot_info, claim).await;
+			return None
+		}
+
 		if self.should_backoff(slot, &slot_info.chain_head) {
 			return None
 		}
@@ -421,6 +453,69 @@ pub trait SimpleSlotWorker<B: BlockT> {
 
 		Some(SlotResult { block: B::new(header, body), storage_proof })
 	}
+
+	/// Called by [`Self::on_slot`] with the claim of a slot the node has no permission to author
+	/// in.
+	///
+	/// If shadow authoring is enabled, a block is built on top of the chain head as it would be
+	/// by [`Self::on_slot`], but it's neither sealed, imported nor announced. The time it took to
+	/// build is reported to the [`ShadowAuthoring`] metrics.
+	async fn on_shadow_slot(&mut self, slot_info: SlotInfo<B>, claim: Self::Claim)
+	where
+		Self: Sync,
+	{
+		let shadow_authoring = match self.shadow_authoring() {
+			Some(shadow_authoring) => shadow_authoring,
+			None => return,
+		};
+		let slot = slot_info.slot;
+		let logging_target = self.logging_target();
+		let proposing_remaining_duration = self.proposing_remaining_duration(&slot_info);
+
+		debug!(target: logging_target, "Starting shadow authorship at slot {}", slot);
+
+		let started = Instant::now();
+		let proposer = match self.proposer(&slot_info.chain_head).await {
+			Ok(p) => p,
+			Err(err) => {
+				debug!(
+					target: logging_target,
+					"Unable to create proposer for shadow block in slot {}: {}", slot, err,
+				);
+				shadow_authoring.report_proposal(ShadowProposalOutcome::Failed, started.elapsed());
+
+				return
+			},
+		};
+
+		// Unlike `Self::propose`, the deadline given to the proposer is extended by a whole slot, so
+		// that a block production running late isn't cut short and its lateness can be measured
+		// against the proposing time.
+		let logs = self.pre_digest_data(slot, &claim);
+		let proposal = proposer
+			.propose(
+				slot_info.inherent_data,
+				sp_runtime::generic::Digest { logs },
+				proposing_remaining_duration + slot_info.duration,
+				None,
+			)
+			.await;
+		let duration = started.elapsed();
+
+		let outcome = match proposal {
+			Ok(_) if duration <= proposing_remaining_duration => ShadowProposalOutcome::InTime,
+			Ok(_) => ShadowProposalOutcome::TooLate,
+			Err(err) => {
+				debug!(target: logging_target, "Shadow proposing failed: {}", err);
+				ShadowProposalOutcome::Failed
+			},
+		};
+		debug!(
+			target: logging_target,
+			"Discarding shadow block for slot {} built in {:?} ({:?})", slot, duration, outcome,
+		);
+		shadow_authoring.report_proposal(outcome, duration);
+	}
 }
 
 /// A type that implements [`SlotWorker`] for a type that implements [`SimpleSlotWorker`].
@@ -486,7 +581,8 @@ impl_inherent_data_provider_ext_tuple!(T, S, A, B, C, D, E, F, G, H, I, J);
 /// Start a new slot worker.
 ///
 /// Every time a new slot is triggered, `worker.on_slot` is called and the future it returns is
-/// polled until completion, unless we are major syncing.
+/// polled until completion, unless we are major syncing. The permission to author in the slot is
+/// checked by the worker, once it claimed the slot, see [`SimpleSlotWorker::is_claim_permitted`].
 pub async fn start_slot_worker<B, C, W, SO, CIDP, CAW, Proof>(
 	slot_duration: SlotDuration,
 	client: C,
@@ -494,11 +590,10 @@ pub async fn start_slot_worker<B, C, W, SO, CIDP, CAW, Proof>(
 	sync_oracle: SO,
 	create_inherent_data_providers: CIDP,
 	can_author_with: CAW,
-	permission_resolver: Arc<dyn PermissionResolver>,
 ) where
 	B: BlockT,
 	C: SelectChain<B>,
-	W: SlotWorker<B, Proof>,
+	W: SlotWorker<B, Proof> + Send,
 	SO: SyncOracle + Send,
 	CIDP: CreateInherentDataProviders<B, ()> + Send,
 	CIDP::InherentDataProviders: InherentDataProviderExt + Send,
@@ -520,12 +615,6 @@ pub async fn start_slot_worker<B, C, W, SO, CIDP, CAW, Proof>(
 			continue
 		}
 
-		let has_permission = permission_resolver.resolve_slot(slot_info.slot).await;
-		if !has_permission {
-			debug!(target: "slots", "Skipping proposal slot due to lack of permission.");
-			continue
-		}
-
 		if let Err(err) =
 			can_author_with.can_author_with(&BlockId::Hash(slot_info.chain_head.hash()))
 		{
@@ -542,6 +631,67 @@ pub async fn start_slot_worker<B, C, W, SO, CIDP, CAW, Proof>(
 	}
 }
 
+/// Report the equivocation of `proof` to `guard` if the offender is a key of type `key_type` in
+/// the keystore of this node.
+///
+/// Returns whether the equivocation was reported.
+pub fn report_self_equivocation<H: HeaderT, P: Encode>(
+	guard: &EquivocationGuard,
+	engine: &'static str,
+	key_type: KeyTypeId,
+	proof: &EquivocationProof<H, P>,
+) -> bool {
+	let offender = proof.offender.encode();
+	if !guard.is_local_key(key_type, &offender) {
+		return false
+	}
+
+	guard.report(
+		engine,
+		&offender,
+		&format!(
+			"slot {} was authored with headers {:?} and {:?}",
+			proof.slot,
+			proof.first_header.hash(),
+			proof.second_header.hash(),
+		),
+	);
+	true
+}
+
+/// Check that `header`, authored by this node with `signer` of type `key_type` in `slot`, doesn't
+/// equivocate with a header seen before for the same slot and signer.
+///
+/// The header is recorded, so that headers imported later on for the same slot and signer are
+/// detected as equivocations by [`check_equivocation`]. An error is returned if the header
+/// equivocates, as importing it would make the equivocation public.
+pub fn check_authored_equivocation<C, H, P>(
+	guard: &EquivocationGuard,
+	engine: &'static str,
+	key_type: KeyTypeId,
+	backend: &C,
+	slot: Slot,
+	header: &H,
+	signer: &P,
+) -> Result<(), sp_consensus::Error>
+where
+	C: AuxStore,
+	H: HeaderT,
+	P: Clone + Encode + codec::Decode + PartialEq,
+{
+	match check_equivocation(backend, slot, slot, header, signer) {
+		Ok(Some(proof)) => {
+			report_self_equivocation(guard, engine, key_type, &proof);
+			Err(sp_consensus::Error::CannotSign(
+				signer.encode(),
+				format!("another header was already authored at slot {}", slot),
+			))
+		},
+		Ok(None) => Ok(()),
+		Err(err) => Err(sp_consensus::Error::ClientImport(err.to_string())),
+	}
+}
+
 /// A header which has been checked
 pub enum CheckedHeader<H, S> {
 	/// A header which has slot in the future. this is the full header (not stripped)
@@ -822,7 +972,10 @@ impl<N> BackoffAuthoringBlocksStrategy<N> for () {
 mod test {
 	use super::*;
 	use sp_runtime::traits::NumberFor;
-	use std::time::{Duration, Instant};
+	use std::{
+		sync::Arc,
+		time::{Duration, Instant},
+	};
 	use substrate_test_runtime_client::runtime::{Block, Header};
 
 	const SLOT_DURATION: Duration = Duration::from_millis(6000);
@@ -1252,4 +1405,88 @@ mod test {
 		assert_eq!((block_for_max_interval, time_to_reach_limit), expected);
 		assert_eq!((block_for_max_interval, time_to_reach_limit), (250, 60906));
 	}
+
+	#[derive(Default)]
+	struct InMemoryAux(parking_lot::Mutex<std::collections::HashMap<Vec<u8>, Vec<u8>>>);
+
+	impl AuxStore for InMemoryAux {
+		fn insert_aux<
+			'a,
+			'b: 'a,
+			'c: 'a,
+			I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+			D: IntoIterator<Item = &'a &'b [u8]>,
+		>(
+			&self,
+			insert: I,
+			delete: D,
+		) -> sp_blockchain::Result<()> {
+			let mut aux = self.0.lock();
+			aux.extend(insert.into_iter().map(|(k, v)| (k.to_vec(), v.to_vec())));
+			delete.into_iter().for_each(|k| {
+				aux.remove(*k);
+			});
+			Ok(())
+		}
+
+		fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+			Ok(self.0.lock().get(key).cloned())
+		}
+	}
+
+	#[test]
+	fn authored_equivocations_are_detected_in_both_orders() {
+		use sp_core::crypto::key_types::AURA;
+		use sp_keystore::{testing::KeyStore, SyncCryptoStore};
+
+		let client = InMemoryAux::default();
+		let keystore = Arc::new(KeyStore::new());
+		let local_key = SyncCryptoStore::sr25519_generate_new(&*keystore, AURA, None).unwrap();
+		let guard = EquivocationGuard::new(true, keystore, None, None).unwrap();
+		let header = |number| {
+			Header::new(
+				number,
+				Default::default(),
+				Default::default(),
+				Default::default(),
+				Default::default(),
+			)
+		};
+
+		// The node authors a block in slot 1, then a header for the same slot and key is imported.
+		assert!(check_authored_equivocation(
+			&guard,
+			"test",
+			AURA,
+			&client,
+			1.into(),
+			&header(1),
+			&local_key
+		)
+		.is_ok());
+		let proof = check_equivocation(&client, 1.into(), 1.into(), &header(2), &local_key)
+			.unwrap()
+			.unwrap();
+		assert!(report_self_equivocation(&guard, "test", AURA, &proof));
+		assert!(guard.is_tripped(&local_key.encode()));
+
+		// A header for slot 2 is imported, then the node authors another block in slot 2.
+		assert!(check_equivocation(&client, 2.into(), 2.into(), &header(3), &local_key)
+			.unwrap()
+			.is_none());
+		assert!(check_authored_equivocation(
+			&guard,
+			"test",
+			AURA,
+			&client,
+			2.into(),
+			&header(4),
+			&local_key
+		)
+		.is_err());
+
+		// Equivocations of keys that are not in the keystore are not reported.
+		let proof = EquivocationProof { offender: sp_core::sr25519::Public::from_raw([2; 32]), ..proof };
+		assert!(!report_self_equivocation(&guard, "test", AURA, &proof));
+	}
 }
@@ -0,0 +1,107 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Shadow authoring of blocks in slots the node has no permission to author in.
+//!
+//! A standby node that is denied a slot by its
+//! [`PermissionResolver`](sp_authority_permission::PermissionResolver) can still claim the slot
+//! and build a candidate block, without sealing, importing or announcing it. This keeps the
+//! proposer and the transaction pool warm, so the first permitted slot after a failover doesn't
+//! pay their full startup cost, and tells through the metrics whether the standby would have
+//! produced its blocks in time.
+
+use std::time::Duration;
+
+use prometheus_endpoint::{
+	register, CounterVec, Histogram, HistogramOpts, Opts, PrometheusError, Registry, U64,
+};
+
+/// The outcome of building a shadow block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowProposalOutcome {
+	/// The block was built within the proposing time of the slot.
+	InTime,
+	/// Building the block took longer than the proposing time of the slot.
+	TooLate,
+	/// Creating the proposer or building the block failed.
+	Failed,
+}
+
+impl ShadowProposalOutcome {
+	fn as_str(&self) -> &'static str {
+		match self {
+			ShadowProposalOutcome::InTime => "in_time",
+			ShadowProposalOutcome::TooLate => "too_late",
+			ShadowProposalOutcome::Failed => "failed",
+		}
+	}
+}
+
+#[derive(Clone)]
+struct Metrics {
+	proposals: CounterVec<U64>,
+	proposal_time: Histogram,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			proposals: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_shadow_authoring_proposals_total",
+						"Blocks built by shadow authoring in slots without authoring permission",
+					),
+					&["outcome"],
+				)?,
+				registry,
+			)?,
+			proposal_time: register(
+				Histogram::with_opts(HistogramOpts::new(
+					"substrate_shadow_authoring_proposal_time",
+					"Time taken to create the proposer and build a shadow block",
+				))?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// Enables shadow authoring in a [`SimpleSlotWorker`](crate::SimpleSlotWorker) and records its
+/// metrics.
+#[derive(Clone)]
+pub struct ShadowAuthoring {
+	metrics: Option<Metrics>,
+}
+
+impl ShadowAuthoring {
+	/// Create a new `ShadowAuthoring`, registering its metrics in `registry` if given.
+	pub fn new(registry: Option<&Registry>) -> Result<Self, PrometheusError> {
+		Ok(Self { metrics: registry.map(Metrics::register).transpose()? })
+	}
+
+	/// Report a shadow block that took `duration` to build.
+	pub fn report_proposal(&self, outcome: ShadowProposalOutcome, duration: Duration) {
+		if let Some(metrics) = &self.metrics {
+			metrics.proposals.with_label_values(&[outcome.as_str()]).inc();
+			if outcome != ShadowProposalOutcome::Failed {
+				metrics.proposal_time.observe(duration.as_secs_f64());
+			}
+		}
+	}
+}
@@ -33,6 +33,7 @@ pub mod offchain;
 pub mod bench;
 
 mod children;
+mod migration;
 mod parity_db;
 mod record_stats_state;
 mod stats;
@@ -94,6 +95,7 @@ pub use sc_state_db::PruningMode;
 pub use sp_database::Database;
 
 pub use bench::BenchmarkingState;
+pub use migration::{migrate_database, MigrationSummary};
 
 const CACHE_HEADERS: usize = 8;
 
@@ -0,0 +1,1047 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Migration of a database between backends.
+//!
+//! Every column of the source database is streamed through [`sp_database::Database`] into a
+//! freshly created database next to the target location. Backends that keep reference counts
+//! natively (ParityDb) store state and transaction preimages under their plain hash, while the
+//! others (RocksDb) prefix state keys and keep counters in separate entries, so those columns
+//! are converted on the way:
+//!
+//! - Towards native reference counting, the prefixes are dropped from the state keys and the
+//!   counters are turned into as many references.
+//! - The other way, the trie node prefixes are restored by walking the state tries of the blocks
+//!   whose state is kept, and the counters of the indexed transactions are restored by counting
+//!   the block bodies referencing them. ParityDb only stores the hash of the keys of most
+//!   columns, so only the ParityDb databases created with a key index can be migrated.
+//!
+//! Once every entry has been verified, the new database is synced to disk and marked complete,
+//! moved in place, and only then is the source database moved aside. If the migration is
+//! interrupted before the new database is complete, it is discarded on the next run. If it is
+//! interrupted while the databases are swapped, running the migration again finishes the swap.
+
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	marker::PhantomData,
+	path::{Path, PathBuf},
+};
+
+use codec::{Decode, Encode};
+use log::{info, warn};
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::{
+	hexdisplay::HexDisplay,
+	storage::{well_known_keys, ChildInfo},
+};
+use sp_database::{ColumnId, Transaction};
+use sp_runtime::{
+	traits::{Block as BlockT, Hash as HashT, HashFor, Header as HeaderT},
+	SaturatedConversion,
+};
+use sp_state_machine::DBValue;
+use sp_trie::NodeCodec;
+use trie_db::{
+	node::{Node, NodeHandle, Value},
+	NibbleVec, NodeCodec as _,
+};
+
+use crate::{
+	columns,
+	utils::{self, DatabaseType, NUM_COLUMNS},
+	Database, DatabaseSource, DbExtrinsic, DbHash, DB_HASH_LEN,
+};
+
+/// Suffix of the directory the target database is built in before it is moved in place.
+const MIGRATING_SUFFIX: &str = "migrating";
+/// Suffix the source database directory is renamed to once migration is complete.
+const BACKUP_SUFFIX: &str = "pre-migration";
+/// File marking the new database as complete until the source database is moved aside. It holds
+/// the number of migrated entries.
+const COMPLETE_MARKER: &str = "MIGRATION_COMPLETE";
+/// Flush the pending transaction once it holds this many bytes.
+const MAX_TRANSACTION_SIZE: usize = 64 * 1024 * 1024;
+
+/// Summary of a completed database migration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationSummary {
+	/// Number of entries read from the source database for each column.
+	pub entries: Vec<(ColumnId, u64)>,
+	/// Location the source database was moved to.
+	pub backup_path: PathBuf,
+}
+
+/// Migrate the database at `source` into a new database at `target`.
+///
+/// `target` must not exist yet, unless a previous migration was interrupted while swapping the
+/// databases, in which case the swap is finished. The source database is kept as a backup, its
+/// new location is returned in the [`MigrationSummary`].
+pub fn migrate_database<Block: BlockT>(
+	source: &DatabaseSource,
+	target: &DatabaseSource,
+) -> ClientResult<MigrationSummary> {
+	let source_path = disk_path(source)?;
+	let target_path = disk_path(target)?;
+	let migrating_path = with_suffix(&target_path, MIGRATING_SUFFIX);
+	let backup_path = with_suffix(&source_path, BACKUP_SUFFIX);
+
+	if target_path.join(COMPLETE_MARKER).exists() {
+		info!(target: "db", "Finishing the interrupted migration to {:?}", target_path);
+		return swap_databases(&source_path, &target_path, &migrating_path, &backup_path)
+	}
+	if target_path.exists() {
+		return Err(backend_err(format!("Target database {:?} already exists", target_path)))
+	}
+	if migrating_path.join(COMPLETE_MARKER).exists() {
+		info!(target: "db", "Finishing the interrupted migration to {:?}", target_path);
+		return swap_databases(&source_path, &target_path, &migrating_path, &backup_path)
+	}
+	if backup_path.exists() {
+		return Err(backend_err(format!("Backup location {:?} already exists", backup_path)))
+	}
+	if !source_path.exists() {
+		return Err(backend_err(format!("No database to migrate found at {:?}", source_path)))
+	}
+	if migrating_path.exists() {
+		info!(target: "db", "Removing leftovers of a previous migration at {:?}", migrating_path);
+		fs::remove_dir_all(&migrating_path).map_err(io_err)?;
+	}
+
+	let entries = {
+		let source_db = utils::open_database::<Block>(source, DatabaseType::Full, false)?;
+		// Fail early, before anything is written, if the source can't be enumerated.
+		source_db
+			.iter(columns::META, &mut |_, _| ())
+			.map_err(|e| backend_err(format!("Source database can not be migrated: {}", e)))?;
+
+		let mut migrating = target.clone();
+		migrating.set_path(&migrating_path);
+		let target_db = utils::open_database::<Block>(&migrating, DatabaseType::Full, true)?;
+
+		let conversions = conversions(&*source_db, &*target_db);
+		let migrated =
+			copy_columns::<Block>(&*source_db, &*target_db, &conversions).and_then(|entries| {
+				verify_columns::<Block>(&*source_db, &*target_db, &conversions)?;
+				Ok(entries)
+			});
+		if migrated.is_err() {
+			drop(target_db);
+			let _ = fs::remove_dir_all(&migrating_path);
+		}
+		migrated?
+	};
+
+	// The target database is closed, make sure all of it is on disk before it's marked complete.
+	sync_dir(&migrating_path)?;
+	write_synced(&migrating_path.join(COMPLETE_MARKER), &entries.encode())?;
+	sync_dir(&migrating_path)?;
+
+	swap_databases(&source_path, &target_path, &migrating_path, &backup_path)
+}
+
+/// Move the complete database at `migrating_path` to `target_path`, and then the source database
+/// aside. Each step is synced to disk before the next one, so the swap can be resumed at any
+/// point: there is always a complete database at either location.
+fn swap_databases(
+	source_path: &Path,
+	target_path: &Path,
+	migrating_path: &Path,
+	backup_path: &Path,
+) -> ClientResult<MigrationSummary> {
+	if migrating_path.exists() {
+		if let Some(parent) = target_path.parent() {
+			fs::create_dir_all(parent).map_err(io_err)?;
+		}
+		fs::rename(migrating_path, target_path).map_err(io_err)?;
+		sync_parent(target_path)?;
+	}
+	if source_path.exists() {
+		fs::rename(source_path, backup_path).map_err(io_err)?;
+		sync_parent(backup_path)?;
+	}
+
+	let marker = target_path.join(COMPLETE_MARKER);
+	let entries = fs::read(&marker).map_err(io_err)?;
+	let entries = Vec::<(ColumnId, u64)>::decode(&mut &entries[..])
+		.map_err(|e| backend_err(format!("Invalid migration marker {:?}: {}", marker, e)))?;
+	fs::remove_file(&marker).map_err(io_err)?;
+	sync_dir(target_path)?;
+	info!(
+		target: "db",
+		"Database migrated to {:?}, the source database was moved to {:?}",
+		target_path,
+		backup_path,
+	);
+
+	Ok(MigrationSummary { entries, backup_path: backup_path.to_path_buf() })
+}
+
+/// Sync every file of the directory at `path`, and the directory itself.
+fn sync_dir(path: &Path) -> ClientResult<()> {
+	for entry in fs::read_dir(path).map_err(io_err)? {
+		let path = entry.map_err(io_err)?.path();
+		if path.is_dir() {
+			sync_dir(&path)?;
+		} else {
+			fs::File::open(&path).and_then(|file| file.sync_all()).map_err(io_err)?;
+		}
+	}
+	fs::File::open(path).and_then(|dir| dir.sync_all()).map_err(io_err)
+}
+
+/// Sync the directory containing `path`, so that a rename to `path` is persisted.
+fn sync_parent(path: &Path) -> ClientResult<()> {
+	match path.parent() {
+		Some(parent) if !parent.as_os_str().is_empty() =>
+			fs::File::open(parent).and_then(|dir| dir.sync_all()).map_err(io_err),
+		_ => Ok(()),
+	}
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> ClientResult<()> {
+	use std::io::Write;
+
+	let mut file = fs::File::create(path).map_err(io_err)?;
+	file.write_all(data).and_then(|_| file.sync_all()).map_err(io_err)
+}
+
+/// How entries of a column are written to the target database.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Conversion {
+	/// Entries are copied as is.
+	Copy,
+	/// Prefixed trie node keys are replaced with their hash.
+	State,
+	/// State-db journals referencing prefixed trie node keys.
+	StateMeta,
+	/// Counter entries are turned into native reference counts.
+	RefCounted,
+	/// Trie nodes stored under their hash are stored under their prefixed keys.
+	PrefixedState,
+	/// State-db journals referencing trie nodes by hash.
+	PrefixedStateMeta,
+	/// Native reference counts are turned into counter entries.
+	Counted,
+}
+
+/// The conversion of every column.
+fn conversions(source: &dyn Database<DbHash>, target: &dyn Database<DbHash>) -> Vec<Conversion> {
+	(0..NUM_COLUMNS)
+		.map(|col| match (source.supports_ref_counting(), target.supports_ref_counting()) {
+			(false, true) => match col {
+				columns::STATE => Conversion::State,
+				columns::STATE_META => Conversion::StateMeta,
+				columns::TRANSACTION => Conversion::RefCounted,
+				_ => Conversion::Copy,
+			},
+			(true, false) => match col {
+				columns::STATE => Conversion::PrefixedState,
+				columns::STATE_META => Conversion::PrefixedStateMeta,
+				columns::TRANSACTION => Conversion::Counted,
+				_ => Conversion::Copy,
+			},
+			_ => Conversion::Copy,
+		})
+		.collect()
+}
+
+/// Key of the reference counter kept next to `key` by backends without native ref counting.
+fn counter_key(key: &[u8]) -> Vec<u8> {
+	let mut counter_key = key.to_vec();
+	counter_key.push(0);
+	counter_key
+}
+
+fn is_counter_key(key: &[u8]) -> bool {
+	key.len() == DB_HASH_LEN + 1 && key.last() == Some(&0)
+}
+
+fn read_counter(db: &dyn Database<DbHash>, col: ColumnId, key: &[u8]) -> ClientResult<u32> {
+	match db.get(col, &counter_key(key)) {
+		Some(data) => {
+			let data: [u8; 4] = data.as_slice().try_into().map_err(|_| {
+				backend_err(format!("Unexpected counter len {} in column {}", data.len(), col))
+			})?;
+			Ok(u32::from_le_bytes(data))
+		},
+		None => Ok(1),
+	}
+}
+
+/// Number of references to each indexed transaction. Backends with native ref counting don't
+/// expose them, but every reference is held by a block body.
+fn index_references<Block: BlockT>(
+	source: &dyn Database<DbHash>,
+) -> ClientResult<HashMap<Vec<u8>, u32>> {
+	let mut references = HashMap::new();
+	let mut result = Ok(());
+	source
+		.iter(columns::BODY_INDEX, &mut |key, index| {
+			let index = match Vec::<DbExtrinsic<Block>>::decode(&mut &index[..]) {
+				Ok(index) => index,
+				Err(e) => {
+					let key = HexDisplay::from(&key);
+					result = Err(backend_err(format!("Error decoding body index 0x{}: {}", key, e)));
+					return
+				},
+			};
+			for extrinsic in index {
+				if let DbExtrinsic::Indexed { hash, .. } = extrinsic {
+					*references.entry(hash.as_ref().to_vec()).or_insert(0) += 1;
+				}
+			}
+		})
+		.map_err(|e| backend_err(e.to_string()))?;
+	result.map(|_| references)
+}
+
+/// Transaction that is committed once it grows too large or touches a key twice. Ref counted
+/// columns count every insertion, so repeated keys must not be merged into one commit.
+struct Batch<'a> {
+	db: &'a dyn Database<DbHash>,
+	transaction: Transaction<DbHash>,
+	keys: HashSet<(ColumnId, Vec<u8>)>,
+	size: usize,
+}
+
+impl<'a> Batch<'a> {
+	fn new(db: &'a dyn Database<DbHash>) -> Self {
+		Batch { db, transaction: Transaction::new(), keys: HashSet::new(), size: 0 }
+	}
+
+	fn set(&mut self, col: ColumnId, key: Vec<u8>, value: Vec<u8>) -> ClientResult<()> {
+		if self.size >= MAX_TRANSACTION_SIZE || self.keys.contains(&(col, key.clone())) {
+			self.flush()?;
+		}
+		self.size += key.len() + value.len();
+		self.transaction.set_from_vec(col, &key, value);
+		self.keys.insert((col, key));
+		Ok(())
+	}
+
+	/// Whether `key` was written, committed or not.
+	fn contains(&self, col: ColumnId, key: &[u8]) -> bool {
+		self.keys.contains(&(col, key.to_vec())) || self.db.contains(col, key)
+	}
+
+	fn flush(&mut self) -> ClientResult<()> {
+		self.keys.clear();
+		self.size = 0;
+		self.db
+			.commit(std::mem::take(&mut self.transaction))
+			.map_err(|e| backend_err(e.to_string()))
+	}
+}
+
+/// Restores the prefixed keys of the trie nodes of a database with native ref counting, which
+/// stores them under their hash only, by walking the state tries of the blocks whose state is
+/// kept.
+///
+/// The nodes of the blocks that are not canonicalized yet are only kept in the state-db journals,
+/// which reference nodes by hash. The keys these nodes are found under are recorded, to rewrite
+/// the journals.
+struct StatePrefixes<'a, Block: BlockT> {
+	source: &'a dyn Database<DbHash>,
+	/// Nodes inserted by the non-canonical blocks.
+	non_canonical: HashMap<Vec<u8>, DBValue>,
+	/// Prefixed keys of the nodes referenced by the journals, with the highest number of the
+	/// blocks whose state contains them.
+	journal_keys: HashMap<Vec<u8>, Vec<(Vec<u8>, u64)>>,
+	/// Prefixed keys of the non-canonical nodes walked so far.
+	walked: HashSet<Vec<u8>>,
+	/// Number of nodes written.
+	written: u64,
+	_phantom: PhantomData<Block>,
+}
+
+impl<'a, Block: BlockT> StatePrefixes<'a, Block> {
+	fn new(source: &'a dyn Database<DbHash>) -> ClientResult<Self> {
+		let mut non_canonical = HashMap::new();
+		let mut journal_keys = HashMap::new();
+		let mut result = Ok(());
+		source
+			.iter(columns::STATE_META, &mut |key, value| {
+				if result.is_err() {
+					return
+				}
+				result = sc_state_db::map_journal_keys::<Block::Hash>(key, value.to_vec(), |key| {
+					journal_keys.entry(key.key.to_vec()).or_insert_with(Vec::new);
+					Vec::new()
+				})
+				.and_then(|_| sc_state_db::journal_inserted_nodes::<Block::Hash>(key, value))
+				.map(|nodes| {
+					// Nodes inserted more than once are repeated with an empty value.
+					non_canonical.extend(nodes.into_iter().filter(|(_, node)| !node.is_empty()))
+				})
+				.map_err(|e| backend_err(format!("Error decoding state-db journal: {}", e)));
+			})
+			.map_err(|e| backend_err(e.to_string()))?;
+		result?;
+
+		Ok(StatePrefixes {
+			source,
+			non_canonical,
+			journal_keys,
+			walked: HashSet::new(),
+			written: 0,
+			_phantom: PhantomData,
+		})
+	}
+
+	/// Write the nodes of every state kept by the source database to `batch`, and return their
+	/// number.
+	///
+	/// The states are walked from the highest block down, and the nodes already written are
+	/// skipped along with their children, so that the first block a node is found in is the
+	/// highest one containing it.
+	fn walk_states(&mut self, batch: &mut Batch) -> ClientResult<u64> {
+		let mut roots = Vec::new();
+		let mut result = Ok(());
+		self.source
+			.iter(columns::HEADER, &mut |_, header| match Block::Header::decode(&mut &header[..]) {
+				Ok(header) =>
+					roots.push(((*header.number()).saturated_into::<u64>(), *header.state_root())),
+				Err(e) => result = Err(backend_err(format!("Error decoding header: {}", e))),
+			})
+			.map_err(|e| backend_err(e.to_string()))?;
+		result?;
+		roots.sort_by_key(|(number, _)| std::cmp::Reverse(*number));
+
+		let mut walked_roots = HashSet::new();
+		for (number, root) in roots {
+			// The states of the pruned blocks, and the empty states, are not stored.
+			if !walked_roots.insert(root) || self.node(root.as_ref()).is_none() {
+				continue
+			}
+			self.walk_node(batch, &[], &mut NibbleVec::new(), root.as_ref(), number)?;
+		}
+		Ok(self.written)
+	}
+
+	fn node(&self, hash: &[u8]) -> Option<DBValue> {
+		self.source
+			.get(columns::STATE, hash)
+			.or_else(|| self.non_canonical.get(hash).cloned())
+	}
+
+	fn walk_node(
+		&mut self,
+		batch: &mut Batch,
+		keyspace: &[u8],
+		path: &mut NibbleVec,
+		hash: &[u8],
+		number: u64,
+	) -> ClientResult<()> {
+		match self.visit(batch, keyspace, path.as_prefix(), hash, number)? {
+			Some(node) => self.walk_encoded(batch, keyspace, path, &node, number),
+			None => Ok(()),
+		}
+	}
+
+	fn walk_encoded(
+		&mut self,
+		batch: &mut Batch,
+		keyspace: &[u8],
+		path: &mut NibbleVec,
+		node: &[u8],
+		number: u64,
+	) -> ClientResult<()> {
+		let depth = path.len();
+		match NodeCodec::<HashFor<Block>>::decode(node)
+			.map_err(|e| backend_err(format!("Error decoding trie node: {}", e)))?
+		{
+			Node::Empty => {},
+			Node::Leaf(partial, value) => {
+				path.append_partial(partial.right());
+				self.walk_value(batch, keyspace, path, value, number)?;
+			},
+			Node::NibbledBranch(partial, children, value) => {
+				path.append_partial(partial.right());
+				if let Some(value) = value {
+					self.walk_value(batch, keyspace, path, value, number)?;
+				}
+				for (index, child) in children.iter().enumerate() {
+					path.push(index as u8);
+					match child {
+						Some(NodeHandle::Hash(hash)) =>
+							self.walk_node(batch, keyspace, path, hash, number)?,
+						Some(NodeHandle::Inline(node)) =>
+							self.walk_encoded(batch, keyspace, path, node, number)?,
+						None => {},
+					}
+					path.pop();
+				}
+			},
+			Node::Extension(..) | Node::Branch(..) =>
+				return Err(backend_err("Unexpected trie node layout".into())),
+		}
+		path.drop_lasts(path.len() - depth);
+		Ok(())
+	}
+
+	/// Walk the value at the end of `path`: its node if it is not inlined, and the child trie it is
+	/// the root of.
+	fn walk_value(
+		&mut self,
+		batch: &mut Batch,
+		keyspace: &[u8],
+		path: &NibbleVec,
+		value: Value,
+		number: u64,
+	) -> ClientResult<()> {
+		let key = path.as_prefix().0;
+		let value = match value {
+			Value::Inline(value) => value.to_vec(),
+			Value::Node(hash) => {
+				// Values are stored under the full key of their leaf.
+				self.visit(batch, keyspace, (key, None), hash, number)?;
+				match self.node(hash) {
+					Some(value) => value,
+					None => return Ok(()),
+				}
+			},
+		};
+
+		let child_prefix = well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX;
+		if keyspace.is_empty() && key.starts_with(child_prefix) {
+			let child_info = ChildInfo::new_default(&key[child_prefix.len()..]);
+			self.walk_node(batch, child_info.keyspace(), &mut NibbleVec::new(), &value, number)?;
+		}
+		Ok(())
+	}
+
+	/// Write the node found under `hash` at `prefix` if it is canonical, and return it if it was
+	/// not walked yet.
+	fn visit(
+		&mut self,
+		batch: &mut Batch,
+		keyspace: &[u8],
+		prefix: (&[u8], Option<u8>),
+		hash: &[u8],
+		number: u64,
+	) -> ClientResult<Option<DBValue>> {
+		let mut key = keyspace.to_vec();
+		key.extend_from_slice(prefix.0);
+		key.extend(prefix.1);
+		key.extend_from_slice(hash);
+
+		if let Some(keys) = self.journal_keys.get_mut(hash) {
+			if !keys.iter().any(|(journal_key, _)| *journal_key == key) {
+				keys.push((key.clone(), number));
+			}
+		}
+
+		match self.source.get(columns::STATE, hash) {
+			Some(_) if batch.contains(columns::STATE, &key) => Ok(None),
+			Some(node) => {
+				batch.set(columns::STATE, key, node.clone())?;
+				self.written += 1;
+				Ok(Some(node))
+			},
+			None => match self.non_canonical.get(hash) {
+				Some(node) => Ok(self.walked.insert(key).then(|| node.clone())),
+				None => Err(backend_err(format!(
+					"Missing trie node 0x{} in the source database",
+					HexDisplay::from(&key),
+				))),
+			},
+		}
+	}
+
+	/// Rewrite the node hashes of a state-db journal with their prefixed keys. The nodes deleted by
+	/// a block are the ones that are not part of its state, or of any later state.
+	fn map_journal(&self, meta_key: &[u8], value: Vec<u8>) -> ClientResult<Vec<u8>> {
+		// Nodes referenced more than once by a record are repeated, databases without native ref
+		// counting store them once.
+		let mut mapped = HashSet::new();
+		let mut dropped = 0;
+		let value = sc_state_db::map_journal_keys::<Block::Hash>(meta_key, value, |key| {
+			if !mapped.insert((key.key.to_vec(), key.inserted)) {
+				return Vec::new()
+			}
+			let keys: Vec<_> = self
+				.journal_keys
+				.get(key.key)
+				.into_iter()
+				.flatten()
+				.filter(|(_, highest)| key.inserted || *highest < key.block)
+				.map(|(prefixed, _)| prefixed.clone())
+				.collect();
+			if keys.is_empty() {
+				dropped += 1;
+			}
+			keys
+		})
+		.map_err(|e| backend_err(format!("Error decoding state-db journal: {}", e)))?;
+		if dropped > 0 {
+			warn!(
+				target: "db",
+				"{} nodes of state-db journal 0x{} are not part of any state and were dropped",
+				dropped,
+				HexDisplay::from(&meta_key),
+			);
+		}
+		Ok(value)
+	}
+}
+
+fn copy_columns<Block: BlockT>(
+	source: &dyn Database<DbHash>,
+	target: &dyn Database<DbHash>,
+	conversions: &[Conversion],
+) -> ClientResult<Vec<(ColumnId, u64)>> {
+	let mut entries = Vec::new();
+	let mut batch = Batch::new(target);
+	let mut state_prefixes = None;
+	let mut references = HashMap::new();
+	for col in 0..NUM_COLUMNS {
+		let conversion = conversions[col as usize];
+		let mut count = 0u64;
+		match conversion {
+			Conversion::PrefixedState => {
+				let mut prefixes = StatePrefixes::<Block>::new(source)?;
+				count = prefixes.walk_states(&mut batch)?;
+				state_prefixes = Some(prefixes);
+			},
+			Conversion::Counted => references = index_references::<Block>(source)?,
+			_ => {},
+		}
+		let mut result = Ok(());
+		if conversion != Conversion::PrefixedState {
+			source
+				.iter(col, &mut |key, value| {
+					if result.is_err() {
+						return
+					}
+					count += 1;
+					result = match conversion {
+						Conversion::Copy => batch.set(col, key.to_vec(), value.to_vec()),
+						Conversion::State => {
+							let mut key = key.to_vec();
+							target.sanitize_key(&mut key);
+							batch.set(col, key, value.to_vec())
+						},
+						Conversion::StateMeta =>
+							state_meta_value::<Block>(target, key, value.to_vec())
+								.and_then(|value| batch.set(col, key.to_vec(), value)),
+						Conversion::RefCounted if is_counter_key(key) => Ok(()),
+						Conversion::RefCounted => read_counter(source, col, key).and_then(|rc| {
+							(0..rc).try_for_each(|_| batch.set(col, key.to_vec(), value.to_vec()))
+						}),
+						Conversion::PrefixedStateMeta => state_prefixes
+							.as_ref()
+							.expect("the state column is converted before its meta column; qed")
+							.map_journal(key, value.to_vec())
+							.and_then(|value| batch.set(col, key.to_vec(), value)),
+						Conversion::Counted => {
+							let rc = references.get(key).copied().unwrap_or(1);
+							batch.set(col, key.to_vec(), value.to_vec()).and_then(|_| {
+								batch.set(col, counter_key(key), rc.to_le_bytes().to_vec())
+							})
+						},
+						Conversion::PrefixedState => Ok(()),
+					};
+				})
+				.map_err(|e| backend_err(e.to_string()))?;
+		}
+		result?;
+		batch.flush()?;
+		if count > 0 {
+			info!(target: "db", "Migrated column {}: {} entries", col, count);
+		}
+		entries.push((col, count));
+	}
+	Ok(entries)
+}
+
+fn verify_columns<Block: BlockT>(
+	source: &dyn Database<DbHash>,
+	target: &dyn Database<DbHash>,
+	conversions: &[Conversion],
+) -> ClientResult<()> {
+	for col in 0..NUM_COLUMNS {
+		let conversion = conversions[col as usize];
+		let mut result = Ok(());
+		if conversion == Conversion::PrefixedState {
+			// Every node written must be the one stored under its hash by the source.
+			target
+				.iter(col, &mut |key, value| {
+					let hash = &key[key.len().saturating_sub(DB_HASH_LEN)..];
+					if result.is_ok() &&
+						(HashFor::<Block>::hash(value).as_ref() != hash ||
+							source.get(col, hash).as_ref().map(|v| &v[..]) != Some(value))
+					{
+						result = Err(mismatch(col, key));
+					}
+				})
+				.map_err(|e| backend_err(e.to_string()))?;
+			result?;
+			continue
+		}
+		source
+			.iter(col, &mut |key, value| {
+				if result.is_err() {
+					return
+				}
+				let (key, expected) = match conversion {
+					Conversion::Copy | Conversion::RefCounted | Conversion::Counted =>
+						(key.to_vec(), value.to_vec()),
+					Conversion::State => {
+						let mut key = key.to_vec();
+						target.sanitize_key(&mut key);
+						(key, value.to_vec())
+					},
+					Conversion::StateMeta =>
+						match state_meta_value::<Block>(target, key, value.to_vec()) {
+							Ok(value) => (key.to_vec(), value),
+							Err(e) => {
+								result = Err(e);
+								return
+							},
+						},
+					// The node keys of the journals depend on the states walked, only check
+					// that every entry was migrated.
+					Conversion::PrefixedStateMeta | Conversion::PrefixedState => {
+						if !target.contains(col, key) {
+							result = Err(mismatch(col, key));
+						}
+						return
+					},
+				};
+				if conversion == Conversion::RefCounted && is_counter_key(&key) {
+					return
+				}
+				if target.get(col, &key).as_ref() != Some(&expected) {
+					result = Err(mismatch(col, &key));
+				}
+			})
+			.map_err(|e| backend_err(e.to_string()))?;
+		result?;
+	}
+	info!(target: "db", "Verified migrated database");
+	Ok(())
+}
+
+fn state_meta_value<Block: BlockT>(
+	target: &dyn Database<DbHash>,
+	key: &[u8],
+	value: Vec<u8>,
+) -> ClientResult<Vec<u8>> {
+	sc_state_db::map_journal_keys::<Block::Hash>(key, value, |key| {
+		let mut key = key.key.to_vec();
+		target.sanitize_key(&mut key);
+		vec![key]
+	})
+	.map_err(|e| backend_err(format!("Error decoding state-db journal: {}", e)))
+}
+
+fn mismatch(col: ColumnId, key: &[u8]) -> ClientError {
+	backend_err(format!(
+		"Verification failed: entry 0x{} in column {} does not match the source",
+		HexDisplay::from(&key),
+		col,
+	))
+}
+
+fn disk_path(source: &DatabaseSource) -> ClientResult<PathBuf> {
+	match source {
+		DatabaseSource::Auto { .. } | DatabaseSource::Custom { .. } =>
+			Err(backend_err("Database migration requires an explicit on-disk backend".into())),
+		_ => Ok(source.path().expect("on-disk backends have a path; qed").to_path_buf()),
+	}
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut name = path.as_os_str().to_owned();
+	name.push(".");
+	name.push(suffix);
+	name.into()
+}
+
+fn backend_err(msg: String) -> ClientError {
+	ClientError::Backend(msg)
+}
+
+fn io_err(e: std::io::Error) -> ClientError {
+	ClientError::Backend(format!("IO error during database migration: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Backend, BlocksPruning, DatabaseSettings, PruningMode};
+	use sc_client_api::{
+		backend::{Backend as _, BlockImportOperation as _, NewBlockState},
+		blockchain::Backend as _,
+	};
+	use sp_core::{
+		storage::{Storage, StorageChild},
+		H256,
+	};
+	use sp_database::MemDb;
+	use sp_runtime::{
+		generic::BlockId,
+		testing::{Block as RawBlock, ExtrinsicWrapper, Header},
+		StateVersion,
+	};
+	use sp_state_machine::{Backend as _, IndexOperation};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	#[test]
+	fn converts_prefixed_and_counted_entries() {
+		let dir = tempfile::tempdir().unwrap();
+		let target: std::sync::Arc<dyn Database<DbHash>> =
+			crate::parity_db::open(dir.path(), DatabaseType::Full, true, false).unwrap();
+
+		// Lay out the source the way a database without native ref counting does.
+		let source = MemDb::new();
+		let node = b"trie node".to_vec();
+		let node_hash = sp_core::blake2_256(&node);
+		let mut prefixed_node_key = b"prefix".to_vec();
+		prefixed_node_key.extend_from_slice(&node_hash);
+		let extrinsic = b"indexed extrinsic".to_vec();
+		let extrinsic_hash = DbHash::from(sp_core::blake2_256(&extrinsic));
+		let mut tx = Transaction::new();
+		tx.set(columns::AUX, b"aux", b"value");
+		tx.set(columns::STATE, &prefixed_node_key, &node);
+		tx.set(columns::TRANSACTION, extrinsic_hash.as_ref(), &extrinsic);
+		tx.set(columns::TRANSACTION, &counter_key(extrinsic_hash.as_ref()), &2u32.to_le_bytes());
+		Database::<DbHash>::commit(&source, tx).unwrap();
+
+		let conversions = conversions(&source, &*target);
+		let entries = copy_columns::<Block>(&source, &*target, &conversions).unwrap();
+		verify_columns::<Block>(&source, &*target, &conversions).unwrap();
+
+		assert!(entries.contains(&(columns::TRANSACTION, 2)));
+		assert_eq!(target.get(columns::AUX, b"aux"), Some(b"value".to_vec()));
+		assert_eq!(target.get(columns::STATE, &node_hash), Some(node));
+
+		// Both references to the extrinsic have been migrated.
+		let mut tx = Transaction::new();
+		tx.release(columns::TRANSACTION, extrinsic_hash);
+		target.commit(tx).unwrap();
+		assert_eq!(target.get(columns::TRANSACTION, extrinsic_hash.as_ref()), Some(extrinsic));
+	}
+
+	#[test]
+	fn enumerates_indexed_paritydb_columns() {
+		let dir = tempfile::tempdir().unwrap();
+		let open = || -> std::sync::Arc<dyn Database<DbHash>> {
+			crate::parity_db::open(dir.path(), DatabaseType::Full, true, false).unwrap()
+		};
+		let node = b"trie node".to_vec();
+		let node_hash = DbHash::from(sp_core::blake2_256(&node));
+		let mut tx = Transaction::new();
+		tx.store(columns::STATE, node_hash, node.clone());
+		tx.set(columns::AUX, b"aux", b"value");
+		tx.set(columns::AUX, b"removed", b"value");
+		open().commit(tx).unwrap();
+		let mut tx = Transaction::new();
+		tx.remove(columns::AUX, b"removed");
+		open().commit(tx).unwrap();
+
+		// The entries can be enumerated once the commits are written to the tables, which is
+		// done when reopening.
+		let source = open();
+		let mut state = Vec::new();
+		source
+			.iter(columns::STATE, &mut |key, value| state.push((key.to_vec(), value.to_vec())))
+			.unwrap();
+		assert_eq!(state, vec![(node_hash.as_ref().to_vec(), node)]);
+		let mut aux = Vec::new();
+		source
+			.iter(columns::AUX, &mut |key, value| aux.push((key.to_vec(), value.to_vec())))
+			.unwrap();
+		assert_eq!(aux, vec![(b"aux".to_vec(), b"value".to_vec())]);
+	}
+
+	#[test]
+	fn restores_prefixed_and_counted_entries() {
+		let dir = tempfile::tempdir().unwrap();
+		let settings = |source| DatabaseSettings {
+			trie_cache_maximum_size: None,
+			state_pruning: Some(PruningMode::blocks_pruning(10)),
+			source,
+			blocks_pruning: BlocksPruning::Some(10),
+		};
+		let child_info = ChildInfo::new_default(b"child");
+		let large_value = vec![42u8; 64];
+		let extrinsic = ExtrinsicWrapper::from(0u64).encode();
+		let extrinsic_hash = <HashFor<Block> as sp_core::Hasher>::hash(&extrinsic[1..]);
+
+		let (block0, block1) = {
+			let source = DatabaseSource::ParityDb { path: dir.path().join("paritydb") };
+			// Block 1 is not canonicalized, its nodes are only found in the state-db journals.
+			let backend = Backend::<Block>::new(settings(source), 1).unwrap();
+
+			let mut op = backend.begin_operation().unwrap();
+			let storage = Storage {
+				top: vec![(b"key".to_vec(), b"value".to_vec()), (b"large".to_vec(), large_value)]
+					.into_iter()
+					.collect(),
+				children_default: vec![(
+					child_info.storage_key().to_vec(),
+					StorageChild {
+						data: vec![(b"child key".to_vec(), b"child value".to_vec())]
+							.into_iter()
+							.collect(),
+						child_info: child_info.clone(),
+					},
+				)]
+				.into_iter()
+				.collect(),
+			};
+			let header = Header {
+				number: 0,
+				parent_hash: Default::default(),
+				state_root: op.reset_storage(storage, StateVersion::V1).unwrap(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			let block0 = header.hash();
+			op.set_block_data(header, Some(vec![0u64.into()]), None, None, NewBlockState::Best)
+				.unwrap();
+			op.update_transaction_index(vec![IndexOperation::Insert {
+				extrinsic: 0,
+				hash: extrinsic_hash.as_ref().to_vec(),
+				size: (extrinsic.len() - 1) as u32,
+			}])
+			.unwrap();
+			backend.commit_operation(op).unwrap();
+
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(block0)).unwrap();
+			let changes = vec![(b"key".to_vec(), Some(b"new value".to_vec()))];
+			let (state_root, overlay) = op.old_state.storage_root(
+				changes.iter().map(|(k, v)| (k.as_slice(), v.as_ref().map(|v| &v[..]))),
+				StateVersion::V1,
+			);
+			op.update_db_storage(overlay).unwrap();
+			op.update_storage(changes, Vec::new()).unwrap();
+			let header = Header {
+				number: 1,
+				parent_hash: block0,
+				state_root,
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+			let block1 = header.hash();
+			op.set_block_data(header, Some(vec![0u64.into()]), None, None, NewBlockState::Best)
+				.unwrap();
+			op.update_transaction_index(vec![IndexOperation::Renew {
+				extrinsic: 0,
+				hash: extrinsic_hash.as_ref().to_vec(),
+			}])
+			.unwrap();
+			backend.commit_operation(op).unwrap();
+			(block0, block1)
+		};
+
+		let source =
+			crate::parity_db::open(&dir.path().join("paritydb"), DatabaseType::Full, false, false)
+				.unwrap();
+		let target = sp_database::as_database(kvdb_memorydb::create(NUM_COLUMNS));
+		let conversions = conversions(&*source, &*target);
+		copy_columns::<Block>(&*source, &*target, &conversions).unwrap();
+		verify_columns::<Block>(&*source, &*target, &conversions).unwrap();
+		drop(source);
+
+		// Both references to the indexed transaction are counted.
+		assert_eq!(
+			target.get(columns::TRANSACTION, &counter_key(extrinsic_hash.as_ref())),
+			Some(2u32.to_le_bytes().to_vec()),
+		);
+
+		let source = DatabaseSource::Custom { db: target, require_create_flag: false };
+		let backend = Backend::<Block>::new(settings(source), 1).unwrap();
+		let state = backend.state_at(BlockId::Hash(block0)).unwrap();
+		assert_eq!(state.storage(b"key").unwrap(), Some(b"value".to_vec()));
+		assert_eq!(state.storage(b"large").unwrap(), Some(vec![42u8; 64]));
+		assert_eq!(
+			state.child_storage(&child_info, b"child key").unwrap(),
+			Some(b"child value".to_vec()),
+		);
+		let state = backend.state_at(BlockId::Hash(block1)).unwrap();
+		assert_eq!(state.storage(b"key").unwrap(), Some(b"new value".to_vec()));
+		assert_eq!(
+			state.child_storage(&child_info, b"child key").unwrap(),
+			Some(b"child value".to_vec()),
+		);
+		assert_eq!(
+			backend.blockchain().indexed_transaction(&extrinsic_hash).unwrap(),
+			Some(extrinsic[1..].to_vec()),
+		);
+
+		// The journal of the non-canonical block references the prefixed node keys, so the
+		// nodes it inserted are written when it is canonicalized.
+		backend.finalize_block(BlockId::Hash(block1), None).unwrap();
+		let state = backend.state_at(BlockId::Hash(block1)).unwrap();
+		assert_eq!(state.storage(b"key").unwrap(), Some(b"new value".to_vec()));
+	}
+
+	#[test]
+	fn refuses_existing_target() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = DatabaseSource::ParityDb { path: dir.path().join("db") };
+		let target = DatabaseSource::ParityDb { path: dir.path().join("paritydb") };
+		fs::create_dir_all(dir.path().join("db")).unwrap();
+		fs::create_dir_all(dir.path().join("paritydb")).unwrap();
+
+		assert!(migrate_database::<Block>(&source, &target).is_err());
+	}
+
+	#[test]
+	fn finishes_interrupted_swap() {
+		let dir = tempfile::tempdir().unwrap();
+		let source_path = dir.path().join("db");
+		let target_path = dir.path().join("paritydb");
+		let source = DatabaseSource::ParityDb { path: source_path.clone() };
+		let target = DatabaseSource::ParityDb { path: target_path.clone() };
+		let migrating_path = with_suffix(&target_path, MIGRATING_SUFFIX);
+		let backup_path = with_suffix(&source_path, BACKUP_SUFFIX);
+		let entries = vec![(columns::META, 1u64)];
+
+		// Interrupted once the new database was complete, before it was moved in place.
+		fs::create_dir_all(&source_path).unwrap();
+		fs::create_dir_all(&migrating_path).unwrap();
+		fs::write(migrating_path.join(COMPLETE_MARKER), entries.encode()).unwrap();
+		let summary = migrate_database::<Block>(&source, &target).unwrap();
+		assert_eq!(summary, MigrationSummary { entries: entries.clone(), backup_path });
+		assert!(target_path.exists() && !migrating_path.exists());
+		assert!(!source_path.exists() && summary.backup_path.exists());
+		assert!(!target_path.join(COMPLETE_MARKER).exists());
+
+		// Interrupted once the new database was moved in place, before the source was moved.
+		fs::remove_dir_all(&summary.backup_path).unwrap();
+		fs::rename(&target_path, &source_path).unwrap();
+		fs::create_dir_all(&target_path).unwrap();
+		fs::write(target_path.join(COMPLETE_MARKER), entries.encode()).unwrap();
+		assert_eq!(migrate_database::<Block>(&source, &target).unwrap(), summary);
+		assert!(!source_path.exists() && summary.backup_path.exists());
+
+		// The database being built when interrupted is discarded.
+		fs::create_dir_all(&migrating_path).unwrap();
+		fs::create_dir_all(&source_path).unwrap();
+		fs::remove_dir_all(&summary.backup_path).unwrap();
+		fs::remove_dir_all(&target_path).unwrap();
+		assert!(migrate_database::<Block>(&source, &target).is_err());
+		assert!(!migrating_path.exists());
+	}
+}
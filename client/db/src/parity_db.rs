@@ -22,7 +22,17 @@ use crate::{
 /// A `Database` adapter for parity-db.
 use sp_database::{error::DatabaseError, Change, ColumnId, Database, Transaction};
 
-struct DbAdapter(parity_db::Db);
+/// Column of the databases created with key indexing, holding the keys of the other columns.
+///
+/// ParityDb only stores the hash of the keys of the columns that are not uniform, so they can
+/// only be enumerated through this index.
+const KEY_INDEX: u8 = NUM_COLUMNS as u8;
+
+struct DbAdapter {
+	db: parity_db::Db,
+	/// Whether the keys of the columns that are not uniform are indexed in [`KEY_INDEX`].
+	indexed: bool,
+}
 
 fn handle_err<T>(result: parity_db::Result<T>) -> T {
 	match result {
@@ -40,7 +50,16 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 	create: bool,
 	upgrade: bool,
 ) -> parity_db::Result<std::sync::Arc<dyn Database<H>>> {
-	let mut config = parity_db::Options::with_columns(path, NUM_COLUMNS as u8);
+	// The key index is added to new databases only: the keys of an existing database can't be
+	// indexed after the fact.
+	let indexed = match parity_db::Options::load_metadata(path)? {
+		Some(meta) => meta.columns.len() > NUM_COLUMNS as usize,
+		None => create,
+	};
+	let mut config = parity_db::Options::with_columns(path, NUM_COLUMNS as u8 + indexed as u8);
+	if indexed {
+		config.columns[KEY_INDEX as usize].uniform = true;
+	}
 
 	match db_type {
 		DatabaseType::Full => {
@@ -83,17 +102,38 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 		parity_db::Db::open(&config)?
 	};
 
-	Ok(std::sync::Arc::new(DbAdapter(db)))
+	Ok(std::sync::Arc::new(DbAdapter { db, indexed }))
 }
 
 fn ref_counted_column(col: u32) -> bool {
 	col == columns::TRANSACTION || col == columns::STATE
 }
 
+/// Entry of the key index for `key` of `col`, and the key it is stored under. Keys of the index
+/// must be uniformly distributed.
+fn index_entry(col: ColumnId, key: &[u8]) -> (Vec<u8>, Vec<u8>) {
+	let mut entry = vec![col as u8];
+	entry.extend_from_slice(key);
+	(sp_core::blake2_256(&entry).to_vec(), entry)
+}
+
 impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 	fn commit(&self, transaction: Transaction<H>) -> Result<(), DatabaseError> {
 		let mut not_ref_counted_column = Vec::new();
-		let result = self.0.commit(transaction.0.into_iter().filter_map(|change| {
+		let index_changes: Vec<_> = transaction
+			.0
+			.iter()
+			.filter_map(|change| match change {
+				Change::Set(col, key, _) | Change::Remove(col, key)
+					if self.indexed && !ref_counted_column(*col) =>
+				{
+					let (index_key, entry) = index_entry(*col, key);
+					Some((KEY_INDEX, index_key, matches!(change, Change::Set(..)).then(|| entry)))
+				},
+				_ => None,
+			})
+			.collect();
+		let changes = transaction.0.into_iter().filter_map(|change| {
 			Some(match change {
 				Change::Set(col, key, value) => (col as u8, key, Some(value)),
 				Change::Remove(col, key) => (col as u8, key, None),
@@ -128,7 +168,8 @@ impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 						return None
 					},
 			})
-		}));
+		});
+		let result = self.db.commit(changes.chain(index_changes));
 
 		if not_ref_counted_column.len() > 0 {
 			return Err(DatabaseError(Box::new(parity_db::Error::InvalidInput(format!(
@@ -141,15 +182,15 @@ impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 	}
 
 	fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
-		handle_err(self.0.get(col as u8, key))
+		handle_err(self.db.get(col as u8, key))
 	}
 
 	fn contains(&self, col: ColumnId, key: &[u8]) -> bool {
-		handle_err(self.0.get_size(col as u8, key)).is_some()
+		handle_err(self.db.get_size(col as u8, key)).is_some()
 	}
 
 	fn value_size(&self, col: ColumnId, key: &[u8]) -> Option<usize> {
-		handle_err(self.0.get_size(col as u8, key)).map(|s| s as usize)
+		handle_err(self.db.get_size(col as u8, key)).map(|s| s as usize)
 	}
 
 	fn supports_ref_counting(&self) -> bool {
@@ -159,4 +200,41 @@ impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 	fn sanitize_key(&self, key: &mut Vec<u8>) {
 		let _prefix = key.drain(0..key.len() - crate::DB_HASH_LEN);
 	}
+
+	fn iter(&self, col: ColumnId, f: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), DatabaseError> {
+		// The ref counted columns are the uniform ones, whose keys are stored as is. The other
+		// columns only store a salted hash of their keys, which are found in the key index.
+		if ref_counted_column(col) {
+			return self
+				.db
+				.iter_column_while(col as u8, |state| {
+					f(&state.key, &state.value);
+					true
+				})
+				.map_err(|e| DatabaseError(Box::new(e)))
+		}
+		if !self.indexed {
+			return Err(DatabaseError(Box::new(parity_db::Error::InvalidInput(format!(
+				"Column {} only stores the hash of its keys and the database was created without \
+				key index, its entries can not be enumerated",
+				col
+			)))))
+		}
+		let mut keys = Vec::new();
+		self.db
+			.iter_column_while(KEY_INDEX, |state| {
+				if state.value.first() == Some(&(col as u8)) {
+					keys.push(state.value[1..].to_vec());
+				}
+				true
+			})
+			.map_err(|e| DatabaseError(Box::new(e)))?;
+		for key in keys {
+			let value = self.db.get(col as u8, &key).map_err(|e| DatabaseError(Box::new(e)))?;
+			if let Some(value) = value {
+				f(&key, &value);
+			}
+		}
+		Ok(())
+	}
 }
@@ -79,6 +79,7 @@ fn initialize(
 					canonicalize_nans: false,
 					parallel_compilation: true,
 					max_memory_size: None,
+					fuel_metering: false,
 				},
 			};
 
@@ -107,6 +107,9 @@ pub enum Error {
 
 	#[error("Execution aborted due to trap: {0}")]
 	AbortedDueToTrap(MessageWithBacktrace),
+
+	#[error("Execution ran out of fuel after consuming the limit of {0} units")]
+	OutOfFuel(u64),
 }
 
 impl wasmi::HostError for Error {}
@@ -109,6 +109,12 @@ pub trait WasmInstance: Send {
 	/// This method is only suitable for getting immutable globals.
 	fn get_global_const(&mut self, name: &str) -> Result<Option<Value>, Error>;
 
+	/// Limit the amount of fuel the following calls on this instance may consume.
+	///
+	/// A call that runs out of fuel fails with [`Error::OutOfFuel`]. `None` removes the limit.
+	/// Backends without fuel metering ignore the limit.
+	fn set_fuel_limit(&mut self, _limit: Option<u64>) {}
+
 	/// **Testing Only**. This function returns the base address of the linear memory.
 	///
 	/// This is meant to be the starting address of the memory mapped area for the linear memory.
@@ -600,6 +600,7 @@ fn mk_test_runtime(wasm_method: WasmExecutionMethod, pages: u64) -> Arc<dyn Wasm
 		blob,
 		true,
 		None,
+		false,
 	)
 	.expect("failed to instantiate wasm runtime")
 }
@@ -860,6 +861,7 @@ fn memory_is_cleared_between_invocations(wasm_method: WasmExecutionMethod) {
 		RuntimeBlob::uncompress_if_needed(&binary[..]).unwrap(),
 		true,
 		None,
+		false,
 	)
 	.unwrap();
 
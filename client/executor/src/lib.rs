@@ -46,7 +46,7 @@ pub use sp_core::traits::Externalities;
 pub use sp_version::{NativeVersion, RuntimeVersion};
 #[doc(hidden)]
 pub use sp_wasm_interface;
-pub use wasm_runtime::{read_embedded_version, WasmExecutionMethod};
+pub use wasm_runtime::{find_runtime_code, read_embedded_version, WasmExecutionMethod};
 pub use wasmi;
 
 pub use sc_executor_common::{error, sandbox};
@@ -54,6 +54,15 @@ pub use sc_executor_common::{error, sandbox};
 #[cfg(feature = "wasmtime")]
 pub use sc_executor_wasmtime::InstantiationStrategy as WasmtimeInstantiationStrategy;
 
+sp_externalities::decl_extension! {
+	/// The amount of fuel a call into the runtime may consume.
+	///
+	/// The limit is only enforced by runtimes compiled with fuel metering, see
+	/// [`WasmExecutor::with_fuel_metering`]. A call exceeding it fails with
+	/// [`Error::OutOfFuel`](error::Error::OutOfFuel).
+	pub struct FuelLimitExt(u64);
+}
+
 /// Extracts the runtime version of a given runtime code.
 pub trait RuntimeVersionOf {
 	/// Extract [`RuntimeVersion`](sp_version::RuntimeVersion) of the given `runtime_code`.
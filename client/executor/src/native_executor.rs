@@ -165,6 +165,16 @@ where
 		self.allow_missing_host_functions = allow_missing_host_functions
 	}
 
+	/// Compile runtimes with fuel metering if `fuel_metering` is `true`.
+	///
+	/// The fuel that a call into such a runtime may consume is limited by registering a
+	/// [`FuelLimitExt`](crate::FuelLimitExt) for the call. Metering slows down every call and is
+	/// only supported by the compiled execution method. Runtimes compiled before are dropped.
+	pub fn with_fuel_metering(mut self, fuel_metering: bool) -> Self {
+		self.cache = Arc::new(self.cache.with_fuel_metering(fuel_metering));
+		self
+	}
+
 	/// Execute the given closure `f` with the latest runtime (based on `runtime_code`).
 	///
 	/// The closure `f` is expected to return `Err(_)` when there happened a `panic!` in native code
@@ -273,6 +283,7 @@ where
 			runtime_blob,
 			allow_missing_host_functions,
 			self.cache_path.as_deref(),
+			false,
 		)
 		.map_err(|e| format!("Failed to create module: {}", e))?;
 
@@ -428,6 +439,18 @@ impl<D: NativeExecutionDispatch> NativeElseWasmExecutor<D> {
 			runtime_cache_size,
 		);
 
+		Self::new_with_wasm_executor(wasm)
+	}
+
+	/// Create a new instance using the given [`WasmExecutor`] to execute the fallback Wasm code.
+	///
+	/// This allows configuring the fallback executor further, for instance to cache compiled
+	/// runtimes on disk.
+	pub fn new_with_wasm_executor(
+		wasm: WasmExecutor<
+			ExtendedHostFunctions<sp_io::SubstrateHostFunctions, D::ExtendHostFunctions>,
+		>,
+	) -> Self {
 		NativeElseWasmExecutor {
 			_dummy: Default::default(),
 			native_version: D::native_version(),
@@ -20,9 +20,15 @@
 //!
 //! The primary means of accessing the runtimes is through a cache which saves the reusable
 //! components of the runtime that are expensive to initialize.
+//!
+//! When a cache directory is configured, runtimes compiled with wasmtime are also stored on disk,
+//! keyed by their code hash, so that they don't need to be compiled again after a restart.
 
-use crate::error::{Error, WasmError};
-use codec::Decode;
+use crate::{
+	error::{Error, WasmError},
+	FuelLimitExt,
+};
+use codec::{Compact, Decode, DecodeAll};
 use lru::LruCache;
 use parking_lot::Mutex;
 use sc_executor_common::{
@@ -30,6 +36,7 @@ use sc_executor_common::{
 	wasm_runtime::{WasmInstance, WasmModule},
 };
 use sp_core::traits::{Externalities, FetchRuntimeCode, RuntimeCode};
+use sp_externalities::ExternalitiesExt;
 use sp_version::RuntimeVersion;
 use std::{
 	panic::AssertUnwindSafe,
@@ -39,6 +46,22 @@ use std::{
 
 use sp_wasm_interface::HostFunctions;
 
+/// Magic number and version that wasm code starts with.
+const WASM_PREFIX: &[u8] = b"\0asm\x01\0\0\0";
+
+/// Directory of the cache path in which the compiled runtimes are stored.
+#[cfg(feature = "wasmtime")]
+const COMPILED_RUNTIMES_DIR: &str = "runtimes";
+
+/// Maximum number of compiled runtimes kept in [`COMPILED_RUNTIMES_DIR`].
+#[cfg(feature = "wasmtime")]
+const MAX_COMPILED_RUNTIMES: usize = 16;
+
+/// Age after which the temporary files of [`COMPILED_RUNTIMES_DIR`] are considered left behind
+/// by an interrupted compilation.
+#[cfg(feature = "wasmtime")]
+const STALE_TEMP_FILE_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
 /// Specification of different methods of executing the runtime Wasm code.
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum WasmExecutionMethod {
@@ -92,6 +115,11 @@ impl VersionedRuntime {
 			&mut dyn Externalities,
 		) -> Result<R, Error>,
 	{
+		let fuel_limit = {
+			let mut ext: &mut dyn Externalities = &mut *ext;
+			ext.extension::<FuelLimitExt>().map(|limit| limit.0)
+		};
+
 		// Find a free instance
 		let instance = self
 			.instances
@@ -105,6 +133,7 @@ impl VersionedRuntime {
 					.take()
 					.map(|r| Ok((r, false)))
 					.unwrap_or_else(|| self.module.new_instance().map(|i| (i, true)))?;
+				instance.set_fuel_limit(fuel_limit);
 
 				let result = f(&self.module, &mut *instance, self.version.as_ref(), ext);
 				if let Err(e) = &result {
@@ -141,6 +170,7 @@ impl VersionedRuntime {
 
 				// Allocate a new instance
 				let mut instance = self.module.new_instance()?;
+				instance.set_fuel_limit(fuel_limit);
 
 				f(&self.module, &mut *instance, self.version.as_ref(), ext)
 			},
@@ -167,6 +197,8 @@ pub struct RuntimeCache {
 	/// The size of the instances cache for each runtime.
 	max_runtime_instances: usize,
 	cache_path: Option<PathBuf>,
+	/// Compile runtimes with fuel metering.
+	fuel_metering: bool,
 }
 
 impl RuntimeCache {
@@ -189,6 +221,21 @@ impl RuntimeCache {
 			runtimes: Mutex::new(LruCache::new(runtime_cache_size.into())),
 			max_runtime_instances,
 			cache_path,
+			fuel_metering: false,
+		}
+	}
+
+	/// Returns an empty cache with the same settings, which compiles runtimes with fuel metering
+	/// if `fuel_metering` is `true`.
+	///
+	/// The fuel that calls into runtimes compiled with fuel metering may consume is limited by
+	/// registering [`FuelLimitExt`]. Fuel metering is only supported by wasmtime.
+	pub fn with_fuel_metering(&self, fuel_metering: bool) -> RuntimeCache {
+		RuntimeCache {
+			runtimes: Mutex::new(LruCache::new(self.runtimes.lock().cap())),
+			max_runtime_instances: self.max_runtime_instances,
+			cache_path: self.cache_path.clone(),
+			fuel_metering,
 		}
 	}
 
@@ -249,15 +296,16 @@ impl RuntimeCache {
 
 			let time = std::time::Instant::now();
 
-			let result = create_versioned_wasm_runtime::<H>(
-				&code,
-				ext,
-				wasm_method,
-				heap_pages,
-				allow_missing_func_imports,
-				self.max_runtime_instances,
-				self.cache_path.as_deref(),
-			);
+			let result =
+				create_versioned_wasm_runtime(&code, ext, self.max_runtime_instances, |blob| {
+					self.create_runtime::<H>(
+						blob,
+						code_hash,
+						wasm_method,
+						heap_pages,
+						allow_missing_func_imports,
+					)
+				});
 
 			match result {
 				Ok(ref result) => {
@@ -286,26 +334,73 @@ impl RuntimeCache {
 
 		Ok(versioned_runtime.with_instance(ext, f))
 	}
+
+	/// Create a runtime from `blob`, which is stored on disk once compiled if the cache has a
+	/// `cache_path`.
+	fn create_runtime<H>(
+		&self,
+		blob: RuntimeBlob,
+		code_hash: &[u8],
+		wasm_method: WasmExecutionMethod,
+		heap_pages: u64,
+		allow_missing_func_imports: bool,
+	) -> Result<Arc<dyn WasmModule>, WasmError>
+	where
+		H: HostFunctions,
+	{
+		let artifact_path = self.cache_path.as_deref().and_then(|cache_path| {
+			compiled_runtime_path(
+				cache_path,
+				code_hash,
+				wasm_method,
+				heap_pages,
+				self.fuel_metering,
+			)
+		});
+		match (wasm_method, artifact_path) {
+			#[cfg(feature = "wasmtime")]
+			(WasmExecutionMethod::Compiled { instantiation_strategy }, Some(artifact_path)) =>
+				create_compiled_runtime_with_cache::<H>(
+					blob,
+					&artifact_path,
+					heap_pages,
+					instantiation_strategy,
+					allow_missing_func_imports,
+					self.fuel_metering,
+				),
+			_ => create_wasm_runtime_with_code::<H>(
+				wasm_method,
+				heap_pages,
+				blob,
+				allow_missing_func_imports,
+				None,
+				self.fuel_metering,
+			),
+		}
+	}
 }
 
 /// Create a wasm runtime with the given `code`.
+///
+/// `fuel_metering` is ignored by the interpreted execution method.
 pub fn create_wasm_runtime_with_code<H>(
 	wasm_method: WasmExecutionMethod,
 	heap_pages: u64,
 	blob: RuntimeBlob,
 	allow_missing_func_imports: bool,
 	cache_path: Option<&Path>,
+	fuel_metering: bool,
 ) -> Result<Arc<dyn WasmModule>, WasmError>
 where
 	H: HostFunctions,
 {
 	match wasm_method {
 		WasmExecutionMethod::Interpreted => {
-			// Wasmi doesn't have any need in a cache directory.
+			// Wasmi doesn't have any need in a cache directory and doesn't support fuel metering.
 			//
-			// We drop the cache_path here to silence warnings that cache_path is not used if
-			// compiling without the `wasmtime` flag.
-			let _ = cache_path;
+			// We drop the cache_path and fuel_metering here to silence warnings that they are not
+			// used if compiling without the `wasmtime` flag.
+			let _ = (cache_path, fuel_metering);
 
 			sc_executor_wasmi::create_runtime(
 				blob,
@@ -319,23 +414,198 @@ where
 		WasmExecutionMethod::Compiled { instantiation_strategy } =>
 			sc_executor_wasmtime::create_runtime::<H>(
 				blob,
-				sc_executor_wasmtime::Config {
+				wasmtime_config(
+					heap_pages,
+					instantiation_strategy,
 					allow_missing_func_imports,
-					cache_path: cache_path.map(ToOwned::to_owned),
-					semantics: sc_executor_wasmtime::Semantics {
-						extra_heap_pages: heap_pages,
-						instantiation_strategy,
-						deterministic_stack_limit: None,
-						canonicalize_nans: false,
-						parallel_compilation: true,
-						max_memory_size: None,
-					},
-				},
+					cache_path,
+					fuel_metering,
+				),
 			)
 			.map(|runtime| -> Arc<dyn WasmModule> { Arc::new(runtime) }),
 	}
 }
 
+#[cfg(feature = "wasmtime")]
+fn wasmtime_config(
+	heap_pages: u64,
+	instantiation_strategy: sc_executor_wasmtime::InstantiationStrategy,
+	allow_missing_func_imports: bool,
+	cache_path: Option<&Path>,
+	fuel_metering: bool,
+) -> sc_executor_wasmtime::Config {
+	sc_executor_wasmtime::Config {
+		allow_missing_func_imports,
+		cache_path: cache_path.map(ToOwned::to_owned),
+		semantics: sc_executor_wasmtime::Semantics {
+			extra_heap_pages: heap_pages,
+			instantiation_strategy,
+			deterministic_stack_limit: None,
+			canonicalize_nans: false,
+			parallel_compilation: true,
+			max_memory_size: None,
+			fuel_metering,
+		},
+	}
+}
+
+/// Returns the path in `cache_path` of the compiled artifact of the runtime with the given
+/// `code_hash`, or `None` if runtimes of `wasm_method` are not compiled.
+fn compiled_runtime_path(
+	cache_path: &Path,
+	code_hash: &[u8],
+	wasm_method: WasmExecutionMethod,
+	heap_pages: u64,
+	fuel_metering: bool,
+) -> Option<PathBuf> {
+	match wasm_method {
+		WasmExecutionMethod::Interpreted => {
+			let _ = (cache_path, code_hash, heap_pages, fuel_metering);
+			None
+		},
+		#[cfg(feature = "wasmtime")]
+		WasmExecutionMethod::Compiled { instantiation_strategy } =>
+			Some(cache_path.join(COMPILED_RUNTIMES_DIR).join(format!(
+				"{}-{}-{:?}{}.bin",
+				sp_core::hexdisplay::HexDisplay::from(&code_hash),
+				heap_pages,
+				instantiation_strategy,
+				if fuel_metering { "-metered" } else { "" },
+			))),
+	}
+}
+
+/// Create a wasmtime runtime from the compiled artifact at `path` if any. Otherwise the runtime
+/// is compiled and the artifact is stored at `path` for the next time.
+#[cfg(feature = "wasmtime")]
+fn create_compiled_runtime_with_cache<H>(
+	blob: RuntimeBlob,
+	path: &Path,
+	heap_pages: u64,
+	instantiation_strategy: sc_executor_wasmtime::InstantiationStrategy,
+	allow_missing_func_imports: bool,
+	fuel_metering: bool,
+) -> Result<Arc<dyn WasmModule>, WasmError>
+where
+	H: HostFunctions,
+{
+	let config = || {
+		// The artifact replaces the cache of wasmtime.
+		wasmtime_config(
+			heap_pages,
+			instantiation_strategy,
+			allow_missing_func_imports,
+			None,
+			fuel_metering,
+		)
+	};
+
+	if path.exists() {
+		// SAFETY: artifacts are produced by `prepare_runtime_artifact` and written to a uniquely
+		// named temporary file that is then renamed, so an existing artifact is never modified,
+		// even by concurrent compilations of the same runtime. Artifacts
+		// produced by another version of wasmtime are rejected with an error.
+		match unsafe { sc_executor_wasmtime::create_runtime_from_artifact::<H>(path, config()) } {
+			Ok(runtime) => return Ok(Arc::new(runtime)),
+			Err(error) => tracing::debug!(
+				target: "wasm-runtime",
+				?error,
+				"Cannot load compiled runtime from {}, compiling it again",
+				path.display(),
+			),
+		}
+	}
+
+	let artifact =
+		sc_executor_wasmtime::prepare_runtime_artifact(blob.clone(), &config().semantics)?;
+	match store_compiled_runtime(path, &artifact) {
+		// SAFETY: see above.
+		Ok(()) => unsafe {
+			sc_executor_wasmtime::create_runtime_from_artifact::<H>(path, config())
+		},
+		Err(error) => {
+			tracing::warn!(
+				target: "wasm-runtime",
+				%error,
+				"Cannot store compiled runtime at {}",
+				path.display(),
+			);
+			sc_executor_wasmtime::create_runtime::<H>(blob, config())
+		},
+	}
+	.map(|runtime| -> Arc<dyn WasmModule> { Arc::new(runtime) })
+}
+
+/// Store the compiled `artifact` at `path`, then prune the directory of `path`.
+///
+/// The artifact is written to a uniquely named temporary file that is synced and renamed to
+/// `path`, so that readers never see a partially written artifact.
+#[cfg(feature = "wasmtime")]
+fn store_compiled_runtime(path: &Path, artifact: &[u8]) -> std::io::Result<()> {
+	use std::io::Write;
+
+	let dir = path.parent().unwrap_or_else(|| Path::new("."));
+	std::fs::create_dir_all(dir)?;
+	let mut file = tempfile::NamedTempFile::new_in(dir)?;
+	file.write_all(artifact)?;
+	file.as_file().sync_all()?;
+	file.persist(path).map_err(|e| e.error)?;
+
+	prune_compiled_runtimes(dir, path, std::time::SystemTime::now());
+	Ok(())
+}
+
+/// Remove from `dir` the temporary files older than [`STALE_TEMP_FILE_AGE`] at `now`, and the
+/// least recently compiled runtimes beyond [`MAX_COMPILED_RUNTIMES`], except `keep`.
+#[cfg(feature = "wasmtime")]
+fn prune_compiled_runtimes(dir: &Path, keep: &Path, now: std::time::SystemTime) {
+	let entries = match std::fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(error) => {
+			tracing::debug!(
+				target: "wasm-runtime",
+				%error,
+				"Cannot prune compiled runtimes in {}",
+				dir.display(),
+			);
+			return
+		},
+	};
+
+	let remove = |path: &Path| {
+		if let Err(error) = std::fs::remove_file(path) {
+			tracing::debug!(
+				target: "wasm-runtime",
+				%error,
+				"Cannot remove {} from the compiled runtimes",
+				path.display(),
+			);
+		}
+	};
+
+	let mut artifacts = Vec::new();
+	for entry in entries.filter_map(Result::ok) {
+		let path = entry.path();
+		let modified = match entry.metadata().and_then(|metadata| metadata.modified()) {
+			Ok(modified) => modified,
+			Err(_) => continue,
+		};
+		if path.extension().map_or(false, |ext| ext == "bin") {
+			if path != keep {
+				artifacts.push((modified, path));
+			}
+		} else if now.duration_since(modified).map_or(false, |age| age > STALE_TEMP_FILE_AGE) {
+			remove(&path);
+		}
+	}
+
+	// Keep the most recent artifacts, `keep` included.
+	artifacts.sort_by(|a, b| b.0.cmp(&a.0));
+	for (_, path) in artifacts.iter().skip(MAX_COMPILED_RUNTIMES - 1) {
+		remove(path);
+	}
+}
+
 fn decode_version(mut version: &[u8]) -> Result<RuntimeVersion, WasmError> {
 	Decode::decode(&mut version).map_err(|_| {
 		WasmError::Instantiation(
@@ -392,34 +662,49 @@ pub fn read_embedded_version(blob: &RuntimeBlob) -> Result<Option<RuntimeVersion
 	}
 }
 
-fn create_versioned_wasm_runtime<H>(
+/// Returns the runtime code staged in the storage `value`, if any.
+///
+/// The code is either the whole `value`, as for `:code`, or SCALE encoded as a `Vec<u8>` inside
+/// of it, as for a `set_code` call that is scheduled or noted as a preimage. This allows to compile
+/// a runtime upgrade before it is enacted. The code is recognized by the wasm magic number or by
+/// the prefix of compressed code.
+pub fn find_runtime_code(value: &[u8]) -> Option<&[u8]> {
+	let is_code = |code: &[u8]| {
+		code.starts_with(WASM_PREFIX) || code.starts_with(&sp_maybe_compressed_blob::ZSTD_PREFIX)
+	};
+	if is_code(value) {
+		return Some(value)
+	}
+
+	(1..value.len()).filter(|&start| is_code(&value[start..])).find_map(|start| {
+		// The length prefix is a compact encoded `u32` of 1, 2, 4 or 5 bytes. The longer ones are
+		// tried first, as the last bytes of a longer prefix may be a valid shorter one.
+		[5, 4, 2, 1]
+			.into_iter()
+			.filter_map(|size| start.checked_sub(size))
+			.find_map(|from| {
+				let len = Compact::<u32>::decode_all(&mut &value[from..start]).ok()?.0 as usize;
+				value.get(start..start.checked_add(len)?).filter(|code| is_code(code))
+			})
+	})
+}
+
+fn create_versioned_wasm_runtime(
 	code: &[u8],
 	ext: &mut dyn Externalities,
-	wasm_method: WasmExecutionMethod,
-	heap_pages: u64,
-	allow_missing_func_imports: bool,
 	max_instances: usize,
-	cache_path: Option<&Path>,
-) -> Result<VersionedRuntime, WasmError>
-where
-	H: HostFunctions,
-{
+	create_runtime: impl FnOnce(RuntimeBlob) -> Result<Arc<dyn WasmModule>, WasmError>,
+) -> Result<VersionedRuntime, WasmError> {
 	// The incoming code may be actually compressed. We decompress it here and then work with
 	// the uncompressed code from now on.
-	let blob = sc_executor_common::runtime_blob::RuntimeBlob::uncompress_if_needed(code)?;
+	let blob = RuntimeBlob::uncompress_if_needed(code)?;
 
 	// Use the runtime blob to scan if there is any metadata embedded into the wasm binary
 	// pertaining to runtime version. We do it before consuming the runtime blob for creating the
 	// runtime.
 	let mut version: Option<_> = read_embedded_version(&blob)?;
 
-	let runtime = create_wasm_runtime_with_code::<H>(
-		wasm_method,
-		heap_pages,
-		blob,
-		allow_missing_func_imports,
-		cache_path,
-	)?;
+	let runtime = create_runtime(blob)?;
 
 	// If the runtime blob doesn't embed the runtime version then use the legacy version query
 	// mechanism: call the runtime.
@@ -569,4 +854,218 @@ mod tests {
 
 		assert_eq!(runtime_version, read_version);
 	}
+
+	#[cfg(feature = "wasmtime")]
+	#[test]
+	fn compiled_runtimes_are_cached_on_disk_by_code_hash() {
+		let cache_dir = tempfile::tempdir().unwrap();
+		let module = |export| {
+			wat::parse_str(format!(
+				r#"
+				(module
+					(memory $0 32)
+					(export "memory" (memory $0))
+					(global (export "__heap_base") i32 (i32.const 0))
+					(func (export "{}") (param i32 i32) (result i64) (i64.const 0))
+				)
+				"#,
+				export
+			))
+			.unwrap()
+		};
+		let wasm_method = WasmExecutionMethod::Compiled {
+			instantiation_strategy: sc_executor_wasmtime::InstantiationStrategy::RecreateInstance,
+		};
+		let cache = RuntimeCache::new(1, Some(cache_dir.path().to_owned()), 1);
+		let create = |code: &[u8]| {
+			create_versioned_wasm_runtime(
+				code,
+				&mut sp_state_machine::BasicExternalities::default(),
+				1,
+				|blob| {
+					cache.create_runtime::<sp_io::SubstrateHostFunctions>(
+						blob,
+						&[42; 4],
+						wasm_method,
+						1024,
+						true,
+					)
+				},
+			)
+			.unwrap()
+		};
+
+		create(&module("first"))
+			.module
+			.new_instance()
+			.unwrap()
+			.call_export("first", &[])
+			.unwrap();
+
+		let artifacts = std::fs::read_dir(cache_dir.path().join(COMPILED_RUNTIMES_DIR))
+			.unwrap()
+			.map(|entry| entry.unwrap().file_name().into_string().unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(artifacts, vec!["2a2a2a2a-1024-RecreateInstance.bin".to_string()]);
+
+		// The artifact compiled for the same code hash is reused.
+		let mut instance = create(&module("second")).module.new_instance().unwrap();
+		instance.call_export("first", &[]).unwrap();
+		assert!(instance.call_export("second", &[]).is_err());
+	}
+
+	#[test]
+	fn runtime_code_is_found_in_storage_values() {
+		let code = substrate_test_runtime::wasm_binary_unwrap();
+		let wasm = sp_maybe_compressed_blob::decompress(
+			code,
+			sp_maybe_compressed_blob::CODE_BLOB_BOMB_LIMIT,
+		)
+		.expect("Decompressing works");
+
+		assert_eq!(find_runtime_code(code), Some(code));
+		assert_eq!(find_runtime_code(&wasm), Some(&wasm[..]));
+
+		// A `set_code` call, with the indexes of the pallet and of the call.
+		let call = (0u8, 2u8, code.to_vec()).encode();
+		assert_eq!(find_runtime_code(&call), Some(code));
+		let call = (7u32, 0u8, 2u8, wasm.to_vec()).encode();
+		assert_eq!(find_runtime_code(&call), Some(&wasm[..]));
+
+		let mut truncated = (0u8, 2u8, code.to_vec()).encode();
+		truncated.pop();
+		assert_eq!(find_runtime_code(&truncated), None);
+		assert_eq!(find_runtime_code(&[0; 64]), None);
+	}
+
+	#[cfg(feature = "wasmtime")]
+	#[test]
+	fn staged_runtime_code_is_compiled_before_its_first_execution() {
+		let cache_dir = tempfile::tempdir().unwrap();
+		let artifacts = || {
+			std::fs::read_dir(cache_dir.path().join(COMPILED_RUNTIMES_DIR))
+				.unwrap()
+				.map(|entry| {
+					let entry = entry.unwrap();
+					(
+						entry.file_name().into_string().unwrap(),
+						entry.metadata().unwrap().modified().unwrap(),
+					)
+				})
+				.collect::<Vec<_>>()
+		};
+		let call = (0u8, 2u8, substrate_test_runtime::wasm_binary_unwrap().to_vec()).encode();
+		let code = find_runtime_code(&call).unwrap();
+		let runtime_code = RuntimeCode {
+			code_fetcher: &sp_core::traits::WrappedRuntimeCode(code.into()),
+			hash: sp_core::blake2_256(code).to_vec(),
+			heap_pages: None,
+		};
+		let wasm_method = WasmExecutionMethod::Compiled {
+			instantiation_strategy: sc_executor_wasmtime::InstantiationStrategy::RecreateInstance,
+		};
+		let with_instance = |f: &dyn Fn(&mut dyn WasmInstance) -> Result<Vec<u8>, Error>| {
+			// A new cache, as if the node was restarted, only shares the compiled artifacts.
+			RuntimeCache::new(1, Some(cache_dir.path().to_owned()), 1)
+				.with_instance::<sp_io::SubstrateHostFunctions, _, _>(
+					&runtime_code,
+					&mut sp_state_machine::BasicExternalities::default(),
+					wasm_method,
+					1024,
+					true,
+					|_, instance, _, _| f(instance),
+				)
+				.unwrap()
+				.unwrap()
+		};
+
+		// Warming up only reads the version of the staged runtime.
+		with_instance(&|_| Ok(Vec::new()));
+		let warmed_up = artifacts();
+		assert_eq!(warmed_up.len(), 1);
+		assert!(warmed_up[0]
+			.0
+			.starts_with(&sp_core::hexdisplay::HexDisplay::from(&runtime_code.hash).to_string()));
+
+		// Once enacted, the runtime is executed from the artifact compiled ahead.
+		let version = with_instance(&|instance| instance.call_export("Core_version", &[]));
+		assert_eq!(decode_version(&version).unwrap(), substrate_test_runtime::VERSION);
+		assert_eq!(artifacts(), warmed_up);
+	}
+
+	#[cfg(feature = "wasmtime")]
+	#[test]
+	fn compiled_runtimes_are_pruned() {
+		let dir = tempfile::tempdir().unwrap();
+		let file = |name: &str| dir.path().join(name);
+		let count_artifacts = || {
+			std::fs::read_dir(dir.path())
+				.unwrap()
+				.filter(|entry| {
+					entry.as_ref().unwrap().path().extension().unwrap_or_default() == "bin"
+				})
+				.count()
+		};
+
+		for i in 0..MAX_COMPILED_RUNTIMES + 4 {
+			std::fs::write(file(&format!("{}.bin", i)), b"artifact").unwrap();
+		}
+		std::fs::write(file("keep.bin"), b"artifact").unwrap();
+		std::fs::write(file(".tmp1234"), b"partial artifact").unwrap();
+
+		// The compilation writing to the temporary file may still be running.
+		let now = std::time::SystemTime::now();
+		prune_compiled_runtimes(dir.path(), &file("keep.bin"), now);
+		assert_eq!(count_artifacts(), MAX_COMPILED_RUNTIMES);
+		assert!(file("keep.bin").exists());
+		assert!(file(".tmp1234").exists());
+
+		prune_compiled_runtimes(dir.path(), &file("keep.bin"), now + STALE_TEMP_FILE_AGE * 2);
+		assert_eq!(count_artifacts(), MAX_COMPILED_RUNTIMES);
+		assert!(file("keep.bin").exists());
+		assert!(!file(".tmp1234").exists());
+	}
+
+	#[cfg(feature = "wasmtime")]
+	#[test]
+	fn calls_are_limited_by_fuel_limit_ext() {
+		let code = wat::parse_str(
+			r#"
+			(module
+				(memory $0 32)
+				(export "memory" (memory $0))
+				(global (export "__heap_base") i32 (i32.const 0))
+				(func (export "loop_forever") (param i32 i32) (result i64)
+					(loop $l (br $l))
+					(i64.const 0)
+				)
+			)
+			"#,
+		)
+		.unwrap();
+		let runtime_code = RuntimeCode {
+			code_fetcher: &sp_core::traits::WrappedRuntimeCode(code.into()),
+			hash: vec![1, 2, 3],
+			heap_pages: None,
+		};
+		let wasm_method = WasmExecutionMethod::Compiled {
+			instantiation_strategy: sc_executor_wasmtime::InstantiationStrategy::RecreateInstance,
+		};
+		let cache = RuntimeCache::new(1, None, 1).with_fuel_metering(true);
+
+		let mut ext = sp_state_machine::BasicExternalities::default();
+		ext.register_extension(FuelLimitExt(10_000));
+		let result = cache
+			.with_instance::<sp_io::SubstrateHostFunctions, _, _>(
+				&runtime_code,
+				&mut ext,
+				wasm_method,
+				1024,
+				true,
+				|_, instance, _, _| instance.call_export("loop_forever", &[]),
+			)
+			.unwrap();
+
+		assert!(matches!(result, Err(Error::OutOfFuel(10_000))));
+	}
 }
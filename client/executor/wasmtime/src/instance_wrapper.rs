@@ -397,6 +397,22 @@ impl InstanceWrapper {
 		self.memory.data_mut(self.store.as_context_mut()).fill(0);
 	}
 
+	/// Add `fuel` to the store for the next call to consume.
+	///
+	/// The store must have been created with fuel metering enabled.
+	pub(crate) fn add_fuel(&mut self, fuel: u64) -> Result<()> {
+		self.store
+			.add_fuel(fuel)
+			.map_err(|e| Error::Other(format!("failed to add fuel: {:#}", e)))
+	}
+
+	/// Returns `true` if all the fuel of the store is consumed.
+	///
+	/// The store must have been created with fuel metering enabled.
+	pub(crate) fn is_out_of_fuel(&mut self) -> bool {
+		self.store.consume_fuel(0).is_err()
+	}
+
 	pub(crate) fn store(&self) -> &Store {
 		&self.store
 	}
@@ -26,7 +26,7 @@ use crate::{
 
 use sc_allocator::{AllocationStats, FreeingBumpHeapAllocator};
 use sc_executor_common::{
-	error::{Result, WasmError},
+	error::{Error, Result, WasmError},
 	runtime_blob::{
 		self, DataSegmentsSnapshot, ExposedMutableGlobalsSet, GlobalsSnapshot, RuntimeBlob,
 	},
@@ -174,7 +174,11 @@ impl WasmModule for WasmtimeRuntime {
 			}),
 		};
 
-		Ok(Box::new(WasmtimeInstance { strategy }))
+		Ok(Box::new(WasmtimeInstance {
+			strategy,
+			fuel_metering: self.config.semantics.fuel_metering,
+			fuel_limit: None,
+		}))
 	}
 }
 
@@ -182,6 +186,8 @@ impl WasmModule for WasmtimeRuntime {
 /// to execute the compiled code.
 pub struct WasmtimeInstance {
 	strategy: Strategy,
+	fuel_metering: bool,
+	fuel_limit: Option<u64>,
 }
 
 impl WasmtimeInstance {
@@ -191,6 +197,9 @@ impl WasmtimeInstance {
 		data: &[u8],
 		allocation_stats: &mut Option<AllocationStats>,
 	) -> Result<Vec<u8>> {
+		// With metering enabled every call needs fuel, calls without a limit get all of it.
+		let fuel = self.fuel_metering.then(|| self.fuel_limit.unwrap_or(u64::MAX));
+
 		match &mut self.strategy {
 			Strategy::LegacyInstanceReuse {
 				ref mut instance_wrapper,
@@ -210,8 +219,14 @@ impl WasmtimeInstance {
 				globals_snapshot.apply(&mut InstanceGlobals { instance: instance_wrapper });
 				let allocator = FreeingBumpHeapAllocator::new(*heap_base);
 
-				let result =
-					perform_call(data, instance_wrapper, entrypoint, allocator, allocation_stats);
+				let result = perform_call(
+					data,
+					instance_wrapper,
+					entrypoint,
+					allocator,
+					allocation_stats,
+					fuel,
+				);
 
 				// Signal to the OS that we are done with the linear memory and that it can be
 				// reclaimed.
@@ -225,7 +240,14 @@ impl WasmtimeInstance {
 				let entrypoint = instance_wrapper.resolve_entrypoint(method)?;
 
 				let allocator = FreeingBumpHeapAllocator::new(heap_base);
-				perform_call(data, &mut instance_wrapper, entrypoint, allocator, allocation_stats)
+				perform_call(
+					data,
+					&mut instance_wrapper,
+					entrypoint,
+					allocator,
+					allocation_stats,
+					fuel,
+				)
 			},
 		}
 	}
@@ -251,6 +273,10 @@ impl WasmInstance for WasmtimeInstance {
 		}
 	}
 
+	fn set_fuel_limit(&mut self, limit: Option<u64>) {
+		self.fuel_limit = limit;
+	}
+
 	fn linear_memory_base_ptr(&self) -> Option<*const u8> {
 		match &self.strategy {
 			Strategy::RecreateInstance(_) => {
@@ -336,6 +362,8 @@ fn common_config(semantics: &Semantics) -> std::result::Result<wasmtime::Config,
 
 	config.parallel_compilation(semantics.parallel_compilation);
 
+	config.consume_fuel(semantics.fuel_metering);
+
 	// Be clear and specific about the extensions we support. If an update brings new features
 	// they should be introduced here as well.
 	config.wasm_reference_types(false);
@@ -542,6 +570,14 @@ pub struct Semantics {
 	///
 	/// The default is `None`.
 	pub max_memory_size: Option<usize>,
+
+	/// Compile the runtime with fuel metering, so that the fuel a call may consume can be
+	/// limited with [`WasmInstance::set_fuel_limit`].
+	///
+	/// Metering slows down the execution of every call, including calls without a limit.
+	///
+	/// Not supported with [`InstantiationStrategy::LegacyInstanceReuse`].
+	pub fuel_metering: bool,
 }
 
 pub struct Config {
@@ -650,6 +686,12 @@ where
 
 			match config.semantics.instantiation_strategy {
 				InstantiationStrategy::LegacyInstanceReuse => {
+					// The store of the instance is reused, so the fuel left by a call can't be
+					// reset for the next one.
+					if config.semantics.fuel_metering {
+						return Err(WasmError::Other("the legacy instance reuse instantiation strategy is incompatible with fuel metering".into()));
+					}
+
 					let data_segments_snapshot =
 						DataSegmentsSnapshot::take(&blob).map_err(|e| {
 							WasmError::Other(format!("cannot take data segments snapshot: {}", e))
@@ -762,9 +804,14 @@ fn perform_call(
 	entrypoint: EntryPoint,
 	mut allocator: FreeingBumpHeapAllocator,
 	allocation_stats: &mut Option<AllocationStats>,
+	fuel: Option<u64>,
 ) -> Result<Vec<u8>> {
 	let (data_ptr, data_len) = inject_input_data(instance_wrapper, &mut allocator, data)?;
 
+	if let Some(fuel) = fuel {
+		instance_wrapper.add_fuel(fuel)?;
+	}
+
 	let host_state = HostState::new(allocator);
 
 	// Set the host state before calling into wasm.
@@ -772,7 +819,11 @@ fn perform_call(
 
 	let ret = entrypoint
 		.call(instance_wrapper.store_mut(), data_ptr, data_len)
-		.map(unpack_ptr_and_len);
+		.map(unpack_ptr_and_len)
+		.map_err(|error| match fuel {
+			Some(fuel) if instance_wrapper.is_out_of_fuel() => Error::OutOfFuel(fuel),
+			_ => error,
+		});
 
 	// Reset the host state
 	let host_state = instance_wrapper.store_mut().data_mut().host_state.take().expect(
@@ -79,6 +79,7 @@ struct RuntimeBuilder {
 	deterministic_stack: bool,
 	extra_heap_pages: u64,
 	max_memory_size: Option<usize>,
+	fuel_metering: bool,
 	precompile_runtime: bool,
 	tmpdir: Option<tempfile::TempDir>,
 }
@@ -92,6 +93,7 @@ impl RuntimeBuilder {
 			deterministic_stack: false,
 			extra_heap_pages: 1024,
 			max_memory_size: None,
+			fuel_metering: false,
 			precompile_runtime: false,
 			tmpdir: None,
 		}
@@ -122,6 +124,11 @@ impl RuntimeBuilder {
 		self
 	}
 
+	fn fuel_metering(mut self, fuel_metering: bool) -> Self {
+		self.fuel_metering = fuel_metering;
+		self
+	}
+
 	fn build(&mut self) -> impl WasmModule + '_ {
 		let blob = {
 			let wasm: Vec<u8>;
@@ -154,6 +161,7 @@ impl RuntimeBuilder {
 				parallel_compilation: true,
 				extra_heap_pages: self.extra_heap_pages,
 				max_memory_size: self.max_memory_size,
+				fuel_metering: self.fuel_metering,
 			},
 		};
 
@@ -311,6 +319,42 @@ fn test_stack_depth_reaching(instantiation_strategy: InstantiationStrategy) {
 	}
 }
 
+test_wasm_execution!(@no_legacy_instance_reuse test_fuel_limit);
+fn test_fuel_limit(instantiation_strategy: InstantiationStrategy) {
+	let wat = r#"
+		(module
+		  (memory $0 32)
+		  (export "memory" (memory $0))
+		  (global (export "__heap_base") i32 (i32.const 0))
+		  (func (export "loop_forever") (param i32 i32) (result i64)
+		    (loop $l (br $l))
+		    (i64.const 0)
+		  )
+		  (func (export "finish") (param i32 i32) (result i64)
+		    (i64.const 0)
+		  )
+		)
+	"#;
+	let mut builder = RuntimeBuilder::new(instantiation_strategy)
+		.use_wat(wat.to_string())
+		.fuel_metering(true);
+	let runtime = builder.build();
+	let mut instance = runtime.new_instance().expect("failed to instantiate a runtime");
+
+	// Calls without a limit are not affected by the metering.
+	instance.call_export("finish", &[]).unwrap();
+
+	instance.set_fuel_limit(Some(10_000));
+	match instance.call_export("loop_forever", &[]).unwrap_err() {
+		Error::OutOfFuel(limit) => assert_eq!(limit, 10_000),
+		error => panic!("unexpected error: {:?}", error),
+	}
+
+	// The limit applies to every call on its own.
+	instance.call_export("finish", &[]).unwrap();
+	instance.call_export("finish", &[]).unwrap();
+}
+
 test_wasm_execution!(test_max_memory_pages_imported_memory_without_precompilation);
 fn test_max_memory_pages_imported_memory_without_precompilation(
 	instantiation_strategy: InstantiationStrategy,
@@ -540,6 +584,7 @@ fn test_instances_without_reuse_are_not_leaked() {
 				parallel_compilation: true,
 				extra_heap_pages: 2048,
 				max_memory_size: None,
+				fuel_metering: false,
 			},
 		},
 	)
@@ -35,8 +35,10 @@ mod finality;
 mod notification;
 mod report;
 
-use sc_finality_grandpa::GrandpaJustificationStream;
-use sc_rpc::SubscriptionTaskExecutor;
+use sc_finality_grandpa::{
+	GrandpaJustificationStream, SharedStallReport, SharedVotingPolicy, StallReport, VotingPolicy,
+};
+use sc_rpc::{DenyUnsafe, SubscriptionTaskExecutor};
 use sp_runtime::traits::{Block as BlockT, NumberFor};
 
 use finality::{EncodedFinalityProof, RpcFinalityProofProvider};
@@ -64,6 +66,20 @@ pub trait GrandpaApi<Notification, Hash, Number> {
 	/// in the set and all the intermediary headers to link them together.
 	#[method(name = "grandpa_proveFinality")]
 	async fn prove_finality(&self, block: Number) -> RpcResult<Option<EncodedFinalityProof>>;
+
+	/// Returns a report on the ongoing finality stall, i.e. on the state of the voter while the
+	/// finalized block lags the best block by more than the configured threshold, if any.
+	#[method(name = "grandpa_stallReport")]
+	fn stall_report(&self) -> RpcResult<Option<StallReport<Number>>>;
+
+	/// Returns the policy restricting the votes of this node.
+	#[method(name = "grandpa_votingPolicy")]
+	fn voting_policy(&self) -> RpcResult<VotingPolicy<Hash, Number>>;
+
+	/// Replace the policy restricting the votes of this node, e.g. to hold finality of a
+	/// suspicious fork while it is investigated. Takes effect from the next vote on.
+	#[method(name = "grandpa_setVotingPolicy")]
+	fn set_voting_policy(&self, policy: VotingPolicy<Hash, Number>) -> RpcResult<()>;
 }
 
 /// Provides RPC methods for interacting with GRANDPA.
@@ -73,6 +89,9 @@ pub struct Grandpa<AuthoritySet, VoterState, Block: BlockT, ProofProvider> {
 	voter_state: VoterState,
 	justification_stream: GrandpaJustificationStream<Block>,
 	finality_proof_provider: Arc<ProofProvider>,
+	stall_report: SharedStallReport<NumberFor<Block>>,
+	voting_policy: SharedVotingPolicy<Block::Hash, NumberFor<Block>>,
+	deny_unsafe: DenyUnsafe,
 }
 impl<AuthoritySet, VoterState, Block: BlockT, ProofProvider>
 	Grandpa<AuthoritySet, VoterState, Block, ProofProvider>
@@ -84,8 +103,20 @@ impl<AuthoritySet, VoterState, Block: BlockT, ProofProvider>
 		voter_state: VoterState,
 		justification_stream: GrandpaJustificationStream<Block>,
 		finality_proof_provider: Arc<ProofProvider>,
+		stall_report: SharedStallReport<NumberFor<Block>>,
+		voting_policy: SharedVotingPolicy<Block::Hash, NumberFor<Block>>,
+		deny_unsafe: DenyUnsafe,
 	) -> Self {
-		Self { executor, authority_set, voter_state, justification_stream, finality_proof_provider }
+		Self {
+			executor,
+			authority_set,
+			voter_state,
+			justification_stream,
+			finality_proof_provider,
+			stall_report,
+			voting_policy,
+			deny_unsafe,
+		}
 	}
 }
 
@@ -130,6 +161,24 @@ where
 			})
 			.map_err(Into::into)
 	}
+
+	fn stall_report(&self) -> RpcResult<Option<StallReport<NumberFor<Block>>>> {
+		Ok(self.stall_report.get())
+	}
+
+	fn voting_policy(&self) -> RpcResult<VotingPolicy<Block::Hash, NumberFor<Block>>> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.voting_policy.get())
+	}
+
+	fn set_voting_policy(
+		&self,
+		policy: VotingPolicy<Block::Hash, NumberFor<Block>>,
+	) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		self.voting_policy.set(policy);
+		Ok(())
+	}
 }
 
 #[cfg(test)]
@@ -272,6 +321,9 @@ mod tests {
 			voter_state,
 			justification_stream,
 			finality_proof_provider,
+			SharedStallReport::empty(),
+			SharedVotingPolicy::new(Default::default()),
+			DenyUnsafe::No,
 		)
 		.into_rpc();
 
@@ -422,4 +474,56 @@ mod tests {
 		let finality_proof_rpc: FinalityProof<Header> = Decode::decode(&mut &bytes[..]).unwrap();
 		assert_eq!(finality_proof_rpc, finality_proof);
 	}
+
+	#[tokio::test]
+	async fn no_stall_report_while_finality_progresses() {
+		let (rpc, _) = setup_io_handler(TestVoterState);
+
+		let report: Option<StallReport<u64>> =
+			rpc.call("grandpa_stallReport", EmptyParams::new()).await.unwrap();
+		assert_eq!(report, None);
+	}
+
+	#[tokio::test]
+	async fn voting_policy_can_be_replaced() {
+		let (rpc, _) = setup_io_handler(TestVoterState);
+
+		let policy: VotingPolicy<H256, u64> =
+			rpc.call("grandpa_votingPolicy", EmptyParams::new()).await.unwrap();
+		assert_eq!(policy, Default::default());
+
+		let policy = VotingPolicy {
+			max_finality_lag: Some(100),
+			under_investigation: vec![42],
+			held: vec![H256::repeat_byte(1)],
+		};
+		let _: () = rpc.call("grandpa_setVotingPolicy", [policy.clone()]).await.unwrap();
+
+		let current: VotingPolicy<H256, u64> =
+			rpc.call("grandpa_votingPolicy", EmptyParams::new()).await.unwrap();
+		assert_eq!(current, policy);
+	}
+
+	#[tokio::test]
+	async fn voting_policy_is_unsafe() {
+		let (_, justification_stream) = GrandpaJustificationStream::<Block>::channel();
+		let rpc = Grandpa::new(
+			Arc::new(TaskExecutor::default()),
+			TestAuthoritySet,
+			TestVoterState,
+			justification_stream,
+			Arc::new(TestFinalityProofProvider { finality_proof: None }),
+			SharedStallReport::empty(),
+			SharedVotingPolicy::new(Default::default()),
+			DenyUnsafe::Yes,
+		)
+		.into_rpc();
+
+		let request =
+			r#"{"jsonrpc":"2.0","method":"grandpa_setVotingPolicy","params":[{}],"id":1}"#;
+		let (response, _) = rpc.raw_json_request(request).await.unwrap();
+		let expected = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"RPC call is unsafe to be called externally"},"id":1}"#;
+
+		assert_eq!(&response.result, expected);
+	}
 }
@@ -39,6 +39,7 @@ use sc_client_api::{
 	backend::{apply_aux, Backend as BackendT},
 	utils::is_descendent_of,
 };
+use sc_consensus::EquivocationGuard;
 use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_DEBUG, CONSENSUS_INFO};
 use sp_authority_permission::PermissionResolver;
 use sp_blockchain::HeaderMetadata;
@@ -442,6 +443,7 @@ pub(crate) struct Environment<Backend, Block: BlockT, C, N: NetworkT<Block>, SC,
 	pub(crate) telemetry: Option<TelemetryHandle>,
 	pub(crate) _phantom: PhantomData<Backend>,
 	pub(crate) permission_resolver: Arc<dyn PermissionResolver>,
+	pub(crate) equivocation_guard: Option<EquivocationGuard>,
 }
 
 impl<BE, Block: BlockT, C, N: NetworkT<Block>, SC, VR> Environment<BE, Block, C, N, SC, VR> {
@@ -483,6 +485,21 @@ where
 	N: NetworkT<Block>,
 	SC: SelectChainT<Block>,
 {
+	/// Alert the equivocation guard if `offender` is a voter key of our keystore.
+	fn report_self_equivocation(&self, round: RoundNumber, kind: &str, offender: &AuthorityId) {
+		let guard = match self.equivocation_guard.as_ref() {
+			Some(guard) => guard,
+			None => return,
+		};
+		if guard.is_local_key(sp_application_crypto::key_types::GRANDPA, offender.as_ref()) {
+			guard.report(
+				"afg",
+				offender.as_ref(),
+				&format!("{} equivocation in round {} of set {}", kind, round, self.set_id),
+			);
+		}
+	}
+
 	/// Report the given equivocation to the GRANDPA runtime module. This method
 	/// generates a session membership proof of the offender and then submits an
 	/// extrinsic to report the equivocation. In particular, the session membership
@@ -706,7 +723,12 @@ where
 		let prevote_timer = Delay::new(self.config.gossip_duration * 2);
 		let precommit_timer = Delay::new(self.config.gossip_duration * 4);
 
-		let local_id = local_authority_id(&self.voters, self.config.keystore.as_ref());
+		let local_id =
+			local_authority_id(&self.voters, self.config.keystore.as_ref()).filter(|id| {
+				self.equivocation_guard
+					.as_ref()
+					.map_or(true, |guard| !guard.is_tripped(id.as_ref()))
+			});
 
 		let has_voted = match self.voter_set_state.has_voted(round) {
 			HasVoted::Yes(id, vote) =>
@@ -1094,10 +1116,11 @@ where
 
 	fn prevote_equivocation(
 		&self,
-		_round: RoundNumber,
+		round: RoundNumber,
 		equivocation: finality_grandpa::Equivocation<Self::Id, Prevote<Block>, Self::Signature>,
 	) {
 		warn!(target: "afg", "Detected prevote equivocation in the finality worker: {:?}", equivocation);
+		self.report_self_equivocation(round, "prevote", &equivocation.identity);
 		if let Err(err) = self.report_equivocation(equivocation.into()) {
 			warn!(target: "afg", "Error reporting prevote equivocation: {}", err);
 		}
@@ -1105,10 +1128,11 @@ where
 
 	fn precommit_equivocation(
 		&self,
-		_round: RoundNumber,
+		round: RoundNumber,
 		equivocation: finality_grandpa::Equivocation<Self::Id, Precommit<Block>, Self::Signature>,
 	) {
 		warn!(target: "afg", "Detected precommit equivocation in the finality worker: {:?}", equivocation);
+		self.report_self_equivocation(round, "precommit", &equivocation.identity);
 		if let Err(err) = self.report_equivocation(equivocation.into()) {
 			warn!(target: "afg", "Error reporting precommit equivocation: {}", err);
 		}
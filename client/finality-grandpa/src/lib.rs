@@ -67,7 +67,7 @@ use sc_client_api::{
 	BlockchainEvents, CallExecutor, ExecutionStrategy, ExecutorProvider, Finalizer, LockImportRun,
 	StorageProvider, TransactionFor,
 };
-use sc_consensus::BlockImport;
+use sc_consensus::{BlockImport, EquivocationGuard};
 use sc_network_common::protocol::ProtocolName;
 use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_DEBUG, CONSENSUS_INFO};
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver};
@@ -119,6 +119,7 @@ mod import;
 mod justification;
 mod notification;
 mod observer;
+mod stall_detector;
 mod until_imported;
 mod voting_rule;
 pub mod warp_proof;
@@ -132,9 +133,10 @@ pub use import::{find_forced_change, find_scheduled_change, GrandpaBlockImport};
 pub use justification::GrandpaJustification;
 pub use notification::{GrandpaJustificationSender, GrandpaJustificationStream};
 pub use observer::run_grandpa_observer;
+pub use stall_detector::{RoundReport, SharedStallReport, StallReport, VotesReport};
 pub use voting_rule::{
-	BeforeBestBlockBy, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRuleResult,
-	VotingRulesBuilder,
+	BeforeBestBlockBy, SharedVotingPolicy, ThreeQuartersOfTheUnfinalizedChain, VotingPolicy,
+	VotingRule, VotingRuleResult, VotingRulesBuilder,
 };
 
 use aux_schema::PersistentData;
@@ -721,6 +723,13 @@ pub struct GrandpaParams<Block: BlockT, C, N, SC, VR> {
 	pub telemetry: Option<TelemetryHandle>,
 	/// Do we have permission to author ?
 	pub permission_resolver: Arc<dyn PermissionResolver>,
+	/// Alerts when our voter key equivocates and optionally stops voting with it.
+	pub equivocation_guard: Option<EquivocationGuard>,
+	/// Report a finality stall once the finalized head lags the best head by more than this
+	/// many blocks.
+	pub finality_stall_threshold: Option<NumberFor<Block>>,
+	/// The report of an ongoing finality stall is exposed at an RPC endpoint.
+	pub shared_stall_report: SharedStallReport<NumberFor<Block>>,
 }
 
 /// Returns the configuration value to put in
@@ -741,6 +750,7 @@ pub fn grandpa_peers_set_config(
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: sc_network::config::NonReservedPeerMode::Deny,
 		},
+		priority: sc_network::config::TrafficPriority::High,
 	}
 }
 
@@ -768,6 +778,9 @@ where
 		shared_voter_state,
 		telemetry,
 		permission_resolver,
+		equivocation_guard,
+		finality_stall_threshold,
+		shared_stall_report,
 	} = grandpa_params;
 
 	// NOTE: we have recently removed `run_grandpa_observer` from the public
@@ -829,6 +842,20 @@ where
 			future::Either::Right(future::pending())
 		};
 
+	let stall_detector = match finality_stall_threshold {
+		Some(threshold) => future::Either::Left(stall_detector::run_stall_detector(
+			client.clone(),
+			stall_detector::STALL_CHECK_INTERVAL,
+			threshold,
+			persistent_data.authority_set.clone(),
+			shared_voter_state.clone(),
+			config.keystore.clone(),
+			permission_resolver.clone(),
+			shared_stall_report,
+		)),
+		None => future::Either::Right(future::pending()),
+	};
+
 	let voter_work = VoterWork::new(
 		client,
 		config,
@@ -842,6 +869,7 @@ where
 		justification_sender,
 		telemetry,
 		permission_resolver,
+		equivocation_guard,
 	);
 
 	let voter_work = voter_work.map(|res| match res {
@@ -854,7 +882,9 @@ where
 	// Make sure that `telemetry_task` doesn't accidentally finish and kill grandpa.
 	let telemetry_task = telemetry_task.then(|_| future::pending::<()>());
 
-	Ok(future::select(voter_work, telemetry_task).map(drop))
+	let background_tasks = future::join(telemetry_task, stall_detector);
+
+	Ok(future::select(voter_work, background_tasks).map(drop))
 }
 
 struct Metrics {
@@ -911,6 +941,7 @@ where
 		justification_sender: GrandpaJustificationSender<Block>,
 		telemetry: Option<TelemetryHandle>,
 		permission_resolver: Arc<dyn PermissionResolver>,
+		equivocation_guard: Option<EquivocationGuard>,
 	) -> Self {
 		let metrics = match prometheus_registry.as_ref().map(Metrics::register) {
 			Some(Ok(metrics)) => Some(metrics),
@@ -937,6 +968,7 @@ where
 			telemetry: telemetry.clone(),
 			_phantom: PhantomData,
 			permission_resolver: permission_resolver.clone(),
+			equivocation_guard,
 		});
 
 		let mut work = VoterWork {
@@ -1084,6 +1116,7 @@ where
 					telemetry: self.telemetry.clone(),
 					_phantom: PhantomData,
 					permission_resolver: self.permission_resolver.clone(),
+					equivocation_guard: self.env.equivocation_guard.clone(),
 				});
 
 				self.rebuild_voter();
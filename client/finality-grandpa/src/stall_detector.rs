@@ -0,0 +1,333 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Detection of finality stalls.
+//!
+//! When the finalized head lags the best head by more than a configured number of blocks, a
+//! [`StallReport`] describing the state of the voter is logged periodically and made available
+//! through a [`SharedStallReport`], e.g. to be queried through RPC.
+
+use std::{
+	collections::{BTreeSet, HashSet},
+	fmt::Debug,
+	sync::Arc,
+	time::Duration,
+};
+
+use log::{info, warn};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use sc_client_api::blockchain::HeaderBackend;
+use sp_authority_permission::PermissionResolver;
+use sp_finality_grandpa::SetId;
+use sp_keystore::SyncCryptoStorePtr;
+use sp_runtime::traits::{Block as BlockT, NumberFor, Saturating};
+
+use crate::{
+	authorities::SharedAuthoritySet, local_authority_id, report, AuthorityId, SharedVoterState,
+};
+
+/// How often we check whether finality is stalled, and log the report while it is.
+pub(crate) const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The votes received in a round.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VotesReport {
+	/// The weight of the votes received.
+	pub current_weight: u64,
+	/// The authorities we haven't received a vote from.
+	pub missing: BTreeSet<AuthorityId>,
+}
+
+/// The state of the best round of the voter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundReport {
+	/// The round number.
+	pub round: u64,
+	/// The total weight of the voters.
+	pub total_weight: u64,
+	/// The weight of the votes required to complete the round.
+	pub threshold_weight: u64,
+	/// The prevotes received in the round.
+	pub prevotes: VotesReport,
+	/// The precommits received in the round.
+	pub precommits: VotesReport,
+}
+
+/// A description of a finality stall.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StallReport<N> {
+	/// The number of the best block.
+	pub best_number: N,
+	/// The number of the last finalized block.
+	pub finalized_number: N,
+	/// The id of the current authority set.
+	pub set_id: SetId,
+	/// Our key in the current authority set, if any.
+	pub local_authority_id: Option<AuthorityId>,
+	/// Whether we have permission to vote in the best round.
+	pub has_permission: bool,
+	/// The best round of the voter, if the voter is running.
+	pub round: Option<RoundReport>,
+}
+
+impl RoundReport {
+	fn new(
+		round: u64,
+		round_state: &report::RoundState<AuthorityId>,
+		voters: &BTreeSet<AuthorityId>,
+	) -> Self {
+		let missing = |ids: &HashSet<AuthorityId>| {
+			voters.iter().filter(|id| !ids.contains(id)).cloned().collect()
+		};
+
+		RoundReport {
+			round,
+			total_weight: round_state.total_weight.get(),
+			threshold_weight: round_state.threshold_weight.get(),
+			prevotes: VotesReport {
+				current_weight: round_state.prevote_current_weight.0,
+				missing: missing(&round_state.prevote_ids),
+			},
+			precommits: VotesReport {
+				current_weight: round_state.precommit_current_weight.0,
+				missing: missing(&round_state.precommit_ids),
+			},
+		}
+	}
+}
+
+/// The latest report of the stall detector, `None` while finality isn't stalled.
+pub struct SharedStallReport<N> {
+	inner: Arc<RwLock<Option<StallReport<N>>>>,
+}
+
+impl<N: Clone> SharedStallReport<N> {
+	/// Create a new empty `SharedStallReport` instance.
+	pub fn empty() -> Self {
+		SharedStallReport { inner: Arc::new(RwLock::new(None)) }
+	}
+
+	/// Returns the report of the ongoing finality stall, if any.
+	pub fn get(&self) -> Option<StallReport<N>> {
+		self.inner.read().clone()
+	}
+
+	fn set(&self, report: Option<StallReport<N>>) -> Option<StallReport<N>> {
+		std::mem::replace(&mut *self.inner.write(), report)
+	}
+}
+
+impl<N> Clone for SharedStallReport<N> {
+	fn clone(&self) -> Self {
+		SharedStallReport { inner: self.inner.clone() }
+	}
+}
+
+/// Checks every `check_interval` whether the finalized head lags the best head by more than
+/// `threshold` blocks, and reports the state of the voter while it does.
+pub(crate) async fn run_stall_detector<Block, C>(
+	client: Arc<C>,
+	check_interval: Duration,
+	threshold: NumberFor<Block>,
+	authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
+	voter_state: SharedVoterState,
+	keystore: Option<SyncCryptoStorePtr>,
+	permission_resolver: Arc<dyn PermissionResolver>,
+	shared_report: SharedStallReport<NumberFor<Block>>,
+) where
+	Block: BlockT,
+	C: HeaderBackend<Block>,
+{
+	loop {
+		futures_timer::Delay::new(check_interval).await;
+
+		let info = client.info();
+		if info.best_number <= info.finalized_number.saturating_add(threshold) {
+			if shared_report.set(None).is_some() {
+				info!(
+					target: "afg",
+					"👴 Finality is no longer stalled, finalized #{} with best #{}",
+					info.finalized_number,
+					info.best_number,
+				);
+			}
+			continue;
+		}
+
+		let voters = authority_set.current_authorities();
+		let local_authority_id = local_authority_id(&voters, keystore.as_ref());
+		let voters = voters.iter().map(|(id, _)| id.clone()).collect();
+
+		let round = voter_state.voter_state().map(|voter_state| {
+			let (round, round_state) = voter_state.best_round;
+			RoundReport::new(round, &round_state, &voters)
+		});
+		let has_permission = match round.as_ref() {
+			Some(round) => permission_resolver.resolve_round(round.round).await,
+			None => false,
+		};
+
+		let report = StallReport {
+			best_number: info.best_number,
+			finalized_number: info.finalized_number,
+			set_id: authority_set.set_id(),
+			local_authority_id,
+			has_permission,
+			round,
+		};
+
+		warn!(target: "afg", "👴 Finality is stalled: {}", describe(&report));
+		shared_report.set(Some(report));
+	}
+}
+
+fn describe<N: Debug>(report: &StallReport<N>) -> String {
+	let mut description = format!(
+		"finalized #{:?} with best #{:?}, set {}",
+		report.finalized_number, report.best_number, report.set_id,
+	);
+
+	match report.round.as_ref() {
+		Some(round) => description.push_str(&format!(
+			", round {}, prevotes {}/{} (missing {:?}), precommits {}/{} (missing {:?})",
+			round.round,
+			round.prevotes.current_weight,
+			round.threshold_weight,
+			round.prevotes.missing,
+			round.precommits.current_weight,
+			round.threshold_weight,
+			round.precommits.missing,
+		)),
+		None => description.push_str(", voter not running"),
+	}
+
+	match report.local_authority_id.as_ref() {
+		Some(id) if report.has_permission => {
+			description.push_str(&format!(", voting as {} with permission", id))
+		},
+		Some(id) => description.push_str(&format!(", voting as {} without permission", id)),
+		None => description.push_str(", not an authority"),
+	}
+
+	description
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::authorities::AuthoritySet;
+	use futures::{executor::block_on, future};
+	use sc_block_builder::BlockBuilderProvider;
+	use sp_authority_permission::AlwaysPermissionGranted;
+	use sp_core::crypto::ByteArray;
+	use sp_runtime::generic::BlockId;
+	use substrate_test_runtime_client::{
+		runtime::Block, ClientBlockImportExt, ClientExt, DefaultTestClientBuilderExt,
+		TestClientBuilder, TestClientBuilderExt,
+	};
+
+	#[test]
+	fn round_report_lists_missing_voters() {
+		let id = |b: u8| AuthorityId::from_slice(&[b; 32]).unwrap();
+		let voters: BTreeSet<_> = (1..=4).map(id).collect();
+
+		let round_state = report::RoundState {
+			total_weight: 4_u64.try_into().unwrap(),
+			threshold_weight: 3_u64.try_into().unwrap(),
+			prevote_current_weight: 3.into(),
+			prevote_ids: HashSet::from([id(1), id(2), id(3)]),
+			precommit_current_weight: 1.into(),
+			precommit_ids: HashSet::from([id(2)]),
+		};
+
+		let report = RoundReport::new(7, &round_state, &voters);
+
+		assert_eq!(report.round, 7);
+		assert_eq!(report.threshold_weight, 3);
+		assert_eq!(report.prevotes.current_weight, 3);
+		assert_eq!(report.prevotes.missing, BTreeSet::from([id(4)]));
+		assert_eq!(report.precommits.current_weight, 1);
+		assert_eq!(report.precommits.missing, BTreeSet::from([id(1), id(3), id(4)]));
+
+		let report = StallReport {
+			best_number: 20u64,
+			finalized_number: 5,
+			set_id: 2,
+			local_authority_id: Some(id(4)),
+			has_permission: false,
+			round: Some(report),
+		};
+		assert!(describe(&report).contains("without permission"));
+	}
+
+	#[test]
+	fn reports_stalls_until_finality_catches_up() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		for _ in 0..20 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			block_on(client.import(sp_consensus::BlockOrigin::Own, block)).unwrap();
+		}
+
+		let voter = AuthorityId::from_slice(&[1; 32]).unwrap();
+		let authority_set = AuthoritySet::genesis(vec![(voter, 1)]).unwrap().into();
+		let shared_report = SharedStallReport::empty();
+		let detector = run_stall_detector::<Block, _>(
+			client.clone(),
+			Duration::from_millis(10),
+			10,
+			authority_set,
+			SharedVoterState::empty(),
+			None,
+			Arc::new(AlwaysPermissionGranted {}),
+			shared_report.clone(),
+		);
+
+		let wait_for = |stalled: bool| {
+			let shared_report = shared_report.clone();
+			async move {
+				for _ in 0..500 {
+					if shared_report.get().is_some() == stalled {
+						return shared_report.get()
+					}
+					futures_timer::Delay::new(Duration::from_millis(10)).await;
+				}
+				panic!("The stall detector didn't report stalled = {}", stalled);
+			}
+		};
+
+		let test = async {
+			// nothing is finalized, so finality lags 20 blocks behind the best block.
+			let report = wait_for(true).await.unwrap();
+			assert_eq!((report.best_number, report.finalized_number), (20, 0));
+			assert_eq!(report.set_id, 0);
+			assert_eq!(report.local_authority_id, None);
+			assert_eq!(report.round, None);
+
+			// finality catches up to 10 blocks behind the best block.
+			client.finalize_block(BlockId::Number(10), None).unwrap();
+			assert_eq!(wait_for(false).await, None);
+		};
+
+		block_on(future::select(Box::pin(detector), Box::pin(test)));
+	}
+}
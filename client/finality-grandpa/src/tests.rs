@@ -25,8 +25,8 @@ use futures::executor::block_on;
 use futures_timer::Delay;
 use parking_lot::{Mutex, RwLock};
 use sc_consensus::{
-	BlockImport, BlockImportParams, BoxJustificationImport, ForkChoiceStrategy, ImportResult,
-	ImportedAux,
+	BlockImport, BlockImportParams, BoxJustificationImport, EquivocationGuard, ForkChoiceStrategy,
+	ImportResult, ImportedAux,
 };
 use sc_network::config::Role;
 use sc_network_test::{
@@ -271,6 +271,9 @@ fn initialize_grandpa(
 			shared_voter_state: SharedVoterState::empty(),
 			telemetry: None,
 			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			equivocation_guard: None,
+			finality_stall_threshold: None,
+			shared_stall_report: SharedStallReport::empty(),
 		};
 		let voter =
 			run_grandpa_voter(grandpa_params).expect("all in order with client and network");
@@ -422,6 +425,9 @@ fn finalize_3_voters_1_full_observer() {
 			shared_voter_state: SharedVoterState::empty(),
 			telemetry: None,
 			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			equivocation_guard: None,
+			finality_stall_threshold: None,
+			shared_stall_report: SharedStallReport::empty(),
 		};
 
 		run_grandpa_voter(grandpa_params).expect("all in order with client and network")
@@ -514,6 +520,9 @@ fn transition_3_voters_twice_1_full_observer() {
 			shared_voter_state: SharedVoterState::empty(),
 			telemetry: None,
 			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			equivocation_guard: None,
+			finality_stall_threshold: None,
+			shared_stall_report: SharedStallReport::empty(),
 		};
 
 		voters
@@ -1014,6 +1023,9 @@ fn voter_persists_its_votes() {
 			shared_voter_state: SharedVoterState::empty(),
 			telemetry: None,
 			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			equivocation_guard: None,
+			finality_stall_threshold: None,
+			shared_stall_report: SharedStallReport::empty(),
 		};
 
 		run_grandpa_voter(grandpa_params).expect("all in order with client and network")
@@ -1056,6 +1068,9 @@ fn voter_persists_its_votes() {
 			shared_voter_state: SharedVoterState::empty(),
 			telemetry: None,
 			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			equivocation_guard: None,
+			finality_stall_threshold: None,
+			shared_stall_report: SharedStallReport::empty(),
 		};
 
 		run_grandpa_voter(grandpa_params)
@@ -1274,6 +1289,9 @@ fn voter_catches_up_to_latest_round_when_behind() {
 			shared_voter_state: SharedVoterState::empty(),
 			telemetry: None,
 			permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+			equivocation_guard: None,
+			finality_stall_threshold: None,
+			shared_stall_report: SharedStallReport::empty(),
 		};
 
 		Box::pin(run_grandpa_voter(grandpa_params).expect("all in order with client and network"))
@@ -1405,6 +1423,7 @@ where
 		telemetry: None,
 		_phantom: PhantomData,
 		permission_resolver: Arc::new(AlwaysPermissionGranted {}),
+		equivocation_guard: None,
 	}
 }
 
@@ -1753,6 +1772,62 @@ fn grandpa_environment_doesnt_send_equivocation_reports_for_itself() {
 	assert!(environment.report_equivocation(equivocation_proof).is_ok());
 }
 
+#[test]
+fn grandpa_environment_trips_equivocation_guard_for_local_keys() {
+	use finality_grandpa::voter::Environment;
+
+	let alice = Ed25519Keyring::Alice;
+	let bob = Ed25519Keyring::Bob;
+	let voters = make_ids(&[alice, bob]);
+
+	let mut net = GrandpaTestNet::new(TestApi::new(voters), 1, 0);
+	let peer = net.peer(0);
+	let network_service = peer.network_service().clone();
+	let link = peer.data.lock().take().unwrap();
+	let (keystore, _keystore_path) = create_keystore(alice);
+	let guard = EquivocationGuard::new(true, keystore.clone(), None, None).unwrap();
+	let mut environment = test_environment(&link, Some(keystore), network_service, ());
+	environment.equivocation_guard = Some(guard.clone());
+
+	let prevote_equivocation = |keyring: Ed25519Keyring| {
+		let signed_prevote = |target_number| {
+			let prevote = finality_grandpa::Prevote { target_hash: H256::random(), target_number };
+			(prevote, keyring.sign(&[]).into())
+		};
+		finality_grandpa::Equivocation {
+			round_number: 1,
+			identity: keyring.public().into(),
+			first: signed_prevote(1),
+			second: signed_prevote(2),
+		}
+	};
+	let precommit_equivocation = |keyring: Ed25519Keyring| {
+		let signed_precommit = |target_number| {
+			let precommit =
+				finality_grandpa::Precommit { target_hash: H256::random(), target_number };
+			(precommit, keyring.sign(&[]).into())
+		};
+		finality_grandpa::Equivocation {
+			round_number: 1,
+			identity: keyring.public().into(),
+			first: signed_precommit(1),
+			second: signed_precommit(2),
+		}
+	};
+
+	assert_eq!(environment.round_data(1).voter_id, Some(alice.public().into()));
+
+	// equivocations of a voter whose key isn't in our keystore don't trip the guard
+	environment.prevote_equivocation(1, prevote_equivocation(bob));
+	environment.precommit_equivocation(1, precommit_equivocation(bob));
+	assert!(!guard.is_tripped(bob.public().as_ref()));
+
+	// an equivocation of our own voter key trips the guard, and we stop voting with the key
+	environment.prevote_equivocation(1, prevote_equivocation(alice));
+	assert!(guard.is_tripped(alice.public().as_ref()));
+	assert_eq!(environment.round_data(2).voter_id, None);
+}
+
 #[test]
 fn revert_prunes_authority_changes() {
 	sp_tracing::try_init_simple();
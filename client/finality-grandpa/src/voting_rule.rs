@@ -22,9 +22,12 @@
 //! restrictions that are taken into account by the GRANDPA environment when
 //! selecting a finality target to vote on.
 
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{fs::File, future::Future, path::Path, pin::Pin, sync::Arc};
 
 use dyn_clone::DynClone;
+use log::{info, warn};
+use parking_lot::RwLock;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use sc_client_api::blockchain::HeaderBackend;
 use sp_runtime::{
@@ -169,6 +172,144 @@ where
 	}
 }
 
+/// Restrictions on finality set by the node operator, enforced by [`SharedVotingPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct VotingPolicy<Hash, Number> {
+	/// Stop enforcing the restrictions below once the best block is more than this many blocks
+	/// ahead of the last finalized block, so that they can't stall finality indefinitely.
+	pub max_finality_lag: Option<Number>,
+	/// Numbers of the blocks under investigation. We don't vote for them, nor for blocks above
+	/// them, on any fork.
+	pub under_investigation: Vec<Number>,
+	/// Hashes of the blocks whose finality is held. We don't vote for them, nor for their
+	/// descendants.
+	pub held: Vec<Hash>,
+}
+
+impl<Hash, Number> Default for VotingPolicy<Hash, Number> {
+	fn default() -> Self {
+		VotingPolicy { max_finality_lag: None, under_investigation: Vec::new(), held: Vec::new() }
+	}
+}
+
+impl<Hash, Number> VotingPolicy<Hash, Number>
+where
+	Hash: DeserializeOwned,
+	Number: DeserializeOwned,
+{
+	/// Load a voting policy from a JSON file.
+	pub fn from_json_file(path: &Path) -> Result<Self, String> {
+		let file = File::open(path)
+			.map_err(|e| format!("Error opening voting policy `{}`: {}", path.display(), e))?;
+		serde_json::from_reader(file)
+			.map_err(|e| format!("Error parsing voting policy `{}`: {}", path.display(), e))
+	}
+}
+
+impl<Hash, Number> VotingPolicy<Hash, Number> {
+	fn is_empty(&self) -> bool {
+		self.under_investigation.is_empty() && self.held.is_empty()
+	}
+}
+
+/// A voting rule that enforces a [`VotingPolicy`] which can be replaced at any time, e.g. through
+/// RPC, without restarting the voter.
+pub struct SharedVotingPolicy<Hash, Number> {
+	policy: Arc<RwLock<VotingPolicy<Hash, Number>>>,
+}
+
+impl<Hash, Number> Clone for SharedVotingPolicy<Hash, Number> {
+	fn clone(&self) -> Self {
+		SharedVotingPolicy { policy: self.policy.clone() }
+	}
+}
+
+impl<Hash: Clone, Number: Clone> SharedVotingPolicy<Hash, Number> {
+	/// Create a new `SharedVotingPolicy` enforcing `policy`.
+	pub fn new(policy: VotingPolicy<Hash, Number>) -> Self {
+		SharedVotingPolicy { policy: Arc::new(RwLock::new(policy)) }
+	}
+
+	/// Returns the policy currently enforced.
+	pub fn get(&self) -> VotingPolicy<Hash, Number> {
+		self.policy.read().clone()
+	}
+
+	/// Enforce `policy` from the next vote on.
+	pub fn set(&self, policy: VotingPolicy<Hash, Number>) {
+		*self.policy.write() = policy;
+		info!(target: "afg", "Updated the GRANDPA voting policy");
+	}
+}
+
+impl<Block, B> VotingRule<Block, B> for SharedVotingPolicy<Block::Hash, NumberFor<Block>>
+where
+	Block: BlockT,
+	B: HeaderBackend<Block>,
+{
+	fn restrict_vote(
+		&self,
+		backend: Arc<B>,
+		base: &Block::Header,
+		best_target: &Block::Header,
+		current_target: &Block::Header,
+	) -> VotingRuleResult<Block> {
+		let policy = self.get();
+		if policy.is_empty() {
+			return Box::pin(async { None })
+		}
+
+		if let Some(max_finality_lag) = policy.max_finality_lag {
+			use sp_arithmetic::traits::Saturating;
+
+			let finalized_number = backend.info().finalized_number;
+			if *best_target.number() > finalized_number.saturating_add(max_finality_lag) {
+				warn!(
+					target: "afg",
+					"Finality lags more than {} blocks behind the best block, \
+					 ignoring the restrictions of the voting policy.",
+					max_finality_lag,
+				);
+				return Box::pin(async { None })
+			}
+		}
+
+		Box::pin(std::future::ready(restrict_to_policy(&policy, &*backend, base, current_target)))
+	}
+}
+
+// walk backwards from the target down to the base, stopping before the lowest restricted block
+fn restrict_to_policy<Block, B>(
+	policy: &VotingPolicy<Block::Hash, NumberFor<Block>>,
+	backend: &B,
+	base: &Block::Header,
+	current_target: &Block::Header,
+) -> Option<(Block::Hash, NumberFor<Block>)>
+where
+	Block: BlockT,
+	B: HeaderBackend<Block>,
+{
+	let mut restricted = None;
+	let mut header = current_target.clone();
+
+	while header.number() > base.number() {
+		if policy.held.contains(&header.hash()) ||
+			policy.under_investigation.contains(header.number())
+		{
+			restricted = Some((*header.parent_hash(), *header.number() - One::one()));
+		}
+
+		header = match backend.header(BlockId::Hash(*header.parent_hash())) {
+			Ok(Some(header)) => header,
+			// we can't tell whether the remaining blocks are restricted, vote for the base.
+			_ => return Some((base.hash(), *base.number())),
+		};
+	}
+
+	restricted
+}
+
 // walk backwards until we find the target block
 fn find_target<Block, B>(
 	backend: &B,
@@ -435,4 +576,126 @@ mod tests {
 			assert_eq!(number, expected, "best = {}, lag = 2, base = {}", best_number, i);
 		}
 	}
+
+	/// A linear chain of headers, with the given number of finalized blocks.
+	struct Chain {
+		headers: Vec<Header>,
+		finalized_number: u64,
+	}
+
+	impl Chain {
+		fn new(len: u64, finalized_number: u64) -> Self {
+			let mut headers: Vec<Header> = Vec::new();
+			for number in 0..len {
+				let parent_hash = headers.last().map(|h| h.hash()).unwrap_or_default();
+				headers.push(Header::new(
+					number,
+					Default::default(),
+					Default::default(),
+					parent_hash,
+					Default::default(),
+				));
+			}
+			Chain { headers, finalized_number }
+		}
+	}
+
+	impl HeaderBackend<Block> for Chain {
+		fn header(&self, id: BlockId<Block>) -> sp_blockchain::Result<Option<Header>> {
+			Ok(match id {
+				BlockId::Hash(hash) => self.headers.iter().find(|h| h.hash() == hash).cloned(),
+				BlockId::Number(number) => self.headers.get(number as usize).cloned(),
+			})
+		}
+
+		fn info(&self) -> sp_blockchain::Info<Block> {
+			let best = self.headers.last().unwrap();
+			sp_blockchain::Info {
+				best_hash: best.hash(),
+				best_number: best.number,
+				genesis_hash: self.headers[0].hash(),
+				finalized_hash: self.headers[self.finalized_number as usize].hash(),
+				finalized_number: self.finalized_number,
+				finalized_state: None,
+				number_leaves: 1,
+				block_gap: None,
+			}
+		}
+
+		fn status(&self, id: BlockId<Block>) -> sp_blockchain::Result<sp_blockchain::BlockStatus> {
+			Ok(match self.header(id)? {
+				Some(_) => sp_blockchain::BlockStatus::InChain,
+				None => sp_blockchain::BlockStatus::Unknown,
+			})
+		}
+
+		fn number(&self, hash: <Block as BlockT>::Hash) -> sp_blockchain::Result<Option<u64>> {
+			Ok(self.header(BlockId::Hash(hash))?.map(|h| h.number))
+		}
+
+		fn hash(&self, number: u64) -> sp_blockchain::Result<Option<<Block as BlockT>::Hash>> {
+			Ok(self.headers.get(number as usize).map(|h| h.hash()))
+		}
+	}
+
+	#[test]
+	fn voting_policy_restricts_votes_below_held_and_investigated_blocks() {
+		let chain = Arc::new(Chain::new(10, 0));
+		let base = chain.headers[0].clone();
+		let best = chain.headers[9].clone();
+		let rule = SharedVotingPolicy::new(VotingPolicy::default());
+		let restrict = |rule: &SharedVotingPolicy<_, _>| {
+			futures::executor::block_on(VotingRule::<Block, Chain>::restrict_vote(
+				rule,
+				chain.clone(),
+				&base,
+				&best,
+				&best,
+			))
+			.map(|(_, number)| number)
+		};
+
+		// an empty policy doesn't restrict votes.
+		assert_eq!(restrict(&rule), None);
+
+		// we vote for the parent of a held block.
+		rule.set(VotingPolicy { held: vec![chain.headers[6].hash()], ..Default::default() });
+		assert_eq!(restrict(&rule), Some(5));
+
+		// holding a block that isn't in the voted chain has no effect.
+		rule.set(VotingPolicy { held: vec![Default::default()], ..Default::default() });
+		assert_eq!(restrict(&rule), None);
+
+		// the lowest restricted block applies.
+		rule.set(VotingPolicy {
+			held: vec![chain.headers[6].hash()],
+			under_investigation: vec![4],
+			..Default::default()
+		});
+		assert_eq!(restrict(&rule), Some(3));
+
+		// restrictions are released once finality lags too much.
+		rule.set(VotingPolicy { max_finality_lag: Some(8), ..rule.get() });
+		assert_eq!(restrict(&rule), None);
+		rule.set(VotingPolicy { max_finality_lag: Some(9), ..rule.get() });
+		assert_eq!(restrict(&rule), Some(3));
+		rule.set(VotingPolicy { max_finality_lag: Some(u64::MAX), ..rule.get() });
+		assert_eq!(restrict(&rule), Some(3));
+	}
+
+	#[test]
+	fn voting_policy_is_parsed_from_json() {
+		let policy: VotingPolicy<sp_core::H256, u64> =
+			serde_json::from_str(r#"{"maxFinalityLag":100,"underInvestigation":[42]}"#).unwrap();
+
+		assert_eq!(
+			policy,
+			VotingPolicy {
+				max_finality_lag: Some(100),
+				under_investigation: vec![42],
+				held: vec![]
+			},
+		);
+		assert!(serde_json::from_str::<VotingPolicy<sp_core::H256, u64>>(r#"{"hold":[]}"#).is_err());
+	}
 }
@@ -0,0 +1,456 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Encrypted-at-rest storage of the local keystore.
+//!
+//! An encrypted keystore directory contains a [`HEADER_FILE`] with the parameters used to derive
+//! the encryption key from the keystore password, and one randomly named `.key` file per key.
+//! The password is stretched with scrypt, every key file is sealed with ChaCha20-Poly1305 under a
+//! fresh nonce. Key type, public key and secret URI are only stored inside the sealed payload.
+
+use crate::{Error, Result};
+use chacha20poly1305::{
+	aead::{Aead, NewAead},
+	ChaCha20Poly1305, Key, Nonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sp_core::crypto::{ExposeSecret, KeyTypeId, SecretString};
+use std::{
+	collections::HashMap,
+	fs::{self, File},
+	io::Write,
+	path::{Path, PathBuf},
+};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Name of the file marking a keystore directory as encrypted.
+pub(crate) const HEADER_FILE: &str = "encryption.json";
+
+/// Extension of the encrypted key files.
+const KEY_FILE_EXTENSION: &str = "key";
+
+/// Version of the encrypted keystore format.
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// Plaintext sealed in the header, to tell a wrong password from corrupted key files.
+const PASSWORD_CHECK: &[u8] = b"substrate keystore";
+
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// The maximum `128 * r * p * 2^log_n` bytes processed by a key derivation with parameters read
+/// from untrusted data: 8 times the amount of the default parameters.
+const MAX_KDF_COST: u128 = 256 * 1024 * 1024;
+
+/// Parameters of the scrypt key derivation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct KdfParams {
+	log_n: u8,
+	r: u32,
+	p: u32,
+}
+
+impl Default for KdfParams {
+	/// 32 MiB of memory and roughly 100ms per derivation on current hardware.
+	fn default() -> Self {
+		Self { log_n: 15, r: 8, p: 1 }
+	}
+}
+
+impl KdfParams {
+	/// Checks that a derivation with these parameters takes bounded memory and time, before
+	/// deriving a key with parameters read from untrusted data.
+	pub fn ensure_bounded(&self) -> Result<()> {
+		if self.log_n > 32 ||
+			(128 * u128::from(self.r) * u128::from(self.p)) << self.log_n > MAX_KDF_COST
+		{
+			return Err(Error::Encryption("Key derivation parameters are too expensive".into()))
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+impl KdfParams {
+	/// Cheap parameters that keep tests fast.
+	pub fn insecure() -> Self {
+		Self { log_n: 4, r: 8, p: 1 }
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+	version: u32,
+	kdf: KdfParams,
+	salt: String,
+	check: Sealed,
+}
+
+/// Data sealed with the keystore key.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Sealed {
+	nonce: String,
+	ciphertext: String,
+}
+
+/// Decrypted content of a key file.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct KeyEntry {
+	key_type: String,
+	public: String,
+	/// Secret URI of the key.
+	pub suri: String,
+	/// Password the key is derived with, only set for keys migrated from a plaintext keystore
+	/// opened with a password.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub password: Option<String>,
+}
+
+impl KeyEntry {
+	fn id(&self) -> Option<(KeyTypeId, Vec<u8>)> {
+		let key_type = hex::decode(&self.key_type).ok()?.try_into().ok()?;
+		Some((KeyTypeId(key_type), hex::decode(&self.public).ok()?))
+	}
+}
+
+impl Drop for KeyEntry {
+	fn drop(&mut self) {
+		self.suri.zeroize();
+		if let Some(password) = self.password.as_mut() {
+			password.zeroize();
+		}
+	}
+}
+
+/// Authenticated encryption with a key derived from a password.
+pub(crate) struct Cipher(ChaCha20Poly1305);
+
+impl Cipher {
+	/// Derive the key from `password`.
+	pub fn derive(password: &SecretString, salt: &[u8], params: KdfParams) -> Result<Self> {
+		let params = scrypt::Params::new(params.log_n, params.r, params.p)
+			.map_err(|_| Error::Encryption("Invalid key derivation parameters".into()))?;
+		let mut key = Zeroizing::new([0u8; 32]);
+		scrypt::scrypt(password.expose_secret().as_bytes(), salt, &params, &mut key[..])
+			.map_err(|_| Error::Encryption("Key derivation failed".into()))?;
+		Ok(Self(ChaCha20Poly1305::new(Key::from_slice(&key[..]))))
+	}
+
+	/// Encrypt `data` under a fresh nonce.
+	pub fn seal(&self, data: &[u8]) -> Result<Sealed> {
+		let mut nonce = [0u8; NONCE_LEN];
+		OsRng.fill_bytes(&mut nonce);
+		let ciphertext = self
+			.0
+			.encrypt(Nonce::from_slice(&nonce), data)
+			.map_err(|_| Error::Encryption("Encryption failed".into()))?;
+		Ok(Sealed { nonce: hex::encode(nonce), ciphertext: hex::encode(ciphertext) })
+	}
+
+	/// Decrypt sealed data.
+	///
+	/// Returns `None` if the data was not sealed with this key or was tampered with.
+	pub fn open(&self, sealed: &Sealed) -> Option<Zeroizing<Vec<u8>>> {
+		let nonce = hex::decode(&sealed.nonce).ok().filter(|n| n.len() == NONCE_LEN)?;
+		let ciphertext = hex::decode(&sealed.ciphertext).ok()?;
+		self.0
+			.decrypt(Nonce::from_slice(&nonce), &ciphertext[..])
+			.ok()
+			.map(Zeroizing::new)
+	}
+}
+
+/// Keys of an encrypted keystore directory.
+pub(crate) struct EncryptedStore {
+	path: PathBuf,
+	cipher: Cipher,
+	/// Map over `(KeyTypeId, Raw public key)` -> key file
+	files: HashMap<(KeyTypeId, Vec<u8>), PathBuf>,
+}
+
+impl EncryptedStore {
+	/// Returns `true` if the keystore at `path` is encrypted.
+	pub fn exists(path: &Path) -> bool {
+		path.join(HEADER_FILE).exists()
+	}
+
+	/// Turn the directory at `path` into an empty encrypted keystore.
+	pub fn create(path: &Path, password: &SecretString, params: KdfParams) -> Result<Self> {
+		if Self::exists(path) {
+			return Err(Error::Encryption("Keystore is already encrypted".into()))
+		}
+		fs::create_dir_all(path)?;
+		let cipher = write_header(path, password, params)?;
+		Ok(Self { path: path.into(), cipher, files: HashMap::new() })
+	}
+
+	/// Open the encrypted keystore at `path`.
+	///
+	/// Key files that can not be decrypted are skipped with a warning.
+	pub fn open(path: &Path, password: &SecretString) -> Result<Self> {
+		let header: Header = read_json(&path.join(HEADER_FILE))?;
+		if header.version != FORMAT_VERSION {
+			return Err(Error::Encryption(format!(
+				"Unsupported keystore format version {}",
+				header.version
+			)))
+		}
+		let salt = hex::decode(&header.salt)
+			.map_err(|_| Error::Encryption("Invalid keystore header".into()))?;
+		let cipher = Cipher::derive(password, &salt, header.kdf)?;
+		match cipher.open(&header.check) {
+			Some(check) if &check[..] == PASSWORD_CHECK => {},
+			_ => return Err(Error::InvalidPassword),
+		}
+
+		let mut store = Self { path: path.into(), cipher, files: HashMap::new() };
+		for entry in fs::read_dir(path)? {
+			let file = entry?.path();
+			if file.extension().map_or(true, |ext| ext != KEY_FILE_EXTENSION) {
+				continue
+			}
+			match store.read(&file).and_then(|entry| {
+				entry.id().ok_or_else(|| Error::Encryption("Invalid key entry".into()))
+			}) {
+				Ok(id) => {
+					store.files.insert(id, file);
+				},
+				Err(e) => log::warn!(
+					target: "keystore",
+					"Ignoring key file {} that can not be decrypted: {}",
+					file.display(),
+					e,
+				),
+			}
+		}
+		Ok(store)
+	}
+
+	/// Get the decrypted entry of the given key.
+	pub fn get(&self, public: &[u8], key_type: KeyTypeId) -> Result<Option<KeyEntry>> {
+		self.files
+			.get(&(key_type, public.to_vec()))
+			.map(|file| self.read(file))
+			.transpose()
+	}
+
+	/// Returns `true` if the store contains the given key.
+	pub fn contains(&self, public: &[u8], key_type: KeyTypeId) -> bool {
+		self.files.contains_key(&(key_type, public.to_vec()))
+	}
+
+	/// Iterate over the key types and raw public keys of all keys.
+	pub fn keys(&self) -> impl Iterator<Item = &(KeyTypeId, Vec<u8>)> {
+		self.files.keys()
+	}
+
+	/// Store a key, replacing any previous entry of the same key.
+	pub fn insert(
+		&mut self,
+		key_type: KeyTypeId,
+		public: &[u8],
+		suri: &str,
+		password: Option<&str>,
+	) -> Result<()> {
+		let id = (key_type, public.to_vec());
+		let file = self.files.get(&id).cloned().unwrap_or_else(|| self.new_key_file());
+		let entry = KeyEntry {
+			key_type: hex::encode(key_type.0),
+			public: hex::encode(public),
+			suri: suri.into(),
+			password: password.map(Into::into),
+		};
+		Self::write(&self.cipher, &file, &entry)?;
+		self.files.insert(id, file);
+		Ok(())
+	}
+
+	/// Remove a key, returns `false` if the key is not in the store.
+	pub fn remove(&mut self, public: &[u8], key_type: KeyTypeId) -> Result<bool> {
+		match self.files.remove(&(key_type, public.to_vec())) {
+			Some(file) => {
+				fs::remove_file(file)?;
+				Ok(true)
+			},
+			None => Ok(false),
+		}
+	}
+
+	/// Re-encrypt all keys with a key derived from `password`.
+	///
+	/// Keys are written to new files before the header is replaced, so that an interruption
+	/// leaves either the old or the new password working.
+	pub fn change_password(&mut self, password: &SecretString, params: KdfParams) -> Result<()> {
+		let (cipher, header) = new_header(password, params)?;
+
+		let mut files = HashMap::with_capacity(self.files.len());
+		for (id, old_file) in &self.files {
+			let entry = self.read(old_file)?;
+			let file = self.new_key_file();
+			Self::write(&cipher, &file, &entry)?;
+			files.insert(id.clone(), file);
+		}
+
+		write_json(&self.path.join(HEADER_FILE), &header)?;
+
+		for old_file in self.files.values() {
+			fs::remove_file(old_file)?;
+		}
+		self.cipher = cipher;
+		self.files = files;
+		Ok(())
+	}
+
+	fn new_key_file(&self) -> PathBuf {
+		let mut name = [0u8; 16];
+		OsRng.fill_bytes(&mut name);
+		self.path.join(hex::encode(name)).with_extension(KEY_FILE_EXTENSION)
+	}
+
+	fn read(&self, file: &Path) -> Result<KeyEntry> {
+		let sealed: Sealed = read_json(file)?;
+		let data = self.cipher.open(&sealed).ok_or(Error::InvalidPassword)?;
+		Ok(serde_json::from_slice(&data)?)
+	}
+
+	fn write(cipher: &Cipher, file: &Path, entry: &KeyEntry) -> Result<()> {
+		let data = Zeroizing::new(serde_json::to_vec(entry)?);
+		write_json(file, &cipher.seal(&data)?)
+	}
+}
+
+/// Generate a random salt for the key derivation.
+pub(crate) fn new_salt() -> [u8; SALT_LEN] {
+	let mut salt = [0u8; SALT_LEN];
+	OsRng.fill_bytes(&mut salt);
+	salt
+}
+
+fn new_header(password: &SecretString, params: KdfParams) -> Result<(Cipher, Header)> {
+	let salt = new_salt();
+	let cipher = Cipher::derive(password, &salt, params)?;
+	let check = cipher.seal(PASSWORD_CHECK)?;
+	Ok((cipher, Header { version: FORMAT_VERSION, kdf: params, salt: hex::encode(salt), check }))
+}
+
+fn write_header(path: &Path, password: &SecretString, params: KdfParams) -> Result<Cipher> {
+	let (cipher, header) = new_header(password, params)?;
+	write_json(&path.join(HEADER_FILE), &header)?;
+	Ok(cipher)
+}
+
+fn read_json<T: DeserializeOwned>(file: &Path) -> Result<T> {
+	Ok(serde_json::from_reader(File::open(file)?)?)
+}
+
+/// Write `value` to `path`, replacing it atomically.
+///
+/// The value is written to a temporary file next to `path`, which is synced to disk and then
+/// renamed to `path`, so that a crash leaves either the previous or the new content behind.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+	let mut tmp = path.as_os_str().to_owned();
+	tmp.push(".tmp");
+	let tmp = PathBuf::from(tmp);
+	let mut file = File::create(&tmp)?;
+
+	#[cfg(target_family = "unix")]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		file.set_permissions(fs::Permissions::from_mode(0o600))?;
+	}
+
+	serde_json::to_writer(&file, value)?;
+	file.flush()?;
+	file.sync_all()?;
+	fs::rename(&tmp, path)?;
+
+	// Persist the rename itself.
+	#[cfg(target_family = "unix")]
+	if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		File::open(dir)?.sync_all()?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+	use tempfile::TempDir;
+
+	const KEY_TYPE: KeyTypeId = KeyTypeId(*b"test");
+
+	fn secret(password: &str) -> SecretString {
+		FromStr::from_str(password).unwrap()
+	}
+
+	#[test]
+	fn keys_survive_reopening_and_password_change() {
+		let temp_dir = TempDir::new().unwrap();
+		let mut store =
+			EncryptedStore::create(temp_dir.path(), &secret("password"), KdfParams::insecure())
+				.unwrap();
+		store.insert(KEY_TYPE, &[1; 32], "//Alice", None).unwrap();
+		store.insert(KEY_TYPE, &[2; 32], "//Bob", Some("legacy")).unwrap();
+		assert!(EncryptedStore::create(
+			temp_dir.path(),
+			&secret("password"),
+			KdfParams::insecure()
+		)
+		.is_err());
+
+		let store = EncryptedStore::open(temp_dir.path(), &secret("password")).unwrap();
+		let entry = store.get(&[2; 32], KEY_TYPE).unwrap().unwrap();
+		assert_eq!((entry.suri.as_str(), entry.password.as_deref()), ("//Bob", Some("legacy")));
+		assert!(store.get(&[3; 32], KEY_TYPE).unwrap().is_none());
+		assert!(matches!(
+			EncryptedStore::open(temp_dir.path(), &secret("wrong")),
+			Err(Error::InvalidPassword)
+		));
+
+		let mut store = EncryptedStore::open(temp_dir.path(), &secret("password")).unwrap();
+		store.change_password(&secret("new"), KdfParams::insecure()).unwrap();
+		assert!(store.remove(&[1; 32], KEY_TYPE).unwrap());
+		assert!(!store.remove(&[1; 32], KEY_TYPE).unwrap());
+
+		let store = EncryptedStore::open(temp_dir.path(), &secret("new")).unwrap();
+		assert_eq!(store.keys().collect::<Vec<_>>(), vec![&(KEY_TYPE, vec![2; 32])]);
+		assert_eq!(store.get(&[2; 32], KEY_TYPE).unwrap().unwrap().suri, "//Bob");
+		assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+	}
+
+	#[test]
+	fn tampered_key_files_are_ignored() {
+		let temp_dir = TempDir::new().unwrap();
+		let mut store =
+			EncryptedStore::create(temp_dir.path(), &secret("password"), KdfParams::insecure())
+				.unwrap();
+		store.insert(KEY_TYPE, &[1; 32], "//Alice", None).unwrap();
+
+		let file = store.files.values().next().unwrap().clone();
+		let mut sealed: Sealed = read_json(&file).unwrap();
+		sealed
+			.ciphertext
+			.replace_range(0..2, if &sealed.ciphertext[0..2] == "00" { "01" } else { "00" });
+		write_json(&file, &sealed).unwrap();
+
+		let store = EncryptedStore::open(temp_dir.path(), &secret("password")).unwrap();
+		assert_eq!(store.keys().count(), 0);
+	}
+}
@@ -0,0 +1,148 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Export of keys to move them between keystores.
+//!
+//! Exported keys are sealed with a password the same way keys of an encrypted keystore are, see
+//! [`crate::LocalKeystore::encrypt`].
+
+use crate::{
+	encrypted::{new_salt, Cipher, KdfParams, Sealed, FORMAT_VERSION},
+	Error, Result,
+};
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{KeyTypeId, SecretString};
+use zeroize::{Zeroize, Zeroizing};
+
+/// A key exported from a keystore.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExportedKey {
+	/// Key type of the key.
+	pub key_type: KeyTypeId,
+	/// Raw public key.
+	pub public: Vec<u8>,
+	/// Secret URI the key is derived from without any password.
+	pub suri: String,
+}
+
+impl Drop for ExportedKey {
+	fn drop(&mut self) {
+		self.suri.zeroize();
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+	version: u32,
+	kdf: KdfParams,
+	salt: String,
+	keys: Sealed,
+}
+
+/// Seal exported keys with `password`.
+pub fn seal(keys: &[ExportedKey], password: &SecretString) -> Result<Vec<u8>> {
+	seal_with(keys, password, KdfParams::default())
+}
+
+pub(crate) fn seal_with(
+	keys: &[ExportedKey],
+	password: &SecretString,
+	params: KdfParams,
+) -> Result<Vec<u8>> {
+	let salt = new_salt();
+	let cipher = Cipher::derive(password, &salt, params)?;
+	let data = Zeroizing::new(serde_json::to_vec(keys)?);
+	let bundle = Bundle {
+		version: FORMAT_VERSION,
+		kdf: params,
+		salt: hex::encode(salt),
+		keys: cipher.seal(&data)?,
+	};
+	Ok(serde_json::to_vec(&bundle)?)
+}
+
+/// Open keys sealed with [`seal`].
+///
+/// The sealed keys may come from another node, so the key derivation parameters they carry are
+/// rejected when deriving the key would take more than a few times the default memory and time.
+pub fn open(data: &[u8], password: &SecretString) -> Result<Vec<ExportedKey>> {
+	let bundle: Bundle = serde_json::from_slice(data)?;
+	if bundle.version != FORMAT_VERSION {
+		return Err(Error::Encryption(format!(
+			"Unsupported key export format version {}",
+			bundle.version
+		)))
+	}
+	bundle.kdf.ensure_bounded()?;
+	let salt = hex::decode(&bundle.salt)
+		.map_err(|_| Error::Encryption("Invalid key export salt".into()))?;
+	let data = Cipher::derive(password, &salt, bundle.kdf)?
+		.open(&bundle.keys)
+		.ok_or(Error::InvalidPassword)?;
+	Ok(serde_json::from_slice(&data)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	#[test]
+	fn sealed_keys_require_password() {
+		let keys = vec![ExportedKey {
+			key_type: KeyTypeId(*b"test"),
+			public: vec![1; 32],
+			suri: "//Alice".into(),
+		}];
+		let password = SecretString::from_str("password").unwrap();
+		let sealed = seal_with(&keys, &password, KdfParams::insecure()).unwrap();
+		assert!(!String::from_utf8_lossy(&sealed).contains("Alice"));
+
+		let opened = open(&sealed, &password).unwrap();
+		assert_eq!(opened.len(), 1);
+		assert_eq!(
+			(opened[0].key_type, &opened[0].public, opened[0].suri.as_str()),
+			(KeyTypeId(*b"test"), &vec![1; 32], "//Alice")
+		);
+
+		let wrong = SecretString::from_str("wrong").unwrap();
+		assert!(matches!(open(&sealed, &wrong), Err(Error::InvalidPassword)));
+	}
+
+	#[test]
+	fn open_rejects_expensive_key_derivation() {
+		let password = SecretString::from_str("password").unwrap();
+		let sealed = seal_with(&[], &password, KdfParams::insecure()).unwrap();
+		let with_kdf = |log_n: u8, r: u32, p: u32| {
+			let mut bundle: serde_json::Value = serde_json::from_slice(&sealed).unwrap();
+			bundle["kdf"] = serde_json::json!({ "log_n": log_n, "r": r, "p": p });
+			serde_json::to_vec(&bundle).unwrap()
+		};
+
+		let expensive = [(40, 8, 1), (22, 8, 1), (15, 8, 16), (15, 1024, 1), (4, 8, u32::MAX)];
+		for (log_n, r, p) in expensive {
+			assert!(
+				matches!(open(&with_kdf(log_n, r, p), &password), Err(Error::Encryption(_))),
+				"log_n: {}, r: {}, p: {}",
+				log_n,
+				r,
+				p,
+			);
+		}
+		assert!(open(&with_kdf(4, 8, 1), &password).unwrap().is_empty());
+	}
+}
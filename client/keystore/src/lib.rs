@@ -23,10 +23,21 @@ use sp_core::crypto::KeyTypeId;
 use sp_keystore::Error as TraitError;
 use std::io;
 
+/// Encrypted storage of the local keystore
+mod encrypted;
+
+/// Export of keys sealed with a password
+pub mod export;
+pub use export::ExportedKey;
+
 /// Local keystore implementation
 mod local;
 pub use local::LocalKeystore;
 
+/// Remote signer keystore implementation
+pub mod remote;
+pub use remote::{RemoteKeystore, RemoteKeystoreConfig};
+
 /// Keystore error.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -54,6 +65,18 @@ pub enum Error {
 	/// Keystore unavailable
 	#[error("Keystore unavailable")]
 	Unavailable,
+	/// Remote signer error
+	#[error("Remote signer: {0}")]
+	Remote(String),
+	/// Password of an encrypted keystore is missing or incorrect
+	#[error("Keystore password is missing or incorrect")]
+	InvalidPassword,
+	/// Keystore encryption error
+	#[error("Keystore encryption: {0}")]
+	Encryption(String),
+	/// Key is not in the keystore
+	#[error("Key not found in the keystore")]
+	KeyNotFound(KeyTypeId),
 }
 
 /// Keystore Result
@@ -68,6 +91,8 @@ impl From<Error> for TraitError {
 			Error::Unavailable => TraitError::Unavailable,
 			Error::Io(e) => TraitError::Other(e.to_string()),
 			Error::Json(e) => TraitError::Other(e.to_string()),
+			Error::Remote(e) | Error::Encryption(e) => TraitError::Other(e),
+			Error::InvalidPassword | Error::KeyNotFound(_) => TraitError::Other(error.to_string()),
 		}
 	}
 }
@@ -39,18 +39,80 @@ use std::{
 	sync::Arc,
 };
 
-use crate::{Error, Result};
+use crate::{
+	encrypted::{EncryptedStore, KdfParams},
+	Error, ExportedKey, Result,
+};
 
 /// A local based keystore that is either memory-based or filesystem-based.
 pub struct LocalKeystore(RwLock<KeystoreInner>);
 
 impl LocalKeystore {
 	/// Create a local keystore from filesystem.
+	///
+	/// If the keystore is encrypted the password is used to decrypt the keys, otherwise it is
+	/// appended to the phrase of every key.
 	pub fn open<T: Into<PathBuf>>(path: T, password: Option<SecretString>) -> Result<Self> {
 		let inner = KeystoreInner::open(path, password)?;
 		Ok(Self(RwLock::new(inner)))
 	}
 
+	/// Encrypt the keystore at `path` with `password`.
+	///
+	/// Keys of a plaintext keystore are moved into the encrypted store, they keep being derived
+	/// with `legacy_password`. Creates an empty encrypted keystore if there are no keys yet.
+	pub fn encrypt<T: Into<PathBuf>>(
+		path: T,
+		legacy_password: Option<SecretString>,
+		password: SecretString,
+	) -> Result<Self> {
+		let inner = KeystoreInner::encrypt(path, legacy_password, password, KdfParams::default())?;
+		Ok(Self(RwLock::new(inner)))
+	}
+
+	/// Returns `true` if the keys are encrypted at rest.
+	pub fn is_encrypted(&self) -> bool {
+		self.0.read().encrypted.is_some()
+	}
+
+	/// Re-encrypt the keys of an encrypted keystore with a new password.
+	pub fn change_password(&self, password: SecretString) -> Result<()> {
+		self.0.write().change_password(password, KdfParams::default())
+	}
+
+	/// Get the key types and raw public keys of all keys in the keystore.
+	pub fn public_keys(&self) -> Result<Vec<(KeyTypeId, Vec<u8>)>> {
+		self.0.read().all_public_keys()
+	}
+
+	/// Export the given keys, see [`crate::export`].
+	///
+	/// The keystore password is moved into the exported secret URI of keys derived with it.
+	pub fn export_keys(&self, keys: &[(Vec<u8>, KeyTypeId)]) -> Result<Vec<ExportedKey>> {
+		let inner = self.0.read();
+		keys.iter()
+			.map(|(public, key_type)| {
+				let (phrase, password) = inner
+					.key_secret_by_type(public, *key_type)?
+					.ok_or(Error::KeyNotFound(*key_type))?;
+				let suri = match password {
+					// The password of a secret URI starts at the first `///`.
+					Some(password) =>
+						format!("{}///{}", phrase.split("///").next().unwrap_or_default(), password),
+					None => phrase,
+				};
+				Ok(ExportedKey { key_type: *key_type, public: public.clone(), suri })
+			})
+			.collect()
+	}
+
+	/// Remove a key from the keystore.
+	///
+	/// Returns `Ok(false)` if the key doesn't exist.
+	pub fn remove_key(&self, key_type: KeyTypeId, public: &[u8]) -> Result<bool> {
+		self.0.write().remove_key(public, key_type)
+	}
+
 	/// Create a local keystore in memory.
 	pub fn in_memory() -> Self {
 		let inner = KeystoreInner::new_in_memory();
@@ -318,9 +380,7 @@ impl SyncCryptoStore for LocalKeystore {
 	}
 
 	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
-		public_keys
-			.iter()
-			.all(|(p, t)| self.0.read().key_phrase_by_type(p, *t).ok().flatten().is_some())
+		public_keys.iter().all(|(p, t)| self.0.read().has_key(p, *t))
 	}
 
 	fn sr25519_vrf_sign(
@@ -373,7 +433,10 @@ struct KeystoreInner {
 	path: Option<PathBuf>,
 	/// Map over `(KeyTypeId, Raw public key)` -> `Key phrase/seed`
 	additional: HashMap<(KeyTypeId, Vec<u8>), String>,
+	/// Password appended to the phrase of the keys, never set for encrypted stores.
 	password: Option<SecretString>,
+	/// Encrypted file system store, used instead of the plaintext files if set.
+	encrypted: Option<EncryptedStore>,
 }
 
 impl KeystoreInner {
@@ -384,7 +447,75 @@ impl KeystoreInner {
 		let path = path.into();
 		fs::create_dir_all(&path)?;
 
-		Ok(Self { path: Some(path), additional: HashMap::new(), password })
+		if EncryptedStore::exists(&path) {
+			let encrypted =
+				EncryptedStore::open(&path, password.as_ref().ok_or(Error::InvalidPassword)?)?;
+			return Ok(Self {
+				path: Some(path),
+				additional: HashMap::new(),
+				password: None,
+				encrypted: Some(encrypted),
+			})
+		}
+
+		Ok(Self { path: Some(path), additional: HashMap::new(), password, encrypted: None })
+	}
+
+	/// Encrypt the store at the given path, see [`LocalKeystore::encrypt`].
+	///
+	/// Every plaintext key is checked against its public key before anything is written, and
+	/// only removed once it was written to the encrypted store. An interrupted migration is
+	/// resumed by running it again with the same password.
+	fn encrypt<T: Into<PathBuf>>(
+		path: T,
+		legacy_password: Option<SecretString>,
+		password: SecretString,
+		params: KdfParams,
+	) -> Result<Self> {
+		let path = path.into();
+		fs::create_dir_all(&path)?;
+		let legacy = Self {
+			path: Some(path.clone()),
+			additional: HashMap::new(),
+			password: legacy_password,
+			encrypted: None,
+		};
+
+		let mut keys = Vec::new();
+		for (key_type, public) in legacy.stored_public_keys()? {
+			let phrase = legacy.key_phrase_by_type(&public, key_type)?.ok_or(Error::Unavailable)?;
+			if !derives_public(&phrase, legacy.password(), &public) {
+				return Err(Error::PublicKeyMismatch)
+			}
+			keys.push((key_type, public, phrase));
+		}
+
+		let mut encrypted = if EncryptedStore::exists(&path) {
+			EncryptedStore::open(&path, &password)?
+		} else {
+			EncryptedStore::create(&path, &password, params)?
+		};
+		for (key_type, public, phrase) in keys {
+			encrypted.insert(key_type, &public, &phrase, legacy.password())?;
+			if let Some(file) = legacy.key_file_path(&public, key_type) {
+				fs::remove_file(file)?;
+			}
+		}
+
+		Ok(Self {
+			path: Some(path),
+			additional: HashMap::new(),
+			password: None,
+			encrypted: Some(encrypted),
+		})
+	}
+
+	/// Re-encrypt the keys with a new password.
+	fn change_password(&mut self, password: SecretString, params: KdfParams) -> Result<()> {
+		self.encrypted
+			.as_mut()
+			.ok_or_else(|| Error::Encryption("Keystore is not encrypted".into()))?
+			.change_password(&password, params)
 	}
 
 	/// Get the password for this store.
@@ -394,7 +525,7 @@ impl KeystoreInner {
 
 	/// Create a new in-memory store.
 	fn new_in_memory() -> Self {
-		Self { path: None, additional: HashMap::new(), password: None }
+		Self { path: None, additional: HashMap::new(), password: None, encrypted: None }
 	}
 
 	/// Get the key phrase for the given public key and key type from the in-memory store.
@@ -414,8 +545,10 @@ impl KeystoreInner {
 	/// Insert a new key with anonymous crypto.
 	///
 	/// Places it into the file system store, if a path is configured.
-	fn insert_unknown(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<()> {
-		if let Some(path) = self.key_file_path(public, key_type) {
+	fn insert_unknown(&mut self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<()> {
+		if let Some(encrypted) = self.encrypted.as_mut() {
+			encrypted.insert(key_type, public, suri, None)?;
+		} else if let Some(path) = self.key_file_path(public, key_type) {
 			Self::write_to_file(path, suri)?;
 		}
 
@@ -428,7 +561,9 @@ impl KeystoreInner {
 	/// it into the memory cache only.
 	fn generate_by_type<Pair: PairT>(&mut self, key_type: KeyTypeId) -> Result<Pair> {
 		let (pair, phrase, _) = Pair::generate_with_phrase(self.password());
-		if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
+		if let Some(encrypted) = self.encrypted.as_mut() {
+			encrypted.insert(key_type, pair.public().as_slice(), &phrase, None)?;
+		} else if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
 			Self::write_to_file(path, &phrase)?;
 		} else {
 			self.insert_ephemeral_pair(&pair, &phrase, key_type);
@@ -465,7 +600,36 @@ impl KeystoreInner {
 		Ok(pair)
 	}
 
+	/// Returns `true` if the store contains the given key.
+	fn has_key(&self, public: &[u8], key_type: KeyTypeId) -> bool {
+		match &self.encrypted {
+			Some(encrypted) =>
+				self.get_additional_pair(public, key_type).is_some() ||
+					encrypted.contains(public, key_type),
+			None => self.key_phrase_by_type(public, key_type).ok().flatten().is_some(),
+		}
+	}
+
+	/// Get the key phrase and the password it is derived with for a given public key and key
+	/// type.
+	fn key_secret_by_type(
+		&self,
+		public: &[u8],
+		key_type: KeyTypeId,
+	) -> Result<Option<(String, Option<String>)>> {
+		let password = self.password().map(Into::into);
+		match &self.encrypted {
+			Some(encrypted) if self.get_additional_pair(public, key_type).is_none() =>
+				Ok(encrypted
+					.get(public, key_type)?
+					.map(|entry| (entry.suri.clone(), entry.password.clone()))),
+			_ => Ok(self.key_phrase_by_type(public, key_type)?.map(|phrase| (phrase, password))),
+		}
+	}
+
 	/// Get the key phrase for a given public key and key type.
+	///
+	/// Only looks at the plaintext file system store.
 	fn key_phrase_by_type(&self, public: &[u8], key_type: KeyTypeId) -> Result<Option<String>> {
 		if let Some(phrase) = self.get_additional_pair(public, key_type) {
 			return Ok(Some(phrase.clone()))
@@ -492,13 +656,15 @@ impl KeystoreInner {
 		public: &Pair::Public,
 		key_type: KeyTypeId,
 	) -> Result<Option<Pair>> {
-		let phrase = if let Some(p) = self.key_phrase_by_type(public.as_slice(), key_type)? {
-			p
-		} else {
-			return Ok(None)
-		};
+		let (phrase, password) =
+			if let Some(s) = self.key_secret_by_type(public.as_slice(), key_type)? {
+				s
+			} else {
+				return Ok(None)
+			};
 
-		let pair = Pair::from_string(&phrase, self.password()).map_err(|_| Error::InvalidPhrase)?;
+		let pair =
+			Pair::from_string(&phrase, password.as_deref()).map_err(|_| Error::InvalidPhrase)?;
 
 		if &pair.public() == public {
 			Ok(Some(pair))
@@ -520,13 +686,27 @@ impl KeystoreInner {
 
 	/// Returns a list of raw public keys filtered by `KeyTypeId`
 	fn raw_public_keys(&self, id: KeyTypeId) -> Result<Vec<Vec<u8>>> {
-		let mut public_keys: Vec<Vec<u8>> = self
-			.additional
-			.keys()
+		Ok(self
+			.all_public_keys()?
 			.into_iter()
-			.filter_map(|k| if k.0 == id { Some(k.1.clone()) } else { None })
-			.collect();
+			.filter_map(|(key_type, public)| if key_type == id { Some(public) } else { None })
+			.collect())
+	}
+
+	/// Returns the key types and raw public keys of all keys.
+	fn all_public_keys(&self) -> Result<Vec<(KeyTypeId, Vec<u8>)>> {
+		let mut public_keys: Vec<_> = self.additional.keys().cloned().collect();
+		public_keys.extend(self.stored_public_keys()?);
+		Ok(public_keys)
+	}
+
+	/// Returns the key types and raw public keys of the keys in the file system store.
+	fn stored_public_keys(&self) -> Result<Vec<(KeyTypeId, Vec<u8>)>> {
+		if let Some(encrypted) = &self.encrypted {
+			return Ok(encrypted.keys().cloned().collect())
+		}
 
+		let mut public_keys = Vec::new();
 		if let Some(path) = &self.path {
 			for entry in fs::read_dir(&path)? {
 				let entry = entry?;
@@ -536,11 +716,8 @@ impl KeystoreInner {
 				if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
 					match hex::decode(name) {
 						Ok(ref hex) if hex.len() > 4 => {
-							if hex[0..4] != id.0 {
-								continue
-							}
-							let public = hex[4..].to_vec();
-							public_keys.push(public);
+							let key_type = KeyTypeId([hex[0], hex[1], hex[2], hex[3]]);
+							public_keys.push((key_type, hex[4..].to_vec()));
 						},
 						_ => continue,
 					}
@@ -551,6 +728,20 @@ impl KeystoreInner {
 		Ok(public_keys)
 	}
 
+	/// Remove the given key from memory and the file system store.
+	fn remove_key(&mut self, public: &[u8], key_type: KeyTypeId) -> Result<bool> {
+		let mut removed = self.additional.remove(&(key_type, public.to_vec())).is_some();
+		if let Some(encrypted) = self.encrypted.as_mut() {
+			removed |= encrypted.remove(public, key_type)?;
+		} else if let Some(path) = self.key_file_path(public, key_type) {
+			if path.exists() {
+				fs::remove_file(path)?;
+				removed = true;
+			}
+		}
+		Ok(removed)
+	}
+
 	/// Get a key pair for the given public key.
 	///
 	/// Returns `Ok(None)` if the key doesn't exist, `Ok(Some(_))` if the key exists or `Err(_)`
@@ -564,6 +755,17 @@ impl KeystoreInner {
 	}
 }
 
+/// Returns `true` if `phrase` derives `public` with any of the supported crypto schemes.
+fn derives_public(phrase: &str, password: Option<&str>, public: &[u8]) -> bool {
+	fn derives<Pair: PairT>(phrase: &str, password: Option<&str>, public: &[u8]) -> bool {
+		Pair::from_string(phrase, password).map_or(false, |pair| pair.public().as_slice() == public)
+	}
+
+	derives::<sr25519::Pair>(phrase, password, public) ||
+		derives::<ed25519::Pair>(phrase, password, public) ||
+		derives::<ecdsa::Pair>(phrase, password, public)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -574,6 +776,10 @@ mod tests {
 
 	const TEST_KEY_TYPE: KeyTypeId = KeyTypeId(*b"test");
 
+	fn secret(password: &str) -> SecretString {
+		FromStr::from_str(password).unwrap()
+	}
+
 	impl KeystoreInner {
 		fn insert_ephemeral_from_seed<Pair: AppPair>(&mut self, seed: &str) -> Result<Pair> {
 			self.insert_ephemeral_from_seed_by_type::<Pair::Generic>(seed, Pair::ID)
@@ -717,7 +923,7 @@ mod tests {
 	#[test]
 	fn store_unknown_and_extract_it() {
 		let temp_dir = TempDir::new().unwrap();
-		let store = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let mut store = KeystoreInner::open(temp_dir.path(), None).unwrap();
 
 		let secret_uri = "//Alice";
 		let key_pair = sr25519::AppPair::from_string(secret_uri, None).expect("Generates key pair");
@@ -784,4 +990,131 @@ mod tests {
 
 		assert_eq!(0o100600, permissions.mode());
 	}
+
+	#[test]
+	fn encrypted_store_hides_keys_and_requires_password() {
+		let temp_dir = TempDir::new().unwrap();
+		let mut store = KeystoreInner::encrypt(
+			temp_dir.path(),
+			None,
+			secret("password"),
+			KdfParams::insecure(),
+		)
+		.unwrap();
+
+		let pair: ed25519::AppPair = store.generate().unwrap();
+		let key_file = store.key_file_path(pair.public().as_ref(), ed25519::AppPair::ID).unwrap();
+		assert!(!key_file.exists());
+		for entry in fs::read_dir(temp_dir.path()).unwrap() {
+			let content = fs::read_to_string(entry.unwrap().path()).unwrap();
+			assert!(!content.contains(&hex::encode(pair.public())));
+		}
+
+		assert!(matches!(KeystoreInner::open(temp_dir.path(), None), Err(Error::InvalidPassword)));
+		assert!(matches!(
+			KeystoreInner::open(temp_dir.path(), Some(secret("wrong"))),
+			Err(Error::InvalidPassword)
+		));
+
+		let store = KeystoreInner::open(temp_dir.path(), Some(secret("password"))).unwrap();
+		assert_eq!(store.public_keys::<ed25519::AppPublic>().unwrap(), vec![pair.public()]);
+		assert_eq!(
+			pair.public(),
+			store.key_pair::<ed25519::AppPair>(&pair.public()).unwrap().unwrap().public(),
+		);
+	}
+
+	#[test]
+	fn plaintext_keystore_is_encrypted() {
+		let temp_dir = TempDir::new().unwrap();
+		let mut store = KeystoreInner::open(temp_dir.path(), Some(secret("legacy"))).unwrap();
+		let pair: sr25519::AppPair = store.generate().unwrap();
+		store
+			.insert_unknown(
+				SR25519,
+				"//Alice",
+				sr25519::Pair::from_string("//Alice///legacy", None).unwrap().public().as_ref(),
+			)
+			.unwrap();
+
+		// The legacy password has to match the keys
+		assert!(matches!(
+			KeystoreInner::encrypt(
+				temp_dir.path(),
+				None,
+				secret("password"),
+				KdfParams::insecure()
+			),
+			Err(Error::PublicKeyMismatch)
+		));
+		assert!(store.key_file_path(pair.public().as_ref(), SR25519).unwrap().exists());
+
+		KeystoreInner::encrypt(
+			temp_dir.path(),
+			Some(secret("legacy")),
+			secret("password"),
+			KdfParams::insecure(),
+		)
+		.unwrap();
+		assert!(!store.key_file_path(pair.public().as_ref(), SR25519).unwrap().exists());
+
+		let store = KeystoreInner::open(temp_dir.path(), Some(secret("password"))).unwrap();
+		assert_eq!(store.all_public_keys().unwrap().len(), 2);
+		assert_eq!(
+			pair.public(),
+			store.key_pair::<sr25519::AppPair>(&pair.public()).unwrap().unwrap().public(),
+		);
+	}
+
+	#[test]
+	fn password_of_encrypted_store_can_be_changed() {
+		let temp_dir = TempDir::new().unwrap();
+		let mut store = KeystoreInner::encrypt(
+			temp_dir.path(),
+			None,
+			secret("password"),
+			KdfParams::insecure(),
+		)
+		.unwrap();
+		let pair: ed25519::AppPair = store.generate().unwrap();
+
+		store.change_password(secret("new password"), KdfParams::insecure()).unwrap();
+		assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+		assert!(matches!(
+			KeystoreInner::open(temp_dir.path(), Some(secret("password"))),
+			Err(Error::InvalidPassword)
+		));
+
+		let store = KeystoreInner::open(temp_dir.path(), Some(secret("new password"))).unwrap();
+		assert_eq!(
+			pair.public(),
+			store.key_pair::<ed25519::AppPair>(&pair.public()).unwrap().unwrap().public(),
+		);
+
+		let mut plaintext = KeystoreInner::open(TempDir::new().unwrap().path(), None).unwrap();
+		assert!(plaintext.change_password(secret("password"), KdfParams::insecure()).is_err());
+	}
+
+	#[test]
+	fn keys_can_be_removed() {
+		let temp_dir = TempDir::new().unwrap();
+		let mut plaintext = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let mut encrypted = KeystoreInner::encrypt(
+			temp_dir.path().join("encrypted"),
+			None,
+			secret("password"),
+			KdfParams::insecure(),
+		)
+		.unwrap();
+
+		for store in [&mut plaintext, &mut encrypted] {
+			let pair: ed25519::AppPair = store.generate().unwrap();
+			let public = pair.public().to_raw_vec();
+
+			assert!(store.remove_key(&public, ed25519::AppPair::ID).unwrap());
+			assert!(!store.remove_key(&public, ed25519::AppPair::ID).unwrap());
+			assert!(store.public_keys::<ed25519::AppPublic>().unwrap().is_empty());
+			assert!(store.key_pair::<ed25519::AppPair>(&pair.public()).unwrap().is_none());
+		}
+	}
 }
@@ -0,0 +1,632 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Keystore forwarding all operations to a remote signer.
+//!
+//! The keys never leave the signer, the node only learns the public keys and the signatures. The
+//! signer is reached over a Unix domain socket or over TCP with mutual TLS, see [`protocol`] for
+//! the messages exchanged.
+
+use async_trait::async_trait;
+use codec::Decode;
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use rustls::ClientConfig;
+use sp_application_crypto::{ecdsa, ed25519, sr25519};
+use sp_core::crypto::{CryptoTypePublicPair, KeyTypeId};
+use sp_keystore::{
+	vrf::{VRFSignature, VRFTranscriptData},
+	CryptoStore, Error as TraitError, SyncCryptoStore,
+};
+use std::{
+	sync::{mpsc, Arc},
+	time::Duration,
+};
+
+use crate::{Error, Result};
+
+pub mod protocol;
+mod server;
+mod transport;
+
+pub use server::RemoteSigner;
+pub use transport::{Endpoint, TlsConfig};
+
+use protocol::{decode_vrf_signature, read_message, write_message, Request, PROTOCOL_VERSION};
+use transport::Stream;
+
+/// Default timeout of a single request to the signer.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum number of idle connections kept open to the signer.
+const MAX_IDLE_CONNECTIONS: usize = 4;
+/// Number of threads sending the requests of the [`CryptoStore`] methods.
+const ASYNC_WORKERS: usize = MAX_IDLE_CONNECTIONS;
+/// Maximum number of [`CryptoStore`] requests waiting for a thread.
+const MAX_QUEUED_REQUESTS: usize = 1024;
+
+/// Request of a [`CryptoStore`] method, with the channel its result is sent back on.
+type AsyncRequest = (Request, oneshot::Sender<std::result::Result<Vec<u8>, TraitError>>);
+
+/// Configuration of the remote keystore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteKeystoreConfig {
+	/// Endpoint of the signer.
+	pub endpoint: Endpoint,
+	/// Mutual TLS configuration, required for TLS endpoints.
+	pub tls: Option<TlsConfig>,
+}
+
+/// A keystore forwarding all operations to a remote signer.
+///
+/// The [`SyncCryptoStore`] methods block the calling thread until the signer answers, the
+/// [`CryptoStore`] methods hand their request to a fixed pool of threads waiting for the answers.
+pub struct RemoteKeystore {
+	client: Arc<SignerClient>,
+	/// Queue of the requests of the [`CryptoStore`] methods.
+	///
+	/// The threads serving it stop once the keystore is dropped.
+	queue: mpsc::SyncSender<AsyncRequest>,
+}
+
+impl RemoteKeystore {
+	/// Create a keystore using the signer at the configured endpoint.
+	///
+	/// The connection is established on the first request and re-established whenever it fails,
+	/// so the signer does not need to be reachable yet.
+	pub fn open(config: RemoteKeystoreConfig) -> Result<Self> {
+		let tls = match (&config.endpoint, config.tls) {
+			(Endpoint::Tls { .. }, None) =>
+				return Err(Error::Remote("TLS endpoint requires a TLS configuration".into())),
+			(_, tls) => tls.map(|tls| tls.client_config()).transpose()?,
+		};
+
+		let client = Arc::new(SignerClient {
+			endpoint: config.endpoint,
+			tls,
+			timeout: DEFAULT_TIMEOUT,
+			idle: Mutex::new(Vec::new()),
+		});
+
+		let (queue, receiver) = mpsc::sync_channel::<AsyncRequest>(MAX_QUEUED_REQUESTS);
+		let receiver = Arc::new(Mutex::new(receiver));
+		for _ in 0..ASYNC_WORKERS {
+			let (client, receiver) = (client.clone(), receiver.clone());
+			std::thread::Builder::new().name("remote-keystore".into()).spawn(move || loop {
+				let (request, sender) = match receiver.lock().recv() {
+					Ok(request) => request,
+					Err(_) => return,
+				};
+				let _ = sender.send(client.request(&request));
+			})?;
+		}
+
+		Ok(Self { client, queue })
+	}
+
+	/// Send a request to the signer and decode the returned value.
+	fn call<R: Decode>(&self, request: Request) -> std::result::Result<R, TraitError> {
+		decode_response(self.client.request(&request)?)
+	}
+
+	/// Send a request to the signer from one of the request threads and decode the returned
+	/// value.
+	async fn call_async<R: Decode>(&self, request: Request) -> std::result::Result<R, TraitError> {
+		let (sender, receiver) = oneshot::channel();
+		self.queue.try_send((request, sender)).map_err(|_| {
+			log::warn!(target: "keystore", "Too many pending requests to the remote signer");
+			TraitError::Unavailable
+		})?;
+
+		let value = receiver.await.map_err(|_| TraitError::Unavailable)??;
+		decode_response(value)
+	}
+}
+
+/// Connections to the signer.
+struct SignerClient {
+	endpoint: Endpoint,
+	tls: Option<Arc<ClientConfig>>,
+	timeout: Duration,
+	/// Connections kept from previous requests.
+	///
+	/// The lock is only held to take or give back a connection, concurrent requests use separate
+	/// connections.
+	idle: Mutex<Vec<Box<dyn Stream>>>,
+}
+
+impl SignerClient {
+	/// Send a request to the signer and return the encoded value it returned.
+	fn request(&self, request: &Request) -> std::result::Result<Vec<u8>, TraitError> {
+		let message = (PROTOCOL_VERSION, request);
+
+		// A connection kept from a previous request might have been closed by the signer in the
+		// meantime. Idempotent requests are retried once with a fresh connection in that case,
+		// the others are sent over a fresh connection right away so that they are never sent
+		// twice.
+		let idle = if request.is_idempotent() { self.idle.lock().pop() } else { None };
+		let reused = idle.is_some();
+		let response = match self.exchange(idle, &message) {
+			Err(e) if reused => {
+				log::debug!(target: "keystore", "Reconnecting to the remote signer: {}", e);
+				self.exchange(None, &message)
+			},
+			response => response,
+		};
+
+		let response = response.map_err(|e| {
+			log::warn!(
+				target: "keystore",
+				"Request to the remote signer at {:?} failed: {}",
+				self.endpoint,
+				e,
+			);
+			TraitError::Unavailable
+		})?;
+
+		response.map_err(TraitError::from)
+	}
+
+	/// Send the message over `connection`, or over a new connection if it is `None`.
+	///
+	/// The connection is kept for later requests unless anything fails.
+	fn exchange(
+		&self,
+		connection: Option<Box<dyn Stream>>,
+		message: &(u32, &Request),
+	) -> std::io::Result<protocol::Response> {
+		let mut stream = match connection {
+			Some(stream) => stream,
+			None => transport::connect(&self.endpoint, self.tls.as_ref(), self.timeout)?,
+		};
+
+		let response = write_message(&mut stream, message).and_then(|()| read_message(&mut stream));
+		match response {
+			Ok(Some(response)) => {
+				let mut idle = self.idle.lock();
+				if idle.len() < MAX_IDLE_CONNECTIONS {
+					idle.push(stream);
+				}
+				Ok(response)
+			},
+			Ok(None) => Err(std::io::ErrorKind::UnexpectedEof.into()),
+			Err(e) => Err(e),
+		}
+	}
+}
+
+/// Decode a value returned by the signer.
+fn decode_response<R: Decode>(value: Vec<u8>) -> std::result::Result<R, TraitError> {
+	R::decode(&mut &value[..])
+		.map_err(|e| TraitError::Other(format!("Invalid response from the remote signer: {}", e)))
+}
+
+#[async_trait]
+impl CryptoStore for RemoteKeystore {
+	async fn keys(
+		&self,
+		id: KeyTypeId,
+	) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		self.call_async(Request::Keys(id)).await
+	}
+
+	async fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+		self.call_async(Request::Sr25519PublicKeys(id)).await.unwrap_or_default()
+	}
+
+	async fn sr25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<sr25519::Public, TraitError> {
+		self.call_async(Request::Sr25519GenerateNew(id, seed.map(Into::into))).await
+	}
+
+	async fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+		self.call_async(Request::Ed25519PublicKeys(id)).await.unwrap_or_default()
+	}
+
+	async fn ed25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ed25519::Public, TraitError> {
+		self.call_async(Request::Ed25519GenerateNew(id, seed.map(Into::into))).await
+	}
+
+	async fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+		self.call_async(Request::EcdsaPublicKeys(id)).await.unwrap_or_default()
+	}
+
+	async fn ecdsa_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ecdsa::Public, TraitError> {
+		self.call_async(Request::EcdsaGenerateNew(id, seed.map(Into::into))).await
+	}
+
+	async fn insert_unknown(
+		&self,
+		id: KeyTypeId,
+		suri: &str,
+		public: &[u8],
+	) -> std::result::Result<(), ()> {
+		SyncCryptoStore::insert_unknown(self, id, suri, public)
+	}
+
+	async fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		self.call_async(Request::HasKeys(public_keys.to_vec())).await.unwrap_or(false)
+	}
+
+	async fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>,
+	) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		self.call_async(Request::SupportedKeys(id, keys)).await
+	}
+
+	async fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> std::result::Result<Option<Vec<u8>>, TraitError> {
+		self.call_async(Request::SignWith(id, key.clone(), msg.to_vec())).await
+	}
+
+	async fn sr25519_vrf_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		transcript_data: VRFTranscriptData,
+	) -> std::result::Result<Option<VRFSignature>, TraitError> {
+		self.call_async::<Option<protocol::EncodedVrfSignature>>(Request::Sr25519VrfSign(
+			key_type,
+			*public,
+			transcript_data.into(),
+		))
+		.await?
+		.map(decode_vrf_signature)
+		.transpose()
+	}
+
+	async fn ecdsa_sign_prehashed(
+		&self,
+		id: KeyTypeId,
+		public: &ecdsa::Public,
+		msg: &[u8; 32],
+	) -> std::result::Result<Option<ecdsa::Signature>, TraitError> {
+		self.call_async(Request::EcdsaSignPrehashed(id, *public, *msg)).await
+	}
+}
+
+impl SyncCryptoStore for RemoteKeystore {
+	fn keys(&self, id: KeyTypeId) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		self.call(Request::Keys(id))
+	}
+
+	fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>,
+	) -> std::result::Result<Vec<CryptoTypePublicPair>, TraitError> {
+		self.call(Request::SupportedKeys(id, keys))
+	}
+
+	fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> std::result::Result<Option<Vec<u8>>, TraitError> {
+		self.call(Request::SignWith(id, key.clone(), msg.to_vec()))
+	}
+
+	fn sr25519_public_keys(&self, key_type: KeyTypeId) -> Vec<sr25519::Public> {
+		self.call(Request::Sr25519PublicKeys(key_type)).unwrap_or_default()
+	}
+
+	fn sr25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<sr25519::Public, TraitError> {
+		self.call(Request::Sr25519GenerateNew(id, seed.map(Into::into)))
+	}
+
+	fn ed25519_public_keys(&self, key_type: KeyTypeId) -> Vec<ed25519::Public> {
+		self.call(Request::Ed25519PublicKeys(key_type)).unwrap_or_default()
+	}
+
+	fn ed25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ed25519::Public, TraitError> {
+		self.call(Request::Ed25519GenerateNew(id, seed.map(Into::into)))
+	}
+
+	fn ecdsa_public_keys(&self, key_type: KeyTypeId) -> Vec<ecdsa::Public> {
+		self.call(Request::EcdsaPublicKeys(key_type)).unwrap_or_default()
+	}
+
+	fn ecdsa_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ecdsa::Public, TraitError> {
+		self.call(Request::EcdsaGenerateNew(id, seed.map(Into::into)))
+	}
+
+	/// Secret URIs are never sent to the signer, keys have to be inserted on the signer host.
+	fn insert_unknown(
+		&self,
+		_key_type: KeyTypeId,
+		_suri: &str,
+		_public: &[u8],
+	) -> std::result::Result<(), ()> {
+		log::warn!(
+			target: "keystore",
+			"Refusing to insert a key into the remote signer, insert it on the signer host instead",
+		);
+		Err(())
+	}
+
+	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		self.call(Request::HasKeys(public_keys.to_vec())).unwrap_or(false)
+	}
+
+	fn sr25519_vrf_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		transcript_data: VRFTranscriptData,
+	) -> std::result::Result<Option<VRFSignature>, TraitError> {
+		self.call::<Option<protocol::EncodedVrfSignature>>(Request::Sr25519VrfSign(
+			key_type,
+			*public,
+			transcript_data.into(),
+		))?
+		.map(decode_vrf_signature)
+		.transpose()
+	}
+
+	fn ecdsa_sign_prehashed(
+		&self,
+		id: KeyTypeId,
+		public: &ecdsa::Public,
+		msg: &[u8; 32],
+	) -> std::result::Result<Option<ecdsa::Signature>, TraitError> {
+		self.call(Request::EcdsaSignPrehashed(id, *public, *msg))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::LocalKeystore;
+	use sp_core::{testing::SR25519, Pair};
+	use sp_keystore::vrf::{make_transcript, VRFTranscriptValue};
+	use std::path::{Path, PathBuf};
+	use tempfile::TempDir;
+
+	const TEST_KEY_TYPE: KeyTypeId = KeyTypeId(*b"test");
+
+	fn res(name: &str) -> PathBuf {
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("res/remote-signer").join(name)
+	}
+
+	fn tls_config(ca: &str, identity: &str) -> TlsConfig {
+		TlsConfig {
+			ca: res(&format!("{}.pem", ca)),
+			cert: res(&format!("{}.pem", identity)),
+			key: res(&format!("{}-key.pem", identity)),
+		}
+	}
+
+	fn spawn_signer(
+		endpoint: &Endpoint,
+		tls: Option<&TlsConfig>,
+		keystore: Arc<LocalKeystore>,
+	) -> Option<u16> {
+		let signer = RemoteSigner::bind(endpoint, tls, keystore).unwrap();
+		let port = signer.port();
+		std::thread::spawn(move || signer.run());
+		port
+	}
+
+	fn tls_endpoint(port: u16) -> Endpoint {
+		Endpoint::Tls { host: "localhost".into(), port }
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn parses_endpoints() {
+		assert_eq!(
+			"tls://signer.local:9955".parse::<Endpoint>().unwrap(),
+			Endpoint::Tls { host: "signer.local".into(), port: 9955 },
+		);
+		assert_eq!(
+			"tls://[::1]:9955".parse::<Endpoint>().unwrap(),
+			Endpoint::Tls { host: "::1".into(), port: 9955 },
+		);
+		assert_eq!(
+			"unix:///run/signer.sock".parse::<Endpoint>().unwrap(),
+			Endpoint::Unix("/run/signer.sock".into()),
+		);
+		assert!("tcp://signer.local:9955".parse::<Endpoint>().is_err());
+		assert!("tls://signer.local".parse::<Endpoint>().is_err());
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn forwards_operations_over_unix_socket() {
+		let temp_dir = TempDir::new().unwrap();
+		let local = Arc::new(LocalKeystore::in_memory());
+		let public = SyncCryptoStore::sr25519_generate_new(&*local, SR25519, None).unwrap();
+
+		let endpoint = Endpoint::Unix(temp_dir.path().join("signer.sock"));
+		spawn_signer(&endpoint, None, local.clone());
+		let remote = RemoteKeystore::open(RemoteKeystoreConfig { endpoint, tls: None }).unwrap();
+
+		assert_eq!(SyncCryptoStore::sr25519_public_keys(&remote, SR25519), vec![public]);
+		assert!(SyncCryptoStore::has_keys(&remote, &[(public.to_vec(), SR25519)]));
+		assert!(!SyncCryptoStore::has_keys(&remote, &[(public.to_vec(), TEST_KEY_TYPE)]));
+
+		let msg = b"hello remote signer";
+		let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, public.to_vec());
+		let signature = SyncCryptoStore::sign_with(&remote, SR25519, &key, msg).unwrap().unwrap();
+		let signature = sr25519::Signature::decode(&mut &signature[..]).unwrap();
+		assert!(sr25519::Pair::verify(&signature, &msg[..], &public));
+
+		let transcript_data = || VRFTranscriptData {
+			label: b"Test",
+			items: vec![("one", VRFTranscriptValue::U64(1))],
+		};
+		let signature =
+			SyncCryptoStore::sr25519_vrf_sign(&remote, SR25519, &public, transcript_data())
+				.unwrap()
+				.unwrap();
+		let expected =
+			SyncCryptoStore::sr25519_vrf_sign(&*local, SR25519, &public, transcript_data())
+				.unwrap()
+				.unwrap();
+		assert_eq!(signature.output, expected.output);
+		assert!(schnorrkel::PublicKey::from_bytes(public.as_ref())
+			.unwrap()
+			.vrf_verify(make_transcript(transcript_data()), &signature.output, &signature.proof)
+			.is_ok());
+
+		let generated =
+			SyncCryptoStore::ed25519_generate_new(&remote, TEST_KEY_TYPE, None).unwrap();
+		assert_eq!(SyncCryptoStore::ed25519_public_keys(&*local, TEST_KEY_TYPE), vec![generated]);
+
+		assert!(SyncCryptoStore::insert_unknown(&remote, SR25519, "//Alice", &public).is_err());
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn serves_concurrent_async_requests() {
+		let temp_dir = TempDir::new().unwrap();
+		let local = Arc::new(LocalKeystore::in_memory());
+		let public = SyncCryptoStore::sr25519_generate_new(&*local, SR25519, None).unwrap();
+
+		let endpoint = Endpoint::Unix(temp_dir.path().join("signer.sock"));
+		spawn_signer(&endpoint, None, local);
+		let remote = RemoteKeystore::open(RemoteKeystoreConfig { endpoint, tls: None }).unwrap();
+
+		let (keys, has_keys) = futures::executor::block_on(futures::future::join(
+			CryptoStore::sr25519_public_keys(&remote, SR25519),
+			CryptoStore::has_keys(&remote, &[(public.to_vec(), SR25519)]),
+		));
+		assert_eq!(keys, vec![public]);
+		assert!(has_keys);
+		assert!(remote.client.idle.lock().len() <= MAX_IDLE_CONNECTIONS);
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn restricts_access_to_unix_socket() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let temp_dir = TempDir::new().unwrap();
+		let path = temp_dir.path().join("signer.sock");
+		spawn_signer(&Endpoint::Unix(path.clone()), None, Arc::new(LocalKeystore::in_memory()));
+
+		assert_eq!(std::fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o600);
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn sends_key_generation_over_fresh_connections() {
+		let temp_dir = TempDir::new().unwrap();
+		let local = Arc::new(LocalKeystore::in_memory());
+		let endpoint = Endpoint::Unix(temp_dir.path().join("signer.sock"));
+		spawn_signer(&endpoint, None, local.clone());
+		let remote = RemoteKeystore::open(RemoteKeystoreConfig { endpoint, tls: None }).unwrap();
+
+		// a connection closed by the signer in the meantime.
+		let (closed, _) = std::os::unix::net::UnixStream::pair().unwrap();
+		remote.client.idle.lock().push(Box::new(closed));
+
+		let generated =
+			SyncCryptoStore::ed25519_generate_new(&remote, TEST_KEY_TYPE, None).unwrap();
+		assert_eq!(SyncCryptoStore::ed25519_public_keys(&*local, TEST_KEY_TYPE), vec![generated]);
+		assert_eq!(remote.client.idle.lock().len(), 2);
+	}
+
+	#[test]
+	fn serves_nodes_while_a_handshake_stalls() {
+		let local = Arc::new(LocalKeystore::in_memory());
+		let public = SyncCryptoStore::sr25519_generate_new(&*local, SR25519, None).unwrap();
+
+		let port =
+			spawn_signer(&tls_endpoint(0), Some(&tls_config("ca", "signer")), local).unwrap();
+		let _stalled = std::net::TcpStream::connect(("localhost", port)).unwrap();
+		let remote = RemoteKeystore::open(RemoteKeystoreConfig {
+			endpoint: tls_endpoint(port),
+			tls: Some(tls_config("ca", "node")),
+		})
+		.unwrap();
+
+		assert_eq!(SyncCryptoStore::sr25519_public_keys(&remote, SR25519), vec![public]);
+	}
+
+	#[test]
+	fn forwards_operations_over_mutual_tls() {
+		let local = Arc::new(LocalKeystore::in_memory());
+		let public = SyncCryptoStore::sr25519_generate_new(&*local, SR25519, None).unwrap();
+
+		let server_tls = tls_config("ca", "signer");
+		let port = spawn_signer(&tls_endpoint(0), Some(&server_tls), local).unwrap();
+		let remote = RemoteKeystore::open(RemoteKeystoreConfig {
+			endpoint: tls_endpoint(port),
+			tls: Some(tls_config("ca", "node")),
+		})
+		.unwrap();
+
+		assert_eq!(SyncCryptoStore::sr25519_public_keys(&remote, SR25519), vec![public]);
+	}
+
+	#[test]
+	fn rejects_nodes_without_trusted_certificate() {
+		let local = Arc::new(LocalKeystore::in_memory());
+		SyncCryptoStore::sr25519_generate_new(&*local, SR25519, None).unwrap();
+
+		let port =
+			spawn_signer(&tls_endpoint(0), Some(&tls_config("ca", "signer")), local).unwrap();
+		let remote = RemoteKeystore::open(RemoteKeystoreConfig {
+			endpoint: tls_endpoint(port),
+			tls: Some(tls_config("ca", "untrusted-node")),
+		})
+		.unwrap();
+
+		assert!(SyncCryptoStore::sr25519_public_keys(&remote, SR25519).is_empty());
+		assert!(matches!(SyncCryptoStore::keys(&remote, SR25519), Err(TraitError::Unavailable)));
+	}
+
+	#[test]
+	fn requires_tls_configuration_for_tls_endpoints() {
+		assert!(RemoteKeystore::open(RemoteKeystoreConfig {
+			endpoint: tls_endpoint(9955),
+			tls: None
+		})
+		.is_err());
+	}
+}
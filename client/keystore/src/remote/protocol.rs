@@ -0,0 +1,212 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Wire protocol spoken between the node and the remote signer.
+//!
+//! Every message is a SCALE encoded [`Request`] or [`Response`] prefixed by its length as a
+//! little endian `u32`. The node sends a single request and waits for its response before sending
+//! the next one.
+
+use codec::{Decode, Encode};
+use sp_application_crypto::{ecdsa, sr25519};
+use sp_core::crypto::{CryptoTypePublicPair, KeyTypeId};
+use sp_keystore::{
+	vrf::{VRFSignature, VRFTranscriptData, VRFTranscriptValue},
+	Error as TraitError,
+};
+use std::io::{self, Read, Write};
+
+/// Version of the protocol, sent by the node in every request.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Maximum size of a single message.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Request sent by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum Request {
+	/// `SyncCryptoStore::keys`.
+	Keys(KeyTypeId),
+	/// `SyncCryptoStore::supported_keys`.
+	SupportedKeys(KeyTypeId, Vec<CryptoTypePublicPair>),
+	/// `SyncCryptoStore::has_keys`.
+	HasKeys(Vec<(Vec<u8>, KeyTypeId)>),
+	/// `SyncCryptoStore::sr25519_public_keys`.
+	Sr25519PublicKeys(KeyTypeId),
+	/// `SyncCryptoStore::ed25519_public_keys`.
+	Ed25519PublicKeys(KeyTypeId),
+	/// `SyncCryptoStore::ecdsa_public_keys`.
+	EcdsaPublicKeys(KeyTypeId),
+	/// `SyncCryptoStore::sr25519_generate_new`.
+	Sr25519GenerateNew(KeyTypeId, Option<String>),
+	/// `SyncCryptoStore::ed25519_generate_new`.
+	Ed25519GenerateNew(KeyTypeId, Option<String>),
+	/// `SyncCryptoStore::ecdsa_generate_new`.
+	EcdsaGenerateNew(KeyTypeId, Option<String>),
+	/// `SyncCryptoStore::sign_with`.
+	SignWith(KeyTypeId, CryptoTypePublicPair, Vec<u8>),
+	/// `SyncCryptoStore::sr25519_vrf_sign`.
+	Sr25519VrfSign(KeyTypeId, sr25519::Public, TranscriptData),
+	/// `SyncCryptoStore::ecdsa_sign_prehashed`.
+	EcdsaSignPrehashed(KeyTypeId, ecdsa::Public, [u8; 32]),
+}
+
+impl Request {
+	/// Whether sending the request twice has the same effect as sending it once.
+	///
+	/// Requests generating keys are not, each of them adds a key to the keystore of the signer.
+	pub fn is_idempotent(&self) -> bool {
+		!matches!(
+			self,
+			Request::Sr25519GenerateNew(..) |
+				Request::Ed25519GenerateNew(..) |
+				Request::EcdsaGenerateNew(..)
+		)
+	}
+}
+
+/// Response sent by the signer, the SCALE encoded return value of the requested call.
+pub type Response = Result<Vec<u8>, Error>;
+
+/// Error returned by the signer.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum Error {
+	/// The signer does not speak the protocol version of the node.
+	UnsupportedVersion(u32),
+	/// Public key type is not supported.
+	KeyNotSupported(KeyTypeId),
+	/// Validation error.
+	ValidationError(String),
+	/// Keystore of the signer is unavailable.
+	Unavailable,
+	/// Any other error.
+	Other(String),
+}
+
+impl From<TraitError> for Error {
+	fn from(error: TraitError) -> Self {
+		match error {
+			TraitError::KeyNotSupported(id) => Error::KeyNotSupported(id),
+			TraitError::ValidationError(e) => Error::ValidationError(e),
+			TraitError::Unavailable => Error::Unavailable,
+			TraitError::Other(e) => Error::Other(e),
+		}
+	}
+}
+
+impl From<Error> for TraitError {
+	fn from(error: Error) -> Self {
+		match error {
+			Error::UnsupportedVersion(version) =>
+				TraitError::Other(format!("Remote signer does not support version {}", version)),
+			Error::KeyNotSupported(id) => TraitError::KeyNotSupported(id),
+			Error::ValidationError(e) => TraitError::ValidationError(e),
+			Error::Unavailable => TraitError::Unavailable,
+			Error::Other(e) => TraitError::Other(e),
+		}
+	}
+}
+
+/// Owned version of [`VRFTranscriptData`].
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct TranscriptData {
+	/// The transcript's label.
+	pub label: Vec<u8>,
+	/// Additional data to be registered into the transcript.
+	pub items: Vec<(Vec<u8>, TranscriptValue)>,
+}
+
+/// Owned version of [`VRFTranscriptValue`].
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum TranscriptValue {
+	/// Value is an array of bytes.
+	Bytes(Vec<u8>),
+	/// Value is a u64 integer.
+	U64(u64),
+}
+
+impl From<VRFTranscriptData> for TranscriptData {
+	fn from(data: VRFTranscriptData) -> Self {
+		TranscriptData {
+			label: data.label.to_vec(),
+			items: data
+				.items
+				.into_iter()
+				.map(|(label, value)| {
+					let value = match value {
+						VRFTranscriptValue::Bytes(bytes) => TranscriptValue::Bytes(bytes),
+						VRFTranscriptValue::U64(val) => TranscriptValue::U64(val),
+					};
+					(label.as_bytes().to_vec(), value)
+				})
+				.collect(),
+		}
+	}
+}
+
+/// Encoded VRF signature, the output followed by the proof.
+pub type EncodedVrfSignature = ([u8; 32], [u8; 64]);
+
+/// Encode a VRF signature for the wire.
+pub fn encode_vrf_signature(signature: &VRFSignature) -> EncodedVrfSignature {
+	(signature.output.to_bytes(), signature.proof.to_bytes())
+}
+
+/// Decode a VRF signature received from the wire.
+pub fn decode_vrf_signature(
+	(output, proof): EncodedVrfSignature,
+) -> Result<VRFSignature, TraitError> {
+	let invalid = |e| TraitError::Other(format!("Invalid VRF signature from remote signer: {}", e));
+	Ok(VRFSignature {
+		output: schnorrkel::vrf::VRFOutput::from_bytes(&output).map_err(invalid)?,
+		proof: schnorrkel::vrf::VRFProof::from_bytes(&proof).map_err(invalid)?,
+	})
+}
+
+/// Write a length prefixed message.
+pub fn write_message<T: Encode>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+	let data = message.encode();
+	if data.len() > MAX_MESSAGE_SIZE {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "Message too large"))
+	}
+	stream.write_all(&(data.len() as u32).to_le_bytes())?;
+	stream.write_all(&data)?;
+	stream.flush()
+}
+
+/// Read a length prefixed message.
+///
+/// Returns `Ok(None)` if the stream was closed before the message started.
+pub fn read_message<T: Decode>(stream: &mut impl Read) -> io::Result<Option<T>> {
+	let mut len = [0u8; 4];
+	match stream.read_exact(&mut len) {
+		Ok(()) => {},
+		Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(e) => return Err(e),
+	}
+	let len = u32::from_le_bytes(len) as usize;
+	if len > MAX_MESSAGE_SIZE {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "Message too large"))
+	}
+
+	let mut data = vec![0u8; len];
+	stream.read_exact(&mut data)?;
+	T::decode(&mut &data[..])
+		.map(Some)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
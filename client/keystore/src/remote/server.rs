@@ -0,0 +1,240 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reference implementation of the remote signer.
+
+use super::{
+	protocol::{
+		encode_vrf_signature, read_message, write_message, Error as ProtocolError, Request,
+		Response, TranscriptData, TranscriptValue, PROTOCOL_VERSION,
+	},
+	transport::{Endpoint, Incoming, Listener, Stream, TlsConfig},
+};
+use crate::Result;
+use codec::Encode;
+use parking_lot::Mutex;
+use sp_keystore::{
+	vrf::{VRFTranscriptData, VRFTranscriptValue},
+	SyncCryptoStore, SyncCryptoStorePtr,
+};
+use std::{
+	collections::HashSet,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		mpsc, Arc,
+	},
+	time::Duration,
+};
+
+/// Maximum number of distinct VRF transcript labels the signer accepts.
+///
+/// Transcripts only take static labels, so every distinct label is leaked once.
+const MAX_TRANSCRIPT_LABELS: usize = 1024;
+/// Maximum number of connections served at the same time, each one by a worker thread.
+const MAX_CONNECTIONS: usize = 16;
+/// Time a node has to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time after which an idle or stalled connection is closed.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+/// Pause after failing to accept a connection, so that persistent failures don't spin.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Signer serving the keys of a keystore to remote nodes.
+pub struct RemoteSigner {
+	listener: Listener,
+	handler: Arc<Handler>,
+}
+
+impl RemoteSigner {
+	/// Listen on the given endpoint.
+	///
+	/// TLS endpoints require a TLS configuration, only nodes presenting a certificate signed by
+	/// one of its certificate authorities are accepted.
+	pub fn bind(
+		endpoint: &Endpoint,
+		tls: Option<&TlsConfig>,
+		keystore: SyncCryptoStorePtr,
+	) -> Result<Self> {
+		Ok(Self {
+			listener: Listener::bind(endpoint, tls)?,
+			handler: Arc::new(Handler { keystore, labels: Default::default() }),
+		})
+	}
+
+	/// Returns the port the signer listens on, if it is listening on a TCP socket.
+	pub fn port(&self) -> Option<u16> {
+		self.listener.port()
+	}
+
+	/// Serve connections forever.
+	///
+	/// Connections are handled by a fixed pool of [`MAX_CONNECTIONS`] worker threads, further
+	/// connections are closed right away. Only fails if the workers can't be started.
+	pub fn run(self) -> Result<()> {
+		let (sender, receiver) = mpsc::sync_channel::<Incoming>(MAX_CONNECTIONS);
+		let receiver = Arc::new(Mutex::new(receiver));
+		let active = Arc::new(AtomicUsize::new(0));
+		for _ in 0..MAX_CONNECTIONS {
+			let (handler, receiver, active) =
+				(self.handler.clone(), receiver.clone(), active.clone());
+			std::thread::Builder::new().name("remote-signer".into()).spawn(move || loop {
+				let incoming = match receiver.lock().recv() {
+					Ok(incoming) => incoming,
+					Err(_) => return,
+				};
+				match incoming.establish(HANDSHAKE_TIMEOUT, CONNECTION_TIMEOUT) {
+					Ok(stream) => handler.serve(stream),
+					Err(e) => log::debug!(
+						target: "keystore",
+						"Failed to establish remote signer connection: {}",
+						e,
+					),
+				}
+				active.fetch_sub(1, Ordering::SeqCst);
+			})?;
+		}
+
+		loop {
+			let incoming = match self.listener.accept() {
+				Ok(incoming) => incoming,
+				Err(e) => {
+					log::warn!(target: "keystore", "Failed to accept remote signer connection: {}", e);
+					std::thread::sleep(ACCEPT_ERROR_BACKOFF);
+					continue
+				},
+			};
+
+			// Every accepted connection has a worker, so the queue never fills up.
+			if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+				active.fetch_sub(1, Ordering::SeqCst);
+				log::warn!(
+					target: "keystore",
+					"Rejecting remote signer connection, {} connections are already served",
+					MAX_CONNECTIONS,
+				);
+				continue
+			}
+			let _ = sender.send(incoming);
+		}
+	}
+}
+
+struct Handler {
+	keystore: SyncCryptoStorePtr,
+	labels: Mutex<HashSet<&'static [u8]>>,
+}
+
+impl Handler {
+	fn serve(&self, mut stream: Box<dyn Stream>) {
+		loop {
+			let (version, request) = match read_message::<(u32, Request)>(&mut stream) {
+				Ok(Some(message)) => message,
+				Ok(None) => return,
+				Err(e) => {
+					log::debug!(target: "keystore", "Closing remote signer connection: {}", e);
+					return
+				},
+			};
+
+			let response = if version == PROTOCOL_VERSION {
+				log::trace!(target: "keystore", "Remote signer request: {:?}", request);
+				self.handle(request)
+			} else {
+				Err(ProtocolError::UnsupportedVersion(version))
+			};
+
+			if let Err(e) = write_message(&mut stream, &response) {
+				log::debug!(target: "keystore", "Closing remote signer connection: {}", e);
+				return
+			}
+		}
+	}
+
+	fn handle(&self, request: Request) -> Response {
+		let keystore = &*self.keystore;
+		Ok(match request {
+			Request::Keys(id) => SyncCryptoStore::keys(keystore, id)?.encode(),
+			Request::SupportedKeys(id, keys) =>
+				SyncCryptoStore::supported_keys(keystore, id, keys)?.encode(),
+			Request::HasKeys(keys) => SyncCryptoStore::has_keys(keystore, &keys).encode(),
+			Request::Sr25519PublicKeys(id) =>
+				SyncCryptoStore::sr25519_public_keys(keystore, id).encode(),
+			Request::Ed25519PublicKeys(id) =>
+				SyncCryptoStore::ed25519_public_keys(keystore, id).encode(),
+			Request::EcdsaPublicKeys(id) =>
+				SyncCryptoStore::ecdsa_public_keys(keystore, id).encode(),
+			Request::Sr25519GenerateNew(id, seed) =>
+				SyncCryptoStore::sr25519_generate_new(keystore, id, seed.as_deref())?.encode(),
+			Request::Ed25519GenerateNew(id, seed) =>
+				SyncCryptoStore::ed25519_generate_new(keystore, id, seed.as_deref())?.encode(),
+			Request::EcdsaGenerateNew(id, seed) =>
+				SyncCryptoStore::ecdsa_generate_new(keystore, id, seed.as_deref())?.encode(),
+			Request::SignWith(id, key, msg) =>
+				SyncCryptoStore::sign_with(keystore, id, &key, &msg)?.encode(),
+			Request::Sr25519VrfSign(id, public, data) => SyncCryptoStore::sr25519_vrf_sign(
+				keystore,
+				id,
+				&public,
+				self.transcript_data(data)?,
+			)?
+			.as_ref()
+			.map(encode_vrf_signature)
+			.encode(),
+			Request::EcdsaSignPrehashed(id, public, msg) =>
+				SyncCryptoStore::ecdsa_sign_prehashed(keystore, id, &public, &msg)?.encode(),
+		})
+	}
+
+	fn transcript_data(
+		&self,
+		data: TranscriptData,
+	) -> std::result::Result<VRFTranscriptData, ProtocolError> {
+		let items = data
+			.items
+			.into_iter()
+			.map(|(label, value)| {
+				let not_utf8 =
+					|_| ProtocolError::ValidationError("Transcript item label is not UTF-8".into());
+				// Check before interning so that invalid labels are not leaked.
+				std::str::from_utf8(&label).map_err(not_utf8)?;
+				let label = std::str::from_utf8(self.intern(label)?).map_err(not_utf8)?;
+				let value = match value {
+					TranscriptValue::Bytes(bytes) => VRFTranscriptValue::Bytes(bytes),
+					TranscriptValue::U64(val) => VRFTranscriptValue::U64(val),
+				};
+				Ok((label, value))
+			})
+			.collect::<std::result::Result<_, ProtocolError>>()?;
+
+		Ok(VRFTranscriptData { label: self.intern(data.label)?, items })
+	}
+
+	fn intern(&self, label: Vec<u8>) -> std::result::Result<&'static [u8], ProtocolError> {
+		let mut labels = self.labels.lock();
+		if let Some(label) = labels.get(&label[..]) {
+			return Ok(label)
+		}
+		if labels.len() >= MAX_TRANSCRIPT_LABELS {
+			return Err(ProtocolError::ValidationError("Too many distinct transcript labels".into()))
+		}
+
+		let label: &'static [u8] = Box::leak(label.into_boxed_slice());
+		labels.insert(label);
+		Ok(label)
+	}
+}
@@ -0,0 +1,279 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Connections between the node and the remote signer.
+
+use crate::{Error, Result};
+use rustls::{
+	server::AllowAnyAuthenticatedClient, Certificate, ClientConfig, ClientConnection, PrivateKey,
+	RootCertStore, ServerConfig, ServerConnection, ServerName, StreamOwned,
+};
+use std::{
+	fs::File,
+	io::{self, BufReader, Read, Write},
+	net::{TcpListener, TcpStream},
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::{
+	fs::PermissionsExt,
+	net::{UnixListener, UnixStream},
+};
+
+/// Endpoint of a remote signer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+	/// TCP connection protected by mutual TLS, `tls://<host>:<port>`.
+	Tls {
+		/// Host name, also used to verify the certificate of the signer.
+		host: String,
+		/// Port number.
+		port: u16,
+	},
+	/// Unix domain socket, `unix://<path>`.
+	#[cfg(unix)]
+	Unix(PathBuf),
+}
+
+impl FromStr for Endpoint {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let invalid = || Error::Remote(format!("Invalid remote signer endpoint: {}", s));
+		if let Some(address) = s.strip_prefix("tls://") {
+			let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+			let port = port.parse().map_err(|_| invalid())?;
+			let host = host.trim_start_matches('[').trim_end_matches(']');
+			if host.is_empty() {
+				return Err(invalid())
+			}
+			return Ok(Endpoint::Tls { host: host.into(), port })
+		}
+		#[cfg(unix)]
+		if let Some(path) = s.strip_prefix("unix://") {
+			if path.is_empty() {
+				return Err(invalid())
+			}
+			return Ok(Endpoint::Unix(path.into()))
+		}
+		Err(invalid())
+	}
+}
+
+/// Certificates and key used for mutual TLS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+	/// PEM file with the certificate authorities the peer certificate is verified against.
+	pub ca: PathBuf,
+	/// PEM file with the certificate chain presented to the peer.
+	pub cert: PathBuf,
+	/// PEM file with the PKCS#8 or RSA private key of the certificate.
+	pub key: PathBuf,
+}
+
+impl TlsConfig {
+	/// Build the TLS configuration of the node.
+	pub(crate) fn client_config(&self) -> Result<Arc<ClientConfig>> {
+		ClientConfig::builder()
+			.with_safe_defaults()
+			.with_root_certificates(self.roots()?)
+			.with_single_cert(read_certs(&self.cert)?, read_key(&self.key)?)
+			.map(Arc::new)
+			.map_err(|e| Error::Remote(format!("Invalid TLS configuration: {}", e)))
+	}
+
+	/// Build the TLS configuration of the signer.
+	pub(crate) fn server_config(&self) -> Result<Arc<ServerConfig>> {
+		ServerConfig::builder()
+			.with_safe_defaults()
+			.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(self.roots()?))
+			.with_single_cert(read_certs(&self.cert)?, read_key(&self.key)?)
+			.map(Arc::new)
+			.map_err(|e| Error::Remote(format!("Invalid TLS configuration: {}", e)))
+	}
+
+	fn roots(&self) -> Result<RootCertStore> {
+		let mut roots = RootCertStore::empty();
+		for cert in read_certs(&self.ca)? {
+			roots
+				.add(&cert)
+				.map_err(|e| Error::Remote(format!("Invalid CA certificate: {}", e)))?;
+		}
+		Ok(roots)
+	}
+}
+
+fn read_certs(path: &Path) -> Result<Vec<Certificate>> {
+	let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))?;
+	if certs.is_empty() {
+		return Err(Error::Remote(format!("No certificate found in {}", path.display())))
+	}
+	Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_key(path: &Path) -> Result<PrivateKey> {
+	let mut reader = BufReader::new(File::open(path)?);
+	loop {
+		match rustls_pemfile::read_one(&mut reader)? {
+			Some(rustls_pemfile::Item::PKCS8Key(key)) | Some(rustls_pemfile::Item::RSAKey(key)) =>
+				return Ok(PrivateKey(key)),
+			Some(rustls_pemfile::Item::X509Certificate(_)) => continue,
+			None =>
+				return Err(Error::Remote(format!("No private key found in {}", path.display()))),
+		}
+	}
+}
+
+/// A bidirectional byte stream.
+pub(crate) trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+/// Open a connection to the signer.
+pub(crate) fn connect(
+	endpoint: &Endpoint,
+	tls: Option<&Arc<ClientConfig>>,
+	timeout: Duration,
+) -> io::Result<Box<dyn Stream>> {
+	match endpoint {
+		Endpoint::Tls { host, port } => {
+			let tls = tls.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidInput,
+					"TLS endpoint without TLS configuration",
+				)
+			})?;
+			let server_name = ServerName::try_from(host.as_str())
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+			let socket = TcpStream::connect((host.as_str(), *port))?;
+			socket.set_read_timeout(Some(timeout))?;
+			socket.set_write_timeout(Some(timeout))?;
+			socket.set_nodelay(true)?;
+			let connection = ClientConnection::new(tls.clone(), server_name)
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+			Ok(Box::new(StreamOwned::new(connection, socket)))
+		},
+		#[cfg(unix)]
+		Endpoint::Unix(path) => {
+			let socket = UnixStream::connect(path)?;
+			socket.set_read_timeout(Some(timeout))?;
+			socket.set_write_timeout(Some(timeout))?;
+			Ok(Box::new(socket))
+		},
+	}
+}
+
+/// Listening socket of the signer.
+pub(crate) enum Listener {
+	Tls(TcpListener, Arc<ServerConfig>),
+	#[cfg(unix)]
+	Unix(UnixListener),
+}
+
+impl Listener {
+	/// Listen on the given endpoint.
+	///
+	/// Unix domain sockets are only accessible to the user running the signer.
+	pub fn bind(endpoint: &Endpoint, tls: Option<&TlsConfig>) -> Result<Self> {
+		match endpoint {
+			Endpoint::Tls { host, port } => {
+				let tls = tls.ok_or_else(|| {
+					Error::Remote("TLS endpoint requires a TLS configuration".into())
+				})?;
+				Ok(Listener::Tls(TcpListener::bind((host.as_str(), *port))?, tls.server_config()?))
+			},
+			#[cfg(unix)]
+			Endpoint::Unix(path) => {
+				let listener = UnixListener::bind(path)?;
+				std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+				Ok(Listener::Unix(listener))
+			},
+		}
+	}
+
+	/// Returns the port the listener is bound to, if it is a TCP listener.
+	pub fn port(&self) -> Option<u16> {
+		match self {
+			Listener::Tls(listener, _) => listener.local_addr().ok().map(|addr| addr.port()),
+			#[cfg(unix)]
+			Listener::Unix(_) => None,
+		}
+	}
+
+	/// Wait for the next connection.
+	pub fn accept(&self) -> io::Result<Incoming> {
+		match self {
+			Listener::Tls(listener, tls) => Ok(Incoming::Tls(listener.accept()?.0, tls.clone())),
+			#[cfg(unix)]
+			Listener::Unix(listener) => Ok(Incoming::Unix(listener.accept()?.0)),
+		}
+	}
+}
+
+/// Connection accepted by a [`Listener`], not ready to carry messages yet.
+pub(crate) enum Incoming {
+	Tls(TcpStream, Arc<ServerConfig>),
+	#[cfg(unix)]
+	Unix(UnixStream),
+}
+
+impl Incoming {
+	/// Complete the TLS handshake, failing if it takes longer than `handshake_timeout`.
+	///
+	/// Reads and writes on the returned stream fail after `timeout`.
+	pub fn establish(
+		self,
+		handshake_timeout: Duration,
+		timeout: Duration,
+	) -> io::Result<Box<dyn Stream>> {
+		match self {
+			Incoming::Tls(mut socket, tls) => {
+				socket.set_nodelay(true)?;
+				let mut connection = ServerConnection::new(tls)
+					.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+				let deadline = Instant::now() + handshake_timeout;
+				while connection.is_handshaking() {
+					let remaining = deadline
+						.checked_duration_since(Instant::now())
+						.filter(|remaining| !remaining.is_zero())
+						.ok_or_else(|| {
+							io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out")
+						})?;
+					socket.set_read_timeout(Some(remaining))?;
+					socket.set_write_timeout(Some(remaining))?;
+					connection.complete_io(&mut socket)?;
+				}
+
+				socket.set_read_timeout(Some(timeout))?;
+				socket.set_write_timeout(Some(timeout))?;
+				Ok(Box::new(StreamOwned::new(connection, socket)))
+			},
+			#[cfg(unix)]
+			Incoming::Unix(socket) => {
+				socket.set_read_timeout(Some(timeout))?;
+				socket.set_write_timeout(Some(timeout))?;
+				Ok(Box::new(socket))
+			},
+		}
+	}
+}
@@ -171,3 +171,19 @@ impl From<multiaddr::Error> for ParseErr {
 		Self::MultiaddrParse(err)
 	}
 }
+
+/// Priority of the traffic of a protocol when the bandwidth is limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrafficPriority {
+	/// Can use the share of the global bandwidth limits reserved for high priority traffic.
+	/// Meant for consensus protocols.
+	High,
+	/// Regular traffic.
+	Normal,
+}
+
+impl Default for TrafficPriority {
+	fn default() -> Self {
+		Self::Normal
+	}
+}
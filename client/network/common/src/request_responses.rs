@@ -18,7 +18,7 @@
 
 //! Collection of generic data structures for request-response protocols.
 
-use crate::protocol::ProtocolName;
+use crate::{config::TrafficPriority, protocol::ProtocolName};
 use futures::channel::{mpsc, oneshot};
 use libp2p::{request_response::OutboundFailure, PeerId};
 use sc_peerset::ReputationChange;
@@ -50,6 +50,9 @@ pub struct ProtocolConfig {
 	/// If you expect the response to come back quickly, you should set this to a smaller duration.
 	pub request_timeout: Duration,
 
+	/// Priority of the requests and responses when the bandwidth is limited.
+	pub priority: TrafficPriority,
+
 	/// Channel on which the networking service will send incoming requests.
 	///
 	/// Every time a peer sends a request to the local node using this protocol, the networking
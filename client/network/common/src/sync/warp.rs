@@ -37,6 +37,22 @@ pub enum VerificationResult<Block: BlockT> {
 	Complete(SetId, AuthorityList, Block::Header),
 }
 
+/// A finalized block trusted by the node, e.g. because it is listed in the chain specification.
+///
+/// Warp sync and fast sync start from the newest checkpoint, and the blocks conflicting with any
+/// checkpoint are rejected.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Checkpoint<Block: BlockT> {
+	/// Number of the block.
+	pub number: NumberFor<Block>,
+	/// Hash of the block.
+	pub hash: Block::Hash,
+	/// Id of the authority set finalizing the children of the block.
+	pub set_id: SetId,
+	/// The authorities of the set `set_id`.
+	pub authorities: AuthorityList,
+}
+
 /// Warp sync backend. Handles retrieveing and verifying warp sync proofs.
 pub trait WarpSyncProvider<Block: BlockT>: Send + Sync {
 	/// Generate proof starting at given block hash. The proof is accumulated until maximum proof
@@ -21,7 +21,10 @@
 /// For incoming light client requests.
 pub mod handler;
 
-use sc_network_common::{config::ProtocolId, request_responses::ProtocolConfig};
+use sc_network_common::{
+	config::{ProtocolId, TrafficPriority},
+	request_responses::ProtocolConfig,
+};
 
 use std::time::Duration;
 
@@ -53,6 +56,7 @@ pub fn generate_protocol_config<Hash: AsRef<[u8]>>(
 		max_request_size: 1 * 1024 * 1024,
 		max_response_size: 16 * 1024 * 1024,
 		request_timeout: Duration::from_secs(15),
+		priority: TrafficPriority::Normal,
 		inbound_queue: None,
 	}
 }
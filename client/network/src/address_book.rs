@@ -0,0 +1,269 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Book of reachable peer addresses, persisted on disk.
+//!
+//! The [`DiscoveryBehaviour`](crate::discovery::DiscoveryBehaviour) records the addresses it
+//! successfully dialed, and seeds itself from the book on start so that known peers are found
+//! again after a restart without going through the bootnodes and the DHT first.
+
+use crate::config::AddressBookConfig;
+use futures::prelude::*;
+use futures_timer::Delay;
+use libp2p::{Multiaddr, PeerId};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+	task::{Context, Poll},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the file the address book is stored in.
+pub const ADDRESS_BOOK_FILE: &str = "address_book.json";
+
+/// Maximum number of addresses kept for a single peer.
+const MAX_ADDRESSES_PER_PEER: usize = 4;
+
+/// Addresses of a peer and the last time we were connected to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+	addresses: Vec<Multiaddr>,
+	/// Seconds since the UNIX epoch.
+	last_seen: u64,
+}
+
+/// Reachable addresses of the peers we were recently connected to.
+pub struct AddressBook {
+	path: PathBuf,
+	config: AddressBookConfig,
+	entries: HashMap<PeerId, Entry>,
+	/// Whether `entries` changed since they were last written to disk.
+	dirty: bool,
+	next_persist: Delay,
+}
+
+impl AddressBook {
+	/// Loads the address book stored at `path`, pruning the stale entries.
+	pub fn load(path: PathBuf, config: AddressBookConfig) -> Self {
+		let entries = match read_entries(&path) {
+			Ok(entries) => entries,
+			Err(err) => {
+				warn!(target: "sub-libp2p", "Failed to load the peer address book: {}", err);
+				HashMap::new()
+			},
+		};
+
+		let mut book = Self {
+			path,
+			next_persist: Delay::new(config.persist_interval),
+			config,
+			entries,
+			dirty: false,
+		};
+		book.prune();
+		debug!(target: "sub-libp2p", "Loaded {} peers from the address book", book.entries.len());
+		book
+	}
+
+	/// Returns the addresses of all the peers in the book.
+	pub fn addresses(&self) -> impl Iterator<Item = (&PeerId, &Multiaddr)> {
+		self.entries
+			.iter()
+			.flat_map(|(peer_id, entry)| entry.addresses.iter().map(move |addr| (peer_id, addr)))
+	}
+
+	/// Records that we successfully dialed `peer_id` at `address`.
+	pub fn on_dialed(&mut self, peer_id: PeerId, address: &Multiaddr) {
+		let entry = self
+			.entries
+			.entry(peer_id)
+			.or_insert_with(|| Entry { addresses: Vec::new(), last_seen: 0 });
+		entry.last_seen = now();
+		entry.addresses.retain(|a| a != address);
+		entry.addresses.insert(0, address.clone());
+		entry.addresses.truncate(MAX_ADDRESSES_PER_PEER);
+		self.dirty = true;
+	}
+
+	/// Records that `peer_id` connected to us. Only refreshes peers that are already known, as
+	/// the address of an incoming connection can't be dialed back.
+	pub fn on_connected(&mut self, peer_id: &PeerId) {
+		if let Some(entry) = self.entries.get_mut(peer_id) {
+			entry.last_seen = now();
+			self.dirty = true;
+		}
+	}
+
+	/// Forgets an address of `peer_id` that we failed to dial.
+	pub fn on_dial_failure(&mut self, peer_id: &PeerId, address: &Multiaddr) {
+		if let Some(entry) = self.entries.get_mut(peer_id) {
+			if let Some(position) = entry.addresses.iter().position(|a| a == address) {
+				entry.addresses.remove(position);
+				if entry.addresses.is_empty() {
+					self.entries.remove(peer_id);
+				}
+				self.dirty = true;
+			}
+		}
+	}
+
+	/// Writes the book to disk if it changed, once every
+	/// [`AddressBookConfig::persist_interval`].
+	pub fn poll(&mut self, cx: &mut Context) -> Poll<()> {
+		while self.next_persist.poll_unpin(cx).is_ready() {
+			self.next_persist = Delay::new(self.config.persist_interval);
+			self.persist();
+		}
+
+		Poll::Pending
+	}
+
+	/// Removes the entries older than [`AddressBookConfig::max_age`] and keeps at most
+	/// [`AddressBookConfig::max_peers`] of the most recently seen peers.
+	fn prune(&mut self) {
+		let len = self.entries.len();
+		let oldest = now().saturating_sub(self.config.max_age.as_secs());
+		self.entries.retain(|_, entry| entry.last_seen >= oldest);
+
+		if self.entries.len() > self.config.max_peers {
+			let mut by_last_seen = self
+				.entries
+				.iter()
+				.map(|(peer_id, e)| (e.last_seen, *peer_id))
+				.collect::<Vec<_>>();
+			by_last_seen.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+			for (_, peer_id) in by_last_seen.into_iter().skip(self.config.max_peers) {
+				self.entries.remove(&peer_id);
+			}
+		}
+
+		self.dirty |= self.entries.len() != len;
+	}
+
+	fn persist(&mut self) {
+		self.prune();
+		if !self.dirty {
+			return
+		}
+
+		match write_entries(&self.path, &self.entries) {
+			Ok(()) => self.dirty = false,
+			Err(err) =>
+				warn!(target: "sub-libp2p", "Failed to persist the peer address book: {}", err),
+		}
+	}
+}
+
+impl Drop for AddressBook {
+	fn drop(&mut self) {
+		self.persist();
+	}
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn read_entries(path: &Path) -> io::Result<HashMap<PeerId, Entry>> {
+	let entries: HashMap<String, Entry> = match fs::read(path) {
+		Ok(data) => serde_json::from_slice(&data)?,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+		Err(err) => return Err(err),
+	};
+
+	Ok(entries
+		.into_iter()
+		.filter_map(|(peer_id, entry)| Some((peer_id.parse().ok()?, entry)))
+		.collect())
+}
+
+fn write_entries(path: &Path, entries: &HashMap<PeerId, Entry>) -> io::Result<()> {
+	let entries = entries
+		.iter()
+		.map(|(peer_id, entry)| (peer_id.to_base58(), entry))
+		.collect::<HashMap<_, _>>();
+	let tmp_path = path.with_extension("json.tmp");
+	fs::write(&tmp_path, serde_json::to_vec(&entries)?)?;
+	fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	fn address(port: u16) -> Multiaddr {
+		format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap()
+	}
+
+	#[test]
+	fn dialed_addresses_survive_reload() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join(ADDRESS_BOOK_FILE);
+		let (peer1, peer2) = (PeerId::random(), PeerId::random());
+
+		let mut book = AddressBook::load(path.clone(), Default::default());
+		book.on_dialed(peer1, &address(1));
+		book.on_dialed(peer1, &address(2));
+		book.on_dialed(peer2, &address(3));
+		book.on_dial_failure(&peer2, &address(3));
+		// Peers that only connected to us have no address to dial.
+		book.on_connected(&PeerId::random());
+		drop(book);
+
+		let book = AddressBook::load(path, Default::default());
+		let mut addresses = book.addresses().map(|(p, a)| (*p, a.clone())).collect::<Vec<_>>();
+		addresses.sort_by_key(|(_, a)| a.to_string());
+		assert_eq!(addresses, vec![(peer1, address(1)), (peer1, address(2))]);
+	}
+
+	#[test]
+	fn stale_and_excess_entries_are_pruned() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join(ADDRESS_BOOK_FILE);
+		let config = AddressBookConfig {
+			max_age: Duration::from_secs(3600),
+			max_peers: 2,
+			..Default::default()
+		};
+		let peers = (0..4).map(|_| PeerId::random()).collect::<Vec<_>>();
+
+		let entries = peers
+			.iter()
+			.enumerate()
+			.map(|(i, peer_id)| {
+				let entry = Entry {
+					addresses: vec![address(i as u16)],
+					last_seen: now() - i as u64 * 1000,
+				};
+				(*peer_id, entry)
+			})
+			.collect();
+		write_entries(&path, &entries).unwrap();
+
+		let book = AddressBook::load(path, config);
+		let mut kept = book.addresses().map(|(p, _)| *p).collect::<Vec<_>>();
+		kept.sort();
+		let mut expected = peers[..2].to_vec();
+		expected.sort();
+		assert_eq!(kept, expected);
+	}
+}
@@ -21,6 +21,7 @@ use crate::{
 	discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
 	peer_info,
 	protocol::{message::Roles, CustomMessageOutcome, NotificationsSink, Protocol},
+	rate_limit::RateLimits,
 	request_responses,
 };
 
@@ -219,6 +220,7 @@ where
 		// All remaining request protocol configs.
 		mut request_response_protocols: Vec<ProtocolConfig>,
 		peerset: PeersetHandle,
+		rate_limits: RateLimits,
 	) -> Result<Self, request_responses::RegisterError> {
 		// Extract protocol name and add to `request_response_protocols`.
 		let block_request_protocol_name = block_request_protocol_config.name.to_string();
@@ -243,6 +245,7 @@ where
 			request_responses: request_responses::RequestResponsesBehaviour::new(
 				request_response_protocols.into_iter(),
 				peerset,
+				rate_limits,
 			)?,
 			events: VecDeque::new(),
 			block_request_protocol_name,
@@ -22,7 +22,7 @@
 //! See the documentation of [`Params`].
 
 pub use sc_network_common::{
-	config::ProtocolId,
+	config::{ProtocolId, TrafficPriority},
 	request_responses::{
 		IncomingRequest, OutgoingResponse, ProtocolConfig as RequestResponseConfig,
 	},
@@ -50,10 +50,12 @@ use std::{
 	future::Future,
 	io::{self, Write},
 	net::Ipv4Addr,
+	num::NonZeroU64,
 	path::{Path, PathBuf},
 	pin::Pin,
 	str,
 	sync::Arc,
+	time::Duration,
 };
 use zeroize::Zeroize;
 
@@ -309,6 +311,12 @@ pub struct NetworkConfiguration {
 	/// Enable serving block data over IPFS bitswap.
 	pub ipfs_server: bool,
 
+	/// If true, the node can listen on and dial QUIC addresses, such as
+	/// `/ip4/0.0.0.0/udp/30333/quic`, in addition to the TCP and WebSocket ones.
+	///
+	/// Ignored with [`TransportConfig::MemoryOnly`].
+	pub enable_quic: bool,
+
 	/// Size of Yamux receive window of all substreams. `None` for the default (256kiB).
 	/// Any value less than 256kiB is invalid.
 	///
@@ -329,6 +337,15 @@ pub struct NetworkConfiguration {
 	/// a modification of the way the implementation works. Different nodes with different
 	/// configured values remain compatible with each other.
 	pub yamux_window_size: Option<u32>,
+
+	/// Configuration of the book of reachable peer addresses that discovery is seeded from on
+	/// start. The book is stored in [`NetworkConfiguration::net_config_path`].
+	///
+	/// `None` disables the address book.
+	pub address_book: Option<AddressBookConfig>,
+
+	/// Limits of the bandwidth used by the notifications and request-response protocols.
+	pub bandwidth: BandwidthConfig,
 }
 
 impl NetworkConfiguration {
@@ -360,6 +377,9 @@ impl NetworkConfiguration {
 			kademlia_disjoint_query_paths: false,
 			yamux_window_size: None,
 			ipfs_server: false,
+			enable_quic: false,
+			address_book: Some(Default::default()),
+			bandwidth: Default::default(),
 		}
 	}
 
@@ -394,6 +414,70 @@ impl NetworkConfiguration {
 	}
 }
 
+/// Configuration of the on-disk book of peer addresses.
+///
+/// Addresses we successfully dialed are recorded along with the last time we were connected to
+/// the peer, and are used to find peers again after a restart without relying on the bootnodes.
+#[derive(Clone, Debug)]
+pub struct AddressBookConfig {
+	/// How often the book is written to disk.
+	pub persist_interval: Duration,
+	/// Peers we haven't been connected to for longer than this are pruned from the book.
+	pub max_age: Duration,
+	/// Maximum number of peers kept in the book. The least recently seen peers are pruned
+	/// first.
+	pub max_peers: usize,
+}
+
+impl Default for AddressBookConfig {
+	fn default() -> Self {
+		Self {
+			persist_interval: Duration::from_secs(60),
+			max_age: Duration::from_secs(7 * 24 * 3600),
+			max_peers: 1000,
+		}
+	}
+}
+
+/// Limits of the bandwidth used by the notifications and request-response protocols.
+///
+/// Limits are expressed in bytes per second and enforced with token buckets that allow bursts
+/// of up to one second worth of traffic. Traffic over the limit is delayed rather than dropped.
+#[derive(Clone, Debug)]
+pub struct BandwidthConfig {
+	/// Maximum number of bytes per second received over all protocols. `None` for no limit.
+	pub max_inbound: Option<NonZeroU64>,
+	/// Maximum number of bytes per second sent over all protocols. `None` for no limit.
+	pub max_outbound: Option<NonZeroU64>,
+	/// Percentage of [`BandwidthConfig::max_inbound`] and [`BandwidthConfig::max_outbound`]
+	/// that only protocols with [`TrafficPriority::High`] can use, so that they are never
+	/// starved by lower priority traffic.
+	pub high_priority_reserve: u8,
+	/// Limits specific to some protocols, in addition to the global limits. Indexed by the main
+	/// name of the protocol.
+	pub protocols: HashMap<ProtocolName, ProtocolBandwidth>,
+}
+
+impl Default for BandwidthConfig {
+	fn default() -> Self {
+		Self {
+			max_inbound: None,
+			max_outbound: None,
+			high_priority_reserve: 25,
+			protocols: HashMap::new(),
+		}
+	}
+}
+
+/// Bandwidth limits of a single protocol.
+#[derive(Clone, Debug, Default)]
+pub struct ProtocolBandwidth {
+	/// Maximum number of bytes per second received over this protocol. `None` for no limit.
+	pub max_inbound: Option<NonZeroU64>,
+	/// Maximum number of bytes per second sent over this protocol. `None` for no limit.
+	pub max_outbound: Option<NonZeroU64>,
+}
+
 /// Configuration for a set of nodes.
 #[derive(Clone, Debug)]
 pub struct SetConfig {
@@ -442,6 +526,8 @@ pub struct NonDefaultSetConfig {
 	pub max_notification_size: u64,
 	/// Base configuration.
 	pub set_config: SetConfig,
+	/// Priority of the notifications of this set when the bandwidth is limited.
+	pub priority: TrafficPriority,
 }
 
 impl NonDefaultSetConfig {
@@ -457,6 +543,7 @@ impl NonDefaultSetConfig {
 				reserved_nodes: Vec::new(),
 				non_reserved_mode: NonReservedPeerMode::Deny,
 			},
+			priority: TrafficPriority::Normal,
 		}
 	}
 
@@ -32,6 +32,9 @@
 //! configured Kademlia DHTs in order for nodes to propagate to us their view of the network. This
 //! is performed automatically by the `DiscoveryBehaviour`.
 //!
+//! - Address book. The addresses of the nodes we successfully dialed are persisted on disk and
+//! loaded again on start, so that known nodes are found again after a restart.
+//!
 //! Additionally, the `DiscoveryBehaviour` is also capable of storing and loading value in the
 //! configured DHTs.
 //!
@@ -46,7 +49,7 @@
 //! active mechanism that asks nodes for the addresses they are listening on. Whenever we learn
 //! of a node's address, you must call `add_self_reported_address`.
 
-use crate::utils::LruHashSet;
+use crate::{address_book::AddressBook, config::AddressBookConfig, utils::LruHashSet};
 use futures::prelude::*;
 use futures_timer::Delay;
 use ip_network::IpNetwork;
@@ -79,6 +82,7 @@ use std::{
 	collections::{HashMap, HashSet, VecDeque},
 	io,
 	num::NonZeroUsize,
+	path::PathBuf,
 	task::{Context, Poll},
 	time::Duration,
 };
@@ -102,6 +106,7 @@ pub struct DiscoveryConfig {
 	enable_mdns: bool,
 	kademlia_disjoint_query_paths: bool,
 	protocol_ids: HashSet<ProtocolId>,
+	address_book: Option<(PathBuf, AddressBookConfig)>,
 }
 
 impl DiscoveryConfig {
@@ -117,6 +122,7 @@ impl DiscoveryConfig {
 			enable_mdns: false,
 			kademlia_disjoint_query_paths: false,
 			protocol_ids: HashSet::new(),
+			address_book: None,
 		}
 	}
 
@@ -179,6 +185,12 @@ impl DiscoveryConfig {
 		self
 	}
 
+	/// Persist the addresses of the nodes we dialed at `path`, and discover them again on start.
+	pub fn with_address_book(&mut self, path: PathBuf, config: AddressBookConfig) -> &mut Self {
+		self.address_book = Some((path, config));
+		self
+	}
+
 	/// Create a `DiscoveryBehaviour` from this config.
 	pub fn finish(self) -> DiscoveryBehaviour {
 		let Self {
@@ -191,8 +203,17 @@ impl DiscoveryConfig {
 			enable_mdns,
 			kademlia_disjoint_query_paths,
 			protocol_ids,
+			address_book,
 		} = self;
 
+		let address_book = address_book.map(|(path, config)| AddressBook::load(path, config));
+		let mut ephemeral_addresses = HashMap::<_, Vec<_>>::new();
+		if let Some(address_book) = &address_book {
+			for (peer_id, addr) in address_book.addresses() {
+				ephemeral_addresses.entry(*peer_id).or_default().push(addr.clone());
+			}
+		}
+
 		let kademlias = protocol_ids
 			.into_iter()
 			.map(|protocol_id| {
@@ -212,6 +233,11 @@ impl DiscoveryConfig {
 				for (peer_id, addr) in &permanent_addresses {
 					kad.add_address(peer_id, addr.clone());
 				}
+				for (peer_id, addrs) in &ephemeral_addresses {
+					for addr in addrs {
+						kad.add_address(peer_id, addr.clone());
+					}
+				}
 
 				(protocol_id, kad)
 			})
@@ -219,7 +245,12 @@ impl DiscoveryConfig {
 
 		DiscoveryBehaviour {
 			permanent_addresses,
-			ephemeral_addresses: HashMap::new(),
+			pending_events: ephemeral_addresses
+				.keys()
+				.map(|peer_id| DiscoveryOut::Discovered(*peer_id))
+				.collect(),
+			ephemeral_addresses,
+			address_book,
 			kademlias,
 			next_kad_random_query: if dht_random_walk {
 				Some(Delay::new(Duration::new(0, 0)))
@@ -227,7 +258,6 @@ impl DiscoveryConfig {
 				None
 			},
 			duration_to_next_kad: Duration::from_secs(1),
-			pending_events: VecDeque::new(),
 			local_peer_id,
 			num_connections: 0,
 			allow_private_ipv4,
@@ -254,6 +284,8 @@ pub struct DiscoveryBehaviour {
 	/// Same as `permanent_addresses`, except that addresses that fail to reach a peer are
 	/// removed.
 	ephemeral_addresses: HashMap<PeerId, Vec<Multiaddr>>,
+	/// Addresses of the nodes we dialed, persisted on disk. `None` if disabled.
+	address_book: Option<AddressBook>,
 	/// Kademlia requests and answers.
 	kademlias: HashMap<ProtocolId, Kademlia<MemoryStore>>,
 	/// Discovers nodes on the local network.
@@ -572,6 +604,12 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 		other_established: usize,
 	) {
 		self.num_connections += 1;
+		if let Some(address_book) = &mut self.address_book {
+			match endpoint {
+				ConnectedPoint::Dialer { address, .. } => address_book.on_dialed(*peer_id, address),
+				ConnectedPoint::Listener { .. } => address_book.on_connected(peer_id),
+			}
+		}
 		for k in self.kademlias.values_mut() {
 			NetworkBehaviour::inject_connection_established(
 				k,
@@ -619,6 +657,11 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 						list.retain(|a| a != addr);
 					}
 				}
+				if let Some(address_book) = &mut self.address_book {
+					for (addr, _error) in errors {
+						address_book.on_dial_failure(&peer_id, addr);
+					}
+				}
 			}
 		}
 
@@ -717,6 +760,10 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev))
 		}
 
+		if let Some(address_book) = &mut self.address_book {
+			let _ = address_book.poll(cx);
+		}
+
 		// Poll the stream that fires when we need to start a random Kademlia query.
 		if let Some(next_kad_random_query) = self.next_kad_random_query.as_mut() {
 			while next_kad_random_query.poll_unpin(cx).is_ready() {
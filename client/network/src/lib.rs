@@ -244,10 +244,12 @@
 //!
 //! More precise usage details are still being worked on and will likely change in the future.
 
+mod address_book;
 mod behaviour;
 mod discovery;
 mod peer_info;
 mod protocol;
+mod rate_limit;
 mod request_responses;
 mod schema;
 mod service;
@@ -280,11 +282,11 @@ pub use sc_network_common::{
 	},
 };
 pub use service::{
-	DecodingError, Keypair, NetworkService, NetworkWorker, NotificationSender,
-	NotificationSenderReady, OutboundFailure, PublicKey,
+	BanTarget, DecodingError, Keypair, NetworkService, NetworkWorker, NotificationSender,
+	NotificationSenderReady, OutboundFailure, PublicKey, BAN_LIST_FILE,
 };
 
-pub use sc_peerset::ReputationChange;
+pub use sc_peerset::{PeerReputation, ReputationChange};
 
 /// The maximum allowed number of established connections per peer.
 ///
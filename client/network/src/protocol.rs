@@ -18,6 +18,7 @@
 
 use crate::{
 	config, error,
+	rate_limit::RateLimits,
 	utils::{interval, LruHashSet},
 };
 
@@ -273,6 +274,7 @@ where
 		notifications_protocols_handshakes: Vec<Vec<u8>>,
 		metrics_registry: Option<&Registry>,
 		chain_sync: Box<dyn ChainSync<B>>,
+		rate_limits: RateLimits,
 	) -> error::Result<(Self, sc_peerset::PeersetHandle, Vec<(PeerId, Multiaddr)>)> {
 		let info = chain.info();
 
@@ -390,6 +392,7 @@ where
 				fallback_names: iter::once(legacy_ba_protocol_name.into()).collect(),
 				handshake: block_announces_handshake,
 				max_notification_size: MAX_BLOCK_ANNOUNCE_SIZE,
+				priority: config::TrafficPriority::High,
 			};
 
 			Notifications::new(
@@ -401,9 +404,11 @@ where
 							fallback_names: s.fallback_names.clone(),
 							handshake: hs,
 							max_notification_size: s.max_notification_size,
+							priority: s.priority,
 						},
 					),
 				),
+				rate_limits,
 			)
 		};
 
@@ -477,6 +482,11 @@ where
 		self.behaviour.peerset_debug_info()
 	}
 
+	/// Returns the reputation of the nodes known to the peerset manager.
+	pub fn peer_reputations(&mut self) -> Vec<sc_peerset::PeerReputation> {
+		self.behaviour.peer_reputations()
+	}
+
 	/// Returns the number of peers we're connected to.
 	pub fn num_connected_peers(&self) -> usize {
 		self.peers.len()
@@ -16,8 +16,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::protocol::notifications::handler::{
-	self, NotificationsSink, NotifsHandlerIn, NotifsHandlerOut, NotifsHandlerProto,
+use crate::{
+	config::TrafficPriority,
+	protocol::notifications::handler::{
+		self, NotificationsSink, NotifsHandlerIn, NotifsHandlerOut, NotifsHandlerProto,
+	},
+	rate_limit::RateLimits,
 };
 
 use bytes::BytesMut;
@@ -103,6 +107,9 @@ pub struct Notifications {
 	/// Notification protocols. Entries never change after initialization.
 	notif_protocols: Vec<handler::ProtocolConfig>,
 
+	/// Bandwidth limits applied by the handlers.
+	rate_limits: RateLimits,
+
 	/// Receiver for instructions about who to connect to or disconnect from.
 	peerset: sc_peerset::Peerset,
 
@@ -146,6 +153,8 @@ pub struct ProtocolConfig {
 	pub handshake: Vec<u8>,
 	/// Maximum allowed size for a notification.
 	pub max_notification_size: u64,
+	/// Priority of the notifications when the bandwidth is limited.
+	pub priority: TrafficPriority,
 }
 
 /// Identifier for a delay firing.
@@ -357,6 +366,7 @@ impl Notifications {
 	pub fn new(
 		peerset: sc_peerset::Peerset,
 		notif_protocols: impl Iterator<Item = ProtocolConfig>,
+		rate_limits: RateLimits,
 	) -> Self {
 		let notif_protocols = notif_protocols
 			.map(|cfg| handler::ProtocolConfig {
@@ -364,6 +374,7 @@ impl Notifications {
 				fallback_names: cfg.fallback_names,
 				handshake: Arc::new(RwLock::new(cfg.handshake)),
 				max_notification_size: cfg.max_notification_size,
+				priority: cfg.priority,
 			})
 			.collect::<Vec<_>>();
 
@@ -371,6 +382,7 @@ impl Notifications {
 
 		Self {
 			notif_protocols,
+			rate_limits,
 			peerset,
 			peers: FnvHashMap::default(),
 			delays: Default::default(),
@@ -601,6 +613,11 @@ impl Notifications {
 		self.peerset.debug_info()
 	}
 
+	/// Returns the reputation of the nodes known to the peerset manager.
+	pub fn peer_reputations(&mut self) -> Vec<sc_peerset::PeerReputation> {
+		self.peerset.peer_reputations()
+	}
+
 	/// Function that is called when the peerset wants us to connect to a peer.
 	fn peerset_report_connect(&mut self, peer_id: PeerId, set_id: sc_peerset::SetId) {
 		// If `PeerId` is unknown to us, insert an entry, start dialing, and return early.
@@ -1053,7 +1070,7 @@ impl NetworkBehaviour for Notifications {
 	type OutEvent = NotificationsOut;
 
 	fn new_handler(&mut self) -> Self::ConnectionHandler {
-		NotifsHandlerProto::new(self.notif_protocols.clone())
+		NotifsHandlerProto::new(self.notif_protocols.clone(), self.rate_limits.clone())
 	}
 
 	fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
@@ -57,9 +57,13 @@
 //! It is illegal to send a [`NotifsHandlerIn::Open`] before a previously-emitted
 //! [`NotifsHandlerIn::Open`] has gotten an answer.
 
-use crate::protocol::notifications::upgrade::{
-	NotificationsHandshakeError, NotificationsIn, NotificationsInSubstream, NotificationsOut,
-	NotificationsOutSubstream, UpgradeCollec,
+use crate::{
+	config::TrafficPriority,
+	protocol::notifications::upgrade::{
+		NotificationsHandshakeError, NotificationsIn, NotificationsInSubstream, NotificationsOut,
+		NotificationsOutSubstream, UpgradeCollec,
+	},
+	rate_limit::{Direction, RateLimits, Throttle},
 };
 
 use bytes::BytesMut;
@@ -78,7 +82,7 @@ use libp2p::{
 		KeepAlive, NegotiatedSubstream, SubstreamProtocol,
 	},
 };
-use log::error;
+use log::{debug, error};
 use parking_lot::{Mutex, RwLock};
 use sc_network_common::protocol::ProtocolName;
 use std::{
@@ -117,6 +121,9 @@ pub struct NotifsHandlerProto {
 	/// Name of protocols, prototypes for upgrades for inbound substreams, and the message we
 	/// send or respond with in the handshake.
 	protocols: Vec<ProtocolConfig>,
+
+	/// Bandwidth limits shared with the other connections.
+	rate_limits: RateLimits,
 }
 
 /// The actual handler once the connection has been established.
@@ -152,6 +159,8 @@ pub struct ProtocolConfig {
 	pub handshake: Arc<RwLock<Vec<u8>>>,
 	/// Maximum allowed size for a notification.
 	pub max_notification_size: u64,
+	/// Priority of the notifications when the bandwidth is limited.
+	pub priority: TrafficPriority,
 }
 
 /// Fields specific for each individual protocol.
@@ -162,6 +171,12 @@ struct Protocol {
 	/// Prototype for the inbound upgrade.
 	in_upgrade: NotificationsIn,
 
+	/// Bandwidth limits of the received notifications.
+	inbound_throttle: Throttle,
+
+	/// Bandwidth limits of the sent notifications.
+	outbound_throttle: Throttle,
+
 	/// Current state of the substreams for this protocol.
 	state: State,
 }
@@ -252,7 +267,24 @@ impl IntoConnectionHandler for NotifsHandlerProto {
 						config.max_notification_size,
 					);
 
-					Protocol { config, in_upgrade, state: State::Closed { pending_opening: false } }
+					let inbound_throttle = self.rate_limits.throttle(
+						&config.name,
+						Direction::Inbound,
+						config.priority,
+					);
+					let outbound_throttle = self.rate_limits.throttle(
+						&config.name,
+						Direction::Outbound,
+						config.priority,
+					);
+
+					Protocol {
+						config,
+						in_upgrade,
+						inbound_throttle,
+						outbound_throttle,
+						state: State::Closed { pending_opening: false },
+					}
 				})
 				.collect(),
 			peer_id: *peer_id,
@@ -393,7 +425,8 @@ impl NotificationsSink {
 	/// Sends a notification to the peer.
 	///
 	/// If too many messages are already buffered, the notification is silently discarded and the
-	/// connection to the peer will be closed shortly after.
+	/// connection to the peer will be closed shortly after. While the bandwidth limits of the
+	/// protocol are exceeded, the notification is silently discarded instead.
 	///
 	/// The protocol name is expected to be checked ahead of calling this method. It is a logic
 	/// error to send a notification using an unknown protocol.
@@ -469,8 +502,10 @@ impl NotifsHandlerProto {
 	/// handshake, and the maximum allowed size of a notification. At the moment, the message
 	/// is always the same whether we open a substream ourselves or respond to handshake from
 	/// the remote.
-	pub fn new(list: impl Into<Vec<ProtocolConfig>>) -> Self {
-		Self { protocols: list.into() }
+	///
+	/// The traffic of the notifications is subject to `rate_limits`.
+	pub fn new(list: impl Into<Vec<ProtocolConfig>>, rate_limits: RateLimits) -> Self {
+		Self { protocols: list.into(), rate_limits }
 	}
 }
 
@@ -730,22 +765,25 @@ impl ConnectionHandler for NotifsHandler {
 		// For each open substream, try send messages from `notifications_sink_rx` to the
 		// substream.
 		for protocol_index in 0..self.protocols.len() {
+			let Protocol { config, state, outbound_throttle, .. } =
+				&mut self.protocols[protocol_index];
 			if let State::Open {
 				notifications_sink_rx, out_substream: Some(out_substream), ..
-			} = &mut self.protocols[protocol_index].state
+			} = state
 			{
 				loop {
 					// Only proceed with `out_substream.poll_ready_unpin` if there is an element
 					// available in `notifications_sink_rx`. This avoids waking up the task when
 					// a substream is ready to send if there isn't actually something to send.
-					match Pin::new(&mut *notifications_sink_rx).as_mut().poll_peek(cx) {
+					let len = match Pin::new(&mut *notifications_sink_rx).as_mut().poll_peek(cx) {
 						Poll::Ready(Some(&NotificationsSinkMessage::ForceClose)) =>
 							return Poll::Ready(ConnectionHandlerEvent::Close(
 								NotifsHandlerError::SyncNotificationsClogged,
 							)),
-						Poll::Ready(Some(&NotificationsSinkMessage::Notification { .. })) => {},
+						Poll::Ready(Some(NotificationsSinkMessage::Notification { message })) =>
+							message.len(),
 						Poll::Ready(None) | Poll::Pending => break,
-					}
+					};
 
 					// Before we extract the element from `notifications_sink_rx`, check that the
 					// substream is ready to accept a message.
@@ -754,6 +792,29 @@ impl ConnectionHandler for NotifsHandler {
 						Poll::Pending => break,
 					}
 
+					// Then wait for the bandwidth limits to allow sending it.
+					if outbound_throttle.poll_take(cx, len as u64).is_pending() {
+						// Notifications sent through `send_sync_notification` don't wait for
+						// room in the channel. Letting them queue up while the limits hold the
+						// substream back would clog the channel and close the connection, so
+						// they are dropped instead.
+						let (_, sync_rx) = notifications_sink_rx.get_mut().get_mut();
+						let mut dropped = 0;
+						while let Poll::Ready(Some(_)) = sync_rx.poll_next_unpin(cx) {
+							dropped += 1;
+						}
+						if dropped != 0 {
+							debug!(
+								target: "sub-libp2p",
+								"Dropped {} notifications of {} to {} exceeding the bandwidth limits",
+								dropped,
+								config.name,
+								self.peer_id,
+							);
+						}
+						break
+					}
+
 					// Now that the substream is ready for a message, grab what to send.
 					let message = match notifications_sink_rx.poll_next_unpin(cx) {
 						Poll::Ready(Some(NotificationsSinkMessage::Notification { message })) =>
@@ -804,15 +865,22 @@ impl ConnectionHandler for NotifsHandler {
 		for protocol_index in 0..self.protocols.len() {
 			// Inbound substreams being closed is always tolerated, except for the
 			// `OpenDesiredByRemote` state which might need to be switched back to `Closed`.
-			match &mut self.protocols[protocol_index].state {
+			let Protocol { state, inbound_throttle, .. } = &mut self.protocols[protocol_index];
+			match state {
 				State::Closed { .. } |
 				State::Open { in_substream: None, .. } |
 				State::Opening { in_substream: None } => {},
 
+				// Stop reading while the bandwidth limits are exceeded, which eventually applies
+				// back-pressure on the remote.
+				State::Open { in_substream: Some(_), .. }
+					if inbound_throttle.poll_take(cx, 0).is_pending() => {},
+
 				State::Open { in_substream: in_substream @ Some(_), .. } =>
 					match Stream::poll_next(Pin::new(in_substream.as_mut().unwrap()), cx) {
 						Poll::Pending => {},
 						Poll::Ready(Some(Ok(message))) => {
+							inbound_throttle.charge(message.len() as u64);
 							let event = NotifsHandlerOut::Notification { protocol_index, message };
 							return Poll::Ready(ConnectionHandlerEvent::Custom(event))
 						},
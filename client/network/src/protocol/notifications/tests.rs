@@ -18,7 +18,11 @@
 
 #![cfg(test)]
 
-use crate::protocol::notifications::{Notifications, NotificationsOut, ProtocolConfig};
+use crate::{
+	config::{BandwidthConfig, ProtocolBandwidth},
+	protocol::notifications::{Notifications, NotificationsOut, ProtocolConfig},
+	rate_limit::RateLimits,
+};
 
 use futures::prelude::*;
 use libp2p::{
@@ -36,6 +40,7 @@ use libp2p::{
 };
 use std::{
 	error, io, iter,
+	num::NonZeroU64,
 	task::{Context, Poll},
 	time::Duration,
 };
@@ -43,6 +48,13 @@ use std::{
 /// Builds two nodes that have each other as bootstrap nodes.
 /// This is to be used only for testing, and a panic will happen if something goes wrong.
 fn build_nodes() -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
+	build_nodes_with_limits(&Default::default())
+}
+
+/// Same as [`build_nodes`], but the traffic of each node is subject to the `limits`.
+fn build_nodes_with_limits(
+	limits: &BandwidthConfig,
+) -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
 	let mut out = Vec::with_capacity(2);
 
 	let keypairs: Vec<_> = (0..2).map(|_| identity::Keypair::generate_ed25519()).collect();
@@ -85,7 +97,9 @@ fn build_nodes() -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
 					fallback_names: Vec::new(),
 					handshake: Vec::new(),
 					max_notification_size: 1024 * 1024,
+					priority: Default::default(),
 				}),
+				RateLimits::new(limits),
 			),
 			addrs: addrs
 				.iter()
@@ -347,3 +361,73 @@ fn reconnect_after_disconnect() {
 		}
 	});
 }
+
+#[test]
+fn flooding_a_limited_protocol_keeps_the_peer_connected() {
+	// Node 1 sends notifications much faster than the bandwidth limits of the protocol allow, and
+	// more of them than the channel of the notifications sink can hold. The ones exceeding the
+	// limits must be dropped rather than clog the channel and close the connection.
+
+	let limits = BandwidthConfig {
+		protocols: iter::once((
+			"/foo".into(),
+			ProtocolBandwidth { max_outbound: NonZeroU64::new(4096), ..Default::default() },
+		))
+		.collect(),
+		..Default::default()
+	};
+	let (mut service1, mut service2) = build_nodes_with_limits(&limits);
+
+	futures::executor::block_on(async move {
+		let mut received = 0;
+		// Started once the substream is open and flooded.
+		let mut delay = None;
+
+		loop {
+			let event = {
+				let s1 = service1.select_next_some();
+				let s2 = service2.select_next_some();
+				futures::pin_mut!(s1, s2);
+				let timeout = match &mut delay {
+					Some(delay) => future::Either::Left(delay),
+					None => future::Either::Right(future::pending()),
+				};
+				match future::select(future::select(s1, s2), timeout).await {
+					future::Either::Right(_) => break,
+					future::Either::Left((future::Either::Left((ev, _)), _)) =>
+						future::Either::Left(ev),
+					future::Either::Left((future::Either::Right((ev, _)), _)) =>
+						future::Either::Right(ev),
+				}
+			};
+
+			match event {
+				future::Either::Left(SwarmEvent::Behaviour(
+					NotificationsOut::CustomProtocolOpen { notifications_sink, .. },
+				)) => {
+					assert!(delay.is_none());
+					for _ in 0..10_000 {
+						notifications_sink.send_sync_notification(vec![0; 256]);
+					}
+					delay = Some(futures_timer::Delay::new(Duration::from_secs(3)));
+				},
+				future::Either::Right(SwarmEvent::Behaviour(NotificationsOut::Notification {
+					..
+				})) => received += 1,
+				future::Either::Left(SwarmEvent::Behaviour(
+					NotificationsOut::CustomProtocolClosed { .. },
+				)) |
+				future::Either::Right(SwarmEvent::Behaviour(
+					NotificationsOut::CustomProtocolClosed { .. },
+				)) |
+				future::Either::Left(SwarmEvent::ConnectionClosed { .. }) |
+				future::Either::Right(SwarmEvent::ConnectionClosed { .. }) => panic!(),
+				_ => {},
+			}
+		}
+
+		// Some notifications got through, but far from all of them.
+		assert!(received > 0);
+		assert!(received < 10_000);
+	});
+}
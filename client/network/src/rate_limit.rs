@@ -0,0 +1,354 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Bandwidth limits of the notifications and request-response protocols.
+//!
+//! The limits of [`BandwidthConfig`] are enforced with token buckets shared by all the
+//! connections. Sending or receiving a message takes as many tokens as the message has bytes
+//! from both the bucket of the protocol and the global bucket of the direction. When the tokens
+//! run out, the traffic is delayed until the buckets are refilled.
+//!
+//! A share of each global bucket is reserved to [`TrafficPriority::High`] protocols: traffic of
+//! normal priority is delayed as soon as the bucket falls below that share.
+
+use crate::config::{BandwidthConfig, TrafficPriority};
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use parking_lot::Mutex;
+use sc_network_common::protocol::ProtocolName;
+use std::{
+	collections::HashMap,
+	future,
+	num::NonZeroU64,
+	sync::Arc,
+	task::{Context, Poll},
+	time::{Duration, Instant},
+};
+
+/// Direction of the traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	/// Traffic received from the remote.
+	Inbound,
+	/// Traffic sent to the remote.
+	Outbound,
+}
+
+/// Handle to the bandwidth limits shared by all the connections. Cheap to clone.
+///
+/// The default value doesn't enforce any limit.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+	/// `None` if no limit is configured at all.
+	buckets: Option<Arc<Mutex<Buckets>>>,
+}
+
+#[derive(Debug)]
+struct Buckets {
+	inbound: Option<TokenBucket>,
+	outbound: Option<TokenBucket>,
+	/// Percentage of the global buckets only usable by high priority traffic.
+	high_priority_reserve: u8,
+	/// Buckets of the protocols with specific limits, as `(inbound, outbound)`.
+	protocols: HashMap<ProtocolName, (Option<TokenBucket>, Option<TokenBucket>)>,
+}
+
+impl RateLimits {
+	/// Builds the limits described by `config`.
+	pub fn new(config: &BandwidthConfig) -> Self {
+		let protocols = config
+			.protocols
+			.iter()
+			.filter(|(_, limits)| limits.max_inbound.is_some() || limits.max_outbound.is_some())
+			.map(|(name, limits)| {
+				let buckets = (
+					limits.max_inbound.map(TokenBucket::new),
+					limits.max_outbound.map(TokenBucket::new),
+				);
+				(name.clone(), buckets)
+			})
+			.collect::<HashMap<_, _>>();
+
+		if config.max_inbound.is_none() && config.max_outbound.is_none() && protocols.is_empty() {
+			return Self::default()
+		}
+
+		let buckets = Buckets {
+			inbound: config.max_inbound.map(TokenBucket::new),
+			outbound: config.max_outbound.map(TokenBucket::new),
+			high_priority_reserve: config.high_priority_reserve.min(100),
+			protocols,
+		};
+		Self { buckets: Some(Arc::new(Mutex::new(buckets))) }
+	}
+
+	/// Returns a [`Throttle`] for the traffic of `protocol` in the given direction.
+	pub fn throttle(
+		&self,
+		protocol: &ProtocolName,
+		direction: Direction,
+		priority: TrafficPriority,
+	) -> Throttle {
+		Throttle {
+			limits: self.clone(),
+			protocol: protocol.clone(),
+			direction,
+			priority,
+			delay: None,
+		}
+	}
+
+	/// Takes `bytes` tokens from the buckets of `protocol`. On failure, returns how long to wait
+	/// before trying again.
+	///
+	/// A message larger than what the buckets can hold goes through once they are full, leaving
+	/// them in debt. Taking zero bytes succeeds if and only if the buckets aren't in debt.
+	fn try_take(
+		&self,
+		protocol: &ProtocolName,
+		direction: Direction,
+		priority: TrafficPriority,
+		bytes: u64,
+	) -> Result<(), Duration> {
+		let mut buckets = match &self.buckets {
+			Some(buckets) => buckets.lock(),
+			None => return Ok(()),
+		};
+		let now = Instant::now();
+
+		let reserve = match priority {
+			TrafficPriority::High => 0,
+			TrafficPriority::Normal => buckets.high_priority_reserve,
+		};
+		let (global, protocol) = buckets.of(protocol, direction);
+
+		let global_wait = global.as_ref().map_or(Duration::ZERO, |b| b.wait(now, bytes, reserve));
+		let protocol_wait = protocol.as_ref().map_or(Duration::ZERO, |b| b.wait(now, bytes, 0));
+		let wait = global_wait.max(protocol_wait);
+		if !wait.is_zero() {
+			return Err(wait)
+		}
+
+		for bucket in global.into_iter().chain(protocol) {
+			bucket.take(now, bytes);
+		}
+		Ok(())
+	}
+
+	/// Waits until `bytes` tokens could be taken from the buckets of `protocol`, and takes them.
+	pub async fn take(
+		&self,
+		protocol: &ProtocolName,
+		direction: Direction,
+		priority: TrafficPriority,
+		bytes: u64,
+	) {
+		if self.buckets.is_none() {
+			return
+		}
+
+		let mut throttle = self.throttle(protocol, direction, priority);
+		future::poll_fn(|cx| throttle.poll_take(cx, bytes)).await
+	}
+}
+
+/// Bandwidth limits of one protocol in one direction, usable from `poll` methods.
+#[derive(Debug)]
+pub struct Throttle {
+	limits: RateLimits,
+	protocol: ProtocolName,
+	direction: Direction,
+	priority: TrafficPriority,
+	/// Fires when the buckets might have enough tokens again.
+	delay: Option<Delay>,
+}
+
+impl Throttle {
+	/// Takes `bytes` tokens from the buckets, or returns `Pending` and wakes up the task once
+	/// they might be available.
+	///
+	/// Polling with zero bytes doesn't take anything, but waits for the buckets to be out of
+	/// debt. This is used for inbound traffic, whose size is only known once received.
+	pub fn poll_take(&mut self, cx: &mut Context, bytes: u64) -> Poll<()> {
+		loop {
+			if let Some(delay) = &mut self.delay {
+				match delay.poll_unpin(cx) {
+					Poll::Ready(()) => self.delay = None,
+					Poll::Pending => return Poll::Pending,
+				}
+			}
+
+			match self.limits.try_take(&self.protocol, self.direction, self.priority, bytes) {
+				Ok(()) => return Poll::Ready(()),
+				Err(wait) => self.delay = Some(Delay::new(wait)),
+			}
+		}
+	}
+
+	/// Takes `bytes` tokens from the buckets unconditionally, possibly putting them in debt.
+	pub fn charge(&mut self, bytes: u64) {
+		if let Some(buckets) = &self.limits.buckets {
+			let mut buckets = buckets.lock();
+			let now = Instant::now();
+			let (global, protocol) = buckets.of(&self.protocol, self.direction);
+			for bucket in global.into_iter().chain(protocol) {
+				bucket.take(now, bytes);
+			}
+		}
+	}
+}
+
+impl Buckets {
+	/// Returns the global bucket and the bucket of `protocol` for the given direction.
+	fn of(
+		&mut self,
+		protocol: &ProtocolName,
+		direction: Direction,
+	) -> (Option<&mut TokenBucket>, Option<&mut TokenBucket>) {
+		let protocol = self.protocols.get_mut(protocol);
+		match direction {
+			Direction::Inbound =>
+				(self.inbound.as_mut(), protocol.and_then(|(inbound, _)| inbound.as_mut())),
+			Direction::Outbound =>
+				(self.outbound.as_mut(), protocol.and_then(|(_, outbound)| outbound.as_mut())),
+		}
+	}
+}
+
+/// Token bucket refilled at `rate` tokens per second, holding at most `rate` tokens.
+#[derive(Debug)]
+struct TokenBucket {
+	rate: f64,
+	/// Number of tokens, negative if the bucket is in debt.
+	tokens: f64,
+	last_refill: Instant,
+}
+
+impl TokenBucket {
+	fn new(rate: NonZeroU64) -> Self {
+		let rate = rate.get() as f64;
+		Self { rate, tokens: rate, last_refill: Instant::now() }
+	}
+
+	fn refill(&mut self, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+		self.last_refill = now;
+	}
+
+	/// Returns how long to wait before `bytes` tokens can be taken while leaving `reserve`
+	/// percent of the capacity in the bucket. Zero if they can be taken now.
+	fn wait(&self, now: Instant, bytes: u64, reserve: u8) -> Duration {
+		let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+		let tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+		let reserve = self.rate * f64::from(reserve) / 100.0;
+		// Messages larger than the usable capacity only require the bucket to be full.
+		let needed = reserve + (bytes as f64).min(self.rate - reserve);
+		if tokens >= needed {
+			Duration::ZERO
+		} else {
+			// Round up to avoid waking up slightly too early and spinning.
+			Duration::from_secs_f64((needed - tokens) / self.rate) + Duration::from_millis(1)
+		}
+	}
+
+	fn take(&mut self, now: Instant, bytes: u64) {
+		self.refill(now);
+		self.tokens -= bytes as f64;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::ProtocolBandwidth;
+
+	fn limits(global: u64, reserve: u8, protocol: Option<(&ProtocolName, u64)>) -> RateLimits {
+		let mut config = BandwidthConfig {
+			max_outbound: NonZeroU64::new(global),
+			high_priority_reserve: reserve,
+			..Default::default()
+		};
+		if let Some((name, rate)) = protocol {
+			config.protocols.insert(
+				name.clone(),
+				ProtocolBandwidth { max_outbound: NonZeroU64::new(rate), ..Default::default() },
+			);
+		}
+		RateLimits::new(&config)
+	}
+
+	#[test]
+	fn unlimited_by_default() {
+		let limits = RateLimits::new(&BandwidthConfig::default());
+		let name = ProtocolName::from("/test/1");
+		for direction in [Direction::Inbound, Direction::Outbound] {
+			assert!(limits.try_take(&name, direction, TrafficPriority::Normal, u64::MAX).is_ok());
+		}
+	}
+
+	#[test]
+	fn normal_priority_cannot_use_reserve() {
+		let limits = limits(1000, 25, None);
+		let name = ProtocolName::from("/test/1");
+
+		assert!(limits
+			.try_take(&name, Direction::Outbound, TrafficPriority::Normal, 700)
+			.is_ok());
+		// Only 300 bytes left, all of them reserved.
+		assert!(limits
+			.try_take(&name, Direction::Outbound, TrafficPriority::Normal, 100)
+			.is_err());
+		assert!(limits.try_take(&name, Direction::Outbound, TrafficPriority::High, 250).is_ok());
+		// Inbound is not limited.
+		assert!(limits
+			.try_take(&name, Direction::Inbound, TrafficPriority::Normal, 5000)
+			.is_ok());
+	}
+
+	#[test]
+	fn protocol_limit_applies_on_top_of_global_limit() {
+		let limited = ProtocolName::from("/limited/1");
+		let other = ProtocolName::from("/other/1");
+		let limits = limits(10_000, 0, Some((&limited, 100)));
+
+		assert!(limits
+			.try_take(&limited, Direction::Outbound, TrafficPriority::High, 100)
+			.is_ok());
+		let wait = limits
+			.try_take(&limited, Direction::Outbound, TrafficPriority::High, 50)
+			.unwrap_err();
+		assert!(wait > Duration::from_millis(400) && wait < Duration::from_secs(1));
+		assert!(limits
+			.try_take(&other, Direction::Outbound, TrafficPriority::High, 5000)
+			.is_ok());
+	}
+
+	#[test]
+	fn oversized_messages_put_buckets_in_debt() {
+		let limits = limits(1000, 0, None);
+		let name = ProtocolName::from("/test/1");
+
+		assert!(limits.try_take(&name, Direction::Outbound, TrafficPriority::High, 5000).is_ok());
+		let wait = limits
+			.try_take(&name, Direction::Outbound, TrafficPriority::High, 0)
+			.unwrap_err();
+		assert!(wait > Duration::from_secs(3));
+	}
+}
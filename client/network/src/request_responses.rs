@@ -34,7 +34,11 @@
 //! - If provided, a ["requests processing"](ProtocolConfig::inbound_queue) channel
 //! is used to handle incoming requests.
 
-use crate::ReputationChange;
+use crate::{
+	config::TrafficPriority,
+	rate_limit::{Direction, RateLimits},
+	ReputationChange,
+};
 use futures::{
 	channel::{mpsc, oneshot},
 	prelude::*,
@@ -183,9 +187,12 @@ struct RequestProcessingOutcome {
 impl RequestResponsesBehaviour {
 	/// Creates a new behaviour. Must be passed a list of supported protocols. Returns an error if
 	/// the same protocol is passed twice.
+	///
+	/// The traffic of all the protocols is subject to `rate_limits`.
 	pub fn new(
 		list: impl Iterator<Item = ProtocolConfig>,
 		peerset: PeersetHandle,
+		rate_limits: RateLimits,
 	) -> Result<Self, RegisterError> {
 		let mut protocols = HashMap::new();
 		for protocol in list {
@@ -201,8 +208,11 @@ impl RequestResponsesBehaviour {
 
 			let rq_rp = RequestResponse::new(
 				GenericCodec {
+					protocol: protocol.name.clone(),
 					max_request_size: protocol.max_request_size,
 					max_response_size: protocol.max_response_size,
+					priority: protocol.priority,
+					rate_limits: rate_limits.clone(),
 				},
 				iter::once(protocol.name.as_bytes().to_vec())
 					.chain(protocol.fallback_names.iter().map(|name| name.as_bytes().to_vec()))
@@ -783,8 +793,21 @@ pub enum ResponseFailure {
 #[derive(Debug, Clone)]
 #[doc(hidden)] // Needs to be public in order to satisfy the Rust compiler.
 pub struct GenericCodec {
+	/// Main name of the protocol, used to look up its bandwidth limits.
+	protocol: ProtocolName,
 	max_request_size: u64,
 	max_response_size: u64,
+	priority: TrafficPriority,
+	rate_limits: RateLimits,
+}
+
+impl GenericCodec {
+	/// Waits for the bandwidth limits to allow transferring `length` bytes in `direction`.
+	async fn throttle(&self, direction: Direction, length: usize) {
+		self.rate_limits
+			.take(&self.protocol, direction, self.priority, length as u64)
+			.await
+	}
 }
 
 #[async_trait::async_trait]
@@ -812,6 +835,8 @@ impl RequestResponseCodec for GenericCodec {
 			))
 		}
 
+		self.throttle(Direction::Inbound, length).await;
+
 		// Read the payload.
 		let mut buffer = vec![0; length];
 		io.read_exact(&mut buffer).await?;
@@ -847,6 +872,8 @@ impl RequestResponseCodec for GenericCodec {
 			))
 		}
 
+		self.throttle(Direction::Inbound, length).await;
+
 		// Read the payload.
 		let mut buffer = vec![0; length];
 		io.read_exact(&mut buffer).await?;
@@ -862,6 +889,8 @@ impl RequestResponseCodec for GenericCodec {
 	where
 		T: AsyncWrite + Unpin + Send,
 	{
+		self.throttle(Direction::Outbound, req.len()).await;
+
 		// TODO: check the length?
 		// Write the length.
 		{
@@ -887,6 +916,8 @@ impl RequestResponseCodec for GenericCodec {
 	{
 		// If `res` is an `Err`, we jump to closing the substream without writing anything on it.
 		if let Ok(res) = res {
+			self.throttle(Direction::Outbound, res.len()).await;
+
 			// TODO: check the length?
 			// Write the length.
 			{
@@ -951,7 +982,8 @@ mod tests {
 
 		let (peerset, handle) = Peerset::from_config(config);
 
-		let behaviour = RequestResponsesBehaviour::new(list, handle).unwrap();
+		let behaviour =
+			RequestResponsesBehaviour::new(list, handle, RateLimits::default()).unwrap();
 
 		let mut swarm = Swarm::new(transport, behaviour, keypair.public().to_peer_id());
 		let listen_addr: Multiaddr = format!("/memory/{}", rand::random::<u64>()).parse().unwrap();
@@ -999,6 +1031,7 @@ mod tests {
 					max_request_size: 1024,
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
+					priority: Default::default(),
 					inbound_queue: Some(tx),
 				};
 
@@ -1100,6 +1133,7 @@ mod tests {
 					max_request_size: 1024,
 					max_response_size: 8, // <-- important for the test
 					request_timeout: Duration::from_secs(30),
+					priority: Default::default(),
 					inbound_queue: Some(tx),
 				};
 
@@ -1197,6 +1231,7 @@ mod tests {
 					max_request_size: 1024,
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
+					priority: Default::default(),
 					inbound_queue: None,
 				},
 				ProtocolConfig {
@@ -1205,6 +1240,7 @@ mod tests {
 					max_request_size: 1024,
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
+					priority: Default::default(),
 					inbound_queue: None,
 				},
 			];
@@ -1223,6 +1259,7 @@ mod tests {
 					max_request_size: 1024,
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
+					priority: Default::default(),
 					inbound_queue: Some(tx_1),
 				},
 				ProtocolConfig {
@@ -1231,6 +1268,7 @@ mod tests {
 					max_request_size: 1024,
 					max_response_size: 1024 * 1024,
 					request_timeout: Duration::from_secs(30),
+					priority: Default::default(),
 					inbound_queue: Some(tx_2),
 				},
 			];
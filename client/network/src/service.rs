@@ -39,9 +39,11 @@ use crate::{
 		self, message::generic::Roles, NotificationsSink, NotifsHandlerError, PeerInfo, Protocol,
 		Ready,
 	},
+	rate_limit::RateLimits,
 	transactions, transport, ExHashT, ReputationChange,
 };
 
+use ban_list::BanList;
 use codec::Encode as _;
 use futures::{channel::oneshot, prelude::*};
 use libp2p::{
@@ -83,6 +85,7 @@ use std::{
 	collections::{HashMap, HashSet},
 	fs, iter,
 	marker::PhantomData,
+	net::IpAddr,
 	num::NonZeroUsize,
 	pin::Pin,
 	str,
@@ -91,10 +94,13 @@ use std::{
 		Arc,
 	},
 	task::Poll,
+	time::Duration,
 };
 
+pub use ban_list::{BanTarget, BAN_LIST_FILE};
 pub use behaviour::{InboundFailure, OutboundFailure, ResponseFailure};
 
+mod ban_list;
 mod metrics;
 mod out_events;
 #[cfg(test)]
@@ -209,6 +215,13 @@ where
 		if let Some(path) = &params.network_config.net_config_path {
 			fs::create_dir_all(path)?;
 		}
+		let ban_list = BanList::load(
+			params
+				.network_config
+				.net_config_path
+				.as_ref()
+				.map(|path| path.join(BAN_LIST_FILE)),
+		);
 
 		let transactions_handler_proto = transactions::TransactionsHandlerPrototype::new(
 			params.protocol_id.clone(),
@@ -232,6 +245,7 @@ where
 		);
 
 		let default_notif_handshake_message = Roles::from(&params.role).encode();
+		let rate_limits = RateLimits::new(&params.network_config.bandwidth);
 
 		let (protocol, peerset_handle, mut known_addresses) = Protocol::new(
 			From::from(&params.role),
@@ -247,6 +261,7 @@ where
 				.collect(),
 			params.metrics_registry.as_ref(),
 			params.chain_sync,
+			rate_limits.clone(),
 		)?;
 
 		// List of multiaddresses that we know in the network.
@@ -260,6 +275,13 @@ where
 
 		let boot_node_ids = Arc::new(boot_node_ids);
 
+		// Restore the bans of the previous run.
+		for (target, remaining) in ban_list.active() {
+			if let BanTarget::Peer(peer_id) = target {
+				peerset_handle.ban_peer(peer_id, remaining);
+			}
+		}
+
 		// Check for duplicate bootnodes.
 		params.network_config.boot_nodes.iter().try_for_each(|bootnode| {
 			if let Some(other) = params
@@ -301,6 +323,14 @@ where
 				config.use_kademlia_disjoint_query_paths(
 					params.network_config.kademlia_disjoint_query_paths,
 				);
+				if let (Some(path), Some(address_book)) =
+					(&params.network_config.net_config_path, &params.network_config.address_book)
+				{
+					config.with_address_book(
+						path.join(crate::address_book::ADDRESS_BOOK_FILE),
+						address_book.clone(),
+					);
+				}
 
 				match params.network_config.transport {
 					TransportConfig::MemoryOnly => {
@@ -362,6 +392,7 @@ where
 				transport::build_transport(
 					local_identity.clone(),
 					config_mem,
+					params.network_config.enable_quic,
 					params.network_config.yamux_window_size,
 					yamux_maximum_buffer_size,
 				)
@@ -380,6 +411,7 @@ where
 					params.light_client_request_protocol_config,
 					params.network_config.request_response_protocols,
 					peerset_handle.clone(),
+					rate_limits,
 				);
 
 				match result {
@@ -481,6 +513,8 @@ where
 			tx_handler_controller,
 			metrics,
 			boot_node_ids,
+			ban_list,
+			ip_banned_peers: HashMap::new(),
 		})
 	}
 
@@ -704,6 +738,84 @@ where
 	pub fn reserved_peers(&self) -> impl Iterator<Item = &PeerId> {
 		self.network_service.behaviour().user_protocol().reserved_peers()
 	}
+
+	/// Returns the reputation of the peers known to the peerset manager, along with the reasons
+	/// of their recent reputation changes.
+	pub fn peer_reputations(&mut self) -> Vec<sc_peerset::PeerReputation> {
+		self.network_service.behaviour_mut().user_protocol_mut().peer_reputations()
+	}
+
+	/// Bans a peer or IP address for `duration`, disconnecting the matching peers.
+	///
+	/// The ban is persisted in the network configuration directory and restored on restart.
+	pub fn ban(&mut self, target: BanTarget, duration: Duration) {
+		info!(target: "sub-libp2p", "Banning {} for {:?}", target, duration);
+		self.ban_list.insert(target, duration);
+
+		match target {
+			BanTarget::Peer(peer_id) => self.service.peerset.ban_peer(peer_id, duration),
+			BanTarget::Ip(ip) => {
+				let swarm = &mut self.network_service;
+				let peers = swarm
+					.behaviour()
+					.user_protocol()
+					.open_peers()
+					.filter(|peer_id| {
+						swarm
+							.behaviour()
+							.node(peer_id)
+							.and_then(|info| {
+								info.endpoint().map(ConnectedPoint::get_remote_address)
+							})
+							.and_then(ban_list::ip_of) ==
+							Some(ip)
+					})
+					.cloned()
+					.collect::<Vec<_>>();
+				for peer_id in peers {
+					self.ban_connection_from_ip(peer_id, ip, duration);
+				}
+			},
+		}
+	}
+
+	/// Lifts the ban of a peer or IP address. Returns `false` if it wasn't banned.
+	pub fn unban(&mut self, target: BanTarget) -> bool {
+		if !self.ban_list.remove(&target) {
+			return false
+		}
+
+		info!(target: "sub-libp2p", "Unbanning {}", target);
+		match target {
+			BanTarget::Peer(peer_id) => self.service.peerset.unban_peer(peer_id),
+			BanTarget::Ip(ip) =>
+				for peer_id in self.ip_banned_peers.remove(&ip).unwrap_or_default() {
+					// The peer may also have been banned on its own.
+					if self.ban_list.remaining(&BanTarget::Peer(peer_id)).is_none() {
+						self.service.peerset.unban_peer(peer_id);
+					}
+				},
+		}
+		true
+	}
+
+	/// Returns the active bans along with their remaining duration.
+	pub fn bans(&self) -> Vec<(BanTarget, Duration)> {
+		self.ban_list.active()
+	}
+
+	/// Disconnects a peer whose connection comes from a banned IP address.
+	///
+	/// The peer itself is banned for as long as the IP address, but only until the next restart.
+	fn ban_connection_from_ip(&mut self, peer_id: PeerId, ip: IpAddr, remaining: Duration) {
+		debug!(target: "sub-libp2p", "Disconnecting {} connected from a banned IP address", peer_id);
+		let ban_list = &self.ban_list;
+		self.ip_banned_peers
+			.retain(|ip, _| ban_list.remaining(&BanTarget::Ip(*ip)).is_some());
+		self.ip_banned_peers.entry(ip).or_default().insert(peer_id);
+		self.service.peerset.ban_peer(peer_id, remaining);
+		let _ = self.network_service.disconnect_peer_id(peer_id);
+	}
 }
 
 impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
@@ -727,6 +839,20 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		}
 	}
 
+	/// Bans a peer or IP address for `duration`, disconnecting the matching peers.
+	///
+	/// The ban is persisted in the network configuration directory and restored on restart.
+	pub fn ban(&self, target: BanTarget, duration: Duration) {
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::Ban(target, duration));
+	}
+
+	/// Lifts the ban of a peer or IP address.
+	///
+	/// Has no effect if it wasn't banned.
+	pub fn unban(&self, target: BanTarget) {
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::Unban(target));
+	}
+
 	/// Utility function to extract `PeerId` from each `Multiaddr` for peer set updates.
 	///
 	/// Returns an `Err` if one of the given addresses is invalid or contains an
@@ -1280,6 +1406,8 @@ enum ServiceToWorkerMsg<B: BlockT, H: ExHashT> {
 	},
 	DisconnectPeer(PeerId, ProtocolName),
 	NewBestBlockImported(B::Hash, NumberFor<B>),
+	Ban(BanTarget, Duration),
+	Unban(BanTarget),
 }
 
 /// Main network worker. Must be polled in order for the network to advance.
@@ -1317,6 +1445,10 @@ where
 	peers_notifications_sinks: Arc<Mutex<HashMap<(PeerId, ProtocolName), NotificationsSink>>>,
 	/// Controller for the handler of incoming and outgoing transactions.
 	tx_handler_controller: transactions::TransactionsHandlerController<H>,
+	/// Peers and IP addresses banned through [`NetworkService::ban`].
+	ban_list: BanList,
+	/// Peers banned in the peerset because they connected from a banned IP address.
+	ip_banned_peers: HashMap<IpAddr, HashSet<PeerId>>,
 }
 
 impl<B, H, Client> Future for NetworkWorker<B, H, Client>
@@ -1464,6 +1596,10 @@ where
 					.behaviour_mut()
 					.user_protocol_mut()
 					.new_best_block_imported(hash, number),
+				ServiceToWorkerMsg::Ban(target, duration) => this.ban(target, duration),
+				ServiceToWorkerMsg::Unban(target) => {
+					this.unban(target);
+				},
 			}
 		}
 
@@ -1716,6 +1852,12 @@ where
 						debug!(target: "sub-libp2p", "Libp2p => Connected({:?})", peer_id);
 					}
 
+					if let Some((ip, remaining)) = ban_list::ip_of(endpoint.get_remote_address())
+						.and_then(|ip| Some((ip, this.ban_list.remaining(&BanTarget::Ip(ip))?)))
+					{
+						this.ban_connection_from_ip(peer_id, ip, remaining);
+					}
+
 					if let Some(metrics) = this.metrics.as_ref() {
 						let direction = match endpoint {
 							ConnectedPoint::Dialer { .. } => "out",
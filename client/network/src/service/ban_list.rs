@@ -0,0 +1,271 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2017-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Peers and IP addresses banned through [`NetworkService::ban`](super::NetworkService::ban).
+//!
+//! The bans are stored as JSON in the network configuration directory, so that they survive a
+//! restart of the node.
+
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use log::warn;
+use sc_peerset::MAX_BAN_DURATION;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt, fs,
+	net::IpAddr,
+	path::{Path, PathBuf},
+	str::FromStr,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the file the bans are persisted to.
+pub const BAN_LIST_FILE: &str = "banned_peers.json";
+
+/// Peer or IP address banned from connecting to the local node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BanTarget {
+	/// All connections of a peer.
+	Peer(PeerId),
+	/// All connections from an IP address, whatever the peer.
+	Ip(IpAddr),
+}
+
+impl FromStr for BanTarget {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Ok(peer_id) = s.parse::<PeerId>() {
+			return Ok(Self::Peer(peer_id))
+		}
+
+		s.parse::<IpAddr>()
+			.map(Self::Ip)
+			.map_err(|_| format!("`{}` is neither a peer ID nor an IP address", s))
+	}
+}
+
+impl fmt::Display for BanTarget {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Peer(peer_id) => peer_id.fmt(f),
+			Self::Ip(ip) => ip.fmt(f),
+		}
+	}
+}
+
+/// Returns the IP address of `address`, if any.
+pub(super) fn ip_of(address: &Multiaddr) -> Option<IpAddr> {
+	address.iter().find_map(|protocol| match protocol {
+		Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+		Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+		_ => None,
+	})
+}
+
+/// Content of [`BAN_LIST_FILE`]: the expiry of each ban, in seconds since the UNIX epoch.
+#[derive(Default, Serialize, Deserialize)]
+struct StoredBans {
+	peers: BTreeMap<String, u64>,
+	ips: BTreeMap<IpAddr, u64>,
+}
+
+/// Active bans and when they expire.
+pub(super) struct BanList {
+	path: Option<PathBuf>,
+	bans: HashMap<BanTarget, SystemTime>,
+}
+
+impl BanList {
+	/// Loads the bans persisted at `path`, or starts with an empty list if there are none.
+	///
+	/// The list is kept in memory only if `path` is `None`.
+	pub fn load(path: Option<PathBuf>) -> Self {
+		let bans = match path.as_deref().map(read_bans) {
+			Some(Ok(bans)) => bans,
+			Some(Err(err)) => {
+				warn!(target: "sub-libp2p", "Failed to load the list of banned peers: {}", err);
+				HashMap::new()
+			},
+			None => HashMap::new(),
+		};
+
+		let mut list = Self { path, bans };
+		list.prune();
+		list
+	}
+
+	/// Bans `target` for `duration`, replacing any previous ban.
+	///
+	/// Bans longer than [`MAX_BAN_DURATION`] are shortened to it.
+	pub fn insert(&mut self, target: BanTarget, duration: Duration) {
+		self.bans.insert(target, SystemTime::now() + duration.min(MAX_BAN_DURATION));
+		self.persist();
+	}
+
+	/// Lifts the ban of `target`. Returns `false` if it wasn't banned.
+	pub fn remove(&mut self, target: &BanTarget) -> bool {
+		self.prune();
+		let removed = self.bans.remove(target).is_some();
+		if removed {
+			self.persist();
+		}
+		removed
+	}
+
+	/// Returns the remaining duration of the ban of `target`, if it is banned.
+	pub fn remaining(&self, target: &BanTarget) -> Option<Duration> {
+		self.bans
+			.get(target)
+			.and_then(|until| until.duration_since(SystemTime::now()).ok())
+			.filter(|remaining| !remaining.is_zero())
+	}
+
+	/// Returns the active bans along with their remaining duration.
+	pub fn active(&self) -> Vec<(BanTarget, Duration)> {
+		self.bans
+			.keys()
+			.filter_map(|target| self.remaining(target).map(|remaining| (*target, remaining)))
+			.collect()
+	}
+
+	/// Forgets the expired bans.
+	fn prune(&mut self) {
+		let now = SystemTime::now();
+		self.bans.retain(|_, until| *until > now);
+	}
+
+	fn persist(&self) {
+		let path = match self.path {
+			Some(ref path) => path,
+			None => return,
+		};
+
+		let mut stored = StoredBans::default();
+		for (target, until) in &self.bans {
+			let until = until.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+			match target {
+				BanTarget::Peer(peer_id) => stored.peers.insert(peer_id.to_base58(), until),
+				BanTarget::Ip(ip) => stored.ips.insert(*ip, until),
+			};
+		}
+
+		if let Err(err) = write_bans(path, &stored) {
+			warn!(target: "sub-libp2p", "Failed to persist the list of banned peers: {}", err);
+		}
+	}
+}
+
+fn read_bans(path: &Path) -> Result<HashMap<BanTarget, SystemTime>, String> {
+	let stored: StoredBans = match fs::read(path) {
+		Ok(data) => serde_json::from_slice(&data).map_err(|e| e.to_string())?,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+		Err(err) => return Err(err.to_string()),
+	};
+
+	// The file may have been edited by hand, don't trust it to hold a sensible expiry.
+	let max_until = SystemTime::now() + MAX_BAN_DURATION;
+	let until = |secs| {
+		UNIX_EPOCH
+			.checked_add(Duration::from_secs(secs))
+			.map_or(max_until, |until| until.min(max_until))
+	};
+	let mut bans = HashMap::new();
+	for (peer_id, secs) in stored.peers {
+		let peer_id = peer_id.parse::<PeerId>().map_err(|e| format!("{}: {}", peer_id, e))?;
+		bans.insert(BanTarget::Peer(peer_id), until(secs));
+	}
+	for (ip, secs) in stored.ips {
+		bans.insert(BanTarget::Ip(ip), until(secs));
+	}
+
+	Ok(bans)
+}
+
+fn write_bans(path: &Path, stored: &StoredBans) -> Result<(), String> {
+	let data = serde_json::to_vec_pretty(stored).map_err(|e| e.to_string())?;
+	let tmp_path = path.with_extension("json.tmp");
+	fs::write(&tmp_path, data).map_err(|e| e.to_string())?;
+	fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_ban_targets() {
+		let peer_id = PeerId::random();
+		assert_eq!(peer_id.to_base58().parse::<BanTarget>(), Ok(BanTarget::Peer(peer_id)));
+		assert_eq!(
+			"192.0.2.1".parse::<BanTarget>(),
+			Ok(BanTarget::Ip(IpAddr::from([192, 0, 2, 1])))
+		);
+		assert!("/ip4/192.0.2.1".parse::<BanTarget>().is_err());
+	}
+
+	#[test]
+	fn caps_ban_duration() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join(BAN_LIST_FILE);
+		let peer = BanTarget::Peer(PeerId::random());
+		let ip = IpAddr::from([192, 0, 2, 1]);
+
+		let mut list = BanList::load(Some(path.clone()));
+		list.insert(peer, Duration::MAX);
+		assert!(list.remaining(&peer).unwrap() <= MAX_BAN_DURATION);
+
+		let stored = StoredBans { peers: BTreeMap::new(), ips: [(ip, u64::MAX)].into() };
+		write_bans(&path, &stored).unwrap();
+		let list = BanList::load(Some(path));
+		assert!(list.remaining(&BanTarget::Ip(ip)).unwrap() <= MAX_BAN_DURATION);
+	}
+
+	#[test]
+	fn bans_survive_reload() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join(BAN_LIST_FILE);
+		let peer = BanTarget::Peer(PeerId::random());
+		let ip = BanTarget::Ip(IpAddr::from([192, 0, 2, 1]));
+		let unbanned = BanTarget::Peer(PeerId::random());
+
+		let mut list = BanList::load(Some(path.clone()));
+		list.insert(peer, Duration::from_secs(3600));
+		list.insert(ip, Duration::from_secs(60));
+		list.insert(unbanned, Duration::from_secs(60));
+		assert!(list.remove(&unbanned));
+		assert!(!list.remove(&unbanned));
+
+		let list = BanList::load(Some(path));
+		let mut active = list.active().into_iter().map(|(target, _)| target).collect::<Vec<_>>();
+		active.sort_by_key(|target| target.to_string());
+		let mut expected = vec![peer, ip];
+		expected.sort_by_key(|target| target.to_string());
+		assert_eq!(active, expected);
+		assert!(list.remaining(&peer).unwrap() > Duration::from_secs(3500));
+		assert_eq!(list.remaining(&unbanned), None);
+	}
+
+	#[test]
+	fn extracts_ip_of_multiaddr() {
+		let address: Multiaddr = "/ip6/::1/tcp/30333".parse().unwrap();
+		assert_eq!(ip_of(&address), Some(IpAddr::from(std::net::Ipv6Addr::LOCALHOST)));
+		let address: Multiaddr = "/dns/example.com/tcp/30333".parse().unwrap();
+		assert_eq!(ip_of(&address), None);
+	}
+}
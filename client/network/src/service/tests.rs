@@ -130,6 +130,7 @@ fn build_test_full_node(
 		Box::new(DefaultBlockAnnounceValidator),
 		network_config.max_parallel_downloads,
 		None,
+		Vec::new(),
 	)
 	.unwrap();
 	let worker = NetworkWorker::new(config::Params {
@@ -183,6 +184,7 @@ fn build_nodes_one_proto() -> (
 			fallback_names: Vec::new(),
 			max_notification_size: 1024 * 1024,
 			set_config: Default::default(),
+			priority: Default::default(),
 		}],
 		listen_addresses: vec![listen_addr.clone()],
 		transport: config::TransportConfig::MemoryOnly,
@@ -201,6 +203,7 @@ fn build_nodes_one_proto() -> (
 				}],
 				..Default::default()
 			},
+			priority: Default::default(),
 		}],
 		listen_addresses: vec![],
 		transport: config::TransportConfig::MemoryOnly,
@@ -373,6 +376,7 @@ fn lots_of_incoming_peers_works() {
 			fallback_names: Vec::new(),
 			max_notification_size: 1024 * 1024,
 			set_config: config::SetConfig { in_peers: u32::MAX, ..Default::default() },
+			priority: Default::default(),
 		}],
 		transport: config::TransportConfig::MemoryOnly,
 		..config::NetworkConfiguration::new_local()
@@ -398,6 +402,7 @@ fn lots_of_incoming_peers_works() {
 					}],
 					..Default::default()
 				},
+				priority: Default::default(),
 			}],
 			transport: config::TransportConfig::MemoryOnly,
 			..config::NetworkConfiguration::new_local()
@@ -509,6 +514,7 @@ fn fallback_name_working() {
 			fallback_names: vec![PROTOCOL_NAME.into()],
 			max_notification_size: 1024 * 1024,
 			set_config: Default::default(),
+			priority: Default::default(),
 		}],
 		listen_addresses: vec![listen_addr.clone()],
 		transport: config::TransportConfig::MemoryOnly,
@@ -527,6 +533,7 @@ fn fallback_name_working() {
 				}],
 				..Default::default()
 			},
+			priority: Default::default(),
 		}],
 		listen_addresses: vec![],
 		transport: config::TransportConfig::MemoryOnly,
@@ -565,6 +572,82 @@ fn fallback_name_working() {
 	});
 }
 
+#[test]
+fn notifications_over_quic() {
+	// Node 2 connects to node 1 over QUIC on the loopback interface, and node 1 sends it a
+	// notification.
+
+	let port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+	let listen_addr = config::build_multiaddr![Ip4([127, 0, 0, 1]), Udp(port), Quic];
+
+	let (node1, mut events_stream1) = build_test_full_node(config::NetworkConfiguration {
+		extra_sets: vec![config::NonDefaultSetConfig {
+			notifications_protocol: PROTOCOL_NAME.into(),
+			fallback_names: Vec::new(),
+			max_notification_size: 1024 * 1024,
+			set_config: Default::default(),
+			priority: Default::default(),
+		}],
+		listen_addresses: vec![listen_addr.clone()],
+		enable_quic: true,
+		..config::NetworkConfiguration::new_local()
+	});
+
+	let (node2, mut events_stream2) = build_test_full_node(config::NetworkConfiguration {
+		extra_sets: vec![config::NonDefaultSetConfig {
+			notifications_protocol: PROTOCOL_NAME.into(),
+			fallback_names: Vec::new(),
+			max_notification_size: 1024 * 1024,
+			set_config: config::SetConfig {
+				reserved_nodes: vec![MultiaddrWithPeerId {
+					multiaddr: listen_addr,
+					peer_id: node1.local_peer_id(),
+				}],
+				..Default::default()
+			},
+			priority: Default::default(),
+		}],
+		listen_addresses: vec![],
+		enable_quic: true,
+		..config::NetworkConfiguration::new_local()
+	});
+	let node1_id = node1.local_peer_id();
+	let node2_id = node2.local_peer_id();
+
+	let receiver = async_std::task::spawn(async move {
+		loop {
+			match events_stream2.next().await.unwrap() {
+				Event::NotificationStreamClosed { .. } => panic!(),
+				Event::NotificationsReceived { remote, messages } => {
+					assert_eq!(remote, node1_id);
+					assert_eq!(messages.len(), 1);
+					assert_eq!(messages[0].0, PROTOCOL_NAME.into());
+					assert_eq!(messages[0].1, &b"hello quic"[..]);
+					break
+				},
+				_ => {},
+			};
+		}
+	});
+
+	async_std::task::block_on(async move {
+		// Wait for the `NotificationStreamOpened`.
+		loop {
+			match events_stream1.next().await.unwrap() {
+				Event::NotificationStreamOpened { remote, .. } => {
+					assert_eq!(remote, node2_id);
+					break
+				},
+				_ => {},
+			};
+		}
+
+		node1.write_notification(node2_id, PROTOCOL_NAME.into(), b"hello quic".to_vec());
+
+		receiver.await;
+	});
+}
+
 #[test]
 #[should_panic(expected = "don't match the transport")]
 fn ensure_listen_addresses_consistent_with_transport_memory() {
@@ -168,6 +168,7 @@ impl TransactionsHandlerPrototype {
 				reserved_nodes: Vec::new(),
 				non_reserved_mode: config::NonReservedPeerMode::Deny,
 			},
+			priority: config::TrafficPriority::Normal,
 		}
 	}
 
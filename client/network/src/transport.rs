@@ -20,17 +20,20 @@ use libp2p::{
 	bandwidth,
 	core::{
 		self,
-		either::EitherTransport,
+		either::{EitherOutput, EitherTransport},
 		muxing::StreamMuxerBox,
 		transport::{Boxed, OptionalTransport},
 		upgrade,
 	},
 	dns, identity, mplex, noise, tcp, websocket, PeerId, Transport,
 };
+use log::warn;
 use std::{sync::Arc, time::Duration};
 
 pub use self::bandwidth::BandwidthSinks;
 
+mod quic;
+
 /// Builds the transport that serves as a common ground for all connections.
 ///
 /// If `memory_only` is true, then only communication within the same process are allowed. Only
@@ -44,11 +47,15 @@ pub use self::bandwidth::BandwidthSinks;
 /// high-level protocols combined, or to some generously high value if you are sure that a maximum
 /// size is enforced on all high-level protocols.
 ///
+/// If `enable_quic` is true and `memory_only` is false, QUIC addresses of the format
+/// `/ip4/.../udp/.../quic` are supported in addition to the TCP and WebSocket ones.
+///
 /// Returns a `BandwidthSinks` object that allows querying the average bandwidth produced by all
-/// the connections spawned with this transport.
+/// the connections spawned with this transport. QUIC connections are not accounted for.
 pub fn build_transport(
 	keypair: identity::Keypair,
 	memory_only: bool,
+	enable_quic: bool,
 	yamux_window_size: Option<u32>,
 	yamux_maximum_buffer_size: usize,
 ) -> (Boxed<(PeerId, StreamMuxerBox)>, Arc<BandwidthSinks>) {
@@ -119,5 +126,25 @@ pub fn build_transport(
 		.timeout(Duration::from_secs(20))
 		.boxed();
 
+	if !enable_quic || memory_only {
+		return (transport, bandwidth)
+	}
+
+	let quic = match quic::QuicTransport::new(&keypair) {
+		Some(quic) => quic,
+		None => {
+			warn!(target: "sub-libp2p", "QUIC requires an Ed25519 node key; QUIC is disabled");
+			return (transport, bandwidth)
+		},
+	};
+
+	let transport = quic
+		.or_transport(transport)
+		.map(|output, _| match output {
+			EitherOutput::First(output) => output,
+			EitherOutput::Second(output) => output,
+		})
+		.boxed();
+
 	(transport, bandwidth)
 }
@@ -0,0 +1,280 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! QUIC transport.
+//!
+//! `libp2p-quic` is built against a newer `libp2p-core` than the rest of the networking stack.
+//! This module wraps it so that it can be combined with the other transports: addresses, peer
+//! IDs and listener IDs are converted at the boundary, and the QUIC connection is exposed through
+//! the `StreamMuxer` trait of the `libp2p-core` used by this crate.
+//!
+//! QUIC connections are encrypted with TLS 1.3 and multiplexed natively, so they skip the Noise
+//! and Yamux upgrades applied to the TCP and WebSocket transports.
+//!
+//! Only the draft-29 version of QUIC (`/quic` addresses) is supported, as this is the only one
+//! that the `multiaddr` crate used by this crate knows about.
+
+use futures::{future::BoxFuture, prelude::*};
+use libp2p::{
+	core::{
+		muxing::{StreamMuxer, StreamMuxerBox, StreamMuxerEvent},
+		transport::{ListenerId, TransportError, TransportEvent},
+	},
+	identity, Multiaddr, PeerId, Transport,
+};
+use libp2p_quic_core::{
+	muxing::{StreamMuxerEvent as QuicStreamMuxerEvent, StreamMuxerExt as _},
+	Transport as _,
+};
+use log::debug;
+use parking_lot::Mutex;
+use std::{
+	collections::HashMap,
+	io,
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+type QuicListenerId = libp2p_quic_core::transport::ListenerId;
+type QuicMultiaddr = libp2p_quic_core::Multiaddr;
+type QuicTransportError = libp2p_quic_core::transport::TransportError<libp2p_quic::Error>;
+type Upgrade = BoxFuture<'static, Result<(PeerId, StreamMuxerBox), libp2p_quic::Error>>;
+type QuicTransportEvent =
+	libp2p_quic_core::transport::TransportEvent<libp2p_quic::Connecting, libp2p_quic::Error>;
+
+/// Transport listening on and dialing `/ip4/.../udp/.../quic` and `/ip6/.../udp/.../quic`
+/// addresses.
+pub struct QuicTransport {
+	inner: libp2p_quic::async_std::Transport,
+	/// Identifiers we handed out for the listeners of `inner`.
+	listeners: HashMap<QuicListenerId, ListenerId>,
+}
+
+impl QuicTransport {
+	/// Creates a new QUIC transport authenticating connections with `keypair`.
+	///
+	/// Returns `None` if `keypair` is not an Ed25519 keypair.
+	pub fn new(keypair: &identity::Keypair) -> Option<Self> {
+		let keypair = match keypair {
+			identity::Keypair::Ed25519(keypair) => {
+				let mut encoded = keypair.encode();
+				let keypair = libp2p_identity::ed25519::Keypair::try_from_bytes(&mut encoded)
+					.expect("the encoding of a valid keypair always decodes; qed");
+				libp2p_identity::Keypair::from(keypair)
+			},
+			_ => return None,
+		};
+
+		let mut config = libp2p_quic::Config::new(&keypair);
+		config.support_draft_29 = true;
+
+		Some(Self {
+			inner: libp2p_quic::async_std::Transport::new(config),
+			listeners: HashMap::new(),
+		})
+	}
+
+	/// Converts an event of the inner transport. Returns `None` for events that can't be
+	/// expressed with the types of this crate, which are then dropped.
+	fn convert_event(
+		&mut self,
+		event: QuicTransportEvent,
+	) -> Option<TransportEvent<Upgrade, libp2p_quic::Error>> {
+		let event = match event {
+			QuicTransportEvent::NewAddress { listener_id, listen_addr } =>
+				TransportEvent::NewAddress {
+					listener_id: *self.listeners.get(&listener_id)?,
+					listen_addr: convert_listen_addr(&listen_addr)?,
+				},
+			QuicTransportEvent::AddressExpired { listener_id, listen_addr } =>
+				TransportEvent::AddressExpired {
+					listener_id: *self.listeners.get(&listener_id)?,
+					listen_addr: convert_listen_addr(&listen_addr)?,
+				},
+			QuicTransportEvent::Incoming { listener_id, upgrade, local_addr, send_back_addr } =>
+				TransportEvent::Incoming {
+					listener_id: *self.listeners.get(&listener_id)?,
+					upgrade: upgrade
+						.and_then(|output| future::ready(from_quic_output(output)))
+						.boxed(),
+					local_addr: convert_listen_addr(&local_addr)?,
+					send_back_addr: convert_listen_addr(&send_back_addr)?,
+				},
+			QuicTransportEvent::ListenerClosed { listener_id, reason } =>
+				TransportEvent::ListenerClosed {
+					listener_id: self.listeners.remove(&listener_id)?,
+					reason,
+				},
+			QuicTransportEvent::ListenerError { listener_id, error } =>
+				TransportEvent::ListenerError {
+					listener_id: *self.listeners.get(&listener_id)?,
+					error,
+				},
+		};
+
+		Some(event)
+	}
+}
+
+impl Transport for QuicTransport {
+	type Output = (PeerId, StreamMuxerBox);
+	type Error = libp2p_quic::Error;
+	type ListenerUpgrade = Upgrade;
+	type Dial = Upgrade;
+
+	fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<Self::Error>> {
+		let quic_addr = to_quic_multiaddr(&addr)
+			.ok_or_else(|| TransportError::MultiaddrNotSupported(addr.clone()))?;
+		let quic_id = self.inner.listen_on(quic_addr).map_err(|e| from_quic_error(e, addr))?;
+		let id = ListenerId::new();
+		self.listeners.insert(quic_id, id);
+		Ok(id)
+	}
+
+	fn remove_listener(&mut self, id: ListenerId) -> bool {
+		match self.listeners.iter().find(|(_, listener)| **listener == id) {
+			Some((quic_id, _)) => self.inner.remove_listener(*quic_id),
+			None => false,
+		}
+	}
+
+	fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+		let quic_addr = to_quic_multiaddr(&addr)
+			.ok_or_else(|| TransportError::MultiaddrNotSupported(addr.clone()))?;
+		let dial = self.inner.dial(quic_addr).map_err(|e| from_quic_error(e, addr))?;
+		Ok(dial.and_then(|output| future::ready(from_quic_output(output))).boxed())
+	}
+
+	fn dial_as_listener(
+		&mut self,
+		addr: Multiaddr,
+	) -> Result<Self::Dial, TransportError<Self::Error>> {
+		let quic_addr = to_quic_multiaddr(&addr)
+			.ok_or_else(|| TransportError::MultiaddrNotSupported(addr.clone()))?;
+		let dial = self.inner.dial_as_listener(quic_addr).map_err(|e| from_quic_error(e, addr))?;
+		Ok(dial.and_then(|output| future::ready(from_quic_output(output))).boxed())
+	}
+
+	fn address_translation(&self, listen: &Multiaddr, observed: &Multiaddr) -> Option<Multiaddr> {
+		let translated = self
+			.inner
+			.address_translation(&to_quic_multiaddr(listen)?, &to_quic_multiaddr(observed)?)?;
+		from_quic_multiaddr(&translated)
+	}
+
+	fn poll(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+		loop {
+			let event = match Pin::new(&mut self.inner).poll(cx) {
+				Poll::Ready(event) => event,
+				Poll::Pending => return Poll::Pending,
+			};
+
+			if let Some(event) = self.convert_event(event) {
+				return Poll::Ready(event)
+			}
+		}
+	}
+}
+
+/// A QUIC connection, exposed through the `StreamMuxer` trait of this crate.
+struct QuicMuxer {
+	connection: Mutex<libp2p_quic::Connection>,
+}
+
+impl StreamMuxer for QuicMuxer {
+	type Substream = libp2p_quic::Substream;
+	type OutboundSubstream = ();
+	type Error = libp2p_quic::Error;
+
+	fn poll_event(
+		&self,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<StreamMuxerEvent<Self::Substream>, Self::Error>> {
+		let mut connection = self.connection.lock();
+
+		// Drives the connection forward, in addition to reporting address changes.
+		while let Poll::Ready(event) = connection.poll_unpin(cx) {
+			let QuicStreamMuxerEvent::AddressChange(address) = event?;
+			match from_quic_multiaddr(&address) {
+				Some(address) => return Poll::Ready(Ok(StreamMuxerEvent::AddressChange(address))),
+				None => debug!(
+					target: "sub-libp2p",
+					"Ignoring change of QUIC remote address to unsupported {}",
+					address,
+				),
+			}
+		}
+
+		connection.poll_inbound_unpin(cx).map_ok(StreamMuxerEvent::InboundSubstream)
+	}
+
+	fn open_outbound(&self) -> Self::OutboundSubstream {}
+
+	fn poll_outbound(
+		&self,
+		cx: &mut Context<'_>,
+		_: &mut Self::OutboundSubstream,
+	) -> Poll<Result<Self::Substream, Self::Error>> {
+		self.connection.lock().poll_outbound_unpin(cx)
+	}
+
+	fn destroy_outbound(&self, _: Self::OutboundSubstream) {}
+
+	fn poll_close(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.connection.lock().poll_close_unpin(cx)
+	}
+}
+
+fn to_quic_multiaddr(addr: &Multiaddr) -> Option<QuicMultiaddr> {
+	QuicMultiaddr::try_from(addr.to_vec()).ok()
+}
+
+fn from_quic_multiaddr(addr: &QuicMultiaddr) -> Option<Multiaddr> {
+	Multiaddr::try_from(addr.to_vec()).ok()
+}
+
+/// Converts an address reported by the inner transport, logging the ones we can't represent.
+fn convert_listen_addr(addr: &QuicMultiaddr) -> Option<Multiaddr> {
+	let converted = from_quic_multiaddr(addr);
+	if converted.is_none() {
+		debug!(target: "sub-libp2p", "Ignoring unsupported QUIC address {}", addr);
+	}
+	converted
+}
+
+fn from_quic_error(
+	error: QuicTransportError,
+	addr: Multiaddr,
+) -> TransportError<libp2p_quic::Error> {
+	match error {
+		QuicTransportError::MultiaddrNotSupported(_) => TransportError::MultiaddrNotSupported(addr),
+		QuicTransportError::Other(error) => TransportError::Other(error),
+	}
+}
+
+fn from_quic_output(
+	(peer_id, connection): (libp2p_identity::PeerId, libp2p_quic::Connection),
+) -> Result<(PeerId, StreamMuxerBox), libp2p_quic::Error> {
+	let peer_id = PeerId::from_bytes(&peer_id.to_bytes())
+		.map_err(|e| libp2p_quic::Error::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+	let muxer = QuicMuxer { connection: Mutex::new(connection) };
+	Ok((peer_id, StreamMuxerBox::new(muxer)))
+}
@@ -55,7 +55,10 @@ use sc_network_common::sync::{
 		BlockAnnounce, BlockAttributes, BlockData, BlockRequest, BlockResponse, Direction,
 		FromBlock,
 	},
-	warp::{EncodedProof, WarpProofRequest, WarpSyncPhase, WarpSyncProgress, WarpSyncProvider},
+	warp::{
+		Checkpoint, EncodedProof, WarpProofRequest, WarpSyncPhase, WarpSyncProgress,
+		WarpSyncProvider,
+	},
 	BadPeer, ChainSync as ChainSyncT, Metrics, OnBlockData, OnBlockJustification, OnStateData,
 	OpaqueBlockRequest, OpaqueBlockResponse, OpaqueStateRequest, OpaqueStateResponse, PeerInfo,
 	PollBlockAnnounceValidation, SyncMode, SyncState, SyncStatus,
@@ -153,6 +156,9 @@ mod rep {
 
 	/// Peer response data does not have requested bits.
 	pub const BAD_RESPONSE: Rep = Rep::new(-(1 << 12), "Incomplete response");
+
+	/// Reputation change when a peer sent us a block conflicting with one of our checkpoints.
+	pub const CHECKPOINT_MISMATCH: Rep = Rep::new(i32::MIN, "Checkpoint mismatch");
 }
 
 enum AllowedRequests {
@@ -252,6 +258,8 @@ pub struct ChainSync<B: BlockT, Client> {
 	import_existing: bool,
 	/// Gap download process.
 	gap_sync: Option<GapSync<B>>,
+	/// Trusted finalized blocks, sorted by number.
+	checkpoints: Vec<Checkpoint<B>>,
 }
 
 /// All the data we have about a Peer that we are trying to sync with
@@ -317,6 +325,8 @@ pub enum PeerSyncState<B: BlockT> {
 	DownloadingWarpProof,
 	/// Actively downloading block history after warp sync.
 	DownloadingGap(NumberFor<B>),
+	/// Downloading the header of the checkpoint to start fast sync from.
+	DownloadingCheckpoint(B::Hash),
 }
 
 impl<B: BlockT> PeerSyncState<B> {
@@ -445,6 +455,14 @@ where
 		best_hash: B::Hash,
 		best_number: NumberFor<B>,
 	) -> Result<Option<BlockRequest<B>>, BadPeer> {
+		if let Some(checkpoint) = self.conflicting_checkpoint(&best_hash, best_number) {
+			info!(
+				"💔 New peer with best block {} ({}) conflicting with checkpoint {}.",
+				best_hash, best_number, checkpoint.hash,
+			);
+			return Err(BadPeer(who, rep::CHECKPOINT_MISMATCH))
+		}
+
 		// There is nothing sync can get from the node that has no blockchain data.
 		match self.block_status(&best_hash) {
 			Err(e) => {
@@ -529,9 +547,13 @@ where
 					if self.peers.len() >= MIN_PEERS_TO_START_WARP_SYNC && self.warp_sync.is_none()
 					{
 						log::debug!(target: "sync", "Starting warp state sync.");
+						let checkpoint = self.sync_checkpoint().cloned();
 						if let Some(provider) = &self.warp_sync_provider {
-							self.warp_sync =
-								Some(WarpSync::new(self.client.clone(), provider.clone()));
+							self.warp_sync = Some(WarpSync::new(
+								self.client.clone(),
+								provider.clone(),
+								checkpoint,
+							));
 						}
 					}
 				}
@@ -568,6 +590,18 @@ where
 	}
 
 	fn request_justification(&mut self, hash: &B::Hash, number: NumberFor<B>) {
+		// Never help finalizing a block conflicting with a checkpoint.
+		if let Some(checkpoint) = self.conflicting_checkpoint(hash, number) {
+			warn!(
+				target: "sync",
+				"💔 Not requesting the justification of block #{} ({}) conflicting with checkpoint {}",
+				number,
+				hash,
+				checkpoint.hash,
+			);
+			return
+		}
+
 		let client = &self.client;
 		self.extra_justifications
 			.schedule((*hash, number), |base, block| is_descendent_of(&**client, base, block))
@@ -670,6 +704,38 @@ where
 			trace!(target: "sync", "Too many blocks in the queue.");
 			return Box::new(std::iter::empty())
 		}
+
+		// Fast sync starts with the state of the newest checkpoint, once we have its header.
+		let checkpoint = match self.mode {
+			SyncMode::LightState { .. } => self.sync_checkpoint().map(|c| (c.hash, c.number)),
+			_ => None,
+		};
+		if let Some((hash, number)) = checkpoint.filter(|(_, n)| *n > self.best_queued_number) {
+			if self
+				.peers
+				.values()
+				.any(|peer| peer.state == PeerSyncState::DownloadingCheckpoint(hash))
+			{
+				return Box::new(std::iter::empty())
+			}
+			let request = self
+				.peers
+				.iter_mut()
+				.find(|(_, peer)| peer.state.is_available() && peer.best_number >= number)
+				.map(|(id, peer)| {
+					trace!(
+						target: "sync",
+						"Downloading checkpoint #{} ({}) from {}",
+						number,
+						hash,
+						id,
+					);
+					peer.state = PeerSyncState::DownloadingCheckpoint(hash);
+					(id, checkpoint_request::<B>(hash))
+				});
+			return Box::new(request.into_iter())
+		}
+
 		let major_sync = self.status().state == SyncState::Downloading;
 		let attrs = self.required_block_attributes();
 		let blocks = &mut self.blocks;
@@ -783,8 +849,15 @@ where
 				return None
 			}
 
+			// We don't have the ancestors of a checkpoint, so any peer that has it will do.
+			let from_checkpoint = self.checkpoints.iter().any(|c| c.hash == sync.target());
 			for (id, peer) in self.peers.iter_mut() {
-				if peer.state.is_available() && peer.common_number >= sync.target_block_num() {
+				let has_target = if from_checkpoint {
+					peer.best_number >= sync.target_block_num()
+				} else {
+					peer.common_number >= sync.target_block_num()
+				};
+				if peer.state.is_available() && has_target {
 					peer.state = PeerSyncState::DownloadingState;
 					let request = sync.next_request();
 					trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
@@ -851,6 +924,7 @@ where
 		response: BlockResponse<B>,
 	) -> Result<OnBlockData<B>, BadPeer> {
 		self.downloaded_blocks += response.blocks.len();
+		self.check_checkpoints(&response.blocks, who)?;
 		let mut gap = false;
 		let new_blocks: Vec<IncomingBlock<B>> = if let Some(peer) = self.peers.get_mut(who) {
 			let mut blocks = response.blocks;
@@ -1031,6 +1105,28 @@ where
 							Vec::new()
 						}
 					},
+					PeerSyncState::DownloadingCheckpoint(hash) => {
+						let hash = *hash;
+						peer.state = PeerSyncState::Available;
+						validate_blocks::<B>(&blocks, who, Some(request))?;
+						let header = blocks
+							.into_iter()
+							.next()
+							.and_then(|b| b.header)
+							.ok_or(BadPeer(*who, rep::NO_BLOCK))?;
+						if let SyncMode::LightState { skip_proofs, .. } = &self.mode {
+							debug!(
+								target: "sync",
+								"Starting state sync from checkpoint #{} ({})",
+								header.number(),
+								hash,
+							);
+							self.state_sync =
+								Some(StateSync::new(self.client.clone(), header, *skip_proofs));
+							self.allowed_requests.set_all();
+						}
+						Vec::new()
+					},
 					PeerSyncState::Available |
 					PeerSyncState::DownloadingJustification(..) |
 					PeerSyncState::DownloadingState |
@@ -1449,6 +1545,29 @@ where
 			return
 		}
 
+		// The parent is checked as well, since the announced block itself is usually not known.
+		let conflict = self
+			.conflicting_checkpoint(&hash, number)
+			.or_else(|| self.conflicting_checkpoint(header.parent_hash(), number - One::one()));
+		if let Some(checkpoint) = conflict {
+			let checkpoint_hash = checkpoint.hash;
+			self.block_announce_validation.push(
+				async move {
+					warn!(
+						target: "sync",
+						"💔 Block (#{} -- {}) announced by {} conflicts with checkpoint {}",
+						number,
+						hash,
+						who,
+						checkpoint_hash,
+					);
+					PreValidateBlockAnnounce::Failure { who, disconnect: true }
+				}
+				.boxed(),
+			);
+			return
+		}
+
 		// Check if there is a slot for this block announce validation.
 		match self.has_slot_for_block_announce_validation(&who) {
 			HasSlotForBlockAnnounceValidation::Yes => {},
@@ -1692,7 +1811,9 @@ where
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		max_parallel_downloads: u32,
 		warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
+		mut checkpoints: Vec<Checkpoint<B>>,
 	) -> Result<Self, ClientError> {
+		checkpoints.sort_by_key(|checkpoint| checkpoint.number);
 		let mut sync = Self {
 			client,
 			peers: HashMap::new(),
@@ -1714,11 +1835,79 @@ where
 			warp_sync_provider,
 			import_existing: false,
 			gap_sync: None,
+			checkpoints,
 		};
 		sync.reset_sync_start_point()?;
 		Ok(sync)
 	}
 
+	/// The newest checkpoint, if we didn't finalize it yet.
+	fn sync_checkpoint(&self) -> Option<&Checkpoint<B>> {
+		self.checkpoints
+			.last()
+			.filter(|checkpoint| checkpoint.number > self.client.info().finalized_number)
+	}
+
+	/// Returns the checkpoint the block `hash` at `number` conflicts with, if any.
+	///
+	/// A block at the height of a checkpoint conflicts with it if it has another hash. A block
+	/// above a checkpoint conflicts with it if the block is known to us but doesn't descend from
+	/// the checkpoint. Whether a block we don't know descends from a checkpoint can't be decided
+	/// yet, so it's checked once its ancestors are downloaded.
+	fn conflicting_checkpoint(
+		&self,
+		hash: &B::Hash,
+		number: NumberFor<B>,
+	) -> Option<&Checkpoint<B>> {
+		self.checkpoints.iter().find(|checkpoint| {
+			if checkpoint.number == number {
+				return checkpoint.hash != *hash
+			}
+			if checkpoint.number > number || self.client.header_metadata(*hash).is_err() {
+				return false
+			}
+			// Fast path for the canonical chain, where the ancestor is found by number.
+			if self.client.hash(number).ok().flatten().as_ref() == Some(hash) {
+				return self.client.hash(checkpoint.number).ok().flatten() != Some(checkpoint.hash)
+			}
+			match sp_blockchain::lowest_common_ancestor(&*self.client, *hash, checkpoint.hash) {
+				Ok(ancestor) => ancestor.hash != checkpoint.hash,
+				// The checkpoint isn't known, so none of our blocks can descend from it.
+				Err(_) => true,
+			}
+		})
+	}
+
+	/// Check that none of the given `blocks` conflicts with one of the checkpoints.
+	///
+	/// The parents of the blocks are checked as well, so that a chain we already have can't be
+	/// extended by a conflicting fork.
+	fn check_checkpoints(&self, blocks: &[BlockData<B>], who: &PeerId) -> Result<(), BadPeer> {
+		if self.checkpoints.is_empty() {
+			return Ok(())
+		}
+		let hashes = blocks.iter().map(|b| b.hash).collect::<HashSet<_>>();
+		for header in blocks.iter().filter_map(|b| b.header.as_ref()) {
+			let parent = (!header.number().is_zero() && !hashes.contains(header.parent_hash()))
+				.then(|| (*header.parent_hash(), *header.number() - One::one()));
+			let conflict = std::iter::once((header.hash(), *header.number()))
+				.chain(parent)
+				.find_map(|(hash, number)| self.conflicting_checkpoint(&hash, number));
+			if let Some(checkpoint) = conflict {
+				debug!(
+					target: "sync",
+					"Block #{} ({}) received from {} conflicts with checkpoint {}",
+					header.number(),
+					header.hash(),
+					who,
+					checkpoint.hash,
+				);
+				return Err(BadPeer(*who, rep::CHECKPOINT_MISMATCH))
+			}
+		}
+		Ok(())
+	}
+
 	/// Returns the best seen block number if we don't have that block yet, `None` otherwise.
 	fn best_seen(&self) -> Option<NumberFor<B>> {
 		let mut best_seens = self.peers.values().map(|p| p.best_number).collect::<Vec<_>>();
@@ -2188,6 +2377,18 @@ fn ancestry_request<B: BlockT>(block: NumberFor<B>) -> BlockRequest<B> {
 	}
 }
 
+/// Request the header of the checkpoint `hash`.
+fn checkpoint_request<B: BlockT>(hash: B::Hash) -> BlockRequest<B> {
+	BlockRequest::<B> {
+		id: 0,
+		fields: BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION,
+		from: FromBlock::Hash(hash),
+		to: None,
+		direction: Direction::Ascending,
+		max: Some(1),
+	}
+}
+
 /// The ancestor search state expresses which algorithm, and its stateful parameters, we are using
 /// to try to find an ancestor block
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -2540,9 +2741,15 @@ mod test {
 		let block_announce_validator = Box::new(DefaultBlockAnnounceValidator);
 		let peer_id = PeerId::random();
 
-		let mut sync =
-			ChainSync::new(SyncMode::Full, client.clone(), block_announce_validator, 1, None)
-				.unwrap();
+		let mut sync = ChainSync::new(
+			SyncMode::Full,
+			client.clone(),
+			block_announce_validator,
+			1,
+			None,
+			Vec::new(),
+		)
+		.unwrap();
 
 		let (a1_hash, a1_number) = {
 			let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
@@ -2594,6 +2801,7 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			1,
 			None,
+			Vec::new(),
 		)
 		.unwrap();
 
@@ -2762,6 +2970,7 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			Vec::new(),
 		)
 		.unwrap();
 
@@ -2877,6 +3086,7 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			Vec::new(),
 		)
 		.unwrap();
 
@@ -3020,6 +3230,7 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			Vec::new(),
 		)
 		.unwrap();
 
@@ -3151,6 +3362,7 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			5,
 			None,
+			Vec::new(),
 		)
 		.unwrap();
 
@@ -3282,6 +3494,7 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			1,
 			None,
+			Vec::new(),
 		)
 		.unwrap();
 
@@ -3315,6 +3528,7 @@ mod test {
 			Box::new(DefaultBlockAnnounceValidator),
 			1,
 			None,
+			Vec::new(),
 		)
 		.unwrap();
 
@@ -3340,6 +3554,184 @@ mod test {
 		sync.on_block_data(&peer_id1, Some(request), response).unwrap();
 		assert_eq!(sync.best_queued_number, 4);
 	}
+
+	#[test]
+	fn rejects_peers_and_blocks_conflicting_with_checkpoints() {
+		sp_tracing::try_init_simple();
+
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let blocks = (0..2).map(|_| build_block(&mut client, None, false)).collect::<Vec<_>>();
+		let mut fork_client = Arc::new(TestClientBuilder::new().build());
+		let fork_blocks =
+			(0..2).map(|_| build_block(&mut fork_client, None, true)).collect::<Vec<_>>();
+
+		let checkpoint =
+			Checkpoint { number: 2, hash: blocks[1].hash(), set_id: 0, authorities: Vec::new() };
+		let mut sync = ChainSync::new(
+			SyncMode::Full,
+			Arc::new(TestClientBuilder::new().build()),
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			None,
+			vec![checkpoint],
+		)
+		.unwrap();
+
+		// A peer with another best block at the height of the checkpoint is rejected.
+		let fork_peer = PeerId::random();
+		assert!(matches!(
+			sync.new_peer(fork_peer, fork_blocks[1].hash(), 2),
+			Err(BadPeer(peer, reputation))
+				if peer == fork_peer && reputation == rep::CHECKPOINT_MISMATCH,
+		));
+		assert!(!sync.peers.contains_key(&fork_peer));
+
+		// A peer following the checkpoint is accepted, but not when it sends conflicting blocks.
+		let peer_id = PeerId::random();
+		sync.new_peer(peer_id, blocks[1].hash(), 2).unwrap();
+		sync.peers.get_mut(&peer_id).unwrap().state = PeerSyncState::Available;
+
+		let request = get_block_request(&mut sync, FromBlock::Hash(blocks[1].hash()), 2, &peer_id);
+		let response = create_block_response(vec![fork_blocks[1].clone(), fork_blocks[0].clone()]);
+		assert!(matches!(
+			sync.on_block_data(&peer_id, Some(request), response),
+			Err(BadPeer(peer, reputation))
+				if peer == peer_id && reputation == rep::CHECKPOINT_MISMATCH,
+		));
+	}
+
+	#[test]
+	fn rejects_blocks_above_checkpoints_not_descending_from_them() {
+		sp_tracing::try_init_simple();
+
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let checkpoint_block = build_block(&mut client, None, false);
+		let mut fork_client = Arc::new(TestClientBuilder::new().build());
+		let fork_blocks =
+			(0..3).map(|_| build_block(&mut fork_client, None, true)).collect::<Vec<_>>();
+
+		// We already have the first blocks of a fork not containing the checkpoint.
+		let client = Arc::new(TestClientBuilder::new().build());
+		for block in &fork_blocks[..2] {
+			block_on(client.import(BlockOrigin::Own, block.clone())).unwrap();
+		}
+
+		let checkpoint = Checkpoint {
+			number: 1,
+			hash: checkpoint_block.hash(),
+			set_id: 0,
+			authorities: Vec::new(),
+		};
+		let mut sync = ChainSync::new(
+			SyncMode::Full,
+			client,
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			None,
+			vec![checkpoint],
+		)
+		.unwrap();
+
+		// A peer with a known best block above the checkpoint, on the fork, is rejected.
+		let fork_peer = PeerId::random();
+		assert!(matches!(
+			sync.new_peer(fork_peer, fork_blocks[1].hash(), 2),
+			Err(BadPeer(peer, reputation))
+				if peer == fork_peer && reputation == rep::CHECKPOINT_MISMATCH,
+		));
+
+		// So is the announcement of an unknown block extending the fork.
+		let peer_id = PeerId::random();
+		sync.new_peer(peer_id, checkpoint_block.hash(), 1).unwrap();
+		let header = fork_blocks[2].header().clone();
+		let announce = BlockAnnounce { header: header.clone(), state: None, data: None };
+		sync.push_block_announce_validation(peer_id, header.hash(), announce, true);
+		let result = block_on(poll_fn(|cx| sync.poll_block_announce_validation(cx)));
+		assert!(matches!(
+			result,
+			PollBlockAnnounceValidation::Failure { who, disconnect: true } if who == peer_id,
+		));
+
+		// And no justification is requested to finalize the fork.
+		sync.request_justification(&fork_blocks[1].hash(), 2);
+		assert_eq!(sync.extra_justifications.pending_requests().count(), 0);
+	}
+
+	#[test]
+	fn sync_starts_from_the_newest_checkpoint() {
+		use sc_network_common::sync::warp::VerificationResult;
+		use sp_finality_grandpa::{AuthorityList, SetId};
+
+		sp_tracing::try_init_simple();
+
+		struct TestWarpSyncProvider;
+
+		impl WarpSyncProvider<Block> for TestWarpSyncProvider {
+			fn generate(
+				&self,
+				_start: Hash,
+			) -> Result<EncodedProof, Box<dyn std::error::Error + Send + Sync>> {
+				unimplemented!()
+			}
+
+			fn verify(
+				&self,
+				_proof: &EncodedProof,
+				_set_id: SetId,
+				_authorities: AuthorityList,
+			) -> Result<VerificationResult<Block>, Box<dyn std::error::Error + Send + Sync>> {
+				unimplemented!()
+			}
+
+			fn current_authorities(&self) -> AuthorityList {
+				Vec::new()
+			}
+		}
+
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let blocks = (0..5).map(|_| build_block(&mut client, None, false)).collect::<Vec<_>>();
+		let checkpoints = vec![
+			Checkpoint { number: 4, hash: blocks[3].hash(), set_id: 1, authorities: Vec::new() },
+			Checkpoint { number: 2, hash: blocks[1].hash(), set_id: 0, authorities: Vec::new() },
+		];
+
+		// Fast sync downloads the header of the newest checkpoint and syncs its state.
+		let mut sync = ChainSync::new(
+			SyncMode::LightState { storage_chain_mode: false, skip_proofs: false },
+			Arc::new(TestClientBuilder::new().build()),
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			None,
+			checkpoints.clone(),
+		)
+		.unwrap();
+
+		let peer_id = PeerId::random();
+		sync.new_peer(peer_id, blocks[4].hash(), 5).unwrap();
+		let request = get_block_request(&mut sync, FromBlock::Hash(blocks[3].hash()), 1, &peer_id);
+		let response = create_block_response(vec![blocks[3].clone()]);
+		sync.on_block_data(&peer_id, Some(request), response).unwrap();
+		assert_eq!(sync.state_sync.as_ref().map(|s| s.target()), Some(blocks[3].hash()));
+		assert_eq!(sync.state_request().map(|(peer, _)| peer), Some(peer_id));
+
+		// Warp sync requests the proofs following the newest checkpoint.
+		let mut sync = ChainSync::new(
+			SyncMode::Warp,
+			Arc::new(TestClientBuilder::new().build()),
+			Box::new(DefaultBlockAnnounceValidator),
+			1,
+			Some(Arc::new(TestWarpSyncProvider)),
+			checkpoints,
+		)
+		.unwrap();
+
+		for _ in 0..MIN_PEERS_TO_START_WARP_SYNC {
+			sync.new_peer(PeerId::random(), blocks[4].hash(), 5).unwrap();
+		}
+		let (_, request) = sync.warp_sync_request().unwrap();
+		assert_eq!(request.begin, blocks[3].hash());
+	}
+
 	#[test]
 	fn ancestor_search_repeat() {
 		let state = AncestorSearchState::<Block>::BinarySearch(1, 3);
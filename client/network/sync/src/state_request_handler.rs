@@ -29,7 +29,7 @@ use lru::LruCache;
 use prost::Message;
 use sc_client_api::{BlockBackend, ProofProvider};
 use sc_network_common::{
-	config::ProtocolId,
+	config::{ProtocolId, TrafficPriority},
 	request_responses::{IncomingRequest, OutgoingResponse, ProtocolConfig},
 };
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
@@ -63,6 +63,7 @@ pub fn generate_protocol_config<Hash: AsRef<[u8]>>(
 		max_request_size: 1024 * 1024,
 		max_response_size: 16 * 1024 * 1024,
 		request_timeout: Duration::from_secs(40),
+		priority: TrafficPriority::Normal,
 		inbound_queue: None,
 	}
 }
@@ -24,8 +24,8 @@ use crate::{
 };
 use sc_client_api::ProofProvider;
 use sc_network_common::sync::warp::{
-	EncodedProof, VerificationResult, WarpProofRequest, WarpSyncPhase, WarpSyncProgress,
-	WarpSyncProvider,
+	Checkpoint, EncodedProof, VerificationResult, WarpProofRequest, WarpSyncPhase,
+	WarpSyncProgress, WarpSyncProvider,
 };
 use sp_blockchain::HeaderBackend;
 use sp_finality_grandpa::{AuthorityList, SetId};
@@ -59,12 +59,25 @@ where
 	Client: HeaderBackend<B> + ProofProvider<B> + 'static,
 {
 	///  Create a new instance.
-	pub fn new(client: Arc<Client>, warp_sync_provider: Arc<dyn WarpSyncProvider<B>>) -> Self {
-		let last_hash = client.hash(Zero::zero()).unwrap().expect("Genesis header always exists");
-		let phase = Phase::WarpProof {
-			set_id: 0,
-			authorities: warp_sync_provider.current_authorities(),
-			last_hash,
+	///
+	/// The warp proof is requested from `checkpoint` if any, or from genesis otherwise.
+	pub fn new(
+		client: Arc<Client>,
+		warp_sync_provider: Arc<dyn WarpSyncProvider<B>>,
+		checkpoint: Option<Checkpoint<B>>,
+	) -> Self {
+		let phase = match checkpoint {
+			Some(Checkpoint { hash, set_id, authorities, .. }) =>
+				Phase::WarpProof { set_id, authorities, last_hash: hash },
+			None => {
+				let last_hash =
+					client.hash(Zero::zero()).unwrap().expect("Genesis header always exists");
+				Phase::WarpProof {
+					set_id: 0,
+					authorities: warp_sync_provider.current_authorities(),
+					last_hash,
+				}
+			},
 		};
 		Self { client, warp_sync_provider, phase, total_proof_bytes: 0 }
 	}
@@ -23,7 +23,7 @@ use futures::{
 };
 use log::debug;
 use sc_network_common::{
-	config::ProtocolId,
+	config::{ProtocolId, TrafficPriority},
 	request_responses::{
 		IncomingRequest, OutgoingResponse, ProtocolConfig as RequestResponseConfig,
 	},
@@ -48,6 +48,7 @@ pub fn generate_request_response_config<Hash: AsRef<[u8]>>(
 		max_request_size: 32,
 		max_response_size: MAX_RESPONSE_SIZE,
 		request_timeout: Duration::from_secs(10),
+		priority: TrafficPriority::Normal,
 		inbound_queue: None,
 	}
 }
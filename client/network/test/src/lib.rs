@@ -793,6 +793,7 @@ where
 				fallback_names: Vec::new(),
 				max_notification_size: 1024 * 1024,
 				set_config: Default::default(),
+				priority: Default::default(),
 			})
 			.collect();
 		if let Some(connect_to) = config.connect_to_peers {
@@ -867,6 +868,7 @@ where
 			block_announce_validator,
 			network_config.max_parallel_downloads,
 			Some(warp_sync),
+			Vec::new(),
 		)
 		.unwrap();
 		let network = NetworkWorker::new(sc_network::config::Params {
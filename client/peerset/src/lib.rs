@@ -55,6 +55,12 @@ const DISCONNECT_REPUTATION_CHANGE: i32 = -256;
 /// Amount of time between the moment we disconnect from a node and the moment we remove it from
 /// the list.
 const FORGET_AFTER: Duration = Duration::from_secs(3600);
+/// Number of reputation changes kept per node for [`Peerset::peer_reputations`].
+const MAX_RECENT_REPORTS: usize = 8;
+/// Reason recorded for nodes banned with [`PeersetHandle::ban_peer`].
+const MANUAL_BAN_REASON: &str = "Banned manually";
+/// Longest ban accepted by [`PeersetHandle::ban_peer`]; longer bans are shortened to it.
+pub const MAX_BAN_DURATION: Duration = Duration::from_secs(100 * 365 * 24 * 3600);
 
 #[derive(Debug)]
 enum Action {
@@ -66,6 +72,8 @@ enum Action {
 	AddToPeersSet(SetId, PeerId),
 	RemoveFromPeersSet(SetId, PeerId),
 	PeerReputation(PeerId, oneshot::Sender<i32>),
+	BanPeer(PeerId, Duration),
+	UnbanPeer(PeerId),
 }
 
 /// Identifier of a set in the peerset.
@@ -176,6 +184,35 @@ impl PeersetHandle {
 		// The channel can only be closed if the peerset no longer exists.
 		rx.await.map_err(|_| ())
 	}
+
+	/// Bans the given peer for `duration`, disconnecting it from all sets.
+	///
+	/// The reputation of the peer is kept under [`BANNED_THRESHOLD`] until the ban expires or
+	/// is lifted with [`PeersetHandle::unban_peer`], regardless of the reputation changes
+	/// reported in the meantime. Bans longer than [`MAX_BAN_DURATION`] are shortened to it.
+	pub fn ban_peer(&self, peer_id: PeerId, duration: Duration) {
+		let _ = self.tx.unbounded_send(Action::BanPeer(peer_id, duration));
+	}
+
+	/// Lifts a ban set with [`PeersetHandle::ban_peer`] and resets the reputation of the peer.
+	///
+	/// Has no effect if the peer isn't banned.
+	pub fn unban_peer(&self, peer_id: PeerId) {
+		let _ = self.tx.unbounded_send(Action::UnbanPeer(peer_id));
+	}
+}
+
+/// Reputation of a node, as returned by [`Peerset::peer_reputations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerReputation {
+	/// The node.
+	pub peer_id: PeerId,
+	/// Current reputation value.
+	pub reputation: i32,
+	/// Remaining duration of a ban set with [`PeersetHandle::ban_peer`].
+	pub banned_for: Option<Duration>,
+	/// Most recent reputation changes reported for the node, oldest first.
+	pub recent_reports: Vec<ReputationChange>,
 }
 
 /// Message that can be sent by the peer set manager (PSM).
@@ -270,6 +307,10 @@ pub struct Peerset {
 	/// Next time to do a periodic call to `alloc_slots` with all sets. This is done once per
 	/// second, to match the period of the reputation updates.
 	next_periodic_alloc_slots: Delay,
+	/// Nodes banned with [`PeersetHandle::ban_peer`] and when their ban expires.
+	banned: HashMap<PeerId, Instant>,
+	/// Most recent reputation changes of each node known to [`Peerset::data`].
+	recent_reports: HashMap<PeerId, VecDeque<ReputationChange>>,
 }
 
 impl Peerset {
@@ -297,6 +338,8 @@ impl Peerset {
 				created: now,
 				latest_time_update: now,
 				next_periodic_alloc_slots: Delay::new(Duration::new(0, 0)),
+				banned: HashMap::new(),
+				recent_reports: HashMap::new(),
 			}
 		};
 
@@ -436,8 +479,18 @@ impl Peerset {
 		// We want reputations to be up-to-date before adjusting them.
 		self.update_time();
 
+		let reports = self.recent_reports.entry(peer_id).or_default();
+		if reports.len() == MAX_RECENT_REPORTS {
+			reports.pop_front();
+		}
+		reports.push_back(change);
+
 		let mut reputation = self.data.peer_reputation(peer_id);
-		reputation.add_reputation(change.value);
+		if self.banned.contains_key(&peer_id) {
+			reputation.set_reputation(i32::MIN);
+		} else {
+			reputation.add_reputation(change.value);
+		}
 		if reputation.reputation() >= BANNED_THRESHOLD {
 			trace!(target: "peerset", "Report {}: {:+} to {}. Reason: {}",
 				peer_id, change.value, reputation.reputation(), change.reason
@@ -469,6 +522,22 @@ impl Peerset {
 		let _ = pending_response.send(reputation.reputation());
 	}
 
+	fn on_ban_peer(&mut self, peer_id: PeerId, duration: Duration) {
+		self.banned.insert(peer_id, Instant::now() + duration.min(MAX_BAN_DURATION));
+		self.on_report_peer(peer_id, ReputationChange::new_fatal(MANUAL_BAN_REASON));
+	}
+
+	fn on_unban_peer(&mut self, peer_id: PeerId) {
+		if self.banned.remove(&peer_id).is_none() {
+			return
+		}
+
+		self.data.peer_reputation(peer_id).set_reputation(0);
+		for set_index in 0..self.data.num_sets() {
+			self.alloc_slots(SetId(set_index));
+		}
+	}
+
 	/// Updates the value of `self.latest_time_update` and performs all the updates that happen
 	/// over time, such as reputation increases for staying connected.
 	fn update_time(&mut self) {
@@ -483,6 +552,19 @@ impl Peerset {
 			elapsed_now.as_secs() - elapsed_latest.as_secs()
 		};
 
+		// Lift the bans that have expired, giving the nodes a fresh start.
+		let expired = self
+			.banned
+			.iter()
+			.filter(|(_, until)| **until <= now)
+			.map(|(peer_id, _)| *peer_id)
+			.collect::<Vec<_>>();
+		for peer_id in expired {
+			self.banned.remove(&peer_id);
+			debug!(target: "peerset", "Ban of {} expired", peer_id);
+			self.data.peer_reputation(peer_id).set_reputation(0);
+		}
+
 		// For each elapsed second, move the node reputation towards zero.
 		// If we multiply each second the reputation by `k` (where `k` is between 0 and 1), it
 		// takes `ln(0.5) / ln(k)` seconds to reduce the reputation by half. Use this formula to
@@ -501,6 +583,11 @@ impl Peerset {
 					reput.saturating_sub(diff)
 				}
 
+				// Banned nodes keep their reputation until the ban is lifted.
+				if self.banned.contains_key(&peer_id) {
+					continue
+				}
+
 				let mut peer_reputation = self.data.peer_reputation(peer_id);
 
 				let before = peer_reputation.reputation();
@@ -531,6 +618,11 @@ impl Peerset {
 				}
 			}
 		}
+
+		if secs_diff > 0 && !self.recent_reports.is_empty() {
+			let known = self.data.peers().cloned().collect::<HashSet<_>>();
+			self.recent_reports.retain(|peer_id, _| known.contains(peer_id));
+		}
 	}
 
 	/// Try to fill available out slots with nodes for the given set.
@@ -713,6 +805,31 @@ impl Peerset {
 		})
 	}
 
+	/// Returns the reputation of every node known to the peerset, along with its recent
+	/// reputation changes and remaining ban.
+	pub fn peer_reputations(&mut self) -> Vec<PeerReputation> {
+		self.update_time();
+
+		let now = Instant::now();
+		let peers = self.data.peers().cloned().collect::<Vec<_>>();
+		peers
+			.into_iter()
+			.map(|peer_id| PeerReputation {
+				peer_id,
+				reputation: self.data.peer_reputation(peer_id).reputation(),
+				banned_for: self
+					.banned
+					.get(&peer_id)
+					.map(|until| until.saturating_duration_since(now)),
+				recent_reports: self
+					.recent_reports
+					.get(&peer_id)
+					.map(|reports| reports.iter().copied().collect())
+					.unwrap_or_default(),
+			})
+			.collect()
+	}
+
 	/// Returns the number of peers that we have discovered.
 	pub fn num_discovered_peers(&self) -> usize {
 		self.data.peers().len()
@@ -758,6 +875,8 @@ impl Stream for Peerset {
 					self.on_remove_from_peers_set(sets_name, peer_id),
 				Action::PeerReputation(peer_id, pending_response) =>
 					self.on_peer_reputation(peer_id, pending_response),
+				Action::BanPeer(peer_id, duration) => self.on_ban_peer(peer_id, duration),
+				Action::UnbanPeer(peer_id) => self.on_unban_peer(peer_id),
 			}
 		}
 	}
@@ -996,4 +1115,90 @@ mod tests {
 
 		futures::executor::block_on(fut);
 	}
+
+	#[test]
+	fn test_peerset_manual_ban() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+		});
+
+		let peer_id = PeerId::random();
+		handle.ban_peer(peer_id, Duration::from_secs(3600));
+		// Good behaviour doesn't lift a manual ban.
+		handle.report_peer(peer_id, ReputationChange::new(i32::MAX, "Good"));
+
+		let fut = futures::future::poll_fn(move |cx| {
+			// We need one polling for the messages to be processed.
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+
+			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(1));
+			if let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
+				assert_eq!(msg.unwrap(), Message::Reject(IncomingIndex(1)));
+			} else {
+				panic!()
+			}
+
+			let reputations = peerset.peer_reputations();
+			assert_eq!(reputations.len(), 1);
+			assert_eq!(reputations[0].peer_id, peer_id);
+			assert!(reputations[0].reputation < BANNED_THRESHOLD);
+			assert!(reputations[0].banned_for.is_some());
+			assert_eq!(
+				reputations[0].recent_reports.iter().map(|r| r.reason).collect::<Vec<_>>(),
+				vec!["Banned manually", "Good"],
+			);
+
+			// Once unbanned, the peerset connects to the node again.
+			handle.unban_peer(peer_id);
+			if let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
+				assert_eq!(msg.unwrap(), Message::Connect { set_id: SetId::from(0), peer_id });
+			} else {
+				panic!()
+			}
+			assert_eq!(peerset.peer_reputations()[0].banned_for, None);
+
+			Poll::Ready(())
+		});
+
+		futures::executor::block_on(fut);
+	}
+
+	#[test]
+	fn test_peerset_manual_ban_expires() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+		});
+
+		let peer_id = PeerId::random();
+		handle.ban_peer(peer_id, Duration::from_millis(500));
+
+		let fut = futures::future::poll_fn(move |cx| {
+			// We need one polling for the message to be processed.
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+
+			// Wait for the ban to expire, which is checked on every reputation update.
+			thread::sleep(Duration::from_millis(1500));
+
+			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(1));
+			while let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
+				assert_eq!(msg.unwrap(), Message::Accept(IncomingIndex(1)));
+			}
+
+			Poll::Ready(())
+		});
+
+		futures::executor::block_on(fut);
+	}
 }
@@ -51,6 +51,12 @@ pub enum Error {
 	/// Invalid session keys encoding.
 	#[error("Session keys are not encoded correctly")]
 	InvalidSessionKeys,
+	/// Key rotation with replicas is not configured.
+	#[error("Key rotation is not configured")]
+	KeyRotationUnavailable,
+	/// Rotating or importing keys failed.
+	#[error("Key rotation failed: {}", .0)]
+	KeyRotation(String),
 	/// Call to an unsafe RPC was denied.
 	#[error(transparent)]
 	UnsafeRpcCalled(#[from] crate::policy::UnsafeRpcError),
@@ -24,6 +24,7 @@ use sp_core::Bytes;
 
 pub mod error;
 pub mod hash;
+pub mod pool;
 
 /// Substrate authoring RPC API
 #[rpc(client, server)]
@@ -40,6 +41,22 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[method(name = "author_rotateKeys")]
 	fn rotate_keys(&self) -> RpcResult<Bytes>;
 
+	/// Generate new session keys, import them into the configured standby replicas and return
+	/// the corresponding public keys.
+	///
+	/// The keys are sealed with the key rotation password before they are sent to a replica.
+	/// Public keys are only returned once every replica holds the private keys, so that the
+	/// session keys can be registered on chain without breaking failover.
+	#[method(name = "author_rotateKeysWithReplicas")]
+	async fn rotate_keys_with_replicas(&self) -> RpcResult<Bytes>;
+
+	/// Import keys sealed by `author_rotateKeysWithReplicas` of another node.
+	///
+	/// `session_keys` is the SCALE encoded session keys object the keys belong to, the import
+	/// fails if the keystore does not hold all of its private keys afterwards.
+	#[method(name = "author_importKeys")]
+	async fn import_keys(&self, keys: Bytes, session_keys: Bytes) -> RpcResult<()>;
+
 	/// Checks if the keystore has private keys for the given session public keys.
 	///
 	/// `session_keys` is the SCALE encoded session keys object from the runtime.
@@ -58,6 +75,17 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[method(name = "author_pendingExtrinsics")]
 	fn pending_extrinsics(&self) -> RpcResult<Vec<Bytes>>;
 
+	/// Returns all transactions in the ready and future queues of the pool together with their
+	/// dependencies on other transactions in the pool.
+	#[method(name = "author_poolStatus", aliases = ["txpool_inspect"])]
+	fn pool_status(&self) -> RpcResult<Vec<pool::PoolTransaction<Hash>>>;
+
+	/// Explains why the transaction with the given hash is in the future queue.
+	///
+	/// Returns `None` if the transaction is not in the future queue.
+	#[method(name = "author_poolFutureReason")]
+	fn pool_future_reason(&self, hash: Hash) -> RpcResult<Option<pool::FutureReason<Hash>>>;
+
 	/// Remove given extrinsic from the pool and temporarily ban it to prevent reimporting.
 	#[method(name = "author_removeExtrinsic")]
 	fn remove_extrinsic(
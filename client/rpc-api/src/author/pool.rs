@@ -0,0 +1,149 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Transaction pool inspection types for author RPC module.
+
+use sc_transaction_pool_api::{InspectedTransaction, TransactionSource};
+use serde::{Deserialize, Serialize};
+use sp_core::Bytes;
+
+/// Queue of the transaction pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PoolQueue {
+	/// Transactions that can be included in the next block.
+	Ready,
+	/// Transactions waiting for tags that are not provided yet.
+	Future,
+}
+
+/// Where a pool transaction is coming from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PoolTransactionSource {
+	/// Transaction is already included in a block.
+	InBlock,
+	/// Transaction was submitted by the node itself.
+	Local,
+	/// Transaction was received from the network or over RPC.
+	External,
+}
+
+impl From<TransactionSource> for PoolTransactionSource {
+	fn from(source: TransactionSource) -> Self {
+		match source {
+			TransactionSource::InBlock => Self::InBlock,
+			TransactionSource::Local => Self::Local,
+			TransactionSource::External => Self::External,
+		}
+	}
+}
+
+/// Transaction in the pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolTransaction<Hash> {
+	/// Hash of the transaction.
+	pub hash: Hash,
+	/// Queue the transaction is in.
+	pub queue: PoolQueue,
+	/// Priority of the transaction.
+	pub priority: u64,
+	/// Block number until which the transaction is valid.
+	pub valid_till: u64,
+	/// Tags required by the transaction.
+	pub requires: Vec<Bytes>,
+	/// Tags provided by the transaction.
+	pub provides: Vec<Bytes>,
+	/// Source of the transaction.
+	pub source: PoolTransactionSource,
+	/// Whether the transaction is propagated to other peers.
+	pub propagate: bool,
+	/// Size of the encoded transaction.
+	pub bytes: u64,
+	/// Milliseconds since the transaction was imported to the pool.
+	pub time_in_pool: u64,
+	/// Transactions in the pool this transaction depends on.
+	pub depends_on: Vec<Hash>,
+	/// Transactions in the pool that depend on this transaction.
+	pub unlocks: Vec<Hash>,
+}
+
+impl<Hash> From<InspectedTransaction<Hash>> for PoolTransaction<Hash> {
+	fn from(tx: InspectedTransaction<Hash>) -> Self {
+		PoolTransaction {
+			hash: tx.hash,
+			queue: if tx.is_ready { PoolQueue::Ready } else { PoolQueue::Future },
+			priority: tx.priority,
+			valid_till: tx.valid_till,
+			requires: tx.requires.into_iter().map(Into::into).collect(),
+			provides: tx.provides.into_iter().map(Into::into).collect(),
+			source: tx.source.into(),
+			propagate: tx.propagate,
+			bytes: tx.bytes as u64,
+			time_in_pool: tx.time_in_pool.as_millis() as u64,
+			depends_on: tx.depends_on,
+			unlocks: tx.unlocks,
+		}
+	}
+}
+
+/// Reason why a transaction is in the future queue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FutureReason<Hash> {
+	/// Required tags that are neither provided by the chain nor by a ready transaction.
+	pub missing_tags: Vec<Bytes>,
+	/// Future transactions that provide some of the missing tags.
+	pub blocked_by: Vec<Hash>,
+	/// Missing tags that no transaction in the pool provides.
+	pub unprovided_tags: Vec<Bytes>,
+}
+
+impl<Hash: Clone + PartialEq> FutureReason<Hash> {
+	/// Explains why `tx` is in the future queue given all transactions in the pool.
+	///
+	/// Returns `None` if the transaction is ready.
+	pub fn new(
+		tx: &InspectedTransaction<Hash>,
+		pool: &[InspectedTransaction<Hash>],
+	) -> Option<Self> {
+		if tx.is_ready {
+			return None
+		}
+
+		let provider = |tag: &Vec<u8>| pool.iter().find(|other| other.provides.contains(tag));
+		let mut blocked_by = Vec::new();
+		let mut unprovided_tags = Vec::new();
+		for tag in &tx.missing_tags {
+			match provider(tag) {
+				Some(other) =>
+					if !blocked_by.contains(&other.hash) {
+						blocked_by.push(other.hash.clone())
+					},
+				None => unprovided_tags.push(tag.clone().into()),
+			}
+		}
+
+		Some(FutureReason {
+			missing_tags: tx.missing_tags.iter().cloned().map(Into::into).collect(),
+			blocked_by,
+			unprovided_tags,
+		})
+	}
+}
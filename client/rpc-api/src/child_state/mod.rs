@@ -17,7 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 //! Substrate child state API
-use crate::state::ReadProof;
+use crate::state::{ReadProof, StorageDiffPage};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use sp_core::storage::{PrefixedStorageKey, StorageData, StorageKey};
 
@@ -50,6 +50,23 @@ pub trait ChildStateApi<Hash> {
 		hash: Option<Hash>,
 	) -> RpcResult<Vec<StorageKey>>;
 
+	/// Returns the child storage entries with prefix that differ between the states of two
+	/// blocks.
+	/// Up to `count` entries will be returned, fewer if the page stops early to bound the work
+	/// done by the call.
+	/// If `start_key` is passed, return next entries in lexicographic order of their keys. The
+	/// returned `nextKey` is the `start_key` of the next page.
+	#[method(name = "childstate_getStorageDiff", blocking)]
+	fn storage_diff(
+		&self,
+		child_storage_key: PrefixedStorageKey,
+		from: Hash,
+		to: Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> RpcResult<StorageDiffPage>;
+
 	/// Returns a child storage entry at a specific block's state.
 	#[method(name = "childstate_getStorage", blocking)]
 	fn storage(
@@ -19,7 +19,10 @@
 //! Substrate state API helpers.
 
 use serde::{Deserialize, Serialize};
-use sp_core::Bytes;
+use sp_core::{
+	storage::{PrefixedStorageKey, StorageData, StorageKey},
+	Bytes,
+};
 
 /// ReadProof struct returned by the RPC
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -30,3 +33,44 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// Kind of a storage change between two blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageDiffKind {
+	/// Entry does not exist in the old block.
+	Added,
+	/// Entry does not exist in the new block.
+	Removed,
+	/// Entry exists in both blocks with different values.
+	Changed,
+}
+
+/// Storage entry that differs between two blocks, returned by the RPC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiffItem {
+	/// Key of the entry.
+	pub key: StorageKey,
+	/// Kind of the change.
+	pub kind: StorageDiffKind,
+	/// Value in the old block.
+	pub old_value: Option<StorageData>,
+	/// Value in the new block.
+	pub new_value: Option<StorageData>,
+	/// Child trie holding the entry, `None` for entries of the top trie.
+	pub child_storage_key: Option<PrefixedStorageKey>,
+}
+
+/// Page of the storage entries that differ between two blocks, returned by the RPC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiffPage {
+	/// Entries that differ, in lexicographic order of their keys.
+	pub items: Vec<StorageDiffItem>,
+	/// Key to pass as `start_key` to get the next page, `None` if there are no more entries.
+	pub next_key: Option<StorageKey>,
+	/// Key to pass as `start_child_key` to get the next page, set if the page ended within the
+	/// child trie stored at `next_key`.
+	pub next_child_key: Option<StorageKey>,
+}
@@ -28,7 +28,7 @@ use sp_version::RuntimeVersion;
 pub mod error;
 pub mod helpers;
 
-pub use self::helpers::ReadProof;
+pub use self::helpers::{ReadProof, StorageDiffItem, StorageDiffKind, StorageDiffPage};
 
 /// Substrate state API
 #[rpc(client, server)]
@@ -62,6 +62,26 @@ pub trait StateApi<Hash> {
 		hash: Option<Hash>,
 	) -> RpcResult<Vec<StorageKey>>;
 
+	/// Returns the storage entries with prefix that differ between the states of two blocks.
+	/// Up to `count` entries will be returned, fewer if the page stops early to bound the work
+	/// done by the call.
+	/// If `start_key` is passed, return next entries in lexicographic order of their keys. The
+	/// returned `nextKey` and `nextChildKey` are the `start_key` and `start_child_key` of the next
+	/// page.
+	///
+	/// The entries of changed child tries are returned right before the change of their root,
+	/// with their `childStorageKey` set.
+	#[method(name = "state_getStorageDiff", blocking)]
+	fn storage_diff(
+		&self,
+		from: Hash,
+		to: Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		start_child_key: Option<StorageKey>,
+	) -> RpcResult<StorageDiffPage>;
+
 	/// Returns a storage entry at a specific block's state.
 	#[method(name = "state_getStorage", aliases = ["state_getStorageAt"], blocking)]
 	fn storage(&self, key: StorageKey, hash: Option<Hash>) -> RpcResult<Option<StorageData>>;
@@ -285,4 +305,34 @@ pub trait StateApi<Hash> {
 		storage_keys: Option<String>,
 		methods: Option<String>,
 	) -> RpcResult<sp_rpc::tracing::TraceBlockResponse>;
+
+	/// The `state_profileBlock` RPC provides a way to measure where the time is spent when
+	/// re-executing a block.
+	///
+	/// The block is re-executed with the tracing targets given in `targets` and the time spent in
+	/// every span is returned folded into stacks, one line per stack with the frames separated by
+	/// `;` followed by the nanoseconds spent in the innermost frame, e.g.
+	/// `pallet_balances::transfer;sp_io::storage::get;state::Get 26aa394e... 5000`.
+	/// This is the input format of flamegraph tools such as `inferno-flamegraph`.
+	///
+	/// Host functions accessing the storage get an additional frame with the method and the
+	/// first 32 bytes of the accessed key, the hashes of the pallet and storage item prefixes.
+	///
+	/// ## Node requirements
+	///
+	/// The same as for `state_traceBlock`: the node must run with `--rpc-methods=Unsafe` and the
+	/// runtime must be compiled with the `with-tracing` feature to see the pallet spans.
+	///
+	/// ## Params
+	///
+	/// - `block` (param index 0): Hash of the block to profile.
+	/// - `targets` (param index 1): String of comma separated (no spaces) targets. Time spent in
+	/// spans of other targets is attributed to their closest recorded parent. Defaults to
+	/// `pallet,frame,sp_io`.
+	#[method(name = "state_profileBlock", blocking)]
+	fn profile_block(
+		&self,
+		block: Hash,
+		targets: Option<String>,
+	) -> RpcResult<sp_rpc::tracing::ProfileBlockResponse>;
 }
@@ -71,6 +71,40 @@ pub struct PeerInfo<Hash, Number> {
 	pub best_number: Number,
 }
 
+/// Reputation of a peer, as tracked by the peer set manager.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+	/// Peer ID
+	pub peer_id: String,
+	/// Current reputation. Peers under the ban threshold are refused.
+	pub reputation: i32,
+	/// Remaining seconds of a manual ban, if the peer is banned.
+	pub banned_for: Option<u64>,
+	/// Most recent reputation changes, oldest first.
+	pub recent_reports: Vec<ReputationReport>,
+}
+
+/// A reputation change reported for a peer.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReputationReport {
+	/// Reputation delta.
+	pub value: i32,
+	/// Reason for the change.
+	pub reason: String,
+}
+
+/// A manual ban of a peer or IP address.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ban {
+	/// Banned peer ID or IP address.
+	pub target: String,
+	/// Remaining seconds of the ban.
+	pub expires_in: u64,
+}
+
 /// The role the node is running as
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum NodeRole {
@@ -123,6 +157,20 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn should_serialize_peer_reputation() {
+		assert_eq!(
+			::serde_json::to_string(&PeerReputation {
+				peer_id: "2".into(),
+				reputation: -100,
+				banned_for: Some(60),
+				recent_reports: vec![ReputationReport { value: -100, reason: "a".into() }],
+			})
+			.unwrap(),
+			r#"{"peerId":"2","reputation":-100,"bannedFor":60,"recentReports":[{"value":-100,"reason":"a"}]}"#,
+		);
+	}
+
 	#[test]
 	fn should_serialize_sync_state() {
 		assert_eq!(
@@ -23,7 +23,9 @@ use jsonrpsee::{
 	proc_macros::rpc,
 };
 
-pub use self::helpers::{Health, NodeRole, PeerInfo, SyncState, SystemInfo};
+pub use self::helpers::{
+	Ban, Health, NodeRole, PeerInfo, PeerReputation, ReputationReport, SyncState, SystemInfo,
+};
 
 pub mod error;
 pub mod helpers;
@@ -100,6 +102,27 @@ pub trait SystemApi<Hash, Number> {
 	#[method(name = "system_reservedPeers")]
 	async fn system_reserved_peers(&self) -> RpcResult<Vec<String>>;
 
+	/// Returns the reputation of the known peers, along with the reasons of their most recent
+	/// reputation changes.
+	#[method(name = "system_peerReputations")]
+	async fn system_peer_reputations(&self) -> RpcResult<Vec<PeerReputation>>;
+
+	/// Bans a peer for `duration` seconds. The string should encode either a PeerId, e.g.
+	/// `QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV`, or an IP address, in which case all
+	/// the peers connecting from it are banned.
+	///
+	/// Bans are persisted and survive a restart of the node.
+	#[method(name = "system_banPeer")]
+	async fn system_ban_peer(&self, peer: String, duration: u64) -> RpcResult<()>;
+
+	/// Lifts the ban of a PeerId or IP address banned with `system_banPeer`.
+	#[method(name = "system_unbanPeer")]
+	async fn system_unban_peer(&self, peer: String) -> RpcResult<()>;
+
+	/// Returns the active bans.
+	#[method(name = "system_bans")]
+	async fn system_bans(&self) -> RpcResult<Vec<Ban>>;
+
 	/// Returns the roles the node is running as.
 	#[method(name = "system_nodeRoles")]
 	async fn system_node_roles(&self) -> RpcResult<Vec<NodeRole>>;
@@ -18,6 +18,7 @@
 
 //! Substrate block-author/full-node API.
 
+mod rotation;
 #[cfg(test)]
 mod tests;
 
@@ -26,12 +27,14 @@ use std::sync::Arc;
 use crate::SubscriptionTaskExecutor;
 
 use codec::{Decode, Encode};
-use futures::{FutureExt, TryFutureExt};
+use futures::{channel::oneshot, FutureExt, TryFutureExt};
 use jsonrpsee::{
 	core::{async_trait, Error as JsonRpseeError, RpcResult},
 	types::SubscriptionResult,
 	SubscriptionSink,
 };
+use sc_client_api::ExecutorProvider;
+use sc_keystore::ExportedKey;
 use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool_api::{
 	error::IntoPoolError, BlockHash, InPoolTransaction, TransactionFor, TransactionPool,
@@ -45,6 +48,7 @@ use sp_runtime::{generic, traits::Block as BlockT};
 use sp_session::SessionKeys;
 
 use self::error::{Error, Result};
+pub use self::rotation::{rotate_keys_with_replicas, KeyRotationConfig};
 /// Re-export the API for backward compatibility.
 pub use sc_rpc_api::author::*;
 
@@ -60,6 +64,8 @@ pub struct Author<P, Client> {
 	deny_unsafe: DenyUnsafe,
 	/// Executor to spawn subscriptions.
 	executor: SubscriptionTaskExecutor,
+	/// Session key rotation with standby replicas.
+	key_rotation: Option<KeyRotationConfig>,
 }
 
 impl<P, Client> Author<P, Client> {
@@ -71,8 +77,79 @@ impl<P, Client> Author<P, Client> {
 		deny_unsafe: DenyUnsafe,
 		executor: SubscriptionTaskExecutor,
 	) -> Self {
-		Author { client, pool, keystore, deny_unsafe, executor }
+		Author { client, pool, keystore, deny_unsafe, executor, key_rotation: None }
 	}
+
+	/// Enable the rotation of session keys with standby replicas.
+	pub fn with_key_rotation(mut self, key_rotation: Option<KeyRotationConfig>) -> Self {
+		self.key_rotation = key_rotation;
+		self
+	}
+
+	fn key_rotation(&self) -> Result<&KeyRotationConfig> {
+		self.key_rotation.as_ref().ok_or(Error::KeyRotationUnavailable)
+	}
+
+	/// Run `task` as a blocking task, away from the threads serving the RPC requests: generating
+	/// keys calls into the runtime, and sealing them derives a key from the password.
+	async fn spawn_blocking<R: Send + 'static>(
+		&self,
+		name: &'static str,
+		task: impl FnOnce() -> Result<R> + Send + 'static,
+	) -> Result<R> {
+		let (tx, rx) = oneshot::channel();
+		self.executor.spawn_blocking(
+			name,
+			Some("rpc"),
+			Box::pin(async move {
+				let _ = tx.send(task());
+			}),
+		);
+		rx.await
+			.map_err(|_| Error::KeyRotation(format!("Task {} was cancelled", name)))?
+	}
+}
+
+impl<P, Client> Author<P, Client>
+where
+	P: TransactionPool,
+	Client: HeaderBackend<P::Block> + ProvideRuntimeApi<P::Block>,
+	Client::Api: SessionKeys<P::Block>,
+{
+	/// Insert the keys of `session_keys` into the keystore.
+	///
+	/// Fails if any key is not part of `session_keys` or the keystore is missing some of the
+	/// session keys afterwards.
+	fn insert_session_keys(&self, keys: &[ExportedKey], session_keys: Vec<u8>) -> Result<()> {
+		let best_block_hash = self.client.info().best_hash;
+		let expected = self
+			.client
+			.runtime_api()
+			.decode_session_keys(&generic::BlockId::Hash(best_block_hash), session_keys)
+			.map_err(|e| Error::Client(Box::new(e)))?
+			.ok_or(Error::InvalidSessionKeys)?;
+
+		if let Some(key) = keys.iter().find(|k| !expected.contains(&(k.public.clone(), k.key_type)))
+		{
+			return Err(Error::KeyRotation(format!(
+				"Key 0x{} is not part of the session keys",
+				hex_public(&key.public)
+			)))
+		}
+		for key in keys {
+			SyncCryptoStore::insert_unknown(&*self.keystore, key.key_type, &key.suri, &key.public)
+				.map_err(|_| Error::KeyStoreUnavailable)?;
+		}
+		if !SyncCryptoStore::has_keys(&*self.keystore, &expected) {
+			return Err(Error::KeyRotation("Keystore is missing some of the session keys".into()))
+		}
+
+		Ok(())
+	}
+}
+
+fn hex_public(public: &[u8]) -> String {
+	sp_core::hexdisplay::HexDisplay::from(&public).to_string()
 }
 
 /// Currently we treat all RPC transactions as externals.
@@ -86,7 +163,12 @@ const TX_SOURCE: TransactionSource = TransactionSource::External;
 impl<P, Client> AuthorApiServer<TxHash<P>, BlockHash<P>> for Author<P, Client>
 where
 	P: TransactionPool + Sync + Send + 'static,
-	Client: HeaderBackend<P::Block> + ProvideRuntimeApi<P::Block> + Send + Sync + 'static,
+	Client: HeaderBackend<P::Block>
+		+ ProvideRuntimeApi<P::Block>
+		+ ExecutorProvider<P::Block>
+		+ Send
+		+ Sync
+		+ 'static,
 	Client::Api: SessionKeys<P::Block>,
 	P::Hash: Unpin,
 	<P::Block as BlockT>::Hash: Unpin,
@@ -128,6 +210,47 @@ where
 			.map_err(|api_err| Error::Client(Box::new(api_err)).into())
 	}
 
+	async fn rotate_keys_with_replicas(&self) -> RpcResult<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let key_rotation = self.key_rotation()?;
+
+		let best_block_hash = self.client.info().best_hash;
+		let client = self.client.clone();
+		let password = key_rotation.password.clone();
+		let (session_keys, keys, sealed) = self
+			.spawn_blocking("author-generate-session-keys", move || {
+				let (session_keys, keys) =
+					rotation::generate_session_keys(&*client, best_block_hash)?;
+				let sealed = sc_keystore::export::seal(&keys, &password)
+					.map_err(rotation::rotation_error)?;
+				Ok((session_keys, keys, sealed))
+			})
+			.await?;
+
+		rotation::import_into_replicas(&key_rotation.replicas, &sealed, &session_keys).await?;
+		self.insert_session_keys(&keys, session_keys.clone())?;
+
+		log::info!(
+			target: "rpc",
+			"Rotated session keys on this node and {} replicas",
+			key_rotation.replicas.len(),
+		);
+		Ok(session_keys.into())
+	}
+
+	async fn import_keys(&self, keys: Bytes, session_keys: Bytes) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		let password = self.key_rotation()?.password.clone();
+
+		let keys = self
+			.spawn_blocking("author-open-keys", move || {
+				sc_keystore::export::open(&keys, &password).map_err(rotation::rotation_error)
+			})
+			.await?;
+		self.insert_session_keys(&keys, session_keys.to_vec())?;
+		Ok(())
+	}
+
 	fn has_session_keys(&self, session_keys: Bytes) -> RpcResult<bool> {
 		self.deny_unsafe.check_if_safe()?;
 
@@ -153,6 +276,23 @@ where
 		Ok(self.pool.ready().map(|tx| tx.data().encode().into()).collect())
 	}
 
+	fn pool_status(&self) -> RpcResult<Vec<pool::PoolTransaction<TxHash<P>>>> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.pool.inspect().into_iter().map(Into::into).collect())
+	}
+
+	fn pool_future_reason(
+		&self,
+		hash: TxHash<P>,
+	) -> RpcResult<Option<pool::FutureReason<TxHash<P>>>> {
+		self.deny_unsafe.check_if_safe()?;
+		let transactions = self.pool.inspect();
+		Ok(transactions
+			.iter()
+			.find(|tx| tx.hash == hash)
+			.and_then(|tx| pool::FutureReason::new(tx, &transactions)))
+	}
+
 	fn remove_extrinsic(
 		&self,
 		bytes_or_hash: Vec<hash::ExtrinsicOrHash<TxHash<P>>>,
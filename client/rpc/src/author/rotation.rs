@@ -0,0 +1,132 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2017-2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Rotation of session keys onto standby replicas.
+
+use super::error::{Error, Result};
+use codec::{Decode, Encode};
+use futures::future::try_join_all;
+use jsonrpsee::{core::client::ClientT, rpc_params, ws_client::WsClientBuilder};
+use sc_client_api::{CallExecutor, ExecutorProvider};
+use sc_keystore::{ExportedKey, LocalKeystore};
+use sp_core::{crypto::SecretString, Bytes};
+use sp_externalities::Extensions;
+use sp_keystore::KeystoreExt;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::{sync::Arc, time::Duration};
+
+/// Timeout of a key import on a replica.
+const REPLICA_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Configuration of the session key rotation with standby replicas.
+#[derive(Debug, Clone)]
+pub struct KeyRotationConfig {
+	/// WebSocket RPC endpoints of the replicas the rotated keys are imported into.
+	///
+	/// Replicas have to allow unsafe RPC calls from the node.
+	pub replicas: Vec<String>,
+	/// Password the keys are sealed with while they are sent to the replicas.
+	///
+	/// Has to be the same on the node and all of its replicas.
+	pub password: SecretString,
+}
+
+/// Generate new session keys in a temporary keystore.
+///
+/// Returns the SCALE encoded session keys together with the exported private keys.
+pub(super) fn generate_session_keys<Block, Client>(
+	client: &Client,
+	at: Block::Hash,
+) -> Result<(Vec<u8>, Vec<ExportedKey>)>
+where
+	Block: BlockT,
+	Client: ExecutorProvider<Block>,
+{
+	let keystore = Arc::new(LocalKeystore::in_memory());
+	let mut extensions = Extensions::new();
+	extensions.register(KeystoreExt(keystore.clone()));
+
+	let at = BlockId::Hash(at);
+	let encoded = client
+		.executor()
+		.call(
+			&at,
+			"SessionKeys_generate_session_keys",
+			&None::<Vec<u8>>.encode(),
+			client.execution_extensions().strategies().other,
+			Some(extensions),
+		)
+		.map_err(|e| Error::Client(Box::new(e)))?;
+	let session_keys = Vec::<u8>::decode(&mut &encoded[..])?;
+
+	let public_keys = keystore
+		.public_keys()
+		.map_err(rotation_error)?
+		.into_iter()
+		.map(|(key_type, public)| (public, key_type))
+		.collect::<Vec<_>>();
+	let keys = keystore.export_keys(&public_keys).map_err(rotation_error)?;
+
+	Ok((session_keys, keys))
+}
+
+/// Import sealed keys into all replicas.
+pub(super) async fn import_into_replicas(
+	replicas: &[String],
+	keys: &[u8],
+	session_keys: &[u8],
+) -> Result<()> {
+	try_join_all(replicas.iter().map(|replica| async move {
+		let client = WsClientBuilder::default()
+			.request_timeout(REPLICA_TIMEOUT)
+			.build(replica)
+			.await
+			.map_err(|e| replica_error(replica, e))?;
+		client
+			.request::<()>(
+				"author_importKeys",
+				rpc_params![Bytes(keys.to_vec()), Bytes(session_keys.to_vec())],
+			)
+			.await
+			.map_err(|e| replica_error(replica, e))
+	}))
+	.await
+	.map(|_| ())
+}
+
+/// Ask the node listening on `url` to rotate its session keys with its replicas.
+///
+/// Returns the SCALE encoded session keys to register on chain.
+pub async fn rotate_keys_with_replicas(url: &str) -> Result<Bytes> {
+	let client = WsClientBuilder::default()
+		.request_timeout(REPLICA_TIMEOUT * 2)
+		.build(url)
+		.await
+		.map_err(|e| Error::KeyRotation(format!("Failed to connect to {}: {}", url, e)))?;
+	client
+		.request("author_rotateKeysWithReplicas", None)
+		.await
+		.map_err(|e| Error::KeyRotation(e.to_string()))
+}
+
+pub(super) fn rotation_error(e: sc_keystore::Error) -> Error {
+	Error::KeyRotation(e.to_string())
+}
+
+fn replica_error(replica: &str, e: impl std::fmt::Display) -> Error {
+	Error::KeyRotation(format!("Import into replica {} failed: {}", replica, e))
+}
@@ -83,9 +83,20 @@ impl TestSetup {
 			keystore: self.keystore.clone(),
 			deny_unsafe: DenyUnsafe::No,
 			executor: test_executor(),
+			key_rotation: None,
 		}
 	}
 
+	fn author_with_key_rotation(
+		&self,
+		password: &str,
+	) -> Author<FullTransactionPool, Client<Backend>> {
+		self.author().with_key_rotation(Some(KeyRotationConfig {
+			replicas: Vec::new(),
+			password: std::str::FromStr::from_str(password).unwrap(),
+		}))
+	}
+
 	fn into_rpc() -> RpcModule<Author<FullTransactionPool, Client<Backend>>> {
 		Self::default().author().into_rpc()
 	}
@@ -177,6 +188,38 @@ async fn author_should_return_pending_extrinsics() {
 	assert_eq!(pending, vec![xt_bytes]);
 }
 
+#[tokio::test]
+async fn author_should_inspect_pool() {
+	let api = TestSetup::into_rpc();
+
+	let xt1 = to_hex(&uxt(AccountKeyring::Alice, 0).encode(), true);
+	let xt1_hash: H256 = api.call("author_submitExtrinsic", [xt1]).await.unwrap();
+	let xt2 = to_hex(&uxt(AccountKeyring::Alice, 2).encode(), true);
+	let xt2_hash: H256 = api.call("author_submitExtrinsic", [xt2]).await.unwrap();
+
+	let mut transactions: Vec<pool::PoolTransaction<H256>> =
+		api.call("txpool_inspect", EmptyParams::new()).await.unwrap();
+	transactions.sort_by_key(|tx| tx.queue == pool::PoolQueue::Future);
+	assert_eq!(transactions.len(), 2);
+	assert_eq!(transactions[0].hash, xt1_hash);
+	assert_eq!(transactions[0].queue, pool::PoolQueue::Ready);
+	assert_eq!(transactions[0].source, pool::PoolTransactionSource::External);
+	assert_eq!(transactions[1].hash, xt2_hash);
+	assert_eq!(transactions[1].queue, pool::PoolQueue::Future);
+	assert!(transactions[1].depends_on.is_empty());
+
+	let reason: Option<pool::FutureReason<H256>> =
+		api.call("author_poolFutureReason", [xt2_hash]).await.unwrap();
+	let reason = reason.unwrap();
+	assert_eq!(reason.missing_tags, transactions[1].requires);
+	assert!(reason.blocked_by.is_empty());
+	assert_eq!(reason.unprovided_tags, reason.missing_tags);
+
+	let reason: Option<pool::FutureReason<H256>> =
+		api.call("author_poolFutureReason", [xt1_hash]).await.unwrap();
+	assert_eq!(reason, None);
+}
+
 #[tokio::test]
 async fn author_should_remove_extrinsics() {
 	const METHOD: &'static str = "author_removeExtrinsic";
@@ -248,6 +291,61 @@ async fn author_should_rotate_keys() {
 		.contains(&CryptoTypePublicPair(sr25519::CRYPTO_ID, session_keys.sr25519.to_raw_vec())));
 }
 
+#[tokio::test]
+async fn author_rotate_keys_with_replicas_requires_configuration() {
+	let api = TestSetup::into_rpc();
+
+	assert_matches!(
+		api.call::<_, Bytes>("author_rotateKeysWithReplicas", EmptyParams::new()).await,
+		Err(RpcError::Call(CallError::Failed(err))) if err.to_string().contains("not configured")
+	);
+}
+
+#[tokio::test]
+async fn author_should_rotate_keys_with_replicas() {
+	let setup = TestSetup::default();
+	let api = setup.author_with_key_rotation("password").into_rpc();
+
+	let new_pubkeys: Bytes =
+		api.call("author_rotateKeysWithReplicas", EmptyParams::new()).await.unwrap();
+	let session_keys =
+		SessionKeys::decode(&mut &new_pubkeys[..]).expect("SessionKeys decode successfully");
+	let ed25519_pubkeys = SyncCryptoStore::keys(&*setup.keystore, ED25519).unwrap();
+	let sr25519_pubkeys = SyncCryptoStore::keys(&*setup.keystore, SR25519).unwrap();
+	assert!(ed25519_pubkeys
+		.contains(&CryptoTypePublicPair(ed25519::CRYPTO_ID, session_keys.ed25519.to_raw_vec())));
+	assert!(sr25519_pubkeys
+		.contains(&CryptoTypePublicPair(sr25519::CRYPTO_ID, session_keys.sr25519.to_raw_vec())));
+}
+
+#[tokio::test]
+async fn author_should_import_keys_sealed_by_active_node() {
+	let active = TestSetup::default();
+	let best_block_hash = active.client.info().best_hash;
+	let (session_keys, keys) =
+		rotation::generate_session_keys(&*active.client, best_block_hash).unwrap();
+	let password = std::str::FromStr::from_str("password").unwrap();
+	let sealed: Bytes = sc_keystore::export::seal(&keys, &password).unwrap().into();
+	let session_keys: Bytes = session_keys.into();
+
+	let standby = TestSetup::default();
+	let api = standby.author_with_key_rotation("wrong password").into_rpc();
+	assert!(api
+		.call::<_, ()>("author_importKeys", (sealed.clone(), session_keys.clone()))
+		.await
+		.is_err());
+	assert!(!api
+		.call::<_, bool>("author_hasSessionKeys", [session_keys.clone()])
+		.await
+		.unwrap());
+
+	let api = standby.author_with_key_rotation("password").into_rpc();
+	api.call::<_, ()>("author_importKeys", (sealed, session_keys.clone()))
+		.await
+		.unwrap();
+	assert!(api.call::<_, bool>("author_hasSessionKeys", [session_keys]).await.unwrap());
+}
+
 #[tokio::test]
 async fn author_has_session_keys() {
 	// Setup
@@ -90,6 +90,18 @@ where
 		start_key: Option<StorageKey>,
 	) -> Result<Vec<StorageKey>, Error>;
 
+	/// Returns the storage entries with prefix that differ between the states of two blocks, with
+	/// pagination support.
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		start_child_key: Option<StorageKey>,
+	) -> Result<StorageDiffPage, Error>;
+
 	/// Returns a storage entry at a specific block's state.
 	fn storage(
 		&self,
@@ -155,6 +167,13 @@ where
 		methods: Option<String>,
 	) -> Result<sp_rpc::tracing::TraceBlockResponse, Error>;
 
+	/// Measure the time spent executing block
+	fn profile_block(
+		&self,
+		block: Block::Hash,
+		targets: Option<String>,
+	) -> Result<sp_rpc::tracing::ProfileBlockResponse, Error>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(&self, sink: SubscriptionSink);
 
@@ -193,7 +212,7 @@ where
 		rpc_max_payload,
 	));
 	let backend = Box::new(self::state_full::FullState::new(client, executor, rpc_max_payload));
-	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
+	(State { backend, deny_unsafe }, ChildState { backend: child_backend, deny_unsafe })
 }
 
 /// State API with subscriptions support.
@@ -247,6 +266,27 @@ where
 			.map_err(Into::into)
 	}
 
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		start_child_key: Option<StorageKey>,
+	) -> RpcResult<StorageDiffPage> {
+		self.deny_unsafe.check_if_safe()?;
+		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			return Err(JsonRpseeError::from(Error::InvalidCount {
+				value: count,
+				max: STORAGE_KEYS_PAGED_MAX_COUNT,
+			}))
+		}
+		self.backend
+			.storage_diff(from, to, prefix, count, start_key, start_child_key)
+			.map_err(Into::into)
+	}
+
 	fn storage(
 		&self,
 		key: StorageKey,
@@ -319,6 +359,20 @@ where
 			.map_err(Into::into)
 	}
 
+	/// Re-execute the given block with the tracing targets given in `targets`
+	/// and return the time spent in their spans as folded stacks.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	/// Note: requires runtimes compiled with wasm tracing support, `--features with-tracing`.
+	fn profile_block(
+		&self,
+		block: Block::Hash,
+		targets: Option<String>,
+	) -> RpcResult<sp_rpc::tracing::ProfileBlockResponse> {
+		self.deny_unsafe.check_if_safe()?;
+		self.backend.profile_block(block, targets).map_err(Into::into)
+	}
+
 	fn subscribe_runtime_version(&self, sink: SubscriptionSink) -> SubscriptionResult {
 		self.backend.subscribe_runtime_version(sink);
 		Ok(())
@@ -374,6 +428,18 @@ where
 		start_key: Option<StorageKey>,
 	) -> Result<Vec<StorageKey>, Error>;
 
+	/// Returns the child storage entries with prefix that differ between the states of two
+	/// blocks, with pagination support.
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		storage_key: PrefixedStorageKey,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> Result<StorageDiffPage, Error>;
+
 	/// Returns a child storage entry at a specific block's state.
 	fn storage(
 		&self,
@@ -412,6 +478,8 @@ where
 /// Child state API with subscriptions support.
 pub struct ChildState<Block, Client> {
 	backend: Box<dyn ChildStateBackend<Block, Client>>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 }
 
 impl<Block, Client> ChildStateApiServer<Block::Hash> for ChildState<Block, Client>
@@ -441,6 +509,27 @@ where
 			.map_err(Into::into)
 	}
 
+	fn storage_diff(
+		&self,
+		storage_key: PrefixedStorageKey,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> RpcResult<StorageDiffPage> {
+		self.deny_unsafe.check_if_safe()?;
+		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			return Err(JsonRpseeError::from(Error::InvalidCount {
+				value: count,
+				max: STORAGE_KEYS_PAGED_MAX_COUNT,
+			}))
+		}
+		self.backend
+			.storage_diff(from, to, storage_key, prefix, count, start_key)
+			.map_err(Into::into)
+	}
+
 	fn storage(
 		&self,
 		storage_key: PrefixedStorageKey,
@@ -31,9 +31,9 @@ use futures::{future, stream, FutureExt, StreamExt};
 use jsonrpsee::{core::Error as JsonRpseeError, SubscriptionSink};
 use sc_client_api::{
 	Backend, BlockBackend, BlockchainEvents, CallExecutor, ExecutorProvider, ProofProvider,
-	StorageProvider,
+	StorageDiff, StorageDiffEntry, StorageProvider,
 };
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::state::{ReadProof, StorageDiffItem, StorageDiffKind, StorageDiffPage};
 use sp_api::{CallApiAt, Metadata, ProvideRuntimeApi};
 use sp_blockchain::{
 	CachedHeaderMetadata, Error as ClientError, HeaderBackend, HeaderMetadata,
@@ -201,7 +201,7 @@ where
 						&method,
 						&call_data,
 						self.client.execution_extensions().strategies().other,
-						None,
+						Some(self.client.execution_extensions().rpc_call_extensions()),
 					)
 					.map(Into::into)
 			})
@@ -247,6 +247,28 @@ where
 			.map_err(client_err)
 	}
 
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		start_child_key: Option<StorageKey>,
+	) -> std::result::Result<StorageDiffPage, Error> {
+		self.client
+			.storage_diff(
+				&BlockId::Hash(from),
+				&BlockId::Hash(to),
+				prefix.as_ref(),
+				start_key.as_ref(),
+				start_child_key.as_ref(),
+				count as usize,
+			)
+			.map(diff_page)
+			.map_err(client_err)
+	}
+
 	fn storage(
 		&self,
 		block: Option<Block::Hash>,
@@ -464,6 +486,23 @@ where
 		.trace_block()
 		.map_err(|e| invalid_block::<Block>(block, None, e.to_string()))
 	}
+
+	fn profile_block(
+		&self,
+		block: Block::Hash,
+		targets: Option<String>,
+	) -> std::result::Result<sp_rpc::tracing::ProfileBlockResponse, Error> {
+		sc_tracing::block::BlockExecutor::new(
+			self.client.clone(),
+			block,
+			targets,
+			None,
+			None,
+			self.rpc_max_payload,
+		)
+		.profile_block()
+		.map_err(|e| invalid_block::<Block>(block, None, e.to_string()))
+	}
 }
 
 impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block, Client>
@@ -553,6 +592,32 @@ where
 			.map_err(client_err)
 	}
 
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		storage_key: PrefixedStorageKey,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> std::result::Result<StorageDiffPage, Error> {
+		let child_info = match ChildType::from_prefixed_key(&storage_key) {
+			Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
+			None => return Err(client_err(sp_blockchain::Error::InvalidChildStorageKey)),
+		};
+		self.client
+			.child_storage_diff(
+				&BlockId::Hash(from),
+				&BlockId::Hash(to),
+				&child_info,
+				prefix.as_ref(),
+				start_key.as_ref(),
+				count as usize,
+			)
+			.map(diff_page)
+			.map_err(client_err)
+	}
+
 	fn storage(
 		&self,
 		block: Option<Block::Hash>,
@@ -616,6 +681,31 @@ where
 	}
 }
 
+/// Converts a page of a storage diff into its RPC representation.
+fn diff_page(diff: StorageDiff) -> StorageDiffPage {
+	StorageDiffPage {
+		items: diff.entries.into_iter().map(diff_item).collect(),
+		next_key: diff.next_key,
+		next_child_key: diff.next_child_key,
+	}
+}
+
+/// Converts a storage diff entry into its RPC representation.
+fn diff_item(entry: StorageDiffEntry) -> StorageDiffItem {
+	let kind = match (&entry.old_value, &entry.new_value) {
+		(None, _) => StorageDiffKind::Added,
+		(_, None) => StorageDiffKind::Removed,
+		_ => StorageDiffKind::Changed,
+	};
+	StorageDiffItem {
+		key: entry.key,
+		kind,
+		old_value: entry.old_value,
+		new_value: entry.new_value,
+		child_storage_key: entry.child_info.map(|child_info| child_info.prefixed_storage_key()),
+	}
+}
+
 fn invalid_block_range<B: BlockT>(
 	from: &CachedHeaderMetadata<B>,
 	to: &CachedHeaderMetadata<B>,
@@ -85,6 +85,94 @@ async fn should_return_storage() {
 	);
 }
 
+#[tokio::test]
+async fn should_return_storage_diff() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(client.clone(), test_executor(), DenyUnsafe::No, None);
+
+	let add_block = |changes: Vec<(Vec<u8>, Option<Vec<u8>>)>| {
+		let mut builder = client.new_block(Default::default()).unwrap();
+		for (key, value) in changes {
+			builder.push_storage_change(key, value).unwrap();
+		}
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		executor::block_on(client.clone().import(BlockOrigin::Own, block)).unwrap();
+		hash
+	};
+	let block1_hash = add_block(vec![(vec![9, 1], Some(vec![1])), (vec![9, 2], Some(vec![2]))]);
+	let block2_hash = add_block(vec![
+		(vec![9, 1], None),
+		(vec![9, 2], Some(vec![3])),
+		(vec![9, 3], Some(vec![4])),
+	]);
+	let prefix = Some(StorageKey(vec![9]));
+
+	assert_eq!(
+		api.storage_diff(block1_hash, block2_hash, prefix.clone(), 10, None, None)
+			.unwrap(),
+		StorageDiffPage {
+			items: vec![
+				StorageDiffItem {
+					key: StorageKey(vec![9, 1]),
+					kind: StorageDiffKind::Removed,
+					old_value: Some(StorageData(vec![1])),
+					new_value: None,
+					child_storage_key: None,
+				},
+				StorageDiffItem {
+					key: StorageKey(vec![9, 2]),
+					kind: StorageDiffKind::Changed,
+					old_value: Some(StorageData(vec![2])),
+					new_value: Some(StorageData(vec![3])),
+					child_storage_key: None,
+				},
+				StorageDiffItem {
+					key: StorageKey(vec![9, 3]),
+					kind: StorageDiffKind::Added,
+					old_value: None,
+					new_value: Some(StorageData(vec![4])),
+					child_storage_key: None,
+				},
+			],
+			next_key: None,
+			next_child_key: None,
+		},
+	);
+
+	let first_page = api
+		.storage_diff(block1_hash, block2_hash, prefix.clone(), 1, None, None)
+		.unwrap();
+	assert_eq!(first_page.items.len(), 1);
+	assert_eq!(first_page.next_key, Some(StorageKey(vec![9, 1])));
+	let second_page = api
+		.storage_diff(
+			block1_hash,
+			block2_hash,
+			prefix.clone(),
+			1,
+			Some(StorageKey(vec![9, 2])),
+			None,
+		)
+		.unwrap();
+	assert_eq!(
+		second_page.items.into_iter().map(|item| item.kind).collect::<Vec<_>>(),
+		vec![StorageDiffKind::Added],
+	);
+	assert_eq!(second_page.next_key, None);
+	assert_matches!(
+		api.storage_diff(
+			block1_hash,
+			block2_hash,
+			prefix,
+			STORAGE_KEYS_PAGED_MAX_COUNT + 1,
+			None,
+			None,
+		),
+		Err(RpcError::Call(RpcCallError::Custom(_)))
+	);
+}
+
 #[tokio::test]
 async fn should_return_storage_entries() {
 	const KEY1: &[u8] = b":mock";
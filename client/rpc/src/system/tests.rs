@@ -116,6 +116,28 @@ fn api<T: Into<Option<Status>>>(sync: T) -> RpcModule<System<Block>> {
 					let _ = sender
 						.send(vec!["QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string()]);
 				},
+				Request::NetworkPeerReputations(sender) => {
+					let _ = sender.send(vec![PeerReputation {
+						peer_id: "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string(),
+						reputation: i32::MIN,
+						banned_for: Some(60),
+						recent_reports: vec![ReputationReport {
+							value: i32::MIN,
+							reason: "Banned manually".into(),
+						}],
+					}]);
+				},
+				Request::NetworkBanPeer(peer, _, sender) |
+				Request::NetworkUnbanPeer(peer, sender) => {
+					let _ = match peer.parse::<sc_network::BanTarget>() {
+						Ok(_) => sender.send(Ok(())),
+						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s))),
+					};
+				},
+				Request::NetworkBans(sender) => {
+					let _ =
+						sender.send(vec![Ban { target: "198.51.100.19".into(), expires_in: 60 }]);
+				},
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				},
@@ -338,6 +360,39 @@ async fn system_network_reserved_peers() {
 	assert_eq!(reserved_peers, vec!["QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string()],);
 }
 
+#[tokio::test]
+async fn system_network_peer_reputations() {
+	let reputations: Vec<PeerReputation> =
+		api(None).call("system_peerReputations", EmptyParams::new()).await.unwrap();
+	assert_eq!(reputations.len(), 1);
+	assert_eq!(reputations[0].banned_for, Some(60));
+	assert_eq!(reputations[0].recent_reports[0].reason, "Banned manually");
+}
+
+#[tokio::test]
+async fn system_network_ban_peer() {
+	let _good_peer: () = api(None)
+		.call("system_banPeer", ("QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV", 60))
+		.await
+		.expect("call with good peer id works");
+	let _good_ip: () = api(None)
+		.call("system_banPeer", ("198.51.100.19", 60))
+		.await
+		.expect("call with good ip address works");
+	let _unban: () = api(None)
+		.call("system_unbanPeer", ["198.51.100.19"])
+		.await
+		.expect("call with good ip address works");
+
+	assert_matches!(
+		api(None).call::<_, ()>("system_banPeer", ("/ip4/198.51.100.19", 60)).await,
+		Err(RpcError::Call(CallError::Custom(err))) if err.message().contains("neither a peer ID nor an IP address")
+	);
+
+	let bans: Vec<Ban> = api(None).call("system_bans", EmptyParams::new()).await.unwrap();
+	assert_eq!(bans, vec![Ban { target: "198.51.100.19".into(), expires_in: 60 }]);
+}
+
 #[test]
 fn test_add_reset_log_filter() {
 	const EXPECTED_BEFORE_ADD: &'static str = "EXPECTED_BEFORE_ADD";
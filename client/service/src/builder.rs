@@ -24,9 +24,10 @@ use crate::{
 	metrics::MetricsService,
 	start_rpc_servers, RpcHandlers, SpawnTaskHandle, TaskManager, TransactionPoolAdapter,
 };
+use codec::{DecodeAll, Encode};
 use futures::{channel::oneshot, future::ready, FutureExt, StreamExt};
 use jsonrpsee::RpcModule;
-use log::info;
+use log::{debug, info, warn};
 use prometheus_endpoint::Registry;
 use sc_chain_spec::get_extension;
 use sc_client_api::{
@@ -35,12 +36,15 @@ use sc_client_api::{
 };
 use sc_client_db::{Backend, DatabaseSettings};
 use sc_consensus::import_queue::ImportQueue;
-use sc_executor::RuntimeVersionOf;
-use sc_keystore::LocalKeystore;
+use sc_executor::{
+	NativeElseWasmExecutor, NativeExecutionDispatch, RuntimeVersionOf, WasmExecutionMethod,
+	WasmExecutor,
+};
+use sc_keystore::{LocalKeystore, RemoteKeystore};
 use sc_network::{bitswap::Bitswap, config::SyncMode, NetworkService};
 use sc_network_common::{
 	service::{NetworkStateInfo, NetworkStatusProvider, NetworkTransaction},
-	sync::warp::WarpSyncProvider,
+	sync::warp::{Checkpoint, WarpSyncProvider},
 };
 use sc_network_light::light_client_requests::handler::LightClientRequestHandler;
 use sc_network_sync::{
@@ -68,10 +72,14 @@ use sp_core::traits::{CodeExecutor, SpawnNamed};
 use sp_keystore::{CryptoStore, SyncCryptoStore, SyncCryptoStorePtr};
 use sp_runtime::{
 	generic::BlockId,
-	traits::{Block as BlockT, BlockIdTo, NumberFor, Zero},
+	traits::{Block as BlockT, BlockIdTo, Hash as HashT, HashFor, NumberFor, Zero},
 	BuildStorage,
 };
-use std::{str::FromStr, sync::Arc, time::SystemTime};
+use std::{
+	str::FromStr,
+	sync::Arc,
+	time::{Instant, SystemTime},
+};
 
 /// Full client type.
 pub type TFullClient<TBl, TRtApi, TExec> =
@@ -113,13 +121,18 @@ pub struct KeystoreContainer {
 impl KeystoreContainer {
 	/// Construct KeystoreContainer
 	pub fn new(config: &KeystoreConfig) -> Result<Self, Error> {
-		let keystore = Arc::new(match config {
+		let (keystore, remote) = match config {
 			KeystoreConfig::Path { path, password } =>
-				LocalKeystore::open(path.clone(), password.clone())?,
-			KeystoreConfig::InMemory => LocalKeystore::in_memory(),
-		});
+				(LocalKeystore::open(path.clone(), password.clone())?, None),
+			KeystoreConfig::InMemory => (LocalKeystore::in_memory(), None),
+			KeystoreConfig::Remote(remote) => (
+				LocalKeystore::in_memory(),
+				Some(Box::new(Arc::new(RemoteKeystore::open(remote.clone())?))
+					as Box<dyn AsCryptoStoreRef>),
+			),
+		};
 
-		Ok(Self { remote: Default::default(), local: keystore })
+		Ok(Self { remote, local: Arc::new(keystore) })
 	}
 
 	/// Set the remote keystore.
@@ -162,7 +175,10 @@ impl KeystoreContainer {
 	/// implementation, like a remote keystore for example. Only use this if you a certain that you
 	/// require it!
 	pub fn local_keystore(&self) -> Option<Arc<LocalKeystore>> {
-		Some(self.local.clone())
+		match self.remote {
+			Some(_) => None,
+			None => Some(self.local.clone()),
+		}
 	}
 }
 
@@ -179,6 +195,25 @@ where
 	new_full_parts(config, telemetry, executor).map(|parts| parts.0)
 }
 
+/// Creates a [`NativeElseWasmExecutor`] according to the given [`Configuration`].
+///
+/// Compiled runtimes are cached in the `wasm-cache` directory of the base path. They are compiled
+/// with fuel metering if [`Configuration::fuel_limits`] limits any call, so that the limits are
+/// enforced.
+pub fn new_native_or_wasm_executor<D: NativeExecutionDispatch>(
+	config: &Configuration,
+) -> NativeElseWasmExecutor<D> {
+	let wasm_executor = WasmExecutor::new(
+		config.wasm_method,
+		config.default_heap_pages,
+		config.max_runtime_instances,
+		config.base_path.as_ref().map(|base_path| base_path.path().join("wasm-cache")),
+		config.runtime_cache_size,
+	)
+	.with_fuel_metering(config.fuel_limits.is_enabled());
+	NativeElseWasmExecutor::new_with_wasm_executor(wasm_executor)
+}
+
 /// Create the initial parts of a full node.
 pub fn new_full_parts<TBl, TRtApi, TExec>(
 	config: &Configuration,
@@ -189,6 +224,29 @@ where
 	TBl: BlockT,
 	TExec: CodeExecutor + RuntimeVersionOf + Clone,
 {
+	// Only runtimes compiled with fuel metering enforce the limits. This is checked here rather
+	// than when a runtime is instantiated, as custom executors may not compile them with it.
+	if config.fuel_limits.is_enabled() {
+		match config.wasm_method {
+			WasmExecutionMethod::Interpreted =>
+				return Err(Error::Other(
+					"Fuel limits are only supported by the compiled wasm execution method".into(),
+				)),
+			#[cfg(feature = "wasmtime")]
+			WasmExecutionMethod::Compiled {
+				instantiation_strategy:
+					sc_executor::WasmtimeInstantiationStrategy::LegacyInstanceReuse,
+			} =>
+				return Err(Error::Other(
+					"Fuel limits are not supported by the legacy instance reuse instantiation \
+					 strategy"
+						.into(),
+				)),
+			#[cfg(feature = "wasmtime")]
+			WasmExecutionMethod::Compiled { .. } => {},
+		}
+	}
+
 	let keystore_container = KeystoreContainer::new(&config.keystore)?;
 
 	let task_manager = {
@@ -219,7 +277,8 @@ where
 			config.execution_strategies.clone(),
 			Some(keystore_container.sync_keystore()),
 			sc_offchain::OffchainDb::factory_from_backend(&*backend),
-		);
+		)
+		.with_fuel_limits(config.fuel_limits);
 
 		let wasm_runtime_substitutes = config
 			.chain_spec
@@ -504,6 +563,12 @@ where
 			MetricsService::new(telemetry)
 		};
 
+	spawn_handle.spawn(
+		"runtime-upgrade-warmup",
+		None,
+		warm_up_runtime_upgrades::<_, _, TBackend>(client.clone(), spawn_handle.clone()),
+	);
+
 	// Periodically updated metrics and telemetry updates.
 	spawn_handle.spawn(
 		"telemetry-periodic-send",
@@ -548,6 +613,88 @@ where
 	Ok(rpc_handlers)
 }
 
+/// Compiles the runtime code staged by the imported blocks, so that it is ready by the time the
+/// first block using it is built or imported. Otherwise compiling the new runtime would delay that
+/// block, possibly making it miss its slot.
+///
+/// Besides a new `:code`, this finds the code of runtime upgrades that are scheduled or noted as a
+/// preimage, see [`sc_executor::find_runtime_code`], so that they are compiled ahead of their
+/// enactment. The executor keeps the compiled runtime keyed by its code hash, also on disk when it
+/// has a cache path.
+async fn warm_up_runtime_upgrades<Block, Client, Backend>(
+	client: Arc<Client>,
+	spawn_handle: SpawnTaskHandle,
+) where
+	Block: BlockT,
+	Client: BlockchainEvents<Block>
+		+ StorageProvider<Block, Backend>
+		+ ExecutorProvider<Block>
+		+ Send
+		+ Sync
+		+ 'static,
+	Backend: sc_client_api::backend::Backend<Block>,
+{
+	// Storage changes are not notified while catching up, when the next block is imported right
+	// away anyway.
+	let mut storage_changes = match client.storage_changes_notification_stream(None, None) {
+		Ok(storage_changes) => storage_changes,
+		Err(err) => {
+			warn!("Failed to watch for runtime upgrades: {}", err);
+			return
+		},
+	};
+	let heap_pages_key =
+		sp_core::storage::StorageKey(sp_core::storage::well_known_keys::HEAP_PAGES.to_vec());
+
+	while let Some(notification) = storage_changes.next().await {
+		let block = notification.block;
+		let staged_code = notification
+			.changes
+			.iter()
+			.filter(|(child_key, _, _)| child_key.is_none())
+			.filter_map(|(_, _, value)| sc_executor::find_runtime_code(&value?.0).map(Vec::from))
+			.collect::<Vec<_>>();
+		if staged_code.is_empty() {
+			continue
+		}
+
+		// The upgrade is expected to keep the heap pages, like most do.
+		let heap_pages = client
+			.storage(&BlockId::Hash(block), &heap_pages_key)
+			.ok()
+			.flatten()
+			.and_then(|heap_pages| u64::decode_all(&mut &heap_pages.0[..]).ok());
+		let client = client.clone();
+		spawn_handle.spawn_blocking("runtime-upgrade-compilation", None, async move {
+			for code in staged_code {
+				let started = Instant::now();
+				let hash = HashFor::<Block>::hash(&code);
+				let runtime_code = sp_core::traits::RuntimeCode {
+					hash: hash.encode(),
+					code_fetcher: &sp_core::traits::WrappedRuntimeCode(code.into()),
+					heap_pages,
+				};
+				match client.executor().runtime_version(
+					&mut sp_state_machine::BasicExternalities::new_empty(),
+					&runtime_code,
+				) {
+					Ok(version) => debug!(
+						"Prepared runtime {} with code hash {:?} staged by block {} in {} ms",
+						version,
+						hash,
+						block,
+						started.elapsed().as_millis(),
+					),
+					Err(err) => warn!(
+						"Failed to prepare runtime with code hash {:?} staged by block {}: {}",
+						hash, block, err,
+					),
+				}
+			}
+		});
+	}
+}
+
 async fn transaction_notifications<Block, ExPool, Network>(
 	transaction_pool: Arc<ExPool>,
 	network: Network,
@@ -676,6 +823,7 @@ where
 		deny_unsafe,
 		task_executor.clone(),
 	)
+	.with_key_rotation(config.key_rotation.clone())
 	.into_rpc();
 
 	let system = sc_rpc::system::System::new(system_info, system_rpc_tx, deny_unsafe).into_rpc();
@@ -828,6 +976,33 @@ where
 		protocol_config
 	};
 
+	let checkpoints = config
+		.chain_spec
+		.checkpoints()
+		.iter()
+		.map(|checkpoint| {
+			let invalid = |what: &str| {
+				Error::Application(Box::from(format!(
+					"Invalid {} of checkpoint #{} in the chain spec",
+					what, checkpoint.number,
+				)))
+			};
+			Ok(Checkpoint {
+				number: NumberFor::<TBl>::try_from(checkpoint.number)
+					.map_err(|_| invalid("number"))?,
+				hash: DecodeAll::decode_all(&mut &checkpoint.hash[..])
+					.map_err(|_| invalid("hash"))?,
+				set_id: checkpoint.set_id,
+				authorities: checkpoint
+					.authorities
+					.iter()
+					.map(|(id, weight)| Ok((DecodeAll::decode_all(&mut &id[..])?, *weight)))
+					.collect::<Result<_, codec::Error>>()
+					.map_err(|_| invalid("authorities"))?,
+			})
+		})
+		.collect::<Result<Vec<_>, Error>>()?;
+
 	let chain_sync = ChainSync::new(
 		match config.network.sync_mode {
 			SyncMode::Full => sc_network_common::sync::SyncMode::Full,
@@ -839,6 +1014,7 @@ where
 		block_announce_validator,
 		config.network.max_parallel_downloads,
 		warp_sync_provider,
+		checkpoints,
 	)?;
 	let network_params = sc_network::config::Params {
 		role: config.role.clone(),
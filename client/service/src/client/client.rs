@@ -41,7 +41,7 @@ use sc_client_api::{
 	execution_extensions::ExecutionExtensions,
 	notifications::{StorageEventStream, StorageNotifications},
 	CallExecutor, ExecutorProvider, KeyIterator, OnFinalityAction, OnImportAction, ProofProvider,
-	UsageProvider,
+	StorageDiff, UsageProvider,
 };
 use sc_consensus::{
 	BlockCheckParams, BlockImportParams, ForkChoiceStrategy, ImportResult, StateAction,
@@ -1510,6 +1510,48 @@ where
 			.child_storage_hash(child_info, &key.0)
 			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
 	}
+
+	fn storage_diff(
+		&self,
+		from: &BlockId<Block>,
+		to: &BlockId<Block>,
+		prefix: Option<&StorageKey>,
+		start_key: Option<&StorageKey>,
+		start_child_key: Option<&StorageKey>,
+		count: usize,
+	) -> sp_blockchain::Result<StorageDiff> {
+		sc_client_api::storage_diff::storage_diff::<HashFor<Block>, _>(
+			&self.state_at(from)?,
+			&self.state_at(to)?,
+			None,
+			prefix,
+			start_key,
+			start_child_key,
+			count,
+		)
+		.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+	}
+
+	fn child_storage_diff(
+		&self,
+		from: &BlockId<Block>,
+		to: &BlockId<Block>,
+		child_info: &ChildInfo,
+		prefix: Option<&StorageKey>,
+		start_key: Option<&StorageKey>,
+		count: usize,
+	) -> sp_blockchain::Result<StorageDiff> {
+		sc_client_api::storage_diff::storage_diff::<HashFor<Block>, _>(
+			&self.state_at(from)?,
+			&self.state_at(to)?,
+			Some(child_info),
+			prefix,
+			start_key,
+			None,
+			count,
+		)
+		.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+	}
 }
 
 impl<B, E, Block, RA> HeaderMetadata<Block> for Client<B, E, Block, RA>
@@ -18,11 +18,15 @@
 
 //! Service configuration.
 
-pub use sc_client_api::execution_extensions::{ExecutionStrategies, ExecutionStrategy};
+pub use sc_client_api::execution_extensions::{ExecutionStrategies, ExecutionStrategy, FuelLimits};
 pub use sc_client_db::{BlocksPruning, Database, DatabaseSource, PruningMode};
 pub use sc_executor::WasmExecutionMethod;
 #[cfg(feature = "wasmtime")]
 pub use sc_executor::WasmtimeInstantiationStrategy;
+pub use sc_keystore::{
+	remote::{Endpoint as RemoteKeystoreEndpoint, TlsConfig as RemoteKeystoreTlsConfig},
+	RemoteKeystoreConfig,
+};
 pub use sc_network::{
 	config::{
 		NetworkConfiguration, NodeKeyConfig, NonDefaultSetConfig, Role, SetConfig, TransportConfig,
@@ -35,11 +39,15 @@ pub use sc_network_common::{
 		IncomingRequest, OutgoingResponse, ProtocolConfig as RequestResponseConfig,
 	},
 };
+pub use sc_rpc::author::KeyRotationConfig;
 
 use prometheus_endpoint::Registry;
 use sc_chain_spec::ChainSpec;
 pub use sc_telemetry::TelemetryEndpoints;
-pub use sc_transaction_pool::Options as TransactionPoolOptions;
+pub use sc_transaction_pool::{
+	JournalOptions as PoolJournalOptions, Options as TransactionPoolOptions, PoolLimit,
+	PoolSenderLimit,
+};
 use sp_authority_permission::PermissionResolverFactory;
 use sp_core::crypto::SecretString;
 use std::{
@@ -68,6 +76,10 @@ pub struct Configuration {
 	pub keystore: KeystoreConfig,
 	/// Remote URI to connect to for async keystore support
 	pub keystore_remote: Option<String>,
+	/// Standby replicas that must import new session keys before this node switches to them.
+	///
+	/// `None` if `author_rotateKeysWithReplicas` is disabled.
+	pub key_rotation: Option<KeyRotationConfig>,
 	/// Configuration for the database.
 	pub database: DatabaseSource,
 	/// Maximum size of internal trie cache in bytes.
@@ -90,6 +102,12 @@ pub struct Configuration {
 	pub wasm_runtime_overrides: Option<PathBuf>,
 	/// Execution strategies.
 	pub execution_strategies: ExecutionStrategies,
+	/// Limits of the fuel that runtime calls may consume.
+	///
+	/// Runtimes have to be compiled with fuel metering for the limits to be enforced, which the
+	/// executors created by [`new_native_or_wasm_executor`](crate::new_native_or_wasm_executor)
+	/// do.
+	pub fuel_limits: FuelLimits,
 	/// RPC over HTTP binding address. `None` if disabled.
 	pub rpc_http: Option<SocketAddr>,
 	/// RPC over Websockets binding address. `None` if disabled.
@@ -128,8 +146,16 @@ pub struct Configuration {
 	pub offchain_worker: OffchainWorkerConfig,
 	/// Enable authoring even when offline.
 	pub force_authoring: bool,
+	/// Build, but neither seal nor announce, blocks in slots without authoring permission.
+	pub shadow_authoring: bool,
+	/// Stop signing with a key once an equivocation committed with it is detected.
+	pub equivocation_circuit_breaker: bool,
 	/// Disable GRANDPA when running in validator mode
 	pub disable_grandpa: bool,
+	/// Path of the policy restricting GRANDPA votes.
+	pub grandpa_voting_policy: Option<PathBuf>,
+	/// Finality lag, in blocks, from which GRANDPA reports a finality stall.
+	pub grandpa_stall_threshold: Option<u32>,
 	/// Development key seed.
 	///
 	/// When running in development mode, the seed will be used to generate authority keys by the
@@ -178,6 +204,8 @@ pub enum KeystoreConfig {
 	},
 	/// In-memory keystore. Recommended for in-browser nodes.
 	InMemory,
+	/// Keys are kept by a remote signer, all operations are forwarded to it.
+	Remote(RemoteKeystoreConfig),
 }
 
 impl KeystoreConfig {
@@ -185,7 +213,7 @@ impl KeystoreConfig {
 	pub fn path(&self) -> Option<&Path> {
 		match self {
 			Self::Path { path, .. } => Some(path),
-			Self::InMemory => None,
+			Self::InMemory | Self::Remote(_) => None,
 		}
 	}
 }
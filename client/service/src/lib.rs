@@ -34,7 +34,7 @@ mod client;
 mod metrics;
 mod task_manager;
 
-use std::{collections::HashMap, net::SocketAddr};
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
 
 use codec::{Decode, Encode};
 use futures::{channel::mpsc, FutureExt, StreamExt};
@@ -55,8 +55,9 @@ use sp_runtime::{
 pub use self::{
 	builder::{
 		build_network, build_offchain_workers, new_client, new_db_backend, new_full_client,
-		new_full_parts, spawn_tasks, BuildNetworkParams, KeystoreContainer, NetworkStarter,
-		SpawnTasksParams, TFullBackend, TFullCallExecutor, TFullClient,
+		new_full_parts, new_native_or_wasm_executor, spawn_tasks, BuildNetworkParams,
+		KeystoreContainer, NetworkStarter, SpawnTasksParams, TFullBackend, TFullCallExecutor,
+		TFullClient,
 	},
 	client::{ClientConfig, LocalCallExecutor},
 	error::Error,
@@ -260,6 +261,55 @@ async fn build_network_future<
 
 						let _ = sender.send(reserved_peers);
 					}
+					sc_rpc::system::Request::NetworkPeerReputations(sender) => {
+						let reputations = network.peer_reputations().into_iter().map(|p|
+							sc_rpc::system::PeerReputation {
+								peer_id: p.peer_id.to_base58(),
+								reputation: p.reputation,
+								banned_for: p.banned_for.map(|d| d.as_secs()),
+								recent_reports: p.recent_reports.into_iter().map(|r|
+									sc_rpc::system::ReputationReport {
+										value: r.value,
+										reason: r.reason.into(),
+									}
+								).collect(),
+							}
+						).collect();
+
+						let _ = sender.send(reputations);
+					}
+					sc_rpc::system::Request::NetworkBanPeer(target, duration, sender) => {
+						let _ = match target.parse::<sc_network::BanTarget>() {
+							Ok(target) => {
+								network.ban(target, Duration::from_secs(duration));
+								sender.send(Ok(()))
+							}
+							Err(e) => sender.send(Err(
+								sc_rpc::system::error::Error::MalformattedPeerArg(e),
+							)),
+						};
+					}
+					sc_rpc::system::Request::NetworkUnbanPeer(target, sender) => {
+						let _ = match target.parse::<sc_network::BanTarget>() {
+							Ok(target) => {
+								network.unban(target);
+								sender.send(Ok(()))
+							}
+							Err(e) => sender.send(Err(
+								sc_rpc::system::error::Error::MalformattedPeerArg(e),
+							)),
+						};
+					}
+					sc_rpc::system::Request::NetworkBans(sender) => {
+						let bans = network.bans().into_iter().map(|(target, remaining)|
+							sc_rpc::system::Ban {
+								target: target.to_string(),
+								expires_in: remaining.as_secs(),
+							}
+						).collect();
+
+						let _ = sender.send(bans);
+					}
 					sc_rpc::system::Request::NodeRoles(sender) => {
 						use sc_rpc::system::NodeRole;
 
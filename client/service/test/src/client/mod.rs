@@ -25,7 +25,8 @@ use sc_client_api::{
 };
 use sc_client_db::{Backend, BlocksPruning, DatabaseSettings, DatabaseSource, PruningMode};
 use sc_consensus::{
-	BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult,
+	BlockCheckParams, BlockImport, BlockImportParams, CheckpointRule, ForkChoiceBlockImport,
+	ForkChoiceSelectChain, ForkChoiceStrategy, ImportResult,
 };
 use sc_service::client::{new_in_mem, Client, LocalCallExecutor};
 use sp_api::ProvideRuntimeApi;
@@ -838,6 +839,85 @@ fn best_containing_on_longest_chain_with_max_depth_higher_than_best() {
 	);
 }
 
+#[test]
+fn fork_choice_rule_is_applied_at_import_and_by_select_chain() {
+	// block tree:
+	// G -> A1 -> A2 -> A3
+	//      A1 -> B2
+	// where B2 is a checkpoint, so the shorter B fork is preferred.
+	let (client, backend) = TestClientBuilder::new().build_with_backend();
+	let client = Arc::new(client);
+
+	let import_with_fork_choice = |block_import: &mut ForkChoiceBlockImport<_, _, _, _>,
+	                               block: Block| {
+		let (header, extrinsics) = block.deconstruct();
+		let mut import = BlockImportParams::new(BlockOrigin::Own, header);
+		import.body = Some(extrinsics);
+		import.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+		block_on(block_import.import_block(import, Default::default())).unwrap();
+	};
+
+	// G -> A1
+	let a1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	block_on(client.import(BlockOrigin::Own, a1.clone())).unwrap();
+
+	// A1 -> B2
+	let mut builder = client
+		.new_block_at(&BlockId::Hash(a1.hash()), Default::default(), false)
+		.unwrap();
+	// this push is required as otherwise B2 has the same hash as A2
+	builder
+		.push_transfer(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 41,
+			nonce: 0,
+		})
+		.unwrap();
+	let b2 = builder.build().unwrap().block;
+
+	let rule = Arc::new(CheckpointRule::new(client.clone(), vec![(2, b2.hash())]));
+	let mut block_import = ForkChoiceBlockImport::new(client.clone(), client.clone(), rule.clone());
+	let select_chain = ForkChoiceSelectChain::new(backend, rule);
+
+	// A1 -> A2 -> A3, which the rule doesn't prefer over A1.
+	let a2 = client
+		.new_block_at(&BlockId::Hash(a1.hash()), Default::default(), false)
+		.unwrap()
+		.build()
+		.unwrap()
+		.block;
+	import_with_fork_choice(&mut block_import, a2.clone());
+	let a3 = client
+		.new_block_at(&BlockId::Hash(a2.hash()), Default::default(), false)
+		.unwrap()
+		.build()
+		.unwrap()
+		.block;
+	import_with_fork_choice(&mut block_import, a3.clone());
+	assert_eq!(client.chain_info().best_hash, a1.hash());
+
+	// the checkpoint takes over, even though its fork is shorter.
+	import_with_fork_choice(&mut block_import, b2.clone());
+	assert_eq!(client.chain_info().best_hash, b2.hash());
+	assert_eq!(block_on(select_chain.best_chain()).unwrap().hash(), b2.hash());
+
+	let genesis_hash = client.chain_info().genesis_hash;
+	let finality_target =
+		|target_hash, number| block_on(select_chain.finality_target(target_hash, number)).unwrap();
+
+	assert_eq!(b2.hash(), finality_target(genesis_hash, None));
+	assert_eq!(b2.hash(), finality_target(a1.hash(), None));
+	assert_eq!(a3.hash(), finality_target(a2.hash(), None));
+
+	// the target is clamped to `max_number`, along the preferred fork.
+	assert_eq!(a1.hash(), finality_target(genesis_hash, Some(1)));
+	assert_eq!(genesis_hash, finality_target(genesis_hash, Some(0)));
+	assert_eq!(a2.hash(), finality_target(a2.hash(), Some(1)));
+	assert_eq!(a2.hash(), finality_target(a2.hash(), Some(2)));
+	assert_eq!(a3.hash(), finality_target(a2.hash(), Some(3)));
+}
+
 #[test]
 fn import_with_justification() {
 	// block tree:
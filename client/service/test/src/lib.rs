@@ -234,6 +234,7 @@ fn node_config<
 		transaction_pool: Default::default(),
 		network: network_config,
 		keystore_remote: Default::default(),
+		key_rotation: None,
 		keystore: KeystoreConfig::Path { path: root.join("key"), password: None },
 		database: DatabaseSource::RocksDb { path: root.join("db"), cache_size: 128 },
 		trie_cache_maximum_size: Some(16 * 1024 * 1024),
@@ -243,6 +244,7 @@ fn node_config<
 		wasm_method: sc_service::config::WasmExecutionMethod::Interpreted,
 		wasm_runtime_overrides: Default::default(),
 		execution_strategies: Default::default(),
+		fuel_limits: Default::default(),
 		rpc_http: None,
 		rpc_ipc: None,
 		rpc_ws: None,
@@ -260,7 +262,11 @@ fn node_config<
 		default_heap_pages: None,
 		offchain_worker: Default::default(),
 		force_authoring: false,
+		shadow_authoring: false,
+		equivocation_circuit_breaker: false,
 		disable_grandpa: false,
+		grandpa_voting_policy: None,
+		grandpa_stall_threshold: None,
 		dev_key_seed: key_seed,
 		tracing_targets: None,
 		tracing_receiver: Default::default(),
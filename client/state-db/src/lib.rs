@@ -286,6 +286,44 @@ fn to_meta_key<S: Codec>(suffix: &[u8], data: &S) -> Vec<u8> {
 	buffer
 }
 
+/// A node key referenced by a state-db journal record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalKey<'a> {
+	/// Database key of the node.
+	pub key: &'a [u8],
+	/// Number of the block the record belongs to.
+	pub block: u64,
+	/// Whether the block inserted the node, or deleted it.
+	pub inserted: bool,
+}
+
+/// Rewrite the node keys referenced by a state-db meta entry.
+///
+/// Journal records store database keys of trie nodes, which need to be rewritten when the
+/// state is moved to a database that keys the nodes differently. `f` returns the keys replacing
+/// the given one: a node may be stored under several keys by one database and under a single key
+/// by the other. Meta entries that are not journal records are returned as is.
+pub fn map_journal_keys<BlockHash: Hash>(
+	meta_key: &[u8],
+	value: DBValue,
+	mut f: impl FnMut(JournalKey) -> Vec<Vec<u8>>,
+) -> Result<DBValue, codec::Error> {
+	noncanonical::map_journal_keys::<BlockHash>(meta_key, &value, &mut f)
+		.or_else(|| pruning::map_journal_keys::<BlockHash>(meta_key, &value, &mut f))
+		.unwrap_or(Ok(value))
+}
+
+/// The trie nodes inserted by the block of a non-canonical journal record, with their database
+/// keys. Nothing is returned for the other meta entries.
+///
+/// These nodes are only written to the state column once the block is canonicalized.
+pub fn journal_inserted_nodes<BlockHash: Hash>(
+	meta_key: &[u8],
+	value: &[u8],
+) -> Result<Vec<(Vec<u8>, DBValue)>, codec::Error> {
+	noncanonical::journal_inserted_nodes::<BlockHash>(meta_key, value).unwrap_or(Ok(Vec::new()))
+}
+
 pub struct StateDbSync<BlockHash: Hash, Key: Hash, D: MetaDb> {
 	mode: PruningMode,
 	non_canonical: NonCanonicalOverlay<BlockHash, Key>,
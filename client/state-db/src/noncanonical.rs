@@ -22,7 +22,9 @@
 //! All pending changes are kept in memory until next call to `apply_pending` or
 //! `revert_pending`
 
-use super::{to_meta_key, ChangeSet, CommitSet, DBValue, Error, Hash, MetaDb, StateDbError};
+use super::{
+	to_meta_key, ChangeSet, CommitSet, DBValue, Error, Hash, JournalKey, MetaDb, StateDbError,
+};
 use codec::{Decode, Encode};
 use log::trace;
 use std::collections::{hash_map::Entry, HashMap, VecDeque};
@@ -84,6 +86,52 @@ fn to_journal_key(block: u64, index: u64) -> Vec<u8> {
 	to_meta_key(NON_CANONICAL_JOURNAL, &(block, index))
 }
 
+fn is_journal_key(meta_key: &[u8]) -> bool {
+	meta_key.len() == 16 + NON_CANONICAL_JOURNAL.len() && meta_key.ends_with(NON_CANONICAL_JOURNAL)
+}
+
+/// Rewrite node keys of a non-canonical journal record. Returns `None` if `meta_key` is not
+/// a non-canonical journal key.
+pub(crate) fn map_journal_keys<BlockHash: Hash>(
+	meta_key: &[u8],
+	value: &[u8],
+	f: &mut impl FnMut(JournalKey) -> Vec<Vec<u8>>,
+) -> Option<Result<DBValue, codec::Error>> {
+	if !is_journal_key(meta_key) {
+		return None
+	}
+	let block = u64::decode(&mut &meta_key[..]).ok()?;
+	Some(JournalRecord::<BlockHash, Vec<u8>>::decode(&mut &value[..]).map(|mut record| {
+		record.inserted = record
+			.inserted
+			.into_iter()
+			.flat_map(|(key, value)| {
+				f(JournalKey { key: &key, block, inserted: true })
+					.into_iter()
+					.map(move |key| (key, value.clone()))
+			})
+			.collect();
+		record.deleted = record
+			.deleted
+			.iter()
+			.flat_map(|key| f(JournalKey { key, block, inserted: false }))
+			.collect();
+		record.encode()
+	}))
+}
+
+/// The nodes inserted by a non-canonical journal record. Returns `None` if `meta_key` is not a
+/// non-canonical journal key.
+pub(crate) fn journal_inserted_nodes<BlockHash: Hash>(
+	meta_key: &[u8],
+	value: &[u8],
+) -> Option<Result<Vec<(Vec<u8>, DBValue)>, codec::Error>> {
+	if !is_journal_key(meta_key) {
+		return None
+	}
+	Some(JournalRecord::<BlockHash, Vec<u8>>::decode(&mut &value[..]).map(|record| record.inserted))
+}
+
 #[cfg_attr(test, derive(PartialEq, Debug))]
 #[derive(parity_util_mem_derive::MallocSizeOf)]
 struct BlockOverlay<BlockHash: Hash, Key: Hash> {
@@ -635,11 +683,15 @@ impl<BlockHash: Hash, Key: Hash> NonCanonicalOverlay<BlockHash, Key> {
 
 #[cfg(test)]
 mod tests {
-	use super::{to_journal_key, NonCanonicalOverlay};
+	use super::{
+		journal_inserted_nodes, map_journal_keys, to_journal_key, JournalRecord,
+		NonCanonicalOverlay,
+	};
 	use crate::{
 		test::{make_changeset, make_db},
-		ChangeSet, CommitSet, MetaDb, StateDbError,
+		ChangeSet, CommitSet, JournalKey, MetaDb, StateDbError,
 	};
+	use codec::{Decode, Encode};
 	use sp_core::H256;
 
 	fn contains(overlay: &NonCanonicalOverlay<H256, H256>, key: u64) -> bool {
@@ -1210,4 +1262,49 @@ mod tests {
 		db.commit(&overlay.remove(&h2).unwrap());
 		assert!(!contains(&overlay, 2));
 	}
+
+	#[test]
+	fn maps_journal_record_keys() {
+		let record = JournalRecord::<H256, Vec<u8>> {
+			hash: H256::from_low_u64_be(1),
+			parent_hash: H256::from_low_u64_be(0),
+			inserted: vec![(b"prefix:a".to_vec(), b"value".to_vec())],
+			deleted: vec![b"prefix:b".to_vec()],
+		};
+		let strip = &mut |key: JournalKey| vec![key.key[7..].to_vec()];
+
+		let mapped = map_journal_keys::<H256>(&to_journal_key(1, 0), &record.encode(), strip)
+			.unwrap()
+			.unwrap();
+		let mapped = JournalRecord::<H256, Vec<u8>>::decode(&mut &mapped[..]).unwrap();
+		assert_eq!(mapped.inserted, vec![(b"a".to_vec(), b"value".to_vec())]);
+		assert_eq!(mapped.deleted, vec![b"b".to_vec()]);
+
+		// A key can be replaced by several keys, or dropped.
+		let prefix = &mut |key: JournalKey| {
+			assert_eq!(key.block, 1);
+			match key.key {
+				b"prefix:a" if key.inserted => vec![b"x:a".to_vec(), b"y:a".to_vec()],
+				_ => Vec::new(),
+			}
+		};
+		let mapped = map_journal_keys::<H256>(&to_journal_key(1, 0), &record.encode(), prefix)
+			.unwrap()
+			.unwrap();
+		let mapped = JournalRecord::<H256, Vec<u8>>::decode(&mut &mapped[..]).unwrap();
+		assert_eq!(
+			mapped.inserted,
+			vec![(b"x:a".to_vec(), b"value".to_vec()), (b"y:a".to_vec(), b"value".to_vec())],
+		);
+		assert!(mapped.deleted.is_empty());
+
+		assert_eq!(
+			journal_inserted_nodes::<H256>(&to_journal_key(1, 0), &record.encode())
+				.unwrap()
+				.unwrap(),
+			record.inserted,
+		);
+		assert!(map_journal_keys::<H256>(b"last_canonical", &[], strip).is_none());
+		assert!(journal_inserted_nodes::<H256>(b"last_canonical", &[]).is_none());
+	}
 }
@@ -25,8 +25,8 @@
 //! The changes are journaled in the DB.
 
 use crate::{
-	noncanonical::LAST_CANONICAL, to_meta_key, CommitSet, Error, Hash, MetaDb, StateDbError,
-	DEFAULT_MAX_BLOCK_CONSTRAINT,
+	noncanonical::LAST_CANONICAL, to_meta_key, CommitSet, Error, Hash, JournalKey, MetaDb,
+	StateDbError, DEFAULT_MAX_BLOCK_CONSTRAINT,
 };
 use codec::{Decode, Encode};
 use log::{error, trace, warn};
@@ -366,6 +366,32 @@ fn to_journal_key(block: u64) -> Vec<u8> {
 	to_meta_key(PRUNING_JOURNAL, &block)
 }
 
+/// Rewrite node keys of a pruning journal record. Returns `None` if `meta_key` is not
+/// a pruning journal key.
+pub(crate) fn map_journal_keys<BlockHash: Hash>(
+	meta_key: &[u8],
+	value: &[u8],
+	f: &mut impl FnMut(JournalKey) -> Vec<Vec<u8>>,
+) -> Option<Result<Vec<u8>, codec::Error>> {
+	if meta_key.len() != 8 + PRUNING_JOURNAL.len() || !meta_key.ends_with(PRUNING_JOURNAL) {
+		return None
+	}
+	let block = u64::decode(&mut &meta_key[..]).ok()?;
+	Some(JournalRecord::<BlockHash, Vec<u8>>::decode(&mut &value[..]).map(|mut record| {
+		record.inserted = record
+			.inserted
+			.iter()
+			.flat_map(|key| f(JournalKey { key, block, inserted: true }))
+			.collect();
+		record.deleted = record
+			.deleted
+			.iter()
+			.flat_map(|key| f(JournalKey { key, block, inserted: false }))
+			.collect();
+		record.encode()
+	}))
+}
+
 /// The result return by `RefWindow::have_block`
 #[derive(Debug, PartialEq, Eq)]
 pub enum HaveBlock {
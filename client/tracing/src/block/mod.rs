@@ -16,6 +16,8 @@
 
 //! Utilities for tracing block execution
 
+mod profile;
+
 use std::{
 	collections::HashMap,
 	sync::{
@@ -38,7 +40,9 @@ use sc_rpc_server::RPC_MAX_PAYLOAD_DEFAULT;
 use sp_api::{Core, Encode, Metadata, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
 use sp_core::hexdisplay::HexDisplay;
-use sp_rpc::tracing::{BlockTrace, Span, TraceBlockResponse, TraceError};
+use sp_rpc::tracing::{
+	BlockProfile, BlockTrace, ProfileBlockResponse, Span, TraceBlockResponse, TraceError,
+};
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Header},
@@ -62,6 +66,8 @@ const AVG_SPAN: usize = 100 * 8;
 const BASE_PAYLOAD: usize = 100;
 // Default to only pallet, frame support and state related traces
 const DEFAULT_TARGETS: &str = "pallet,frame,state";
+// Default to pallet and frame support spans, along with the host functions they call.
+const DEFAULT_PROFILE_TARGETS: &str = "pallet,frame,sp_io";
 const TRACE_TARGET: &str = "block_trace";
 // The name of a field required for all events.
 const REQUIRED_EVENT_FIELD: &str = "method";
@@ -216,25 +222,7 @@ where
 	/// prefixes in `Self::storage_keys`.
 	pub fn trace_block(&self) -> TraceBlockResult<TraceBlockResponse> {
 		tracing::debug!(target: "state_tracing", "Tracing block: {}", self.block);
-		// Prepare the block
-		let id = BlockId::Hash(self.block);
-		let mut header = self
-			.client
-			.header(id)
-			.map_err(Error::InvalidBlockId)?
-			.ok_or_else(|| Error::MissingBlockComponent("Header not found".to_string()))?;
-		let extrinsics = self
-			.client
-			.block_body(&id)
-			.map_err(Error::InvalidBlockId)?
-			.ok_or_else(|| Error::MissingBlockComponent("Extrinsics not found".to_string()))?;
-		tracing::debug!(target: "state_tracing", "Found {} extrinsics", extrinsics.len());
-		let parent_hash = *header.parent_hash();
-		let parent_id = BlockId::Hash(parent_hash);
-		// Remove all `Seal`s as they are added by the consensus engines after building the block.
-		// On import they are normally removed by the consensus engine.
-		header.digest_mut().logs.retain(|d| d.as_seal().is_none());
-		let block = Block::new(header, extrinsics);
+		let (block, id, parent_id) = self.prepare_block()?;
 
 		let targets = if let Some(t) = &self.targets { t } else { DEFAULT_TARGETS };
 		let block_subscriber = BlockSubscriber::new(targets);
@@ -310,6 +298,77 @@ where
 
 		Ok(response)
 	}
+
+	/// Execute block, measuring the time spent in the spans belonging to `Self::targets`, and
+	/// return it folded into stacks.
+	///
+	/// Host functions accessing the storage get an additional frame for the storage item they
+	/// accessed, so the time is attributed per pallet call, host function and storage item.
+	pub fn profile_block(&self) -> TraceBlockResult<ProfileBlockResponse> {
+		tracing::debug!(target: "state_tracing", "Profiling block: {}", self.block);
+		let (block, id, parent_id) = self.prepare_block()?;
+
+		let targets = if let Some(t) = &self.targets { t } else { DEFAULT_PROFILE_TARGETS };
+		let dispatch = Dispatch::new(profile::ProfileSubscriber::new(targets));
+
+		if let Err(e) = dispatcher::with_default(&dispatch, || {
+			let span = tracing::info_span!(target: TRACE_TARGET, "profile_block");
+			let _enter = span.enter();
+			self.client.runtime_api().execute_block(&parent_id, block)
+		}) {
+			return Err(Error::Dispatch(format!(
+				"Failed to collect timings and execute block: {}",
+				e
+			)))
+		}
+
+		let profile_subscriber =
+			dispatch.downcast_ref::<profile::ProfileSubscriber>().ok_or_else(|| {
+				Error::Dispatch(
+					"Cannot downcast Dispatch to ProfileSubscriber after profiling block"
+						.to_string(),
+				)
+			})?;
+		let folded_stacks = profile_subscriber.folded_stacks(targets);
+
+		let response = if BASE_PAYLOAD + folded_stacks.len() > self.rpc_max_payload {
+			ProfileBlockResponse::TraceError(TraceError {
+				error: "Payload likely exceeds max payload size of RPC server.".to_string(),
+			})
+		} else {
+			ProfileBlockResponse::BlockProfile(BlockProfile {
+				block_hash: block_id_as_string(id),
+				parent_hash: block_id_as_string(parent_id),
+				tracing_targets: targets.to_string(),
+				folded_stacks,
+			})
+		};
+
+		Ok(response)
+	}
+
+	/// Fetch the block and remove its seals, returning it along with its id and the id of its
+	/// parent.
+	fn prepare_block(&self) -> TraceBlockResult<(Block, BlockId<Block>, BlockId<Block>)> {
+		let id = BlockId::Hash(self.block);
+		let mut header = self
+			.client
+			.header(id)
+			.map_err(Error::InvalidBlockId)?
+			.ok_or_else(|| Error::MissingBlockComponent("Header not found".to_string()))?;
+		let extrinsics = self
+			.client
+			.block_body(&id)
+			.map_err(Error::InvalidBlockId)?
+			.ok_or_else(|| Error::MissingBlockComponent("Extrinsics not found".to_string()))?;
+		tracing::debug!(target: "state_tracing", "Found {} extrinsics", extrinsics.len());
+		let parent_hash = *header.parent_hash();
+		let parent_id = BlockId::Hash(parent_hash);
+		// Remove all `Seal`s as they are added by the consensus engines after building the block.
+		// On import they are normally removed by the consensus engine.
+		header.digest_mut().logs.retain(|d| d.as_seal().is_none());
+		Ok((Block::new(header, extrinsics), id, parent_id))
+	}
 }
 
 fn event_values_filter(event: &TraceEvent, filter_kind: &str, values: &str) -> bool {
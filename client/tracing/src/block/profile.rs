@@ -0,0 +1,285 @@
+// Copyright 2022 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Aggregated timing of block execution.
+//!
+//! The [`ProfileSubscriber`] measures the time spent in every span while a block is executed and
+//! folds the spans into stacks, in the format expected by flamegraph tools like `inferno` or
+//! `flamegraph.pl`: one line per stack, with its frames separated by `;` followed by the time in
+//! nanoseconds spent in the innermost frame.
+//!
+//! Spans of host functions that access the storage get an extra frame with the accessed storage
+//! item, e.g. `sp_io::storage::get;state::Get 26aa394eea5630e07c48ae0c9558cef7`, so that the time
+//! is attributed per storage item.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt::Write,
+	sync::atomic::{AtomicU64, Ordering},
+	time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tracing::{
+	span::{Attributes, Id, Record},
+	Level, Subscriber,
+};
+
+use crate::Values;
+use sp_tracing::{WASM_NAME_KEY, WASM_TARGET_KEY, WASM_TRACE_IDENTIFIER};
+
+// Target of the events emitted on storage accesses.
+const STATE_TARGET: &str = "state";
+// Number of hex characters of the accessed keys that identify a storage item, the hashes of the
+// pallet and storage item prefixes.
+const STORAGE_ITEM_PREFIX_LEN: usize = 64;
+
+struct ProfileSpan {
+	name: String,
+	target: String,
+	level: Level,
+	values: Values,
+	/// The span that was entered when this span was entered for the first time.
+	parent: Option<Id>,
+	entered_at: Option<Instant>,
+	/// Total time spent in the span, including its children.
+	total: Duration,
+	/// The storage item accessed within the span, if any.
+	storage_access: Option<String>,
+}
+
+/// Measures the time spent in spans of the given targets.
+pub(super) struct ProfileSubscriber {
+	targets: Vec<(String, Level)>,
+	next_id: AtomicU64,
+	spans: Mutex<HashMap<Id, ProfileSpan>>,
+	stack: Mutex<Vec<Id>>,
+}
+
+impl ProfileSubscriber {
+	pub(super) fn new(targets: &str) -> Self {
+		let mut targets: Vec<_> = targets.split(',').map(crate::parse_target).collect();
+		// Ensure that WASM traces are always enabled
+		// Filtering happens when decoding the actual target / level
+		targets.push((WASM_TRACE_IDENTIFIER.to_owned(), Level::TRACE));
+		// Storage accesses are recorded to label the spans they happen in.
+		targets.push((STATE_TARGET.to_owned(), Level::TRACE));
+		ProfileSubscriber {
+			targets,
+			next_id: AtomicU64::new(1),
+			spans: Mutex::new(HashMap::new()),
+			stack: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Fold the recorded spans into stacks of the spans matching `targets`.
+	///
+	/// The time spent in spans that don't match `targets` is attributed to their closest parent
+	/// that does.
+	pub(super) fn folded_stacks(&self, targets: &str) -> String {
+		let spans = self.spans.lock();
+
+		let mut children_time = HashMap::<&Id, Duration>::new();
+		for span in spans.values() {
+			if let Some(parent) = &span.parent {
+				*children_time.entry(parent).or_default() += span.total;
+			}
+		}
+
+		let mut stacks = BTreeMap::<String, u128>::new();
+		for (id, span) in spans.iter() {
+			let self_time =
+				span.total.saturating_sub(children_time.get(id).copied().unwrap_or_default());
+			if self_time.is_zero() {
+				continue
+			}
+
+			let mut frames = Vec::new();
+			let mut next = Some(id);
+			while let Some(id) = next {
+				let span = match spans.get(id) {
+					Some(span) => span,
+					None => break,
+				};
+				if let Some(frame) = frame(span, targets) {
+					frames.push(frame);
+				}
+				next = span.parent.as_ref();
+			}
+			if frames.is_empty() {
+				continue
+			}
+			frames.reverse();
+
+			let mut stack = frames.join(";");
+			if let Some(storage_access) = &span.storage_access {
+				stack.push(';');
+				stack.push_str(storage_access);
+			}
+			*stacks.entry(stack).or_default() += self_time.as_nanos();
+		}
+
+		let mut folded = String::new();
+		for (stack, nanos) in stacks {
+			let _ = writeln!(folded, "{} {}", stack, nanos);
+		}
+		folded
+	}
+}
+
+/// Returns the frame of `span` in a stack, or `None` if it doesn't match `targets`.
+fn frame(span: &ProfileSpan, targets: &str) -> Option<String> {
+	let (name, target) = if span.name == WASM_TRACE_IDENTIFIER {
+		(
+			span.values.string_values.get(WASM_NAME_KEY).unwrap_or(&span.name),
+			span.values.string_values.get(WASM_TARGET_KEY).unwrap_or(&span.target),
+		)
+	} else {
+		(&span.name, &span.target)
+	};
+	if !super::check_target(targets, target, &span.level) {
+		return None
+	}
+	Some(format!("{}::{}", target, name).replace(';', ":"))
+}
+
+impl Subscriber for ProfileSubscriber {
+	fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+		if !metadata.is_span() && metadata.target() != STATE_TARGET {
+			return false
+		}
+		for (target, level) in &self.targets {
+			if metadata.level() <= level && metadata.target().starts_with(target) {
+				return true
+			}
+		}
+		false
+	}
+
+	fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+		let id = Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed));
+		let mut values = Values::default();
+		attrs.record(&mut values);
+		let span = ProfileSpan {
+			name: attrs.metadata().name().to_owned(),
+			target: attrs.metadata().target().to_owned(),
+			level: *attrs.metadata().level(),
+			values,
+			parent: None,
+			entered_at: None,
+			total: Duration::ZERO,
+			storage_access: None,
+		};
+
+		self.spans.lock().insert(id.clone(), span);
+		id
+	}
+
+	fn record(&self, span: &Id, values: &Record<'_>) {
+		if let Some(span) = self.spans.lock().get_mut(span) {
+			values.record(&mut span.values);
+		}
+	}
+
+	fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+	fn event(&self, event: &tracing::Event<'_>) {
+		let mut values = Values::default();
+		event.record(&mut values);
+		let (method, key) =
+			match (values.string_values.get("method"), values.string_values.get("key")) {
+				(Some(method), Some(key)) => (method, key),
+				_ => return,
+			};
+
+		let stack = self.stack.lock();
+		let mut spans = self.spans.lock();
+		if let Some(span) = stack.last().and_then(|id| spans.get_mut(id)) {
+			if span.storage_access.is_none() {
+				let prefix = &key[..key.len().min(STORAGE_ITEM_PREFIX_LEN)];
+				span.storage_access = Some(format!("{}::{} {}", STATE_TARGET, method, prefix));
+			}
+		}
+	}
+
+	fn enter(&self, id: &Id) {
+		let mut stack = self.stack.lock();
+		if let Some(span) = self.spans.lock().get_mut(id) {
+			if span.parent.is_none() && span.entered_at.is_none() && span.total.is_zero() {
+				span.parent = stack.last().cloned();
+			}
+			span.entered_at = Some(Instant::now());
+		}
+		stack.push(id.clone());
+	}
+
+	fn exit(&self, id: &Id) {
+		let mut stack = self.stack.lock();
+		if let Some(position) = stack.iter().rposition(|entered| entered == id) {
+			stack.remove(position);
+		}
+		if let Some(span) = self.spans.lock().get_mut(id) {
+			if let Some(entered_at) = span.entered_at.take() {
+				span.total += entered_at.elapsed();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tracing::Dispatch;
+
+	#[test]
+	fn spans_are_folded_into_stacks() {
+		let dispatch = Dispatch::new(ProfileSubscriber::new("pallet,sp_io"));
+		tracing::dispatcher::with_default(&dispatch, || {
+			let call = tracing::info_span!(target: "pallet_balances", "transfer");
+			let _call = call.enter();
+			// Not profiled, its time goes to the call.
+			let hidden = tracing::info_span!(target: "frame_support", "hidden");
+			let _hidden = hidden.enter();
+			let get = tracing::trace_span!(target: "sp_io::storage", "get");
+			let _get = get.enter();
+			tracing::trace!(
+				target: "state",
+				method = "Get",
+				key = "26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9aabbcc",
+			);
+			std::thread::sleep(Duration::from_millis(1));
+		});
+
+		let subscriber = dispatch.downcast_ref::<ProfileSubscriber>().unwrap();
+		let folded = subscriber.folded_stacks("pallet,sp_io");
+		let stacks = folded
+			.lines()
+			.map(|line| {
+				let (stack, nanos) = line.rsplit_once(' ').unwrap();
+				(stack, nanos.parse::<u64>().unwrap())
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(stacks.len(), 2, "{}", folded);
+		assert_eq!(stacks[0].0, "pallet_balances::transfer");
+		assert_eq!(
+			stacks[1].0,
+			"pallet_balances::transfer;sp_io::storage::get;\
+			 state::Get 26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9",
+		);
+		assert!(stacks[1].1 >= 1_000_000);
+	}
+}
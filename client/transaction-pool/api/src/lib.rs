@@ -27,7 +27,7 @@ use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Member, NumberFor},
 };
-use std::{collections::HashMap, hash::Hash, pin::Pin, sync::Arc};
+use std::{collections::HashMap, hash::Hash, pin::Pin, sync::Arc, time::Duration};
 
 pub use sp_runtime::transaction_validity::{
 	TransactionLongevity, TransactionPriority, TransactionSource, TransactionTag,
@@ -53,6 +53,39 @@ impl PoolStatus {
 	}
 }
 
+/// Snapshot of a transaction in the pool and its relations to other transactions in the pool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectedTransaction<Hash> {
+	/// Hash of the transaction.
+	pub hash: Hash,
+	/// Whether the transaction is in the ready queue. Otherwise it is in the future queue.
+	pub is_ready: bool,
+	/// Priority of the transaction.
+	pub priority: TransactionPriority,
+	/// Block number until which the transaction is valid.
+	pub valid_till: TransactionLongevity,
+	/// Tags required by the transaction.
+	pub requires: Vec<TransactionTag>,
+	/// Tags provided by the transaction.
+	pub provides: Vec<TransactionTag>,
+	/// Source of the transaction.
+	pub source: TransactionSource,
+	/// Whether the transaction is propagated to other peers.
+	pub propagate: bool,
+	/// Size of the encoded transaction.
+	pub bytes: usize,
+	/// Time since the transaction was imported to the pool.
+	pub time_in_pool: Duration,
+	/// Required tags that are neither provided by the chain nor by a ready transaction.
+	///
+	/// Always empty for ready transactions.
+	pub missing_tags: Vec<TransactionTag>,
+	/// Transactions in the pool that provide tags required by this transaction.
+	pub depends_on: Vec<Hash>,
+	/// Transactions in the pool that require tags provided by this transaction.
+	pub unlocks: Vec<Hash>,
+}
+
 /// Possible transaction status events.
 ///
 /// This events are being emitted by `TransactionPool` watchers,
@@ -254,6 +287,9 @@ pub trait TransactionPool: Send + Sync {
 
 	/// Return specific ready transaction by hash, if there is one.
 	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>>;
+
+	/// Returns a snapshot of all ready and future transactions in the pool.
+	fn inspect(&self) -> Vec<InspectedTransaction<TxHash<Self>>>;
 }
 
 /// An iterator of ready transactions.
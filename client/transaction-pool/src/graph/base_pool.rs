@@ -20,10 +20,16 @@
 //!
 //! For a more full-featured pool, have a look at the `pool` module.
 
-use std::{cmp::Ordering, collections::HashSet, fmt, hash, sync::Arc};
+use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	fmt, hash,
+	sync::Arc,
+	time::Instant,
+};
 
 use log::{debug, trace, warn};
-use sc_transaction_pool_api::{error, InPoolTransaction, PoolStatus};
+use sc_transaction_pool_api::{error, InPoolTransaction, InspectedTransaction, PoolStatus};
 use serde::Serialize;
 use sp_core::hexdisplay::HexDisplay;
 use sp_runtime::{
@@ -218,23 +224,27 @@ pub struct BasePool<Hash: hash::Hash + Eq, Ex> {
 	/// transactions to future in case they were just stuck in verification.
 	recently_pruned: [HashSet<Tag>; RECENTLY_PRUNED_TAGS],
 	recently_pruned_index: usize,
+	/// Usage of the queues by the transactions subject to the external and sender limits.
+	#[ignore_malloc_size_of = "small compared to the transactions"]
+	usage: LimitedUsage,
 }
 
 impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> Default for BasePool<Hash, Ex> {
 	fn default() -> Self {
-		Self::new(false)
+		Self::new(false, None)
 	}
 }
 
 impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash, Ex> {
-	/// Create new pool given reject_future_transactions flag.
-	pub fn new(reject_future_transactions: bool) -> Self {
+	/// Create new pool given reject_future_transactions flag and the limit of the senders, if any.
+	pub fn new(reject_future_transactions: bool, sender_limit: Option<SenderLimit>) -> Self {
 		Self {
 			reject_future_transactions,
 			future: Default::default(),
 			ready: Default::default(),
 			recently_pruned: Default::default(),
 			recently_pruned_index: 0,
+			usage: LimitedUsage { sender_limit, ..Default::default() },
 		}
 	}
 
@@ -287,6 +297,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 			}
 
 			let hash = tx.transaction.hash.clone();
+			self.usage.inserted(&tx.transaction, false);
 			self.future.import(tx);
 			return Ok(Imported::Future { hash })
 		}
@@ -312,15 +323,24 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		// take first transaction from the list
 		while let Some(tx) = to_import.pop() {
 			// find transactions in Future that it unlocks
-			to_import.append(&mut self.future.satisfy_tags(&tx.transaction.provides));
+			let unlocked = self.future.satisfy_tags(&tx.transaction.provides);
+			for unlocked in &unlocked {
+				self.usage.removed(&unlocked.transaction, false);
+			}
+			to_import.extend(unlocked);
 
 			// import this transaction
 			let current_hash = tx.transaction.hash.clone();
+			let transaction = tx.transaction.clone();
 			match self.ready.import(tx) {
 				Ok(mut replaced) => {
 					if !first {
 						promoted.push(current_hash);
 					}
+					self.usage.inserted(&transaction, true);
+					for tx in &replaced {
+						self.usage.removed(tx, true);
+					}
 					// The transactions were removed from the ready pool. We might attempt to
 					// re-import them.
 					removed.append(&mut replaced);
@@ -345,7 +365,9 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		if removed.iter().any(|tx| tx.hash == hash) {
 			// We still need to remove all transactions that we promoted
 			// since they depend on each other and will never get to the best iterator.
-			self.ready.remove_subtree(&promoted);
+			for tx in self.ready.remove_subtree(&promoted) {
+				self.usage.removed(&tx, true);
+			}
 
 			debug!(target: "txpool", "[{:?}] Cycle detected, bailing.", hash);
 			return Err(error::Error::CycleDetected)
@@ -359,6 +381,78 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		self.ready.get()
 	}
 
+	/// Returns a snapshot of all transactions in the pool and their dependencies.
+	///
+	/// Tags are attributed to the ready transaction that provides them, if any, and to a future
+	/// transaction providing them otherwise.
+	pub fn inspect(&self) -> Vec<InspectedTransaction<Hash>> {
+		let now = Instant::now();
+		let ready = self.ready.all();
+
+		let mut providers = HashMap::new();
+		for waiting in self.future.waiting() {
+			for tag in &waiting.transaction.provides {
+				providers.insert(tag, &waiting.transaction.hash);
+			}
+		}
+		for tx in &ready {
+			for tag in &tx.transaction.transaction.provides {
+				providers.insert(tag, &tx.transaction.transaction.hash);
+			}
+		}
+
+		let mut inspected = ready
+			.iter()
+			.map(|tx| (&tx.transaction.transaction, true, tx.imported_at, Vec::new()))
+			.chain(self.future.waiting().map(|waiting| {
+				let missing_tags = waiting
+					.transaction
+					.requires
+					.iter()
+					.filter(|tag| waiting.missing_tags.contains(*tag))
+					.cloned()
+					.collect();
+				(&waiting.transaction, false, waiting.imported_at, missing_tags)
+			}))
+			.map(|(transaction, is_ready, imported_at, missing_tags)| {
+				let mut depends_on = Vec::new();
+				for hash in transaction.requires.iter().filter_map(|tag| providers.get(tag)) {
+					if !depends_on.contains(*hash) {
+						depends_on.push((*hash).clone());
+					}
+				}
+
+				InspectedTransaction {
+					hash: transaction.hash.clone(),
+					is_ready,
+					priority: transaction.priority,
+					valid_till: transaction.valid_till,
+					requires: transaction.requires.clone(),
+					provides: transaction.provides.clone(),
+					source: transaction.source,
+					propagate: transaction.propagate,
+					bytes: transaction.bytes,
+					time_in_pool: now.saturating_duration_since(imported_at),
+					missing_tags,
+					depends_on,
+					unlocks: Vec::new(),
+				}
+			})
+			.collect::<Vec<_>>();
+
+		let mut unlocks = HashMap::<Hash, Vec<Hash>>::new();
+		for tx in &inspected {
+			for dependency in &tx.depends_on {
+				unlocks.entry(dependency.clone()).or_default().push(tx.hash.clone());
+			}
+		}
+		for tx in &mut inspected {
+			tx.unlocks = unlocks.remove(&tx.hash).unwrap_or_default();
+		}
+
+		inspected
+	}
+
 	/// Returns an iterator over future transactions in the pool.
 	pub fn futures(&self) -> impl Iterator<Item = &Transaction<Hash, Ex>> {
 		self.future.all()
@@ -384,56 +478,117 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	///
 	/// Removes and returns worst transactions from the queues and all transactions that depend on
 	/// them. Technically the worst transaction should be evaluated by computing the entire pending
-	/// set. We use a simplified approach to remove transactions with the lowest priority first or
-	/// those that occupy the pool for the longest time in case priority is the same.
+	/// set. We use a simplified approach to remove transactions that are not local and have the
+	/// lowest priority per byte first or those that occupy the pool for the longest time in case
+	/// priority is the same.
 	pub fn enforce_limits(
 		&mut self,
 		ready: &Limit,
 		future: &Limit,
+	) -> Vec<Arc<Transaction<Hash, Ex>>> {
+		self.enforce_limits_of(
+			ready,
+			future,
+			|_| true,
+			|pool| {
+				let status = pool.status();
+				(
+					Usage { count: status.ready, bytes: status.ready_bytes },
+					Usage { count: status.future, bytes: status.future_bytes },
+				)
+			},
+		)
+	}
+
+	/// Makes sure that the transactions that are not local stay within provided limits.
+	///
+	/// Used to keep part of the queues reserved for local transactions. Worst transactions are
+	/// selected in the same way as in [`Self::enforce_limits`].
+	pub fn enforce_external_limits(
+		&mut self,
+		ready: &Limit,
+		future: &Limit,
+	) -> Vec<Arc<Transaction<Hash, Ex>>> {
+		self.enforce_limits_of(
+			ready,
+			future,
+			|tx| tx.source != Source::Local,
+			|pool| (pool.usage.external_ready, pool.usage.external_future),
+		)
+	}
+
+	/// Makes sure that the transactions of the given senders stay within the sender limit the
+	/// pool was created with, if any.
+	///
+	/// The limit applies to the ready and future transactions of a sender combined. Future
+	/// transactions of a sender are removed first, followed by its most recently imported ready
+	/// transactions, so that the ones it depends on are kept. Local transactions are not limited.
+	pub fn enforce_sender_limits(
+		&mut self,
+		senders: &HashSet<Tag>,
 	) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		let mut removed = vec![];
+		let limit = match self.usage.sender_limit.clone() {
+			Some(limit) => limit,
+			None => return removed,
+		};
+
+		for sender in senders {
+			let of_sender = |tx: &Transaction<Hash, Ex>| {
+				tx.source != Source::Local && limit.sender(tx) == Some(&sender[..])
+			};
 
-		while ready.is_exceeded(self.ready.len(), self.ready.bytes()) {
-			// find the worst transaction
-			let worst = self.ready.fold::<TransactionRef<Hash, Ex>, _>(|worst, current| {
-				let transaction = &current.transaction;
-				worst
-					.map(|worst| {
-						// Here we don't use `TransactionRef`'s ordering implementation because
-						// while it prefers priority like need here, it also prefers older
-						// transactions for inclusion purposes and limit enforcement needs to prefer
-						// newer transactions instead and drop the older ones.
-						match worst.transaction.priority.cmp(&transaction.transaction.priority) {
-							Ordering::Less => worst,
-							Ordering::Equal =>
-								if worst.insertion_id > transaction.insertion_id {
-									transaction.clone()
-								} else {
-									worst
-								},
-							Ordering::Greater => transaction.clone(),
-						}
-					})
-					.or_else(|| Some(transaction.clone()))
-			});
-
-			if let Some(worst) = worst {
+			loop {
+				let usage = self.usage.senders.get(sender).copied().unwrap_or_default();
+				if !limit.limit.is_exceeded(usage.count, usage.bytes) {
+					break
+				}
+
+				let worst = self
+					.worst_future(of_sender)
+					.or_else(|| self.newest_ready(of_sender).map(|newest| newest.transaction));
+
+				if let Some(worst) = worst {
+					removed.append(&mut self.remove_subtree(&[worst.hash.clone()]))
+				} else {
+					break
+				}
+			}
+		}
+
+		removed
+	}
+
+	fn enforce_limits_of(
+		&mut self,
+		ready: &Limit,
+		future: &Limit,
+		filter: impl Fn(&Transaction<Hash, Ex>) -> bool,
+		usage: impl Fn(&Self) -> (Usage, Usage),
+	) -> Vec<Arc<Transaction<Hash, Ex>>> {
+		let mut removed = vec![];
+
+		loop {
+			let (ready_usage, _) = usage(self);
+			if !ready.is_exceeded(ready_usage.count, ready_usage.bytes) {
+				break
+			}
+
+			if let Some(worst) = self.worst_ready(&filter) {
 				removed.append(&mut self.remove_subtree(&[worst.transaction.hash.clone()]))
 			} else {
 				break
 			}
 		}
 
-		while future.is_exceeded(self.future.len(), self.future.bytes()) {
-			// find the worst transaction
-			let worst = self.future.fold(|worst, current| match worst {
-				None => Some(current.clone()),
-				Some(ref tx) if tx.imported_at > current.imported_at => Some(current.clone()),
-				other => other,
-			});
+		loop {
+			let (_, future_usage) = usage(self);
+			if !future.is_exceeded(future_usage.count, future_usage.bytes) {
+				break
+			}
 
-			if let Some(worst) = worst {
-				removed.append(&mut self.remove_subtree(&[worst.transaction.hash.clone()]))
+			if let Some(worst) = self.worst_future(&filter) {
+				removed.append(&mut self.remove_subtree(&[worst.hash.clone()]))
 			} else {
 				break
 			}
@@ -442,6 +597,71 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 		removed
 	}
 
+	/// Finds the ready transaction matching `filter` that should be evicted first.
+	fn worst_ready(
+		&mut self,
+		filter: impl Fn(&Transaction<Hash, Ex>) -> bool,
+	) -> Option<TransactionRef<Hash, Ex>> {
+		self.ready.fold::<TransactionRef<Hash, Ex>, _>(|worst, current| {
+			let transaction = &current.transaction;
+			if !filter(&transaction.transaction) {
+				return worst
+			}
+			worst
+				.map(|worst| {
+					// Here we don't use `TransactionRef`'s ordering implementation because
+					// while it prefers priority like need here, it also prefers older
+					// transactions for inclusion purposes and limit enforcement needs to prefer
+					// newer transactions instead and drop the older ones.
+					match eviction_order(&worst.transaction, &transaction.transaction) {
+						Ordering::Less => worst,
+						Ordering::Equal =>
+							if worst.insertion_id > transaction.insertion_id {
+								transaction.clone()
+							} else {
+								worst
+							},
+						Ordering::Greater => transaction.clone(),
+					}
+				})
+				.or_else(|| Some(transaction.clone()))
+		})
+	}
+
+	/// Finds the most recently imported ready transaction matching `filter`.
+	fn newest_ready(
+		&mut self,
+		filter: impl Fn(&Transaction<Hash, Ex>) -> bool,
+	) -> Option<TransactionRef<Hash, Ex>> {
+		self.ready.fold::<TransactionRef<Hash, Ex>, _>(|newest, current| match newest {
+			Some(newest) if newest.insertion_id > current.transaction.insertion_id => Some(newest),
+			_ if filter(&current.transaction.transaction) => Some(current.transaction.clone()),
+			newest => newest,
+		})
+	}
+
+	/// Finds the future transaction matching `filter` that should be evicted first.
+	fn worst_future(
+		&mut self,
+		filter: impl Fn(&Transaction<Hash, Ex>) -> bool,
+	) -> Option<Arc<Transaction<Hash, Ex>>> {
+		self.future
+			.fold(|worst: Option<WaitingTransaction<Hash, Ex>>, current| {
+				if !filter(&current.transaction) {
+					return worst
+				}
+				match worst {
+					None => Some(current.clone()),
+					Some(worst) => match eviction_order(&worst.transaction, &current.transaction) {
+						Ordering::Less => Some(worst),
+						Ordering::Equal if worst.imported_at <= current.imported_at => Some(worst),
+						_ => Some(current.clone()),
+					},
+				}
+			})
+			.map(|worst| worst.transaction)
+	}
+
 	/// Removes all transactions represented by the hashes and all other transactions
 	/// that depend on them.
 	///
@@ -452,13 +672,24 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	/// and you don't want them to be stored in the pool use `prune_tags` method.
 	pub fn remove_subtree(&mut self, hashes: &[Hash]) -> Vec<Arc<Transaction<Hash, Ex>>> {
 		let mut removed = self.ready.remove_subtree(hashes);
-		removed.extend(self.future.remove(hashes));
+		for tx in &removed {
+			self.usage.removed(tx, true);
+		}
+		let removed_future = self.future.remove(hashes);
+		for tx in &removed_future {
+			self.usage.removed(tx, false);
+		}
+		removed.extend(removed_future);
 		removed
 	}
 
 	/// Removes and returns all transactions from the future queue.
 	pub fn clear_future(&mut self) -> Vec<Arc<Transaction<Hash, Ex>>> {
-		self.future.clear()
+		let removed = self.future.clear();
+		for tx in &removed {
+			self.usage.removed(tx, false);
+		}
+		removed
 	}
 
 	/// Prunes transactions that provide given list of tags.
@@ -476,9 +707,17 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 
 		for tag in tags {
 			// make sure to promote any future transactions that could be unlocked
-			to_import.append(&mut self.future.satisfy_tags(std::iter::once(&tag)));
+			let unlocked = self.future.satisfy_tags(std::iter::once(&tag));
+			for tx in &unlocked {
+				self.usage.removed(&tx.transaction, false);
+			}
+			to_import.extend(unlocked);
 			// and actually prune transactions in ready queue
-			pruned.append(&mut self.ready.prune_tags(tag.clone()));
+			let pruned_by_tag = self.ready.prune_tags(tag.clone());
+			for tx in &pruned_by_tag {
+				self.usage.removed(tx, true);
+			}
+			pruned.extend(pruned_by_tag);
 			// store the tags for next submission
 			recently_pruned.insert(tag);
 		}
@@ -510,6 +749,29 @@ impl<Hash: hash::Hash + Member + Serialize, Ex: std::fmt::Debug> BasePool<Hash,
 	}
 }
 
+/// Compares transactions by how much they are worth keeping in the pool.
+///
+/// Local transactions are preferred over all other ones, the rest is ordered by priority per byte.
+/// `Ordering::Less` means that `a` should be evicted before `b`.
+fn eviction_order<Hash, Ex>(a: &Transaction<Hash, Ex>, b: &Transaction<Hash, Ex>) -> Ordering {
+	let is_local = |tx: &Transaction<Hash, Ex>| tx.source == Source::Local;
+	is_local(a)
+		.cmp(&is_local(b))
+		.then_with(|| cmp_priority_per_byte(a.priority, a.bytes, b.priority, b.bytes))
+}
+
+/// Compares `a_priority / a_bytes` with `b_priority / b_bytes` without losing precision.
+pub(crate) fn cmp_priority_per_byte(
+	a_priority: Priority,
+	a_bytes: usize,
+	b_priority: Priority,
+	b_bytes: usize,
+) -> Ordering {
+	let a = a_priority as u128 * b_bytes.max(1) as u128;
+	let b = b_priority as u128 * a_bytes.max(1) as u128;
+	a.cmp(&b)
+}
+
 /// Queue limits
 #[derive(Debug, Clone)]
 pub struct Limit {
@@ -524,6 +786,88 @@ impl Limit {
 	pub fn is_exceeded(&self, count: usize, bytes: usize) -> bool {
 		self.count < count || self.total_bytes < bytes
 	}
+
+	/// Returns the limit left after taking away `other`.
+	pub fn saturating_sub(&self, other: &Limit) -> Limit {
+		Limit {
+			count: self.count.saturating_sub(other.count),
+			total_bytes: self.total_bytes.saturating_sub(other.total_bytes),
+		}
+	}
+}
+
+/// Limits of transactions of a single sender.
+#[derive(Debug, Clone)]
+pub struct SenderLimit {
+	/// Number of leading bytes of the first `provides` tag that identify the sender.
+	///
+	/// FRAME based runtimes provide the encoded `(AccountId, Nonce)` pair, so this should be the
+	/// encoded length of the `AccountId`.
+	pub tag_prefix_len: usize,
+	/// Maximal number and size of ready and future transactions of a single sender.
+	pub limit: Limit,
+}
+
+impl SenderLimit {
+	/// Returns the sender of the transaction, if it provides any tags.
+	pub fn sender<'a, Hash, Ex>(&self, tx: &'a Transaction<Hash, Ex>) -> Option<&'a [u8]> {
+		tx.provides.first().map(|tag| &tag[..tag.len().min(self.tag_prefix_len)])
+	}
+}
+
+/// Number and total size of some transactions in the pool.
+#[derive(Debug, Default, Clone, Copy)]
+struct Usage {
+	count: usize,
+	bytes: usize,
+}
+
+/// Usage of the queues by the transactions that are not local, kept up to date on every insertion
+/// and removal so that the external and sender limits are checked without walking the queues.
+#[derive(Debug, Default)]
+struct LimitedUsage {
+	sender_limit: Option<SenderLimit>,
+	external_ready: Usage,
+	external_future: Usage,
+	senders: HashMap<Vec<u8>, Usage>,
+}
+
+impl LimitedUsage {
+	/// Accounts for a transaction inserted to the ready (or future) queue.
+	fn inserted<Hash, Ex>(&mut self, tx: &Transaction<Hash, Ex>, ready: bool) {
+		self.update(tx, ready, |usage| {
+			usage.count += 1;
+			usage.bytes += tx.bytes;
+		})
+	}
+
+	/// Accounts for a transaction removed from the ready (or future) queue.
+	fn removed<Hash, Ex>(&mut self, tx: &Transaction<Hash, Ex>, ready: bool) {
+		self.update(tx, ready, |usage| {
+			usage.count = usage.count.saturating_sub(1);
+			usage.bytes = usage.bytes.saturating_sub(tx.bytes);
+		})
+	}
+
+	fn update<Hash, Ex>(
+		&mut self,
+		tx: &Transaction<Hash, Ex>,
+		ready: bool,
+		update: impl Fn(&mut Usage),
+	) {
+		if tx.source == Source::Local {
+			return
+		}
+
+		update(if ready { &mut self.external_ready } else { &mut self.external_future });
+		if let Some(sender) = self.sender_limit.as_ref().and_then(|limit| limit.sender(tx)) {
+			let usage = self.senders.entry(sender.to_vec()).or_default();
+			update(usage);
+			if usage.count == 0 {
+				self.senders.remove(sender);
+			}
+		}
+	}
 }
 
 #[cfg(test)]
@@ -1060,4 +1404,292 @@ source: TransactionSource::External, requires: [03, 02], provides: [04], data: [
 		assert_eq!(pool.reject_future_transactions, true);
 		assert_eq!(pool.future.len(), 1);
 	}
+
+	#[test]
+	fn should_inspect_transactions_and_dependencies() {
+		// given
+		let mut pool = pool();
+		pool.import(Transaction {
+			data: vec![1u8],
+			hash: 1,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			requires: vec![vec![1]],
+			provides: vec![vec![2]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![3u8],
+			hash: 3,
+			requires: vec![vec![2], vec![4]],
+			provides: vec![vec![3]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![4u8],
+			hash: 4,
+			requires: vec![vec![3]],
+			source: Source::Local,
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let mut inspected = pool.inspect();
+		inspected.sort_by_key(|tx| tx.hash);
+
+		// then
+		let summary = inspected
+			.iter()
+			.map(|tx| {
+				(
+					tx.hash,
+					tx.is_ready,
+					tx.missing_tags.clone(),
+					tx.depends_on.clone(),
+					tx.unlocks.clone(),
+				)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			summary,
+			vec![
+				(1, true, vec![], vec![], vec![2]),
+				(2, true, vec![], vec![1], vec![3]),
+				(3, false, vec![vec![4]], vec![2], vec![4]),
+				(4, false, vec![vec![3]], vec![3], vec![]),
+			]
+		);
+		assert_eq!(inspected[3].source, Source::Local);
+	}
+
+	#[test]
+	fn should_evict_lowest_priority_per_byte_first() {
+		// given
+		let mut pool = pool();
+		pool.import(Transaction {
+			data: vec![1u8],
+			hash: 1,
+			priority: 10,
+			bytes: 10,
+			provides: vec![vec![1]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			priority: 5,
+			bytes: 1,
+			provides: vec![vec![2]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let removed = pool.enforce_limits(
+			&Limit { count: 1, total_bytes: 100 },
+			&Limit { count: 100, total_bytes: 100 },
+		);
+
+		// then
+		assert_eq!(removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![2]);
+	}
+
+	#[test]
+	fn should_prefer_local_transactions_when_enforcing_limits() {
+		// given
+		let mut pool = pool();
+		pool.import(Transaction {
+			data: vec![1u8],
+			hash: 1,
+			priority: 1,
+			provides: vec![vec![1]],
+			source: Source::Local,
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+		pool.import(Transaction {
+			data: vec![2u8],
+			hash: 2,
+			priority: 10,
+			provides: vec![vec![2]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// when
+		let removed = pool.enforce_limits(
+			&Limit { count: 1, total_bytes: 100 },
+			&Limit { count: 100, total_bytes: 100 },
+		);
+
+		// then
+		assert_eq!(removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![2]);
+		assert_eq!(pool.ready().map(|tx| tx.hash).collect::<Vec<_>>(), vec![1]);
+	}
+
+	#[test]
+	fn should_only_limit_external_transactions_to_unreserved_capacity() {
+		// given
+		let mut pool = pool();
+		for hash in 1..4u64 {
+			pool.import(Transaction {
+				data: vec![hash as u8],
+				hash,
+				provides: vec![vec![hash as u8]],
+				source: if hash == 1 { Source::Local } else { Source::External },
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+
+		// when
+		let removed = pool.enforce_external_limits(
+			&Limit { count: 1, total_bytes: 100 },
+			&Limit { count: 100, total_bytes: 100 },
+		);
+
+		// then
+		assert_eq!(removed.len(), 1);
+		assert_eq!(pool.ready().count(), 2);
+		assert!(pool.ready().any(|tx| tx.hash == 1));
+	}
+
+	#[test]
+	fn should_enforce_sender_limits() {
+		// given
+		let limit = SenderLimit { tag_prefix_len: 1, limit: Limit { count: 2, total_bytes: 100 } };
+		let mut pool = BasePool::new(false, Some(limit));
+		// sender 1 with nonces 0, 1 and 3 (future) and sender 2 with nonces 0, 1 and 2
+		for (hash, sender, nonce) in
+			[(1, 1, 0), (2, 1, 1), (3, 1, 3), (4, 2, 0), (5, 2, 1), (6, 2, 2)]
+		{
+			let requires = if nonce == 0 { vec![] } else { vec![vec![sender, nonce - 1]] };
+			pool.import(Transaction {
+				data: vec![hash],
+				hash: hash as u64,
+				requires,
+				provides: vec![vec![sender, nonce]],
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+		assert_eq!(pool.ready().count(), 5);
+		assert_eq!(pool.future.len(), 1);
+
+		// when
+		let removed = pool.enforce_sender_limits(&vec![vec![1]].into_iter().collect());
+
+		// then
+		assert_eq!(removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![3]);
+		assert_eq!(pool.future.len(), 0);
+		assert_eq!(pool.ready().count(), 5);
+
+		// when
+		let removed = pool.enforce_sender_limits(&vec![vec![2]].into_iter().collect());
+
+		// then
+		assert_eq!(removed.iter().map(|tx| tx.hash).collect::<Vec<_>>(), vec![6]);
+		assert_eq!(pool.ready().count(), 4);
+	}
+
+	#[test]
+	fn should_not_limit_local_transactions_of_sender() {
+		// given
+		let limit = SenderLimit { tag_prefix_len: 1, limit: Limit { count: 1, total_bytes: 100 } };
+		let mut pool = BasePool::new(false, Some(limit));
+		for nonce in 0..3u8 {
+			let requires = if nonce == 0 { vec![] } else { vec![vec![1, nonce - 1]] };
+			pool.import(Transaction {
+				data: vec![nonce],
+				hash: nonce as u64,
+				requires,
+				provides: vec![vec![1, nonce]],
+				source: Source::Local,
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+
+		// when
+		let removed = pool.enforce_sender_limits(&vec![vec![1]].into_iter().collect());
+
+		// then
+		assert!(removed.is_empty());
+		assert_eq!(pool.ready().count(), 3);
+	}
+
+	#[test]
+	fn should_keep_usage_of_external_transactions_and_senders_up_to_date() {
+		// given
+		let limit = SenderLimit { tag_prefix_len: 1, limit: Limit { count: 10, total_bytes: 100 } };
+		let mut pool = BasePool::new(false, Some(limit));
+		let usage = |pool: &BasePool<Hash, Vec<u8>>, sender: u8| {
+			let sender = pool.usage.senders.get(&vec![sender]).copied().unwrap_or_default();
+			(
+				(pool.usage.external_ready.count, pool.usage.external_ready.bytes),
+				(pool.usage.external_future.count, pool.usage.external_future.bytes),
+				(sender.count, sender.bytes),
+			)
+		};
+
+		// when: sender 1 with nonces 0 and 2 (future), and a local transaction of sender 2
+		for (hash, sender, nonce, source) in
+			[(1, 1, 0, Source::External), (3, 1, 2, Source::External), (4, 2, 0, Source::Local)]
+		{
+			let requires = if nonce == 0 { vec![] } else { vec![vec![sender, nonce - 1]] };
+			pool.import(Transaction {
+				data: vec![hash],
+				bytes: hash as usize,
+				hash: hash as u64,
+				requires,
+				provides: vec![vec![sender, nonce]],
+				source,
+				..DEFAULT_TX.clone()
+			})
+			.unwrap();
+		}
+
+		// then
+		assert_eq!(usage(&pool, 1), ((1, 1), (1, 3), (2, 4)));
+		assert_eq!(usage(&pool, 2), ((1, 1), (1, 3), (0, 0)));
+
+		// when: nonce 1 promotes nonce 2
+		pool.import(Transaction {
+			data: vec![2],
+			bytes: 2,
+			hash: 2,
+			requires: vec![vec![1, 0]],
+			provides: vec![vec![1, 1]],
+			..DEFAULT_TX.clone()
+		})
+		.unwrap();
+
+		// then
+		assert_eq!(usage(&pool, 1), ((3, 6), (0, 0), (3, 6)));
+
+		// when
+		pool.prune_tags(vec![vec![1, 0]]);
+		pool.remove_subtree(&[3]);
+
+		// then
+		assert_eq!(usage(&pool, 1), ((1, 2), (0, 0), (1, 2)));
+		assert!(!pool.usage.senders.contains_key(&vec![2]));
+
+		// when
+		pool.remove_subtree(&[2, 4]);
+
+		// then
+		assert_eq!(usage(&pool, 1), ((0, 0), (0, 0), (0, 0)));
+		assert!(pool.usage.senders.is_empty());
+	}
 }
@@ -34,6 +34,7 @@ use super::{
 	validated_pool::{IsValidator, ValidatedPool, ValidatedTransaction},
 	watcher::Watcher,
 };
+use crate::{journal::JournalOptions, metrics::MetricsLink};
 
 /// Modification notification event stream type;
 pub type EventStream<H> = Receiver<H>;
@@ -110,6 +111,12 @@ pub struct Options {
 	pub reject_future_transactions: bool,
 	/// How long the extrinsic is banned for.
 	pub ban_time: Duration,
+	/// Limits of transactions of a single sender, if any.
+	pub sender: Option<base::SenderLimit>,
+	/// Capacity of the ready and future queues that only local transactions may use.
+	pub reserved: base::Limit,
+	/// Journal that keeps the pending transactions across restarts, if any.
+	pub journal: Option<JournalOptions>,
 }
 
 impl Default for Options {
@@ -119,6 +126,9 @@ impl Default for Options {
 			future: base::Limit { count: 512, total_bytes: 1 * 1024 * 1024 },
 			reject_future_transactions: false,
 			ban_time: Duration::from_secs(60 * 30),
+			sender: None,
+			reserved: base::Limit { count: 0, total_bytes: 0 },
+			journal: None,
 		}
 	}
 }
@@ -148,7 +158,17 @@ where
 impl<B: ChainApi> Pool<B> {
 	/// Create a new transaction pool.
 	pub fn new(options: Options, is_validator: IsValidator, api: Arc<B>) -> Self {
-		Self { validated_pool: Arc::new(ValidatedPool::new(options, is_validator, api)) }
+		Self::with_metrics(options, is_validator, api, Default::default())
+	}
+
+	/// Create a new transaction pool reporting to the given metrics.
+	pub(crate) fn with_metrics(
+		options: Options,
+		is_validator: IsValidator,
+		api: Arc<B>,
+		metrics: MetricsLink,
+	) -> Self {
+		Self { validated_pool: Arc::new(ValidatedPool::new(options, is_validator, api, metrics)) }
 	}
 
 	/// Imports a bunch of unverified extrinsics to the pool
@@ -21,6 +21,7 @@ use std::{
 	collections::{BTreeSet, HashMap, HashSet},
 	hash,
 	sync::Arc,
+	time::Instant,
 };
 
 use log::{debug, trace};
@@ -29,7 +30,7 @@ use serde::Serialize;
 use sp_runtime::{traits::Member, transaction_validity::TransactionTag as Tag};
 
 use super::{
-	base_pool::Transaction,
+	base_pool::{cmp_priority_per_byte, Transaction},
 	future::WaitingTransaction,
 	tracked_map::{self, TrackedMap},
 };
@@ -85,6 +86,8 @@ pub struct ReadyTx<Hash, Ex> {
 	/// Some transactions might be already pruned from the queue,
 	/// so when we compute ready set we may consider this transactions ready earlier.
 	pub requires_offset: usize,
+	/// Time of import to the pool.
+	pub imported_at: Instant,
 }
 
 impl<Hash: Clone, Ex> Clone for ReadyTx<Hash, Ex> {
@@ -93,6 +96,7 @@ impl<Hash: Clone, Ex> Clone for ReadyTx<Hash, Ex> {
 			transaction: self.transaction.clone(),
 			unlocks: self.unlocks.clone(),
 			requires_offset: self.requires_offset,
+			imported_at: self.imported_at,
 		}
 	}
 }
@@ -191,6 +195,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		self.insertion_id += 1;
 		let insertion_id = self.insertion_id;
 		let hash = tx.transaction.hash.clone();
+		let imported_at = tx.imported_at;
 		let transaction = tx.transaction;
 
 		let (replaced, unlocks) = self.replace_previous(&transaction)?;
@@ -226,7 +231,7 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		}
 
 		// insert to Ready
-		ready.insert(hash, ReadyTx { transaction, unlocks, requires_offset });
+		ready.insert(hash, ReadyTx { transaction, unlocks, requires_offset, imported_at });
 
 		Ok(replaced)
 	}
@@ -239,6 +244,11 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 		self.ready.read().values().fold(None, f)
 	}
 
+	/// Returns all transactions in the queue.
+	pub fn all(&self) -> Vec<ReadyTx<Hash, Ex>> {
+		self.ready.read().values().cloned().collect()
+	}
+
 	/// Returns true if given transaction is part of the queue.
 	pub fn contains(&self, hash: &Hash) -> bool {
 		self.ready.read().contains_key(hash)
@@ -434,19 +444,22 @@ impl<Hash: hash::Hash + Member + Serialize, Ex> ReadyTransactions<Hash, Ex> {
 				return Ok((vec![], vec![]))
 			}
 
-			// now check if collective priority is lower than the replacement transaction.
-			let old_priority = {
+			// now check if collective priority per byte is lower than the replacement transaction.
+			let (old_priority, old_bytes) = {
 				let ready = self.ready.read();
-				replace_hashes
-					.iter()
-					.filter_map(|hash| ready.get(hash))
-					.fold(0u64, |total, tx| {
-						total.saturating_add(tx.transaction.transaction.priority)
-					})
+				replace_hashes.iter().filter_map(|hash| ready.get(hash)).fold(
+					(0u64, 0usize),
+					|(priority, bytes), tx| {
+						let tx = &tx.transaction.transaction;
+						(priority.saturating_add(tx.priority), bytes.saturating_add(tx.bytes))
+					},
+				)
 			};
 
 			// bail - the transaction has too low priority to replace the old ones
-			if old_priority >= tx.priority {
+			if cmp_priority_per_byte(old_priority, old_bytes, tx.priority, tx.bytes) !=
+				cmp::Ordering::Less
+			{
 				return Err(error::Error::TooLowPriority { old: old_priority, new: tx.priority })
 			}
 
@@ -641,6 +654,32 @@ mod tests {
 		assert_eq!(ready.get().count(), 1);
 	}
 
+	#[test]
+	fn should_not_replace_transaction_with_lower_priority_per_byte() {
+		// given
+		let mut ready = ReadyTransactions::default();
+		let mut tx1 = tx(1);
+		tx1.requires.clear();
+		tx1.priority = 5;
+		let mut tx2 = tx(2);
+		tx2.requires.clear();
+		tx2.priority = 8;
+		tx2.bytes = 2;
+
+		// when
+		import(&mut ready, tx1).unwrap();
+
+		// then
+		assert!(matches!(
+			import(&mut ready, tx2.clone()),
+			Err(error::Error::TooLowPriority { old: 5, new: 8 })
+		));
+
+		tx2.priority = 11;
+		import(&mut ready, tx2).unwrap();
+		assert_eq!(ready.get().map(|tx| tx.hash).collect::<Vec<_>>(), vec![2]);
+	}
+
 	#[test]
 	fn should_replace_multiple_transactions_correctly() {
 		// given
@@ -24,7 +24,7 @@ use std::{
 
 use futures::channel::mpsc::{channel, Sender};
 use parking_lot::{Mutex, RwLock};
-use sc_transaction_pool_api::{error, PoolStatus, ReadyTransactions};
+use sc_transaction_pool_api::{error, InspectedTransaction, PoolStatus, ReadyTransactions};
 use serde::Serialize;
 use sp_runtime::{
 	generic::BlockId,
@@ -42,6 +42,7 @@ use super::{
 	rotator::PoolRotator,
 	watcher::Watcher,
 };
+use crate::metrics::MetricsLink;
 
 /// Pre-validated transaction. Validated pool only accepts transactions wrapped in this enum.
 #[derive(Debug)]
@@ -108,6 +109,7 @@ pub struct ValidatedPool<B: ChainApi> {
 	pool: RwLock<base::BasePool<ExtrinsicHash<B>, ExtrinsicFor<B>>>,
 	import_notification_sinks: Mutex<Vec<Sender<ExtrinsicHash<B>>>>,
 	rotator: PoolRotator<ExtrinsicHash<B>>,
+	metrics: MetricsLink,
 }
 
 impl<B: ChainApi> parity_util_mem::MallocSizeOf for ValidatedPool<B>
@@ -122,8 +124,14 @@ where
 
 impl<B: ChainApi> ValidatedPool<B> {
 	/// Create a new transaction pool.
-	pub fn new(options: Options, is_validator: IsValidator, api: Arc<B>) -> Self {
-		let base_pool = base::BasePool::new(options.reject_future_transactions);
+	pub fn new(
+		options: Options,
+		is_validator: IsValidator,
+		api: Arc<B>,
+		metrics: MetricsLink,
+	) -> Self {
+		let base_pool =
+			base::BasePool::new(options.reject_future_transactions, options.sender.clone());
 		let ban_time = options.ban_time;
 		Self {
 			is_validator,
@@ -133,6 +141,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 			pool: RwLock::new(base_pool),
 			import_notification_sinks: Default::default(),
 			rotator: PoolRotator::new(ban_time),
+			metrics,
 		}
 	}
 
@@ -170,14 +179,22 @@ impl<B: ChainApi> ValidatedPool<B> {
 		&self,
 		txs: impl IntoIterator<Item = ValidatedTransactionFor<B>>,
 	) -> Vec<Result<ExtrinsicHash<B>, B::Error>> {
+		let mut senders = HashSet::new();
 		let results = txs
 			.into_iter()
-			.map(|validated_tx| self.submit_one(validated_tx))
+			.map(|validated_tx| {
+				if let (ValidatedTransaction::Valid(tx), Some(limit)) =
+					(&validated_tx, &self.options.sender)
+				{
+					senders.extend(limit.sender(tx).map(|sender| sender.to_vec()));
+				}
+				self.submit_one(validated_tx)
+			})
 			.collect::<Vec<_>>();
 
 		// only enforce limits if there is at least one imported transaction
 		let removed = if results.iter().any(|res| res.is_ok()) {
-			self.enforce_limits()
+			self.enforce_limits(&senders)
 		} else {
 			Default::default()
 		};
@@ -235,48 +252,74 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}
 	}
 
-	fn enforce_limits(&self) -> HashSet<ExtrinsicHash<B>> {
+	fn enforce_limits(&self, senders: &HashSet<Tag>) -> HashSet<ExtrinsicHash<B>> {
 		let status = self.pool.read().status();
 		let ready_limit = &self.options.ready;
 		let future_limit = &self.options.future;
+		let reserved = &self.options.reserved;
 
 		log::debug!(target: "txpool", "Pool Status: {:?}", status);
-		if ready_limit.is_exceeded(status.ready, status.ready_bytes) ||
-			future_limit.is_exceeded(status.future, status.future_bytes)
-		{
-			log::debug!(
-				target: "txpool",
-				"Enforcing limits ({}/{}kB ready, {}/{}kB future",
-				ready_limit.count, ready_limit.total_bytes / 1024,
-				future_limit.count, future_limit.total_bytes / 1024,
-			);
-
-			// clean up the pool
-			let removed = {
-				let mut pool = self.pool.write();
-				let removed = pool
-					.enforce_limits(ready_limit, future_limit)
-					.into_iter()
-					.map(|x| x.hash)
-					.collect::<HashSet<_>>();
-				// ban all removed transactions
-				self.rotator.ban(&Instant::now(), removed.iter().copied());
-				removed
+
+		// clean up the pool
+		let removed = {
+			let mut pool = self.pool.write();
+			let mut removed = HashSet::new();
+			let mut evict = |limit, transactions: Vec<TransactionFor<B>>| {
+				if !transactions.is_empty() {
+					log::debug!(
+						target: "txpool",
+						"Enforcing {} limits: {} dropped",
+						limit,
+						transactions.len(),
+					);
+					self.metrics.report(|metrics| {
+						metrics
+							.transactions_evicted
+							.with_label_values(&[limit])
+							.inc_by(transactions.len() as u64)
+					});
+				}
+				removed.extend(transactions.into_iter().map(|x| x.hash));
 			};
-			if !removed.is_empty() {
-				log::debug!(target: "txpool", "Enforcing limits: {} dropped", removed.len());
+
+			evict("sender", pool.enforce_sender_limits(senders));
+
+			if reserved.count > 0 || reserved.total_bytes > 0 {
+				evict(
+					"reserved",
+					pool.enforce_external_limits(
+						&ready_limit.saturating_sub(reserved),
+						&future_limit.saturating_sub(reserved),
+					),
+				);
 			}
 
-			// run notifications
-			let mut listener = self.listener.write();
-			for h in &removed {
-				listener.dropped(h, None);
+			// the evictions above may have brought the pool back within its limits.
+			let status = pool.status();
+			if ready_limit.is_exceeded(status.ready, status.ready_bytes) ||
+				future_limit.is_exceeded(status.future, status.future_bytes)
+			{
+				log::debug!(
+					target: "txpool",
+					"Enforcing limits ({}/{}kB ready, {}/{}kB future",
+					ready_limit.count, ready_limit.total_bytes / 1024,
+					future_limit.count, future_limit.total_bytes / 1024,
+				);
+				evict("pool", pool.enforce_limits(ready_limit, future_limit));
 			}
 
+			// ban all removed transactions
+			self.rotator.ban(&Instant::now(), removed.iter().copied());
 			removed
-		} else {
-			Default::default()
+		};
+
+		// run notifications
+		let mut listener = self.listener.write();
+		for h in &removed {
+			listener.dropped(h, None);
 		}
+
+		removed
 	}
 
 	/// Import a single extrinsic and starts to watch their progress in the pool.
@@ -632,6 +675,20 @@ impl<B: ChainApi> ValidatedPool<B> {
 		self.pool.read().futures().map(|tx| (tx.hash, tx.data.clone())).collect()
 	}
 
+	/// Returns a snapshot of all transactions in the pool and their dependencies.
+	pub fn inspect(&self) -> Vec<InspectedTransaction<ExtrinsicHash<B>>> {
+		self.pool.read().inspect()
+	}
+
+	/// Returns sources and extrinsics of all ready and future transactions.
+	pub fn transactions(&self) -> Vec<(TransactionSource, ExtrinsicFor<B>)> {
+		let pool = self.pool.read();
+		pool.ready()
+			.map(|tx| (tx.source, tx.data.clone()))
+			.chain(pool.futures().map(|tx| (tx.source, tx.data.clone())))
+			.collect()
+	}
+
 	/// Returns pool status.
 	pub fn status(&self) -> PoolStatus {
 		self.pool.read().status()
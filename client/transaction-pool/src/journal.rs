@@ -0,0 +1,334 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! On-disk journal of pending transactions.
+//!
+//! The journal is a snapshot of the ready and future transactions that is taken whenever the
+//! pool is maintained and when it is dropped. Snapshots are written by a dedicated thread, at most
+//! once per [`WRITE_INTERVAL`] except for the last one. Transactions read from the journal on
+//! startup are not trusted, they are handed to the revalidation queue on the first best block,
+//! which imports the valid ones and drops the stale ones.
+
+use codec::{Decode, Encode};
+use parking_lot::{Condvar, Mutex};
+use sp_runtime::transaction_validity::TransactionSource;
+use std::{
+	fs, io,
+	io::Write,
+	path::{Path, PathBuf},
+	sync::Arc,
+	thread::JoinHandle,
+	time::{Duration, Instant},
+};
+
+/// Version of the journal format.
+const JOURNAL_VERSION: u32 = 1;
+
+/// Minimum interval between two writes of the journal.
+const WRITE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Transaction pool journal configuration.
+#[derive(Debug, Clone)]
+pub struct JournalOptions {
+	/// Path of the journal file.
+	pub path: PathBuf,
+	/// Persist transactions received from the network as well, not only local ones.
+	pub include_external: bool,
+}
+
+/// A journaled transaction.
+#[derive(Encode, Decode)]
+struct Entry {
+	source: TransactionSource,
+	/// Encoded extrinsic, kept separately so that a single undecodable extrinsic does not
+	/// invalidate the whole journal.
+	data: Vec<u8>,
+}
+
+/// Journal of the pool transactions.
+///
+/// Dropping the journal waits for the last snapshot to be written.
+pub(crate) struct Journal<Ex> {
+	options: JournalOptions,
+	/// Transactions read from the journal that were not resubmitted to the pool yet.
+	pending: Mutex<Vec<(TransactionSource, Ex)>>,
+	/// State shared with the writer thread.
+	writer: Arc<(Mutex<WriterState>, Condvar)>,
+	writer_thread: Option<JoinHandle<()>>,
+}
+
+/// State shared with the writer thread.
+#[derive(Default)]
+struct WriterState {
+	/// Latest snapshot that was not written yet.
+	snapshot: Option<Vec<Entry>>,
+	/// Whether the journal was dropped.
+	closed: bool,
+}
+
+impl<Ex: Encode + Decode> Journal<Ex> {
+	/// Opens the journal and reads the transactions persisted by a previous run.
+	pub fn open(options: JournalOptions) -> Self {
+		let pending = match read(&options.path) {
+			Ok(pending) => {
+				log::debug!(
+					target: "txpool",
+					"Read {} transactions from the journal at {:?}",
+					pending.len(),
+					options.path,
+				);
+				pending
+			},
+			Err(e) => {
+				log::warn!(
+					target: "txpool",
+					"Failed to read the transaction journal at {:?}: {}",
+					options.path,
+					e,
+				);
+				Vec::new()
+			},
+		};
+
+		let writer = Arc::new((Mutex::new(WriterState::default()), Condvar::new()));
+		let path = options.path.clone();
+		let thread_writer = writer.clone();
+		let writer_thread = match std::thread::Builder::new()
+			.name("txpool-journal".into())
+			.spawn(move || run_writer(&path, &thread_writer))
+		{
+			Ok(thread) => Some(thread),
+			Err(e) => {
+				log::warn!(target: "txpool", "Failed to spawn the transaction journal writer: {}", e);
+				None
+			},
+		};
+
+		Self { options, pending: Mutex::new(pending), writer, writer_thread }
+	}
+
+	/// Takes the transactions that should be resubmitted to the pool.
+	pub fn take_pending(&self) -> Vec<(TransactionSource, Ex)> {
+		std::mem::take(&mut *self.pending.lock())
+	}
+
+	/// Schedules replacing the journal with the given transactions.
+	///
+	/// Transactions that were read from the journal but not resubmitted yet are kept.
+	pub fn write<'a>(&self, transactions: impl IntoIterator<Item = (TransactionSource, &'a Ex)>)
+	where
+		Ex: 'a,
+	{
+		let keep = |(source, _): &(TransactionSource, &Ex)| {
+			self.options.include_external || *source == TransactionSource::Local
+		};
+		let entry = |(source, ex): (TransactionSource, &Ex)| Entry { source, data: ex.encode() };
+
+		let pending = self.pending.lock();
+		let mut entries = pending
+			.iter()
+			.map(|(source, ex)| (*source, ex))
+			.filter(keep)
+			.map(entry)
+			.collect::<Vec<_>>();
+		entries.extend(transactions.into_iter().filter(keep).map(entry));
+
+		let (state, condvar) = &*self.writer;
+		state.lock().snapshot = Some(entries);
+		condvar.notify_all();
+	}
+}
+
+impl<Ex> Drop for Journal<Ex> {
+	fn drop(&mut self) {
+		let (state, condvar) = &*self.writer;
+		state.lock().closed = true;
+		condvar.notify_all();
+
+		if let Some(thread) = self.writer_thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Writes the snapshots of the journal until it is closed.
+///
+/// A snapshot is only written once it has not been replaced for [`WRITE_INTERVAL`], or right
+/// away when the journal is closed.
+fn run_writer(path: &Path, writer: &(Mutex<WriterState>, Condvar)) {
+	let (state, condvar) = writer;
+	let mut state = state.lock();
+	loop {
+		while state.snapshot.is_none() && !state.closed {
+			condvar.wait(&mut state);
+		}
+
+		let deadline = Instant::now() + WRITE_INTERVAL;
+		while !state.closed && !condvar.wait_until(&mut state, deadline).timed_out() {}
+
+		let entries = match state.snapshot.take() {
+			Some(entries) => entries,
+			None => return,
+		};
+		let closed = state.closed;
+		parking_lot::MutexGuard::unlocked(&mut state, || {
+			if let Err(e) = write(path, &entries) {
+				log::warn!(
+					target: "txpool",
+					"Failed to write the transaction journal at {:?}: {}",
+					path,
+					e,
+				);
+			}
+		});
+		if closed && state.snapshot.is_none() {
+			return
+		}
+	}
+}
+
+/// Reads the journal, returns no transactions if it does not exist.
+fn read<Ex: Decode>(path: &Path) -> io::Result<Vec<(TransactionSource, Ex)>> {
+	let data = match fs::read(path) {
+		Ok(data) => data,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+
+	let (version, entries) = <(u32, Vec<Entry>)>::decode(&mut &data[..])
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+	if version != JOURNAL_VERSION {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("Unsupported journal version {}", version),
+		))
+	}
+
+	Ok(entries
+		.into_iter()
+		.filter_map(|entry| match Ex::decode(&mut &entry.data[..]) {
+			Ok(ex) => Some((entry.source, ex)),
+			Err(e) => {
+				log::debug!(target: "txpool", "Skipping undecodable journaled transaction: {}", e);
+				None
+			},
+		})
+		.collect())
+}
+
+/// Atomically replaces the journal with the given entries.
+fn write(path: &Path, entries: &[Entry]) -> io::Result<()> {
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	let tmp_path = path.with_extension("tmp");
+	let mut file = fs::File::create(&tmp_path)?;
+	file.write_all(&(JOURNAL_VERSION, entries).encode())?;
+	file.sync_all()?;
+	fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn options(dir: &tempfile::TempDir, include_external: bool) -> JournalOptions {
+		JournalOptions { path: dir.path().join("txpool").join("journal"), include_external }
+	}
+
+	#[test]
+	fn should_persist_local_transactions_only_by_default() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::<Vec<u8>>::open(options(&dir, false));
+		assert!(journal.take_pending().is_empty());
+
+		journal.write(vec![
+			(TransactionSource::Local, &vec![1u8]),
+			(TransactionSource::External, &vec![2u8]),
+			(TransactionSource::Local, &vec![3u8]),
+		]);
+		drop(journal);
+
+		let journal = Journal::<Vec<u8>>::open(options(&dir, false));
+		assert_eq!(
+			journal.take_pending(),
+			vec![(TransactionSource::Local, vec![1u8]), (TransactionSource::Local, vec![3u8])],
+		);
+	}
+
+	#[test]
+	fn should_persist_all_transactions_if_asked_to() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::<Vec<u8>>::open(options(&dir, true));
+
+		journal.write(vec![
+			(TransactionSource::Local, &vec![1u8]),
+			(TransactionSource::External, &vec![2u8]),
+		]);
+		drop(journal);
+
+		let journal = Journal::<Vec<u8>>::open(options(&dir, true));
+		assert_eq!(
+			journal.take_pending(),
+			vec![(TransactionSource::Local, vec![1u8]), (TransactionSource::External, vec![2u8])],
+		);
+	}
+
+	#[test]
+	fn should_keep_transactions_that_were_not_resubmitted_yet() {
+		let dir = tempfile::tempdir().unwrap();
+		Journal::<Vec<u8>>::open(options(&dir, false))
+			.write(vec![(TransactionSource::Local, &vec![1u8])]);
+
+		let journal = Journal::<Vec<u8>>::open(options(&dir, false));
+		journal.write(vec![(TransactionSource::Local, &vec![2u8])]);
+		drop(journal);
+
+		let journal = Journal::<Vec<u8>>::open(options(&dir, false));
+		assert_eq!(
+			journal.take_pending(),
+			vec![(TransactionSource::Local, vec![1u8]), (TransactionSource::Local, vec![2u8])],
+		);
+	}
+
+	#[test]
+	fn should_write_only_the_latest_snapshot() {
+		let dir = tempfile::tempdir().unwrap();
+		let journal = Journal::<Vec<u8>>::open(options(&dir, false));
+		journal.write(vec![(TransactionSource::Local, &vec![1u8])]);
+		journal.write(vec![(TransactionSource::Local, &vec![2u8])]);
+
+		// the first snapshot is still waiting for the write interval to elapse.
+		assert!(!journal.options.path.exists());
+		drop(journal);
+
+		let journal = Journal::<Vec<u8>>::open(options(&dir, false));
+		assert_eq!(journal.take_pending(), vec![(TransactionSource::Local, vec![2u8])]);
+	}
+
+	#[test]
+	fn should_ignore_journal_of_unknown_version() {
+		let dir = tempfile::tempdir().unwrap();
+		let options = options(&dir, false);
+		fs::create_dir_all(options.path.parent().unwrap()).unwrap();
+		fs::write(&options.path, (JOURNAL_VERSION + 1, Vec::<Entry>::new()).encode()).unwrap();
+
+		assert!(Journal::<Vec<u8>>::open(options).take_pending().is_empty());
+	}
+}
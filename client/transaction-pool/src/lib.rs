@@ -25,19 +25,21 @@
 mod api;
 pub mod error;
 mod graph;
+mod journal;
 mod metrics;
 mod revalidation;
 #[cfg(test)]
 mod tests;
 
-pub use crate::api::FullChainApi;
+pub use crate::{api::FullChainApi, journal::JournalOptions};
 use futures::{
 	channel::oneshot,
 	future::{self, ready},
 	prelude::*,
 };
 pub use graph::{
-	base_pool::Limit as PoolLimit, ChainApi, Options, Pool, Transaction, ValidatedTransaction,
+	base_pool::{Limit as PoolLimit, SenderLimit as PoolSenderLimit},
+	ChainApi, Options, Pool, Transaction, ValidatedTransaction,
 };
 use parking_lot::Mutex;
 use std::{
@@ -48,9 +50,9 @@ use std::{
 
 use graph::{ExtrinsicHash, IsValidator};
 use sc_transaction_pool_api::{
-	error::Error as TxPoolError, ChainEvent, ImportNotificationStream, MaintainedTransactionPool,
-	PoolFuture, PoolStatus, ReadyTransactions, TransactionFor, TransactionPool, TransactionSource,
-	TransactionStatusStreamFor, TxHash,
+	error::Error as TxPoolError, ChainEvent, ImportNotificationStream, InspectedTransaction,
+	MaintainedTransactionPool, PoolFuture, PoolStatus, ReadyTransactions, TransactionFor,
+	TransactionPool, TransactionSource, TransactionStatusStreamFor, TxHash,
 };
 use sp_core::traits::SpawnEssentialNamed;
 use sp_runtime::{
@@ -85,6 +87,7 @@ where
 	revalidation_queue: Arc<revalidation::RevalidationQueue<PoolApi>>,
 	ready_poll: Arc<Mutex<ReadyPoll<ReadyIteratorFor<PoolApi>, Block>>>,
 	metrics: PrometheusMetrics,
+	journal: Option<Arc<journal::Journal<graph::ExtrinsicFor<PoolApi>>>>,
 }
 
 struct ReadyPoll<T, Block: BlockT> {
@@ -175,6 +178,7 @@ where
 				revalidation_strategy: Arc::new(Mutex::new(RevalidationStrategy::Always)),
 				ready_poll: Default::default(),
 				metrics: Default::default(),
+				journal: None,
 			},
 			background_task,
 		)
@@ -191,7 +195,15 @@ where
 		spawner: impl SpawnEssentialNamed,
 		best_block_number: NumberFor<Block>,
 	) -> Self {
-		let pool = Arc::new(graph::Pool::new(options, is_validator, pool_api.clone()));
+		let metrics = PrometheusMetrics::new(prometheus);
+		let journal =
+			options.journal.clone().map(|options| Arc::new(journal::Journal::open(options)));
+		let pool = Arc::new(graph::Pool::with_metrics(
+			options,
+			is_validator,
+			pool_api.clone(),
+			metrics.clone(),
+		));
 		let (revalidation_queue, background_task) = match revalidation_type {
 			RevalidationType::Light =>
 				(revalidation::RevalidationQueue::new(pool_api.clone(), pool.clone()), None),
@@ -216,7 +228,8 @@ where
 				RevalidationType::Full => RevalidationStrategy::Always,
 			})),
 			ready_poll: Arc::new(Mutex::new(ReadyPoll::new(best_block_number))),
-			metrics: PrometheusMetrics::new(prometheus),
+			metrics,
+			journal,
 		}
 	}
 
@@ -231,6 +244,19 @@ where
 	}
 }
 
+impl<PoolApi, Block> Drop for BasicPool<PoolApi, Block>
+where
+	Block: BlockT,
+	PoolApi: graph::ChainApi<Block = Block>,
+{
+	fn drop(&mut self) {
+		if let Some(ref journal) = self.journal {
+			let transactions = self.pool.validated_pool().transactions();
+			journal.write(transactions.iter().map(|(source, xt)| (*source, xt)));
+		}
+	}
+}
+
 impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 where
 	Block: BlockT,
@@ -316,6 +342,10 @@ where
 		self.pool.validated_pool().ready_by_hash(hash)
 	}
 
+	fn inspect(&self) -> Vec<InspectedTransaction<TxHash<Self>>> {
+		self.pool.validated_pool().inspect()
+	}
+
 	fn ready_at(&self, at: NumberFor<Self::Block>) -> PolledIterator<PoolApi> {
 		let status = self.status();
 		// If there are no transactions in the pool, it is fine to return early.
@@ -596,6 +626,7 @@ where
 				let revalidation_queue = self.revalidation_queue.clone();
 				let ready_poll = self.ready_poll.clone();
 				let metrics = self.metrics.clone();
+				let journal = self.journal.clone();
 
 				async move {
 					// We keep track of everything we prune so that later we won't add
@@ -692,6 +723,14 @@ where
 						}
 					}
 
+					// Transactions persisted by the previous run are revalidated against the first
+					// best block we see.
+					if let Some(ref journal) = journal {
+						revalidation_queue
+							.revalidate_journaled(block_number, journal.take_pending())
+							.await;
+					}
+
 					let extra_pool = pool.clone();
 					// After #5200 lands, this arguably might be moved to the
 					// handler of "all blocks notification".
@@ -705,6 +744,11 @@ where
 
 						revalidation_strategy.lock().clear();
 					}
+
+					if let Some(journal) = journal {
+						let transactions = pool.validated_pool().transactions();
+						journal.write(transactions.iter().map(|(source, xt)| (*source, xt)));
+					}
 				}
 				.boxed()
 			},
@@ -20,7 +20,7 @@
 
 use std::sync::Arc;
 
-use prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
+use prometheus_endpoint::{register, Counter, CounterVec, Opts, PrometheusError, Registry, U64};
 
 #[derive(Clone, Default)]
 pub struct MetricsLink(Arc<Option<Metrics>>);
@@ -49,6 +49,7 @@ pub struct Metrics {
 	pub validations_invalid: Counter<U64>,
 	pub block_transactions_pruned: Counter<U64>,
 	pub block_transactions_resubmitted: Counter<U64>,
+	pub transactions_evicted: CounterVec<U64>,
 }
 
 impl Metrics {
@@ -82,6 +83,16 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			transactions_evicted: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_sub_txpool_transactions_evicted",
+						"Total number of transactions that were evicted to enforce the pool limits",
+					),
+					&["limit"],
+				)?,
+				registry,
+			)?,
 		})
 	}
 }
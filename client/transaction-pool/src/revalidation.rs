@@ -24,7 +24,8 @@ use std::{
 	sync::Arc,
 };
 
-use crate::graph::{ChainApi, ExtrinsicHash, NumberFor, Pool, ValidatedTransaction};
+use crate::graph::{ChainApi, ExtrinsicFor, ExtrinsicHash, NumberFor, Pool, ValidatedTransaction};
+use sc_transaction_pool_api::TransactionSource;
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
 use sp_runtime::{
 	generic::BlockId,
@@ -43,6 +44,7 @@ const MIN_BACKGROUND_REVALIDATION_BATCH_SIZE: usize = 20;
 struct WorkerPayload<Api: ChainApi> {
 	at: NumberFor<Api>,
 	transactions: Vec<ExtrinsicHash<Api>>,
+	journaled: Vec<(TransactionSource, ExtrinsicFor<Api>)>,
 }
 
 /// Async revalidation worker.
@@ -121,6 +123,77 @@ async fn batch_revalidate<Api: ChainApi>(
 	}
 }
 
+/// Revalidate transactions read from the journal of a previous run.
+///
+/// Unlike `batch_revalidate`, the transactions are not in the `pool` yet: valid ones are
+/// imported, while stale ones are banned and reported as invalid.
+async fn batch_revalidate_journaled<Api: ChainApi>(
+	pool: Arc<Pool<Api>>,
+	api: Arc<Api>,
+	at: NumberFor<Api>,
+	journaled: Vec<(TransactionSource, ExtrinsicFor<Api>)>,
+) {
+	let mut invalid_hashes = Vec::new();
+	let mut revalidated = Vec::new();
+
+	let validation_results =
+		futures::future::join_all(journaled.into_iter().map(|(source, xt)| {
+			api.validate_transaction(&BlockId::Number(at), source, xt.clone())
+				.map(move |validation_result| (validation_result, source, xt))
+		}))
+		.await;
+
+	for (validation_result, source, xt) in validation_results {
+		let (ext_hash, bytes) = api.hash_and_length(&xt);
+		match validation_result {
+			Ok(Err(TransactionValidityError::Invalid(err))) => {
+				log::debug!(
+					target: "txpool",
+					"[{:?}]: Journal revalidation: invalid {:?}",
+					ext_hash,
+					err
+				);
+				invalid_hashes.push(ext_hash);
+			},
+			Ok(Err(TransactionValidityError::Unknown(err))) => {
+				log::debug!(
+					target: "txpool",
+					"[{:?}]: Journal revalidation: unknown {:?}",
+					ext_hash,
+					err
+				);
+				invalid_hashes.push(ext_hash);
+			},
+			Ok(Ok(validity)) => {
+				revalidated.push(ValidatedTransaction::valid_at(
+					at.saturated_into::<u64>(),
+					ext_hash,
+					source,
+					xt,
+					bytes,
+					validity,
+				));
+			},
+			Err(validation_err) => {
+				log::debug!(
+					target: "txpool",
+					"[{:?}]: Dropping journaled transaction due to error during revalidation: {}",
+					ext_hash,
+					validation_err
+				);
+				invalid_hashes.push(ext_hash);
+			},
+		}
+	}
+
+	pool.validated_pool().remove_invalid(&invalid_hashes);
+	for result in pool.validated_pool().submit(revalidated) {
+		if let Err(e) = result {
+			log::debug!(target: "txpool", "Error re-importing journaled transaction: {}", e);
+		}
+	}
+}
+
 impl<Api: ChainApi> RevalidationWorker<Api> {
 	fn new(api: Arc<Api>, pool: Arc<Pool<Api>>) -> Self {
 		Self {
@@ -242,8 +315,17 @@ impl<Api: ChainApi> RevalidationWorker<Api> {
 				},
 				workload = from_queue.next() => {
 					match workload {
-						Some(worker_payload) => {
+						Some(mut worker_payload) => {
 							this.best_block = worker_payload.at;
+							let journaled = std::mem::take(&mut worker_payload.journaled);
+							if !journaled.is_empty() {
+								batch_revalidate_journaled(
+									this.pool.clone(),
+									this.api.clone(),
+									this.best_block,
+									journaled,
+								).await;
+							}
 							this.push(worker_payload);
 
 							if this.members.len() > 0 {
@@ -326,7 +408,8 @@ where
 		}
 
 		if let Some(ref to_worker) = self.background {
-			if let Err(e) = to_worker.unbounded_send(WorkerPayload { at, transactions }) {
+			let payload = WorkerPayload { at, transactions, journaled: Vec::new() };
+			if let Err(e) = to_worker.unbounded_send(payload) {
 				log::warn!(target: "txpool", "Failed to update background worker: {:?}", e);
 			}
 		} else {
@@ -335,6 +418,36 @@ where
 			batch_revalidate(pool, api, at, transactions).await
 		}
 	}
+
+	/// Queue transactions read from the journal for revalidation.
+	///
+	/// Valid transactions are imported to the pool, stale ones are dropped. Resolves
+	/// immediately if the queue is configured with a background worker.
+	pub async fn revalidate_journaled(
+		&self,
+		at: NumberFor<Api>,
+		journaled: Vec<(TransactionSource, ExtrinsicFor<Api>)>,
+	) {
+		if journaled.is_empty() {
+			return
+		}
+
+		log::debug!(
+			target: "txpool", "Sent {} journaled transactions to revalidation queue",
+			journaled.len(),
+		);
+
+		if let Some(ref to_worker) = self.background {
+			let payload = WorkerPayload { at, transactions: Vec::new(), journaled };
+			if let Err(e) = to_worker.unbounded_send(payload) {
+				log::warn!(target: "txpool", "Failed to update background worker: {:?}", e);
+			}
+		} else {
+			let pool = self.pool.clone();
+			let api = self.api.clone();
+			batch_revalidate_journaled(pool, api, at, journaled).await
+		}
+	}
 }
 
 #[cfg(test)]
@@ -375,4 +488,32 @@ mod tests {
 		// number of ready
 		assert_eq!(pool.validated_pool().status().ready, 1);
 	}
+
+	#[test]
+	fn revalidation_queue_imports_valid_journaled_transactions() {
+		let api = Arc::new(TestApi::default());
+		let pool = Arc::new(Pool::new(Default::default(), true.into(), api.clone()));
+		let queue = Arc::new(RevalidationQueue::new(api.clone(), pool.clone()));
+
+		let transfer = |nonce| {
+			uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce,
+			})
+		};
+		let stale = transfer(0);
+		let valid = transfer(1);
+		let stale_hash = api.hash_and_length(&stale).0;
+
+		block_on(queue.revalidate_journaled(
+			1,
+			vec![(TransactionSource::Local, stale), (TransactionSource::External, valid)],
+		));
+
+		assert_eq!(api.validation_requests().len(), 2);
+		assert_eq!(pool.validated_pool().status().ready, 1);
+		assert!(pool.validated_pool().is_banned(&stale_hash));
+	}
 }
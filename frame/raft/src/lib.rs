@@ -0,0 +1,301 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Raft Module
+//!
+//! - [`Config`]
+//! - [`Pallet`]
+//!
+//! ## Overview
+//!
+//! The Raft module manages the authority set of the Raft consensus: the authorities that elect a
+//! leader among themselves and acknowledge the blocks it authors. Changes of the authority set
+//! are signaled to the client through a [`ConsensusLog::AuthoritiesChange`] digest, and apply
+//! starting with the children of the block that deposited it.
+//!
+//! A change applies right away, so any majority of the old authorities must share an authority
+//! with any majority of the new ones: otherwise both majorities could acknowledge conflicting
+//! blocks. Changes adding or removing a single authority always satisfy this, changes replacing
+//! authorities have to be split into additions and removals.
+//!
+//! ## Interface
+//!
+//! ### Public Functions
+//!
+//! - `change_authorities` - Replace the authority set, starting with the children of the current
+//!   block. Fails if the majorities of the old and new authority sets might not overlap.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+	dispatch::DispatchResult,
+	log,
+	traits::{FindAuthor, Get, OneSessionHandler},
+	BoundedSlice, BoundedVec, ConsensusEngineId, Parameter,
+};
+use sp_consensus_raft::{ConsensusLog, PreDigest, Term, RAFT_ENGINE_ID};
+use sp_runtime::{
+	generic::DigestItem,
+	traits::{IsMember, Member},
+	RuntimeAppPublic,
+};
+use sp_std::prelude::*;
+
+mod mock;
+mod tests;
+
+pub use pallet::*;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The identifier type for an authority.
+		type AuthorityId: Member
+			+ Parameter
+			+ RuntimeAppPublic
+			+ MaybeSerializeDeserialize
+			+ MaxEncodedLen;
+		/// The maximum number of authorities that the pallet can hold.
+		type MaxAuthorities: Get<u32>;
+	}
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(sp_std::marker::PhantomData<T>);
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// A majority of the old authorities might not share any authority with a majority of
+		/// the new authorities.
+		NonOverlappingMajorities,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_: T::BlockNumber) -> Weight {
+			if let Some(pre_digest) = Self::pre_digest_from_digests() {
+				assert!(CurrentTerm::<T>::get() <= pre_digest.term, "Term must not decrease");
+
+				let n_authorities = <Authorities<T>>::decode_len().unwrap_or(0);
+				assert!(
+					(pre_digest.authority_index as usize) < n_authorities,
+					"Block must be authored by one of the authorities",
+				);
+				CurrentTerm::<T>::put(pre_digest.term);
+
+				T::DbWeight::get().reads_writes(2, 1)
+			} else {
+				T::DbWeight::get().reads(1)
+			}
+		}
+	}
+
+	/// The current authority set.
+	#[pallet::storage]
+	#[pallet::getter(fn authorities)]
+	pub(super) type Authorities<T: Config> =
+		StorageValue<_, BoundedVec<T::AuthorityId, T::MaxAuthorities>, ValueQuery>;
+
+	/// The term during which this block was authored.
+	///
+	/// This will be set in `on_initialize`.
+	#[pallet::storage]
+	#[pallet::getter(fn current_term)]
+	pub(super) type CurrentTerm<T: Config> = StorageValue<_, Term, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub authorities: Vec<T::AuthorityId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { authorities: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			Pallet::<T>::initialize_authorities(&self.authorities);
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Change authorities.
+	///
+	/// The storage will be applied immediately, so the new authorities author and acknowledge
+	/// the children of the current block.
+	/// And raft consensus log will be appended to block's log.
+	///
+	/// Fails without changing anything if some majority of the current authorities and some
+	/// majority of the new authorities have no authority in common.
+	pub fn change_authorities(
+		new: BoundedVec<T::AuthorityId, T::MaxAuthorities>,
+	) -> DispatchResult {
+		let old = Self::authorities();
+		if !majorities_overlap(&old, &new) {
+			return Err(Error::<T>::NonOverlappingMajorities.into())
+		}
+		<Authorities<T>>::put(&new);
+
+		let log = DigestItem::Consensus(
+			RAFT_ENGINE_ID,
+			ConsensusLog::AuthoritiesChange(new.into_inner()).encode(),
+		);
+		<frame_system::Pallet<T>>::deposit_log(log);
+		Ok(())
+	}
+
+	/// Initial authorities.
+	///
+	/// The storage will be applied immediately.
+	///
+	/// The authorities length must be equal or less than T::MaxAuthorities.
+	pub fn initialize_authorities(authorities: &[T::AuthorityId]) {
+		if !authorities.is_empty() {
+			assert!(<Authorities<T>>::get().is_empty(), "Authorities are already initialized!");
+			let bounded = <BoundedSlice<'_, _, T::MaxAuthorities>>::try_from(authorities)
+				.expect("Initial authority set must be less than T::MaxAuthorities");
+			<Authorities<T>>::put(bounded);
+		}
+	}
+
+	/// Get the Raft pre-digest from the pre-runtime digests.
+	fn pre_digest_from_digests() -> Option<PreDigest> {
+		let digest = frame_system::Pallet::<T>::digest();
+		let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+		for (id, mut data) in pre_runtime_digests {
+			if id == RAFT_ENGINE_ID {
+				return PreDigest::decode(&mut data).ok()
+			}
+		}
+
+		None
+	}
+}
+
+/// Returns whether every majority of `old` shares an authority with every majority of `new`.
+///
+/// A majority contains as few shared authorities as possible when it takes all the authorities
+/// that are not shared first. Two such majorities can't be disjoint as long as together they need
+/// more shared authorities than there are.
+fn majorities_overlap<Id: PartialEq>(old: &[Id], new: &[Id]) -> bool {
+	let majority = |authorities: &[Id]| authorities.len() / 2 + 1;
+	let shared = old.iter().filter(|id| new.contains(id)).count();
+
+	let shared_in_old_majority = majority(old).saturating_sub(old.len() - shared);
+	let shared_in_new_majority = majority(new).saturating_sub(new.len() - shared);
+	shared_in_old_majority + shared_in_new_majority > shared
+}
+
+impl<T: Config> sp_runtime::BoundToRuntimeAppPublic for Pallet<T> {
+	type Public = T::AuthorityId;
+}
+
+impl<T: Config> OneSessionHandler<T::AccountId> for Pallet<T> {
+	type Key = T::AuthorityId;
+
+	fn on_genesis_session<'a, I: 'a>(validators: I)
+	where
+		I: Iterator<Item = (&'a T::AccountId, T::AuthorityId)>,
+	{
+		let authorities = validators.map(|(_, k)| k).collect::<Vec<_>>();
+		Self::initialize_authorities(&authorities);
+	}
+
+	fn on_new_session<'a, I: 'a>(changed: bool, validators: I, _queued_validators: I)
+	where
+		I: Iterator<Item = (&'a T::AccountId, T::AuthorityId)>,
+	{
+		// instant changes
+		if changed {
+			let next_authorities = validators.map(|(_, k)| k).collect::<Vec<_>>();
+			let last_authorities = Self::authorities();
+			if last_authorities != next_authorities {
+				if next_authorities.len() as u32 > T::MaxAuthorities::get() {
+					log::warn!(
+						target: "runtime::raft",
+						"next authorities list larger than {}, truncating",
+						T::MaxAuthorities::get(),
+					);
+				}
+				let bounded = <BoundedVec<_, T::MaxAuthorities>>::truncate_from(next_authorities);
+				if Self::change_authorities(bounded).is_err() {
+					log::warn!(
+						target: "runtime::raft",
+						"next authorities don't overlap enough with the current ones, keeping the \
+						 current authorities",
+					);
+				}
+			}
+		}
+	}
+
+	fn on_disabled(_i: u32) {}
+}
+
+impl<T: Config> FindAuthor<u32> for Pallet<T> {
+	fn find_author<'a, I>(digests: I) -> Option<u32>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		for (id, mut data) in digests.into_iter() {
+			if id == RAFT_ENGINE_ID {
+				let pre_digest = PreDigest::decode(&mut data).ok()?;
+				return Some(pre_digest.authority_index)
+			}
+		}
+
+		None
+	}
+}
+
+/// We can not implement `FindAuthor` twice, because the compiler does not know if
+/// `u32 == T::AuthorityId` and thus, prevents us to implement the trait twice.
+#[doc(hidden)]
+pub struct FindAccountFromAuthorIndex<T, Inner>(sp_std::marker::PhantomData<(T, Inner)>);
+
+impl<T: Config, Inner: FindAuthor<u32>> FindAuthor<T::AuthorityId>
+	for FindAccountFromAuthorIndex<T, Inner>
+{
+	fn find_author<'a, I>(digests: I) -> Option<T::AuthorityId>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		let i = Inner::find_author(digests)?;
+
+		let validators = <Pallet<T>>::authorities();
+		validators.get(i as usize).cloned()
+	}
+}
+
+/// Find the authority ID of the Raft leader who authored the current block.
+pub type RaftAuthorId<T> = FindAccountFromAuthorIndex<T, Pallet<T>>;
+
+impl<T: Config> IsMember<T::AuthorityId> for Pallet<T> {
+	fn is_member(authority_id: &T::AuthorityId) -> bool {
+		Self::authorities().iter().any(|id| id == authority_id)
+	}
+}
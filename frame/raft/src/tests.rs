@@ -0,0 +1,118 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the module.
+
+#![cfg(test)]
+
+use crate::{
+	mock::{new_test_ext, Raft, System, Test},
+	Error,
+};
+use codec::Encode;
+use frame_support::{assert_noop, assert_ok, traits::OnInitialize, BoundedVec};
+use sp_consensus_raft::{AuthorityId, ConsensusLog, PreDigest, RAFT_ENGINE_ID};
+use sp_runtime::{testing::UintAuthorityId, Digest, DigestItem};
+
+fn initialize_block(number: u64, term: u64, authority_index: u32) {
+	let pre_digest = PreDigest { term, authority_index };
+	let digest = Digest { logs: vec![DigestItem::PreRuntime(RAFT_ENGINE_ID, pre_digest.encode())] };
+
+	System::reset_events();
+	System::initialize(&number, &System::parent_hash(), &digest);
+	Raft::on_initialize(number);
+}
+
+#[test]
+fn initial_values() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		assert_eq!(Raft::current_term(), 0);
+		assert_eq!(Raft::authorities().len(), 3);
+	});
+}
+
+#[test]
+fn term_is_read_from_pre_digest() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		initialize_block(1, 2, 1);
+		assert_eq!(Raft::current_term(), 2);
+
+		// a leader may author several blocks during its term.
+		initialize_block(2, 2, 1);
+		assert_eq!(Raft::current_term(), 2);
+	});
+}
+
+#[test]
+#[should_panic(expected = "Term must not decrease")]
+fn term_cannot_decrease() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		initialize_block(1, 3, 0);
+		initialize_block(2, 2, 1);
+	});
+}
+
+#[test]
+#[should_panic(expected = "Block must be authored by one of the authorities")]
+fn unknown_authority_cannot_author_blocks() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		initialize_block(1, 1, 3);
+	});
+}
+
+fn authorities(ids: &[u64]) -> Vec<AuthorityId> {
+	ids.iter().map(|id| UintAuthorityId(*id).to_public_key()).collect()
+}
+
+#[test]
+fn change_authorities_deposits_log() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		initialize_block(1, 1, 0);
+
+		let next = authorities(&[0, 1, 2, 3]);
+		assert_ok!(Raft::change_authorities(BoundedVec::truncate_from(next.clone())));
+
+		assert_eq!(Raft::authorities().into_inner(), next);
+		let log = System::digest()
+			.logs
+			.iter()
+			.find_map(|log| log.consensus_try_to::<ConsensusLog<AuthorityId>>(&RAFT_ENGINE_ID))
+			.expect("authorities change log was deposited");
+		assert!(matches!(log, ConsensusLog::AuthoritiesChange(authorities) if authorities == next));
+	});
+}
+
+#[test]
+fn change_authorities_requires_overlapping_majorities() {
+	new_test_ext(vec![0, 1, 2]).execute_with(|| {
+		initialize_block(1, 1, 0);
+
+		// {3, 4} and {0, 1} are majorities of the new and old sets without common authority.
+		for next in [&[3, 4][..], &[0, 3, 4], &[0, 1, 3]] {
+			assert_noop!(
+				Raft::change_authorities(BoundedVec::truncate_from(authorities(next))),
+				Error::<Test>::NonOverlappingMajorities,
+			);
+		}
+		assert!(System::digest().logs.iter().all(|log| log.as_consensus().is_none()));
+
+		// single additions and removals are always allowed.
+		assert_ok!(Raft::change_authorities(BoundedVec::truncate_from(authorities(&[0, 1]))));
+		assert_ok!(Raft::change_authorities(BoundedVec::truncate_from(authorities(&[0, 1, 3]))));
+		assert_eq!(Raft::authorities().into_inner(), authorities(&[0, 1, 3]));
+	});
+}
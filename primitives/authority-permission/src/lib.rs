@@ -33,6 +33,12 @@ pub trait PermissionResolver: Send + Sync {
 	async fn resolve_slot(&self, slot: Slot) -> bool;
 	async fn resolve_round(&self, round: u64) -> bool;
 	async fn resolve_session(&self, session_index: u32) -> bool;
+
+	/// Whether the node may author in `slot` with the authority key whose raw public key is
+	/// `key`, for nodes authoring with several keys. Defaults to [`Self::resolve_slot`].
+	async fn resolve_slot_for_key(&self, slot: Slot, _key: &[u8]) -> bool {
+		self.resolve_slot(slot).await
+	}
 }
 
 impl std::fmt::Debug for dyn PermissionResolverFactory {
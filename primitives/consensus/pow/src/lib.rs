@@ -23,6 +23,8 @@ use codec::Decode;
 use sp_runtime::ConsensusEngineId;
 use sp_std::vec::Vec;
 
+pub mod lwma;
+
 /// The `ConsensusEngineId` of PoW.
 pub const POW_ENGINE_ID: ConsensusEngineId = [b'p', b'o', b'w', b'_'];
 
@@ -62,4 +64,11 @@ sp_api::decl_runtime_apis! {
 		/// Return the target difficulty of the next block.
 		fn difficulty() -> Difficulty;
 	}
+
+	/// API for those chains that adjust their difficulty with [`lwma`], exposing the parameters
+	/// of the adjustment to the client computing it.
+	pub trait LwmaApi {
+		/// Return the parameters of the LWMA difficulty adjustment.
+		fn lwma_params() -> lwma::LwmaParams;
+	}
 }
@@ -0,0 +1,129 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Linearly weighted moving average (LWMA) difficulty adjustment.
+//!
+//! The difficulty of the next block is the average difficulty of the last `window` blocks,
+//! scaled by the ratio of the target block time to their solve times. Recent solve times weigh
+//! more than older ones, so the difficulty follows quick changes of the hash rate.
+
+use codec::{Decode, Encode};
+use sp_core::U256;
+
+/// Parameters of the LWMA difficulty adjustment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct LwmaParams {
+	/// Number of blocks whose solve times are averaged.
+	pub window: u32,
+	/// Targeted time between two blocks, in milliseconds.
+	pub target_block_time: u64,
+	/// Difficulty of the first blocks, until there is a solve time to average.
+	pub initial_difficulty: U256,
+	/// Lower bound of the difficulty.
+	pub min_difficulty: U256,
+}
+
+/// Difficulty of the block following `blocks`.
+///
+/// `blocks` are the timestamp (in milliseconds) and difficulty of the latest blocks of the chain,
+/// oldest first. Only the last `window + 1` of them are used, the oldest of those only for its
+/// timestamp. Solve times are clamped to `[1, 6 * target_block_time]`, so that wrong timestamps
+/// can't move the difficulty too far.
+pub fn next_difficulty(params: &LwmaParams, blocks: &[(u64, U256)]) -> U256 {
+	let blocks = &blocks[blocks.len().saturating_sub(params.window as usize + 1)..];
+	if blocks.len() < 2 {
+		return params.initial_difficulty.max(params.min_difficulty)
+	}
+
+	let target_block_time = params.target_block_time.max(1);
+	let max_solve_time = target_block_time.saturating_mul(6);
+	let mut weighted_solve_times = U256::zero();
+	let mut difficulties = U256::zero();
+	for (weight, pair) in (1u64..).zip(blocks.windows(2)) {
+		let (previous, current) = (pair[0], pair[1]);
+		let solve_time = current.0.saturating_sub(previous.0).clamp(1, max_solve_time);
+		weighted_solve_times = weighted_solve_times.saturating_add(U256::from(solve_time) * weight);
+		difficulties = difficulties.saturating_add(current.1);
+	}
+
+	// The weighted solve times of `n` blocks found right on target.
+	let n = (blocks.len() - 1) as u64;
+	let target = U256::from(n * (n + 1) / 2) * target_block_time;
+	// Limits the increase of the difficulty to 10 times the average.
+	let weighted_solve_times = weighted_solve_times.max(target / 10);
+
+	let next = difficulties.saturating_mul(target) / (weighted_solve_times * n);
+	next.max(params.min_difficulty)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PARAMS: LwmaParams = LwmaParams {
+		window: 4,
+		target_block_time: 1000,
+		initial_difficulty: U256([1000, 0, 0, 0]),
+		min_difficulty: U256([10, 0, 0, 0]),
+	};
+
+	fn blocks(solve_times: &[u64], difficulty: u64) -> Vec<(u64, U256)> {
+		let mut timestamp = 1_000_000;
+		let mut blocks = vec![(timestamp, difficulty.into())];
+		for solve_time in solve_times {
+			timestamp += solve_time;
+			blocks.push((timestamp, difficulty.into()));
+		}
+		blocks
+	}
+
+	#[test]
+	fn initial_difficulty_without_solve_times() {
+		assert_eq!(next_difficulty(&PARAMS, &[]), U256::from(1000));
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[], 500)), U256::from(1000));
+		let params = LwmaParams { window: 0, ..PARAMS };
+		assert_eq!(next_difficulty(&params, &blocks(&[1], 5)), U256::from(1000));
+	}
+
+	#[test]
+	fn difficulty_follows_solve_times() {
+		// on target, the difficulty doesn't change.
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[1000; 4], 500)), U256::from(500));
+		// only the last `window` solve times matter.
+		let history = blocks(&[1, 1, 1000, 1000, 1000, 1000], 500);
+		assert_eq!(next_difficulty(&PARAMS, &history), U256::from(500));
+
+		// twice too slow halves the difficulty, twice too fast doubles it.
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[2000; 4], 500)), U256::from(250));
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[500; 4], 500)), U256::from(1000));
+
+		// the latest solve times weigh more.
+		let slower_lately = next_difficulty(&PARAMS, &blocks(&[500, 500, 2000, 2000], 500));
+		let faster_lately = next_difficulty(&PARAMS, &blocks(&[2000, 2000, 500, 500], 500));
+		assert!(slower_lately < U256::from(500));
+		assert!(faster_lately > U256::from(500));
+	}
+
+	#[test]
+	fn difficulty_is_bounded() {
+		// solve times are clamped, and the increase is at most tenfold.
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[0; 4], 500)), U256::from(5000));
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[1_000_000; 4], 600)), U256::from(100));
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[1_000_000; 4], 30)), U256::from(10));
+		assert_eq!(next_difficulty(&PARAMS, &blocks(&[1000; 4], u64::MAX)), U256::from(u64::MAX));
+	}
+}
@@ -0,0 +1,55 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Raft digests.
+
+use crate::{AuthoritySignature, PreDigest, RAFT_ENGINE_ID};
+use codec::Encode;
+use sp_runtime::generic::DigestItem;
+
+/// A digest item which is usable with Raft consensus.
+pub trait CompatibleDigestItem: Sized {
+	/// Construct a digest item which contains a signature on the hash.
+	fn raft_seal(signature: AuthoritySignature) -> Self;
+
+	/// If this item is a Raft seal, return the signature.
+	fn as_raft_seal(&self) -> Option<AuthoritySignature>;
+
+	/// Construct a digest item which contains the Raft pre-digest.
+	fn raft_pre_digest(pre_digest: PreDigest) -> Self;
+
+	/// If this item is a Raft pre-digest, return it.
+	fn as_raft_pre_digest(&self) -> Option<PreDigest>;
+}
+
+impl CompatibleDigestItem for DigestItem {
+	fn raft_seal(signature: AuthoritySignature) -> Self {
+		DigestItem::Seal(RAFT_ENGINE_ID, signature.encode())
+	}
+
+	fn as_raft_seal(&self) -> Option<AuthoritySignature> {
+		self.seal_try_to(&RAFT_ENGINE_ID)
+	}
+
+	fn raft_pre_digest(pre_digest: PreDigest) -> Self {
+		DigestItem::PreRuntime(RAFT_ENGINE_ID, pre_digest.encode())
+	}
+
+	fn as_raft_pre_digest(&self) -> Option<PreDigest> {
+		self.pre_runtime_try_to(&RAFT_ENGINE_ID)
+	}
+}
@@ -0,0 +1,211 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2022 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Primitives for Raft.
+//!
+//! Raft is a crash fault tolerant consensus for permissioned chains: the authorities elect a
+//! leader for a term, the leader authors the blocks, and a block is final once a majority of the
+//! authorities acknowledged it. The acknowledgements form the [`RaftJustification`] of the block.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{ConsensusEngineId, RuntimeAppPublic, RuntimeDebug};
+use sp_std::vec::Vec;
+
+pub mod digests;
+
+mod app {
+	use sp_application_crypto::{app_crypto, ed25519, key_types::RAFT};
+	app_crypto!(ed25519, RAFT);
+}
+
+sp_application_crypto::with_pair! {
+	/// A Raft authority keypair using Ed25519 as its crypto.
+	pub type AuthorityPair = app::Pair;
+}
+
+/// A Raft authority identifier using Ed25519 as its crypto.
+pub type AuthorityId = app::Public;
+
+/// A Raft authority signature using Ed25519 as its crypto.
+pub type AuthoritySignature = app::Signature;
+
+/// The `ConsensusEngineId` of Raft.
+pub const RAFT_ENGINE_ID: ConsensusEngineId = [b'r', b'a', b'f', b't'];
+
+/// The index of an authority.
+pub type AuthorityIndex = u32;
+
+/// A Raft term, during which at most one leader is elected.
+pub type Term = u64;
+
+/// An consensus log item for Raft.
+#[derive(Decode, Encode)]
+pub enum ConsensusLog<AuthorityId: Codec> {
+	/// The authorities have changed, starting with the children of the block.
+	#[codec(index = 1)]
+	AuthoritiesChange(Vec<AuthorityId>),
+}
+
+/// The pre-runtime digest of a Raft block, identifying its author.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct PreDigest {
+	/// The term during which the block was authored.
+	pub term: Term,
+	/// The index of the leader of the term that authored the block.
+	pub authority_index: AuthorityIndex,
+}
+
+/// Returns the number of authorities forming a majority of `n_authorities` authorities.
+pub fn majority(n_authorities: usize) -> usize {
+	n_authorities / 2 + 1
+}
+
+/// Returns the payload authorities sign to acknowledge the block `block_hash` authored during
+/// `term`.
+pub fn ack_payload<Hash: Encode, Number: Encode>(
+	term: Term,
+	block_hash: &Hash,
+	block_number: &Number,
+) -> Vec<u8> {
+	(RAFT_ENGINE_ID, term, block_hash, block_number).encode()
+}
+
+/// A justification of a Raft block: the acknowledgements of the block by a majority of the
+/// authorities that can author its children.
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct RaftJustification<Hash, Number> {
+	/// The term during which the block was authored.
+	pub term: Term,
+	/// The hash of the block.
+	pub block_hash: Hash,
+	/// The number of the block.
+	pub block_number: Number,
+	/// The authorities that acknowledged the block, with their signature of the
+	/// [`ack_payload`].
+	pub acks: Vec<(AuthorityIndex, AuthoritySignature)>,
+}
+
+/// The reasons a [`RaftJustification`] is invalid.
+#[derive(Clone, Eq, PartialEq, RuntimeDebug)]
+pub enum JustificationError {
+	/// The acknowledgement of an authority that isn't in the set.
+	UnknownAuthority(AuthorityIndex),
+	/// An authority acknowledged the block more than once.
+	DuplicateAck(AuthorityIndex),
+	/// The signature of an authority is invalid.
+	BadSignature(AuthorityIndex),
+	/// Less than a majority of the authorities acknowledged the block.
+	NotEnoughAcks {
+		/// The number of acknowledgements.
+		acks: u32,
+		/// The number of acknowledgements forming a majority.
+		required: u32,
+	},
+}
+
+impl<Hash: Encode, Number: Encode> RaftJustification<Hash, Number> {
+	/// Checks that a majority of `authorities` acknowledged the block.
+	pub fn verify(&self, authorities: &[AuthorityId]) -> Result<(), JustificationError> {
+		let payload = ack_payload(self.term, &self.block_hash, &self.block_number);
+
+		let mut seen = Vec::with_capacity(self.acks.len());
+		for (index, signature) in &self.acks {
+			let authority = authorities
+				.get(*index as usize)
+				.ok_or(JustificationError::UnknownAuthority(*index))?;
+			if seen.contains(index) {
+				return Err(JustificationError::DuplicateAck(*index))
+			}
+			if !authority.verify(&payload, signature) {
+				return Err(JustificationError::BadSignature(*index))
+			}
+			seen.push(*index);
+		}
+
+		let required = majority(authorities.len());
+		if seen.len() < required {
+			return Err(JustificationError::NotEnoughAcks {
+				acks: seen.len() as u32,
+				required: required as u32,
+			})
+		}
+
+		Ok(())
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	/// API necessary for block authorship with Raft.
+	pub trait RaftApi<AuthorityId: Codec> {
+		/// Returns the authorities that can author and acknowledge the children of the block.
+		fn authorities() -> Vec<AuthorityId>;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_application_crypto::Pair;
+
+	fn pairs(n: u8) -> Vec<AuthorityPair> {
+		(0..n).map(|i| AuthorityPair::from_seed(&[i; 32])).collect()
+	}
+
+	fn justification(pairs: &[AuthorityPair], acks: &[u32]) -> RaftJustification<[u8; 32], u64> {
+		let payload = ack_payload(3, &[7; 32], &12u64);
+		RaftJustification {
+			term: 3,
+			block_hash: [7; 32],
+			block_number: 12,
+			acks: acks.iter().map(|i| (*i, pairs[*i as usize].sign(&payload))).collect(),
+		}
+	}
+
+	#[test]
+	fn justification_requires_majority() {
+		let pairs = pairs(4);
+		let authorities = pairs.iter().map(|p| p.public()).collect::<Vec<_>>();
+
+		assert_eq!(majority(4), 3);
+		assert_eq!(justification(&pairs, &[0, 2, 3]).verify(&authorities), Ok(()));
+		assert_eq!(
+			justification(&pairs, &[0, 2]).verify(&authorities),
+			Err(JustificationError::NotEnoughAcks { acks: 2, required: 3 }),
+		);
+		assert_eq!(
+			justification(&pairs, &[0, 2, 2]).verify(&authorities),
+			Err(JustificationError::DuplicateAck(2)),
+		);
+		assert_eq!(
+			justification(&pairs, &[0, 1]).verify(&authorities[..1]),
+			Err(JustificationError::UnknownAuthority(1)),
+		);
+	}
+
+	#[test]
+	fn justification_rejects_signature_of_other_block() {
+		let pairs = pairs(3);
+		let authorities = pairs.iter().map(|p| p.public()).collect::<Vec<_>>();
+
+		let mut justification = justification(&pairs, &[0, 1]);
+		justification.block_number = 13;
+		assert_eq!(justification.verify(&authorities), Err(JustificationError::BadSignature(0)));
+	}
+}
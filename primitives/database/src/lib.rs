@@ -115,6 +115,17 @@ pub trait Database<H: Clone + AsRef<[u8]>>: Send + Sync {
 	///
 	/// Not all database implementations use a prefix for keys, so this function may be a noop.
 	fn sanitize_key(&self, _key: &mut Vec<u8>) {}
+
+	/// Call `f` with every key-value pair stored in `col`.
+	///
+	/// Not all database implementations are able to enumerate their keys. The default
+	/// implementation returns an error.
+	fn iter(&self, _col: ColumnId, _f: &mut dyn FnMut(&[u8], &[u8])) -> error::Result<()> {
+		Err(error::DatabaseError(Box::new(std::io::Error::new(
+			std::io::ErrorKind::Unsupported,
+			"Database does not support iterating over columns",
+		))))
+	}
 }
 
 impl<H> std::fmt::Debug for dyn Database<H> {
@@ -73,6 +73,14 @@ where
 		let s = self.0.read();
 		s.get(&col).and_then(|c| c.get(key).map(|(_, v)| v.clone()))
 	}
+
+	fn iter(&self, col: ColumnId, f: &mut dyn FnMut(&[u8], &[u8])) -> error::Result<()> {
+		let s = self.0.read();
+		for (key, (_, value)) in s.get(&col).into_iter().flatten() {
+			f(key, value);
+		}
+		Ok(())
+	}
 }
 
 impl MemDb {
@@ -23,11 +23,11 @@ use std::{
 	io::{Read, Write},
 };
 
-// An arbitrary prefix, that indicates a blob beginning with should be decompressed with
-// Zstd compression.
-//
-// This differs from the WASM magic bytes, so real WASM blobs will not have this prefix.
-const ZSTD_PREFIX: [u8; 8] = [82, 188, 83, 118, 70, 219, 142, 5];
+/// An arbitrary prefix, that indicates a blob beginning with should be decompressed with
+/// Zstd compression.
+///
+/// This differs from the WASM magic bytes, so real WASM blobs will not have this prefix.
+pub const ZSTD_PREFIX: [u8; 8] = [82, 188, 83, 118, 70, 219, 142, 5];
 
 /// A recommendation for the bomb limit for code blobs.
 ///
@@ -44,6 +44,21 @@ pub struct BlockTrace {
 	pub events: Vec<Event>,
 }
 
+/// Time spent executing a block, folded into stacks of spans.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockProfile {
+	/// Hash of the block being profiled
+	pub block_hash: String,
+	/// Parent hash
+	pub parent_hash: String,
+	/// Module targets of the spans that make up the stacks.
+	pub tracing_targets: String,
+	/// One line per stack, with the spans separated by `;` followed by the nanoseconds spent in
+	/// the innermost span. This is the input format of flamegraph tools.
+	pub folded_stacks: String,
+}
+
 /// Represents a tracing event, complete with recorded data.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -99,3 +114,13 @@ pub enum TraceBlockResponse {
 	/// Successful block tracing response
 	BlockTrace(BlockTrace),
 }
+
+/// Response for the `state_profileBlock` RPC.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ProfileBlockResponse {
+	/// Error block profiling response
+	TraceError(TraceError),
+	/// Successful block profiling response
+	BlockProfile(BlockProfile),
+}
@@ -68,6 +68,9 @@ pub use sp_consensus_babe::{AllowedSlots, AuthorityId, Slot};
 
 pub type AuraId = sp_consensus_aura::sr25519::AuthorityId;
 
+/// Raft uses Ed25519 keys, so its authorities are kept apart from the ones of Babe and Aura.
+pub type RaftId = sp_consensus_raft::AuthorityId;
+
 // Include the WASM binary
 #[cfg(feature = "std")]
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
@@ -848,6 +851,12 @@ cfg_if! {
 				}
 			}
 
+			impl sp_consensus_raft::RaftApi<Block, RaftId> for Runtime {
+				fn authorities() -> Vec<RaftId> {
+					system::raft_authorities()
+				}
+			}
+
 			impl sp_consensus_babe::BabeApi<Block> for Runtime {
 				fn configuration() -> sp_consensus_babe::BabeConfiguration {
 					sp_consensus_babe::BabeConfiguration {
@@ -1122,6 +1131,12 @@ cfg_if! {
 				}
 			}
 
+			impl sp_consensus_raft::RaftApi<Block, RaftId> for Runtime {
+				fn authorities() -> Vec<RaftId> {
+					system::raft_authorities()
+				}
+			}
+
 			impl sp_consensus_babe::BabeApi<Block> for Runtime {
 				fn configuration() -> sp_consensus_babe::BabeConfiguration {
 					sp_consensus_babe::BabeConfiguration {
@@ -19,7 +19,8 @@
 //! and depositing logs.
 
 use crate::{
-	AccountId, AuthorityId, Block, BlockNumber, Digest, Extrinsic, Header, Transfer, H256 as Hash,
+	AccountId, AuthorityId, Block, BlockNumber, Digest, Extrinsic, Header, RaftId, Transfer,
+	H256 as Hash,
 };
 use codec::{Decode, Encode, KeyedVec};
 use frame_support::{decl_module, decl_storage, storage};
@@ -52,6 +53,8 @@ decl_storage! {
 		NewAuthorities get(fn new_authorities): Option<Vec<AuthorityId>>;
 		StorageDigest get(fn storage_digest): Option<Digest>;
 		Authorities get(fn authorities) config(): Vec<AuthorityId>;
+		// Not part of the genesis config, see `raft_authorities_storage`.
+		RaftAuthorities get(fn raft_authorities): Vec<RaftId>;
 	}
 }
 
@@ -85,6 +88,16 @@ pub fn authorities() -> Vec<AuthorityId> {
 	Authorities::get()
 }
 
+pub fn raft_authorities() -> Vec<RaftId> {
+	RaftAuthorities::get()
+}
+
+/// The genesis storage entry setting the Raft authorities to `authorities`.
+#[cfg(feature = "std")]
+pub fn raft_authorities_storage(authorities: &[RaftId]) -> (Vec<u8>, Vec<u8>) {
+	(<RaftAuthorities as storage::StorageValue<_>>::hashed_key().to_vec(), authorities.encode())
+}
+
 pub fn get_block_number() -> Option<BlockNumber> {
 	Number::get()
 }